@@ -4,74 +4,426 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use clap::{ArgAction, Command, arg, command, value_parser};
+use chrono::NaiveDate;
+use clap::{ArgAction, ArgMatches, Command, arg, command, value_parser};
+
+/// Commands that never write to the database. Anything not listed is treated as
+/// mutating so new commands are refused under `--read-only` until reviewed.
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "account list",
+    "category list",
+    "config get",
+    "config list",
+    "config edit",
+    "config env",
+    "tx list",
+    "tx template list",
+    "tx meta get",
+    "budget list",
+    "budget report",
+    "budget scenario list",
+    "budget scenario compare",
+    "report",
+    "portfolio list-assets",
+    "portfolio value",
+    "portfolio tax",
+    "portfolio grant list",
+    "portfolio price list",
+    "portfolio alert list",
+    "portfolio watch list",
+    "account interest list",
+    "export",
+    "import profile list",
+    "import mcc-map list",
+    "fx list",
+    "fx base-history",
+    "fx convert",
+    "fx explain",
+    "fx doctor",
+    "cpi list",
+    "doctor",
+    "period list",
+    "rules list",
+    "rules lint",
+    "envelope status",
+    "envelope history",
+    "envelope alerts",
+    "goal status",
+    "plan list",
+    "plan afford",
+    "invoice list",
+    "invoice aging",
+    "trip list",
+    "jar list",
+    "asset alias list",
+    "man",
+];
+
+/// Space-separated subcommand path, e.g. `"portfolio price list"`, plus the
+/// innermost matches.
+pub fn command_path(m: &ArgMatches) -> (String, &ArgMatches) {
+    let mut names = Vec::new();
+    let mut cur = m;
+    while let Some((name, sub)) = cur.subcommand() {
+        names.push(name);
+        cur = sub;
+    }
+    (names.join(" "), cur)
+}
+
+/// `args` with `--json` added when `MONEYCLIP_JSON` is set and the command takes it
+/// alongside the flags already given (e.g. not with `--jsonl`).
+pub fn with_env_json(args: Vec<std::ffi::OsString>) -> Vec<std::ffi::OsString> {
+    if !crate::utils::env_flag("MONEYCLIP_JSON") {
+        return args;
+    }
+    let mut with_json = args.clone();
+    with_json.push("--json".into());
+    let takes_json = build_cli().try_get_matches_from(&with_json).is_ok();
+    if takes_json { with_json } else { args }
+}
+
+/// Whether the parsed command may write to the database.
+pub fn command_mutates(m: &ArgMatches) -> bool {
+    let (path, leaf) = command_path(m);
+    let dry_run = leaf.try_get_one::<bool>("dry-run").ok().flatten() == Some(&true);
+    match path.as_str() {
+        "portfolio value" => leaf.get_flag("live"),
+        "fx backfill"
+        | "portfolio price prune"
+        | "envelope sweep"
+        | "import pdf"
+        | "account set-currency"
+        | "budget rebase"
+        | "close-month"
+        | "db repair-decimals"
+        | "account merge"
+        | "tx geocode"
+        | "recurring run" => !dry_run,
+        "report snapshot save" | "report snapshot rm" => true,
+        _ => !READ_ONLY_COMMANDS
+            .iter()
+            .any(|ro| path == *ro || path.starts_with(&format!("{} ", ro))),
+    }
+}
+
+/// Inclusive `--from`/`--to` date filters shared by list, report and export commands.
+fn date_range_args(cmd: Command) -> Command {
+    cmd.arg(arg!(--from <DATE> "First date included (YYYY-MM-DD, 2w ago, ...)").required(false))
+        .arg(arg!(--to <DATE> "Last date included").required(false))
+}
+
+/// `--out` for commands that print reports: the output goes to a file, written
+/// atomically, instead of stdout.
+fn out_arg(cmd: Command) -> Command {
+    cmd.arg(
+        arg!(--out <PATH> "Write the output to this file instead of stdout")
+            .id("output")
+            .required(false),
+    )
+}
+
+/// [`out_arg`] plus `--format` to pick how the output is rendered.
+fn output_args(cmd: Command) -> Command {
+    out_arg(cmd).arg(
+        arg!(--format <FMT> "Output format; json where the command has --json [default: table]")
+            .id("output-format")
+            .value_parser(["table", "json", "csv", "markdown", "html"])
+            .required(false),
+    )
+}
+
+/// Parse the `--from`/`--to` pair added by [`date_range_args`]; either end may be open.
+pub fn date_range(m: &ArgMatches) -> anyhow::Result<(Option<NaiveDate>, Option<NaiveDate>)> {
+    let parse = |id: &str| {
+        m.try_get_one::<String>(id)
+            .ok()
+            .flatten()
+            .map(|s| crate::utils::parse_date(s.trim()))
+            .transpose()
+    };
+    let (from, to) = (parse("from")?, parse("to")?);
+    if let (Some(f), Some(t)) = (from, to) {
+        anyhow::ensure!(f <= t, "--from {} is after --to {}", f, t);
+    }
+    Ok((from, to))
+}
+
+/// CSV dialect options shared by `import transactions` and `import profile add`.
+fn csv_format_args(cmd: Command) -> Command {
+    cmd.arg(arg!(--delimiter <CHAR> "Field separator: , ; | or tab [default: ,]").required(false))
+        .arg(
+            arg!(--"decimal-comma" "Amounts use 1.234,56 style (comma decimals)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"no-decimal-comma" "Amounts use 1,234.56 style, overriding --profile")
+                .action(ArgAction::SetTrue)
+                .overrides_with("decimal-comma"),
+        )
+        .arg(
+            arg!(--encoding <LABEL> "File encoding, e.g. windows-1252, latin1 [default: utf-8]")
+                .required(false),
+        )
+        .arg(
+            arg!(--"debit-column" <HEADER> "Column with outflows as positive numbers")
+                .required(false),
+        )
+        .arg(
+            arg!(--"credit-column" <HEADER> "Column with inflows as positive numbers")
+                .required(false),
+        )
+        .arg(
+            arg!(--"invert-sign" "Flip amounts, for exports listing purchases as positive")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"no-invert-sign" "Keep amounts as they are, overriding --profile")
+                .action(ArgAction::SetTrue)
+                .overrides_with("invert-sign"),
+        )
+        .arg(
+            arg!(--"balance-column" <HEADER> "Column with the bank's running balance, checked after import")
+                .required(false),
+        )
+        .arg(
+            arg!(--"bank-category-column" <HEADER> "Column with the bank's category or MCC, mapped via `import mcc-map`")
+                .required(false),
+        )
+}
+
+fn template_fields(cmd: Command) -> Command {
+    cmd.arg(arg!(--name <NAME>).required(true))
+        .arg(arg!(--account <NAME>).required(false))
+        .arg(
+            arg!(--amount <AMOUNT>)
+                .allow_hyphen_values(true)
+                .required(false),
+        )
+        .arg(arg!(--payee <PAYEE> "Defaults to the template name").required(false))
+        .arg(arg!(--category <CAT>).required(false))
+        .arg(arg!(--note <NOTE>).required(false))
+}
 
 pub fn build_cli() -> Command {
-    command!()
+    let cli = command!()
         .name("moneyclip")
         .about("Personal finance, envelope budgeting, and portfolio CLI (multi-currency)")
         .subcommand_required(false)
         .arg_required_else_help(true)
-        .subcommand(Command::new("init").about("Initialize database and folders"))
+        .arg(
+            arg!(--"read-only" "Open the database read-only; commands that write are refused")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            arg!(--"no-input" "Never prompt; fail when required values are missing")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            arg!(-q --quiet "Hide progress bars and spinners")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            arg!(--"log-level" <LEVEL> "Diagnostic logging: off|error|warn|info|debug|trace, or filter directives [env: MONEYCLIP_LOG]")
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            arg!(--"log-file" "Log to a daily file under the data dir instead of stderr")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            arg!(--markdown "Print tables as Markdown pipe tables for notes and issues")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            arg!(--"money-dp" <N> "Decimals shown for money, 0-2 [default: money_dp setting]")
+                .value_parser(clap::value_parser!(u32).range(0..=2))
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            arg!(--"qty-dp" <N> "Decimals shown for quantities, 4-8 [default: quantity_dp setting]")
+                .value_parser(clap::value_parser!(u32).range(4..=8))
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            arg!(--symbols "Show amounts with currency symbols, e.g. €12.50 [default: currency_symbols setting]")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            arg!(--rounding <MODE> "Rounding for displayed figures [default: rounding setting]")
+                .value_parser(["half-up", "half-even"])
+                .required(false)
+                .global(true),
+        )
         .subcommand(
-            Command::new("account")
-                .about("Manage accounts")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("add")
-                        .about("Add a new account")
-                        .arg(arg!(--name <NAME>).required(true))
-                        .arg(arg!(--type <TYPE> "bank|card|cash|broker").required(true))
-                        .arg(arg!(--currency <CCY> "ISO code like INR, USD").required(true)),
-                )
-                .subcommand(Command::new("list").about("List accounts"))
-                .subcommand(
-                    Command::new("rm")
-                        .about("Remove account")
-                        .arg(arg!(--name <NAME>).required(true)),
+            Command::new("init")
+                .about("Initialize the database; in a terminal (without --no-input) a setup wizard follows")
+                .arg(arg!(--base <CCY> "Base currency").required(false))
+                .arg(
+                    arg!(--template <NAME> "Starter accounts and categories")
+                        .value_parser(["basic", "family", "freelancer"])
+                        .required(false),
+                ),
+        )
+        .subcommand(account_command())
+        .subcommand(category_command())
+        .subcommand(tx_command())
+        .subcommand(budget_command())
+        .subcommand(report_command())
+        .subcommand(portfolio_command())
+        .subcommand(import_command())
+        .subcommand(export_command())
+        .subcommand(fx_command())
+        .subcommand(doctor_command())
+        .subcommand(period_command())
+        .subcommand(close_month_command())
+        .subcommand(batch_command())
+        .subcommand(config_command())
+        .subcommand(db_command())
+        .subcommand(rules_command())
+        .subcommand(envelope_command())
+        .subcommand(goal_command())
+        .subcommand(plan_command())
+        .subcommand(invoice_command())
+        .subcommand(trip_command())
+        .subcommand(jar_command())
+        .subcommand(asset_command())
+        .subcommand(recurring_command())
+        .subcommand(sync_command())
+        .subcommand(cpi_command())
+        .subcommand(man_command());
+    let cli = REPORT_OUTPUTS.iter().fold(cli, |cli, path| {
+        let (parent, child) = path.split_once(' ').unwrap();
+        cli.mut_subcommand(parent, |c| c.mut_subcommand(child, output_args))
+    });
+    let cli = cli.mut_subcommand("report", |c| c.mut_subcommand("heatmap", out_arg));
+    crate::help_examples::with_examples(cli)
+}
+
+/// Report-style commands that take `--out` and `--format`; `report heatmap` has its
+/// own `--format` and takes only `--out`.
+const REPORT_OUTPUTS: &[&str] = &[
+    "report balances",
+    "report cashflow",
+    "report fees",
+    "report subscriptions",
+    "report anomalies",
+    "report compare",
+    "report balance-history",
+    "report owed",
+    "report by-project",
+    "report spend-by-location",
+    "report trip",
+    "report tax-collected",
+    "report diff",
+    "report spend-by-category",
+    "budget list",
+    "budget report",
+    "envelope status",
+    "envelope history",
+    "goal status",
+    "invoice aging",
+    "portfolio value",
+    "portfolio tax",
+    "tx list",
+];
+
+fn account_command() -> Command {
+    Command::new("account")
+        .about("Manage accounts")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add a new account")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(arg!(--type <TYPE> "bank|card|cash|broker").required(true))
+                .arg(arg!(--currency <CCY> "ISO code like INR, USD").required(true))
+                .arg(arg!(--group <GROUP> "Rollup group, e.g. Liquid, Retirement").required(false)),
+        )
+        .subcommand(
+            Command::new("list").about("List accounts").arg(
+                arg!(--all "Include accounts archived by `account merge`")
+                    .action(ArgAction::SetTrue),
+            ),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Move transactions, trades and grants into another account and archive this one")
+                .arg(arg!(--from <NAME> "Duplicate account to merge away").required(true))
+                .arg(arg!(--into <NAME> "Account that keeps everything").required(true))
+                .arg(arg!(--"dry-run" "Show what would move").action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("set-group")
+                .about("Put an account in a group for `report balances --by-group`")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(arg!(--group <GROUP>).required_unless_present("clear"))
+                .arg(
+                    arg!(--clear "Remove the account from its group")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("group"),
                 ),
         )
         .subcommand(
-            Command::new("category")
-                .about("Manage categories")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("add")
-                        .about("Add")
-                        .arg(arg!(--name <NAME>).required(true)),
+            Command::new("set-default-category")
+                .about("Category for `tx add` on this account when none is given")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(arg!(--category <CAT>).required_unless_present("clear"))
+                .arg(
+                    arg!(--clear "Remove the default")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("category"),
+                ),
+        )
+        .subcommand(
+            Command::new("set-currency")
+                .about("Fix an account created in the wrong currency")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(arg!(--currency <CCY> "New ISO code").required(true))
+                .arg(
+                    arg!(--"convert-history" "Convert stored amounts at each date's FX rate")
+                        .action(ArgAction::SetTrue)
+                        .required_unless_present("keep-history")
+                        .conflicts_with("keep-history"),
                 )
-                .subcommand(Command::new("list").about("List"))
-                .subcommand(
-                    Command::new("rm")
-                        .about("Remove")
-                        .arg(arg!(--name <NAME>).required(true)),
+                .arg(
+                    arg!(--"keep-history" "Keep amounts and only relabel their currency")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--"dry-run" "Show the impact without changing anything")
+                        .action(ArgAction::SetTrue),
                 ),
         )
         .subcommand(
-            Command::new("tx")
-                .about("Record and list transactions")
+            Command::new("interest")
+                .about("Interest earned on a balance, posted by `recurring run`")
                 .subcommand_required(true)
                 .subcommand(
-                    Command::new("add")
-                        .about("Add transaction. Amount: positive=income, negative=expense")
-                        .arg(arg!(--date <DATE> "YYYY-MM-DD").required(true))
-                        .arg(arg!(--account <NAME>).required(true))
-                        .arg(arg!(--amount <AMOUNT>).required(true))
-                        .arg(arg!(--payee <PAYEE>).required(true))
-                        .arg(arg!(--category <CAT>).required(false))
-                        .arg(arg!(--note <NOTE>).required(false)),
+                    Command::new("set")
+                        .about("Set or change an account's APY")
+                        .arg(arg!(--name <NAME>).required(true))
+                        .arg(arg!(--apy <PERCENT> "Annual percentage yield, e.g. 4.5").required(true))
+                        .arg(
+                            arg!(--compounding <PERIOD> "How often interest is credited")
+                                .value_parser(["daily", "monthly", "quarterly", "yearly"])
+                                .default_value("monthly"),
+                        )
+                        .arg(arg!(--from <DATE> "Accrue from the first full period on or after this date [default: today]").required(false))
+                        .arg(arg!(--category <CAT> "Category of posted interest").required(false)),
                 )
                 .subcommand(
                     Command::new("list")
-                        .about("List transactions")
-                        .arg(
-                            arg!(--limit <N>)
-                                .value_parser(value_parser!(usize))
-                                .required(false),
-                        )
-                        .arg(arg!(--month <YYYY_MM>).required(false))
-                        .arg(arg!(--account <NAME>).required(false))
-                        .arg(arg!(--category <CAT>).required(false))
+                        .about("Accounts earning interest")
                         .arg(
                             arg!(--json)
                                 .action(ArgAction::SetTrue)
@@ -82,89 +434,341 @@ pub fn build_cli() -> Command {
                                 .action(ArgAction::SetTrue)
                                 .conflicts_with("json"),
                         ),
+                )
+                .subcommand(
+                    Command::new("rm")
+                        .about("Stop accruing interest (posted interest stays)")
+                        .arg(arg!(--name <NAME>).required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove account with its transactions, trades and grants")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(
+                    arg!(--yes "Remove even when transactions or trades go with it")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+}
+
+fn category_command() -> Command {
+    Command::new("category")
+        .about("Manage categories")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add")
+                .arg(arg!(--name <NAME>).required(true)),
+        )
+        .subcommand(Command::new("list").about("List"))
+        .subcommand(
+            Command::new("rm")
+                .about("Remove; transactions and rules lose the category, budgets and goals go")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(
+                    arg!(--yes "Remove even when transactions, budgets or rules use it")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("set-cap")
+                .about("Monthly spending cap, warned about when tx add or an import goes over it")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(
+                    arg!(--amount <AMOUNT> "Most to spend per month")
+                        .required_unless_present("clear")
+                        .conflicts_with("clear"),
+                )
+                .arg(arg!(--currency <CCY> "Currency of the cap [default: base]").required(false))
+                .arg(arg!(--clear "Remove the cap").action(ArgAction::SetTrue)),
+        )
+}
+
+fn tx_command() -> Command {
+    Command::new("tx")
+        .about("Record and list transactions")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add transaction (prompts for missing fields in a terminal). Amount: positive=income, negative=expense")
+                .arg(
+                    arg!(--template <NAME> "Start from a saved template; flags override it")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--date <DATE> "YYYY-MM-DD, today, last fri, 2d ago (today if --template)")
+                        .required(false),
+                )
+                .arg(arg!(--account <NAME> "Defaults to `config set default_account`").required(false))
+                .arg(
+                    arg!(--amount <AMOUNT>)
+                        .allow_hyphen_values(true)
+                        .required(false),
+                )
+                .arg(arg!(--payee <PAYEE>).required(false))
+                .arg(arg!(--category <CAT>).required(false))
+                .arg(arg!(--note <NOTE>).required(false))
+                .arg(
+                    arg!(--strict "Refuse the entry instead of warning when it goes over the category's monthly cap")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(arg!(--project <NAME> "Project or job the transaction belongs to").required(false))
+                .arg(arg!(--location <PLACE> "Where it was spent, e.g. \"Kyoto, Japan\"").required(false))
+                .arg(arg!(--coords <LAT_LON> "Coordinates as LAT,LON").allow_hyphen_values(true).required(false))
+                .arg(arg!(--jar <JAR> "Book it to one of the account's jars").required(false))
+                .arg(
+                    arg!(--tax <AMOUNT> "Sales tax (VAT/GST) included in the amount")
+                        .required(false)
+                        .conflicts_with("tax-rate"),
+                )
+                .arg(
+                    arg!(--"tax-rate" <PERCENT> "Work out the included tax from its rate, e.g. 19%")
+                        .required(false),
+                )
+                .arg(arg!(--"tax-code" <CODE> "Tax code, e.g. VAT19 or GST-EXEMPT").required(false))
+                .arg(
+                    arg!(--reimbursable [PERSON] "Paid for someone who will pay it back (no name: expenses)")
+                        .num_args(0..=1)
+                        .default_missing_value(""),
                 ),
         )
         .subcommand(
-            Command::new("budget")
-                .about("Planned monthly budgets per category (in base currency)")
+            Command::new("meta")
+                .about("Key/value metadata on a transaction (ID from `tx list`)")
                 .subcommand_required(true)
                 .subcommand(
                     Command::new("set")
-                        .about("Set budget (overwrite)")
-                        .arg(arg!(--month <YYYY_MM>).required(true))
-                        .arg(arg!(--category <CAT>).required(true))
-                        .arg(arg!(--amount <AMOUNT>).required(true)),
+                        .about("Set or replace a key")
+                        .arg(arg!(--id <ID>).value_parser(value_parser!(i64)).required(true))
+                        .arg(arg!(--key <KEY>).required(true))
+                        .arg(arg!(--value <VALUE>).required(true)),
                 )
                 .subcommand(
-                    Command::new("list")
-                        .about("List budgets")
-                        .arg(arg!(--month <YYYY_MM>).required(false)),
+                    Command::new("get")
+                        .about("Show all keys, or print the value of one")
+                        .arg(arg!(--id <ID>).value_parser(value_parser!(i64)).required(true))
+                        .arg(arg!(--key <KEY>).required(false))
+                        .arg(arg!(--json).action(ArgAction::SetTrue).conflicts_with("key")),
                 )
                 .subcommand(
-                    Command::new("report")
-                        .about("Budget vs actuals")
-                        .arg(arg!(--month <YYYY_MM>).required(true))
-                        .arg(arg!(--base).action(ArgAction::SetTrue))
-                        .arg(arg!(--currency <CCY> "Override output currency").required(false))
-                        .arg(
-                            arg!(--json)
-                                .action(ArgAction::SetTrue)
-                                .conflicts_with("jsonl"),
-                        )
-                        .arg(
-                            arg!(--jsonl)
-                                .action(ArgAction::SetTrue)
-                                .conflicts_with("json"),
-                        ),
+                    Command::new("rm")
+                        .about("Remove a key")
+                        .arg(arg!(--id <ID>).value_parser(value_parser!(i64)).required(true))
+                        .arg(arg!(--key <KEY>).required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("geocode")
+                .about("Name the places of transactions that only have coordinates (OpenStreetMap)")
+                .arg(
+                    arg!(--limit <N> "Most distinct spots to look up, one per second")
+                        .value_parser(value_parser!(usize))
+                        .default_value("50"),
+                )
+                .arg(arg!(--"dry-run" "Show the names without storing them").action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("settle")
+                .about("Mark a person's oldest reimbursable items as repaid by an existing transaction")
+                .arg(arg!(--person <NAME>).required(true))
+                .arg(arg!(--date <DATE> "Date of the repayment").required(true))
+                .arg(
+                    arg!(--amount <AMOUNT> "Amount of the repayment as recorded")
+                        .allow_hyphen_values(true)
+                        .required(true),
+                )
+                .arg(arg!(--account <NAME> "Account the repayment went into").required(false)),
+        )
+        .subcommand(
+            Command::new("template")
+                .about("Saved presets for frequent entries")
+                .subcommand_required(true)
+                .subcommand(template_fields(
+                    Command::new("save").about("Create or replace a template"),
+                ))
+                .subcommand(template_fields(
+                    Command::new("edit").about("Change fields of an existing template"),
+                ))
+                .subcommand(Command::new("list").about("List templates"))
+                .subcommand(
+                    Command::new("rm")
+                        .about("Remove a template")
+                        .arg(arg!(--name <NAME>).required(true)),
+                ),
+        )
+        .subcommand(date_range_args(
+            Command::new("list")
+                .about("List transactions")
+                .arg(
+                    arg!(--limit <N>)
+                        .value_parser(value_parser!(usize))
+                        .required(false),
+                )
+                .arg(
+                    arg!(--offset <N> "Skip this many rows first (for paging with --limit)")
+                        .value_parser(value_parser!(usize))
+                        .required(false),
+                )
+                .arg(
+                    arg!(--sort <KEY> "date|amount|payee [default: date]")
+                        .value_parser(["date", "amount", "payee"])
+                        .required(false),
+                )
+                .arg(
+                    arg!(--asc "Oldest/smallest/A first")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("desc"),
+                )
+                .arg(
+                    arg!(--desc "Newest/largest/Z first (default)")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("asc"),
+                )
+                .arg(
+                    arg!(--"count-only" "Print only the number of matching transactions")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(arg!(--month <YYYY_MM>).required(false))
+                .arg(arg!(--account <NAME>).required(false))
+                .arg(arg!(--category <CAT>).required(false))
+                .arg(
+                    arg!(--uncategorized "Only transactions without a category")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("category"),
+                )
+                .arg(arg!(--"no-note" "Only transactions without a note").action(ArgAction::SetTrue))
+                .arg(arg!(--project <NAME> "Only transactions of this project").required(false))
+                .arg(arg!(--jar <JAR> "Only transactions booked to this jar").required(false))
+                .arg(
+                    arg!(--meta <KEY_VALUE> "Only transactions with this metadata: KEY=VALUE or KEY; repeatable")
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .arg(arg!(--currency <CCY> "Only transactions in this currency").required(false))
+                .arg(
+                    arg!(--totals "Add a row with the count, sums per currency and the base total")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("count-only"),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        ))
+}
+
+fn budget_command() -> Command {
+    Command::new("budget")
+        .about("Planned monthly budgets per category (base currency unless set otherwise)")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("set")
+                .about("Set budget (overwrite)")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(arg!(--category <CAT>).required(true))
+                .arg(
+                    arg!(--amount <AMOUNT> "Number or expression, e.g. 12.50+3.99")
+                        .allow_hyphen_values(true)
+                        .required(true),
+                )
+                .arg(arg!(--currency <CCY> "Currency of the amount [default: base]").required(false)),
+        )
+        .subcommand(
+            Command::new("rebase")
+                .about("Convert budgets to another currency at month-end rates, e.g. after `fx set-base`")
+                .arg(arg!(--to <CCY> "Target currency").required(true))
+                .arg(
+                    arg!(--from <CCY> "Currency of budgets saved without one [default: base]")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--"dry-run" "List the conversions without saving them")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List budgets")
+                .arg(arg!(--month <YYYY_MM>).required(false))
+                .arg(
+                    arg!(--totals "Add a row with the count, sums per currency and the base total")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
                 ),
         )
         .subcommand(
             Command::new("report")
-                .about("Reports and analytics")
+                .about("Budget vs actuals")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(arg!(--base).action(ArgAction::SetTrue))
+                .arg(arg!(--currency <CCY> "Override output currency").required(false))
+                .arg(
+                    arg!(--chart "Show consumption bars instead of a table")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["json", "jsonl"]),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("scenario")
+                .about("What-if copies of a month's budgets, compared without touching the real ones")
                 .subcommand_required(true)
                 .subcommand(
-                    Command::new("balances")
-                        .about("Account balances")
-                        .arg(arg!(--base).action(ArgAction::SetTrue))
-                        .arg(arg!(--currency <CCY> "Override output currency").required(false))
+                    Command::new("create")
+                        .about("Copy a month's budgets into a new scenario")
+                        .arg(arg!(--name <NAME>).required(true))
+                        .arg(arg!(--month <YYYY_MM>).required(true)),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Set one category's amount in a scenario")
+                        .arg(arg!(--name <NAME>).required(true))
+                        .arg(arg!(--category <CAT>).required(true))
                         .arg(
-                            arg!(--json)
-                                .action(ArgAction::SetTrue)
-                                .conflicts_with("jsonl"),
+                            arg!(--amount <AMOUNT> "Number or expression, e.g. 1800+120")
+                                .allow_hyphen_values(true)
+                                .required(true),
                         )
-                        .arg(
-                            arg!(--jsonl)
-                                .action(ArgAction::SetTrue)
-                                .conflicts_with("json"),
-                        ),
+                        .arg(arg!(--currency <CCY> "Currency of the amount [default: base]").required(false)),
                 )
                 .subcommand(
-                    Command::new("cashflow")
-                        .about("Monthly cashflow (income/expense)")
+                    Command::new("compare")
+                        .about("Scenario vs the month's budgets: per category, savings and savings rate (BASE)")
+                        .arg(arg!(--name <NAME>).required(true))
                         .arg(
-                            arg!(--months <N>)
-                                .value_parser(value_parser!(usize))
+                            arg!(--income <AMOUNT> "Monthly income in BASE [default: the month's actual inflows]")
                                 .required(false),
                         )
-                        .arg(arg!(--base).action(ArgAction::SetTrue))
-                        .arg(arg!(--currency <CCY> "Override output currency").required(false))
-                        .arg(
-                            arg!(--json)
-                                .action(ArgAction::SetTrue)
-                                .conflicts_with("jsonl"),
-                        )
-                        .arg(
-                            arg!(--jsonl)
-                                .action(ArgAction::SetTrue)
-                                .conflicts_with("json"),
-                        ),
+                        .arg(arg!(--json).action(ArgAction::SetTrue)),
                 )
                 .subcommand(
-                    Command::new("spend-by-category")
-                        .about("Spending by category for a month")
-                        .arg(arg!(--month <YYYY_MM>).required(true))
-                        .arg(arg!(--base).action(ArgAction::SetTrue))
-                        .arg(arg!(--currency <CCY> "Override output currency").required(false))
+                    Command::new("list")
+                        .about("List scenarios")
                         .arg(
                             arg!(--json)
                                 .action(ArgAction::SetTrue)
@@ -175,159 +779,529 @@ pub fn build_cli() -> Command {
                                 .action(ArgAction::SetTrue)
                                 .conflicts_with("json"),
                         ),
+                )
+                .subcommand(
+                    Command::new("rm")
+                        .about("Remove a scenario")
+                        .arg(arg!(--name <NAME>).required(true)),
                 ),
         )
+}
+
+fn report_command() -> Command {
+    Command::new("report")
+        .about("Reports and analytics")
+        .subcommand_required(true)
         .subcommand(
-            Command::new("portfolio")
-                .about("Track assets and trades")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("add-asset")
-                        .about("Add asset")
-                        .arg(arg!(--ticker <TICKER>).required(true))
-                        .arg(arg!(--name <NAME>).required(true))
-                        .arg(arg!(--currency <CCY>).required(true)),
-                )
-                .subcommand(Command::new("list-assets").about("List assets"))
-                .subcommand(
-                    Command::new("trade")
-                        .about("Record trade")
-                        .subcommand_required(true)
-                        .subcommand(
-                            Command::new("buy")
-                                .about("Buy")
-                                .arg(arg!(--date <YYYY_MM_DD>).required(true))
-                                .arg(arg!(--ticker <TICKER>).required(true))
-                                .arg(arg!(--account <ACCOUNT>).required(true))
-                                .arg(arg!(--quantity <QTY>).required(true))
-                                .arg(arg!(--price <PRICE>).required(true))
-                                .arg(arg!(--fees <FEES>).required(false)),
-                        )
-                        .subcommand(
-                            Command::new("sell")
-                                .about("Sell")
-                                .arg(arg!(--date <YYYY_MM_DD>).required(true))
-                                .arg(arg!(--ticker <TICKER>).required(true))
-                                .arg(arg!(--account <ACCOUNT>).required(true))
-                                .arg(arg!(--quantity <QTY>).required(true))
-                                .arg(arg!(--price <PRICE>).required(true))
-                                .arg(arg!(--fees <FEES>).required(false)),
-                        ),
+            Command::new("balances")
+                .about("Account balances")
+                .arg(arg!(--base).action(ArgAction::SetTrue))
+                .arg(
+                    arg!(--"by-group" "Subtotal per account group and grand total (BASE unless --currency)")
+                        .action(ArgAction::SetTrue),
                 )
-                .subcommand(
-                    Command::new("value")
-                        .about("Portfolio value")
-                        .arg(arg!(--live).action(ArgAction::SetTrue)),
+                .arg(arg!(--currency <CCY> "Override output currency").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
                 )
-                .subcommand(
-                    Command::new("tax")
-                        .about("FIFO capital gains")
-                        .arg(arg!(--year <YYYY>).required(true)),
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(date_range_args(
+            Command::new("cashflow")
+                .about("Monthly or weekly cashflow (income/expense)")
+                .arg(
+                    arg!(--months <N> "Number of periods shown")
+                        .value_parser(value_parser!(usize))
+                        .required(false),
                 )
-                .subcommand(
-                    Command::new("price")
-                        .about("Prices")
-                        .subcommand_required(true)
-                        .subcommand(Command::new("fetch").about("Fetch & cache (Yahoo)"))
-                        .subcommand(Command::new("list").about("List cached")),
+                .arg(
+                    arg!(--by <PERIOD> "Bucket by month or by week (see week_start)")
+                        .value_parser(["month", "week"])
+                        .default_value("month"),
+                )
+                .arg(arg!(--base).action(ArgAction::SetTrue))
+                .arg(arg!(--currency <CCY> "Override output currency").required(false))
+                .arg(
+                    arg!(--"constant-fx" <YYYY_MM_DD> "Value every month at this date's FX rates")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--real "Deflate each month by CPI into --cpi-base prices")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--"cpi-base" <PERIOD> "Year (average) or month whose prices are used [default: latest CPI month]")
+                        .requires("real"),
+                )
+                .arg(
+                    arg!(--chart "Show income/expense bars and a net sparkline instead of a table")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["json", "jsonl"]),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        ))
+        .subcommand(
+            Command::new("fees")
+                .about("Bank fees, trade commissions and interest paid for a year (BASE)")
+                .arg(arg!(--year <YYYY>).required(true))
+                .arg(
+                    arg!(--"fee-category" <CAT> "Category holding bank fees [default: Fees]")
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .arg(
+                    arg!(--"interest-category" <CAT> "Category holding interest paid [default: Interest]")
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
                 ),
         )
         .subcommand(
-            Command::new("import")
-                .about("Import (CSV)")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("transactions")
-                        .about("CSV: date,payee,amount,category,account,currency,note")
-                        .arg(arg!(--path <PATH>).required(true)),
+            Command::new("heatmap")
+                .about("Per-day spending for a year (BASE) as a CSV/JSON grid")
+                .arg(arg!(--year <YYYY>).required(true))
+                .arg(arg!(--category <CAT> "Only this category").required(false))
+                .arg(
+                    arg!(--format <FMT> "csv|json [default: csv]")
+                        .value_parser(["csv", "json"])
+                        .required(false),
                 ),
         )
         .subcommand(
-            Command::new("export")
-                .about("Export data")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("transactions")
-                        .about("Export transactions")
-                        .arg(arg!(--format <FMT> "csv|json").required(true))
-                        .arg(arg!(--out <PATH>).required(true)),
+            Command::new("subscriptions")
+                .about("Recurring monthly charges with yearly cost and price changes")
+                .arg(
+                    arg!(--"min-charges" <N> "Charges needed to count as recurring [default: 3]")
+                        .value_parser(value_parser!(usize))
+                        .required(false),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
                 ),
         )
         .subcommand(
-            Command::new("fx")
-                .about("Foreign exchange (ECB via Frankfurter)")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("set-base")
-                        .about("Set base currency")
-                        .arg(arg!(--currency <CCY>).required(true)),
+            Command::new("anomalies")
+                .about("Flag unusual expenses in a month: outliers, new payees, duplicates")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(
+                    arg!(--z <SIGMA> "Standard deviations above the usual amount [default: 3]")
+                        .value_parser(value_parser!(f64))
+                        .required(false),
                 )
-                .subcommand(
-                    Command::new("fetch")
-                        .about("Fetch historical FX (for currencies you use)")
-                        .arg(
-                            arg!(--days <N>)
-                                .value_parser(value_parser!(usize))
-                                .required(false),
-                        ),
+                .arg(
+                    arg!(--"new-payee-min" <AMOUNT> "Flag first-time payees from this amount [default: 100]")
+                        .required(false),
                 )
-                .subcommand(Command::new("list").about("List cached FX rates"))
-                .subcommand(
-                    Command::new("convert")
-                        .about("Convert an amount using cached rates")
-                        .arg(arg!(--date <YYYY_MM_DD>).required(true))
-                        .arg(arg!(--amount <AMOUNT>).required(true))
-                        .arg(arg!(--from <CCY>).required(true))
-                        .arg(arg!(--to <CCY>).required(true)),
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
                 ),
         )
+        .subcommand(date_range_args(
+            Command::new("balance-history")
+                .about("Running end-of-day balance of one account, every day in the range")
+                .arg(arg!(--account <NAME>).required(true))
+                .arg(
+                    arg!(--base "Convert each day's balance to BASE at that day's rate")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("currency"),
+                )
+                .arg(arg!(--currency <CCY> "Convert to this currency instead").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        ))
         .subcommand(
-            Command::new("doctor").about("Run health checks: FX coverage, currencies, orphan data"),
+            Command::new("trip")
+                .about("Spending of a trip per category, in original currencies and BASE, with the daily average")
+                .arg(arg!(--name <TRIP>).required(true))
+                .arg(arg!(--json).action(ArgAction::SetTrue)),
         )
+        .subcommand(date_range_args(
+            Command::new("spend-by-location")
+                .about("Spending per place in original currencies and BASE")
+                .arg(
+                    arg!(--by <LEVEL> "place (as recorded), city (first part) or country (last part)")
+                        .value_parser(["place", "city", "country"])
+                        .default_value("place"),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        ))
+        .subcommand(date_range_args(
+            Command::new("by-project")
+                .about("Income, costs and net per project, or per category of one project (BASE)")
+                .arg(arg!(--project <NAME> "Break one project down by category").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        ))
         .subcommand(
-            Command::new("rules")
-                .about("Import rules: auto-categorize by payee patterns")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("add")
-                        .about("Add rule")
-                        .arg(arg!(--pattern <REGEX>).required(true))
-                        .arg(arg!(--category <CAT>).required(false))
-                        .arg(arg!(--payee_rewrite <NAME>).required(false)),
+            Command::new("tax-collected")
+                .about("Sales tax collected and paid per tax code for a quarter (BASE)")
+                .arg(arg!(--quarter <QUARTER> "YYYY-Qn, this or last").default_value("last"))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
                 )
-                .subcommand(Command::new("list").about("List rules"))
-                .subcommand(
-                    Command::new("rm")
-                        .about("Remove rule")
-                        .arg(arg!(--id <ID>).required(true)),
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
                 ),
         )
         .subcommand(
-            Command::new("envelope")
-                .about("Envelope budgeting (zero-based)")
-                .subcommand_required(true)
-                .subcommand(
-                    Command::new("fund")
-                        .about("Fund category envelope (BASE currency)")
-                        .arg(arg!(--month <YYYY_MM>).required(true))
-                        .arg(arg!(--category <CAT>).required(true))
-                        .arg(arg!(--amount <AMOUNT>).required(true)),
+            Command::new("owed")
+                .about("Outstanding reimbursements per person")
+                .arg(arg!(--person <NAME>).required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
                 )
-                .subcommand(
-                    Command::new("move")
-                        .about("Move funds between envelopes")
-                        .arg(arg!(--month <YYYY_MM>).required(true))
-                        .arg(arg!(--from <CAT>).required(true))
-                        .arg(arg!(--to <CAT>).required(true))
-                        .arg(arg!(--amount <AMOUNT>).required(true)),
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("compare")
+                .about("Spending in two months side by side, biggest change first (BASE)")
+                .arg(arg!(--a <YYYY_MM> "Earlier month").required(true))
+                .arg(arg!(--b <YYYY_MM> "Later month").required(true))
+                .arg(
+                    arg!(--by <FIELD> "Group spending by")
+                        .value_parser(["category", "payee", "account"])
+                        .default_value("category"),
                 )
-                .subcommand(
-                    Command::new("status")
-                        .about("Carryover, budget, spent, available (BASE)")
-                        .arg(arg!(--month <YYYY_MM>).required(true))
-                        .arg(arg!(--currency <CCY> "Override output currency").required(false))
-                        .arg(
-                            arg!(--json)
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("snapshot")
+                .about("Save report figures to compare later with `report diff`")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("save")
+                        .about("Store balances, spend by category and budgets")
+                        .arg(arg!(--name <NAME>).required(true)),
+                )
+                .subcommand(Command::new("list").about("List saved snapshots"))
+                .subcommand(
+                    Command::new("rm")
+                        .about("Delete a snapshot")
+                        .arg(arg!(--name <NAME>).required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Show figures that changed between two snapshots")
+                .arg(arg!(from: <FROM> "Earlier snapshot"))
+                .arg(arg!(to: [TO] "Later snapshot [default: current data]"))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("digest")
+                .about("Markdown summary of the last week or month: balances, top categories, overruns, portfolio")
+                .arg(
+                    arg!(--period <PERIOD> "week|month [default: week]")
+                        .value_parser(["week", "month"])
+                        .required(false),
+                )
+                .arg(arg!(--"as-of" <DATE> "Last day covered [default: today]").required(false))
+                .arg(arg!(--out <PATH> "Write the digest to this file instead of stdout").required(false))
+                .arg(
+                    arg!(--email "Mail the digest using the smtp_* and digest_to settings")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(date_range_args(
+            Command::new("spend-by-category")
+                .about("Spending by category for a month or date range")
+                .arg(arg!(--month <YYYY_MM>).required_unless_present_any(["from", "to"]))
+                .arg(arg!(--base).action(ArgAction::SetTrue))
+                .arg(arg!(--currency <CCY> "Override output currency").required(false))
+                .arg(
+                    arg!(--"constant-fx" <YYYY_MM_DD> "Value every month at this date's FX rates")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--totals "Add a row with the count, sums per currency and the converted total")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("chart"),
+                )
+                .arg(
+                    arg!(--chart "Show a spending bar per category with its share instead of a table")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["json", "jsonl"]),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        ))
+}
+
+fn portfolio_command() -> Command {
+    Command::new("portfolio")
+        .about("Track assets and trades")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add-asset")
+                .about("Add asset")
+                .arg(arg!(--ticker <TICKER>).required(true))
+                .arg(arg!(--name <NAME>).required_unless_present("auto"))
+                .arg(arg!(--currency <CCY>).required_unless_present("auto"))
+                .arg(
+                    arg!(--auto "Check the ticker with the price provider and fill in name and currency")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("list-assets").about("List assets"))
+        .subcommand(
+            Command::new("trade")
+                .about("Record trade")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("buy")
+                        .about("Buy")
+                        .arg(arg!(--date <YYYY_MM_DD>).required(true))
+                        .arg(arg!(--ticker <TICKER>).required(true))
+                        .arg(arg!(--account <ACCOUNT>).required(true))
+                        .arg(arg!(--quantity <QTY>).required(true))
+                        .arg(arg!(--price <PRICE>).required(true))
+                        .arg(arg!(--fees <FEES>).required(false))
+                        .arg(
+                            arg!(--"fee-currency" <CCY> "Currency the fees were charged in [default: the asset's]")
+                                .requires("fees")
+                                .required(false),
+                        ),
+                )
+                .subcommand(
+                    Command::new("sell")
+                        .about("Sell")
+                        .arg(arg!(--date <YYYY_MM_DD>).required(true))
+                        .arg(arg!(--ticker <TICKER>).required(true))
+                        .arg(arg!(--account <ACCOUNT>).required(true))
+                        .arg(arg!(--quantity <QTY>).required(true))
+                        .arg(arg!(--price <PRICE>).required(true))
+                        .arg(arg!(--fees <FEES>).required(false))
+                        .arg(
+                            arg!(--"fee-currency" <CCY> "Currency the fees were charged in [default: the asset's]")
+                                .requires("fees")
+                                .required(false),
+                        ),
+                )
+                .subcommand(
+                    Command::new("drip")
+                        .about("Reinvest a cash dividend in fractional shares at the day's price")
+                        .arg(arg!(--date <YYYY_MM_DD>).required(true))
+                        .arg(arg!(--ticker <TICKER>).required(true))
+                        .arg(arg!(--account <ACCOUNT>).required(true))
+                        .arg(arg!(--amount <AMOUNT> "Dividend paid, in the asset's currency").required(true))
+                        .arg(
+                            arg!(--price <PRICE> "[default: latest cached price on or before --date]")
+                                .required(false),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("grant")
+                .about("RSU/ESPP grants and vesting")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Add grant")
+                        .arg(arg!(--ticker <TICKER>).required(true))
+                        .arg(arg!(--account <ACCOUNT>).required(true))
+                        .arg(arg!(--kind <KIND> "rsu|espp").required(true))
+                        .arg(arg!(--date <YYYY_MM_DD> "Grant date").required(true))
+                        .arg(arg!(--quantity <QTY>).required(true))
+                        .arg(
+                            arg!(--"purchase-price" <PRICE> "ESPP purchase price per share")
+                                .required(false),
+                        )
+                        .arg(
+                            arg!(--schedule <SCHEDULE> "Vest schedule: YYYY-MM-DD:QTY,...")
+                                .required(false),
+                        ),
+                )
+                .subcommand(
+                    Command::new("vest")
+                        .about("Vest shares into the position at fair market value")
+                        .arg(arg!(--id <ID>).required(true))
+                        .arg(arg!(--date <YYYY_MM_DD>).required(true))
+                        .arg(arg!(--fmv <PRICE> "Fair market value per share").required(true))
+                        .arg(
+                            arg!(--quantity <QTY> "Defaults to the scheduled quantity")
+                                .required(false),
+                        ),
+                )
+                .subcommand(Command::new("list").about("List grants")),
+        )
+        .subcommand(
+            Command::new("value")
+                .about("Portfolio value")
+                .arg(arg!(--live).action(ArgAction::SetTrue).conflicts_with("at"))
+                .arg(
+                    arg!(--at <DATE> "Holdings, prices and FX rates as of this day, with a total")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--currency <CCY> "Currency of the converted values and total [default: BASE]")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("tax")
+                .about("FIFO capital gains")
+                .arg(arg!(--year <YYYY>).required(true)),
+        )
+        .subcommand(
+            Command::new("alert")
+                .about("Price alerts, checked by `portfolio price fetch`")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Alert when the price goes above and/or below a threshold")
+                        .arg(arg!(--ticker <TICKER>).required(true))
+                        .arg(arg!(--above <PRICE>).required_unless_present("below"))
+                        .arg(arg!(--below <PRICE>).required(false)),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List price alerts")
+                        .arg(
+                            arg!(--json)
+                                .action(ArgAction::SetTrue)
+                                .conflicts_with("jsonl"),
+                        )
+                        .arg(
+                            arg!(--jsonl)
+                                .action(ArgAction::SetTrue)
+                                .conflicts_with("json"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("rm")
+                        .about("Remove a price alert")
+                        .arg(arg!(--id <ID>).value_parser(value_parser!(i64)).required(true)),
+                )
+                .subcommand(
+                    Command::new("check")
+                        .about("Check alerts against cached prices; exits with status 1 when one fires")
+                        .arg(
+                            arg!(--email "Also mail crossed alerts to digest_to (needs the email feature)")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(arg!(--webhook <URL> "Also POST crossed alerts as JSON to this URL").required(false)),
+                ),
+        )
+        .subcommand(
+            Command::new("watch")
+                .about("Watchlist: tickers priced by `portfolio price fetch` but not held")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Watch a ticker; new tickers need --currency")
+                        .arg(arg!(--ticker <TICKER>).required(true))
+                        .arg(arg!(--name <NAME> "Name of a new asset [default: TICKER]").required(false))
+                        .arg(arg!(--currency <CCY> "Currency of a new asset").required(false)),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Stop watching a ticker; its cached prices stay")
+                        .arg(arg!(--ticker <TICKER>).required(true)),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("Watched tickers with latest price and daily change")
+                        .arg(
+                            arg!(--json)
                                 .action(ArgAction::SetTrue)
                                 .conflicts_with("jsonl"),
                         )
@@ -338,4 +1312,992 @@ pub fn build_cli() -> Command {
                         ),
                 ),
         )
+        .subcommand(
+            Command::new("price")
+                .about("Prices")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("fetch")
+                        .about("Fetch & cache (Yahoo), then check price alerts")
+                        .arg(
+                            arg!(--email "Mail crossed price alerts to digest_to (needs the email feature)")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(arg!(--webhook <URL> "POST crossed price alerts as JSON to this URL").required(false)),
+                )
+                .subcommand(Command::new("list").about("List cached"))
+                .subcommand(
+                    Command::new("prune")
+                        .about("Downsample old prices, keeping month-end points")
+                        .arg(arg!(--keep <GRANULARITY> "daily|weekly").required(true))
+                        .arg(arg!(--before <YYYY_MM_DD>).required(true))
+                        .arg(
+                            arg!(--"dry-run" "Summarize without deleting")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+        )
+}
+
+fn import_command() -> Command {
+    Command::new("import")
+        .about("Import (CSV, XLSX)")
+        .subcommand_required(true)
+        .subcommand(csv_format_args(
+            Command::new("transactions")
+                .about("CSV or spreadsheet: date,payee,amount,category,account,currency,note")
+                .arg(
+                    arg!(--path <PATH> "File or glob like 'exports/*.csv'; repeat for more, all imported in one transaction")
+                        .action(ArgAction::Append)
+                        .required(true),
+                )
+                .arg(arg!(--sheet <SHEET> "Worksheet of .xlsx/.xls/.ods files: 1-based number or name [default: 1]").required(false))
+                .arg(
+                    arg!(--profile <NAME> "Saved CSV dialect; dialect flags given here override it")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--strict "Import nothing if a category goes over its monthly cap")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--"batch-size" <N> "Commit every N rows instead of all at once; an interrupted import continues with `import resume`")
+                        .value_parser(value_parser!(u64).range(1..))
+                        .conflicts_with("strict")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--"meta-column" <HEADER> "Keep this column as transaction metadata; HEADER=KEY renames it; repeatable")
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .arg(
+                    arg!(--"skip-before" <DATE> "Drop rows dated before DATE, e.g. already imported history")
+                        .required(false),
+                )
+                .arg(
+                    arg!(--"skip-payee-regex" <REGEX> "Drop rows whose payee matches, e.g. '^AVAILABLE BALANCE'")
+                        .required(false),
+                )
+                .arg(arg!(--json "Print the import summary as JSON").action(ArgAction::SetTrue)),
+        ))
+        .subcommand(
+            Command::new("resume")
+                .about("Continue a --batch-size import after the last committed row")
+                .arg(
+                    arg!(--batch <N> "Batch id printed when the import started")
+                        .value_parser(value_parser!(i64))
+                        .required(true),
+                )
+                .arg(arg!(--json "Print the import summary as JSON").action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("pdf")
+                .about("PDF statement via per-bank table heuristics (needs the `pdf` build feature)")
+                .arg(arg!(--path <PATH>).required(true))
+                .arg(
+                    arg!(--profile <LAYOUT> "Statement layout: hdfc | generic")
+                        .value_parser(["hdfc", "generic"])
+                        .required(true),
+                )
+                .arg(arg!(--account <NAME> "Account the statement belongs to").required(true))
+                .arg(
+                    arg!(--"dry-run" "Show the parsed rows without importing")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("mcc-map")
+                .about("Map bank categories or MCCs to categories; applied before rules")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("set")
+                        .about("Add or change a mapping")
+                        .arg(arg!(--code <CODE> "Bank category text or MCC, e.g. 5411").required(true))
+                        .arg(arg!(--category <CAT>).required(true)),
+                )
+                .subcommand(Command::new("list").about("List mappings"))
+                .subcommand(
+                    Command::new("rm")
+                        .about("Remove a mapping")
+                        .arg(arg!(--code <CODE>).required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("profile")
+                .about("Saved CSV dialects per bank")
+                .subcommand_required(true)
+                .subcommand(csv_format_args(
+                    Command::new("add")
+                        .about("Save (or replace) a profile")
+                        .arg(arg!(--name <NAME>).required(true)),
+                ))
+                .subcommand(Command::new("list").about("List profiles"))
+                .subcommand(
+                    Command::new("rm")
+                        .about("Remove a profile")
+                        .arg(arg!(--name <NAME>).required(true)),
+                ),
+        )
+}
+
+fn export_command() -> Command {
+    Command::new("export")
+        .about("Export data")
+        .subcommand_required(true)
+        .subcommand(date_range_args(
+            Command::new("transactions")
+                .about("Export transactions")
+                .arg(arg!(--format <FMT> "csv|json").required(true))
+                .arg(arg!(--out <PATH>).required(true))
+                .arg(
+                    arg!(--"with-meta" "Add metadata: one CSV column per key, or a meta object in JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        ))
+        .subcommand(
+            Command::new("budgets")
+                .about("Export budget vs spending per category, in the base currency")
+                .arg(
+                    arg!(--month <YYYY_MM> "Month to export")
+                        .required_unless_present("year")
+                        .conflicts_with("year"),
+                )
+                .arg(arg!(--year <YYYY> "Every month of a year").required(false))
+                .arg(
+                    arg!(--format <FMT>)
+                        .value_parser(["csv", "json"])
+                        .default_value("csv"),
+                )
+                .arg(arg!(--out <PATH>).required(true)),
+        )
+        .subcommand(
+            Command::new("envelopes")
+                .about("Export envelope status (carryover, funded, spent, available) for a month")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(
+                    arg!(--format <FMT>)
+                        .value_parser(["csv", "json"])
+                        .default_value("csv"),
+                )
+                .arg(arg!(--out <PATH>).required(true)),
+        )
+        .subcommand(date_range_args(
+            Command::new("sheets")
+                .about("Push transactions, budgets or envelopes to a Google Sheets tab (feature `sheets`)")
+                .arg(arg!(--"spreadsheet-id" <ID> "ID from the spreadsheet URL").required(true))
+                .arg(arg!(--tab <NAME> "Sheet tab to write").required(true))
+                .arg(
+                    arg!(--source <SOURCE>)
+                        .value_parser(["transactions", "budgets", "envelopes"])
+                        .default_value("transactions"),
+                )
+                .arg(arg!(--month <YYYY_MM> "Month; required for budgets and envelopes").required(false))
+                .arg(
+                    arg!(--mode <MODE> "Replace the tab, or add rows below its header")
+                        .value_parser(["overwrite", "append"])
+                        .default_value("overwrite"),
+                ),
+        ))
+        .subcommand(
+            Command::new("anonymized")
+                .about("Copy the database with scrambled payees, amounts and dates for bug reports")
+                .arg(arg!(--out <PATH> "New SQLite file to write").required(true)),
+        )
+}
+
+fn fx_command() -> Command {
+    Command::new("fx")
+        .about("Foreign exchange (ECB via Frankfurter)")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("set-base")
+                .about("Set base currency")
+                .arg(arg!(--currency <CCY>).required(true))
+                .arg(
+                    arg!(--migrate "Convert budgets and goal targets to the new base")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--date <DATE> "Rates used by --migrate [default: today]")
+                        .required(false)
+                        .requires("migrate"),
+                )
+                .arg(
+                    arg!(--"no-fetch" "Use stored rates instead of fetching that day's")
+                        .action(ArgAction::SetTrue)
+                        .requires("migrate"),
+                ),
+        )
+        .subcommand(Command::new("base-history").about("List base currency changes"))
+        .subcommand(
+            Command::new("fetch")
+                .about("Fetch historical FX (for currencies you use)")
+                .arg(
+                    arg!(--days <N>)
+                        .value_parser(value_parser!(usize))
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("backfill")
+                .about("Fetch rates only for transaction/trade dates that lack one")
+                .arg(
+                    arg!(--"max-age" <DAYS> "Treat a rate older than this as missing [default: 7]")
+                        .value_parser(value_parser!(i64))
+                        .required(false),
+                )
+                .arg(
+                    arg!(--"dry-run" "List the dates that would be fetched")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("set-policy")
+                .about("Choose how conversions route between currencies")
+                .arg(
+                    arg!(--policy <POLICY> "best (max amount) | direct (pair, then base hub, then fewest hops)")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("set-valuation-date")
+                .about("Choose the rates used when a month end or valuation date is a weekend or holiday")
+                .arg(
+                    arg!(--policy <POLICY> "previous (latest rate on or before) | nearest (Sat -> Fri, Sun -> Mon)")
+                        .value_parser(["previous", "nearest"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Show which stored rates a conversion uses")
+                .arg(arg!(--date <YYYY_MM_DD>).required(true))
+                .arg(arg!(--from <CCY>).required(true))
+                .arg(arg!(--to <CCY>).required(true))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Audit stored rates for jumps, inconsistent inverses and absurd values")
+                .arg(
+                    arg!(--"max-jump" <PCT> "Flag day-over-day moves above this percent [default: 10]")
+                        .value_parser(value_parser!(f64))
+                        .required(false),
+                )
+                .arg(
+                    arg!(--tolerance <PCT> "Allowed disagreement between A/B and B/A [default: 0.5]")
+                        .value_parser(value_parser!(f64))
+                        .required(false),
+                )
+                .arg(
+                    arg!(--"max-age" <DAYS> "Report transaction dates with no rate this recent [default: 7]")
+                        .value_parser(value_parser!(i64))
+                        .required(false),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(Command::new("list").about("List cached FX rates"))
+        .subcommand(
+            Command::new("convert")
+                .about("Convert an amount using cached rates, on one date or across a range")
+                .arg(
+                    arg!(--date <YYYY_MM_DD>)
+                        .required_unless_present("series")
+                        .conflicts_with("series"),
+                )
+                .arg(
+                    arg!(--amount <AMOUNT> "Number or expression, e.g. 12.50+3.99")
+                        .allow_hyphen_values(true)
+                        .required(true),
+                )
+                .arg(arg!(--from <CCY>).required(true))
+                .arg(arg!(--to <CCY>).required(true))
+                .arg(
+                    arg!(--series "Convert on every step from --from-date through --to-date")
+                        .action(ArgAction::SetTrue)
+                        .requires_all(["from-date", "to-date"]),
+                )
+                .arg(arg!(--"from-date" <YYYY_MM_DD>).requires("series"))
+                .arg(arg!(--"to-date" <YYYY_MM_DD>).requires("series"))
+                .arg(
+                    arg!(--step <STEP> "Spacing of --series dates")
+                        .value_parser(["day", "week", "month"])
+                        .default_value("week"),
+                )
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+}
+
+fn doctor_command() -> Command {
+    Command::new("doctor")
+        .about("Run health checks: FX coverage, currencies, orphan data")
+        .arg(
+            arg!(--"inactive-months" <N> "Flag accounts with no activity in this many months")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("12"),
+        )
+        .arg(
+            arg!(--severity <LEVEL> "Only report findings at or above this level")
+                .value_parser(["warn", "error"])
+                .default_value("warn"),
+        )
+        .arg(
+            arg!(--json)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("jsonl"),
+        )
+        .arg(
+            arg!(--jsonl)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("json"),
+        )
+        .after_help(
+            "Exit status: 0 no findings, 1 doctor itself failed, \
+             2 warnings only, 3 at least one error.",
+        )
+}
+
+fn period_command() -> Command {
+    Command::new("period")
+        .about("Close finalized months so their transactions cannot change")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("close")
+                .about("Lock a month against adds, edits, deletes and imports")
+                .arg(arg!(month: <YYYY_MM> "Month to close, or 'last'")),
+        )
+        .subcommand(
+            Command::new("reopen")
+                .about("Unlock a closed month")
+                .arg(arg!(month: <YYYY_MM>))
+                .arg(
+                    arg!(--force "Confirm that finalized reports may change")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("list").about("List closed months"))
+}
+
+fn close_month_command() -> Command {
+    Command::new("close-month")
+        .about("Run the month-end checklist: FX, prices, recurring, doctor, reconcile, lock, summary")
+        .after_help(
+            "Steps and their order come from `config set close_checklist fx,prices,recurring,doctor,reconcile,lock,summary`.",
+        )
+        .arg(arg!(month: <YYYY_MM> "Month to close, or 'last'"))
+        .arg(
+            arg!(--skip <STEP> "Leave out a step this time; repeatable")
+                .value_parser([
+                    "fx",
+                    "prices",
+                    "recurring",
+                    "doctor",
+                    "reconcile",
+                    "lock",
+                    "summary",
+                ])
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            arg!(--statement <ACCOUNT_BALANCE> "Bank statement balance at the month end as ACCOUNT=BALANCE, checked by the reconcile step; repeatable")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            arg!(--force "Lock even when doctor reports errors or a statement balance differs")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(arg!(--"dry-run" "List the steps without running them").action(ArgAction::SetTrue))
+}
+
+fn batch_command() -> Command {
+    Command::new("batch")
+        .about("Run one command per line in a single all-or-nothing transaction")
+        .arg(arg!(--file <PATH> "Command file; omit or '-' to read stdin").required(false))
+}
+
+fn config_command() -> Command {
+    Command::new("config")
+        .about("Persistent defaults")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("set")
+                .about("Set a value, e.g. `config set default_account Cash`")
+                .arg(arg!(key: <KEY> "Setting name; `config list` shows them all"))
+                .arg(arg!(value: <VALUE>)),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Show a value")
+                .arg(arg!(key: <KEY>)),
+        )
+        .subcommand(
+            Command::new("unset")
+                .about("Clear a value")
+                .arg(arg!(key: <KEY>)),
+        )
+        .subcommand(Command::new("list").about("Show all settings"))
+        .subcommand(
+            Command::new("edit")
+                .about("Open config.toml (settings, default flags, hooks) in $EDITOR"),
+        )
+        .subcommand(Command::new("env").about("Show the environment variables moneyclip reads"))
+}
+
+fn db_command() -> Command {
+    Command::new("db")
+        .about("Database housekeeping")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("maintain")
+                .about("integrity_check, prune intraday prices, VACUUM and ANALYZE")
+                .arg(
+                    arg!(--"keep-prices" "Skip pruning intraday price rows")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("repair-decimals")
+                .about("Rewrite amounts, prices and rates that do not parse as plain decimals")
+                .arg(
+                    arg!(--quarantine "Move rows that cannot be repaired to quarantined_rows")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(arg!(--"dry-run" "Show what would change").action(ArgAction::SetTrue)),
+        )
+}
+
+fn rules_command() -> Command {
+    Command::new("rules")
+        .about("Import rules: auto-categorize by payee patterns")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add rule")
+                .arg(arg!(--pattern <REGEX>).required(true))
+                .arg(arg!(--category <CAT>).required(false))
+                .arg(
+                    arg!(--payee_rewrite <NAME> "New payee; $1 or ${name} insert capture groups")
+                        .required(false),
+                )
+                .arg(arg!(--"ignore-case" "Match regardless of letter case"))
+                .arg(arg!(--literal "Match the pattern as plain text, not a regex"))
+                .arg(
+                    arg!(--"match-field" <FIELD> "Test the payee, the note or both")
+                        .value_parser(["payee", "note", "both"])
+                        .default_value("both"),
+                )
+                .arg(
+                    arg!(--skip "Drop matching rows during import instead of categorizing them")
+                        .conflicts_with_all(["category", "payee_rewrite"]),
+                ),
+        )
+        .subcommand(Command::new("list").about("List rules"))
+        .subcommand(
+            Command::new("lint")
+                .about("Find rules matching the same recent payees, and rules that never win")
+                .arg(
+                    arg!(--days <N> "Look at payees of the last N days")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("365"),
+                )
+                .arg(arg!(--json).action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("bootstrap")
+                .about("Seed common merchant rules from a pack shipped with moneyclip")
+                .arg(
+                    arg!(--pack <PACK> "Rule pack; rules you add later take precedence")
+                        .value_parser(["default-us", "default-in"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove rule")
+                .arg(arg!(--id <ID>).required(true)),
+        )
+}
+
+fn envelope_command() -> Command {
+    Command::new("envelope")
+        .about("Envelope budgeting (zero-based)")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("fund")
+                .about("Fund category envelope (BASE currency)")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(arg!(--category <CAT>).required(true))
+                .arg(
+                    arg!(--amount <AMOUNT> "Number or expression, e.g. 12.50+3.99")
+                        .allow_hyphen_values(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("move")
+                .about("Move funds between envelopes")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(arg!(--from <CAT>).required(true))
+                .arg(arg!(--to <CAT>).required(true))
+                .arg(
+                    arg!(--amount <AMOUNT> "Number or expression, e.g. 12.50+3.99")
+                        .allow_hyphen_values(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Funded, spent and available per month for one envelope (BASE)")
+                .arg(arg!(--category <CAT>).required(true))
+                .arg(
+                    arg!(--months <N> "[default: 12]")
+                        .value_parser(value_parser!(usize))
+                        .required(false),
+                )
+                .arg(arg!(--to <YYYY_MM> "Last month shown [default: this month]").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("sweep")
+                .about("Move every envelope's leftover for a month into a goal's envelope")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(arg!(--"to-goal" <GOAL>).required(true))
+                .arg(arg!(--"dry-run" "Show what would move").action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Carryover, budget, spent, available (BASE)")
+                .arg(
+                    arg!(--month <YYYY_MM>)
+                        .required_unless_present("as-of")
+                        .conflicts_with("as-of"),
+                )
+                .arg(
+                    arg!(--"as-of" <DATE> "Count that month's spending up to this day; adds daily burn and projected month-end available")
+                        .required(false),
+                )
+                .arg(arg!(--currency <CCY> "Override output currency").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("alerts")
+                .about("Envelopes running low; exits with status 1 when any are listed")
+                .arg(arg!(--month <YYYY_MM> "[default: this month]").required(false))
+                .arg(
+                    arg!(--threshold <AMOUNT> "Alert below this BASE amount, or below N% of the month's budget")
+                        .required(true),
+                )
+                .arg(
+                    arg!(--email "Also mail the alerts to digest_to (needs the email feature)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(arg!(--webhook <URL> "Also POST the alerts as JSON to this URL").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+}
+
+fn plan_command() -> Command {
+    Command::new("plan")
+        .about("Planned purchases and whether envelopes can cover them")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add an item to the wishlist")
+                .arg(arg!(--item <NAME>).required(true))
+                .arg(arg!(--cost <AMOUNT> "Estimated cost").required(true))
+                .arg(arg!(--currency <CCY> "[default: BASE]").required(false))
+                .arg(arg!(--month <YYYY_MM> "When you want to buy it").required(false))
+                .arg(
+                    arg!(--envelope <CAT> "Envelope that pays for it")
+                        .required(false)
+                        .conflicts_with("goal"),
+                )
+                .arg(arg!(--goal <GOAL> "Goal whose envelope pays for it").required(false)),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List planned purchases")
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove a planned purchase")
+                .arg(arg!(--item <NAME>).required(true)),
+        )
+        .subcommand(
+            Command::new("afford")
+                .about("Check the item against its envelope, scheduled funding and other envelopes' spare money (BASE)")
+                .arg(arg!(--item <NAME>).required(true))
+                .arg(arg!(--"as-of" <DATE> "[default: today]").required(false))
+                .arg(arg!(--json).action(ArgAction::SetTrue)),
+        )
+}
+
+fn invoice_command() -> Command {
+    Command::new("invoice")
+        .about("Invoices to clients, from draft to paid")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("create")
+                .about("Create a draft invoice")
+                .arg(arg!(--client <NAME>).required(true))
+                .arg(arg!(--amount <AMOUNT>).required(true))
+                .arg(arg!(--due <DATE> "Payment due date").required(true))
+                .arg(arg!(--currency <CCY> "[default: BASE]").required(false))
+                .arg(arg!(--issued <DATE> "[default: today]").required(false))
+                .arg(arg!(--number <NUMBER> "[default: INV-0001, INV-0002, ...]").required(false))
+                .arg(arg!(--note <TEXT>).required(false)),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List invoices")
+                .arg(
+                    arg!(--status <STATUS> "open means draft or sent")
+                        .value_parser(["draft", "sent", "paid", "open"])
+                        .required(false),
+                )
+                .arg(arg!(--client <NAME>).required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("sent")
+                .about("Mark an invoice as sent; aging counts it from then on")
+                .arg(arg!(--number <NUMBER>).required(true))
+                .arg(arg!(--date <DATE> "[default: today]").required(false)),
+        )
+        .subcommand(
+            Command::new("paid")
+                .about("Mark an invoice paid by the income transaction that settled it")
+                .arg(arg!(--number <NUMBER>).required(true))
+                .arg(
+                    arg!(--tx <ID> "Id of the income transaction")
+                        .value_parser(value_parser!(i64))
+                        .required_unless_present("date")
+                        .conflicts_with_all(["date", "amount", "account"]),
+                )
+                .arg(arg!(--date <DATE> "Date of the income transaction").required(false))
+                .arg(
+                    arg!(--amount <AMOUNT> "Amount received [default: the invoice amount]")
+                        .required(false),
+                )
+                .arg(arg!(--account <NAME> "Account the payment went into").required(false)),
+        )
+        .subcommand(
+            Command::new("aging")
+                .about("Sent, unpaid invoices per client by days past due (BASE)")
+                .arg(arg!(--"as-of" <DATE> "[default: today]").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+}
+
+fn trip_command() -> Command {
+    Command::new("trip")
+        .about("Trips: a date range (and optional project tag) for `report trip`")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add a trip")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(arg!(--from <DATE> "First day").required(true))
+                .arg(arg!(--to <DATE> "Last day").required(true))
+                .arg(
+                    arg!(--tag <PROJECT> "Also count transactions with this --project at any date, e.g. flights booked early")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List trips")
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove a trip (its transactions are unchanged)")
+                .arg(arg!(--name <NAME>).required(true)),
+        )
+}
+
+fn jar_command() -> Command {
+    Command::new("jar")
+        .about("Jars: sub-balances inside an account, e.g. kids' allowances")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add a jar to an account")
+                .arg(arg!(--account <ACCOUNT>).required(true))
+                .arg(arg!(--name <NAME>).required(true)),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("Jar balances, with each account's unassigned remainder")
+                .arg(arg!(--account <ACCOUNT>).required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("move")
+                .about(
+                    "Move money between jars; leave out --from or --to for the unassigned balance",
+                )
+                .arg(arg!(--account <ACCOUNT>).required(true))
+                .arg(arg!(--from <JAR>).required_unless_present("to"))
+                .arg(arg!(--to <JAR>).required(false))
+                .arg(arg!(--amount <AMOUNT> "Number or expression, e.g. 12.50+3.99").required(true))
+                .arg(arg!(--date <DATE> "[default: today]").required(false))
+                .arg(arg!(--note <TEXT>).required(false)),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove an empty jar")
+                .arg(arg!(--account <ACCOUNT>).required(true))
+                .arg(arg!(--name <NAME>).required(true)),
+        )
+}
+
+fn asset_command() -> Command {
+    Command::new("asset")
+        .about("Assets: ticker renames")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("alias")
+                .about("Old ticker symbols of renamed assets")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Rename a ticker, merging the old one's trades, lots and prices into the new")
+                        .arg(arg!(--from <TICKER> "Old symbol").required(true))
+                        .arg(arg!(--to <TICKER> "New symbol").required(true))
+                        .arg(arg!(--effective <DATE> "First day traded under the new symbol").required(true)),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List aliases")
+                        .arg(
+                            arg!(--json)
+                                .action(ArgAction::SetTrue)
+                                .conflicts_with("jsonl"),
+                        )
+                        .arg(
+                            arg!(--jsonl)
+                                .action(ArgAction::SetTrue)
+                                .conflicts_with("json"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("rm")
+                        .about("Stop resolving an old symbol (merged history is kept)")
+                        .arg(arg!(--from <TICKER>).required(true)),
+                ),
+        )
+}
+
+fn recurring_command() -> Command {
+    Command::new("recurring")
+        .about("Generated transactions: interest on accounts with `account interest set`")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("run")
+                .about("Post interest for every period that ended by --through")
+                .arg(arg!(--through <DATE> "Last day to post for [default: today]").required(false))
+                .arg(
+                    arg!(--"dry-run" "Show the postings, and projected balances for a future --through, without writing")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(arg!(--json).action(ArgAction::SetTrue)),
+        )
+}
+
+fn sync_command() -> Command {
+    Command::new("sync")
+        .about(
+            "Fetch FX rates and prices together without blocking (needs the `async` build feature)",
+        )
+        .arg(
+            arg!(--days <N> "Days of FX history to fetch")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("30"),
+        )
+        .arg(
+            arg!(--every <MINUTES> "Keep running and sync every MINUTES")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .required(false),
+        )
+}
+
+fn cpi_command() -> Command {
+    Command::new("cpi")
+        .about("Consumer price index used by --real reports")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("set")
+                .about("Set the index for one month")
+                .arg(arg!(--month <YYYY_MM>).required(true))
+                .arg(arg!(--value <INDEX>).required(true)),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import month,value rows from a CSV with a header")
+                .arg(arg!(--path <FILE>).required(true)),
+        )
+        .subcommand(
+            Command::new("fetch")
+                .about("Fetch a monthly index series from FRED")
+                .arg(arg!(--series <ID> "FRED series id").default_value("CPIAUCSL")),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List stored index values")
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+}
+
+fn man_command() -> Command {
+    Command::new("man")
+        .about("Write man pages for moneyclip and every subcommand")
+        .hide(true)
+        .arg(arg!(--out <DIR> "Directory for the .1 files; created if missing").required(true))
+}
+
+fn goal_command() -> Command {
+    Command::new("goal")
+        .about("Savings goals backed by an envelope category")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add a goal; funding its envelope counts as saving")
+                .arg(arg!(--name <NAME>).required(true))
+                .arg(arg!(--category <CAT> "Envelope holding the savings").required(true))
+                .arg(arg!(--target <AMOUNT> "Target in BASE currency").required(true))
+                .arg(arg!(--by <YYYY_MM_DD> "Target date").required(false)),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Saved, remaining and monthly amount needed per goal (BASE)")
+                .arg(arg!(--month <YYYY_MM> "[default: this month]").required(false))
+                .arg(
+                    arg!(--json)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("jsonl"),
+                )
+                .arg(
+                    arg!(--jsonl)
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove a goal (the envelope keeps its money)")
+                .arg(arg!(--name <NAME>).required(true)),
+        )
 }
@@ -4,9 +4,11 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::pretty_table;
-use anyhow::Result;
-use rusqlite::{Connection, params};
+use crate::utils::{fx_convert_batch, id_for_account, id_for_category, pretty_table};
+use anyhow::{Context, Result, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
 
 pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
@@ -22,35 +24,500 @@ pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
                 "INSERT INTO accounts(name, type, currency) VALUES (?1, ?2, ?3)",
                 params![name, typ, ccy],
             )?;
-            println!("Added account '{}' ({}, {})", name, typ, ccy);
+            if let Some(group) = sub.get_one::<String>("group") {
+                conn.execute(
+                    "UPDATE accounts SET group_name=?1 WHERE id=?2",
+                    params![group.trim(), conn.last_insert_rowid()],
+                )?;
+            }
+            println!(
+                "{}",
+                crate::i18n::trf("Added account '{}' ({}, {})", &[&name, &typ, &ccy])
+            );
         }
-        Some(("list", _)) => {
-            let mut stmt = conn
-                .prepare("SELECT name, type, currency, created_at FROM accounts ORDER BY name")?;
-            let rows = stmt.query_map([], |r| {
+        Some(("list", sub)) => {
+            let mut stmt = conn.prepare(
+                "SELECT a.name || CASE WHEN a.archived_at IS NULL THEN '' ELSE ' (archived)' END,
+                        a.type, a.currency, a.group_name, c.name, a.created_at
+                 FROM accounts a LEFT JOIN categories c ON c.id = a.default_category_id
+                 WHERE ?1 OR a.archived_at IS NULL
+                 ORDER BY a.name",
+            )?;
+            let rows = stmt.query_map([sub.get_flag("all")], |r| {
                 Ok((
                     r.get::<_, String>(0)?,
                     r.get::<_, String>(1)?,
                     r.get::<_, String>(2)?,
-                    r.get::<_, String>(3)?,
+                    r.get::<_, Option<String>>(3)?,
+                    r.get::<_, Option<String>>(4)?,
+                    r.get::<_, String>(5)?,
                 ))
             })?;
             let mut data = Vec::new();
             for row in rows {
-                let (n, t, c, cr) = row?;
-                data.push(vec![n, t, c, cr]);
+                let (n, t, c, g, dc, cr) = row?;
+                data.push(vec![
+                    n,
+                    t,
+                    c,
+                    g.unwrap_or_default(),
+                    dc.unwrap_or_default(),
+                    cr,
+                ]);
             }
             println!(
                 "{}",
-                pretty_table(&["Name", "Type", "Currency", "Created"], data)
+                pretty_table(
+                    &[
+                        "Name",
+                        "Type",
+                        "Currency",
+                        "Group",
+                        "Default category",
+                        "Created"
+                    ],
+                    data
+                )
+            );
+        }
+        Some(("interest", sub)) => crate::commands::recurring::interest(conn, sub)?,
+        Some(("set-default-category", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim().to_string();
+            let account_id = id_for_account(conn, &name)?;
+            let category = sub.get_one::<String>("category").map(|c| c.trim());
+            let category_id = category.map(|c| id_for_category(conn, c)).transpose()?;
+            conn.execute(
+                "UPDATE accounts SET default_category_id=?1 WHERE id=?2",
+                params![category_id, account_id],
+            )?;
+            match category {
+                Some(c) => println!("Default category for '{}' set to '{}'", name, c),
+                None => println!("Cleared default category for '{}'", name),
+            }
+        }
+        Some(("set-group", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim().to_string();
+            let account_id = id_for_account(conn, &name)?;
+            let group = sub
+                .get_one::<String>("group")
+                .map(|g| g.trim())
+                .filter(|g| !g.is_empty());
+            conn.execute(
+                "UPDATE accounts SET group_name=?1 WHERE id=?2",
+                params![group, account_id],
+            )?;
+            match group {
+                Some(g) => println!("Account '{}' is now in group '{}'", name, g),
+                None => println!("Removed '{}' from its group", name),
+            }
+        }
+        Some(("set-currency", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim().to_string();
+            let ccy = sub
+                .get_one::<String>("currency")
+                .unwrap()
+                .trim()
+                .to_uppercase();
+            let convert = sub.get_flag("convert-history");
+            let plan = plan_currency_change(conn, &name, &ccy, convert)?;
+            let mode = if convert {
+                "converted at historical FX"
+            } else {
+                "relabelled, amounts unchanged"
+            };
+            println!(
+                "'{}': {} -> {}; {} transactions {}",
+                name,
+                plan.from,
+                plan.to,
+                plan.changes.len(),
+                mode
+            );
+            println!(
+                "Balance {} {} -> {} {}",
+                crate::utils::fmt_amount(plan.balance_before, &plan.from),
+                plan.from,
+                crate::utils::fmt_amount(plan.balance_after, &plan.to),
+                plan.to
+            );
+            if plan.other_currency > 0 {
+                println!(
+                    "{} transactions in other currencies are left as they are",
+                    plan.other_currency
+                );
+            }
+            if sub.get_flag("dry-run") {
+                println!("(dry-run) nothing changed");
+            } else {
+                apply_currency_change(conn, &plan)?;
+                println!("Account '{}' is now in {}", name, plan.to);
+            }
+        }
+        Some(("merge", sub)) => {
+            let from = sub.get_one::<String>("from").unwrap().trim();
+            let into = sub.get_one::<String>("into").unwrap().trim();
+            let plan = plan_merge(conn, from, into)?;
+            let verb = if sub.get_flag("dry-run") {
+                "Would move"
+            } else {
+                merge_accounts(conn, &plan)?;
+                "Moved"
+            };
+            println!(
+                "{} {} transactions, {} trades, {} grants, {} dividends and {} jars from '{}' to '{}'",
+                verb,
+                plan.transactions,
+                plan.trades,
+                plan.grants,
+                plan.dividends,
+                plan.jars,
+                plan.from,
+                plan.into
             );
+            if plan.interest {
+                println!(
+                    "{} the interest settings of '{}' to '{}'",
+                    verb, plan.from, plan.into
+                );
+            }
+            println!(
+                "Balance of '{}': {} {} -> {} {}",
+                plan.into,
+                crate::utils::fmt_amount(plan.balance_before, &plan.currency),
+                plan.currency,
+                crate::utils::fmt_amount(plan.balance_after, &plan.currency),
+                plan.currency
+            );
+            if !sub.get_flag("dry-run") {
+                println!("Archived '{}'", plan.from);
+            }
         }
         Some(("rm", sub)) => {
             let name = sub.get_one::<String>("name").unwrap().trim().to_string();
-            conn.execute("DELETE FROM accounts WHERE name=?1", params![name])?;
+            let id: i64 = conn
+                .query_row(
+                    "SELECT id FROM accounts WHERE name=?1",
+                    params![name],
+                    |r| r.get(0),
+                )
+                .optional()?
+                .with_context(|| format!("No account named '{}'", name))?;
+            let impact = crate::utils::removal_impact(
+                conn,
+                id,
+                &[
+                    ("transactions", "account_id", "deleted"),
+                    ("trades", "account_id", "deleted"),
+                    ("grants", "account_id", "deleted with their vests"),
+                    ("dividends", "account_id", "deleted"),
+                    ("jars", "account_id", "deleted"),
+                    ("account_interest", "account_id", "deleted"),
+                ],
+            )?;
+            let what = format!("account '{}'", name);
+            if !crate::interactive::confirm_removal(sub, &what, &impact)? {
+                println!("{}", crate::i18n::tr("Nothing removed"));
+                return Ok(());
+            }
+            conn.execute("DELETE FROM accounts WHERE id=?1", params![id])?;
             println!("Removed account '{}'", name);
         }
         _ => {}
     }
     Ok(())
 }
+
+/// What `account merge` moves from one account into another.
+#[derive(Debug)]
+pub struct MergePlan {
+    pub from: String,
+    pub into: String,
+    pub from_id: i64,
+    pub into_id: i64,
+    pub currency: String,
+    pub transactions: i64,
+    pub trades: i64,
+    pub grants: i64,
+    pub dividends: i64,
+    /// Jars moved over; one named like a jar of `into` is combined with it.
+    pub jars: i64,
+    /// Whether `from` earns interest; its APY and posting state move to `into`.
+    pub interest: bool,
+    /// Balance of `into` before and after taking over `from`'s transactions.
+    pub balance_before: Decimal,
+    pub balance_after: Decimal,
+}
+
+/// Work out a merge of `from` into `into` without writing anything. Both accounts
+/// must be in the same currency, `from` must not be archived already and at most one
+/// of them may earn interest.
+pub fn plan_merge(conn: &Connection, from: &str, into: &str) -> Result<MergePlan> {
+    let from_id = id_for_account(conn, from)?;
+    let into_id = id_for_account(conn, into)?;
+    ensure!(from_id != into_id, "Cannot merge '{}' into itself", from);
+    let lookup = |id: i64| -> Result<(String, String, Option<String>)> {
+        Ok(conn.query_row(
+            "SELECT name, currency, archived_at FROM accounts WHERE id=?1",
+            [id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )?)
+    };
+    let (from_name, from_ccy, archived) = lookup(from_id)?;
+    let (into_name, into_ccy, _) = lookup(into_id)?;
+    ensure!(
+        archived.is_none(),
+        "'{}' was already archived on {}",
+        from_name,
+        archived.unwrap_or_default()
+    );
+    ensure!(
+        from_ccy == into_ccy,
+        "'{}' is in {} but '{}' is in {}; run `account set-currency` on one of them first",
+        from_name,
+        from_ccy,
+        into_name,
+        into_ccy
+    );
+    let earns_interest = |id: i64| -> Result<bool> {
+        Ok(conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM account_interest WHERE account_id=?1)",
+            [id],
+            |r| r.get(0),
+        )?)
+    };
+    let interest = earns_interest(from_id)?;
+    ensure!(
+        !interest || !earns_interest(into_id)?,
+        "Both '{}' and '{}' earn interest; run `account interest rm` on one of them first",
+        from_name,
+        into_name
+    );
+    let count = |table: &str| -> Result<i64> {
+        Ok(conn.query_row(
+            &format!("SELECT COUNT(*) FROM {table} WHERE account_id=?1"),
+            [from_id],
+            |r| r.get(0),
+        )?)
+    };
+    let balance = |id: i64| -> Result<Decimal> {
+        let mut stmt = conn.prepare("SELECT amount FROM transactions WHERE account_id=?1")?;
+        let mut rows = stmt.query([id])?;
+        let mut sum = Decimal::ZERO;
+        while let Some(r) = rows.next()? {
+            let raw: String = r.get(0)?;
+            sum += raw
+                .trim()
+                .parse::<Decimal>()
+                .with_context(|| format!("Invalid amount '{}' in transactions", raw))?;
+        }
+        Ok(sum)
+    };
+    let balance_before = balance(into_id)?;
+    Ok(MergePlan {
+        from: from_name,
+        into: into_name,
+        from_id,
+        into_id,
+        currency: into_ccy,
+        transactions: count("transactions")?,
+        trades: count("trades")?,
+        grants: count("grants")?,
+        dividends: count("dividends")?,
+        jars: count("jars")?,
+        interest,
+        balance_before,
+        balance_after: balance_before + balance(from_id)?,
+    })
+}
+
+/// Move everything booked on `plan.from` and its interest settings to `plan.into`,
+/// carry over its group and default category where `into` has none, then archive
+/// `from`. All or nothing.
+pub fn merge_accounts(conn: &Connection, plan: &MergePlan) -> Result<()> {
+    conn.execute_batch("SAVEPOINT account_merge")?;
+    let result = (|| -> Result<()> {
+        for table in [
+            "transactions",
+            "trades",
+            "grants",
+            "dividends",
+            "account_interest",
+        ] {
+            conn.execute(
+                &format!("UPDATE {table} SET account_id=?1 WHERE account_id=?2"),
+                params![plan.into_id, plan.from_id],
+            )
+            .with_context(|| format!("Move {} from '{}'", table, plan.from))?;
+        }
+        move_jars(conn, plan).with_context(|| format!("Move jars from '{}'", plan.from))?;
+        conn.execute(
+            "UPDATE accounts SET
+                group_name = COALESCE(group_name, (SELECT group_name FROM accounts WHERE id=?2)),
+                default_category_id = COALESCE(default_category_id,
+                    (SELECT default_category_id FROM accounts WHERE id=?2))
+             WHERE id=?1",
+            params![plan.into_id, plan.from_id],
+        )?;
+        conn.execute(
+            "UPDATE accounts SET archived_at=datetime('now') WHERE id=?1",
+            [plan.from_id],
+        )?;
+        Ok(())
+    })();
+    match result {
+        Ok(()) => conn.execute_batch("RELEASE account_merge")?,
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO account_merge; RELEASE account_merge")?;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Give `plan.from`'s jars to `plan.into`. A jar whose name `into` already uses is
+/// folded into that jar: its moves and transactions are repointed, then it is dropped.
+fn move_jars(conn: &Connection, plan: &MergePlan) -> Result<()> {
+    let same_name = conn
+        .prepare(
+            "SELECT f.id, i.id FROM jars f
+             JOIN jars i ON i.account_id=?2 AND i.name=f.name COLLATE NOCASE
+             WHERE f.account_id=?1",
+        )?
+        .query_map(params![plan.from_id, plan.into_id], |r| {
+            Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (from_jar, into_jar) in same_name {
+        for (table, column) in [
+            ("jar_moves", "from_jar_id"),
+            ("jar_moves", "to_jar_id"),
+            ("transactions", "jar_id"),
+        ] {
+            conn.execute(
+                &format!("UPDATE {table} SET {column}=?1 WHERE {column}=?2"),
+                params![into_jar, from_jar],
+            )?;
+        }
+        conn.execute("DELETE FROM jars WHERE id=?1", [from_jar])?;
+    }
+    conn.execute(
+        "UPDATE jars SET account_id=?1 WHERE account_id=?2",
+        params![plan.into_id, plan.from_id],
+    )?;
+    Ok(())
+}
+
+/// What `account set-currency` would do: the new amount for every transaction
+/// booked in the account's old currency.
+#[derive(Debug)]
+pub struct CurrencyChange {
+    pub account_id: i64,
+    pub from: String,
+    pub to: String,
+    /// (transaction id, new amount)
+    pub changes: Vec<(i64, Decimal)>,
+    pub balance_before: Decimal,
+    pub balance_after: Decimal,
+    /// Transactions in a currency other than the account's, which stay untouched.
+    pub other_currency: usize,
+}
+
+/// Work out a currency change for `name` without writing anything. With `convert`,
+/// each amount is converted at the rate of its own date and kept at its original
+/// precision (at least cents); otherwise the amounts are simply relabelled.
+pub fn plan_currency_change(
+    conn: &Connection,
+    name: &str,
+    to: &str,
+    convert: bool,
+) -> Result<CurrencyChange> {
+    ensure!(
+        to.len() == 3 && to.chars().all(|c| c.is_ascii_alphabetic()),
+        "Currency must be a 3-letter code like EUR"
+    );
+    let account_id = id_for_account(conn, name)?;
+    let from: String = conn.query_row(
+        "SELECT currency FROM accounts WHERE id=?1",
+        params![account_id],
+        |r| r.get(0),
+    )?;
+    ensure!(
+        !from.eq_ignore_ascii_case(to),
+        "Account '{}' is already in {}",
+        name,
+        to
+    );
+
+    let mut stmt = conn.prepare(
+        "SELECT id, date, amount, currency FROM transactions WHERE account_id=?1 ORDER BY date, id",
+    )?;
+    let mut rows = stmt.query(params![account_id])?;
+    let mut booked = Vec::new();
+    let mut other_currency = 0;
+    while let Some(r) = rows.next()? {
+        let currency: String = r.get(3)?;
+        if !currency.eq_ignore_ascii_case(&from) {
+            other_currency += 1;
+            continue;
+        }
+        let raw: String = r.get(2)?;
+        let amount = raw
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", raw))?;
+        let date = NaiveDate::parse_from_str(&r.get::<_, String>(1)?, "%Y-%m-%d")?;
+        booked.push((r.get::<_, i64>(0)?, date, amount));
+    }
+
+    let new_amounts = if convert {
+        let items: Vec<_> = booked
+            .iter()
+            .map(|(_, date, amount)| (*date, *amount, from.as_str()))
+            .collect();
+        fx_convert_batch(conn, &items, to)
+            .with_context(|| format!("Convert history from {} to {}", from, to))?
+            .into_iter()
+            .zip(&booked)
+            .map(|(value, (_, _, amount))| value.round_dp(amount.scale().max(2)))
+            .collect()
+    } else {
+        booked
+            .iter()
+            .map(|(_, _, amount)| *amount)
+            .collect::<Vec<_>>()
+    };
+
+    Ok(CurrencyChange {
+        account_id,
+        from,
+        to: to.to_string(),
+        balance_before: booked.iter().map(|(_, _, amount)| *amount).sum(),
+        balance_after: new_amounts.iter().copied().sum(),
+        changes: booked.iter().map(|(id, ..)| *id).zip(new_amounts).collect(),
+        other_currency,
+    })
+}
+
+pub fn apply_currency_change(conn: &Connection, plan: &CurrencyChange) -> Result<()> {
+    conn.execute_batch("SAVEPOINT account_set_currency")?;
+    let applied = (|| -> Result<()> {
+        let mut update =
+            conn.prepare("UPDATE transactions SET amount=?1, currency=?2 WHERE id=?3")?;
+        for (id, amount) in &plan.changes {
+            update.execute(params![amount.to_string(), plan.to, id])?;
+        }
+        conn.execute(
+            "UPDATE accounts SET currency=?1 WHERE id=?2",
+            params![plan.to, plan.account_id],
+        )?;
+        Ok(())
+    })();
+    match applied {
+        Ok(()) => conn.execute_batch("RELEASE account_set_currency")?,
+        Err(err) => {
+            conn.execute_batch("ROLLBACK TO account_set_currency; RELEASE account_set_currency")?;
+            return Err(err);
+        }
+    }
+    Ok(())
+}
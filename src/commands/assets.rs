@@ -0,0 +1,253 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Ticker renames (`asset alias`): the old symbol keeps resolving to the asset, its
+//! trades, lots and prices continue under the new one, and price fetching asks for
+//! the symbol in effect on the day.
+
+use crate::models::AssetAliasRow;
+use crate::utils::{maybe_print_json, parse_date, pretty_table};
+use anyhow::{Context, Result, bail, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("alias", sub)) => alias(conn, sub),
+        _ => Ok(()),
+    }
+}
+
+fn alias(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => {
+            let from = sub.get_one::<String>("from").unwrap().trim();
+            let to = sub.get_one::<String>("to").unwrap().trim();
+            let effective = parse_date(sub.get_one::<String>("effective").unwrap().trim())?;
+            let merged = add_alias(conn, from, to, effective)?;
+            if merged {
+                println!(
+                    "Merged {} into {}; {} now resolves to {} (symbol changed {})",
+                    from, to, from, to, effective
+                );
+            } else {
+                println!(
+                    "{} now resolves to {} (symbol changed {})",
+                    from, to, effective
+                );
+            }
+        }
+        Some(("list", sub)) => {
+            let mut stmt = conn.prepare(
+                "SELECT al.symbol, a.ticker, al.effective FROM asset_aliases al
+                 JOIN assets a ON a.id=al.asset_id ORDER BY a.ticker, al.effective",
+            )?;
+            let data = stmt
+                .query_map([], |r| {
+                    Ok(AssetAliasRow {
+                        old_symbol: r.get(0)?,
+                        ticker: r.get(1)?,
+                        renamed_on: r.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                println!(
+                    "{}",
+                    pretty_table(
+                        &["Old symbol", "Ticker", "Renamed on"],
+                        data.into_iter().map(AssetAliasRow::cells).collect()
+                    )
+                );
+            }
+        }
+        Some(("rm", sub)) => {
+            let from = sub.get_one::<String>("from").unwrap().trim();
+            if conn.execute(
+                "DELETE FROM asset_aliases WHERE symbol=?1 COLLATE NOCASE",
+                params![from],
+            )? == 0
+            {
+                bail!("No alias '{}'", from);
+            }
+            println!(
+                "Removed alias {}; merged history stays with the new ticker",
+                from
+            );
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Tables whose rows belong to an asset and move with it on a merge.
+const ASSET_TABLES: &[&str] = &["trades", "grants", "dividends", "price_alerts"];
+
+/// Record that `from` was renamed to `to` on `effective`. When only `from` exists as an
+/// asset it is renamed; when both exist, `from`'s history is merged into `to`. Returns
+/// whether a merge happened.
+pub fn add_alias(conn: &Connection, from: &str, to: &str, effective: NaiveDate) -> Result<bool> {
+    ensure!(
+        !from.eq_ignore_ascii_case(to),
+        "--from and --to are the same ticker"
+    );
+    let find = |ticker: &str| -> Result<Option<(i64, String)>> {
+        Ok(conn
+            .query_row(
+                "SELECT id, currency FROM assets WHERE ticker=?1 COLLATE NOCASE",
+                params![ticker],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .optional()?)
+    };
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM asset_aliases WHERE symbol=?1 COLLATE NOCASE)",
+        params![from],
+        |r| r.get(0),
+    )?;
+    ensure!(!taken, "{} is already an alias", from);
+
+    conn.execute_batch("SAVEPOINT asset_alias")?;
+    let res = (|| -> Result<bool> {
+        let (to_id, merged) = match (find(from)?, find(to)?) {
+            (None, None) => bail!(
+                "Neither {} nor {} is an asset; add it with `portfolio add-asset`",
+                from,
+                to
+            ),
+            (Some((from_id, _)), None) => {
+                conn.execute(
+                    "UPDATE assets SET ticker=?1 WHERE id=?2",
+                    params![to, from_id],
+                )?;
+                (from_id, false)
+            }
+            (None, Some((to_id, _))) => (to_id, false),
+            (Some((from_id, from_ccy)), Some((to_id, to_ccy))) => {
+                ensure!(
+                    from_ccy == to_ccy,
+                    "{} is in {} but {} is in {}",
+                    from,
+                    from_ccy,
+                    to,
+                    to_ccy
+                );
+                for table in ASSET_TABLES {
+                    conn.execute(
+                        &format!("UPDATE {table} SET asset_id=?1 WHERE asset_id=?2"),
+                        params![to_id, from_id],
+                    )
+                    .with_context(|| format!("Move {} of {} to {}", table, from, to))?;
+                }
+                // Both tickers may have a price for the same moment; the new one wins.
+                conn.execute(
+                    "UPDATE OR IGNORE prices SET asset_id=?1 WHERE asset_id=?2",
+                    params![to_id, from_id],
+                )?;
+                conn.execute("DELETE FROM prices WHERE asset_id=?1", params![from_id])?;
+                conn.execute(
+                    "UPDATE asset_aliases SET asset_id=?1 WHERE asset_id=?2",
+                    params![to_id, from_id],
+                )?;
+                conn.execute("DELETE FROM assets WHERE id=?1", params![from_id])?;
+                (to_id, true)
+            }
+        };
+        conn.execute(
+            "INSERT INTO asset_aliases(asset_id, symbol, effective) VALUES (?1, ?2, ?3)",
+            params![to_id, from, effective.to_string()],
+        )?;
+        Ok(merged)
+    })();
+    match res {
+        Ok(merged) => {
+            conn.execute_batch("RELEASE asset_alias")?;
+            Ok(merged)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO asset_alias; RELEASE asset_alias")?;
+            Err(e)
+        }
+    }
+}
+
+/// The asset a renamed ticker now belongs to, if `symbol` is an alias.
+pub fn aliased_asset(conn: &Connection, symbol: &str) -> Result<Option<i64>> {
+    Ok(conn
+        .query_row(
+            "SELECT asset_id FROM asset_aliases WHERE symbol=?1 COLLATE NOCASE",
+            params![symbol],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// The symbol asset `asset_id` (now `ticker`) traded under on `date`: the oldest alias
+/// whose rename is still ahead of `date`, or the ticker itself.
+pub fn symbol_on(
+    conn: &Connection,
+    asset_id: i64,
+    ticker: &str,
+    date: NaiveDate,
+) -> Result<String> {
+    let old: Option<String> = conn
+        .query_row(
+            "SELECT symbol FROM asset_aliases WHERE asset_id=?1 AND effective > ?2
+             ORDER BY effective LIMIT 1",
+            params![asset_id, date.to_string()],
+            |r| r.get(0),
+        )
+        .optional()?;
+    Ok(old.unwrap_or_else(|| ticker.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alias_merges_history_and_resolves_old_symbol() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT UNIQUE, name TEXT, currency TEXT);
+            CREATE TABLE asset_aliases(id INTEGER PRIMARY KEY, asset_id INTEGER, symbol TEXT UNIQUE, effective TEXT);
+            CREATE TABLE trades(id INTEGER PRIMARY KEY, date TEXT, asset_id INTEGER, quantity TEXT);
+            CREATE TABLE grants(id INTEGER PRIMARY KEY, asset_id INTEGER);
+            CREATE TABLE dividends(id INTEGER PRIMARY KEY, asset_id INTEGER);
+            CREATE TABLE price_alerts(id INTEGER PRIMARY KEY, asset_id INTEGER);
+            CREATE TABLE prices(id INTEGER PRIMARY KEY, asset_id INTEGER, as_of TEXT, price TEXT, UNIQUE(asset_id, as_of));
+            INSERT INTO assets(id, ticker, name, currency) VALUES (1, 'FB', 'Facebook', 'USD'), (2, 'META', 'Meta', 'USD');
+            INSERT INTO trades(date, asset_id, quantity) VALUES ('2021-03-01', 1, '10'), ('2023-01-05', 2, '5');
+            INSERT INTO prices(asset_id, as_of, price) VALUES (1, '2022-06-08', '196'), (1, '2022-06-09', '197'), (2, '2022-06-09', '198');
+            "#,
+        )
+        .unwrap();
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        assert!(add_alias(&conn, "FB", "META", day("2022-06-09")).unwrap());
+        assert!(add_alias(&conn, "FB", "META", day("2022-06-09")).is_err());
+        let (assets, trades, prices): (i64, i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM assets),
+                        (SELECT COUNT(*) FROM trades WHERE asset_id=2),
+                        (SELECT COUNT(*) FROM prices WHERE asset_id=2)",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((assets, trades, prices), (1, 2, 2));
+        assert_eq!(crate::utils::id_for_asset(&conn, "FB").unwrap(), 2);
+        assert_eq!(
+            symbol_on(&conn, 2, "META", day("2022-06-08")).unwrap(),
+            "FB"
+        );
+        assert_eq!(
+            symbol_on(&conn, 2, "META", day("2022-06-09")).unwrap(),
+            "META"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use anyhow::{Context, Result, anyhow, bail};
+use rusqlite::Connection;
+use std::io::Read;
+
+pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
+    let script = match m.get_one::<String>("file").map(|s| s.trim()) {
+        Some(path) if path != "-" => {
+            std::fs::read_to_string(path).with_context(|| format!("Read batch file {}", path))?
+        }
+        _ => {
+            let mut buf = String::new();
+            std::io::stdin()
+                .read_to_string(&mut buf)
+                .context("Read batch commands from stdin")?;
+            buf
+        }
+    };
+    let ran = run_script(conn, &script)?;
+    println!("Batch committed: {} command(s)", ran);
+    Ok(())
+}
+
+/// Execute one command per line inside a single savepoint. Blank lines and `#`
+/// comments are skipped and a leading `moneyclip` is optional. Any failure rolls
+/// back every earlier line.
+pub fn run_script(conn: &mut Connection, script: &str) -> Result<usize> {
+    let mut commands = Vec::new();
+    for (idx, line) in script.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let mut words = shell_words::split(trimmed)
+            .with_context(|| format!("Line {}: cannot parse `{}`", idx + 1, trimmed))?;
+        if words.first().map(String::as_str) == Some("moneyclip") {
+            words.remove(0);
+        }
+        let first = words.first().map(String::as_str);
+        if matches!(first, Some("batch" | "db" | "init")) {
+            bail!(
+                "Line {}: `{}` cannot run inside a batch",
+                idx + 1,
+                first.unwrap()
+            );
+        }
+        let argv = ["moneyclip", "--no-input"]
+            .into_iter()
+            .map(String::from)
+            .chain(words);
+        let matches = crate::cli::build_cli()
+            .try_get_matches_from(argv)
+            .map_err(|e| anyhow!("Line {}: {}", idx + 1, e.render().to_string().trim()))?;
+        commands.push((idx + 1, trimmed.to_string(), matches));
+    }
+
+    conn.execute_batch("SAVEPOINT moneyclip_batch")?;
+    for (line_no, text, matches) in &commands {
+        if let Err(err) = super::dispatch(conn, matches) {
+            conn.execute_batch("ROLLBACK TO moneyclip_batch; RELEASE moneyclip_batch")?;
+            return Err(err.context(format!(
+                "Line {} (`{}`) failed; batch rolled back, nothing was changed",
+                line_no, text
+            )));
+        }
+    }
+    conn.execute_batch("RELEASE moneyclip_batch")?;
+    Ok(commands.len())
+}
@@ -4,7 +4,12 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::{id_for_category, parse_decimal, parse_month, pretty_table};
+use crate::models::{BudgetReportRow, BudgetRow};
+use crate::utils::{
+    Totals, compute_totals, fmt_amount, fx_convert, get_base_currency, id_for_category,
+    maybe_print_json_with_totals, minor_units, parse_amount, parse_month, pretty_table,
+    valuation_month_end,
+};
 use anyhow::{Context, Result};
 use rusqlite::{Connection, OptionalExtension, params};
 use rust_decimal::Decimal;
@@ -14,6 +19,8 @@ pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
         Some(("set", sub)) => set(conn, sub)?,
         Some(("list", sub)) => list(conn, sub)?,
         Some(("report", sub)) => report(conn, sub)?,
+        Some(("rebase", sub)) => rebase(conn, sub)?,
+        Some(("scenario", sub)) => crate::commands::scenarios::handle(conn, sub)?,
         _ => {}
     }
     Ok(())
@@ -26,70 +33,207 @@ fn set(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
         .unwrap()
         .trim()
         .to_string();
-    let amount = parse_decimal(sub.get_one::<String>("amount").unwrap().trim())?;
+    let amount = parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
+    let ccy = match sub.get_one::<String>("currency") {
+        Some(c) => c.trim().to_uppercase(),
+        None => get_base_currency(conn)?,
+    };
     let cat_id = id_for_category(conn, &cat)?;
     conn.execute(
-        "INSERT INTO budgets(month, category_id, amount) VALUES (?1,?2,?3)
-         ON CONFLICT(month, category_id) DO UPDATE SET amount=excluded.amount",
-        params![month, cat_id, amount.to_string()],
+        "INSERT INTO budgets(month, category_id, amount, currency) VALUES (?1,?2,?3,?4)
+         ON CONFLICT(month, category_id) DO UPDATE SET amount=excluded.amount, currency=excluded.currency",
+        params![month, cat_id, amount.to_string(), ccy],
     )?;
-    println!("Budget set for {} / {} = {}", month, cat, amount);
+    println!(
+        "{}",
+        crate::i18n::trf(
+            "Budget set for {} / {} = {} {}",
+            &[&month, &cat, &amount, &ccy]
+        )
+    );
     Ok(())
 }
 
-fn list(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
-    let mut sql = String::from(
-        "SELECT b.month, c.name, b.amount FROM budgets b JOIN categories c ON b.category_id=c.id",
-    );
-    if let Some(month_raw) = sub.get_one::<String>("month") {
-        let month = month_raw.trim();
-        if !month.is_empty() {
-            sql.push_str(" WHERE b.month=?1 ORDER BY c.name");
-            let mut stmt = conn.prepare(&sql)?;
-            let rows = stmt.query_map(params![month], |r| {
-                Ok((
-                    r.get::<_, String>(0)?,
-                    r.get::<_, String>(1)?,
-                    r.get::<_, String>(2)?,
-                ))
-            })?;
-            let mut data = Vec::new();
-            for row in rows {
-                let (m, c, a) = row?;
-                data.push(vec![m, c, a]);
-            }
-            println!(
-                "{}",
-                pretty_table(&["Month", "Category", "Budget (BASE)"], data)
-            );
-            return Ok(());
+/// Convert every budget not already in `--to` at its month-end rate. Rows saved
+/// before budgets had a currency are taken to be in `--from`, by default the
+/// current base currency, so run this right after `fx set-base`.
+fn rebase(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let to = sub.get_one::<String>("to").unwrap().trim().to_uppercase();
+    let from = match sub.get_one::<String>("from") {
+        Some(c) => c.trim().to_uppercase(),
+        None => get_base_currency(conn)?,
+    };
+    let mut stmt = conn.prepare(
+        "SELECT id, month, amount, COALESCE(currency, ?1) FROM budgets
+         WHERE COALESCE(currency, ?1) != ?2 ORDER BY month, id",
+    )?;
+    let mut rows = stmt.query(params![from, to])?;
+    let mut changes = Vec::new();
+    while let Some(r) = rows.next()? {
+        let (id, month, raw, ccy): (i64, String, String, String) =
+            (r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?);
+        let amount = raw
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid budget amount '{}' for {}", raw, month))?;
+        let converted = fx_convert(conn, valuation_month_end(conn, &month)?, amount, &ccy, &to)?
+            .round_dp(minor_units(&to));
+        changes.push((id, month, amount, ccy, converted));
+    }
+    drop(rows);
+    drop(stmt);
+
+    if sub.get_flag("dry-run") {
+        let data = changes
+            .iter()
+            .map(|(_, month, amount, ccy, converted)| {
+                vec![
+                    month.clone(),
+                    format!("{} {}", amount, ccy),
+                    format!("{} {}", converted, to),
+                ]
+            })
+            .collect();
+        crate::utils::emit(pretty_table(&["Month", "Budget", "Rebased"], data));
+        println!(
+            "(dry-run) {} budget(s) would be converted to {}",
+            changes.len(),
+            to
+        );
+        return Ok(());
+    }
+    conn.execute_batch("SAVEPOINT budget_rebase")?;
+    let applied = changes.iter().try_for_each(|(id, _, _, _, converted)| {
+        conn.execute(
+            "UPDATE budgets SET amount=?1, currency=?2 WHERE id=?3",
+            params![converted.to_string(), to, id],
+        )
+        .map(|_| ())
+    });
+    match applied {
+        Ok(()) => conn.execute_batch("RELEASE budget_rebase")?,
+        Err(err) => {
+            conn.execute_batch("ROLLBACK TO budget_rebase; RELEASE budget_rebase")?;
+            return Err(err.into());
         }
     }
-    sql.push_str(" ORDER BY b.month DESC, c.name");
-    let mut stmt = conn.prepare(&sql)?;
-    let rows = stmt.query_map([], |r| {
+    println!("Converted {} budget(s) to {}", changes.len(), to);
+    Ok(())
+}
+
+/// Budget of `category_id` for `month` in `ccy`, converted at the month-end rate;
+/// zero when none is set. Rows without a currency predate per-budget currencies
+/// and are read as base currency.
+pub fn budget_in(conn: &Connection, category_id: i64, month: &str, ccy: &str) -> Result<Decimal> {
+    let row: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT amount, currency FROM budgets WHERE category_id=?1 AND month=?2",
+            params![category_id, month],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?;
+    let Some((raw, budget_ccy)) = row else {
+        return Ok(Decimal::ZERO);
+    };
+    let amount = raw
+        .parse::<Decimal>()
+        .with_context(|| format!("Invalid budget amount '{}' for {}", raw, month))?;
+    let budget_ccy = match budget_ccy {
+        Some(c) => c,
+        None => get_base_currency(conn)?,
+    };
+    if budget_ccy.eq_ignore_ascii_case(ccy) {
+        return Ok(amount);
+    }
+    fx_convert(
+        conn,
+        valuation_month_end(conn, month)?,
+        amount,
+        &budget_ccy,
+        ccy,
+    )
+}
+
+fn list(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let month = match sub.get_one::<String>("month").map(|m| m.trim()) {
+        Some(m) if !m.is_empty() => Some(parse_month(m)?),
+        _ => None,
+    };
+    let mut stmt = conn.prepare(
+        "SELECT b.month, c.name, b.amount, COALESCE(b.currency, ?1)
+         FROM budgets b JOIN categories c ON b.category_id=c.id
+         WHERE ?2 IS NULL OR b.month=?2
+         ORDER BY b.month DESC, c.name",
+    )?;
+    let base = get_base_currency(conn)?;
+    let rows = stmt.query_map(params![base, month], |r| {
         Ok((
             r.get::<_, String>(0)?,
             r.get::<_, String>(1)?,
             r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
         ))
     })?;
     let mut data = Vec::new();
     for row in rows {
-        let (m, c, a) = row?;
-        data.push(vec![m, c, a]);
+        let (month, category, amount, currency) = row?;
+        data.push(BudgetRow {
+            month,
+            category,
+            amount,
+            currency,
+        });
     }
-    println!(
-        "{}",
-        pretty_table(&["Month", "Category", "Budget (BASE)"], data)
-    );
+    let totals = if sub.get_flag("totals") {
+        Some(list_totals(conn, &data, &base)?)
+    } else {
+        None
+    };
+    let (json_flag, jsonl_flag) = (sub.get_flag("json"), sub.get_flag("jsonl"));
+    if maybe_print_json_with_totals(json_flag, jsonl_flag, &data, totals.as_ref())? {
+        return Ok(());
+    }
+    let mut cells: Vec<_> = data.into_iter().map(BudgetRow::cells).collect();
+    if let Some(totals) = &totals {
+        for (i, (amount, ccy)) in totals.lines().into_iter().enumerate() {
+            let label = if i == 0 {
+                format!("{} budgets", totals.count)
+            } else {
+                String::new()
+            };
+            cells.push(vec!["Total".into(), label, amount, ccy]);
+        }
+    }
+    crate::utils::emit(pretty_table(
+        &["Month", "Category", "Budget", "Currency"],
+        cells,
+    ));
     Ok(())
 }
 
+/// `--totals` for `budget list` rows; each budget is converted to `base` at its
+/// month-end rate.
+pub fn list_totals(conn: &Connection, rows: &[BudgetRow], base: &str) -> Result<Totals> {
+    let mut items = Vec::with_capacity(rows.len());
+    for row in rows {
+        let amount = row.amount.trim().parse::<Decimal>().with_context(|| {
+            format!(
+                "Invalid budget '{}' for {} {}",
+                row.amount, row.month, row.category
+            )
+        })?;
+        items.push((
+            valuation_month_end(conn, &row.month)?,
+            amount,
+            row.currency.as_str(),
+        ));
+    }
+    compute_totals(conn, &items, base)
+}
+
 fn report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let json_flag = sub.get_flag("json");
     let jsonl_flag = sub.get_flag("jsonl");
-    let month = sub.get_one::<String>("month").unwrap().trim().to_string();
+    let month = parse_month(sub.get_one::<String>("month").unwrap().trim())?;
     let out_ccy = sub
         .get_one::<String>("currency")
         .map(|s| s.trim().to_uppercase());
@@ -98,23 +242,64 @@ fn report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let data = build_budget_report(conn, &month, &base_ccy, out_ccy.as_deref())?;
     let display_ccy = out_ccy.as_deref().unwrap_or(&base_ccy);
 
+    if sub.get_flag("chart") {
+        crate::utils::emit(budget_chart(&data, display_ccy)?.trim_end_matches('\n'));
+        return Ok(());
+    }
+
     if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
-        let hdr_budget = format!("Budget ({})", display_ccy);
-        let hdr_spent = format!("Spent ({})", display_ccy);
-        println!(
-            "{}",
-            pretty_table(&["Category", &hdr_budget, &hdr_spent], data)
-        );
+        let cells = data.into_iter().map(BudgetReportRow::cells).collect();
+        crate::utils::emit(pretty_table(
+            &["Category", "Budget", "Spent", "Currency"],
+            cells,
+        ));
     }
     Ok(())
 }
 
-fn build_budget_report(
+/// Consumption bar per budgeted category; overspent categories are marked with `!`.
+fn budget_chart(data: &[BudgetReportRow], ccy: &str) -> Result<String> {
+    const WIDTH: usize = 30;
+    let width = data
+        .iter()
+        .map(|r| r.category.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for row in data {
+        let budget = row.budget.parse::<Decimal>()?;
+        let spent = row.spent.parse::<Decimal>()?;
+        if budget <= Decimal::ZERO {
+            continue;
+        }
+        let pct = spent * Decimal::from(100) / budget;
+        let filled = crate::utils::bar(spent, budget, WIDTH);
+        let empty = "░".repeat(WIDTH - filled.chars().count());
+        out.push_str(&format!(
+            "{:<width$} {}{} {:>6}% {} / {} {}{}\n",
+            row.category,
+            filled,
+            empty,
+            crate::utils::fmt_percent(pct),
+            row.spent,
+            row.budget,
+            ccy,
+            if spent > budget { " !" } else { "" },
+            width = width
+        ));
+    }
+    if out.is_empty() {
+        out.push_str("No budgets set for this month\n");
+    }
+    Ok(out)
+}
+
+pub fn build_budget_report(
     conn: &Connection,
     month: &str,
     base_ccy: &str,
     out_ccy: Option<&str>,
-) -> Result<Vec<Vec<String>>> {
+) -> Result<Vec<BudgetReportRow>> {
     let categories = {
         let mut stmt = conn.prepare_cached("SELECT id, name FROM categories ORDER BY name")?;
         let mut rows = stmt.query([])?;
@@ -125,28 +310,19 @@ fn build_budget_report(
         cats
     };
 
-    let mut budget_stmt =
-        conn.prepare_cached("SELECT amount FROM budgets WHERE category_id=?1 AND month=?2")?;
     let mut tx_stmt = conn.prepare_cached(
-        "SELECT date, amount, currency FROM transactions WHERE category_id=?1 AND amount<0 AND substr(date,1,7)=?2",
+        "SELECT date, amount, currency FROM transactions WHERE category_id=?1 AND amount<0 AND date BETWEEN ?2 AND ?3",
     )?;
+    let (start, end) = crate::utils::periods().month_range(month)?;
 
-    let month_end = crate::utils::month_end(month)?;
+    let month_end = valuation_month_end(conn, month)?;
     let mut data = Vec::with_capacity(categories.len());
 
     for (cid, cname) in categories {
-        let budget_s: Option<String> = budget_stmt
-            .query_row(params![cid, month], |r| r.get(0))
-            .optional()?;
-        let budget_dec = match budget_s {
-            Some(ref s) => s
-                .parse::<Decimal>()
-                .with_context(|| format!("Invalid budget amount '{}' for {}", s, month))?,
-            None => Decimal::ZERO,
-        };
+        let budget_dec = budget_in(conn, cid, month, base_ccy)?;
 
-        let mut trs = tx_stmt.query(params![cid, month])?;
-        let mut spent_base = Decimal::ZERO;
+        let mut trs = tx_stmt.query(params![cid, start.to_string(), end.to_string()])?;
+        let mut spends = Vec::new();
         while let Some(r) = trs.next()? {
             let d: String = r.get(0)?;
             let amt_s: String = r.get(1)?;
@@ -155,27 +331,38 @@ fn build_budget_report(
             let amt = amt_s
                 .parse::<Decimal>()
                 .with_context(|| format!("Invalid amount '{}' in transactions", amt_s))?;
-            let conv = crate::utils::fx_convert(conn, date, amt.abs(), &ccy, base_ccy)?;
-            spent_base += conv;
+            spends.push((date, amt.abs(), ccy));
         }
+        let items: Vec<_> = spends
+            .iter()
+            .map(|(date, amt, ccy)| (*date, *amt, ccy.as_str()))
+            .collect();
+        let spent_base: Decimal = crate::utils::fx_convert_batch(conn, &items, base_ccy)?
+            .into_iter()
+            .sum();
 
         let spent_disp = if let Some(target) = out_ccy {
             let converted =
                 crate::utils::fx_convert(conn, month_end, spent_base, base_ccy, target)?;
-            format!("{:.2}", converted)
+            fmt_amount(converted, target)
         } else {
-            format!("{:.2}", spent_base)
+            fmt_amount(spent_base, base_ccy)
         };
 
         let budget_disp = if let Some(target) = out_ccy {
             let converted =
                 crate::utils::fx_convert(conn, month_end, budget_dec, base_ccy, target)?;
-            format!("{:.2}", converted)
+            fmt_amount(converted, target)
         } else {
-            format!("{:.2}", budget_dec)
+            fmt_amount(budget_dec, base_ccy)
         };
 
-        data.push(vec![cname, budget_disp, spent_disp]);
+        data.push(BudgetReportRow {
+            category: cname,
+            budget: budget_disp,
+            spent: spent_disp,
+            currency: out_ccy.unwrap_or(base_ccy).to_string(),
+        });
     }
 
     Ok(data)
@@ -184,6 +371,7 @@ fn build_budget_report(
 #[cfg(test)]
 mod tests {
     use super::build_budget_report;
+    use crate::models::BudgetReportRow;
     use rusqlite::{Connection, params};
 
     fn setup_conn() -> Connection {
@@ -193,7 +381,7 @@ mod tests {
             PRAGMA foreign_keys = ON;
             CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
             CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
-            CREATE TABLE budgets(id INTEGER PRIMARY KEY AUTOINCREMENT, month TEXT NOT NULL, category_id INTEGER NOT NULL, amount TEXT NOT NULL, UNIQUE(month, category_id));
+            CREATE TABLE budgets(id INTEGER PRIMARY KEY AUTOINCREMENT, month TEXT NOT NULL, category_id INTEGER NOT NULL, amount TEXT NOT NULL, currency TEXT, UNIQUE(month, category_id));
             CREATE TABLE transactions(id INTEGER PRIMARY KEY AUTOINCREMENT, date TEXT NOT NULL, account_id INTEGER, amount TEXT NOT NULL, payee TEXT, category_id INTEGER, currency TEXT NOT NULL, note TEXT);
             CREATE TABLE fx_rates(id INTEGER PRIMARY KEY AUTOINCREMENT, date TEXT NOT NULL, base TEXT NOT NULL, quote TEXT NOT NULL, rate TEXT NOT NULL, UNIQUE(date, base, quote));
             "#,
@@ -235,21 +423,23 @@ mod tests {
         let rows_base = build_budget_report(&conn, "2025-08", "USD", None).unwrap();
         assert_eq!(
             rows_base,
-            vec![vec![
-                String::from("Dining"),
-                String::from("100.00"),
-                String::from("20.00"),
-            ]]
+            vec![BudgetReportRow {
+                category: String::from("Dining"),
+                budget: String::from("100.00"),
+                spent: String::from("20.00"),
+                currency: String::from("USD"),
+            }]
         );
 
         let rows_eur = build_budget_report(&conn, "2025-08", "USD", Some("EUR")).unwrap();
         assert_eq!(
             rows_eur,
-            vec![vec![
-                String::from("Dining"),
-                String::from("80.00"),
-                String::from("16.00"),
-            ]]
+            vec![BudgetReportRow {
+                category: String::from("Dining"),
+                budget: String::from("80.00"),
+                spent: String::from("16.00"),
+                currency: String::from("EUR"),
+            }]
         );
     }
 }
@@ -4,9 +4,12 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::pretty_table;
-use anyhow::Result;
-use rusqlite::{Connection, params};
+use crate::utils::{fx_convert_batch, get_base_currency, parse_amount, pretty_table};
+use anyhow::{Context, Result, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+use std::fmt;
 
 pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
@@ -16,20 +19,148 @@ pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
             println!("Added category '{}'", name);
         }
         Some(("list", _)) => {
-            let mut stmt = conn.prepare("SELECT name FROM categories ORDER BY name")?;
-            let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
-            let mut data = Vec::new();
-            for row in rows {
-                data.push(vec![row?]);
+            let mut stmt = conn.prepare(
+                "SELECT name, COALESCE(monthly_cap || ' ' || cap_currency, '')
+                 FROM categories ORDER BY name",
+            )?;
+            let rows = stmt.query_map([], |r| Ok(vec![r.get(0)?, r.get(1)?]))?;
+            let data = rows.collect::<rusqlite::Result<Vec<Vec<String>>>>()?;
+            println!("{}", pretty_table(&["Category", "Monthly cap"], data));
+        }
+        Some(("set-cap", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let id = crate::utils::id_for_category(conn, name)?;
+            if sub.get_flag("clear") {
+                conn.execute(
+                    "UPDATE categories SET monthly_cap=NULL, cap_currency=NULL WHERE id=?1",
+                    params![id],
+                )?;
+                println!("Removed the monthly cap on '{}'", name);
+            } else {
+                let amount = parse_amount(sub.get_one::<String>("amount").unwrap())?;
+                ensure!(amount > Decimal::ZERO, "A monthly cap must be positive");
+                let ccy = match sub.get_one::<String>("currency") {
+                    Some(c) => c.trim().to_uppercase(),
+                    None => get_base_currency(conn)?,
+                };
+                conn.execute(
+                    "UPDATE categories SET monthly_cap=?1, cap_currency=?2 WHERE id=?3",
+                    params![amount.to_string(), ccy, id],
+                )?;
+                println!("Capped '{}' at {} {} a month", name, amount, ccy);
             }
-            println!("{}", pretty_table(&["Category"], data));
         }
         Some(("rm", sub)) => {
             let name = sub.get_one::<String>("name").unwrap().trim().to_string();
-            conn.execute("DELETE FROM categories WHERE name=?1", params![name])?;
+            let id: i64 = conn
+                .query_row(
+                    "SELECT id FROM categories WHERE name=?1",
+                    params![name],
+                    |r| r.get(0),
+                )
+                .optional()?
+                .with_context(|| format!("No category named '{}'", name))?;
+            let impact = crate::utils::removal_impact(
+                conn,
+                id,
+                &[
+                    ("transactions", "category_id", "left uncategorized"),
+                    ("budgets", "category_id", "deleted"),
+                    (
+                        "budget_scenario_lines",
+                        "category_id",
+                        "deleted from budget scenarios",
+                    ),
+                    ("goals", "category_id", "deleted"),
+                    ("rules", "category_id", "left without a category"),
+                    ("mcc_map", "category_id", "deleted"),
+                    (
+                        "accounts",
+                        "default_category_id",
+                        "default category cleared",
+                    ),
+                ],
+            )?;
+            let what = format!("category '{}'", name);
+            if !crate::interactive::confirm_removal(sub, &what, &impact)? {
+                println!("{}", crate::i18n::tr("Nothing removed"));
+                return Ok(());
+            }
+            conn.execute("DELETE FROM categories WHERE id=?1", params![id])?;
             println!("Removed category '{}'", name);
         }
         _ => {}
     }
     Ok(())
 }
+
+/// A category whose spending in `month` is above its monthly cap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapBreach {
+    pub category: String,
+    pub month: String,
+    pub spent: Decimal,
+    pub cap: Decimal,
+    pub currency: String,
+}
+
+impl fmt::Display for CapBreach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is over its monthly cap for {}: spent {} of {} {}",
+            self.category,
+            self.month,
+            crate::utils::fmt_amount(self.spent, &self.currency),
+            crate::utils::fmt_amount(self.cap, &self.currency),
+            self.currency
+        )
+    }
+}
+
+/// Outflows of `category_id` in `month` (YYYY-MM) against its cap, each converted
+/// to the cap's currency at its own date. `None` when uncapped or within the cap.
+pub fn cap_breach(conn: &Connection, category_id: i64, month: &str) -> Result<Option<CapBreach>> {
+    let cap: Option<(String, String, String)> = conn
+        .query_row(
+            "SELECT name, monthly_cap, cap_currency FROM categories
+             WHERE id=?1 AND monthly_cap IS NOT NULL",
+            params![category_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .optional()?;
+    let Some((category, cap, currency)) = cap else {
+        return Ok(None);
+    };
+    let cap = cap
+        .trim()
+        .parse::<Decimal>()
+        .with_context(|| format!("Invalid monthly cap '{}' on {}", cap, category))?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT date, amount, currency FROM transactions
+         WHERE category_id=?1 AND date BETWEEN ?2 AND ?3 AND amount < 0",
+    )?;
+    let (start, end) = crate::utils::periods().month_range(month)?;
+    let mut rows = stmt.query(params![category_id, start.to_string(), end.to_string()])?;
+    let mut items = Vec::new();
+    while let Some(r) = rows.next()? {
+        let date: String = r.get(0)?;
+        let amount: String = r.get(1)?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+        items.push((date, -amount, r.get::<_, String>(2)?));
+    }
+    let refs: Vec<_> = items.iter().map(|(d, a, c)| (*d, *a, c.as_str())).collect();
+    let spent: Decimal = fx_convert_batch(conn, &refs, &currency)?.into_iter().sum();
+    Ok((spent > cap).then(|| CapBreach {
+        category,
+        month: month.to_string(),
+        spent,
+        cap,
+        currency,
+    }))
+}
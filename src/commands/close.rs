@@ -0,0 +1,282 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! `close-month`: the end-of-month routine as one command. The steps and their order
+//! come from the `close_checklist` setting.
+
+use crate::commands::doctor::{Severity, run_checks};
+use crate::utils::{
+    fmt_money, fmt_signed, get_setting, id_for_account, month_end, parse_decimal, parse_month,
+    periods,
+};
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+/// Known checklist steps with what each does.
+pub const STEPS: &[(&str, &str)] = &[
+    (
+        "fx",
+        "backfill FX rates missing for transaction and trade dates",
+    ),
+    ("prices", "fetch current asset prices"),
+    (
+        "recurring",
+        "post interest due through the month end (`recurring run`)",
+    ),
+    ("doctor", "run doctor; errors stop the close unless --force"),
+    (
+        "reconcile",
+        "compare each --statement balance with the account's month-end balance; differences stop the close unless --force",
+    ),
+    ("lock", "close the month against further changes"),
+    ("summary", "print the month's digest"),
+];
+
+/// Checklist used while `close_checklist` is unset.
+pub const DEFAULT_CHECKLIST: &str = "fx,prices,recurring,doctor,reconcile,lock,summary";
+
+/// Steps named by `raw` (comma-separated), in the order given.
+pub fn parse_checklist(raw: &str) -> Result<Vec<&'static str>> {
+    let mut steps = Vec::new();
+    for name in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((step, _)) = STEPS.iter().find(|(s, _)| *s == name) else {
+            let known: Vec<&str> = STEPS.iter().map(|(s, _)| *s).collect();
+            bail!("Unknown close step '{}'; known: {}", name, known.join(", "));
+        };
+        if !steps.contains(step) {
+            steps.push(*step);
+        }
+    }
+    Ok(steps)
+}
+
+pub fn handle(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let month = parse_month(sub.get_one::<String>("month").unwrap().trim())?;
+    let configured = get_setting(conn, "close_checklist")?;
+    let skipped: Vec<&String> = sub
+        .get_many::<String>("skip")
+        .map(|v| v.collect())
+        .unwrap_or_default();
+    let steps: Vec<&str> = parse_checklist(configured.as_deref().unwrap_or(DEFAULT_CHECKLIST))?
+        .into_iter()
+        .filter(|s| !skipped.iter().any(|k| k.as_str() == *s))
+        .collect();
+    let statements = sub
+        .get_many::<String>("statement")
+        .map(|v| v.map(|s| parse_statement(s)).collect::<Result<Vec<_>>>())
+        .transpose()?
+        .unwrap_or_default();
+
+    if sub.get_flag("dry-run") {
+        println!("close-month {} would run:", month);
+        for (i, step) in steps.iter().enumerate() {
+            let about = STEPS.iter().find(|(s, _)| s == step).unwrap().1;
+            println!("  {}. {}: {}", i + 1, step, about);
+        }
+        return Ok(());
+    }
+
+    let end = month_end(&month)?;
+    for (i, step) in steps.iter().enumerate() {
+        println!("[{}/{}] {}", i + 1, steps.len(), step);
+        run_step(conn, step, &month, end, &statements, sub.get_flag("force"))
+            .with_context(|| format!("close-month {} stopped at '{}'", month, step))?;
+    }
+    println!("Closed out {}", month);
+    Ok(())
+}
+
+fn run_step(
+    conn: &mut Connection,
+    step: &str,
+    month: &str,
+    end: NaiveDate,
+    statements: &[(String, Decimal)],
+    force: bool,
+) -> Result<()> {
+    match step {
+        "fx" if crate::utils::offline() => println!("  skipped: MONEYCLIP_OFFLINE is set"),
+        "fx" if provider_off(conn, "fx_provider")? => {
+            println!("  skipped: fx_provider is off");
+        }
+        "fx" => run_command(conn, &["fx", "backfill"])?,
+        "prices" if crate::utils::offline() => println!("  skipped: MONEYCLIP_OFFLINE is set"),
+        "prices" if provider_off(conn, "price_provider")? => {
+            println!("  skipped: price_provider is off");
+        }
+        "prices" => {
+            let has_assets: bool =
+                conn.query_row("SELECT EXISTS(SELECT 1 FROM assets)", [], |r| r.get(0))?;
+            if has_assets {
+                run_command(conn, &["portfolio", "price", "fetch"])?;
+            } else {
+                println!("  skipped: no assets");
+            }
+        }
+        "recurring" => {
+            let configured: bool =
+                conn.query_row("SELECT EXISTS(SELECT 1 FROM account_interest)", [], |r| {
+                    r.get(0)
+                })?;
+            if configured {
+                run_command(conn, &["recurring", "run", "--through", &end.to_string()])?;
+            } else {
+                println!("  skipped: no recurring postings configured");
+            }
+        }
+        "doctor" => {
+            let findings = run_checks(conn, end, 12)?;
+            let errors = findings
+                .iter()
+                .filter(|f| f.severity == Severity::Error)
+                .count();
+            for f in &findings {
+                println!(
+                    "  {} {} {}: {}",
+                    f.severity.as_str(),
+                    f.code,
+                    f.entity,
+                    f.detail
+                );
+            }
+            println!("  {} findings, {} errors", findings.len(), errors);
+            if errors > 0 && !force {
+                bail!(
+                    "doctor found {} error(s); fix them or pass --force to close anyway",
+                    errors
+                );
+            }
+        }
+        "reconcile" if statements.is_empty() => {
+            println!("  skipped: no --statement balances given");
+        }
+        "reconcile" => {
+            let mut differences = 0;
+            for (account, statement) in statements {
+                let (name, ccy, balance) = balance_on(conn, account, end)?;
+                if balance != *statement {
+                    differences += 1;
+                }
+                println!("  {}", reconcile_line(&name, &ccy, *statement, balance));
+            }
+            if differences > 0 && !force {
+                bail!(
+                    "{} account(s) do not match their statement; fix them or pass --force to close anyway",
+                    differences
+                );
+            }
+        }
+        "lock" => {
+            if crate::commands::periods::close_period(conn, month)? {
+                println!("  {} is now locked", month);
+            } else {
+                println!("  {} was already closed", month);
+            }
+        }
+        "summary" => {
+            let (start, end) = periods().month_range(month)?;
+            print!(
+                "{}",
+                crate::commands::digest::build_digest(conn, start, end)?
+            );
+        }
+        other => bail!("Unknown close step '{}'", other),
+    }
+    Ok(())
+}
+
+/// How `account`'s balance compares with its statement.
+fn reconcile_line(account: &str, ccy: &str, statement: Decimal, balance: Decimal) -> String {
+    if balance == statement {
+        format!(
+            "{}: {} matches the statement",
+            account,
+            fmt_money(&balance, ccy)
+        )
+    } else {
+        format!(
+            "{}: statement {} but moneyclip has {} (difference {})",
+            account,
+            fmt_money(&statement, ccy),
+            fmt_money(&balance, ccy),
+            fmt_signed(statement - balance, ccy)
+        )
+    }
+}
+
+/// `ACCOUNT=BALANCE` from `--statement`.
+fn parse_statement(raw: &str) -> Result<(String, Decimal)> {
+    let Some((account, balance)) = raw.rsplit_once('=') else {
+        bail!("Invalid --statement '{}'; expected ACCOUNT=BALANCE", raw);
+    };
+    Ok((account.trim().to_string(), parse_decimal(balance.trim())?))
+}
+
+/// Name, currency and balance on `date` of the account called `account`.
+fn balance_on(
+    conn: &Connection,
+    account: &str,
+    date: NaiveDate,
+) -> Result<(String, String, Decimal)> {
+    let id = id_for_account(conn, account)?;
+    let (name, ccy): (String, String) = conn.query_row(
+        "SELECT name, currency FROM accounts WHERE id=?1",
+        [id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+    let mut stmt =
+        conn.prepare_cached("SELECT amount FROM transactions WHERE account_id=?1 AND date <= ?2")?;
+    let mut rows = stmt.query(rusqlite::params![id, date.to_string()])?;
+    let mut balance = Decimal::ZERO;
+    while let Some(r) = rows.next()? {
+        let amount: String = r.get(0)?;
+        balance += parse_decimal(amount.trim())?;
+    }
+    Ok((name, ccy, balance))
+}
+
+fn provider_off(conn: &Connection, key: &str) -> Result<bool> {
+    Ok(get_setting(conn, key)?.as_deref() == Some("off"))
+}
+
+/// Run an existing subcommand so each step behaves exactly like its own command.
+fn run_command(conn: &mut Connection, args: &[&str]) -> Result<()> {
+    let argv = std::iter::once("moneyclip").chain(args.iter().copied());
+    let matches = crate::cli::build_cli().try_get_matches_from(argv)?;
+    super::dispatch(conn, &matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Precision, Settings, with_settings};
+
+    #[test]
+    fn reconcile_lines_name_the_currency_once() {
+        let line = |symbols: bool| {
+            let settings = Settings {
+                precision: Precision {
+                    symbols,
+                    ..Precision::DEFAULT
+                },
+                ..Settings::DEFAULT
+            };
+            with_settings(settings, || {
+                reconcile_line("Cash", "EUR", Decimal::new(-700, 2), Decimal::new(-1000, 2))
+            })
+        };
+        assert_eq!(
+            line(false),
+            "Cash: statement EUR -7.00 but moneyclip has EUR -10.00 (difference +3.00)"
+        );
+        assert_eq!(
+            line(true),
+            "Cash: statement -€7.00 but moneyclip has -€10.00 (difference +€3.00)"
+        );
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::utils::{get_setting, id_for_account, id_for_category, pretty_table, set_setting};
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OptionalExtension, params};
+
+/// Settings `config set` accepts, with a short description for `config list`.
+const KEYS: &[(&str, &str)] = &[
+    (
+        "default_account",
+        "Account used by `tx add` when --account is omitted",
+    ),
+    (
+        "default_category",
+        "Category used by `tx add` when nothing else applies",
+    ),
+    (
+        "match_prefixes",
+        "true: accept unique name prefixes for accounts, categories, assets",
+    ),
+    ("smtp_host", "SMTP server for `report digest --email`"),
+    ("smtp_port", "SMTP port (STARTTLS) [default: 587]"),
+    (
+        "smtp_user",
+        "SMTP login; the password comes from MONEYCLIP_SMTP_PASSWORD",
+    ),
+    ("smtp_from", "Sender address for digests"),
+    ("digest_to", "Comma-separated digest recipients"),
+    (
+        "sheets_credentials",
+        "Service-account JSON key used by `export sheets`",
+    ),
+    (
+        "fx_provider",
+        "frankfurter or off: source for `fx fetch`/`fx backfill`",
+    ),
+    (
+        "price_provider",
+        "yahoo or off: source for live portfolio prices",
+    ),
+    (
+        "timezone",
+        "IANA zone for \"today\", month boundaries and timestamps [default: system]",
+    ),
+    (
+        "close_checklist",
+        "Steps of `close-month` in order [default: fx,prices,recurring,doctor,reconcile,lock,summary]",
+    ),
+    (
+        "money_dp",
+        "Most decimals shown for money, 0-2 [default: 2]",
+    ),
+    (
+        "quantity_dp",
+        "Decimals shown for share and unit counts, 4-8 [default: 4]",
+    ),
+    (
+        "percent_dp",
+        "Decimals shown for percentages, 0-4 [default: 1]",
+    ),
+    (
+        "rounding",
+        "Rounding for displayed figures: half-up|half-even [default: half-up]",
+    ),
+    (
+        "currency_symbols",
+        "true to show amounts as €12.50 instead of bare numbers [default: false]",
+    ),
+    (
+        "week_start",
+        "First day of report weeks: monday|sunday [default: monday]",
+    ),
+    (
+        "language",
+        "Language of table headers and common messages: en|de|es|hi [default: en]",
+    ),
+    (
+        "month_start_day",
+        "Day 1-28 budget and report months start on, e.g. 25 for payday cycles [default: 1]",
+    ),
+];
+
+/// Environment variables that configure a run without flags, e.g. in cron or a
+/// container; secrets are not echoed by `config env`.
+const ENV_VARS: &[(&str, &str, bool)] = &[
+    (
+        "MONEYCLIP_NO_INPUT",
+        "1: never prompt, like --no-input",
+        false,
+    ),
+    (
+        "MONEYCLIP_BASE_CURRENCY",
+        "Base currency for `init` and for databases without one",
+        false,
+    ),
+    (
+        "MONEYCLIP_OFFLINE",
+        "1: never go online; fetches fail and close-month skips them",
+        false,
+    ),
+    (
+        "MONEYCLIP_JSON",
+        "1: JSON output from every command with --json",
+        false,
+    ),
+    (
+        "MONEYCLIP_LOG",
+        "Log level or filter directives, like --log-level",
+        false,
+    ),
+    ("MONEYCLIP_SMTP_PASSWORD", "Password for smtp_user", true),
+];
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("set", sub)) => {
+            let key = known_key(sub.get_one::<String>("key").unwrap())?;
+            let value = sub.get_one::<String>("value").unwrap().trim();
+            match key {
+                "default_account" => {
+                    id_for_account(conn, value)?;
+                }
+                "default_category" => {
+                    id_for_category(conn, value)?;
+                }
+                "match_prefixes" | "currency_symbols" if value != "true" && value != "false" => {
+                    bail!("{} must be true or false", key);
+                }
+                "smtp_port" if value.parse::<u16>().is_err() => {
+                    bail!("smtp_port must be a port number");
+                }
+                "fx_provider" if value != "frankfurter" && value != "off" => {
+                    bail!("fx_provider must be frankfurter or off");
+                }
+                "price_provider" if value != "yahoo" && value != "off" => {
+                    bail!("price_provider must be yahoo or off");
+                }
+                "timezone" => {
+                    crate::utils::parse_timezone(value)?;
+                }
+                "close_checklist" => {
+                    crate::commands::close::parse_checklist(value)?;
+                }
+                "money_dp" | "quantity_dp" | "percent_dp" => {
+                    let n = value
+                        .trim()
+                        .parse::<u32>()
+                        .with_context(|| format!("{} must be a whole number", key))?;
+                    crate::utils::check_precision(key, n)?;
+                }
+                "rounding" => {
+                    crate::utils::Rounding::parse(value)?;
+                }
+                "language" => {
+                    crate::i18n::Language::parse(value)?;
+                }
+                "week_start" => {
+                    crate::utils::parse_week_start(value)?;
+                }
+                "month_start_day" => {
+                    let day = value
+                        .parse::<u32>()
+                        .context("month_start_day must be a whole number")?;
+                    crate::utils::check_month_start_day(day)?;
+                }
+                _ => {}
+            }
+            set_setting(conn, key, value)?;
+            println!("{} = {}", key, value);
+        }
+        Some(("get", sub)) => {
+            let key = known_key(sub.get_one::<String>("key").unwrap())?;
+            match get_setting(conn, key)? {
+                Some(value) => println!("{}", value),
+                None => println!("{} is not set", key),
+            }
+        }
+        Some(("edit", _)) => edit()?,
+        Some(("env", _)) => {
+            let rows = ENV_VARS
+                .iter()
+                .map(|(name, about, secret)| {
+                    let value = match std::env::var(name) {
+                        Ok(_) if *secret => "(set)".to_string(),
+                        Ok(v) => v,
+                        Err(_) => String::new(),
+                    };
+                    vec![name.to_string(), value, about.to_string()]
+                })
+                .collect();
+            println!("{}", pretty_table(&["Variable", "Value", "Meaning"], rows));
+        }
+        Some(("unset", sub)) => {
+            let key = known_key(sub.get_one::<String>("key").unwrap())?;
+            conn.execute("DELETE FROM settings WHERE key=?1", params![key])?;
+            println!("Cleared {}", key);
+        }
+        Some(("list", _)) => {
+            let mut stmt = conn.prepare("SELECT value FROM settings WHERE key=?1")?;
+            let mut rows = Vec::new();
+            for (key, about) in KEYS {
+                let stored: Option<String> =
+                    stmt.query_row(params![key], |r| r.get(0)).optional()?;
+                let (value, from) = match (stored, crate::config_file::setting(key)) {
+                    (Some(v), _) => (v, "database"),
+                    (None, Some(v)) => (v, "config.toml"),
+                    (None, None) => (String::new(), ""),
+                };
+                rows.push(vec![
+                    key.to_string(),
+                    value,
+                    from.to_string(),
+                    about.to_string(),
+                ]);
+            }
+            println!(
+                "{}",
+                pretty_table(&["Key", "Value", "From", "Meaning"], rows)
+            );
+            println!("Config file: {}", crate::config_file::path()?.display());
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub(crate) fn known_key(raw: &str) -> Result<&'static str> {
+    let key = raw.trim();
+    match KEYS.iter().find(|(k, _)| *k == key) {
+        Some((k, _)) => Ok(k),
+        None => {
+            let known: Vec<&str> = KEYS.iter().map(|(k, _)| *k).collect();
+            bail!("Unknown setting '{}'; known: {}", key, known.join(", "))
+        }
+    }
+}
+
+const TEMPLATE: &str = "\
+# moneyclip settings, see `moneyclip config list`. Values set with `config set` win.
+# language = \"en\"
+# timezone = \"Europe/Berlin\"
+# fx_provider = \"frankfurter\"
+
+# Flags added to a command (or a group such as \"report\") unless given on the command line
+[defaults]
+# \"report cashflow\" = \"--months 6\"
+
+# Shell command run after a command succeeds; MONEYCLIP_COMMAND holds the command
+[hooks]
+# \"import transactions\" = \"moneyclip rules lint\"
+";
+
+/// Open `config.toml` in $VISUAL or $EDITOR, creating it from a template first, and
+/// check that it still parses.
+fn edit() -> Result<()> {
+    let path = crate::config_file::path()?;
+    if !path.exists() {
+        std::fs::write(&path, TEMPLATE).with_context(|| format!("Create {}", path.display()))?;
+    }
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut words = shell_words::split(&editor).context("Invalid $EDITOR")?;
+    if words.is_empty() {
+        bail!("$EDITOR is empty");
+    }
+    let program = words.remove(0);
+    let status = std::process::Command::new(&program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Start editor '{}'", program))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with {}", program, status);
+    }
+    let text = std::fs::read_to_string(&path)?;
+    crate::config_file::ConfigFile::parse(&text)
+        .with_context(|| format!("{} has errors; run `config edit` again", path.display()))?;
+    println!("Saved {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Consumer price index, one value per month, used to deflate reports (`--real`).
+
+use crate::logging::SendTraced;
+use crate::models::CpiRow;
+use crate::utils::{maybe_print_json, parse_decimal, parse_month, pretty_table};
+use anyhow::{Context, Result, bail, ensure};
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("set", sub)) => {
+            let month = parse_month(sub.get_one::<String>("month").unwrap())?;
+            let value = parse_decimal(sub.get_one::<String>("value").unwrap().trim())?;
+            store(conn, &[(month.clone(), value)])?;
+            println!("CPI for {} set to {}", month, value);
+        }
+        Some(("import", sub)) => {
+            let path = sub.get_one::<String>("path").unwrap().trim();
+            let raw = std::fs::read_to_string(path).with_context(|| format!("Read {}", path))?;
+            let values = parse_cpi_csv(&raw)?;
+            let n = store(conn, &values)?;
+            println!("Imported {} CPI months from {}", n, path);
+        }
+        Some(("fetch", sub)) => {
+            let series = sub.get_one::<String>("series").unwrap().trim();
+            let mut url = reqwest::Url::parse("https://fred.stlouisfed.org/graph/fredgraph.csv")?;
+            url.query_pairs_mut().append_pair("id", series);
+            let raw = crate::utils::http_client()?
+                .get(url)
+                .send_traced()
+                .and_then(|r| r.error_for_status())
+                .with_context(|| format!("Fetching CPI series {} from FRED", series))?
+                .text()?;
+            let n = store(conn, &parse_cpi_csv(&raw)?)?;
+            println!(
+                "CPI series {} fetched from FRED; {} months upserted.",
+                series, n
+            );
+        }
+        Some(("list", sub)) => {
+            let data: Vec<CpiRow> = load(conn)?
+                .into_iter()
+                .map(|(month, value)| CpiRow {
+                    month,
+                    value: value.to_string(),
+                })
+                .collect();
+            if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                let cells = data.into_iter().map(CpiRow::cells).collect();
+                println!("{}", pretty_table(&["Month", "CPI"], cells));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `month,value` rows with a header. Months may be written as YYYY-MM or as a full
+/// date (FRED uses the first of the month); rows whose value is `.` (FRED's
+/// "no observation") are skipped.
+pub fn parse_cpi_csv(raw: &str) -> Result<Vec<(String, Decimal)>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .from_reader(raw.as_bytes());
+    let mut out = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let rec = rec?;
+        let (Some(date), Some(value)) = (rec.get(0), rec.get(1)) else {
+            bail!("Line {}: expected month,value", i + 2);
+        };
+        if value == "." || value.is_empty() {
+            continue;
+        }
+        let month = parse_month(date.get(..7).unwrap_or(date))
+            .with_context(|| format!("Line {}", i + 2))?;
+        let value = parse_decimal(value).with_context(|| format!("Line {}", i + 2))?;
+        ensure!(
+            value > Decimal::ZERO,
+            "Line {}: CPI must be positive",
+            i + 2
+        );
+        out.push((month, value));
+    }
+    Ok(out)
+}
+
+fn store(conn: &mut Connection, values: &[(String, Decimal)]) -> Result<usize> {
+    let tx = conn.savepoint()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO cpi(month, value) VALUES (?1, ?2)
+             ON CONFLICT(month) DO UPDATE SET value=excluded.value",
+        )?;
+        for (month, value) in values {
+            stmt.execute(params![month, value.to_string()])?;
+        }
+    }
+    tx.commit()?;
+    Ok(values.len())
+}
+
+fn load(conn: &Connection) -> Result<BTreeMap<String, Decimal>> {
+    let mut stmt = conn.prepare("SELECT month, value FROM cpi ORDER BY month")?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+    let mut out = BTreeMap::new();
+    for row in rows {
+        let (month, value) = row?;
+        let value = value
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid CPI '{}' for {}", value, month))?;
+        out.insert(month, value);
+    }
+    Ok(out)
+}
+
+/// Restates amounts in the prices of a base period: `amount * CPI(base) / CPI(month)`.
+/// A month without its own value uses the latest earlier one.
+#[derive(Debug)]
+pub struct Deflator {
+    index: BTreeMap<String, Decimal>,
+    base: Decimal,
+}
+
+impl Deflator {
+    /// `base` is a year (the average of its months), a month, or `None` for the latest
+    /// month on file.
+    pub fn load(conn: &Connection, base: Option<&str>) -> Result<Self> {
+        let index = load(conn)?;
+        let Some((latest, latest_value)) = index.last_key_value() else {
+            bail!("No CPI data; add it with `cpi import`, `cpi fetch` or `cpi set`");
+        };
+        let base = match base.map(str::trim) {
+            None => *latest_value,
+            Some(year) if year.len() == 4 => {
+                let values: Vec<Decimal> = index
+                    .range(format!("{}-01", year)..=format!("{}-12", year))
+                    .map(|(_, v)| *v)
+                    .collect();
+                ensure!(!values.is_empty(), "No CPI data for {}", year);
+                values.iter().sum::<Decimal>() / Decimal::from(values.len())
+            }
+            Some(month) => {
+                let month = parse_month(month)?;
+                ensure!(month <= *latest, "No CPI data for {}", month);
+                lookup(&index, &month)?
+            }
+        };
+        Ok(Self { index, base })
+    }
+
+    /// `amount` from `month` (YYYY-MM) in base-period prices.
+    pub fn real(&self, month: &str, amount: Decimal) -> Result<Decimal> {
+        Ok(amount * self.base / lookup(&self.index, month)?)
+    }
+}
+
+fn lookup(index: &BTreeMap<String, Decimal>, month: &str) -> Result<Decimal> {
+    index
+        .range(..=month.to_string())
+        .next_back()
+        .map(|(_, v)| *v)
+        .with_context(|| format!("No CPI data on or before {}", month))
+}
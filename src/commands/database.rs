@@ -0,0 +1,301 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::commands::portfolio::prune_intraday_prices;
+use crate::utils::pretty_table;
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Columns that store decimals as TEXT, and whether the column may be NULL.
+pub const DECIMAL_COLUMNS: &[(&str, &str, bool)] = &[
+    ("transactions", "amount", false),
+    ("budgets", "amount", false),
+    ("goals", "target", false),
+    ("categories", "monthly_cap", true),
+    ("trades", "quantity", false),
+    ("trades", "price", false),
+    ("trades", "fees", false),
+    ("grants", "quantity", false),
+    ("grants", "purchase_price", false),
+    ("grant_schedule", "quantity", false),
+    ("grant_vests", "quantity", false),
+    ("grant_vests", "fmv", false),
+    ("grant_vests", "income", false),
+    ("dividends", "amount", false),
+    ("prices", "price", false),
+    ("fx_rates", "rate", false),
+];
+
+/// A stored value that `Decimal::from_str_exact` rejects, or that is not stored as text.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BadDecimal {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub rowid: i64,
+    pub raw: String,
+    /// What `db repair-decimals` would store instead; `None` when it cannot tell.
+    pub repaired: Option<Decimal>,
+}
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("maintain", sub)) => maintain(conn, sub)?,
+        Some(("repair-decimals", sub)) => repair_decimals(conn, sub)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn maintain(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let before = database_size(conn)?;
+
+    let problems = integrity_problems(conn)?;
+    if !problems.is_empty() {
+        let rows = problems.into_iter().map(|p| vec![p]).collect();
+        println!("{}", pretty_table(&["integrity_check"], rows));
+        bail!("Integrity check failed; restore a backup before running maintenance");
+    }
+
+    let pruned = if sub.get_flag("keep-prices") {
+        0
+    } else {
+        prune_intraday_prices(conn)?
+    };
+    conn.execute_batch("VACUUM; ANALYZE;")?;
+    let after = database_size(conn)?;
+
+    let rows = vec![
+        vec!["integrity_check".into(), "ok".into()],
+        vec!["price rows pruned".into(), pruned.to_string()],
+        vec!["size before".into(), human_bytes(before)],
+        vec!["size after".into(), human_bytes(after)],
+    ];
+    println!("{}", pretty_table(&["Step", "Result"], rows));
+    Ok(())
+}
+
+fn repair_decimals(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let dry_run = sub.get_flag("dry-run");
+    let quarantine = sub.get_flag("quarantine");
+    let bad = find_bad_decimals(conn)?;
+    if bad.is_empty() {
+        println!("All stored decimals parse");
+        return Ok(());
+    }
+    let verb = |done: &'static str, planned: &'static str| if dry_run { planned } else { done };
+    let mut rows = Vec::with_capacity(bad.len());
+    let (mut fixed, mut moved, mut left) = (0, 0, 0);
+    conn.execute_batch("SAVEPOINT repair_decimals")?;
+    let result = (|| -> Result<()> {
+        for b in &bad {
+            let action = match (b.repaired, quarantine) {
+                (Some(value), _) => {
+                    if !dry_run {
+                        conn.execute(
+                            &format!("UPDATE {} SET {}=?1 WHERE rowid=?2", b.table, b.column),
+                            params![value.to_string(), b.rowid],
+                        )
+                        .with_context(|| {
+                            format!("Repair {}.{} row {}", b.table, b.column, b.rowid)
+                        })?;
+                    }
+                    fixed += 1;
+                    format!("{} {}", verb("repaired to", "would repair to"), value)
+                }
+                (None, true) => {
+                    if !dry_run {
+                        quarantine_row(conn, b)?;
+                    }
+                    moved += 1;
+                    verb("quarantined", "would quarantine").to_string()
+                }
+                (None, false) => {
+                    left += 1;
+                    "unrepairable; rerun with --quarantine".to_string()
+                }
+            };
+            rows.push(vec![
+                b.table.to_string(),
+                b.column.to_string(),
+                b.rowid.to_string(),
+                format!("{:?}", b.raw),
+                action,
+            ]);
+        }
+        Ok(())
+    })();
+    match result {
+        Ok(()) => conn.execute_batch("RELEASE repair_decimals")?,
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO repair_decimals; RELEASE repair_decimals")?;
+            return Err(e);
+        }
+    }
+    println!(
+        "{}",
+        pretty_table(&["Table", "Column", "Row", "Stored", "Action"], rows)
+    );
+    println!(
+        "{} repaired, {} quarantined, {} left{}",
+        fixed,
+        moved,
+        left,
+        if dry_run {
+            " (--dry-run, nothing changed)"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+/// Every value in [`DECIMAL_COLUMNS`] that would make a report fail to parse it.
+/// Tables or columns missing from an older database are skipped.
+pub fn find_bad_decimals(conn: &Connection) -> Result<Vec<BadDecimal>> {
+    let mut out = Vec::new();
+    for (table, column, _) in DECIMAL_COLUMNS {
+        let present: bool = conn.query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{table}') WHERE name=?1)"),
+            [column],
+            |r| r.get(0),
+        )?;
+        if !present {
+            continue;
+        }
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, CAST({column} AS TEXT), typeof({column}) = 'text'
+             FROM {table} WHERE {column} IS NOT NULL ORDER BY rowid"
+        ))?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            let raw: String = r.get(1)?;
+            let is_text: bool = r.get(2)?;
+            if is_text && Decimal::from_str_exact(&raw).is_ok() {
+                continue;
+            }
+            out.push(BadDecimal {
+                table,
+                column,
+                rowid: r.get(0)?,
+                repaired: repair_decimal(&raw),
+                raw,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Best reading of a malformed decimal: surrounding and grouping whitespace, `'` and
+/// `_` are dropped, `(12.50)` is negative, `1.5e3` is expanded, and commas are
+/// grouping unless they are the last separator and not followed by exactly three
+/// digits (`1.234,56` and `12,5` use a decimal comma; `1,234` is a thousand).
+/// Empty or otherwise unreadable values give `None`.
+pub fn repair_decimal(raw: &str) -> Option<Decimal> {
+    let mut s: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '\'' | '_'))
+        .collect();
+    let negative = s.starts_with('(') && s.ends_with(')');
+    if negative {
+        s = s[1..s.len() - 1].to_string();
+    }
+    if s.is_empty() {
+        return None;
+    }
+    let value = if s.contains(['e', 'E']) {
+        Decimal::from_scientific(&s).ok()?.normalize()
+    } else {
+        let last_comma = s.rfind(',');
+        let last_dot = s.rfind('.');
+        let decimal_comma = match (last_comma, last_dot) {
+            (Some(c), Some(d)) => c > d,
+            (Some(c), None) => s.matches(',').count() == 1 && s.len() - c - 1 != 3,
+            _ => false,
+        };
+        let cleaned = if decimal_comma {
+            s.replace('.', "").replace(',', ".")
+        } else if last_comma.is_none() && s.matches('.').count() > 1 {
+            s.replace('.', "")
+        } else {
+            s.replace(',', "")
+        };
+        Decimal::from_str_exact(&cleaned).ok()?
+    };
+    Some(if negative { -value.abs() } else { value })
+}
+
+/// Copy the row holding `bad` to `quarantined_rows` as JSON, then clear the value
+/// when its column allows NULL or delete the row otherwise.
+fn quarantine_row(conn: &Connection, bad: &BadDecimal) -> Result<()> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT name FROM pragma_table_info('{}')",
+        bad.table
+    ))?;
+    let columns = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let pairs: Vec<String> = columns.iter().map(|c| format!("'{c}', {c}")).collect();
+    conn.execute(
+        &format!(
+            "INSERT INTO quarantined_rows(table_name, row_id, column_name, raw, row_json)
+             SELECT ?1, rowid, ?2, ?3, json_object({}) FROM {} WHERE rowid=?4",
+            pairs.join(", "),
+            bad.table
+        ),
+        params![bad.table, bad.column, bad.raw, bad.rowid],
+    )?;
+    let nullable = DECIMAL_COLUMNS
+        .iter()
+        .any(|(t, c, n)| *t == bad.table && *c == bad.column && *n);
+    let sql = if nullable {
+        format!(
+            "UPDATE {} SET {}=NULL WHERE rowid=?1",
+            bad.table, bad.column
+        )
+    } else {
+        format!("DELETE FROM {} WHERE rowid=?1", bad.table)
+    };
+    conn.execute(&sql, [bad.rowid])
+        .with_context(|| format!("Quarantine {} row {}", bad.table, bad.rowid))?;
+    Ok(())
+}
+
+fn integrity_problems(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+    let mut out = Vec::new();
+    for row in rows {
+        let line = row?;
+        if line != "ok" {
+            out.push(line);
+        }
+    }
+    Ok(out)
+}
+
+/// Allocated size of the main database in bytes (page_count * page_size).
+pub fn database_size(conn: &Connection) -> Result<u64> {
+    let pages: i64 = conn.query_row("PRAGMA page_count", [], |r| r.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |r| r.get(0))?;
+    Ok((pages * page_size) as u64)
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
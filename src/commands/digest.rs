@@ -0,0 +1,313 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::commands::budgets::build_budget_report;
+use crate::commands::portfolio::portfolio_value_at;
+use crate::utils::{fmt_amount, fmt_signed, fx_convert_batch, get_base_currency, parse_date};
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Categories listed under "Top categories".
+const TOP_CATEGORIES: usize = 5;
+
+/// `report digest`: print the summary, write it to --out and/or mail it with --email.
+pub fn handle(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let end = match sub.get_one::<String>("as-of") {
+        Some(d) => parse_date(d.trim())?,
+        None => crate::utils::today(),
+    };
+    let period = sub
+        .get_one::<String>("period")
+        .map(|s| s.as_str())
+        .unwrap_or("week");
+    let start = period_start(period, end)?;
+    let digest = build_digest(conn, start, end)?;
+
+    let out = sub.get_one::<String>("out");
+    let email = sub.get_flag("email");
+    if let Some(path) = out {
+        std::fs::write(path.trim(), &digest)
+            .with_context(|| format!("Write digest to {}", path.trim()))?;
+        println!("Wrote digest to {}", path.trim());
+    }
+    if email {
+        let subject = format!("Moneyclip digest {} to {}", start, end);
+        send_email(conn, &subject, &digest)?;
+        println!("Sent digest to {}", digest_recipients(conn)?.join(", "));
+    }
+    if out.is_none() && !email {
+        print!("{}", digest);
+    }
+    Ok(())
+}
+
+/// First day of the `week` (7 days) or `month` ending on `end`, inclusive.
+pub fn period_start(period: &str, end: NaiveDate) -> Result<NaiveDate> {
+    match period {
+        "week" => Ok(end - Duration::days(6)),
+        "month" => Ok(end
+            .checked_sub_months(Months::new(1))
+            .with_context(|| format!("Date {} is out of range", end))?
+            + Duration::days(1)),
+        other => bail!("Unknown period '{}' (use week|month)", other),
+    }
+}
+
+/// Markdown summary of `start..=end` in the base currency: balance changes per
+/// account, top spending categories, budget overruns in the months the period
+/// touches and, when there are assets, the change in portfolio value.
+pub fn build_digest(conn: &Connection, start: NaiveDate, end: NaiveDate) -> Result<String> {
+    if start > end {
+        bail!("Digest start {} is after its end {}", start, end);
+    }
+    let base = get_base_currency(conn)?;
+    let before = start - Duration::days(1);
+    let mut md = String::new();
+    writeln!(md, "# Moneyclip digest {} to {}", start, end)?;
+    writeln!(md)?;
+    writeln!(md, "All amounts in {}.", base)?;
+
+    writeln!(md)?;
+    writeln!(md, "## Balances")?;
+    writeln!(md)?;
+    let opening = balances_at(conn, before, &base)?;
+    let closing = balances_at(conn, end, &base)?;
+    let mut accounts: Vec<&String> = opening.keys().chain(closing.keys()).collect();
+    accounts.sort();
+    accounts.dedup();
+    writeln!(md, "| Account | Start | End | Change |")?;
+    writeln!(md, "|---|---:|---:|---:|")?;
+    let (mut total_start, mut total_end) = (Decimal::ZERO, Decimal::ZERO);
+    for name in accounts {
+        let s = opening.get(name).copied().unwrap_or_default();
+        let e = closing.get(name).copied().unwrap_or_default();
+        total_start += s;
+        total_end += e;
+        if s.is_zero() && e.is_zero() {
+            continue;
+        }
+        writeln!(
+            md,
+            "| {} | {} | {} | {} |",
+            name,
+            fmt_amount(s, &base),
+            fmt_amount(e, &base),
+            fmt_signed(e - s, &base)
+        )?;
+    }
+    writeln!(
+        md,
+        "| **Total** | {} | {} | {} |",
+        fmt_amount(total_start, &base),
+        fmt_amount(total_end, &base),
+        fmt_signed(total_end - total_start, &base)
+    )?;
+
+    writeln!(md)?;
+    writeln!(md, "## Top categories")?;
+    writeln!(md)?;
+    let spend = spend_by_category(conn, start, end, &base)?;
+    if spend.is_empty() {
+        writeln!(md, "No spending in this period.")?;
+    } else {
+        writeln!(md, "| Category | Spent |")?;
+        writeln!(md, "|---|---:|")?;
+        for (name, spent) in spend.into_iter().take(TOP_CATEGORIES) {
+            writeln!(md, "| {} | {} |", name, fmt_amount(spent, &base))?;
+        }
+    }
+
+    writeln!(md)?;
+    writeln!(md, "## Budget overruns")?;
+    writeln!(md)?;
+    let mut overruns = 0;
+    let mut month = start.with_day(1).unwrap();
+    while month <= end {
+        let label = month.format("%Y-%m").to_string();
+        for row in build_budget_report(conn, &label, &base, None)? {
+            let budget = row.budget.parse::<Decimal>()?;
+            let spent = row.spent.parse::<Decimal>()?;
+            if budget > Decimal::ZERO && spent > budget {
+                writeln!(
+                    md,
+                    "- {} {}: spent {} of {} ({})",
+                    label,
+                    row.category,
+                    fmt_amount(spent, &base),
+                    fmt_amount(budget, &base),
+                    fmt_signed(spent - budget, &base)
+                )?;
+                overruns += 1;
+            }
+        }
+        month = month + Months::new(1);
+    }
+    if overruns == 0 {
+        writeln!(md, "No budget overruns.")?;
+    }
+
+    let has_assets: bool =
+        conn.query_row("SELECT EXISTS(SELECT 1 FROM assets)", [], |r| r.get(0))?;
+    if has_assets {
+        let value_start = portfolio_value_at(conn, before, &base)?;
+        let value_end = portfolio_value_at(conn, end, &base)?;
+        writeln!(md)?;
+        writeln!(md, "## Portfolio")?;
+        writeln!(md)?;
+        writeln!(
+            md,
+            "Value {} -> {} ({})",
+            fmt_amount(value_start, &base),
+            fmt_amount(value_end, &base),
+            fmt_signed(value_end - value_start, &base)
+        )?;
+    }
+    Ok(md)
+}
+
+/// Balance of every account on `date` in `base`, each currency converted at that date.
+fn balances_at(
+    conn: &Connection,
+    date: NaiveDate,
+    base: &str,
+) -> Result<BTreeMap<String, Decimal>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT a.name, t.currency, t.amount
+         FROM accounts a JOIN transactions t ON t.account_id = a.id
+         WHERE t.date <= ?1",
+    )?;
+    let mut sums: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+    let mut rows = stmt.query(params![date.to_string()])?;
+    while let Some(r) = rows.next()? {
+        let amount: String = r.get(2)?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amount))?;
+        *sums.entry((r.get(0)?, r.get(1)?)).or_default() += amount;
+    }
+    let items: Vec<_> = sums
+        .iter()
+        .map(|((_, ccy), amount)| (date, *amount, ccy.as_str()))
+        .collect();
+    let converted = fx_convert_batch(conn, &items, base)?;
+    let mut balances = BTreeMap::new();
+    for (((name, _), _), value) in sums.iter().zip(converted) {
+        *balances.entry(name.clone()).or_default() += value;
+    }
+    Ok(balances)
+}
+
+/// Spending per category in `start..=end` in `base`, largest first.
+fn spend_by_category(
+    conn: &Connection,
+    start: NaiveDate,
+    end: NaiveDate,
+    base: &str,
+) -> Result<Vec<(String, Decimal)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT COALESCE(c.name, '(uncategorized)'), t.date, t.amount, t.currency
+         FROM transactions t LEFT JOIN categories c ON c.id = t.category_id
+         WHERE t.date >= ?1 AND t.date <= ?2",
+    )?;
+    let mut rows = stmt.query(params![start.to_string(), end.to_string()])?;
+    let mut spends = Vec::new();
+    while let Some(r) = rows.next()? {
+        let amount: String = r.get(2)?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amount))?;
+        if amount >= Decimal::ZERO {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(&r.get::<_, String>(1)?, "%Y-%m-%d")?;
+        spends.push((
+            r.get::<_, String>(0)?,
+            date,
+            -amount,
+            r.get::<_, String>(3)?,
+        ));
+    }
+    let items: Vec<_> = spends
+        .iter()
+        .map(|(_, date, amount, ccy)| (*date, *amount, ccy.as_str()))
+        .collect();
+    let converted = fx_convert_batch(conn, &items, base)?;
+    let mut totals: BTreeMap<String, Decimal> = BTreeMap::new();
+    for ((name, ..), value) in spends.into_iter().zip(converted) {
+        *totals.entry(name).or_default() += value;
+    }
+    let mut sorted: Vec<_> = totals.into_iter().collect();
+    sorted.sort_by_key(|(_, spent)| std::cmp::Reverse(*spent));
+    Ok(sorted)
+}
+
+pub fn digest_recipients(conn: &Connection) -> Result<Vec<String>> {
+    let to = crate::utils::get_setting(conn, "digest_to")?
+        .context("Set the recipients with `config set digest_to you@example.com`")?;
+    Ok(to
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect())
+}
+
+/// Mail `body` to the digest recipients through the SMTP server from `config`; the
+/// password is read from `MONEYCLIP_SMTP_PASSWORD` so it never lands in the database.
+#[cfg(feature = "email")]
+pub fn send_email(conn: &Connection, subject: &str, body: &str) -> Result<()> {
+    use crate::utils::get_setting;
+    use lettre::message::header::ContentType;
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{Message, SmtpTransport, Transport};
+
+    let required = |key: &str| -> Result<String> {
+        get_setting(conn, key)?
+            .with_context(|| format!("Set {} with `config set {} ...` to send email", key, key))
+    };
+    let host = required("smtp_host")?;
+    let from = required("smtp_from")?;
+    let port = match get_setting(conn, "smtp_port")? {
+        Some(p) => p.parse::<u16>()?,
+        None => 587,
+    };
+
+    let mut message = Message::builder()
+        .from(
+            from.parse()
+                .with_context(|| format!("Invalid smtp_from '{}'", from))?,
+        )
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+    for to in digest_recipients(conn)? {
+        message = message.to(to
+            .parse()
+            .with_context(|| format!("Invalid recipient '{}'", to))?);
+    }
+    let message = message.body(body.to_string())?;
+
+    let mut transport = SmtpTransport::starttls_relay(&host)?.port(port);
+    if let Some(user) = get_setting(conn, "smtp_user")? {
+        let password = std::env::var("MONEYCLIP_SMTP_PASSWORD")
+            .context("Set MONEYCLIP_SMTP_PASSWORD to the password for smtp_user")?;
+        transport = transport.credentials(Credentials::new(user, password));
+    }
+    transport
+        .build()
+        .send(&message)
+        .with_context(|| format!("Send mail via {}:{}", host, port))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "email"))]
+pub fn send_email(_conn: &Connection, _subject: &str, _body: &str) -> Result<()> {
+    bail!("This build has no email support; rebuild with `--features email`")
+}
@@ -4,12 +4,115 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::pretty_table;
-use anyhow::Result;
-use rusqlite::{Connection, OptionalExtension};
+use crate::utils::{maybe_print_json, pretty_table, set_exit_code};
+use anyhow::{Result, bail};
+use chrono::{Months, NaiveDate};
+use rusqlite::{Connection, params};
+use serde::Serialize;
 
-pub fn handle(conn: &Connection) -> Result<()> {
-    let mut rows = Vec::new();
+/// Exit status when only warnings were reported.
+pub const EXIT_WARNINGS: i32 = 2;
+/// Exit status when at least one error was reported.
+pub const EXIT_ERRORS: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth a look, e.g. stale reference data.
+    Warn,
+    /// Reports are wrong or incomplete until it is fixed.
+    Error,
+}
+
+impl Severity {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "warn" => Ok(Severity::Warn),
+            "error" => Ok(Severity::Error),
+            other => bail!("Unknown severity '{}' (use error|warn)", other),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub code: &'static str,
+    pub severity: Severity,
+    /// Currency, category, asset or account the finding is about.
+    pub entity: String,
+    pub detail: String,
+}
+
+impl Finding {
+    fn new(
+        code: &'static str,
+        severity: Severity,
+        entity: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Self {
+        Finding {
+            code,
+            severity,
+            entity: entity.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Print findings at or above `--severity` and set the exit status: 0 when none
+/// are left, 2 when the worst is a warning and 3 when there is an error.
+pub fn handle(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let months = *sub.get_one::<u32>("inactive-months").unwrap();
+    let min = Severity::parse(sub.get_one::<String>("severity").unwrap())?;
+    let findings: Vec<Finding> = run_checks(conn, crate::utils::today(), months)?
+        .into_iter()
+        .filter(|f| f.severity >= min)
+        .collect();
+
+    set_exit_code(match findings.iter().map(|f| f.severity).max() {
+        None => 0,
+        Some(Severity::Warn) => EXIT_WARNINGS,
+        Some(Severity::Error) => EXIT_ERRORS,
+    });
+    if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &findings)? {
+        return Ok(());
+    }
+    if findings.is_empty() {
+        println!("✅ doctor: no issues found");
+    } else {
+        let rows = findings
+            .into_iter()
+            .map(|f| {
+                vec![
+                    f.severity.as_str().into(),
+                    f.code.into(),
+                    f.entity,
+                    f.detail,
+                ]
+            })
+            .collect();
+        println!(
+            "{}",
+            pretty_table(&["Severity", "Issue", "Entity", "Detail"], rows)
+        );
+    }
+    Ok(())
+}
+
+/// Every check, errors first.
+pub fn run_checks(
+    conn: &Connection,
+    today: NaiveDate,
+    inactive_months: u32,
+) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
 
     // 1) Unknown currencies
     let mut stmt = conn.prepare(
@@ -18,29 +121,167 @@ pub fn handle(conn: &Connection) -> Result<()> {
     let mut cur = stmt.query([])?;
     while let Some(r) = cur.next()? {
         let c: String = r.get(0)?;
-        rows.push(vec!["txn_currency_no_account".into(), c]);
-    }
-
-    // 2) FX coverage gaps: transactions with currency != base lacking a rate on or before date
-    let base = crate::utils::get_base_currency(conn)?;
-    let mut stmt2 =
-        conn.prepare("SELECT date, currency FROM transactions WHERE currency != ?1 ORDER BY date")?;
-    let mut cur2 = stmt2.query([&base])?;
-    while let Some(r) = cur2.next()? {
-        let d: String = r.get(0)?;
-        let ccy: String = r.get(1)?;
-        let _date = chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")?;
-        let mut st = conn.prepare("SELECT 1 FROM fx_rates WHERE base=?1 AND quote=?2 AND date<=?3 ORDER BY date DESC LIMIT 1")?;
-        let ok: Option<i32> = st.query_row((&base, &ccy, &d), |r| r.get(0)).optional()?;
-        if ok.is_none() {
-            rows.push(vec!["missing_fx".into(), format!("{} {}", d, ccy)]);
-        }
+        findings.push(Finding::new(
+            "txn_currency_no_account",
+            Severity::Warn,
+            c,
+            "transactions use a currency no account is in",
+        ));
     }
 
-    if rows.is_empty() {
-        println!("✅ doctor: no issues found");
-    } else {
-        println!("{}", pretty_table(&["Issue", "Detail"], rows));
+    // 2) Rows in another currency than their account's: balances list them separately
+    let mut stmt = conn.prepare(
+        "SELECT a.name, a.currency, t.currency, COUNT(*)
+         FROM transactions t JOIN accounts a ON a.id=t.account_id
+         WHERE upper(t.currency) <> upper(a.currency)
+         GROUP BY a.id, upper(t.currency) ORDER BY a.name, upper(t.currency)",
+    )?;
+    let mut cur = stmt.query([])?;
+    while let Some(r) = cur.next()? {
+        let (account, own, ccy, n): (String, String, String, i64) =
+            (r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?);
+        findings.push(Finding::new(
+            "account_currency_mismatch",
+            Severity::Warn,
+            account,
+            format!(
+                "{} transaction(s) in {}, not the account's {}; `report balances` shows them as a separate {} balance",
+                n,
+                ccy.to_uppercase(),
+                own,
+                ccy.to_uppercase()
+            ),
+        ));
     }
-    Ok(())
+
+    // 3) FX coverage gaps: non-base transactions and trades without any rate on or before their date
+    let base = crate::utils::get_base_currency(conn)?.trim().to_uppercase();
+    for gap in crate::commands::fx::rate_gaps(conn, &base, None)? {
+        let detail = format!(
+            "no {}/{} rate on or before {}",
+            base, gap.currency, gap.date
+        );
+        findings.push(Finding::new(
+            "missing_fx",
+            Severity::Error,
+            gap.currency,
+            detail,
+        ));
+    }
+
+    // 4) Stored numbers that reports cannot parse
+    for bad in crate::commands::database::find_bad_decimals(conn)? {
+        let fix = match bad.repaired {
+            Some(v) => format!("`db repair-decimals` would store {}", v),
+            None => "`db repair-decimals --quarantine` moves the row aside".to_string(),
+        };
+        findings.push(Finding::new(
+            "bad_decimal",
+            Severity::Error,
+            format!("{}.{} row {}", bad.table, bad.column, bad.rowid),
+            format!("{:?} is not a plain decimal; {}", bad.raw, fix),
+        ));
+    }
+
+    // 5) Stale reference data worth pruning
+    findings.extend(stale_checks(conn, today, inactive_months)?);
+
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    Ok(findings)
+}
+
+/// Reference data nothing uses any more: budgeted categories that never had a
+/// transaction, categories with no transactions or budgets, assets with no trades,
+/// grants or prices, and accounts with no transactions or trades in the
+/// `inactive_months` before `today`.
+pub fn stale_checks(
+    conn: &Connection,
+    today: NaiveDate,
+    inactive_months: u32,
+) -> Result<Vec<Finding>> {
+    let mut rows = Vec::new();
+
+    let mut stmt = conn.prepare(
+        "SELECT c.name, COUNT(*), MIN(b.month), MAX(b.month)
+         FROM budgets b JOIN categories c ON c.id = b.category_id
+         WHERE NOT EXISTS(SELECT 1 FROM transactions t WHERE t.category_id = c.id)
+         GROUP BY c.id ORDER BY c.name",
+    )?;
+    let mut cur = stmt.query([])?;
+    while let Some(r) = cur.next()? {
+        let (name, count, first, last): (String, i64, String, String) =
+            (r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?);
+        let span = if first == last {
+            first
+        } else {
+            format!("{}..{}", first, last)
+        };
+        rows.push(Finding::new(
+            "budget_never_spent",
+            Severity::Warn,
+            name,
+            format!("{} budgeted months ({}), no transactions", count, span),
+        ));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT name FROM categories c
+         WHERE NOT EXISTS(SELECT 1 FROM transactions t WHERE t.category_id = c.id)
+           AND NOT EXISTS(SELECT 1 FROM budgets b WHERE b.category_id = c.id)
+         ORDER BY name",
+    )?;
+    let mut cur = stmt.query([])?;
+    while let Some(r) = cur.next()? {
+        rows.push(Finding::new(
+            "unused_category",
+            Severity::Warn,
+            r.get::<_, String>(0)?,
+            "no transactions or budgets",
+        ));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT ticker FROM assets a
+         WHERE NOT EXISTS(SELECT 1 FROM trades t WHERE t.asset_id = a.id)
+           AND NOT EXISTS(SELECT 1 FROM prices p WHERE p.asset_id = a.id)
+           AND NOT EXISTS(SELECT 1 FROM grants g WHERE g.asset_id = a.id)
+         ORDER BY ticker",
+    )?;
+    let mut cur = stmt.query([])?;
+    while let Some(r) = cur.next()? {
+        rows.push(Finding::new(
+            "unused_asset",
+            Severity::Warn,
+            r.get::<_, String>(0)?,
+            "no trades, grants or prices",
+        ));
+    }
+
+    let cutoff = today
+        .checked_sub_months(Months::new(inactive_months))
+        .unwrap_or(NaiveDate::MIN);
+    let mut stmt = conn.prepare(
+        "SELECT a.name, MAX(d.date)
+         FROM accounts a
+         LEFT JOIN (SELECT account_id, date FROM transactions
+                    UNION ALL SELECT account_id, date FROM trades) d ON d.account_id = a.id
+         GROUP BY a.id
+         HAVING MAX(d.date) IS NULL OR MAX(d.date) < ?1
+         ORDER BY a.name",
+    )?;
+    let mut cur = stmt.query(params![cutoff.to_string()])?;
+    while let Some(r) = cur.next()? {
+        let name: String = r.get(0)?;
+        let detail = match r.get::<_, Option<String>>(1)? {
+            Some(last) => format!("last activity {}", last),
+            None => "no activity".to_string(),
+        };
+        rows.push(Finding::new(
+            "inactive_account",
+            Severity::Warn,
+            name,
+            detail,
+        ));
+    }
+    Ok(rows)
 }
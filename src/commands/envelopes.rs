@@ -4,11 +4,16 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
+use crate::commands::budgets::budget_in;
+use crate::logging::SendTraced;
+use crate::models::{EnvelopeMonthRow, EnvelopeStatusRow};
 use crate::utils::{
-    fx_convert, get_base_currency, id_for_category, parse_decimal, parse_month, pretty_table,
+    fmt_amount, fx_convert, get_base_currency, id_for_category, parse_amount, parse_date,
+    parse_month, pretty_table,
 };
 use anyhow::{Context, Result};
-use rusqlite::{Connection, OptionalExtension, params};
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{Connection, params};
 use rust_decimal::Decimal;
 
 pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
@@ -16,6 +21,9 @@ pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
         Some(("fund", sub)) => fund(conn, sub)?,
         Some(("move", sub)) => move_between(conn, sub)?,
         Some(("status", sub)) => status(conn, sub)?,
+        Some(("history", sub)) => history(conn, sub)?,
+        Some(("alerts", sub)) => alerts(conn, sub)?,
+        Some(("sweep", sub)) => sweep(conn, sub)?,
         _ => {}
     }
     Ok(())
@@ -28,29 +36,10 @@ fn fund(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
         .unwrap()
         .trim()
         .to_string();
-    let amount = parse_decimal(sub.get_one::<String>("amount").unwrap().trim())?;
+    let amount = parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
     let cat_id = id_for_category(conn, &cat)?;
 
-    let existing: Option<String> = conn
-        .query_row(
-            "SELECT amount FROM budgets WHERE month=?1 AND category_id=?2",
-            params![&month, cat_id],
-            |r| r.get(0),
-        )
-        .optional()?;
-    let new_amt = if let Some(s) = existing {
-        let cur = s
-            .parse::<Decimal>()
-            .with_context(|| format!("Invalid budget amount '{}' for {}", s, month))?;
-        (cur + amount).to_string()
-    } else {
-        amount.to_string()
-    };
-    conn.execute(
-        "INSERT INTO budgets(month, category_id, amount) VALUES (?1,?2,?3)
-         ON CONFLICT(month, category_id) DO UPDATE SET amount=excluded.amount",
-        params![&month, cat_id, &new_amt],
-    )?;
+    add_to_budget(conn, &month, cat_id, amount)?;
     println!("Funded {} {} for {}", amount, get_base_currency(conn)?, cat);
     Ok(())
 }
@@ -59,41 +48,12 @@ fn move_between(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let month = parse_month(sub.get_one::<String>("month").unwrap().trim())?;
     let from = sub.get_one::<String>("from").unwrap().trim().to_string();
     let to = sub.get_one::<String>("to").unwrap().trim().to_string();
-    let amount = parse_decimal(sub.get_one::<String>("amount").unwrap().trim())?;
+    let amount = parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
     let from_id = id_for_category(conn, &from)?;
     let to_id = id_for_category(conn, &to)?;
 
-    let get_amt = |id: i64| -> Result<Decimal> {
-        let v: Option<String> = conn
-            .query_row(
-                "SELECT amount FROM budgets WHERE month=?1 AND category_id=?2",
-                params![&month, id],
-                |r| r.get(0),
-            )
-            .optional()?;
-        if let Some(s) = v {
-            Ok(s.parse::<Decimal>()
-                .with_context(|| format!("Invalid budget amount '{}' for {}", s, month))?)
-        } else {
-            Ok(Decimal::ZERO)
-        }
-    };
-    let from_amt = get_amt(from_id)?;
-    let to_amt = get_amt(to_id)?;
-
-    let new_from = (from_amt - amount).to_string();
-    let new_to = (to_amt + amount).to_string();
-
-    conn.execute(
-        "INSERT INTO budgets(month, category_id, amount) VALUES (?1,?2,?3)
-         ON CONFLICT(month, category_id) DO UPDATE SET amount=excluded.amount",
-        params![&month, from_id, &new_from],
-    )?;
-    conn.execute(
-        "INSERT INTO budgets(month, category_id, amount) VALUES (?1,?2,?3)
-         ON CONFLICT(month, category_id) DO UPDATE SET amount=excluded.amount",
-        params![&month, to_id, &new_to],
-    )?;
+    add_to_budget(conn, &month, from_id, -amount)?;
+    add_to_budget(conn, &month, to_id, amount)?;
     println!(
         "Moved {} {} from {} to {}",
         amount,
@@ -104,10 +64,101 @@ fn move_between(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn sweep(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let month = parse_month(sub.get_one::<String>("month").unwrap().trim())?;
+    let goal = sub.get_one::<String>("to-goal").unwrap().trim();
+    let dry_run = sub.get_flag("dry-run");
+    let moved = sweep_to_goal(conn, &month, goal, dry_run)?;
+    let base = get_base_currency(conn)?;
+    let total: Decimal = moved.iter().map(|(_, amt)| *amt).sum();
+    let rows = moved
+        .into_iter()
+        .map(|(cat, amt)| vec![cat, fmt_amount(amt, &base)])
+        .collect();
+    crate::utils::emit(pretty_table(
+        &["From envelope", &format!("Leftover ({})", base)],
+        rows,
+    ));
+    let verb = if dry_run { "Would sweep" } else { "Swept" };
+    println!(
+        "{} {} {} into goal '{}'",
+        verb,
+        fmt_amount(total, &base),
+        base,
+        goal
+    );
+    Ok(())
+}
+
+/// Move every envelope's positive balance left at the end of `month` into the envelope
+/// backing `goal`, by lowering that month's budget and raising the goal's. Envelopes
+/// backing any goal are left alone. Returns the moved amounts by category.
+pub fn sweep_to_goal(
+    conn: &Connection,
+    month: &str,
+    goal: &str,
+    dry_run: bool,
+) -> Result<Vec<(String, Decimal)>> {
+    let goal_cat = crate::commands::goals::goal_category(conn, goal)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name FROM categories
+         WHERE id NOT IN (SELECT category_id FROM goals)
+         ORDER BY name",
+    )?;
+    let cats = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut moved = Vec::new();
+    for (cat_id, name) in cats {
+        let (carry, budget_m, spent_m) = envelope_compute(conn, cat_id, month)?;
+        let left = carry + budget_m - spent_m;
+        if left > Decimal::ZERO {
+            moved.push((cat_id, name, left));
+        }
+    }
+    if !dry_run && !moved.is_empty() {
+        conn.execute_batch("SAVEPOINT envelope_sweep")?;
+        let applied = moved.iter().try_for_each(|(cat_id, _, left)| {
+            add_to_budget(conn, month, *cat_id, -*left)?;
+            add_to_budget(conn, month, goal_cat, *left)
+        });
+        match applied {
+            Ok(()) => conn.execute_batch("RELEASE envelope_sweep")?,
+            Err(err) => {
+                conn.execute_batch("ROLLBACK TO envelope_sweep; RELEASE envelope_sweep")?;
+                return Err(err);
+            }
+        }
+    }
+    Ok(moved
+        .into_iter()
+        .map(|(_, name, left)| (name, left))
+        .collect())
+}
+
+/// Envelopes work in the base currency, so the updated budget is stored in it.
+fn add_to_budget(conn: &Connection, month: &str, category_id: i64, delta: Decimal) -> Result<()> {
+    let base = get_base_currency(conn)?;
+    let current = budget_in(conn, category_id, month, &base)?;
+    conn.execute(
+        "INSERT INTO budgets(month, category_id, amount, currency) VALUES (?1,?2,?3,?4)
+         ON CONFLICT(month, category_id) DO UPDATE SET amount=excluded.amount, currency=excluded.currency",
+        params![month, category_id, (current + delta).to_string(), base],
+    )?;
+    Ok(())
+}
+
 fn status(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let json_flag = sub.get_flag("json");
     let jsonl_flag = sub.get_flag("jsonl");
-    let month = sub.get_one::<String>("month").unwrap().trim().to_string();
+    let as_of = sub
+        .get_one::<String>("as-of")
+        .map(|d| parse_date(d.trim()))
+        .transpose()?;
+    let month = match as_of {
+        Some(d) => crate::utils::periods().month_of(d),
+        None => parse_month(sub.get_one::<String>("month").unwrap().trim())?,
+    };
     let out_ccy = sub
         .get_one::<String>("currency")
         .map(|s| s.trim().to_uppercase());
@@ -117,64 +168,267 @@ fn status(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let mut rows = Vec::new();
     for c in cats {
         let (cat_id, cat_name) = c?;
-        let (carry, budget_m, spent_m) = envelope_compute(conn, cat_id, &month)?;
+        let (carry, budget_m, spent_m) = envelope_compute_as_of(conn, cat_id, &month, as_of)?;
         let available = carry + budget_m - spent_m;
-        let dt = crate::utils::month_end(&month)?;
+        let dt = crate::utils::valuation_month_end(conn, &month)?;
         let base = crate::utils::get_base_currency(conn)?;
         let disp_c = |v: rust_decimal::Decimal| -> Result<String> {
             if let Some(ref c) = out_ccy {
-                Ok(format!(
-                    "{:.2}",
-                    crate::utils::fx_convert(conn, dt, v, &base, c)?
+                Ok(fmt_amount(
+                    crate::utils::fx_convert(conn, dt, v, &base, c)?,
+                    c,
                 ))
             } else {
-                Ok(format!("{:.2}", v))
+                Ok(fmt_amount(v, &base))
             }
         };
-        rows.push(vec![
-            cat_name,
-            disp_c(carry)?,
-            disp_c(budget_m)?,
-            disp_c(spent_m)?,
-            disp_c(available)?,
-        ]);
+        let mut row = EnvelopeStatusRow {
+            category: cat_name,
+            carryover: disp_c(carry)?,
+            budget: disp_c(budget_m)?,
+            spent: disp_c(spent_m)?,
+            available: disp_c(available)?,
+            daily_burn: None,
+            projected: None,
+        };
+        if let Some(day) = as_of {
+            let (burn, projected) = burn_projection(carry + budget_m, spent_m, day, dt);
+            row.daily_burn = Some(disp_c(burn)?);
+            row.projected = Some(disp_c(projected)?);
+        }
+        rows.push(row);
     }
     if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &rows)? {
-        println!(
-            "{}",
-            pretty_table(
-                &["Category", "Carryover", "Budget", "Spent", "Available"],
-                rows
+        let mut headers = vec!["Category", "Carryover", "Budget", "Spent", "Available"];
+        if as_of.is_some() {
+            headers.extend(["Daily burn", "Projected"]);
+        }
+        let cells = rows.into_iter().map(EnvelopeStatusRow::cells).collect();
+        crate::utils::emit(pretty_table(&headers, cells));
+    }
+    Ok(())
+}
+
+/// Exit status of `envelope alerts` when an envelope is below its threshold.
+pub const EXIT_ALERTS: i32 = 1;
+
+fn alerts(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let month = parse_month(
+        sub.get_one::<String>("month")
+            .map(|m| m.as_str())
+            .unwrap_or("this"),
+    )?;
+    let threshold = Threshold::parse(sub.get_one::<String>("threshold").unwrap())?;
+    let found = envelope_alerts(conn, &month, threshold)?;
+    if !found.is_empty() {
+        crate::utils::set_exit_code(EXIT_ALERTS);
+    }
+    let base = get_base_currency(conn)?;
+    let summary = found
+        .iter()
+        .map(|a| {
+            format!(
+                "{}: {} available of {} budgeted (threshold {})",
+                a.category,
+                fmt_amount(a.available, &base),
+                fmt_amount(a.budget, &base),
+                fmt_amount(a.threshold, &base)
             )
-        );
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !found.is_empty() && sub.get_flag("email") {
+        let subject = format!("Moneyclip: {} envelopes low in {}", found.len(), month);
+        crate::commands::digest::send_email(conn, &subject, &summary)?;
+    }
+    if let (false, Some(url)) = (found.is_empty(), sub.get_one::<String>("webhook")) {
+        let body = serde_json::json!({
+            "text": format!("Envelopes low in {}:\n{}", month, summary),
+            "month": month,
+            "alerts": found,
+        });
+        crate::utils::http_client()?
+            .post(url.trim())
+            .json(&body)
+            .send_traced()
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("POST alerts to {}", url.trim()))?;
+    }
+    if crate::utils::maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &found)? {
+        return Ok(());
+    }
+    if found.is_empty() {
+        println!("No envelopes below the threshold in {}", month);
+        return Ok(());
+    }
+    let rows = found
+        .into_iter()
+        .map(|a| {
+            vec![
+                a.category,
+                fmt_amount(a.budget, &base),
+                fmt_amount(a.available, &base),
+                fmt_amount(a.threshold, &base),
+            ]
+        })
+        .collect();
+    println!(
+        "{}",
+        pretty_table(&["Category", "Budget", "Available", "Threshold"], rows)
+    );
+    Ok(())
+}
+
+/// `--threshold` of `envelope alerts`: a BASE amount, or `N%` of the month's budget.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    Amount(Decimal),
+    Percent(Decimal),
+}
+
+impl Threshold {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s.strip_suffix('%') {
+            Some(pct) => Ok(Self::Percent(crate::utils::parse_decimal(pct.trim())?)),
+            None => Ok(Self::Amount(parse_amount(s)?)),
+        }
+    }
+}
+
+/// An envelope whose available amount is below its threshold.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct EnvelopeAlert {
+    pub category: String,
+    pub budget: Decimal,
+    pub available: Decimal,
+    pub threshold: Decimal,
+}
+
+/// Envelopes in use in `month` (funded this month or carrying money over) whose available
+/// amount is below `threshold`, lowest first. All amounts are in the base currency.
+pub fn envelope_alerts(
+    conn: &Connection,
+    month: &str,
+    threshold: Threshold,
+) -> Result<Vec<EnvelopeAlert>> {
+    let mut stmt = conn.prepare("SELECT id, name FROM categories ORDER BY name")?;
+    let cats = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut out = Vec::new();
+    for (cat_id, category) in cats {
+        let (carry, budget, spent) = envelope_compute(conn, cat_id, month)?;
+        if budget.is_zero() && carry.is_zero() {
+            continue;
+        }
+        let available = carry + budget - spent;
+        let limit = match threshold {
+            Threshold::Amount(a) => a,
+            Threshold::Percent(p) => budget * p / Decimal::ONE_HUNDRED,
+        };
+        if available < limit {
+            out.push(EnvelopeAlert {
+                category,
+                budget,
+                available,
+                threshold: limit,
+            });
+        }
+    }
+    out.sort_by_key(|a| a.available);
+    Ok(out)
+}
+
+/// Daily spending rate over the month's first `as_of.day()` days and what would be
+/// left of `funds` at `month_end` if it continued.
+pub fn burn_projection(
+    funds: Decimal,
+    spent: Decimal,
+    as_of: NaiveDate,
+    month_end: NaiveDate,
+) -> (Decimal, Decimal) {
+    let burn = spent / Decimal::from(as_of.day());
+    (burn, funds - burn * Decimal::from(month_end.day()))
+}
+
+fn history(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let cat = sub.get_one::<String>("category").unwrap().trim();
+    let months = *sub.get_one::<usize>("months").unwrap_or(&12);
+    let last = match sub.get_one::<String>("to") {
+        Some(m) => parse_month(m.trim())?,
+        None => parse_month("this")?,
+    };
+    let cat_id = id_for_category(conn, cat)?;
+    let data = envelope_history(conn, cat_id, &last, months)?;
+    if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
+        crate::utils::emit(pretty_table(
+            &["Month", "Carryover", "Funded", "Spent", "Available"],
+            data.into_iter().map(EnvelopeMonthRow::cells).collect(),
+        ));
     }
     Ok(())
 }
 
+/// One envelope month by month, oldest first, over the `months` months ending at `last`.
+pub fn envelope_history(
+    conn: &Connection,
+    category_id: i64,
+    last: &str,
+    months: usize,
+) -> Result<Vec<EnvelopeMonthRow>> {
+    let base = get_base_currency(conn)?;
+    let mut data = Vec::with_capacity(months);
+    for back in (0..months as i32).rev() {
+        let month = crate::utils::shift_month(last, -back)?;
+        let (carry, funded, spent) = envelope_compute(conn, category_id, &month)?;
+        data.push(EnvelopeMonthRow {
+            month,
+            carryover: fmt_amount(carry, &base),
+            funded: fmt_amount(funded, &base),
+            spent: fmt_amount(spent, &base),
+            available: fmt_amount(carry + funded - spent, &base),
+        });
+    }
+    Ok(data)
+}
+
 pub fn envelope_compute(
     conn: &Connection,
     category_id: i64,
     month: &str,
+) -> Result<(Decimal, Decimal, Decimal)> {
+    envelope_compute_as_of(conn, category_id, month, None)
+}
+
+/// [`envelope_compute`] counting only the month's spending up to and including
+/// `as_of`; `None` counts the whole month.
+pub fn envelope_compute_as_of(
+    conn: &Connection,
+    category_id: i64,
+    month: &str,
+    as_of: Option<NaiveDate>,
 ) -> Result<(Decimal, Decimal, Decimal)> {
     let base = crate::utils::get_base_currency(conn)?;
 
     let mut carryover = {
         let mut stmt =
-            conn.prepare_cached("SELECT amount FROM budgets WHERE category_id=?1 AND month<?2")?;
-        let mut rows = stmt.query(params![category_id, month])?;
+            conn.prepare_cached("SELECT month FROM budgets WHERE category_id=?1 AND month<?2")?;
+        let months = stmt
+            .query_map(params![category_id, month], |r| r.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
         let mut total = Decimal::ZERO;
-        while let Some(row) = rows.next()? {
-            let amount: String = row.get(0)?;
-            let value = amount
-                .parse::<Decimal>()
-                .with_context(|| format!("Invalid budget amount '{}' before {}", amount, month))?;
-            total += value;
+        for earlier in months {
+            total += budget_in(conn, category_id, &earlier, &base)?;
         }
         total
     };
 
-    let mut stmt_t = conn.prepare("SELECT date, amount, currency FROM transactions WHERE category_id=?1 AND amount<0 AND substr(date,1,7)<?2")?;
-    let mut cur = stmt_t.query(params![category_id, month])?;
+    let (start, end) = crate::utils::periods().month_range(month)?;
+    let mut stmt_t = conn.prepare("SELECT date, amount, currency FROM transactions WHERE category_id=?1 AND amount<0 AND date<?2")?;
+    let mut cur = stmt_t.query(params![category_id, start.to_string()])?;
     while let Some(r) = cur.next()? {
         let d: String = r.get(0)?;
         let a_s: String = r.get(1)?;
@@ -188,22 +442,15 @@ pub fn envelope_compute(
         carryover -= conv;
     }
 
-    let budget_m_s: Option<String> = conn
-        .query_row(
-            "SELECT amount FROM budgets WHERE category_id=?1 AND month=?2",
-            params![category_id, month],
-            |r| r.get(0),
-        )
-        .optional()?;
-    let budget_m = match budget_m_s {
-        Some(s) => s
-            .parse::<Decimal>()
-            .with_context(|| format!("Invalid budget amount '{}' for {}", s, month))?,
-        None => Decimal::ZERO,
-    };
+    let budget_m = budget_in(conn, category_id, month, &base)?;
 
-    let mut stmt_ms = conn.prepare("SELECT date, amount, currency FROM transactions WHERE category_id=?1 AND amount<0 AND substr(date,1,7)=?2")?;
-    let mut cur2 = stmt_ms.query(params![category_id, month])?;
+    let mut stmt_ms = conn.prepare("SELECT date, amount, currency FROM transactions WHERE category_id=?1 AND amount<0 AND date BETWEEN ?2 AND ?3 AND (?4 IS NULL OR date<=?4)")?;
+    let mut cur2 = stmt_ms.query(params![
+        category_id,
+        start.to_string(),
+        end.to_string(),
+        as_of.map(|d| d.to_string())
+    ])?;
     let mut spent_m = Decimal::ZERO;
     while let Some(r) = cur2.next()? {
         let d: String = r.get(0)?;
@@ -4,17 +4,49 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use anyhow::{Result, bail};
-use rusqlite::Connection;
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{Connection, Transaction, params};
+use rust_decimal::Decimal;
 use serde::Serialize;
 use serde::ser::{SerializeSeq, Serializer};
 use serde_json::ser::PrettyFormatter;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
+use std::hash::{BuildHasher, Hash, RandomState};
 use std::io::{BufWriter, Write};
+use std::path::Path;
 
 pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
         Some(("transactions", sub)) => export_transactions(conn, sub),
+        Some(("budgets", sub)) => {
+            let months = match sub.get_one::<String>("year") {
+                Some(year) => {
+                    let year = year.trim();
+                    (1..=12)
+                        .map(|m| crate::utils::parse_month(&format!("{}-{:02}", year, m)))
+                        .collect::<Result<Vec<_>>>()?
+                }
+                None => vec![crate::utils::parse_month(
+                    sub.get_one::<String>("month").unwrap().trim(),
+                )?],
+            };
+            let rows = budget_rows(conn, &months)?;
+            write_rows(sub, "budgets", &rows)
+        }
+        Some(("sheets", sub)) => crate::commands::sheets::handle(conn, sub),
+        Some(("envelopes", sub)) => {
+            let month = crate::utils::parse_month(sub.get_one::<String>("month").unwrap().trim())?;
+            let rows = envelope_rows(conn, &month)?;
+            write_rows(sub, "envelopes", &rows)
+        }
+        Some(("anonymized", sub)) => {
+            let out = sub.get_one::<String>("out").unwrap().trim().to_string();
+            export_anonymized(conn, Path::new(&out))?;
+            println!("Wrote anonymized copy to {}", out);
+            Ok(())
+        }
         _ => Ok(()),
     }
 }
@@ -26,15 +58,18 @@ fn export_transactions(conn: &Connection, sub: &clap::ArgMatches) -> Result<()>
         .trim()
         .to_lowercase();
     let out = sub.get_one::<String>("out").unwrap().trim().to_string();
+    let (from, to) = crate::cli::date_range(sub)?;
 
     let mut stmt = conn.prepare_cached(concat!(
-        "SELECT t.date, a.name as account, t.payee, t.amount, t.currency, c.name as category, t.note\n",
+        "SELECT t.date, a.name as account, t.payee, t.amount, t.currency, c.name as category, t.note, t.id\n",
         " FROM transactions t\n",
         " LEFT JOIN accounts a ON t.account_id=a.id\n",
         " LEFT JOIN categories c ON t.category_id=c.id\n",
+        " WHERE (?1 IS NULL OR t.date>=?1) AND (?2 IS NULL OR t.date<=?2)\n",
         " ORDER BY t.date, t.id",
     ))?;
-    let rows = stmt.query_map([], |r| {
+    let range = params![from.map(|d| d.to_string()), to.map(|d| d.to_string())];
+    let rows = stmt.query_map(range, |r| {
         Ok((
             r.get::<_, String>(0)?,
             r.get::<_, String>(1)?,
@@ -43,18 +78,34 @@ fn export_transactions(conn: &Connection, sub: &clap::ArgMatches) -> Result<()>
             r.get::<_, String>(4)?,
             r.get::<_, Option<String>>(5)?,
             r.get::<_, Option<String>>(6)?,
+            r.get::<_, i64>(7)?,
         ))
     })?;
+    let meta = if sub.get_flag("with-meta") {
+        Some(transaction_meta(conn)?)
+    } else {
+        None
+    };
+    let meta_keys: Vec<String> = meta
+        .iter()
+        .flat_map(|m| m.values().flat_map(|kv| kv.keys().cloned()))
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
 
     match fmt.as_str() {
         "csv" => {
             let mut wtr = csv::Writer::from_path(&out)?;
-            wtr.write_record([
+            let mut header: Vec<String> = [
                 "date", "account", "payee", "amount", "currency", "category", "note",
-            ])?;
+            ]
+            .map(String::from)
+            .to_vec();
+            header.extend(meta_keys.iter().cloned());
+            wtr.write_record(&header)?;
             for row in rows {
-                let (d, a, p, amt, ccy, cat, note) = row?;
-                wtr.write_record([
+                let (d, a, p, amt, ccy, cat, note, id) = row?;
+                let mut record = vec![
                     d,
                     a,
                     p,
@@ -62,7 +113,15 @@ fn export_transactions(conn: &Connection, sub: &clap::ArgMatches) -> Result<()>
                     ccy,
                     cat.unwrap_or_default(),
                     note.unwrap_or_default(),
-                ])?;
+                ];
+                let tx_meta = meta.as_ref().and_then(|m| m.get(&id));
+                record.extend(meta_keys.iter().map(|k| {
+                    tx_meta
+                        .and_then(|kv| kv.get(k))
+                        .cloned()
+                        .unwrap_or_default()
+                }));
+                wtr.write_record(&record)?;
             }
             wtr.flush()?;
         }
@@ -73,7 +132,7 @@ fn export_transactions(conn: &Connection, sub: &clap::ArgMatches) -> Result<()>
             let mut serializer = serde_json::Serializer::with_formatter(&mut writer, formatter);
             let mut seq = serializer.serialize_seq(None)?;
             for row in rows {
-                let (date, account, payee, amount, currency, category, note) = row?;
+                let (date, account, payee, amount, currency, category, note, id) = row?;
                 seq.serialize_element(&ExportedTransaction {
                     date,
                     account,
@@ -82,6 +141,9 @@ fn export_transactions(conn: &Connection, sub: &clap::ArgMatches) -> Result<()>
                     currency,
                     category,
                     note,
+                    meta: meta
+                        .as_ref()
+                        .map(|m| m.get(&id).cloned().unwrap_or_default()),
                 })?;
             }
             seq.end()?;
@@ -102,4 +164,415 @@ struct ExportedTransaction {
     currency: String,
     category: Option<String>,
     note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<BTreeMap<String, String>>,
+}
+
+/// Metadata of every transaction that has some, by transaction id.
+fn transaction_meta(conn: &Connection) -> Result<HashMap<i64, BTreeMap<String, String>>> {
+    let mut stmt = conn.prepare("SELECT tx_id, key, value FROM transaction_meta")?;
+    let mut rows = stmt.query([])?;
+    let mut out: HashMap<i64, BTreeMap<String, String>> = HashMap::new();
+    while let Some(r) = rows.next()? {
+        out.entry(r.get(0)?)
+            .or_default()
+            .insert(r.get(1)?, r.get(2)?);
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedBudget {
+    pub month: String,
+    pub category: String,
+    pub budget: String,
+    pub spent: String,
+    pub remaining: String,
+    pub currency: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedEnvelope {
+    pub month: String,
+    pub category: String,
+    pub carryover: String,
+    pub funded: String,
+    pub spent: String,
+    pub available: String,
+    pub currency: String,
+}
+
+/// Budget against spending per category for each of `months`, in the base
+/// currency; categories with neither a budget nor spending are left out.
+pub fn budget_rows(conn: &Connection, months: &[String]) -> Result<Vec<ExportedBudget>> {
+    let base = crate::utils::get_base_currency(conn)?;
+    let mut out = Vec::new();
+    for month in months {
+        for row in crate::commands::budgets::build_budget_report(conn, month, &base, None)? {
+            let budget = row.budget.parse::<Decimal>()?;
+            let spent = row.spent.parse::<Decimal>()?;
+            if budget.is_zero() && spent.is_zero() {
+                continue;
+            }
+            out.push(ExportedBudget {
+                month: month.clone(),
+                category: row.category,
+                budget: row.budget,
+                spent: row.spent,
+                remaining: crate::utils::fmt_amount(budget - spent, &base),
+                currency: base.clone(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Envelope status of every category with activity in `month`, in the base currency.
+pub fn envelope_rows(conn: &Connection, month: &str) -> Result<Vec<ExportedEnvelope>> {
+    use crate::utils::fmt_amount;
+    let base = crate::utils::get_base_currency(conn)?;
+    let mut stmt = conn.prepare("SELECT id, name FROM categories ORDER BY name")?;
+    let categories = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut out = Vec::new();
+    for (id, name) in categories {
+        let (carry, funded, spent) = crate::commands::envelopes::envelope_compute(conn, id, month)?;
+        if carry.is_zero() && funded.is_zero() && spent.is_zero() {
+            continue;
+        }
+        out.push(ExportedEnvelope {
+            month: month.to_string(),
+            category: name,
+            carryover: fmt_amount(carry, &base),
+            funded: fmt_amount(funded, &base),
+            spent: fmt_amount(spent, &base),
+            available: fmt_amount(carry + funded - spent, &base),
+            currency: base.clone(),
+        });
+    }
+    Ok(out)
+}
+
+/// Write `rows` to `--out` as CSV (field names as the header) or a JSON array.
+fn write_rows<T: Serialize>(sub: &clap::ArgMatches, what: &str, rows: &[T]) -> Result<()> {
+    let out = sub.get_one::<String>("out").unwrap().trim();
+    match sub.get_one::<String>("format").unwrap().as_str() {
+        "csv" => {
+            let mut wtr = csv::Writer::from_path(out)?;
+            for row in rows {
+                wtr.serialize(row)?;
+            }
+            wtr.flush()?;
+        }
+        _ => {
+            let mut writer = BufWriter::new(File::create(out)?);
+            serde_json::to_writer_pretty(&mut writer, rows)?;
+            writer.flush()?;
+        }
+    }
+    println!("Exported {} {} rows to {}", rows.len(), what, out);
+    Ok(())
+}
+
+/// Copy the whole database to `out` and scramble what identifies the owner:
+/// payees, invoice clients, notes, rule text and transaction metadata become salted hashes (equal
+/// inputs stay equal, so grouping and rule behaviour survive), account names become
+/// `Account <id>`, jars `Jar <id>` and trips `Trip <id>`, people owing a transaction become `Person <n>`, projects `Project <n>`, locations `Location <n>`
+/// with their coordinates dropped, transaction (with their tax), budget and invoice amounts are jittered by up to ±20% at their original
+/// precision, and transaction dates move to a random day of the same month.
+/// Values that do not parse are left untouched so data bugs still reproduce.
+pub fn export_anonymized(conn: &Connection, out: &Path) -> Result<()> {
+    if out.exists() {
+        bail!("{} already exists; choose a new --out path", out.display());
+    }
+    let out_str = out
+        .to_str()
+        .with_context(|| format!("Output path {} is not valid UTF-8", out.display()))?;
+    conn.execute("VACUUM INTO ?1", [out_str])
+        .with_context(|| format!("Copy database to {}", out.display()))?;
+
+    let mut copy = Connection::open(out)?;
+    let salt = Scrambler(RandomState::new());
+    let tx = copy.transaction()?;
+    // Closed-period triggers would reject the date shuffle; lift them for the copy.
+    let closed = if table_exists(&tx, "closed_periods")? {
+        let months = tx
+            .prepare("SELECT month, closed_at FROM closed_periods")?
+            .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        tx.execute("DELETE FROM closed_periods", [])?;
+        months
+    } else {
+        Vec::new()
+    };
+    if table_exists(&tx, "transactions")? {
+        scramble_transactions(&tx, &salt)?;
+    }
+    if column_exists(&tx, "transactions", "reimbursable_by")? {
+        pseudonymize(
+            &tx,
+            "transactions",
+            "reimbursable_by",
+            "Person",
+            &mut HashMap::new(),
+        )?;
+    }
+    let mut projects = HashMap::new();
+    if column_exists(&tx, "transactions", "project")? {
+        pseudonymize(&tx, "transactions", "project", "Project", &mut projects)?;
+    }
+    if table_exists(&tx, "jars")? {
+        tx.execute("UPDATE jars SET name = 'Jar ' || id", [])?;
+    }
+    if table_exists(&tx, "jar_moves")? {
+        let mut stmt = tx.prepare("SELECT id, note FROM jar_moves WHERE note IS NOT NULL")?;
+        let rows = stmt
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, note) in rows {
+            tx.execute(
+                "UPDATE jar_moves SET note=?1 WHERE id=?2",
+                params![salt.label("note", &note), id],
+            )?;
+        }
+    }
+    if table_exists(&tx, "trips")? {
+        // Names are usually destinations; tags name a project and share its placeholder.
+        tx.execute("UPDATE trips SET name = 'Trip ' || id", [])?;
+        pseudonymize(&tx, "trips", "tag", "Project", &mut projects)?;
+    }
+    if column_exists(&tx, "transactions", "location")? {
+        pseudonymize(
+            &tx,
+            "transactions",
+            "location",
+            "Location",
+            &mut HashMap::new(),
+        )?;
+        tx.execute(
+            "UPDATE transactions SET latitude = NULL, longitude = NULL",
+            [],
+        )?;
+    }
+    for (month, closed_at) in &closed {
+        tx.execute(
+            "INSERT INTO closed_periods(month, closed_at) VALUES (?1, ?2)",
+            params![month, closed_at],
+        )?;
+    }
+    if table_exists(&tx, "accounts")? {
+        tx.execute("UPDATE accounts SET name = 'Account ' || id", [])?;
+    }
+    if table_exists(&tx, "budgets")? {
+        let mut stmt = tx.prepare("SELECT id, amount FROM budgets")?;
+        let rows = stmt
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, amount) in rows {
+            let jittered = salt.jitter(("budget", id), &amount);
+            tx.execute(
+                "UPDATE budgets SET amount=?1 WHERE id=?2",
+                params![jittered, id],
+            )?;
+        }
+    }
+    if table_exists(&tx, "invoices")? {
+        let mut stmt = tx.prepare("SELECT id, client, amount, note FROM invoices")?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, String>(2)?,
+                    r.get::<_, Option<String>>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        // Clients hash like payees, so an invoice still matches the payment that settled it.
+        for (id, client, amount, note) in rows {
+            tx.execute(
+                "UPDATE invoices SET client=?1, amount=?2, note=?3 WHERE id=?4",
+                params![
+                    salt.label("payee", &client),
+                    salt.jitter(("invoice", id), &amount),
+                    note.map(|n| salt.label("note", &n)),
+                    id
+                ],
+            )?;
+        }
+    }
+    if table_exists(&tx, "trades")? {
+        let mut stmt = tx.prepare("SELECT id, note FROM trades WHERE note IS NOT NULL")?;
+        let rows = stmt
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, note) in rows {
+            tx.execute(
+                "UPDATE trades SET note=?1 WHERE id=?2",
+                params![salt.label("note", &note), id],
+            )?;
+        }
+    }
+    if table_exists(&tx, "transaction_meta")? {
+        let mut stmt = tx.prepare("SELECT tx_id, key, value FROM transaction_meta")?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, key, value) in rows {
+            tx.execute(
+                "UPDATE transaction_meta SET value=?1 WHERE tx_id=?2 AND key=?3",
+                params![salt.label("meta", &value), id, key],
+            )?;
+        }
+    }
+    if table_exists(&tx, "rules")? {
+        let mut stmt = tx.prepare("SELECT id, pattern, payee_rewrite, note FROM rules")?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, Option<String>>(2)?,
+                    r.get::<_, Option<String>>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, pattern, rewrite, note) in rows {
+            tx.execute(
+                "UPDATE rules SET pattern=?1, payee_rewrite=?2, note=?3 WHERE id=?4",
+                params![
+                    salt.label("rule", &pattern),
+                    rewrite.map(|p| salt.label("payee", &p)),
+                    note.map(|n| salt.label("note", &n)),
+                    id
+                ],
+            )?;
+        }
+    }
+    tx.commit()?;
+    copy.execute_batch("VACUUM;")?;
+    Ok(())
+}
+
+fn scramble_transactions(tx: &Transaction<'_>, salt: &Scrambler) -> Result<()> {
+    let mut stmt = tx.prepare("SELECT id, date, amount, payee, note FROM transactions")?;
+    let rows = stmt
+        .query_map([], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+                r.get::<_, Option<String>>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut update =
+        tx.prepare("UPDATE transactions SET date=?1, amount=?2, payee=?3, note=?4 WHERE id=?5")?;
+    for (id, date, amount, payee, note) in rows {
+        update.execute(params![
+            salt.shuffle_day(id, &date),
+            salt.jitter(("txn", id), &amount),
+            salt.label("payee", &payee),
+            note.map(|n| salt.label("note", &n)),
+            id
+        ])?;
+    }
+    if column_exists(tx, "transactions", "tax_amount")? {
+        // Same key as the amount, hence the same factor: the tax rate stays plausible
+        // without letting tax / rate recover the original amount.
+        let rows = tx
+            .prepare("SELECT id, tax_amount FROM transactions WHERE tax_amount IS NOT NULL")?
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut update = tx.prepare("UPDATE transactions SET tax_amount=?1 WHERE id=?2")?;
+        for (id, tax) in rows {
+            update.execute(params![salt.jitter(("txn", id), &tax), id])?;
+        }
+    }
+    Ok(())
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type='table' AND name=?1)",
+        [name],
+        |r| r.get(0),
+    )?)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{table}') WHERE name=?1)"),
+        [column],
+        |r| r.get(0),
+    )?)
+}
+
+/// Replace every distinct value of `table.column` with `<prefix> <n>`, numbered in
+/// order of first appearance. Values already in `seen` keep their placeholder, so
+/// one mapping can span several columns.
+fn pseudonymize(
+    tx: &Transaction<'_>,
+    table: &str,
+    column: &str,
+    prefix: &str,
+    seen: &mut HashMap<String, String>,
+) -> Result<()> {
+    let rows = tx
+        .prepare(&format!(
+            "SELECT rowid, {column} FROM {table} WHERE {column} IS NOT NULL ORDER BY rowid"
+        ))?
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut update = tx.prepare(&format!("UPDATE {table} SET {column}=?1 WHERE rowid=?2"))?;
+    for (rowid, value) in rows {
+        let next = seen.len() + 1;
+        let alias = seen
+            .entry(value)
+            .or_insert_with(|| format!("{} {}", prefix, next));
+        update.execute(params![alias.as_str(), rowid])?;
+    }
+    Ok(())
+}
+
+struct Scrambler(RandomState);
+
+impl Scrambler {
+    fn hash(&self, value: impl Hash) -> u64 {
+        self.0.hash_one(value)
+    }
+
+    fn label(&self, kind: &str, value: &str) -> String {
+        format!("{}-{:08x}", kind, self.hash((kind, value)) as u32)
+    }
+
+    fn jitter(&self, key: impl Hash, amount: &str) -> String {
+        let Ok(value) = amount.trim().parse::<Decimal>() else {
+            return amount.to_string();
+        };
+        let permille = (self.hash(key) % 401) as i64 - 200;
+        let factor = Decimal::ONE + Decimal::new(permille, 3);
+        (value * factor).round_dp(value.scale()).to_string()
+    }
+
+    fn shuffle_day(&self, id: i64, date: &str) -> String {
+        let Ok(parsed) = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d") else {
+            return date.to_string();
+        };
+        let first = parsed.with_day(1).unwrap();
+        let next = first
+            .checked_add_months(chrono::Months::new(1))
+            .unwrap_or(first);
+        let days = (next - first).num_days().max(1) as u64;
+        let day = 1 + (self.hash(("day", id)) % days) as u32;
+        first.with_day(day).unwrap_or(parsed).to_string()
+    }
 }
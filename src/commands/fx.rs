@@ -4,12 +4,18 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::{get_base_currency, http_client, pretty_table, set_base_currency};
+use crate::logging::SendTraced;
+use crate::providers::{Frankfurter, FxProvider};
+use crate::utils::{
+    FxPathPolicy, ValuationPolicy, ensure_provider, fx_convert, get_base_currency, http_client,
+    maybe_print_json, minor_units, parse_date, parse_decimal, pretty_table, set_base_currency,
+    set_setting,
+};
 use anyhow::{Context, Result, ensure};
-use chrono::Utc;
+use chrono::NaiveDate;
 use rusqlite::{Connection, params};
-use serde::Deserialize;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 
 use rust_decimal::Decimal;
@@ -22,13 +28,44 @@ pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
                 .unwrap()
                 .trim()
                 .to_uppercase();
-            set_base_currency(conn, &ccy)?;
-            println!("Base currency set to {}", ccy);
+            let migrate = sub.get_flag("migrate").then(|| -> Result<NaiveDate> {
+                match sub.get_one::<String>("date") {
+                    Some(d) => parse_date(d.trim()),
+                    None => Ok(crate::utils::today()),
+                }
+            });
+            let change = change_base(conn, &ccy, migrate.transpose()?, !sub.get_flag("no-fetch"))?;
+            println!("Base currency set to {} (was {})", ccy, change.from);
+            match change.rate_date {
+                Some(date) => println!(
+                    "Converted {} budget(s) and {} goal(s) at {} rates",
+                    change.budgets, change.goals, date
+                ),
+                None if change.goals > 0 => println!(
+                    "{} goal target(s) still hold {} amounts; rerun with --migrate to convert them",
+                    change.goals, change.from
+                ),
+                None => {}
+            }
         }
+        Some(("base-history", _)) => base_history(conn)?,
         Some(("fetch", sub)) => {
             let days: usize = *sub.get_one::<usize>("days").unwrap_or(&120);
             fetch_rates(conn, days)?;
         }
+        Some(("backfill", sub)) => backfill(conn, sub)?,
+        Some(("set-policy", sub)) => {
+            let policy = FxPathPolicy::parse(sub.get_one::<String>("policy").unwrap())?;
+            set_setting(conn, "fx_path_policy", policy.as_str())?;
+            println!("FX path policy set to {}", policy.as_str());
+        }
+        Some(("set-valuation-date", sub)) => {
+            let policy = ValuationPolicy::parse(sub.get_one::<String>("policy").unwrap())?;
+            set_setting(conn, "valuation_date", policy.as_str())?;
+            println!("Valuation date policy set to {}", policy.as_str());
+        }
+        Some(("explain", sub)) => explain(conn, sub)?,
+        Some(("doctor", sub)) => doctor(conn, sub)?,
         Some(("list", _)) => list_rates(conn)?,
         Some(("convert", sub)) => convert_amount(conn, sub)?,
         _ => {}
@@ -36,7 +73,7 @@ pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn distinct_currencies(conn: &Connection) -> Result<Vec<String>> {
+pub(crate) fn distinct_currencies(conn: &Connection) -> Result<Vec<String>> {
     let mut out = Vec::<String>::new();
     let mut seen = HashSet::new();
     for sql in [
@@ -60,15 +97,9 @@ fn distinct_currencies(conn: &Connection) -> Result<Vec<String>> {
     Ok(out)
 }
 
-#[derive(Debug, Deserialize)]
-struct Series {
-    rates: std::collections::HashMap<String, std::collections::HashMap<String, f64>>,
-    #[serde(rename = "base")]
-    _base: String,
-}
 fn fetch_rates(conn: &mut Connection, days: usize) -> Result<()> {
     let base = get_base_currency(conn)?.trim().to_uppercase();
-    let today = Utc::now().date_naive();
+    let today = crate::utils::today();
     let start = today - chrono::Duration::days(days as i64);
     let ccy_list = distinct_currencies(conn)?;
     let targets: Vec<String> = ccy_list.into_iter().filter(|c| c != &base).collect();
@@ -76,35 +107,285 @@ fn fetch_rates(conn: &mut Connection, days: usize) -> Result<()> {
         println!("No non-base currencies found; nothing to fetch.");
         return Ok(());
     }
-    let to_param = targets.join(",");
-    let url = format!("https://api.frankfurter.dev/{start}..{today}?from={base}&to={to_param}");
-    let client = http_client()?;
-    let resp = client.get(url).send()?.error_for_status()?;
-    let s: Series = resp.json()?;
+    ensure_provider(conn, "fx_provider", "frankfurter")?;
+    let progress = crate::progress::spinner("Fetching FX rates from Frankfurter");
+    let rates = Frankfurter.rates(&base, &targets, start, today)?;
+    drop(progress);
+    let upserted = store_rates(conn, &base, rates)?;
+    println!(
+        "FX rates fetched via Frankfurter (ECB); {} rows upserted.",
+        upserted
+    );
+    Ok(())
+}
+
+pub(crate) fn store_rates(
+    conn: &mut Connection,
+    base: &str,
+    rates: HashMap<String, HashMap<String, f64>>,
+) -> Result<usize> {
     let mut upserted = 0usize;
-    let tx = conn.transaction()?;
+    let tx = conn.savepoint()?;
     {
         let mut stmt = tx.prepare_cached(
             "INSERT OR REPLACE INTO fx_rates(date, base, quote, rate) VALUES (?1, ?2, ?3, ?4)",
         )?;
-        for (date, mp) in s.rates {
+        for (date, mp) in rates {
             for (quote, rate) in mp {
                 let normalized_quote = quote.trim().to_uppercase();
                 let rate_str = decimal_string(rate).with_context(|| {
                     format!("Invalid FX rate {} for {}/{}", rate, base, normalized_quote)
                 })?;
-                upserted += stmt.execute(params![&date, &base, &normalized_quote, &rate_str])?;
+                upserted += stmt.execute(params![&date, base, &normalized_quote, &rate_str])?;
             }
         }
     }
     tx.commit()?;
+    Ok(upserted)
+}
+
+#[derive(Debug, Deserialize)]
+struct DayRates {
+    date: String,
+    rates: HashMap<String, f64>,
+}
+
+fn backfill(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let max_age = *sub.get_one::<i64>("max-age").unwrap_or(&7);
+    ensure!(max_age >= 0, "--max-age must not be negative");
+    let base = get_base_currency(conn)?.trim().to_uppercase();
+    let gaps = rate_gaps(conn, &base, Some(max_age))?;
+    if gaps.is_empty() {
+        println!("Every transaction and trade date already has a rate; nothing to backfill.");
+        return Ok(());
+    }
+    let mut by_date: Vec<(NaiveDate, Vec<String>)> = Vec::new();
+    for gap in gaps {
+        match by_date.last_mut() {
+            Some((date, ccys)) if *date == gap.date => ccys.push(gap.currency),
+            _ => by_date.push((gap.date, vec![gap.currency])),
+        }
+    }
+    if sub.get_flag("dry-run") {
+        let rows = by_date
+            .iter()
+            .map(|(date, ccys)| vec![date.to_string(), ccys.join(",")])
+            .collect();
+        println!("{}", pretty_table(&["Date", "Currencies"], rows));
+        println!("(dry-run) {} date(s) would be fetched", by_date.len());
+        return Ok(());
+    }
+    ensure_provider(conn, "fx_provider", "frankfurter")?;
+    let mut upserted = 0usize;
+    let progress = crate::progress::bar(by_date.len(), "Backfilling FX rates");
+    for (date, ccys) in progress.wrap_iter(by_date.iter()) {
+        upserted += fetch_day(conn, &base, *date, ccys)?;
+    }
+    drop(progress);
     println!(
-        "FX rates fetched via Frankfurter (ECB); {} rows upserted.",
+        "Backfilled {} date(s) via Frankfurter (ECB); {} rows upserted.",
+        by_date.len(),
         upserted
     );
     Ok(())
 }
 
+/// Store `base`'s rates against `ccys` on `date` from Frankfurter. Weekends and
+/// holidays are answered with the previous business day, which still covers the
+/// date because lookups use the latest rate on or before it.
+fn fetch_day(conn: &mut Connection, base: &str, date: NaiveDate, ccys: &[String]) -> Result<usize> {
+    let url = format!(
+        "https://api.frankfurter.dev/{date}?from={base}&to={}",
+        ccys.join(",")
+    );
+    let day: DayRates = http_client()?
+        .get(url)
+        .send_traced()?
+        .error_for_status()
+        .with_context(|| format!("Fetching FX rates for {}", date))?
+        .json()?;
+    store_rates(conn, base, HashMap::from([(day.date, day.rates)]))
+}
+
+#[derive(Debug)]
+pub struct BaseChange {
+    pub from: String,
+    /// Set when values were converted (`--migrate`).
+    pub rate_date: Option<NaiveDate>,
+    pub budgets: usize,
+    pub goals: usize,
+}
+
+/// Switch the base currency to `to`. Budgets saved without a currency are stamped
+/// with the old base either way, so their meaning does not silently change. With
+/// `migrate`, budgets and goal targets (which are kept in the base currency) are
+/// converted at that date's rates, fetched first unless `fetch` is false. Every
+/// change is recorded in `base_currency_changes`.
+pub fn change_base(
+    conn: &mut Connection,
+    to: &str,
+    migrate: Option<NaiveDate>,
+    fetch: bool,
+) -> Result<BaseChange> {
+    ensure!(
+        to.len() == 3 && to.chars().all(|c| c.is_ascii_alphabetic()),
+        "Currency must be a 3-letter code like EUR"
+    );
+    let from = get_base_currency(conn)?.trim().to_uppercase();
+    ensure!(from != to, "Base currency is already {}", to);
+    let goal_count: usize = conn.query_row("SELECT COUNT(*) FROM goals", [], |r| r.get(0))?;
+
+    if let (Some(date), true) = (migrate, fetch) {
+        ensure_provider(conn, "fx_provider", "frankfurter")?;
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT UPPER(currency) FROM budgets WHERE currency IS NOT NULL")?;
+        let mut ccys = vec![to.to_string()];
+        for c in stmt.query_map([], |r| r.get::<_, String>(0))? {
+            let c = c?;
+            if c != from && !ccys.contains(&c) {
+                ccys.push(c);
+            }
+        }
+        drop(stmt);
+        fetch_day(conn, &from, date, &ccys)?;
+    }
+
+    let tx = conn.savepoint()?;
+    tx.execute(
+        "UPDATE budgets SET currency=?1 WHERE currency IS NULL",
+        params![from],
+    )?;
+    let (mut budgets, mut goals) = (0, 0);
+    if let Some(date) = migrate {
+        let rows = tx
+            .prepare("SELECT id, amount, currency FROM budgets WHERE UPPER(currency) != ?1")?
+            .query_map(params![to], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, raw, ccy) in rows {
+            let amount = parse_decimal(raw.trim())?;
+            let converted = fx_convert(&tx, date, amount, &ccy, to)?.round_dp(minor_units(to));
+            tx.execute(
+                "UPDATE budgets SET amount=?1, currency=?2 WHERE id=?3",
+                params![converted.to_string(), to, id],
+            )?;
+            budgets += 1;
+        }
+        let rows = tx
+            .prepare("SELECT id, target FROM goals")?
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, raw) in rows {
+            let target = parse_decimal(raw.trim())?;
+            let converted = fx_convert(&tx, date, target, &from, to)?.round_dp(minor_units(to));
+            tx.execute(
+                "UPDATE goals SET target=?1 WHERE id=?2",
+                params![converted.to_string(), id],
+            )?;
+            goals += 1;
+        }
+    } else {
+        goals = goal_count;
+    }
+    set_base_currency(&tx, to)?;
+    tx.execute(
+        "INSERT INTO base_currency_changes(from_currency, to_currency, rate_date, budgets, goals)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![from, to, migrate.map(|d| d.to_string()), budgets, goals],
+    )?;
+    tx.commit()?;
+    Ok(BaseChange {
+        from,
+        rate_date: migrate,
+        budgets,
+        goals,
+    })
+}
+
+fn base_history(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT changed_at, from_currency, to_currency, COALESCE(rate_date, ''), budgets, goals
+         FROM base_currency_changes ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map([], |r| {
+            let migrated = r.get::<_, String>(3)?;
+            let (budgets, goals) = if migrated.is_empty() {
+                (String::new(), String::new())
+            } else {
+                (
+                    r.get::<_, i64>(4)?.to_string(),
+                    r.get::<_, i64>(5)?.to_string(),
+                )
+            };
+            Ok(vec![
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                migrated,
+                budgets,
+                goals,
+            ])
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    println!(
+        "{}",
+        pretty_table(
+            &["Changed", "From", "To", "Rates of", "Budgets", "Goals"],
+            rows
+        )
+    );
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RateGap {
+    pub date: NaiveDate,
+    pub currency: String,
+}
+
+/// Dates on which a transaction or trade is booked in a non-base currency but no
+/// rate between that currency and `base` exists within `max_age_days` before it, or
+/// on or before it at all when `max_age_days` is `None`. Shared by `fx backfill`,
+/// `fx doctor` and `doctor`.
+pub fn rate_gaps(conn: &Connection, base: &str, max_age_days: Option<i64>) -> Result<Vec<RateGap>> {
+    let mut stmt = conn.prepare(
+        "SELECT date, UPPER(TRIM(currency)) FROM transactions
+         UNION
+         SELECT tr.date, UPPER(TRIM(a.currency)) FROM trades tr JOIN assets a ON a.id = tr.asset_id
+         ORDER BY 1, 2",
+    )?;
+    let used = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+    let mut covered = conn.prepare_cached(
+        "SELECT EXISTS(SELECT 1 FROM fx_rates
+          WHERE ((base=?1 AND quote=?2) OR (base=?2 AND quote=?1))
+            AND date <= ?3 AND (?4 IS NULL OR date >= ?4))",
+    )?;
+    let mut gaps = Vec::new();
+    for row in used {
+        let (date_s, currency) = row?;
+        if currency.is_empty() || currency == base {
+            continue;
+        }
+        let date = crate::utils::parse_date(date_s.trim())?;
+        let oldest = max_age_days.map(|days| (date - chrono::Duration::days(days)).to_string());
+        let ok: bool = covered
+            .query_row(params![base, &currency, date.to_string(), oldest], |r| {
+                r.get(0)
+            })?;
+        if !ok {
+            gaps.push(RateGap { date, currency });
+        }
+    }
+    gaps.dedup();
+    Ok(gaps)
+}
+
 fn decimal_string(rate: f64) -> Result<String> {
     ensure!(rate.is_finite(), "Fetched FX rate must be finite");
     let decimal = Decimal::try_from(rate).context("Failed to convert FX rate to Decimal")?;
@@ -134,19 +415,303 @@ fn list_rates(conn: &Connection) -> Result<()> {
 }
 
 fn convert_amount(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
-    let date = crate::utils::parse_date(sub.get_one::<String>("date").unwrap().trim())?;
-    let amount = crate::utils::parse_decimal(sub.get_one::<String>("amount").unwrap().trim())?;
+    let amount = crate::utils::parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
     let from = sub.get_one::<String>("from").unwrap().trim().to_uppercase();
     let to = sub.get_one::<String>("to").unwrap().trim().to_uppercase();
+    if sub.get_flag("series") {
+        let start = parse_date(sub.get_one::<String>("from-date").unwrap().trim())?;
+        let end = parse_date(sub.get_one::<String>("to-date").unwrap().trim())?;
+        let step = sub.get_one::<String>("step").unwrap();
+        let points = convert_series(conn, amount, &from, &to, start, end, step)?;
+        if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &points)? {
+            return Ok(());
+        }
+        let rows = points
+            .iter()
+            .map(|p| {
+                vec![
+                    p.date.clone(),
+                    format!("{:.6}", p.rate),
+                    crate::utils::fmt_amount(p.converted, &to),
+                ]
+            })
+            .collect();
+        println!(
+            "{}",
+            pretty_table(
+                &["Date", "Rate", &format!("{} {} in {}", amount, from, to)],
+                rows
+            )
+        );
+        return Ok(());
+    }
+    let date = parse_date(sub.get_one::<String>("date").unwrap().trim())?;
     let res = crate::utils::fx_convert(conn, date, amount, &from, &to)?;
-    println!("{} {} -> {:.4} {}", amount, from, res, to);
+    println!(
+        "{} {} -> {} {}",
+        amount,
+        from,
+        crate::utils::fmt_amount(res, &to),
+        to
+    );
+    Ok(())
+}
+
+/// One date of `fx convert --series`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConvertedPoint {
+    pub date: String,
+    pub rate: Decimal,
+    pub converted: Decimal,
+}
+
+/// `amount` converted from `from` to `to` on every `step` (day|week|month) from `start`
+/// through `end`. Month steps stay on `start`'s day of month, clamped to short months.
+pub fn convert_series(
+    conn: &Connection,
+    amount: Decimal,
+    from: &str,
+    to: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    step: &str,
+) -> Result<Vec<ConvertedPoint>> {
+    ensure!(
+        start <= end,
+        "--from-date {} is after --to-date {}",
+        start,
+        end
+    );
+    ensure!(
+        ["day", "week", "month"].contains(&step),
+        "Unknown step '{}' (use day|week|month)",
+        step
+    );
+    let nth = |i: u32| -> Option<NaiveDate> {
+        match step {
+            "day" => start.checked_add_days(chrono::Days::new(i.into())),
+            "week" => start.checked_add_days(chrono::Days::new(7 * u64::from(i))),
+            _ => start.checked_add_months(chrono::Months::new(i)),
+        }
+    };
+    let mut out = Vec::new();
+    let mut i = 0;
+    while let Some(date) = nth(i).filter(|d| *d <= end) {
+        let rate = fx_convert(conn, date, Decimal::ONE, from, to)?;
+        out.push(ConvertedPoint {
+            date: date.to_string(),
+            rate,
+            converted: amount * rate,
+        });
+        i += 1;
+    }
+    Ok(out)
+}
+
+fn explain(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let date = crate::utils::parse_date(sub.get_one::<String>("date").unwrap().trim())?;
+    let from = sub.get_one::<String>("from").unwrap().trim().to_uppercase();
+    let to = sub.get_one::<String>("to").unwrap().trim().to_uppercase();
+    let (policy, steps) = crate::utils::fx_explain(conn, date, &from, &to)?;
+    if maybe_print_json(json_flag, jsonl_flag, &steps)? {
+        return Ok(());
+    }
+    let factor = steps
+        .iter()
+        .fold(Decimal::ONE, |acc, step| acc * step.applied_rate);
+    let rows = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            vec![
+                (i + 1).to_string(),
+                format!("{} -> {}", step.from, step.to),
+                step.pair.clone(),
+                step.rate_date.clone(),
+                step.stored_rate.to_string(),
+                format!("{:.6}", step.applied_rate),
+            ]
+        })
+        .collect();
+    println!(
+        "{}",
+        pretty_table(
+            &[
+                "Step",
+                "Leg",
+                "Stored Pair",
+                "Rate Date",
+                "Stored Rate",
+                "Applied"
+            ],
+            rows
+        )
+    );
+    println!(
+        "Policy {}: 1 {} = {:.6} {} on {}",
+        policy.as_str(),
+        from,
+        factor,
+        to,
+        date
+    );
+    Ok(())
+}
+
+fn doctor(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let pct = |id: &str, default: f64| -> Result<Decimal> {
+        let value = *sub.get_one::<f64>(id).unwrap_or(&default);
+        Decimal::try_from(value).with_context(|| format!("Invalid --{} value {}", id, value))
+    };
+    let max_age = *sub.get_one::<i64>("max-age").unwrap_or(&7);
+    let mut findings = audit_rates(conn, pct("max-jump", 10.0)?, pct("tolerance", 0.5)?)?;
+    let base = get_base_currency(conn)?.trim().to_uppercase();
+    findings.extend(
+        rate_gaps(conn, &base, Some(max_age))?
+            .into_iter()
+            .map(|gap| RateFinding {
+                issue: "missing_rate",
+                date: gap.date.to_string(),
+                pair: format!("{}/{}", base, gap.currency),
+                detail: format!("no rate within {} day(s); run `fx backfill`", max_age),
+            }),
+    );
+    if maybe_print_json(json_flag, jsonl_flag, &findings)? {
+        return Ok(());
+    }
+    if findings.is_empty() {
+        println!("✅ fx doctor: no suspicious rates found");
+        return Ok(());
+    }
+    let rows = findings
+        .into_iter()
+        .map(|f| vec![f.issue.to_string(), f.date, f.pair, f.detail])
+        .collect();
+    println!(
+        "{}",
+        pretty_table(&["Issue", "Date", "Pair", "Detail"], rows)
+    );
     Ok(())
 }
 
+#[derive(Debug, Serialize)]
+pub struct RateFinding {
+    pub issue: &'static str,
+    pub date: String,
+    pub pair: String,
+    pub detail: String,
+}
+
+/// Scan `fx_rates` for rows that would silently skew conversions: unparsable or
+/// non-positive values, magnitudes outside 1e-6..1e6, day-over-day moves above
+/// `max_jump_pct`, and same-day inverse pairs whose product strays from 1 by more
+/// than `tolerance_pct`.
+pub fn audit_rates(
+    conn: &Connection,
+    max_jump_pct: Decimal,
+    tolerance_pct: Decimal,
+) -> Result<Vec<RateFinding>> {
+    let hundred = Decimal::ONE_HUNDRED;
+    let min_sane = Decimal::new(1, 6);
+    let max_sane = Decimal::from(1_000_000);
+
+    let mut stmt =
+        conn.prepare("SELECT date, base, quote, rate FROM fx_rates ORDER BY base, quote, date")?;
+    let rows = stmt.query_map([], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut findings = Vec::new();
+    let mut by_day: HashMap<(String, String, String), Decimal> = HashMap::new();
+    let mut previous: Option<(String, String, Decimal)> = None;
+    for row in rows {
+        let (date, base, quote, rate_s) = row?;
+        let pair = format!("{}/{}", base, quote);
+        let Ok(rate) = rate_s.trim().parse::<Decimal>() else {
+            findings.push(RateFinding {
+                issue: "unparsable_rate",
+                date,
+                pair,
+                detail: format!("'{}' is not a decimal", rate_s),
+            });
+            continue;
+        };
+        if rate <= Decimal::ZERO {
+            findings.push(RateFinding {
+                issue: "non_positive_rate",
+                date,
+                pair,
+                detail: rate.to_string(),
+            });
+            continue;
+        }
+        if rate < min_sane || rate > max_sane {
+            findings.push(RateFinding {
+                issue: "absurd_magnitude",
+                date: date.clone(),
+                pair: pair.clone(),
+                detail: rate.to_string(),
+            });
+        }
+        let prior = previous
+            .as_ref()
+            .filter(|(prev_pair, _, _)| prev_pair == &pair);
+        if let Some((_, prev_date, prev_rate)) = prior {
+            let change = ((rate / prev_rate) - Decimal::ONE).abs() * hundred;
+            if change > max_jump_pct {
+                findings.push(RateFinding {
+                    issue: "rate_jump",
+                    date: date.clone(),
+                    pair: pair.clone(),
+                    detail: format!(
+                        "{} -> {} ({:.1}% since {})",
+                        prev_rate, rate, change, prev_date
+                    ),
+                });
+            }
+        }
+        by_day.insert((date.clone(), base, quote), rate);
+        previous = Some((pair, date, rate));
+    }
+
+    let mut inverse: Vec<_> = by_day
+        .iter()
+        .filter(|((_, base, quote), _)| base < quote)
+        .filter_map(|((date, base, quote), rate)| {
+            by_day
+                .get(&(date.clone(), quote.clone(), base.clone()))
+                .map(|inv| (date.clone(), base.clone(), quote.clone(), *rate, *inv))
+        })
+        .collect();
+    inverse.sort();
+    for (date, base, quote, rate, inv) in inverse {
+        let drift = ((rate * inv) - Decimal::ONE).abs() * hundred;
+        if drift > tolerance_pct {
+            findings.push(RateFinding {
+                issue: "inverse_mismatch",
+                date,
+                pair: format!("{}/{}", base, quote),
+                detail: format!("{} vs 1/{} disagree by {:.2}%", rate, inv, drift),
+            });
+        }
+    }
+    Ok(findings)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{decimal_string, distinct_currencies};
+    use super::{audit_rates, decimal_string, distinct_currencies};
     use rusqlite::Connection;
+    use rust_decimal::Decimal;
 
     #[test]
     fn distinct_currencies_dedupes_and_normalizes() {
@@ -177,6 +742,38 @@ mod tests {
         assert_eq!(values, vec!["USD", "EUR", "JPY"]);
     }
 
+    #[test]
+    fn audit_rates_flags_jumps_inverses_and_bad_values() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE fx_rates(date TEXT, base TEXT, quote TEXT, rate TEXT);
+            INSERT INTO fx_rates VALUES ('2025-01-01','USD','EUR','0.90');
+            INSERT INTO fx_rates VALUES ('2025-01-02','USD','EUR','0.91');
+            INSERT INTO fx_rates VALUES ('2025-01-03','USD','EUR','9.1');
+            INSERT INTO fx_rates VALUES ('2025-01-02','EUR','USD','1.2');
+            INSERT INTO fx_rates VALUES ('2025-01-02','USD','JPY','0');
+            INSERT INTO fx_rates VALUES ('2025-01-02','USD','XAU','0.0000001');
+            "#,
+        )
+        .unwrap();
+
+        let findings = audit_rates(&conn, Decimal::from(10), Decimal::new(5, 1)).unwrap();
+        let issues: Vec<(&str, &str, &str)> = findings
+            .iter()
+            .map(|f| (f.issue, f.date.as_str(), f.pair.as_str()))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                ("rate_jump", "2025-01-03", "USD/EUR"),
+                ("non_positive_rate", "2025-01-02", "USD/JPY"),
+                ("absurd_magnitude", "2025-01-02", "USD/XAU"),
+                ("inverse_mismatch", "2025-01-02", "EUR/USD"),
+            ]
+        );
+    }
+
     #[test]
     fn decimal_string_formats_small_rates_without_exponent() {
         let formatted = decimal_string(0.00001234).unwrap();
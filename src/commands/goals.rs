@@ -0,0 +1,152 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::commands::envelopes::envelope_compute;
+use crate::models::GoalStatusRow;
+use crate::utils::{
+    fmt_amount, fmt_percent, id_for_category, parse_amount, parse_date, parse_month, pretty_table,
+};
+use anyhow::{Context, Result, bail};
+use chrono::Datelike;
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim().to_string();
+            let cat = sub.get_one::<String>("category").unwrap().trim();
+            let target = parse_amount(sub.get_one::<String>("target").unwrap().trim())?;
+            if target <= Decimal::ZERO {
+                bail!("Goal target must be positive");
+            }
+            let by = sub
+                .get_one::<String>("by")
+                .map(|d| parse_date(d.trim()))
+                .transpose()?;
+            let cat_id = id_for_category(conn, cat)?;
+            conn.execute(
+                "INSERT INTO goals(name, category_id, target, target_date) VALUES (?1, ?2, ?3, ?4)",
+                params![name, cat_id, target.to_string(), by.map(|d| d.to_string())],
+            )?;
+            println!(
+                "Added goal '{}' for {} {} backed by envelope '{}'",
+                name,
+                target,
+                crate::utils::get_base_currency(conn)?,
+                cat
+            );
+        }
+        Some(("status", sub)) => status(conn, sub)?,
+        Some(("rm", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            if conn.execute("DELETE FROM goals WHERE name=?1", params![name])? == 0 {
+                bail!("Goal '{}' not found", name);
+            }
+            println!("Removed goal '{}'; its envelope is unchanged", name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn status(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let month = match sub.get_one::<String>("month") {
+        Some(m) => parse_month(m.trim())?,
+        None => parse_month("this")?,
+    };
+    let data = goal_status(conn, &month)?;
+    if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
+        crate::utils::emit(pretty_table(
+            &[
+                "Goal",
+                "Envelope",
+                "Saved",
+                "Target",
+                "Remaining",
+                "Progress",
+                "By",
+                "Per month",
+            ],
+            data.into_iter().map(GoalStatusRow::cells).collect(),
+        ));
+    }
+    Ok(())
+}
+
+/// Progress of every goal at the end of `month`. Saved is what is available in the
+/// backing envelope, so `envelope fund`, `move` and `sweep` all count towards it and
+/// spending from the envelope counts against it. Per month is what still has to be
+/// funded each month, this one included, to reach the target by its date.
+pub fn goal_status(conn: &Connection, month: &str) -> Result<Vec<GoalStatusRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT g.name, c.name, g.category_id, g.target, g.target_date
+         FROM goals g JOIN categories c ON c.id = g.category_id
+         ORDER BY g.target_date IS NULL, g.target_date, g.name",
+    )?;
+    let rows = stmt
+        .query_map([], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, i64>(2)?,
+                r.get::<_, String>(3)?,
+                r.get::<_, Option<String>>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let base = crate::utils::get_base_currency(conn)?;
+    let month_start = crate::utils::month_end(month)?.with_day(1).unwrap();
+    let mut data = Vec::with_capacity(rows.len());
+    for (name, envelope, cat_id, target_s, by) in rows {
+        let target = target_s
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid target '{}' for goal {}", target_s, name))?;
+        let (carry, funded, spent) = envelope_compute(conn, cat_id, month)?;
+        let saved = carry + funded - spent;
+        let remaining = (target - saved).max(Decimal::ZERO);
+        let progress = (saved * Decimal::from(100) / target).max(Decimal::ZERO);
+        let per_month = match by.as_deref() {
+            Some(d) if !remaining.is_zero() => {
+                let due = chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")?;
+                let months_left = (due.year() - month_start.year()) * 12 + due.month() as i32
+                    - month_start.month() as i32
+                    + 1;
+                if months_left > 0 {
+                    Some(fmt_amount(remaining / Decimal::from(months_left), &base))
+                } else {
+                    Some("overdue".to_string())
+                }
+            }
+            _ => None,
+        };
+        data.push(GoalStatusRow {
+            goal: name,
+            envelope,
+            saved: fmt_amount(saved, &base),
+            target: fmt_amount(target, &base),
+            remaining: fmt_amount(remaining, &base),
+            progress_percent: fmt_percent(progress),
+            by,
+            per_month,
+        });
+    }
+    Ok(data)
+}
+
+/// Category id of the envelope backing goal `name`.
+pub fn goal_category(conn: &Connection, name: &str) -> Result<i64> {
+    conn.query_row(
+        "SELECT category_id FROM goals WHERE name=?1",
+        params![name],
+        |r| r.get(0),
+    )
+    .optional()?
+    .with_context(|| format!("Goal '{}' not found", name))
+}
@@ -4,52 +4,1084 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::{apply_import_rules, id_for_category, parse_date, parse_decimal};
-use anyhow::{Context, Result, anyhow};
-use csv::ReaderBuilder;
-use rusqlite::{Connection, params};
-use std::collections::{HashMap, hash_map::Entry};
+use crate::commands::categories::{CapBreach, cap_breach};
+use crate::utils::{
+    apply_import_rules, ensure_period_open, id_for_category, import_skip_rule, maybe_print_json,
+    parse_date, parse_decimal, pretty_table,
+};
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use calamine::{Data, DataType, Reader, open_workbook_auto};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+use encoding_rs::Encoding;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+use std::collections::{BTreeSet, HashMap, hash_map::Entry};
 
 pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
         Some(("transactions", sub)) => import_transactions(conn, sub),
+        Some(("resume", sub)) => resume(conn, sub),
+        Some(("profile", sub)) => profile(conn, sub),
+        Some(("mcc-map", sub)) => mcc_map(conn, sub),
+        Some(("pdf", sub)) => crate::commands::pdf::handle(conn, sub),
         _ => Ok(()),
     }
 }
 
+/// How to read a bank's CSV dialect; saved under a name with `import profile add`.
+#[derive(Debug, Clone)]
+pub struct CsvFormat {
+    pub delimiter: u8,
+    pub decimal_comma: bool,
+    pub encoding: &'static Encoding,
+    /// Header of a column holding outflows as positive numbers.
+    pub debit_column: Option<String>,
+    /// Header of a column holding inflows as positive numbers.
+    pub credit_column: Option<String>,
+    /// Flip every amount, for exports that list purchases as positive.
+    pub invert_sign: bool,
+    /// Header of the bank's own category or MCC, looked up in `import mcc-map`.
+    pub bank_category_column: Option<String>,
+    /// Header of the bank's running balance, checked after the import.
+    pub balance_column: Option<String>,
+    /// (header, key) of columns kept as transaction metadata; not saved in profiles.
+    pub meta_columns: Vec<(String, String)>,
+    /// Rows dated before this are dropped; not saved in profiles.
+    pub skip_before: Option<NaiveDate>,
+    /// Rows whose payee matches are dropped; not saved in profiles.
+    pub skip_payee: Option<Regex>,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        CsvFormat {
+            delimiter: b',',
+            decimal_comma: false,
+            encoding: encoding_rs::UTF_8,
+            debit_column: None,
+            credit_column: None,
+            invert_sign: false,
+            bank_category_column: None,
+            balance_column: None,
+            meta_columns: Vec::new(),
+            skip_before: None,
+            skip_payee: None,
+        }
+    }
+}
+
+impl CsvFormat {
+    /// Options from the command line on top of `--profile`, if one is given.
+    pub fn from_matches(conn: &Connection, sub: &clap::ArgMatches) -> Result<Self> {
+        let mut format = match sub.try_get_one::<String>("profile").ok().flatten() {
+            Some(name) => load_profile(conn, name.trim())?,
+            None => CsvFormat::default(),
+        };
+        if sub.get_flag("decimal-comma") || sub.get_flag("no-decimal-comma") {
+            format.decimal_comma = sub.get_flag("decimal-comma");
+        }
+        if sub.get_flag("invert-sign") || sub.get_flag("no-invert-sign") {
+            format.invert_sign = sub.get_flag("invert-sign");
+        }
+        if let Some(raw) = sub.get_one::<String>("delimiter") {
+            format.delimiter = parse_delimiter(raw)?;
+        }
+        if let Some(label) = sub.get_one::<String>("encoding") {
+            format.encoding = parse_encoding(label)?;
+        }
+        if let Some(col) = sub.get_one::<String>("debit-column") {
+            format.debit_column = Some(col.trim().to_string());
+        }
+        if let Some(col) = sub.get_one::<String>("credit-column") {
+            format.credit_column = Some(col.trim().to_string());
+        }
+        if let Some(col) = sub.get_one::<String>("bank-category-column") {
+            format.bank_category_column = Some(col.trim().to_string());
+        }
+        if let Some(col) = sub.get_one::<String>("balance-column") {
+            format.balance_column = Some(col.trim().to_string());
+        }
+        if let Ok(Some(cols)) = sub.try_get_many::<String>("meta-column") {
+            for col in cols {
+                let (header, key) = col.split_once('=').unwrap_or((col, col));
+                format
+                    .meta_columns
+                    .push((header.trim().to_string(), key.trim().to_string()));
+            }
+        }
+        if let Ok(Some(date)) = sub.try_get_one::<String>("skip-before") {
+            format.skip_before = Some(parse_date(date.trim()).context("Invalid --skip-before")?);
+        }
+        if let Ok(Some(pattern)) = sub.try_get_one::<String>("skip-payee-regex") {
+            format.skip_payee = Some(
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid --skip-payee-regex '{}'", pattern))?,
+            );
+        }
+        Ok(format)
+    }
+
+    /// Decode raw file bytes; a byte-order mark wins over the configured encoding
+    /// and is stripped.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String> {
+        let (text, used, had_errors) = self.encoding.decode(bytes);
+        if had_errors {
+            bail!(
+                "File is not valid {}; pass --encoding (e.g. windows-1252)",
+                used.name()
+            );
+        }
+        Ok(text.into_owned())
+    }
+
+    /// Parse an amount cell, turning `1.234,56` into `1234.56` with --decimal-comma.
+    pub fn parse_amount(&self, raw: &str) -> Result<Decimal> {
+        if self.decimal_comma {
+            let normalized: String = raw
+                .chars()
+                .filter(|c| *c != '.' && *c != ' ' && *c != '\u{a0}')
+                .map(|c| if c == ',' { '.' } else { c })
+                .collect();
+            parse_decimal(&normalized)
+        } else {
+            parse_decimal(raw)
+        }
+    }
+}
+
+fn parse_delimiter(raw: &str) -> Result<u8> {
+    Ok(match raw {
+        "tab" | "\\t" => b'\t',
+        s if s.len() == 1 && s.is_ascii() => s.as_bytes()[0],
+        other => bail!(
+            "Delimiter must be one ASCII character or 'tab', got '{}'",
+            other
+        ),
+    })
+}
+
+fn parse_encoding(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+        .with_context(|| format!("Unknown encoding '{}'", label.trim()))
+}
+
+fn load_profile(conn: &Connection, name: &str) -> Result<CsvFormat> {
+    let (delimiter, encoding, mut format) = conn
+        .query_row(
+            "SELECT delimiter, encoding, decimal_comma, debit_column, credit_column, invert_sign,
+                    bank_category_column, balance_column
+             FROM import_profiles WHERE name=?1",
+            params![name],
+            |r| {
+                let format = CsvFormat {
+                    decimal_comma: r.get(2)?,
+                    debit_column: r.get(3)?,
+                    credit_column: r.get(4)?,
+                    invert_sign: r.get(5)?,
+                    bank_category_column: r.get(6)?,
+                    balance_column: r.get(7)?,
+                    ..CsvFormat::default()
+                };
+                Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, format))
+            },
+        )
+        .optional()?
+        .with_context(|| format!("Import profile '{}' not found", name))?;
+    format.delimiter = parse_delimiter(&delimiter)?;
+    format.encoding = parse_encoding(&encoding)?;
+    Ok(format)
+}
+
+fn profile(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let format = CsvFormat::from_matches(conn, sub)?;
+            let delimiter = match format.delimiter {
+                b'\t' => "tab".to_string(),
+                d => (d as char).to_string(),
+            };
+            conn.execute(
+                "INSERT INTO import_profiles(name, delimiter, decimal_comma, encoding, debit_column,
+                     credit_column, invert_sign, bank_category_column, balance_column)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(name) DO UPDATE SET delimiter=excluded.delimiter,
+                     decimal_comma=excluded.decimal_comma, encoding=excluded.encoding,
+                     debit_column=excluded.debit_column, credit_column=excluded.credit_column,
+                     invert_sign=excluded.invert_sign,
+                     bank_category_column=excluded.bank_category_column,
+                     balance_column=excluded.balance_column",
+                params![
+                    name,
+                    delimiter,
+                    format.decimal_comma,
+                    format.encoding.name(),
+                    format.debit_column,
+                    format.credit_column,
+                    format.invert_sign,
+                    format.bank_category_column,
+                    format.balance_column
+                ],
+            )?;
+            println!("Saved import profile '{}'", name);
+        }
+        Some(("list", _)) => {
+            let mut stmt = conn.prepare(
+                "SELECT name, delimiter, decimal_comma, encoding, debit_column, credit_column, invert_sign,
+                        bank_category_column, balance_column
+                 FROM import_profiles ORDER BY name",
+            )?;
+            let rows = stmt
+                .query_map([], |r| {
+                    let flag = |b: bool| if b { "yes" } else { "" }.to_string();
+                    Ok(vec![
+                        r.get::<_, String>(0)?,
+                        r.get::<_, String>(1)?,
+                        flag(r.get(2)?),
+                        r.get::<_, String>(3)?,
+                        r.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                        r.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                        flag(r.get(6)?),
+                        r.get::<_, Option<String>>(7)?.unwrap_or_default(),
+                        r.get::<_, Option<String>>(8)?.unwrap_or_default(),
+                    ])
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            println!(
+                "{}",
+                pretty_table(
+                    &[
+                        "Name",
+                        "Delimiter",
+                        "Decimal comma",
+                        "Encoding",
+                        "Debit column",
+                        "Credit column",
+                        "Invert sign",
+                        "Bank category column",
+                        "Balance column"
+                    ],
+                    rows
+                )
+            );
+        }
+        Some(("rm", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            if conn.execute("DELETE FROM import_profiles WHERE name=?1", params![name])? == 0 {
+                bail!("Import profile '{}' not found", name);
+            }
+            println!("Removed import profile '{}'", name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Category mapped from a bank category or MCC with `import mcc-map set`.
+pub fn mapped_category(conn: &Connection, code: &str) -> Result<Option<i64>> {
+    Ok(conn
+        .prepare_cached("SELECT category_id FROM mcc_map WHERE code=?1")?
+        .query_row(params![code.trim()], |r| r.get(0))
+        .optional()?)
+}
+
+fn mcc_map(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("set", sub)) => {
+            let code = sub.get_one::<String>("code").unwrap().trim();
+            let category = sub.get_one::<String>("category").unwrap().trim();
+            if code.is_empty() {
+                bail!("Bank category or MCC must not be empty");
+            }
+            let cat_id = id_for_category(conn, category)?;
+            conn.execute(
+                "INSERT INTO mcc_map(code, category_id) VALUES (?1, ?2)
+                 ON CONFLICT(code) DO UPDATE SET category_id=excluded.category_id",
+                params![code, cat_id],
+            )?;
+            println!("Mapped '{}' to {}", code, category);
+        }
+        Some(("list", _)) => {
+            let mut stmt = conn.prepare(
+                "SELECT m.code, c.name FROM mcc_map m JOIN categories c ON c.id = m.category_id
+                 ORDER BY m.code",
+            )?;
+            let rows = stmt
+                .query_map([], |r| Ok(vec![r.get(0)?, r.get(1)?]))?
+                .collect::<rusqlite::Result<Vec<Vec<String>>>>()?;
+            println!(
+                "{}",
+                pretty_table(&["Bank category / MCC", "Category"], rows)
+            );
+        }
+        Some(("rm", sub)) => {
+            let code = sub.get_one::<String>("code").unwrap().trim();
+            if conn.execute("DELETE FROM mcc_map WHERE code=?1", params![code])? == 0 {
+                bail!("No mapping for '{}'", code);
+            }
+            println!("Removed mapping for '{}'", code);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Position of each field in a row. Headers naming `date`, `payee` and `account`
+/// are matched by name, in any order and with extra columns ignored; otherwise the
+/// documented `date,payee,amount,category,account,currency,note` order applies.
+/// Sales tax columns (`tax_amount` or `tax_rate`, and `tax_code`), `project` and
+/// location columns (`location`, `latitude`/`lat`, `longitude`/`lon`) are only read by
+/// name.
+struct Columns {
+    date: usize,
+    payee: usize,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    bank_category: Option<usize>,
+    balance: Option<usize>,
+    category: Option<usize>,
+    account: usize,
+    currency: Option<usize>,
+    note: Option<usize>,
+    tax_amount: Option<usize>,
+    tax_rate: Option<usize>,
+    tax_code: Option<usize>,
+    project: Option<usize>,
+    location: Option<usize>,
+    latitude: Option<usize>,
+    longitude: Option<usize>,
+    /// Position and metadata key of each `--meta-column`.
+    meta: Vec<(usize, String)>,
+}
+
+impl Columns {
+    fn resolve(headers: &StringRecord, format: &CsvFormat) -> Result<Self> {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+        };
+        let named = |name: &Option<String>| -> Result<Option<usize>> {
+            name.as_deref()
+                .map(|n| find(n).with_context(|| format!("Column '{}' not in the CSV header", n)))
+                .transpose()
+        };
+        let (debit, credit) = (named(&format.debit_column)?, named(&format.credit_column)?);
+        let bank_category = named(&format.bank_category_column)?;
+        let balance = named(&format.balance_column)?;
+        let meta = format
+            .meta_columns
+            .iter()
+            .map(|(header, key)| {
+                let idx = find(header)
+                    .with_context(|| format!("Column '{}' not in the CSV header", header))?;
+                Ok((idx, key.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let cols = match (find("date"), find("payee"), find("account")) {
+            (Some(date), Some(payee), Some(account)) => Columns {
+                date,
+                payee,
+                amount: find("amount"),
+                debit,
+                credit,
+                bank_category,
+                balance,
+                category: find("category"),
+                account,
+                currency: find("currency"),
+                note: find("note"),
+                tax_amount: find("tax_amount"),
+                tax_rate: find("tax_rate"),
+                tax_code: find("tax_code"),
+                project: find("project"),
+                location: find("location"),
+                latitude: find("latitude").or(find("lat")),
+                longitude: find("longitude").or(find("lon")),
+                meta,
+            },
+            _ => Columns {
+                date: 0,
+                payee: 1,
+                amount: Some(2),
+                debit,
+                credit,
+                bank_category,
+                balance,
+                category: Some(3),
+                account: 4,
+                currency: Some(5),
+                note: Some(6),
+                tax_amount: None,
+                tax_rate: None,
+                tax_code: None,
+                project: None,
+                location: None,
+                latitude: None,
+                longitude: None,
+                meta,
+            },
+        };
+        if cols.amount.is_none() && cols.debit.is_none() && cols.credit.is_none() {
+            bail!(
+                "CSV has no amount column; pass --debit-column/--credit-column for split amounts"
+            );
+        }
+        Ok(cols)
+    }
+
+    /// Signed amount of `rec`: credit minus debit when split columns are
+    /// configured, otherwise the amount column; flipped with `invert_sign`.
+    fn amount(&self, rec: &StringRecord, format: &CsvFormat, payee: &str) -> Result<Decimal> {
+        let cell = |idx: usize| rec.get(idx).unwrap_or("").trim();
+        let parse = |raw: &str| {
+            format
+                .parse_amount(raw)
+                .with_context(|| format!("Invalid amount '{}' for {}", raw, payee))
+        };
+        let amount = if self.debit.is_some() || self.credit.is_some() {
+            let debit = self.debit.map(cell).filter(|s| !s.is_empty());
+            let credit = self.credit.map(cell).filter(|s| !s.is_empty());
+            if debit.is_none() && credit.is_none() {
+                bail!("No debit or credit amount for {}", payee);
+            }
+            let debit = debit.map(parse).transpose()?.unwrap_or_default();
+            let credit = credit.map(parse).transpose()?.unwrap_or_default();
+            credit.abs() - debit.abs()
+        } else {
+            let raw = self
+                .amount
+                .and_then(|idx| rec.get(idx))
+                .context("amount missing")?
+                .trim();
+            parse(raw)?
+        };
+        Ok(if format.invert_sign { -amount } else { amount })
+    }
+}
+
+/// Every `--path`, with glob patterns such as `exports/*.csv` expanded in name order.
+pub fn expand_paths<'a>(patterns: impl Iterator<Item = &'a String>) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.trim();
+        if !pattern.contains(['*', '?', '[']) {
+            paths.push(pattern.to_string());
+            continue;
+        }
+        let mut matched: Vec<String> = glob::glob(pattern)
+            .with_context(|| format!("Invalid glob pattern '{}'", pattern))?
+            .map(|entry| entry.map(|p| p.display().to_string()))
+            .collect::<Result<_, _>>()?;
+        if matched.is_empty() {
+            bail!("No files match '{}'", pattern);
+        }
+        matched.sort();
+        paths.extend(matched);
+    }
+    let mut seen = std::collections::HashSet::new();
+    paths.retain(|p| seen.insert(p.clone()));
+    Ok(paths)
+}
+
+/// Header and data rows of a worksheet as text, so they go through the same parsing
+/// as CSV cells. `sheet` is a 1-based index or a sheet name; the first sheet by
+/// default. Date cells become `YYYY-MM-DD` and blank rows are skipped. Number cells
+/// already hold the exact value, so with `format.decimal_comma` they are written with a
+/// comma decimal point; [`CsvFormat::parse_amount`] then reads them back unchanged.
+pub fn read_sheet(
+    path: &str,
+    sheet: Option<&str>,
+    format: &CsvFormat,
+) -> Result<(StringRecord, Vec<StringRecord>)> {
+    let mut workbook =
+        open_workbook_auto(path).with_context(|| format!("Open workbook {}", path))?;
+    let names = workbook.sheet_names();
+    let name = match sheet.map(str::trim) {
+        None => names.first().cloned(),
+        Some(s) => match s.parse::<usize>() {
+            Ok(n) if n >= 1 => names.get(n - 1).cloned(),
+            _ => names.iter().find(|n| n.as_str() == s).cloned(),
+        },
+    }
+    .with_context(|| {
+        format!(
+            "Sheet '{}' not found in {}; sheets: {}",
+            sheet.unwrap_or("1"),
+            path,
+            names.join(", ")
+        )
+    })?;
+    let range = workbook
+        .worksheet_range(&name)
+        .with_context(|| format!("Read sheet '{}' of {}", name, path))?;
+
+    let cell_text = |cell: &Data| match cell {
+        Data::DateTime(_) => cell.as_date().map(|d| d.to_string()).unwrap_or_default(),
+        Data::DateTimeIso(s) => s.chars().take(10).collect(),
+        Data::Float(_) if format.decimal_comma => cell.to_string().replace('.', ","),
+        other => other.to_string(),
+    };
+    let mut rows = range
+        .rows()
+        .map(|row| row.iter().map(cell_text).collect::<StringRecord>())
+        .filter(|rec| rec.iter().any(|c| !c.trim().is_empty()));
+    let headers = rows
+        .next()
+        .with_context(|| format!("Sheet '{}' of {} is empty", name, path))?;
+    Ok((headers, rows.collect()))
+}
+
+/// Account and category lookups shared across the files of one import.
+#[derive(Default)]
+pub struct ImportCaches {
+    accounts: HashMap<String, (i64, String)>,
+    categories: HashMap<String, i64>,
+    /// Last transaction id before the current file, when it is imported in chunks;
+    /// otherwise each call to [`import_records`] looks it up.
+    before: Option<i64>,
+}
+
 fn import_transactions(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
-    let path = sub.get_one::<String>("path").unwrap().trim();
-    let mut rdr = ReaderBuilder::new()
-        .has_headers(true)
-        .from_path(path)
-        .with_context(|| format!("Open CSV {}", path))?;
-
-    let tx = conn.transaction()?;
-    let mut account_cache: HashMap<String, (i64, String)> = HashMap::new();
-    let mut category_cache: HashMap<String, i64> = HashMap::new();
-
-    for result in rdr.records() {
-        let rec = result?;
-        let date_raw = rec.get(0).context("date missing")?.trim().to_string();
-        let mut payee = rec.get(1).context("payee missing")?.trim().to_string();
-        let amount_raw = rec.get(2).context("amount missing")?.trim().to_string();
-        let category = rec.get(3).unwrap_or("").trim().to_string();
-        let account = rec.get(4).context("account missing")?.trim().to_string();
-        let csv_currency = rec.get(5).unwrap_or("").trim();
-        let note = rec
-            .get(6)
-            .map(|s| s.trim())
+    let format = CsvFormat::from_matches(conn, sub)?;
+    let paths = expand_paths(sub.get_many::<String>("path").unwrap())?;
+    let sheet = sub.get_one::<String>("sheet").map(String::as_str);
+
+    if let Some(&batch_size) = sub.get_one::<u64>("batch-size") {
+        let id = start_batch(conn, &paths, &format, sheet, batch_size as usize)?;
+        println!(
+            "Import batch {}: committing every {} rows; if interrupted, continue with `import resume --batch {}`",
+            id, batch_size, id
+        );
+        let (paths, reports) = run_batch(conn, id)?;
+        let breaches = check_imported(conn, &paths, &reports)?;
+        let summary = summarize(conn, &reports)?;
+        return print_import(sub.get_flag("json"), &paths, &reports, &summary, &breaches);
+    }
+
+    let tx = conn.savepoint()?;
+    let mut caches = ImportCaches::default();
+    let mut reports = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let report = import_file(&tx, path, &format, sheet, &mut caches);
+        let report = if paths.len() > 1 {
+            report.with_context(|| format!("Import {} (nothing was imported)", path))?
+        } else {
+            report?
+        };
+        reports.push(report);
+    }
+    let breaches = check_imported(&tx, &paths, &reports)?;
+    if let (Some(first), true) = (breaches.first(), sub.get_flag("strict")) {
+        bail!("Nothing imported: {} (--strict)", first);
+    }
+    let summary = summarize(&tx, &reports)?;
+    tx.commit()?;
+    print_import(sub.get_flag("json"), &paths, &reports, &summary, &breaches)
+}
+
+/// Print running-balance warnings of the imported files and return the monthly caps
+/// their outflows broke. Checked once every file is in, so later files can fill
+/// earlier gaps.
+fn check_imported(
+    conn: &Connection,
+    paths: &[String],
+    reports: &[ImportReport],
+) -> Result<Vec<CapBreach>> {
+    for (path, report) in paths.iter().zip(reports) {
+        for warning in balance_warnings(conn, &report.balances)? {
+            eprintln!("Warning: {}: {}", path, warning);
+        }
+    }
+    let spent: BTreeSet<&(i64, String)> = reports.iter().flat_map(|r| &r.spent).collect();
+    let mut breaches = Vec::new();
+    for (category_id, month) in spent {
+        breaches.extend(cap_breach(conn, *category_id, month)?);
+    }
+    Ok(breaches)
+}
+
+fn print_import(
+    json: bool,
+    paths: &[String],
+    reports: &[ImportReport],
+    summary: &ImportSummary,
+    breaches: &[CapBreach],
+) -> Result<()> {
+    for breach in breaches {
+        eprintln!("Warning: {}", breach);
+    }
+    if maybe_print_json(json, false, std::slice::from_ref(summary))? {
+        return Ok(());
+    }
+    if let [report] = reports {
+        println!(
+            "Imported {} transactions from {}",
+            report.imported, paths[0]
+        );
+    } else {
+        let rows = paths
+            .iter()
+            .zip(reports)
+            .map(|(path, report)| vec![path.clone(), report.imported.to_string()])
+            .collect();
+        println!("{}", pretty_table(&["File", "Transactions"], rows));
+        println!(
+            "Imported {} transactions from {} files",
+            summary.imported,
+            paths.len()
+        );
+    }
+    print_summary(summary);
+    Ok(())
+}
+
+/// Payees listed under "top uncategorized" in the import summary.
+const TOP_UNCATEGORIZED: usize = 5;
+
+/// Days apart the two legs of a transfer between own accounts may be booked.
+const TRANSFER_WINDOW_DAYS: u64 = 3;
+
+/// What an import did, for spotting rule gaps right away.
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub skipped: usize,
+    pub categorized_from_file: usize,
+    pub categorized_by_mapping: usize,
+    pub categorized_by_rules: usize,
+    pub uncategorized: usize,
+    /// Payees of uncategorized rows, most rows first.
+    pub top_uncategorized: Vec<PayeeCount>,
+    /// Imported rows that look like one leg of a transfer between own accounts.
+    pub transfers: usize,
+    /// Imported rows that repeat a transaction stored before.
+    pub possible_duplicates: usize,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct PayeeCount {
+    pub payee: String,
+    pub rows: usize,
+}
+
+/// Sum up the reports of one import; transfers are looked up in `conn`, which must
+/// already hold the imported rows.
+pub fn summarize(conn: &Connection, reports: &[ImportReport]) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    let mut payees: HashMap<&str, usize> = HashMap::new();
+    for report in reports {
+        summary.imported += report.imported;
+        summary.skipped += report.skipped;
+        summary.categorized_from_file += report.from_file;
+        summary.categorized_by_mapping += report.mapped;
+        summary.categorized_by_rules += report.by_rules;
+        summary.possible_duplicates += report.duplicates;
+        for (payee, rows) in &report.uncategorized {
+            summary.uncategorized += rows;
+            *payees.entry(payee).or_default() += rows;
+        }
+        summary.transfers += transfer_legs(conn, &report.ids)?;
+    }
+    let mut payees: Vec<PayeeCount> = payees
+        .into_iter()
+        .map(|(payee, rows)| PayeeCount {
+            payee: payee.to_string(),
+            rows,
+        })
+        .collect();
+    payees.sort_by(|a, b| b.rows.cmp(&a.rows).then_with(|| a.payee.cmp(&b.payee)));
+    payees.truncate(TOP_UNCATEGORIZED);
+    summary.top_uncategorized = payees;
+    Ok(summary)
+}
+
+/// How many of `ids` have the opposite amount in the same currency in another account
+/// within [`TRANSFER_WINDOW_DAYS`].
+fn transfer_legs(conn: &Connection, ids: &[i64]) -> Result<usize> {
+    let mut row = conn.prepare_cached(
+        "SELECT account_id, date, amount, currency FROM transactions WHERE id=?1",
+    )?;
+    let mut others = conn.prepare_cached(
+        "SELECT amount FROM transactions
+         WHERE account_id != ?1 AND currency=?2 AND date BETWEEN ?3 AND ?4",
+    )?;
+    let window = chrono::Days::new(TRANSFER_WINDOW_DAYS);
+    let mut legs = 0;
+    for id in ids {
+        let (account_id, date, amount, currency): (i64, String, String, String) = row
+            .query_row(params![id], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
+            })?;
+        let date = parse_date(&date)?;
+        let amount = parse_decimal(&amount)?;
+        if amount.is_zero() {
+            continue;
+        }
+        let amounts = others
+            .query_map(
+                params![
+                    account_id,
+                    currency,
+                    (date - window).to_string(),
+                    (date + window).to_string()
+                ],
+                |r| r.get::<_, String>(0),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if amounts
+            .iter()
+            .any(|a| a.trim().parse::<Decimal>().is_ok_and(|a| a == -amount))
+        {
+            legs += 1;
+        }
+    }
+    Ok(legs)
+}
+
+/// The summary as a table, with the payees rules could pick up next.
+pub fn print_summary(summary: &ImportSummary) {
+    let rows = [
+        ("Imported", summary.imported),
+        ("Skipped", summary.skipped),
+        ("Categorized from file", summary.categorized_from_file),
+        (
+            "Categorized by bank category",
+            summary.categorized_by_mapping,
+        ),
+        ("Categorized by rules", summary.categorized_by_rules),
+        ("Uncategorized", summary.uncategorized),
+        ("Transfers detected", summary.transfers),
+        ("Possible duplicates", summary.possible_duplicates),
+    ]
+    .into_iter()
+    .map(|(label, n)| vec![label.to_string(), n.to_string()])
+    .collect();
+    println!("{}", pretty_table(&["Import summary", "Rows"], rows));
+    if !summary.top_uncategorized.is_empty() {
+        let payees: Vec<String> = summary
+            .top_uncategorized
+            .iter()
+            .map(|p| format!("{} ({})", p.payee, p.rows))
+            .collect();
+        println!(
+            "Top uncategorized payees: {}; cover them with `rules add`",
+            payees.join(", ")
+        );
+    }
+    if summary.possible_duplicates > 0 {
+        println!("Possible duplicates were imported; review them with `report anomalies`");
+    }
+}
+
+/// Spreadsheet extensions read with calamine instead of the CSV reader.
+const SHEET_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+/// Import one CSV or spreadsheet inside the caller's savepoint.
+fn import_file(
+    tx: &Connection,
+    path: &str,
+    format: &CsvFormat,
+    sheet: Option<&str>,
+    caches: &mut ImportCaches,
+) -> Result<ImportReport> {
+    let (headers, records) = read_file(path, format, sheet)?;
+    import_records(tx, &headers, records, format, caches)
+}
+
+/// Header and rows of one CSV or spreadsheet.
+fn read_file(
+    path: &str,
+    format: &CsvFormat,
+    sheet: Option<&str>,
+) -> Result<(StringRecord, Vec<StringRecord>)> {
+    let is_sheet = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SHEET_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+    let (headers, records) = if is_sheet {
+        read_sheet(path, sheet, format)?
+    } else {
+        let bytes = std::fs::read(path).with_context(|| format!("Open CSV {}", path))?;
+        let text = format.decode(&bytes)?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .delimiter(format.delimiter)
+            .from_reader(text.as_bytes());
+        let headers = rdr.headers()?.clone();
+        let records = rdr.records().collect::<Result<Vec<_>, _>>()?;
+        (headers, records)
+    };
+    Ok((headers, records))
+}
+
+/// What `import resume` needs to read the files of a batch again: the parsed dialect
+/// and skip options, which may have come from a profile that has changed since.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedFormat {
+    delimiter: u8,
+    decimal_comma: bool,
+    encoding: String,
+    debit_column: Option<String>,
+    credit_column: Option<String>,
+    invert_sign: bool,
+    bank_category_column: Option<String>,
+    balance_column: Option<String>,
+    meta_columns: Vec<(String, String)>,
+    skip_before: Option<NaiveDate>,
+    skip_payee: Option<String>,
+}
+
+impl SavedFormat {
+    fn new(format: &CsvFormat) -> Self {
+        SavedFormat {
+            delimiter: format.delimiter,
+            decimal_comma: format.decimal_comma,
+            encoding: format.encoding.name().to_string(),
+            debit_column: format.debit_column.clone(),
+            credit_column: format.credit_column.clone(),
+            invert_sign: format.invert_sign,
+            bank_category_column: format.bank_category_column.clone(),
+            balance_column: format.balance_column.clone(),
+            meta_columns: format.meta_columns.clone(),
+            skip_before: format.skip_before,
+            skip_payee: format.skip_payee.as_ref().map(|re| re.as_str().to_string()),
+        }
+    }
+
+    fn into_format(self) -> Result<CsvFormat> {
+        Ok(CsvFormat {
+            delimiter: self.delimiter,
+            decimal_comma: self.decimal_comma,
+            encoding: parse_encoding(&self.encoding)?,
+            debit_column: self.debit_column,
+            credit_column: self.credit_column,
+            invert_sign: self.invert_sign,
+            bank_category_column: self.bank_category_column,
+            balance_column: self.balance_column,
+            meta_columns: self.meta_columns,
+            skip_before: self.skip_before,
+            skip_payee: self.skip_payee.as_deref().map(Regex::new).transpose()?,
+        })
+    }
+}
+
+/// Record an import of `paths` to be committed `batch_size` rows at a time and return
+/// its id; [`run_batch`] then imports it, and again after an interruption.
+pub fn start_batch(
+    conn: &Connection,
+    paths: &[String],
+    format: &CsvFormat,
+    sheet: Option<&str>,
+    batch_size: usize,
+) -> Result<i64> {
+    ensure!(batch_size > 0, "--batch-size must be at least 1");
+    let mut rows_total = 0;
+    for path in paths {
+        rows_total += read_file(path, format, sheet)?.1.len();
+    }
+    conn.execute(
+        "INSERT INTO import_batches(paths, sheet, format, batch_size, rows_total)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            serde_json::to_string(paths)?,
+            sheet,
+            serde_json::to_string(&SavedFormat::new(format))?,
+            batch_size as i64,
+            rows_total as i64
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Import the rows of batch `id` not committed yet, `batch_size` rows per savepoint.
+/// Each chunk's rows, with their rules applied, and the batch's progress are committed
+/// together, so after a crash or a failing row the batch continues right after the
+/// last committed row. Returns the files touched and what was imported from each.
+pub fn run_batch(conn: &mut Connection, id: i64) -> Result<(Vec<String>, Vec<ImportReport>)> {
+    let (paths, sheet, format, batch_size, rows_total, rows_done, file_before, finished) = conn
+        .query_row(
+            "SELECT paths, sheet, format, batch_size, rows_total, rows_done, file_before,
+                    finished_at IS NOT NULL
+             FROM import_batches WHERE id=?1",
+            params![id],
+            |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, Option<String>>(1)?,
+                    r.get::<_, String>(2)?,
+                    r.get::<_, i64>(3)? as usize,
+                    r.get::<_, i64>(4)? as usize,
+                    r.get::<_, i64>(5)? as usize,
+                    r.get::<_, i64>(6)?,
+                    r.get::<_, bool>(7)?,
+                ))
+            },
+        )
+        .optional()?
+        .with_context(|| format!("No import batch {}", id))?;
+    ensure!(!finished, "Import batch {} has already finished", id);
+    let paths: Vec<String> = serde_json::from_str(&paths)?;
+    let format = serde_json::from_str::<SavedFormat>(&format)?.into_format()?;
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let (headers, records) = read_file(&path, &format, sheet.as_deref())?;
+        files.push((path, headers, records));
+    }
+    let found: usize = files.iter().map(|(_, _, records)| records.len()).sum();
+    ensure!(
+        found == rows_total,
+        "The files of import batch {} now hold {} rows instead of {}; resuming would skip or repeat rows",
+        id,
+        found,
+        rows_total
+    );
+
+    let mut done = rows_done;
+    let mut caches = ImportCaches::default();
+    let mut touched = Vec::new();
+    let mut reports = Vec::new();
+    let mut offset = 0;
+    let result = (|| -> Result<()> {
+        for (path, headers, records) in &files {
+            let (start, end) = (offset, offset + records.len());
+            offset = end;
+            if done >= end {
+                continue;
+            }
+            // Duplicates are looked for among rows stored before this file, as in one go.
+            caches.before = Some(if done == start {
+                conn.query_row("SELECT COALESCE(MAX(id), 0) FROM transactions", [], |r| {
+                    r.get(0)
+                })?
+            } else {
+                file_before
+            });
+            let mut report = ImportReport::default();
+            for chunk in records[done - start..].chunks(batch_size) {
+                let tx = conn.savepoint()?;
+                let part = import_records(&tx, headers, chunk.to_vec(), &format, &mut caches)
+                    .with_context(|| format!("Import {}", path))?;
+                tx.execute(
+                    "UPDATE import_batches SET rows_done=?1, file_before=?2 WHERE id=?3",
+                    params![(done + chunk.len()) as i64, caches.before, id],
+                )?;
+                tx.commit()?;
+                done += chunk.len();
+                report.absorb(part);
+            }
+            touched.push(path.clone());
+            reports.push(report);
+        }
+        Ok(())
+    })();
+    result.with_context(|| {
+        format!(
+            "Import batch {} stopped with {} of {} rows committed; fix the file and run `import resume --batch {}`",
+            id, done, rows_total, id
+        )
+    })?;
+    conn.execute(
+        "UPDATE import_batches SET finished_at=datetime('now') WHERE id=?1",
+        params![id],
+    )?;
+    Ok((touched, reports))
+}
+
+fn resume(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let id = *sub.get_one::<i64>("batch").unwrap();
+    let (paths, reports) = run_batch(conn, id)?;
+    let breaches = check_imported(conn, &paths, &reports)?;
+    let summary = summarize(conn, &reports)?;
+    print_import(sub.get_flag("json"), &paths, &reports, &summary, &breaches)
+}
+
+/// Validate and insert parsed rows: dates, closed periods, accounts, categories,
+/// currencies and import rules are checked exactly as for CSV files.
+pub fn import_records(
+    tx: &Connection,
+    headers: &StringRecord,
+    records: Vec<StringRecord>,
+    format: &CsvFormat,
+    caches: &mut ImportCaches,
+) -> Result<ImportReport> {
+    let cols = Columns::resolve(headers, format)?;
+    let mut report = ImportReport::default();
+    // Rows from earlier imports (or earlier files of this one) that a row may repeat.
+    let before: i64 = match caches.before {
+        Some(before) => before,
+        None => tx.query_row("SELECT COALESCE(MAX(id), 0) FROM transactions", [], |r| {
+            r.get(0)
+        })?,
+    };
+
+    let progress = if records.len() >= crate::progress::LARGE_IMPORT_ROWS {
+        crate::progress::bar(records.len(), "Importing")
+    } else {
+        indicatif::ProgressBar::hidden()
+    };
+    for rec in progress.wrap_iter(records.into_iter()) {
+        let date_raw = rec
+            .get(cols.date)
+            .context("date missing")?
+            .trim()
+            .to_string();
+        let mut payee = rec
+            .get(cols.payee)
+            .context("payee missing")?
+            .trim()
+            .to_string();
+        let optional = |idx: Option<usize>| idx.and_then(|i| rec.get(i)).unwrap_or("").trim();
+        let category = optional(cols.category).to_string();
+        let bank_category = optional(cols.bank_category).to_string();
+        let account = rec
+            .get(cols.account)
+            .context("account missing")?
+            .trim()
+            .to_string();
+        let csv_currency = optional(cols.currency);
+        let note = Some(optional(cols.note))
             .filter(|s| !s.is_empty())
             .map(|s| s.to_string());
 
+        // Noise rows such as "AVAILABLE BALANCE" often have no valid date or amount.
+        if format
+            .skip_payee
+            .as_ref()
+            .is_some_and(|re| re.is_match(&payee))
+            || import_skip_rule(tx, &payee, note.as_deref())?
+        {
+            report.skipped += 1;
+            continue;
+        }
         let date = parse_date(&date_raw)
             .with_context(|| format!("Invalid transaction date '{}'", date_raw))?;
-        let amount = parse_decimal(&amount_raw)
-            .with_context(|| format!("Invalid amount '{}' for {}", amount_raw, payee))?;
+        if format.skip_before.is_some_and(|before| date < before) {
+            report.skipped += 1;
+            continue;
+        }
+        ensure_period_open(tx, date)?;
+        let amount = cols.amount(&rec, format, &payee)?;
+        let bank_balance = match optional(cols.balance) {
+            "" => None,
+            raw => {
+                let balance = format
+                    .parse_amount(raw)
+                    .with_context(|| format!("Invalid balance '{}' for {}", raw, payee))?;
+                Some(if format.invert_sign {
+                    -balance
+                } else {
+                    balance
+                })
+            }
+        };
 
         let acct_id: i64;
         let account_currency: &str;
-        match account_cache.entry(account.clone()) {
+        match caches.accounts.entry(account.clone()) {
             Entry::Occupied(entry) => {
                 let (cached_id, cached_ccy) = entry.into_mut();
                 acct_id = *cached_id;
@@ -71,23 +1103,35 @@ fn import_transactions(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<
         let mut cat_id = if category.is_empty() {
             None
         } else {
-            let cat_id = match category_cache.entry(category.clone()) {
+            let cat_id = match caches.categories.entry(category.clone()) {
                 Entry::Occupied(entry) => *entry.get(),
                 Entry::Vacant(entry) => {
-                    let fetched = id_for_category(&tx, &category)?;
+                    let fetched = id_for_category(tx, &category)?;
                     *entry.insert(fetched)
                 }
             };
             Some(cat_id)
         };
+        let mut source = cat_id.map(|_| CategorySource::File);
+        if cat_id.is_none() && !bank_category.is_empty() {
+            cat_id = mapped_category(tx, &bank_category)?;
+            source = cat_id.map(|_| CategorySource::Mapping);
+        }
 
-        let (rule_cat, rewrite) = apply_import_rules(&tx, &payee, note.as_deref())?;
+        let (rule_cat, rewrite) = apply_import_rules(tx, &payee, note.as_deref())?;
         if cat_id.is_none() {
             cat_id = rule_cat;
+            source = cat_id.map(|_| CategorySource::Rule);
         }
         if let Some(newp) = rewrite.filter(|newp| newp != &payee) {
             payee = newp;
         }
+        match source {
+            Some(CategorySource::File) => report.from_file += 1,
+            Some(CategorySource::Mapping) => report.mapped += 1,
+            Some(CategorySource::Rule) => report.by_rules += 1,
+            None => *report.uncategorized.entry(payee.clone()).or_default() += 1,
+        }
         if !csv_currency.is_empty() && !csv_currency.eq_ignore_ascii_case(account_currency) {
             return Err(anyhow!(
                 "Currency '{}' does not match account '{}' currency '{}'",
@@ -97,6 +1141,9 @@ fn import_transactions(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<
             ));
         }
 
+        if is_duplicate(tx, acct_id, date, amount, &payee, before)? {
+            report.duplicates += 1;
+        }
         tx.execute(
             "INSERT INTO transactions(date, account_id, amount, payee, category_id, currency, note) \
              VALUES (?1,?2,?3,?4,?5,?6,?7)",
@@ -110,8 +1157,199 @@ fn import_transactions(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<
                 note.as_deref()
             ],
         )?;
+        let tx_id = tx.last_insert_rowid();
+        report.ids.push(tx_id);
+        let tax = crate::commands::sales_tax::tax_for(
+            amount,
+            Some(optional(cols.tax_amount)).filter(|s| !s.is_empty()),
+            Some(optional(cols.tax_rate)).filter(|s| !s.is_empty()),
+            account_currency,
+        )
+        .with_context(|| format!("Tax for {} on {}", payee, date))?;
+        let tax_code = Some(optional(cols.tax_code)).filter(|s| !s.is_empty());
+        if tax.is_some() || tax_code.is_some() {
+            tx.execute(
+                "UPDATE transactions SET tax_amount=?1, tax_code=?2 WHERE id=?3",
+                params![tax.map(|t| t.to_string()), tax_code, tx_id],
+            )?;
+        }
+        if let Some(project) = Some(optional(cols.project)).filter(|s| !s.is_empty()) {
+            tx.execute(
+                "UPDATE transactions SET project=?1 WHERE id=?2",
+                params![project, tx_id],
+            )?;
+        }
+        let place = Some(optional(cols.location)).filter(|s| !s.is_empty());
+        let coords = match (optional(cols.latitude), optional(cols.longitude)) {
+            ("", "") => None,
+            (lat, lon) => Some(
+                crate::commands::locations::parse_coords(&format!("{},{}", lat, lon))
+                    .with_context(|| format!("Location of {} on {}", payee, date))?,
+            ),
+        };
+        if place.is_some() || coords.is_some() {
+            crate::commands::locations::set_location(tx, tx_id, place, coords)?;
+        }
+        for (idx, key) in &cols.meta {
+            let value = optional(Some(*idx));
+            if !value.is_empty() {
+                crate::commands::meta::set_meta(tx, tx_id, key, value)?;
+            }
+        }
+        if let (Some(id), true) = (cat_id, amount < Decimal::ZERO) {
+            report
+                .spent
+                .insert((id, crate::utils::periods().month_of(date)));
+        }
+        if let Some(balance) = bank_balance {
+            report.balances.push(BalanceRow {
+                account_id: acct_id,
+                account: account.clone(),
+                date,
+                amount,
+                balance,
+            });
+        }
+        report.imported += 1;
     }
-    tx.commit()?;
-    println!("Imported transactions from {}", path);
-    Ok(())
+    Ok(report)
+}
+
+/// Where an imported row's category came from.
+enum CategorySource {
+    File,
+    Mapping,
+    Rule,
+}
+
+/// Whether a transaction with the same account, date, amount and payee was stored
+/// before this file (id up to `before`).
+fn is_duplicate(
+    tx: &Connection,
+    account_id: i64,
+    date: NaiveDate,
+    amount: Decimal,
+    payee: &str,
+    before: i64,
+) -> Result<bool> {
+    let mut stmt = tx.prepare_cached(
+        "SELECT amount FROM transactions
+         WHERE account_id=?1 AND date=?2 AND payee=?3 COLLATE NOCASE AND id <= ?4",
+    )?;
+    let amounts = stmt
+        .query_map(params![account_id, date.to_string(), payee, before], |r| {
+            r.get::<_, String>(0)
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(amounts
+        .iter()
+        .any(|a| a.trim().parse::<Decimal>().is_ok_and(|a| a == amount)))
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Rows dropped by skip rules, `--skip-before` or `--skip-payee-regex`.
+    pub skipped: usize,
+    /// IDs of the inserted transactions.
+    pub ids: Vec<i64>,
+    /// Rows categorized by the file's category column.
+    pub from_file: usize,
+    /// Rows categorized through `import mcc-map`.
+    pub mapped: usize,
+    /// Rows categorized by import rules.
+    pub by_rules: usize,
+    /// Uncategorized rows per payee.
+    pub uncategorized: HashMap<String, usize>,
+    /// Rows matching a transaction that was already stored; imported anyway.
+    pub duplicates: usize,
+    /// Rows that carried the bank's running balance, in file order.
+    pub balances: Vec<BalanceRow>,
+    /// (category, YYYY-MM) pairs that received outflows, for monthly cap checks.
+    pub spent: BTreeSet<(i64, String)>,
+}
+
+impl ImportReport {
+    /// Add the counts of another part of the same file.
+    fn absorb(&mut self, other: ImportReport) {
+        self.imported += other.imported;
+        self.skipped += other.skipped;
+        self.ids.extend(other.ids);
+        self.from_file += other.from_file;
+        self.mapped += other.mapped;
+        self.by_rules += other.by_rules;
+        for (payee, rows) in other.uncategorized {
+            *self.uncategorized.entry(payee).or_default() += rows;
+        }
+        self.duplicates += other.duplicates;
+        self.balances.extend(other.balances);
+        self.spent.extend(other.spent);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceRow {
+    pub account_id: i64,
+    pub account: String,
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    pub balance: Decimal,
+}
+
+/// Compare one file's running balances with its amounts and with the stored
+/// account: a step that does not match its row's amount points at a missing or
+/// duplicated row in the file, and a final balance that differs from the sum of
+/// the account's transactions up to that date points at gaps in the database.
+/// Files may list rows newest first; the order is detected per account.
+pub fn balance_warnings(conn: &Connection, rows: &[BalanceRow]) -> Result<Vec<String>> {
+    let mut by_account: Vec<(i64, Vec<&BalanceRow>)> = Vec::new();
+    for row in rows {
+        match by_account.iter_mut().find(|(id, _)| *id == row.account_id) {
+            Some((_, list)) => list.push(row),
+            None => by_account.push((row.account_id, vec![row])),
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for (account_id, mut list) in by_account {
+        if list.first().map(|r| r.date) > list.last().map(|r| r.date) {
+            list.reverse();
+        }
+        for pair in list.windows(2) {
+            let expected = pair[0].balance + pair[1].amount;
+            if expected != pair[1].balance {
+                warnings.push(format!(
+                    "{}: balance on {} is {:.2} but {:.2} {:+.2} gives {:.2}; a row may be missing or duplicated",
+                    pair[1].account,
+                    pair[1].date,
+                    pair[1].balance,
+                    pair[0].balance,
+                    pair[1].amount,
+                    expected
+                ));
+            }
+        }
+        let Some(last) = list.last() else {
+            continue;
+        };
+        let mut stmt = conn
+            .prepare_cached("SELECT amount FROM transactions WHERE account_id=?1 AND date<=?2")?;
+        let mut computed = Decimal::ZERO;
+        let mut amounts = stmt.query(params![account_id, last.date.to_string()])?;
+        while let Some(r) = amounts.next()? {
+            let raw: String = r.get(0)?;
+            computed += parse_decimal(raw.trim())?;
+        }
+        if computed != last.balance {
+            warnings.push(format!(
+                "{}: bank balance on {} is {:.2} but moneyclip has {:.2} (difference {:+.2}); check for missing or duplicate transactions",
+                last.account,
+                last.date,
+                last.balance,
+                computed,
+                last.balance - computed
+            ));
+        }
+    }
+    Ok(warnings)
 }
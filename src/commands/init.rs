@@ -0,0 +1,255 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! `init`: create the database and, in a terminal, walk through first-time setup.
+
+use crate::interactive;
+use crate::utils::{get_base_currency, get_setting, set_base_currency, set_setting};
+use anyhow::{Result, bail, ensure};
+use rusqlite::{Connection, params};
+
+/// Template name, starter accounts as (name, type), and categories.
+type Template = (
+    &'static str,
+    &'static [(&'static str, &'static str)],
+    &'static [&'static str],
+);
+
+/// Starter data for `init --template`.
+pub const TEMPLATES: &[Template] = &[
+    (
+        "basic",
+        &[("Checking", "bank"), ("Cash", "cash")],
+        &[
+            "Groceries",
+            "Dining",
+            "Transport",
+            "Utilities",
+            "Rent",
+            "Shopping",
+            "Health",
+            "Entertainment",
+            "Salary",
+        ],
+    ),
+    (
+        "family",
+        &[
+            ("Joint Checking", "bank"),
+            ("Savings", "bank"),
+            ("Credit Card", "card"),
+            ("Cash", "cash"),
+        ],
+        &[
+            "Groceries",
+            "Dining",
+            "Transport",
+            "Utilities",
+            "Rent",
+            "Shopping",
+            "Health",
+            "Childcare",
+            "Education",
+            "Insurance",
+            "Entertainment",
+            "Salary",
+        ],
+    ),
+    (
+        "freelancer",
+        &[
+            ("Business Checking", "bank"),
+            ("Personal Checking", "bank"),
+            ("Tax Savings", "bank"),
+            ("Credit Card", "card"),
+        ],
+        &[
+            "Client Income",
+            "Software",
+            "Office",
+            "Taxes",
+            "Groceries",
+            "Dining",
+            "Transport",
+            "Utilities",
+            "Rent",
+            "Health",
+        ],
+    ),
+];
+
+pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
+    println!(
+        "Database initialized at {}",
+        crate::db::db_path()?.display()
+    );
+    let sub = m.subcommand_matches("init").unwrap();
+    let stored = get_setting(conn, "base_currency")?;
+    let base = match sub.get_one::<String>("base") {
+        Some(ccy) => Some(ccy.trim().to_uppercase()),
+        // The variable seeds a new ledger; it never replaces a stored base.
+        None => crate::utils::env_base_currency().filter(|_| stored.is_none()),
+    };
+    let template = sub.get_one::<String>("template").map(|s| s.trim());
+    if let Some(ccy) = &base {
+        set_base(conn, ccy)?;
+    }
+    if let Some(name) = template {
+        apply_and_report(conn, name)?;
+    }
+    if interactive::enabled(m) {
+        wizard(conn, base.is_none(), template.is_none())?;
+    }
+    Ok(())
+}
+
+/// Make `ccy` the base currency. A new ledger just stores it; an existing one goes
+/// through [`super::fx::change_base`], which stamps budgets saved without a currency
+/// with the old base and records the change in `fx base-history`.
+pub fn set_base(conn: &mut Connection, ccy: &str) -> Result<()> {
+    validate_currency(ccy)?;
+    match get_setting(conn, "base_currency")? {
+        None => {
+            set_base_currency(conn, ccy)?;
+            println!("Base currency set to {}", ccy);
+        }
+        Some(current) if current.trim().eq_ignore_ascii_case(ccy) => {
+            println!("Base currency is already {}", ccy);
+        }
+        Some(_) => {
+            let change = super::fx::change_base(conn, ccy, None, false)?;
+            println!("Base currency set to {} (was {})", ccy, change.from);
+            if change.goals > 0 {
+                println!(
+                    "{} goal target(s) still hold {} amounts; `fx set-base --migrate` converts them",
+                    change.goals, change.from
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_currency(ccy: &str) -> Result<()> {
+    ensure!(
+        ccy.len() == 3 && ccy.chars().all(|c| c.is_ascii_alphabetic()),
+        "Currency must be a 3-letter code like USD"
+    );
+    Ok(())
+}
+
+/// Ask for whatever the flags did not settle: base currency, a starter template,
+/// extra accounts, the online FX and price sources, and a first CSV import.
+fn wizard(conn: &mut Connection, ask_base: bool, ask_template: bool) -> Result<()> {
+    if ask_base {
+        let current = get_base_currency(conn)?;
+        let ccy = interactive::text("Base currency", Some(&current), &[], validate_currency)?
+            .to_uppercase();
+        set_base(conn, &ccy)?;
+    }
+
+    if ask_template {
+        let mut names = vec![String::from("none")];
+        names.extend(TEMPLATES.iter().map(|(n, ..)| n.to_string()));
+        let pick = interactive::choose("Starter accounts and categories", &names, Some(0))?;
+        if pick > 0 {
+            apply_and_report(conn, &names[pick])?;
+        }
+    }
+
+    let base = get_base_currency(conn)?;
+    loop {
+        let name = interactive::text("Add an account (blank to continue)", Some(""), &[], |_| {
+            Ok(())
+        })?;
+        if name.is_empty() {
+            break;
+        }
+        let types: Vec<String> = ["bank", "card", "cash", "broker", "loan"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let typ = &types[interactive::choose("Type", &types, Some(0))?];
+        let ccy =
+            interactive::text("Currency", Some(&base), &[], validate_currency)?.to_uppercase();
+        conn.execute(
+            "INSERT INTO accounts(name, type, currency) VALUES (?1, ?2, ?3)",
+            params![name, typ, ccy],
+        )?;
+        println!("Added account '{}' ({}, {})", name, typ, ccy);
+    }
+
+    let fx = interactive::confirm("Fetch FX rates online from frankfurter.dev?", true)?;
+    set_setting(conn, "fx_provider", if fx { "frankfurter" } else { "off" })?;
+    let prices = interactive::confirm("Fetch live asset prices from Yahoo Finance?", true)?;
+    set_setting(conn, "price_provider", if prices { "yahoo" } else { "off" })?;
+
+    let path = interactive::text("CSV to import now (blank to skip)", Some(""), &[], |s| {
+        ensure!(
+            s.is_empty() || std::path::Path::new(s).exists(),
+            "No such file"
+        );
+        Ok(())
+    })?;
+    if !path.is_empty() {
+        let matches = crate::cli::build_cli().try_get_matches_from([
+            "moneyclip",
+            "import",
+            "transactions",
+            "--path",
+            path.as_str(),
+        ])?;
+        if let Err(err) = super::dispatch(conn, &matches) {
+            eprintln!("Warning: import failed: {:#}", err);
+            eprintln!("Fix the file and rerun `moneyclip import transactions --path ...`");
+        }
+    }
+    println!("Setup complete; try `moneyclip report balances`");
+    Ok(())
+}
+
+fn apply_and_report(conn: &Connection, name: &str) -> Result<()> {
+    let (accounts, categories) = apply_template(conn, name)?;
+    println!(
+        "Template '{}': added {} accounts and {} categories",
+        name,
+        accounts.len(),
+        categories.len()
+    );
+    Ok(())
+}
+
+/// Create the template's accounts (in the base currency) and categories, skipping
+/// names that already exist. Returns the accounts and categories actually added.
+pub fn apply_template(conn: &Connection, name: &str) -> Result<(Vec<String>, Vec<String>)> {
+    let Some((_, accounts, categories)) = TEMPLATES.iter().find(|(n, ..)| *n == name) else {
+        let known: Vec<&str> = TEMPLATES.iter().map(|(n, ..)| *n).collect();
+        bail!("Unknown template '{}'; known: {}", name, known.join(", "));
+    };
+    let base = get_base_currency(conn)?;
+    let mut added_accounts = Vec::new();
+    for (account, typ) in accounts.iter() {
+        let added = conn.execute(
+            "INSERT INTO accounts(name, type, currency)
+             SELECT ?1, ?2, ?3 WHERE NOT EXISTS(SELECT 1 FROM accounts WHERE name=?1)",
+            params![account, typ, base],
+        )?;
+        if added > 0 {
+            added_accounts.push(account.to_string());
+        }
+    }
+    let mut added_categories = Vec::new();
+    for category in categories.iter() {
+        if conn.execute(
+            "INSERT OR IGNORE INTO categories(name) VALUES (?1)",
+            params![category],
+        )? > 0
+        {
+            added_categories.push(category.to_string());
+        }
+    }
+    Ok((added_accounts, added_categories))
+}
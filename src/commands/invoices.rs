@@ -0,0 +1,338 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Invoices (`invoice`): money clients owe, from draft through sent to paid, where
+//! paying links the income transaction that settled it.
+
+use crate::models::InvoiceRow;
+use crate::utils::{
+    fmt_amount, fx_convert, get_base_currency, maybe_print_json, parse_amount, parse_date,
+    pretty_table,
+};
+use anyhow::{Context, Result, bail, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("create", sub)) => create(conn, sub)?,
+        Some(("list", sub)) => list(conn, sub)?,
+        Some(("sent", sub)) => {
+            let number = sub.get_one::<String>("number").unwrap().trim();
+            let date = match sub.get_one::<String>("date") {
+                Some(d) => parse_date(d.trim())?,
+                None => crate::utils::today(),
+            };
+            let status = status_of(conn, number)?;
+            ensure!(status != "paid", "Invoice {} is already paid", number);
+            conn.execute(
+                "UPDATE invoices SET status='sent', sent_on=?1 WHERE number=?2",
+                params![date.to_string(), number],
+            )?;
+            println!("Invoice {} marked sent on {}", number, date);
+        }
+        Some(("paid", sub)) => paid(conn, sub)?,
+        Some(("aging", sub)) => aging_report(conn, sub)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn status_of(conn: &Connection, number: &str) -> Result<String> {
+    conn.query_row(
+        "SELECT status FROM invoices WHERE number=?1",
+        params![number],
+        |r| r.get(0),
+    )
+    .optional()?
+    .with_context(|| format!("No invoice {}", number))
+}
+
+fn create(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let client = sub.get_one::<String>("client").unwrap().trim();
+    let amount = parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
+    ensure!(amount > Decimal::ZERO, "Invoice amount must be positive");
+    let currency = match sub.get_one::<String>("currency") {
+        Some(c) => c.trim().to_uppercase(),
+        None => get_base_currency(conn)?,
+    };
+    let issued = match sub.get_one::<String>("issued") {
+        Some(d) => parse_date(d.trim())?,
+        None => crate::utils::today(),
+    };
+    let due = parse_date(sub.get_one::<String>("due").unwrap().trim())?;
+    ensure!(
+        due >= issued,
+        "Due date {} is before the issue date {}",
+        due,
+        issued
+    );
+    let number = match sub.get_one::<String>("number") {
+        Some(n) => n.trim().to_string(),
+        None => {
+            let next: i64 =
+                conn.query_row("SELECT COALESCE(MAX(id), 0) + 1 FROM invoices", [], |r| {
+                    r.get(0)
+                })?;
+            format!("INV-{:04}", next)
+        }
+    };
+    conn.execute(
+        "INSERT INTO invoices(number, client, amount, currency, issued, due, note)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            number,
+            client,
+            amount.to_string(),
+            currency,
+            issued.to_string(),
+            due.to_string(),
+            sub.get_one::<String>("note").map(|n| n.trim())
+        ],
+    )
+    .with_context(|| format!("Create invoice {}", number))?;
+    println!(
+        "Created invoice {} to {} for {} {}, due {}",
+        number,
+        client,
+        fmt_amount(amount, &currency),
+        currency,
+        due
+    );
+    Ok(())
+}
+
+fn list(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let status = sub.get_one::<String>("status").map(|s| s.as_str());
+    let client = sub.get_one::<String>("client").map(|c| c.trim());
+    let mut stmt = conn.prepare(
+        "SELECT number, client, issued, due, amount, currency, status, paid_on
+         FROM invoices
+         WHERE (?1 IS NULL OR (?1='open' AND status<>'paid') OR status=?1)
+           AND (?2 IS NULL OR client=?2 COLLATE NOCASE)
+         ORDER BY issued, number",
+    )?;
+    let rows = stmt.query_map(params![status, client], |r| {
+        let amount: String = r.get(4)?;
+        let currency: String = r.get(5)?;
+        Ok(InvoiceRow {
+            number: r.get(0)?,
+            client: r.get(1)?,
+            issued: r.get(2)?,
+            due: r.get(3)?,
+            amount: amount
+                .parse::<Decimal>()
+                .map(|a| fmt_amount(a, &currency))
+                .unwrap_or(amount),
+            currency,
+            status: r.get(6)?,
+            paid_on: r.get(7)?,
+        })
+    })?;
+    let data = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+        println!(
+            "{}",
+            pretty_table(
+                &[
+                    "Number", "Client", "Issued", "Due", "Amount", "CCY", "Status", "Paid"
+                ],
+                data.into_iter().map(InvoiceRow::cells).collect()
+            )
+        );
+    }
+    Ok(())
+}
+
+fn paid(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let number = sub.get_one::<String>("number").unwrap().trim();
+    ensure!(
+        status_of(conn, number)? != "paid",
+        "Invoice {} is already paid",
+        number
+    );
+    let tx_id = match sub.get_one::<i64>("tx") {
+        Some(id) => *id,
+        None => {
+            let date = parse_date(sub.get_one::<String>("date").unwrap().trim())?;
+            let amount: String = conn.query_row(
+                "SELECT amount FROM invoices WHERE number=?1",
+                params![number],
+                |r| r.get(0),
+            )?;
+            let amount = match sub.get_one::<String>("amount") {
+                Some(a) => parse_amount(a.trim())?,
+                None => amount.parse::<Decimal>().with_context(|| {
+                    format!("Invalid amount '{}' on invoice {}", amount, number)
+                })?,
+            };
+            let account = sub.get_one::<String>("account").map(|a| a.trim());
+            crate::commands::transactions::find_by_amount(conn, date, amount, account)?
+        }
+    };
+    let paid_on = mark_paid(conn, number, tx_id)?;
+    println!(
+        "Invoice {} paid on {} (transaction {})",
+        number, paid_on, tx_id
+    );
+    Ok(())
+}
+
+/// Link invoice `number` to the income transaction `tx_id` and mark it paid on that
+/// transaction's date, which is returned.
+pub fn mark_paid(conn: &Connection, number: &str, tx_id: i64) -> Result<String> {
+    let (date, amount): (String, String) = conn
+        .query_row(
+            "SELECT date, amount FROM transactions WHERE id=?1",
+            params![tx_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?
+        .with_context(|| format!("No transaction {}", tx_id))?;
+    if amount.trim().starts_with('-') {
+        bail!(
+            "Transaction {} is an outflow of {}; link the income that paid the invoice",
+            tx_id,
+            amount
+        );
+    }
+    let taken: Option<String> = conn
+        .query_row(
+            "SELECT number FROM invoices WHERE paid_tx_id=?1",
+            params![tx_id],
+            |r| r.get(0),
+        )
+        .optional()?;
+    if let Some(other) = taken {
+        bail!("Transaction {} already paid invoice {}", tx_id, other);
+    }
+    conn.execute(
+        "UPDATE invoices SET status='paid', paid_on=?1, paid_tx_id=?2 WHERE number=?3",
+        params![date, tx_id, number],
+    )?;
+    Ok(date)
+}
+
+/// Sent, unpaid invoices of one client by how far past due they are, in the base
+/// currency.
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
+pub struct Aging {
+    pub client: String,
+    pub current: Decimal,
+    pub days_1_30: Decimal,
+    pub days_31_60: Decimal,
+    pub days_61_90: Decimal,
+    pub over_90: Decimal,
+    pub total: Decimal,
+}
+
+impl Aging {
+    fn add(&mut self, days_overdue: i64, amount: Decimal) {
+        let bucket = match days_overdue {
+            i64::MIN..=0 => &mut self.current,
+            1..=30 => &mut self.days_1_30,
+            31..=60 => &mut self.days_31_60,
+            61..=90 => &mut self.days_61_90,
+            _ => &mut self.over_90,
+        };
+        *bucket += amount;
+        self.total += amount;
+    }
+}
+
+fn aging_report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let as_of = match sub.get_one::<String>("as-of") {
+        Some(d) => parse_date(d.trim())?,
+        None => crate::utils::today(),
+    };
+    let data = aging(conn, as_of)?;
+    if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+        return Ok(());
+    }
+    if data.is_empty() {
+        crate::utils::emit("No outstanding invoices");
+        return Ok(());
+    }
+    let base = get_base_currency(conn)?;
+    let mut totals = Aging {
+        client: "Total".to_string(),
+        ..Aging::default()
+    };
+    let mut rows: Vec<Vec<String>> = data.iter().map(|a| aging_cells(a, &base)).collect();
+    for a in &data {
+        totals.current += a.current;
+        totals.days_1_30 += a.days_1_30;
+        totals.days_31_60 += a.days_31_60;
+        totals.days_61_90 += a.days_61_90;
+        totals.over_90 += a.over_90;
+        totals.total += a.total;
+    }
+    rows.push(aging_cells(&totals, &base));
+    crate::utils::emit(pretty_table(
+        &[
+            "Client",
+            "Current",
+            "1-30",
+            "31-60",
+            "61-90",
+            "90+",
+            &format!("Total ({})", base),
+        ],
+        rows,
+    ));
+    Ok(())
+}
+
+fn aging_cells(a: &Aging, base: &str) -> Vec<String> {
+    vec![
+        a.client.clone(),
+        fmt_amount(a.current, base),
+        fmt_amount(a.days_1_30, base),
+        fmt_amount(a.days_31_60, base),
+        fmt_amount(a.days_61_90, base),
+        fmt_amount(a.over_90, base),
+        fmt_amount(a.total, base),
+    ]
+}
+
+/// Receivables aging as of `as_of`: every sent invoice not yet paid, converted to the
+/// base currency at `as_of` and bucketed by days past its due date, one row per
+/// client in name order. Drafts have not been billed yet and are left out.
+pub fn aging(conn: &Connection, as_of: NaiveDate) -> Result<Vec<Aging>> {
+    let base = get_base_currency(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT client, amount, currency, due FROM invoices
+         WHERE status='sent' AND issued<=?1
+         ORDER BY client COLLATE NOCASE, due",
+    )?;
+    let mut rows = stmt.query(params![as_of.to_string()])?;
+    let mut out: Vec<Aging> = Vec::new();
+    while let Some(r) = rows.next()? {
+        let client: String = r.get(0)?;
+        let amount: String = r.get(1)?;
+        let currency: String = r.get(2)?;
+        let due: String = r.get(3)?;
+        let amount = amount
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid invoice amount '{}'", amount))?;
+        let amount = fx_convert(conn, as_of, amount, &currency, &base)?;
+        let due = NaiveDate::parse_from_str(&due, "%Y-%m-%d")?;
+        let overdue = (as_of - due).num_days();
+        match out.last_mut() {
+            Some(a) if a.client.eq_ignore_ascii_case(&client) => a.add(overdue, amount),
+            _ => {
+                let mut a = Aging {
+                    client,
+                    ..Aging::default()
+                };
+                a.add(overdue, amount);
+                out.push(a);
+            }
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,240 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Jars: named sub-balances inside one account, such as each child's allowance, that
+//! do not show up as accounts of their own. A jar's balance is what was moved into it
+//! (`jar move`) less what was moved out or spent from it (`tx add --jar`); the rest of
+//! the account's balance is unassigned.
+
+use crate::utils::{
+    fmt_amount, id_for_account, maybe_print_json, parse_amount, parse_date, pretty_table, today,
+};
+use anyhow::{Context, Result, bail, ensure};
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+
+/// Label of the part of an account's balance that is in none of its jars.
+pub const UNASSIGNED: &str = "(unassigned)";
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => {
+            let account = sub.get_one::<String>("account").unwrap().trim();
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            ensure!(
+                !name.is_empty() && name != UNASSIGNED,
+                "Invalid jar name '{}'",
+                name
+            );
+            let account_id = id_for_account(conn, account)?;
+            conn.execute(
+                "INSERT INTO jars(account_id, name) VALUES (?1, ?2)",
+                params![account_id, name],
+            )
+            .with_context(|| format!("Add jar '{}' to '{}'", name, account))?;
+            println!("Added jar '{}' to account '{}'", name, account);
+        }
+        Some(("list", sub)) => {
+            let account = sub.get_one::<String>("account").map(|a| a.trim());
+            let data = jar_balances(conn, account)?;
+            if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                return Ok(());
+            }
+            if data.is_empty() {
+                println!("No jars; add one with `jar add --account ACCOUNT --name NAME`");
+                return Ok(());
+            }
+            let rows = data
+                .iter()
+                .map(|j| {
+                    vec![
+                        j.account.clone(),
+                        j.jar.clone(),
+                        j.currency.clone(),
+                        fmt_amount(j.balance, &j.currency),
+                    ]
+                })
+                .collect();
+            println!(
+                "{}",
+                pretty_table(&["Account", "Jar", "CCY", "Balance"], rows)
+            );
+        }
+        Some(("move", sub)) => {
+            let account = sub.get_one::<String>("account").unwrap().trim();
+            let account_id = id_for_account(conn, account)?;
+            let from = sub.get_one::<String>("from").map(|j| j.trim());
+            let to = sub.get_one::<String>("to").map(|j| j.trim());
+            let amount = parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
+            let date = match sub.get_one::<String>("date") {
+                Some(d) => parse_date(d.trim())?,
+                None => today(),
+            };
+            let note = sub.get_one::<String>("note").map(|n| n.trim());
+            let from_id = from.map(|j| jar_id(conn, account_id, j)).transpose()?;
+            let to_id = to.map(|j| jar_id(conn, account_id, j)).transpose()?;
+            move_funds(conn, from_id, to_id, amount, date, note)?;
+            println!(
+                "Moved {} from {} to {} in '{}'",
+                amount,
+                from.unwrap_or(UNASSIGNED),
+                to.unwrap_or(UNASSIGNED),
+                account
+            );
+        }
+        Some(("rm", sub)) => {
+            let account = sub.get_one::<String>("account").unwrap().trim();
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let id = jar_id(conn, id_for_account(conn, account)?, name)?;
+            let balance = jar_balance(conn, id)?;
+            ensure!(
+                balance.is_zero(),
+                "Jar '{}' still holds {}; move it out first",
+                name,
+                balance
+            );
+            // Moves and spending keep their amounts; the other side of each stays put.
+            conn.execute("DELETE FROM jars WHERE id=?1", params![id])?;
+            println!("Removed jar '{}' from account '{}'", name, account);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The id of jar `name` in account `account_id`.
+pub fn jar_id(conn: &Connection, account_id: i64, name: &str) -> Result<i64> {
+    let id = conn
+        .query_row(
+            "SELECT id FROM jars WHERE account_id=?1 AND name=?2 COLLATE NOCASE",
+            params![account_id, name],
+            |r| r.get(0),
+        )
+        .optional()?;
+    match id {
+        Some(id) => Ok(id),
+        None => bail!("No jar '{}' in this account; see `jar list`", name),
+    }
+}
+
+/// Move `amount` between two jars of the same account, or between a jar and the
+/// account's unassigned balance (`None`). Taking more than a jar holds is refused.
+pub fn move_funds(
+    conn: &Connection,
+    from: Option<i64>,
+    to: Option<i64>,
+    amount: Decimal,
+    date: chrono::NaiveDate,
+    note: Option<&str>,
+) -> Result<()> {
+    ensure!(amount > Decimal::ZERO, "Amount to move must be positive");
+    ensure!(from.is_some() || to.is_some(), "Give --from, --to or both");
+    ensure!(from != to, "--from and --to are the same jar");
+    if let Some(from) = from {
+        let held = jar_balance(conn, from)?;
+        ensure!(
+            held >= amount,
+            "Jar holds only {}, cannot move {}",
+            held,
+            amount
+        );
+    }
+    conn.execute(
+        "INSERT INTO jar_moves(date, from_jar_id, to_jar_id, amount, note)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![date.to_string(), from, to, amount.to_string(), note],
+    )?;
+    Ok(())
+}
+
+/// What jar `id` holds: moves in, less moves out, plus transactions booked to it.
+pub fn jar_balance(conn: &Connection, id: i64) -> Result<Decimal> {
+    let mut stmt = conn.prepare(
+        "SELECT amount FROM jar_moves WHERE to_jar_id=?1
+         UNION ALL SELECT '-' || amount FROM jar_moves WHERE from_jar_id=?1
+         UNION ALL SELECT amount FROM transactions WHERE jar_id=?1",
+    )?;
+    let amounts = stmt
+        .query_map(params![id], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut total = Decimal::ZERO;
+    for amount in amounts {
+        total += amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in jar {}", amount, id))?;
+    }
+    Ok(total)
+}
+
+/// One jar's balance, or an account's unassigned remainder, in the account currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct JarBalance {
+    pub account: String,
+    pub jar: String,
+    pub currency: String,
+    pub balance: Decimal,
+}
+
+/// Balances of every jar (of `account`, when given), each account's jars followed by
+/// its unassigned remainder.
+pub fn jar_balances(conn: &Connection, account: Option<&str>) -> Result<Vec<JarBalance>> {
+    let account_id = account.map(|a| id_for_account(conn, a)).transpose()?;
+    let mut stmt = conn.prepare(
+        "SELECT j.id, j.account_id, a.name, a.currency, j.name
+         FROM jars j JOIN accounts a ON a.id=j.account_id
+         WHERE ?1 IS NULL OR j.account_id=?1
+         ORDER BY a.name COLLATE NOCASE, j.name COLLATE NOCASE",
+    )?;
+    let jars = stmt
+        .query_map(params![account_id], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, i64>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+                r.get::<_, String>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut out = Vec::new();
+    let mut in_jars = Decimal::ZERO;
+    for (i, (id, account_id, account, currency, jar)) in jars.iter().enumerate() {
+        let balance = jar_balance(conn, *id)?;
+        in_jars += balance;
+        out.push(JarBalance {
+            account: account.clone(),
+            jar: jar.clone(),
+            currency: currency.clone(),
+            balance,
+        });
+        if jars.get(i + 1).is_none_or(|next| next.1 != *account_id) {
+            out.push(JarBalance {
+                account: account.clone(),
+                jar: UNASSIGNED.to_string(),
+                currency: currency.clone(),
+                balance: account_balance(conn, *account_id)? - in_jars,
+            });
+            in_jars = Decimal::ZERO;
+        }
+    }
+    Ok(out)
+}
+
+fn account_balance(conn: &Connection, account_id: i64) -> Result<Decimal> {
+    let mut stmt = conn.prepare("SELECT amount FROM transactions WHERE account_id=?1")?;
+    let amounts = stmt
+        .query_map(params![account_id], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut total = Decimal::ZERO;
+    for amount in amounts {
+        total += amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amount))?;
+    }
+    Ok(total)
+}
@@ -0,0 +1,277 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Where money was spent: a place name (`City, Country`) and/or coordinates per
+//! transaction, place names looked up for bare coordinates (`tx geocode`), and
+//! `report spend-by-location`.
+
+use crate::logging::SendTraced;
+use crate::utils::{
+    fmt_amount, fmt_money, fx_convert_batch, get_base_currency, maybe_print_json, pretty_table,
+};
+use anyhow::{Context, Result, bail, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// `LAT,LON` in decimal degrees.
+pub fn parse_coords(raw: &str) -> Result<(f64, f64)> {
+    let (lat, lon) = raw
+        .split_once(',')
+        .with_context(|| format!("Invalid coordinates '{}', expected LAT,LON", raw))?;
+    let lat: f64 = lat
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid latitude '{}'", lat.trim()))?;
+    let lon: f64 = lon
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid longitude '{}'", lon.trim()))?;
+    ensure!(
+        (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon),
+        "Coordinates {},{} are out of range",
+        lat,
+        lon
+    );
+    Ok((lat, lon))
+}
+
+/// Store a place name and/or coordinates on transaction `tx_id`.
+pub fn set_location(
+    conn: &Connection,
+    tx_id: i64,
+    place: Option<&str>,
+    coords: Option<(f64, f64)>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET location=COALESCE(?1, location),
+             latitude=COALESCE(?2, latitude), longitude=COALESCE(?3, longitude)
+         WHERE id=?4",
+        params![place, coords.map(|c| c.0), coords.map(|c| c.1), tx_id],
+    )?;
+    Ok(())
+}
+
+/// Spending at one place, in its original currencies and in the base currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct LocationSpend {
+    pub location: String,
+    pub transactions: usize,
+    pub original: BTreeMap<String, Decimal>,
+    pub base: Decimal,
+}
+
+pub fn spend_by_location_report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let by = sub.get_one::<String>("by").unwrap();
+    let (from, to) = crate::cli::date_range(sub)?;
+    let data = spend_by_location(conn, by, from, to)?;
+    if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+        return Ok(());
+    }
+    if data.is_empty() {
+        crate::utils::emit(
+            "No spending with a location; add --location to tx add or import a location column",
+        );
+        return Ok(());
+    }
+    let base = get_base_currency(conn)?;
+    let rows = data
+        .iter()
+        .map(|l| {
+            vec![
+                l.location.clone(),
+                l.transactions.to_string(),
+                l.original
+                    .iter()
+                    .map(|(ccy, amount)| fmt_money(amount, ccy))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                fmt_amount(l.base, &base),
+            ]
+        })
+        .collect();
+    crate::utils::emit(pretty_table(
+        &["Location", "Tx", "Spent", &format!("Spent ({})", base)],
+        rows,
+    ));
+    Ok(())
+}
+
+/// Outflows with a location, grouped by the full place name (`place`), its first part
+/// (`city`) or its last part (`country`), largest base-currency total first. Rows with
+/// coordinates but no place name are grouped by coordinates rounded to 0.01°.
+pub fn spend_by_location(
+    conn: &Connection,
+    by: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<LocationSpend>> {
+    let base = get_base_currency(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT location, latitude, longitude, date, amount, currency FROM transactions
+         WHERE (location IS NOT NULL OR latitude IS NOT NULL) AND amount < 0
+           AND (?1 IS NULL OR date>=?1) AND (?2 IS NULL OR date<=?2)",
+    )?;
+    type Row = (
+        Option<String>,
+        Option<f64>,
+        Option<f64>,
+        String,
+        String,
+        String,
+    );
+    let rows = stmt
+        .query_map(
+            params![from.map(|d| d.to_string()), to.map(|d| d.to_string())],
+            |r| {
+                Ok((
+                    r.get(0)?,
+                    r.get(1)?,
+                    r.get(2)?,
+                    r.get(3)?,
+                    r.get(4)?,
+                    r.get(5)?,
+                ))
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<Row>>>()?;
+    let mut items = Vec::with_capacity(rows.len());
+    for (.., date, amount, currency) in &rows {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        items.push((date, -amount, currency.as_str()));
+    }
+    let converted = fx_convert_batch(conn, &items, &base)?;
+    let mut groups: BTreeMap<String, LocationSpend> = BTreeMap::new();
+    for (((place, lat, lon, ..), (_, amount, currency)), in_base) in
+        rows.iter().zip(&items).zip(converted)
+    {
+        let key = match (place.as_deref().map(str::trim), lat, lon) {
+            (Some(place), ..) if !place.is_empty() => location_key(place, by)?,
+            (_, Some(lat), Some(lon)) => format!("{:.2},{:.2}", lat, lon),
+            _ => continue,
+        };
+        let group = groups
+            .entry(key.to_lowercase())
+            .or_insert_with(|| LocationSpend {
+                location: key,
+                transactions: 0,
+                original: BTreeMap::new(),
+                base: Decimal::ZERO,
+            });
+        group.transactions += 1;
+        *group.original.entry(currency.to_string()).or_default() += *amount;
+        group.base += in_base;
+    }
+    let mut out: Vec<LocationSpend> = groups.into_values().collect();
+    out.sort_by(|a, b| {
+        b.base
+            .cmp(&a.base)
+            .then_with(|| a.location.cmp(&b.location))
+    });
+    Ok(out)
+}
+
+fn location_key(place: &str, by: &str) -> Result<String> {
+    let mut parts = place.split(',').map(str::trim).filter(|p| !p.is_empty());
+    Ok(match by {
+        "place" => place.to_string(),
+        "city" => parts.next().unwrap_or(place).to_string(),
+        "country" => parts.next_back().unwrap_or(place).to_string(),
+        other => bail!("Unknown grouping '{}'; use place, city or country", other),
+    })
+}
+
+/// `tx geocode`: name the places of transactions that only have coordinates, one
+/// OpenStreetMap lookup per distinct spot (0.01°), at most one request a second.
+pub fn geocode(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let limit = *sub.get_one::<usize>("limit").unwrap();
+    let dry_run = sub.get_flag("dry-run");
+    let mut stmt = conn.prepare(
+        "SELECT ROUND(latitude, 2), ROUND(longitude, 2), COUNT(*) FROM transactions
+         WHERE location IS NULL AND latitude IS NOT NULL AND longitude IS NOT NULL
+         GROUP BY 1, 2 ORDER BY 3 DESC LIMIT ?1",
+    )?;
+    let spots = stmt
+        .query_map(params![limit as i64], |r| {
+            Ok((
+                r.get::<_, f64>(0)?,
+                r.get::<_, f64>(1)?,
+                r.get::<_, i64>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if spots.is_empty() {
+        println!("No transactions with coordinates but no location");
+        return Ok(());
+    }
+    let mut named = 0;
+    for (i, (lat, lon, count)) in spots.iter().enumerate() {
+        if i > 0 {
+            // Nominatim's usage policy allows one request per second.
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+        let place = match reverse_geocode(*lat, *lon) {
+            Ok(Some(place)) => place,
+            Ok(None) => {
+                eprintln!("No place found at {:.2},{:.2}", lat, lon);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Lookup of {:.2},{:.2} failed: {:#}", lat, lon, e);
+                continue;
+            }
+        };
+        println!(
+            "{:.2},{:.2} -> {} ({} transactions)",
+            lat, lon, place, count
+        );
+        if !dry_run {
+            conn.execute(
+                "UPDATE transactions SET location=?1
+                 WHERE location IS NULL AND ROUND(latitude, 2)=?2 AND ROUND(longitude, 2)=?3",
+                params![place, lat, lon],
+            )?;
+        }
+        named += 1;
+    }
+    if dry_run {
+        println!("Dry run: {} of {} spots would be named", named, spots.len());
+    } else {
+        println!("Named {} of {} spots", named, spots.len());
+    }
+    Ok(())
+}
+
+/// `City, Country` at the coordinates from OpenStreetMap's Nominatim.
+fn reverse_geocode(lat: f64, lon: f64) -> Result<Option<String>> {
+    let mut url = reqwest::Url::parse("https://nominatim.openstreetmap.org/reverse")?;
+    url.query_pairs_mut()
+        .append_pair("format", "jsonv2")
+        .append_pair("zoom", "10")
+        .append_pair("lat", &lat.to_string())
+        .append_pair("lon", &lon.to_string());
+    let body: serde_json::Value = crate::utils::http_client()?
+        .get(url)
+        .send_traced()
+        .and_then(|r| r.error_for_status())?
+        .json()?;
+    let address = &body["address"];
+    let city = ["city", "town", "village", "municipality", "county", "state"]
+        .iter()
+        .find_map(|k| address[k].as_str());
+    let country = address["country"].as_str();
+    Ok(match (city, country) {
+        (Some(city), Some(country)) => Some(format!("{}, {}", city, country)),
+        (None, Some(country)) => Some(country.to_string()),
+        (Some(city), None) => Some(city.to_string()),
+        (None, None) => None,
+    })
+}
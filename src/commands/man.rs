@@ -0,0 +1,43 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! `man --out DIR`: roff man pages for packagers, one per command
+//! (`moneyclip.1`, `moneyclip-portfolio-price-fetch.1`, ...). Hidden from help.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::Command;
+
+pub fn handle(m: &clap::ArgMatches) -> Result<()> {
+    let out = Path::new(m.get_one::<String>("out").unwrap().trim());
+    std::fs::create_dir_all(out).with_context(|| format!("creating {}", out.display()))?;
+    let mut cli = crate::cli::build_cli();
+    cli.build();
+    let written = write_pages(&cli, "moneyclip", out)?;
+    println!("Wrote {} man pages to {}", written, out.display());
+    Ok(())
+}
+
+/// Writes `cmd` as `<name>.1` and recurses into its visible subcommands, naming
+/// each page after its full path joined with dashes.
+fn write_pages(cmd: &Command, name: &str, out: &Path) -> Result<usize> {
+    let path = out.join(format!("{name}.1"));
+    let mut buf = Vec::new();
+    clap_mangen::Man::new(cmd.clone())
+        .title(name)
+        .source(concat!("moneyclip ", env!("CARGO_PKG_VERSION")))
+        .render(&mut buf)?;
+    crate::utils::write_atomic(&path, &buf)?;
+    let mut written = 1;
+    for sub in cmd.get_subcommands() {
+        if sub.is_hide_set() || sub.get_name() == "help" {
+            continue;
+        }
+        written += write_pages(sub, &format!("{name}-{}", sub.get_name()), out)?;
+    }
+    Ok(written)
+}
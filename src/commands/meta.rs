@@ -0,0 +1,96 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Free-form key/value metadata on transactions (`tx meta`), such as bank reference
+//! numbers or card last-4, kept from imports with `--meta-column`.
+
+use crate::utils::{maybe_print_json, pretty_table};
+use anyhow::{Result, bail, ensure};
+use rusqlite::{Connection, params};
+use std::collections::BTreeMap;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("set", sub)) => {
+            let id = *sub.get_one::<i64>("id").unwrap();
+            let key = sub.get_one::<String>("key").unwrap().trim();
+            let value = sub.get_one::<String>("value").unwrap().trim();
+            set_meta(conn, id, key, value)?;
+            println!("Set {}={} on transaction {}", key, value, id);
+        }
+        Some(("get", sub)) => {
+            let id = *sub.get_one::<i64>("id").unwrap();
+            let mut meta = get_meta(conn, id)?;
+            if let Some(key) = sub.get_one::<String>("key") {
+                let key = key.trim();
+                let Some(value) = meta.remove(key) else {
+                    bail!("Transaction {} has no '{}'", id, key);
+                };
+                println!("{}", value);
+                return Ok(());
+            }
+            if !maybe_print_json(sub.get_flag("json"), false, std::slice::from_ref(&meta))? {
+                let rows = meta.into_iter().map(|(k, v)| vec![k, v]).collect();
+                println!("{}", pretty_table(&["Key", "Value"], rows));
+            }
+        }
+        Some(("rm", sub)) => {
+            let id = *sub.get_one::<i64>("id").unwrap();
+            let key = sub.get_one::<String>("key").unwrap().trim();
+            if conn.execute(
+                "DELETE FROM transaction_meta WHERE tx_id=?1 AND key=?2",
+                params![id, key],
+            )? == 0
+            {
+                bail!("Transaction {} has no '{}'", id, key);
+            }
+            println!("Removed '{}' from transaction {}", key, id);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Set (or replace) `key` on transaction `tx_id`.
+pub fn set_meta(conn: &Connection, tx_id: i64, key: &str, value: &str) -> Result<()> {
+    ensure!(!key.is_empty(), "Metadata key must not be empty");
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM transactions WHERE id=?1)",
+        params![tx_id],
+        |r| r.get(0),
+    )?;
+    ensure!(exists, "No transaction {}", tx_id);
+    conn.execute(
+        "INSERT INTO transaction_meta(tx_id, key, value) VALUES (?1, ?2, ?3)
+         ON CONFLICT(tx_id, key) DO UPDATE SET value=excluded.value",
+        params![tx_id, key, value],
+    )?;
+    Ok(())
+}
+
+/// All metadata of transaction `tx_id`, by key.
+pub fn get_meta(conn: &Connection, tx_id: i64) -> Result<BTreeMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM transaction_meta WHERE tx_id=?1")?;
+    let rows = stmt.query_map(params![tx_id], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// `--meta KEY=VALUE` (or just `KEY`, for transactions that have it) as a condition
+/// on `t.id` and its parameters.
+pub fn meta_filter(raw: &str) -> (String, Vec<String>) {
+    match raw.split_once('=') {
+        Some((key, value)) => (
+            " AND EXISTS (SELECT 1 FROM transaction_meta m WHERE m.tx_id=t.id AND m.key=? AND m.value=?)"
+                .to_string(),
+            vec![key.trim().to_string(), value.trim().to_string()],
+        ),
+        None => (
+            " AND EXISTS (SELECT 1 FROM transaction_meta m WHERE m.tx_id=t.id AND m.key=?)"
+                .to_string(),
+            vec![raw.trim().to_string()],
+        ),
+    }
+}
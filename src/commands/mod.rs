@@ -5,14 +5,140 @@
 // LICENSE file in the root directory of this source tree.
 
 pub mod accounts;
+pub mod assets;
+pub mod batch;
 pub mod budgets;
 pub mod categories;
+pub mod close;
+pub mod config;
+pub mod cpi;
+pub mod database;
+pub mod digest;
 pub mod doctor;
 pub mod envelopes;
 pub mod exporter;
 pub mod fx;
+pub mod goals;
 pub mod importer;
+pub mod init;
+pub mod invoices;
+pub mod jars;
+pub mod locations;
+pub mod man;
+pub mod meta;
+pub mod pdf;
+pub mod periods;
+pub mod plans;
 pub mod portfolio;
+pub mod price_alerts;
+pub mod projects;
+pub mod recurring;
+pub mod reimbursements;
 pub mod reports;
 pub mod rules;
+pub mod sales_tax;
+pub mod scenarios;
+pub mod sheets;
+pub mod snapshots;
+pub mod sync;
 pub mod transactions;
+pub mod trips;
+pub mod watchlist;
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+/// Run a parsed top-level command against `conn`.
+pub fn dispatch(conn: &mut Connection, matches: &clap::ArgMatches) -> Result<()> {
+    use crate::utils::OutputFormat;
+    let (_, leaf) = crate::cli::command_path(matches);
+    let format = match leaf.try_get_one::<String>("output-format") {
+        Ok(Some(f)) => OutputFormat::parse(f)?,
+        _ if matches.try_get_one::<bool>("markdown").ok().flatten() == Some(&true) => {
+            OutputFormat::Markdown
+        }
+        _ => OutputFormat::Table,
+    };
+    crate::utils::set_output_format(format);
+    let mut settings = crate::utils::settings();
+    let precision = &mut settings.precision;
+    if let Ok(Some(dp)) = matches.try_get_one::<u32>("money-dp") {
+        precision.money = *dp;
+    }
+    if let Ok(Some(dp)) = matches.try_get_one::<u32>("qty-dp") {
+        precision.quantity = *dp;
+    }
+    if let Ok(Some(mode)) = matches.try_get_one::<String>("rounding") {
+        precision.rounding = crate::utils::Rounding::parse(mode)?;
+    }
+    if matches.try_get_one::<bool>("symbols").ok().flatten() == Some(&true) {
+        precision.symbols = true;
+    }
+    // JSON amounts stay plain decimals whatever `currency_symbols` says.
+    let flag = |id: &str| leaf.try_get_one::<bool>(id).ok().flatten() == Some(&true);
+    if format == OutputFormat::Json || flag("json") || flag("jsonl") {
+        precision.symbols = false;
+    }
+    crate::progress::set_quiet(matches.try_get_one::<bool>("quiet").ok().flatten() == Some(&true));
+
+    // The overrides belong to this command only; batch lines and close-month steps
+    // dispatch again and start from the settings they were called with.
+    crate::utils::with_settings(settings, || run_with_output(conn, matches, leaf))
+}
+
+/// [`run`], writing what it prints to `--output` instead when the command has one.
+fn run_with_output(
+    conn: &mut Connection,
+    matches: &clap::ArgMatches,
+    leaf: &clap::ArgMatches,
+) -> Result<()> {
+    let Ok(Some(out)) = leaf.try_get_one::<String>("output") else {
+        return run(conn, matches);
+    };
+    crate::utils::start_capture();
+    let result = run(conn, matches);
+    let captured = crate::utils::take_capture();
+    result?;
+    let path = std::path::Path::new(out.trim());
+    crate::utils::write_atomic(path, captured.as_bytes())?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn run(conn: &mut Connection, matches: &clap::ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("init", _)) => init::handle(conn, matches)?,
+        Some(("account", sub)) => accounts::handle(conn, sub)?,
+        Some(("category", sub)) => categories::handle(conn, sub)?,
+        Some(("tx", sub)) => transactions::handle(conn, sub)?,
+        Some(("budget", sub)) => budgets::handle(conn, sub)?,
+        Some(("report", sub)) => reports::handle(conn, sub)?,
+        Some(("portfolio", sub)) => portfolio::handle(conn, sub)?,
+        Some(("import", sub)) => importer::handle(conn, sub)?,
+        Some(("export", sub)) => exporter::handle(conn, sub)?,
+        Some(("fx", sub)) => fx::handle(conn, sub)?,
+        Some(("cpi", sub)) => cpi::handle(conn, sub)?,
+        Some(("sync", sub)) => sync::handle(conn, sub)?,
+        Some(("man", sub)) => man::handle(sub)?,
+        Some(("config", sub)) => config::handle(conn, sub)?,
+        Some(("db", sub)) => database::handle(conn, sub)?,
+        Some(("batch", sub)) => batch::handle(conn, sub)?,
+        Some(("period", sub)) => periods::handle(conn, sub)?,
+        Some(("close-month", sub)) => close::handle(conn, sub)?,
+        Some(("doctor", sub)) => doctor::handle(conn, sub)?,
+        Some(("envelope", sub)) => envelopes::handle(conn, sub)?,
+        Some(("goal", sub)) => goals::handle(conn, sub)?,
+        Some(("plan", sub)) => plans::handle(conn, sub)?,
+        Some(("invoice", sub)) => invoices::handle(conn, sub)?,
+        Some(("trip", sub)) => trips::handle(conn, sub)?,
+        Some(("jar", sub)) => jars::handle(conn, sub)?,
+        Some(("asset", sub)) => assets::handle(conn, sub)?,
+        Some(("recurring", sub)) => recurring::handle(conn, sub)?,
+        Some(("rules", sub)) => rules::handle(conn, sub)?,
+        _ => {
+            crate::cli::build_cli().print_help()?;
+            println!();
+        }
+    }
+    Ok(())
+}
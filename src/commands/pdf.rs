@@ -0,0 +1,395 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! `import pdf`: statement tables recovered from PDF text. Text extraction needs the
+//! `pdf` feature; the per-bank parsing below works on plain text and is always built.
+
+use crate::commands::categories::cap_breach;
+use crate::commands::importer::{
+    CsvFormat, ImportCaches, import_records, print_summary, summarize,
+};
+use crate::utils::pretty_table;
+use anyhow::{Result, bail};
+use chrono::NaiveDate;
+use csv::StringRecord;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Built-in statement layouts for `--profile`.
+pub const LAYOUTS: &[&str] = &["hdfc", "generic"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatementRow {
+    pub date: NaiveDate,
+    pub payee: String,
+    pub amount: Decimal,
+}
+
+pub fn handle(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let path = sub.get_one::<String>("path").unwrap().trim();
+    let layout = sub.get_one::<String>("profile").unwrap().trim();
+    let account = sub.get_one::<String>("account").unwrap().trim();
+    let text = extract_text(path)?;
+    let rows = parse_statement(&text, layout)?;
+    if rows.is_empty() {
+        bail!(
+            "No transactions recognised in {} with profile '{}'",
+            path,
+            layout
+        );
+    }
+
+    if sub.get_flag("dry-run") {
+        let table = rows
+            .iter()
+            .map(|r| vec![r.date.to_string(), r.payee.clone(), r.amount.to_string()])
+            .collect();
+        println!("{}", pretty_table(&["Date", "Payee", "Amount"], table));
+        println!(
+            "{} transactions parsed; nothing imported (--dry-run)",
+            rows.len()
+        );
+        return Ok(());
+    }
+
+    let headers = StringRecord::from(vec!["date", "payee", "amount", "account"]);
+    let records = rows
+        .iter()
+        .map(|r| {
+            StringRecord::from(vec![
+                r.date.to_string(),
+                r.payee.clone(),
+                r.amount.to_string(),
+                account.to_string(),
+            ])
+        })
+        .collect();
+    let tx = conn.savepoint()?;
+    let report = import_records(
+        &tx,
+        &headers,
+        records,
+        &CsvFormat::default(),
+        &mut ImportCaches::default(),
+    )?;
+    let mut breaches = Vec::new();
+    for (category_id, month) in &report.spent {
+        breaches.extend(cap_breach(&tx, *category_id, month)?);
+    }
+    let summary = summarize(&tx, std::slice::from_ref(&report))?;
+    tx.commit()?;
+    println!("Imported {} transactions from {}", report.imported, path);
+    print_summary(&summary);
+    for breach in &breaches {
+        eprintln!("Warning: {}", breach);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "pdf")]
+fn extract_text(path: &str) -> Result<String> {
+    use anyhow::Context;
+    pdf_extract::extract_text(path).with_context(|| format!("Extract text from {}", path))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn extract_text(_path: &str) -> Result<String> {
+    bail!("This build has no PDF support; rebuild with `--features pdf`")
+}
+
+/// Transactions found in statement text using the named layout.
+///
+/// `hdfc`: rows start with `dd/mm/yy`, narration follows (continuing on indented
+/// lines below, up to the next row or page footer), then reference number, value
+/// date, the amount and the closing balance. Withdrawal and deposit share a position once extracted, so the sign
+/// comes from the change in closing balance, seeded from "Opening Balance".
+///
+/// `generic`: rows start with a date and end with a signed amount, optionally
+/// followed by a balance; `Dr`/`Cr` after an amount sets its sign.
+pub fn parse_statement(text: &str, layout: &str) -> Result<Vec<StatementRow>> {
+    match layout {
+        "hdfc" => parse_hdfc(text),
+        "generic" => Ok(parse_generic(text)),
+        other => bail!(
+            "Unknown PDF profile '{}'; known: {}",
+            other,
+            LAYOUTS.join(", ")
+        ),
+    }
+}
+
+/// Starts of lines that are page furniture rather than narration: footers, and the
+/// bank and account details and column header repeated at the top of each page.
+const HDFC_PAGE_LINES: &[&str] = &[
+    "page ",
+    "page no",
+    "hdfc bank",
+    "statement of account",
+    "statement summary",
+    "date narration",
+    "opening balance",
+    "closing balance",
+    "generated on",
+    "registered office",
+    "end of statement",
+    "account no",
+    "account branch",
+    "cust id",
+];
+
+fn parse_hdfc(text: &str) -> Result<Vec<StatementRow>> {
+    let mut rows: Vec<StatementRow> = Vec::new();
+    let mut balance = opening_balance(text);
+    // Whether the next indented line may still be narration of the last row.
+    let mut continuing = false;
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(date) = tokens.first().and_then(|t| parse_statement_date(t)) else {
+            let lower = line.trim().to_ascii_lowercase();
+            if lower.is_empty() {
+                continue;
+            }
+            // Wrapped narration is indented under the row above; anything else,
+            // including a page break, ends that row.
+            let wrapped = line.starts_with(char::is_whitespace)
+                && trailing_amounts(&tokens).0.is_empty()
+                && !HDFC_PAGE_LINES.iter().any(|p| lower.starts_with(p));
+            match rows.last_mut() {
+                Some(last) if continuing && wrapped => {
+                    last.payee.push(' ');
+                    last.payee.push_str(line.trim());
+                }
+                _ => continuing = false,
+            }
+            continue;
+        };
+        continuing = false;
+        let (amounts, rest) = trailing_amounts(&tokens[1..]);
+        let [amount, closing] = amounts[..] else {
+            continue;
+        };
+        // Drop the value date and reference number between narration and amounts.
+        let mut narration = rest.to_vec();
+        if narration
+            .last()
+            .is_some_and(|t| parse_statement_date(t).is_some())
+        {
+            narration.pop();
+        }
+        if narration
+            .last()
+            .is_some_and(|t| t.len() >= 6 && t.chars().all(|c| c.is_ascii_digit()))
+        {
+            narration.pop();
+        }
+        let signed = match balance {
+            Some(prev) if closing < prev => -amount.abs(),
+            Some(_) => amount.abs(),
+            None => bail!(
+                "Cannot tell withdrawals from deposits: no 'Opening Balance' before {}",
+                date
+            ),
+        };
+        balance = Some(closing);
+        rows.push(StatementRow {
+            date,
+            payee: narration.join(" "),
+            amount: signed,
+        });
+        continuing = true;
+    }
+    Ok(rows)
+}
+
+fn parse_generic(text: &str) -> Vec<StatementRow> {
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(date) = tokens.first().and_then(|t| parse_statement_date(t)) else {
+            continue;
+        };
+        let (amounts, rest) = trailing_amounts(&tokens[1..]);
+        let (Some(amount), false) = (amounts.first(), rest.is_empty()) else {
+            continue;
+        };
+        rows.push(StatementRow {
+            date,
+            payee: rest.join(" "),
+            amount: *amount,
+        });
+    }
+    rows
+}
+
+/// First amount on the line after "Opening Balance", or on the line below it when
+/// the statement prints a summary header row.
+fn opening_balance(text: &str) -> Option<Decimal> {
+    let lines: Vec<&str> = text.lines().collect();
+    let idx = lines
+        .iter()
+        .position(|l| l.to_ascii_lowercase().contains("opening balance"))?;
+    lines[idx..]
+        .iter()
+        .take(2)
+        .flat_map(|l| l.split_whitespace())
+        .find_map(parse_statement_amount)
+}
+
+/// Amounts at the end of `tokens` (with `Dr`/`Cr` markers applied) and the tokens
+/// before them.
+fn trailing_amounts<'a>(tokens: &'a [&'a str]) -> (Vec<Decimal>, &'a [&'a str]) {
+    let mut end = tokens.len();
+    let mut amounts = Vec::new();
+    while end > 0 {
+        let token = tokens[end - 1];
+        let (value, used) = match token.to_ascii_lowercase().as_str() {
+            "dr" | "cr" if end >= 2 => match parse_statement_amount(tokens[end - 2]) {
+                Some(v) if token.eq_ignore_ascii_case("dr") => (-v.abs(), 2),
+                Some(v) => (v.abs(), 2),
+                None => break,
+            },
+            _ => match parse_statement_amount(token) {
+                Some(v) => (v, 1),
+                None => break,
+            },
+        };
+        amounts.push(value);
+        end -= used;
+    }
+    amounts.reverse();
+    (amounts, &tokens[..end])
+}
+
+/// `1,234.56`, `-12.00`, `12.00Dr` or `(12.00)`; two decimals are required so
+/// reference numbers are not mistaken for amounts.
+fn parse_statement_amount(token: &str) -> Option<Decimal> {
+    let lower = token.to_ascii_lowercase();
+    let (body, negative) = if let Some(b) = lower.strip_suffix("dr") {
+        (b.to_string(), true)
+    } else if let Some(b) = lower.strip_suffix("cr") {
+        (b.to_string(), false)
+    } else if lower.starts_with('(') && lower.ends_with(')') {
+        (lower[1..lower.len() - 1].to_string(), true)
+    } else {
+        (lower, false)
+    };
+    let cleaned = body.replace(',', "");
+    let (_, decimals) = cleaned.split_once('.')?;
+    if decimals.len() != 2 {
+        return None;
+    }
+    let value = cleaned.parse::<Decimal>().ok()?;
+    Some(if negative { -value.abs() } else { value })
+}
+
+fn parse_statement_date(token: &str) -> Option<NaiveDate> {
+    ["%d/%m/%y", "%d/%m/%Y", "%Y-%m-%d", "%d-%m-%Y", "%d.%m.%Y"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(token, fmt).ok())
+        .filter(|_| token.len() >= 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_statement, parse_statement_amount};
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn hdfc_rows_take_sign_from_closing_balance() {
+        let text = "\
+Statement of account
+Opening Balance : 10,000.00
+Date Narration Chq./Ref.No. Value Dt Withdrawal Amt. Deposit Amt. Closing Balance
+01/04/25 UPI-SWIGGY-BANGALORE 0000512345678 01/04/25 450.00 9,550.00
+02/04/25 NEFT CR-ACME CORP 0000598765432 02/04/25 50,000.00 59,550.00
+         SALARY APRIL
+03/04/25 ATW-ATM CASH 0000511111111 03/04/25 2,000.00 57,550.00
+";
+        let rows = parse_statement(text, "hdfc").unwrap();
+        let got: Vec<(String, String, Decimal)> = rows
+            .into_iter()
+            .map(|r| (r.date.to_string(), r.payee, r.amount))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (
+                    "2025-04-01".into(),
+                    "UPI-SWIGGY-BANGALORE".into(),
+                    Decimal::new(-45000, 2)
+                ),
+                (
+                    "2025-04-02".into(),
+                    "NEFT CR-ACME CORP SALARY APRIL".into(),
+                    Decimal::new(5000000, 2)
+                ),
+                (
+                    "2025-04-03".into(),
+                    "ATW-ATM CASH".into(),
+                    Decimal::new(-200000, 2)
+                ),
+            ]
+        );
+        assert!(parse_statement("01/04/25 X 0000512345678 01/04/25 1.00 2.00", "hdfc").is_err());
+    }
+
+    #[test]
+    fn hdfc_narration_stops_at_page_breaks() {
+        let text = "\
+HDFC BANK LIMITED
+Statement of account
+Opening Balance : 1,000.00
+Date Narration Chq./Ref.No. Value Dt Withdrawal Amt. Deposit Amt. Closing Balance
+30/04/25 POS 4140XXXXXXXX1234 0000512345678 30/04/25 250.00 750.00
+         AMAZON RETAIL
+*Closing balance includes funds earmarked for hold and uncleared funds
+  Page 1 of 2
+HDFC BANK LIMITED
+Statement of account
+   MR A CUSTOMER
+   Account Branch : MG ROAD
+Date Narration Chq./Ref.No. Value Dt Withdrawal Amt. Deposit Amt. Closing Balance
+         Cust ID : 12345678
+02/05/25 IMPS-P2P-R SHARMA 0000598765432 02/05/25 100.00 850.00
+         RENT SHARE
+  Page 2 of 2
+";
+        let rows = parse_statement(text, "hdfc").unwrap();
+        let payees: Vec<&str> = rows.iter().map(|r| r.payee.as_str()).collect();
+        assert_eq!(
+            payees,
+            vec![
+                "POS 4140XXXXXXXX1234 AMAZON RETAIL",
+                "IMPS-P2P-R SHARMA RENT SHARE"
+            ]
+        );
+        assert_eq!(rows[1].amount, Decimal::new(10000, 2));
+    }
+
+    #[test]
+    fn generic_rows_use_signed_amounts_and_dr_cr() {
+        let text = "\
+2025-05-01 Coffee shop -3.50 96.50
+2025-05-02 Refund 12.00 Cr
+02.05.2025 Card fee 1.00Dr
+Page 1 of 2
+";
+        let rows = parse_statement(text, "generic").unwrap();
+        let amounts: Vec<Decimal> = rows.iter().map(|r| r.amount).collect();
+        assert_eq!(
+            amounts,
+            vec![
+                Decimal::new(-350, 2),
+                Decimal::new(1200, 2),
+                Decimal::new(-100, 2)
+            ]
+        );
+        assert_eq!(rows[0].payee, "Coffee shop");
+        assert!(parse_statement(text, "nope").is_err());
+        assert_eq!(parse_statement_amount("12345678"), None);
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::utils::{parse_month, pretty_table};
+use anyhow::{Result, bail, ensure};
+use rusqlite::{Connection, params};
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("close", sub)) => {
+            let month = parse_month(sub.get_one::<String>("month").unwrap().trim())?;
+            if close_period(conn, &month)? {
+                println!("Closed {}; its transactions are now locked", month);
+            } else {
+                println!("{} was already closed", month);
+            }
+        }
+        Some(("reopen", sub)) => {
+            let month = parse_month(sub.get_one::<String>("month").unwrap().trim())?;
+            ensure!(
+                sub.get_flag("force"),
+                "Reopening {} lets finalized reports change; pass --force to confirm",
+                month
+            );
+            let removed =
+                conn.execute("DELETE FROM closed_periods WHERE month=?1", params![&month])?;
+            if removed == 0 {
+                bail!("{} is not closed", month);
+            }
+            println!("Reopened {}", month);
+        }
+        Some(("list", _)) => {
+            let mut stmt =
+                conn.prepare("SELECT month, closed_at FROM closed_periods ORDER BY month")?;
+            let rows = stmt
+                .query_map([], |r| {
+                    Ok(vec![r.get::<_, String>(0)?, r.get::<_, String>(1)?])
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            println!("{}", pretty_table(&["Month", "Closed at"], rows));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Lock `month` (YYYY-MM); false when it was already closed.
+pub fn close_period(conn: &Connection, month: &str) -> Result<bool> {
+    let added = conn.execute(
+        "INSERT OR IGNORE INTO closed_periods(month) VALUES (?1)",
+        params![month],
+    )?;
+    Ok(added > 0)
+}
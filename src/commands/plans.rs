@@ -0,0 +1,270 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Planned purchases (`plan`): a wishlist whose items can be checked against
+//! envelope balances before buying.
+
+use crate::commands::envelopes::envelope_compute;
+use crate::models::PlanRow;
+use crate::utils::{
+    fmt_amount, fx_convert, get_base_currency, id_for_category, maybe_print_json, parse_amount,
+    parse_month, pretty_table,
+};
+use anyhow::{Context, Result, bail, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => add(conn, sub)?,
+        Some(("list", sub)) => list(conn, sub)?,
+        Some(("rm", sub)) => {
+            let item = sub.get_one::<String>("item").unwrap().trim();
+            if conn.execute("DELETE FROM planned_purchases WHERE item=?1", params![item])? == 0 {
+                bail!("No planned purchase '{}'", item);
+            }
+            println!("Removed planned purchase '{}'", item);
+        }
+        Some(("afford", sub)) => afford(conn, sub)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn add(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let item = sub.get_one::<String>("item").unwrap().trim();
+    let cost = parse_amount(sub.get_one::<String>("cost").unwrap().trim())?;
+    ensure!(cost > Decimal::ZERO, "Cost must be positive");
+    let currency = match sub.get_one::<String>("currency") {
+        Some(c) => c.trim().to_uppercase(),
+        None => get_base_currency(conn)?,
+    };
+    let month = sub
+        .get_one::<String>("month")
+        .map(|m| parse_month(m.trim()))
+        .transpose()?;
+    let category_id = sub
+        .get_one::<String>("envelope")
+        .map(|c| id_for_category(conn, c.trim()))
+        .transpose()?;
+    let goal_id = match sub.get_one::<String>("goal") {
+        Some(goal) => Some(
+            conn.query_row(
+                "SELECT id FROM goals WHERE name=?1",
+                params![goal.trim()],
+                |r| r.get::<_, i64>(0),
+            )
+            .optional()?
+            .with_context(|| format!("No goal named '{}'", goal.trim()))?,
+        ),
+        None => None,
+    };
+    conn.execute(
+        "INSERT INTO planned_purchases(item, cost, currency, month, category_id, goal_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            item,
+            cost.to_string(),
+            currency,
+            month,
+            category_id,
+            goal_id
+        ],
+    )
+    .with_context(|| format!("Add planned purchase '{}'", item))?;
+    println!(
+        "Planned '{}' for {} {}",
+        item,
+        fmt_amount(cost, &currency),
+        currency
+    );
+    Ok(())
+}
+
+fn list(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT p.item, p.cost, p.currency, p.month, COALESCE(g.name, c.name)
+         FROM planned_purchases p
+         LEFT JOIN categories c ON p.category_id=c.id
+         LEFT JOIN goals g ON p.goal_id=g.id
+         ORDER BY p.month IS NULL, p.month, p.item",
+    )?;
+    let rows = stmt.query_map([], |r| {
+        let cost: String = r.get(1)?;
+        let currency: String = r.get(2)?;
+        Ok(PlanRow {
+            item: r.get(0)?,
+            cost: cost
+                .parse::<Decimal>()
+                .map(|c| fmt_amount(c, &currency))
+                .unwrap_or(cost),
+            currency,
+            month: r.get(3)?,
+            funded_from: r.get(4)?,
+        })
+    })?;
+    let data = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+        println!(
+            "{}",
+            pretty_table(
+                &["Item", "Cost", "CCY", "Month", "Funded from"],
+                data.into_iter().map(PlanRow::cells).collect()
+            )
+        );
+    }
+    Ok(())
+}
+
+fn afford(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let item = sub.get_one::<String>("item").unwrap().trim();
+    let today = match sub.get_one::<String>("as-of") {
+        Some(d) => crate::utils::parse_date(d.trim())?,
+        None => crate::utils::today(),
+    };
+    let check = check_affordability(conn, item, today)?;
+    if maybe_print_json(sub.get_flag("json"), false, std::slice::from_ref(&check))? {
+        return Ok(());
+    }
+    let base = get_base_currency(conn)?;
+    let mut rows = vec![
+        vec!["Cost".to_string(), fmt_amount(check.cost, &base)],
+        vec![
+            format!(
+                "Available in {}",
+                check.envelope.as_deref().unwrap_or("(no envelope)")
+            ),
+            fmt_amount(check.available, &base),
+        ],
+        vec![
+            format!("Funding set up to {}", check.month),
+            fmt_amount(check.scheduled, &base),
+        ],
+    ];
+    for (category, spare) in &check.spare {
+        rows.push(vec![
+            format!("Spare in {}", category),
+            fmt_amount(*spare, &base),
+        ]);
+    }
+    println!("{}", pretty_table(&[item, &base], rows));
+    let verdict = if check.shortfall.is_zero() && check.from_others.is_zero() {
+        "Affordable from its envelope".to_string()
+    } else if check.shortfall.is_zero() {
+        format!(
+            "Affordable by moving {} from other envelopes' spare money",
+            fmt_amount(check.from_others, &base)
+        )
+    } else {
+        format!(
+            "Not affordable by {}: {} {} short",
+            check.month,
+            fmt_amount(check.shortfall, &base),
+            base
+        )
+    };
+    println!("{}", verdict);
+    Ok(())
+}
+
+/// The outcome of `plan afford`, all amounts in the base currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Affordability {
+    pub item: String,
+    /// The target month, or the current one for items without a month.
+    pub month: String,
+    pub cost: Decimal,
+    pub envelope: Option<String>,
+    /// Available in the linked envelope this month.
+    pub available: Decimal,
+    /// Budgets already set for the linked envelope after this month, up to `month`.
+    pub scheduled: Decimal,
+    /// Other envelopes with money left after their projected spending this month.
+    pub spare: Vec<(String, Decimal)>,
+    /// Taken from `spare` to cover what the linked envelope cannot.
+    pub from_others: Decimal,
+    pub shortfall: Decimal,
+}
+
+/// Whether the linked envelope (directly or through its goal) plus the funding already
+/// budgeted for it up to the target month covers `item`, and if not, whether the spare
+/// money of other envelopes does. An envelope's spare money is what would remain at the
+/// end of this month if it kept spending at its rate so far, so covering the item never
+/// leaves another envelope short.
+pub fn check_affordability(
+    conn: &Connection,
+    item: &str,
+    today: NaiveDate,
+) -> Result<Affordability> {
+    let periods = crate::utils::periods();
+    let this_month = periods.month_of(today);
+    type Row = (String, String, Option<String>, Option<i64>);
+    let (cost, currency, month, envelope_id): Row = conn
+        .query_row(
+            "SELECT p.cost, p.currency, p.month, COALESCE(g.category_id, p.category_id)
+             FROM planned_purchases p LEFT JOIN goals g ON p.goal_id=g.id
+             WHERE p.item=?1",
+            params![item],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .optional()?
+        .with_context(|| format!("No planned purchase '{}'", item))?;
+    let base = get_base_currency(conn)?;
+    let cost = cost
+        .parse::<Decimal>()
+        .with_context(|| format!("Invalid cost '{}' for {}", cost, item))?;
+    let cost = fx_convert(conn, today, cost, &currency, &base)?;
+    let month = month
+        .filter(|m| *m > this_month)
+        .unwrap_or(this_month.clone());
+
+    let (start, end) = periods.month_range(&this_month)?;
+    let elapsed = Decimal::from((today - start).num_days() + 1);
+    let remaining = Decimal::from((end - today).num_days().max(0));
+    let mut stmt = conn.prepare("SELECT id, name FROM categories ORDER BY name")?;
+    let categories = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut envelope = None;
+    let mut available = Decimal::ZERO;
+    let mut spare = Vec::new();
+    for (id, name) in categories {
+        let (carry, budget, spent) = envelope_compute(conn, id, &this_month)?;
+        if Some(id) == envelope_id {
+            envelope = Some(name);
+            available = carry + budget - spent;
+            continue;
+        }
+        let projected = carry + budget - spent - spent / elapsed * remaining;
+        if projected > Decimal::ZERO {
+            spare.push((name, projected));
+        }
+    }
+
+    let mut scheduled = Decimal::ZERO;
+    if let Some(id) = envelope_id {
+        let mut later = crate::utils::shift_month(&this_month, 1)?;
+        while later <= month {
+            scheduled += crate::commands::budgets::budget_in(conn, id, &later, &base)?;
+            later = crate::utils::shift_month(&later, 1)?;
+        }
+    }
+    let uncovered = (cost - available.max(Decimal::ZERO) - scheduled).max(Decimal::ZERO);
+    let spare_total: Decimal = spare.iter().map(|(_, s)| *s).sum();
+    let from_others = uncovered.min(spare_total);
+    Ok(Affordability {
+        item: item.to_string(),
+        month,
+        cost,
+        envelope,
+        available,
+        scheduled,
+        spare,
+        from_others,
+        shortfall: uncovered - from_others,
+    })
+}
@@ -4,13 +4,13 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
+use crate::providers::{PriceProvider, Quote, Yahoo};
 use crate::utils::{
-    http_client, id_for_account, id_for_asset, parse_date, parse_decimal, pretty_table,
+    fmt_amount, fmt_qty, id_for_account, id_for_asset, parse_date, parse_decimal, pretty_table,
 };
-use anyhow::{Context, Result, anyhow};
-use chrono::{NaiveDate, Utc};
-use rusqlite::{Connection, params};
-use serde::Deserialize;
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
 use std::collections::{HashMap, HashSet, hash_map::Entry};
 
 use rust_decimal::Decimal;
@@ -20,9 +20,12 @@ pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
         Some(("add-asset", sub)) => add_asset(conn, sub)?,
         Some(("list-assets", _)) => list_assets(conn)?,
         Some(("trade", sub)) => trade(conn, sub)?,
+        Some(("grant", sub)) => grant_cmd(conn, sub)?,
         Some(("value", sub)) => value(conn, sub)?,
         Some(("tax", sub)) => tax_cg(conn, sub)?,
         Some(("price", sub)) => price_cmd(conn, sub)?,
+        Some(("alert", sub)) => crate::commands::price_alerts::handle(conn, sub)?,
+        Some(("watch", sub)) => crate::commands::watchlist::handle(conn, sub)?,
         _ => {}
     }
     Ok(())
@@ -33,14 +36,26 @@ fn add_asset(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
         .get_one::<String>("ticker")
         .map(|s| s.trim().to_string())
         .unwrap();
-    let name = sub
-        .get_one::<String>("name")
-        .map(|s| s.trim().to_string())
-        .unwrap();
+    let name = sub.get_one::<String>("name").map(|s| s.trim().to_string());
     let currency = sub
         .get_one::<String>("currency")
-        .map(|s| s.trim().to_string())
-        .unwrap();
+        .map(|s| s.trim().to_string());
+    let (name, currency) = if sub.get_flag("auto") {
+        crate::utils::ensure_provider(conn, "price_provider", "yahoo")?;
+        let quote = Yahoo
+            .quotes(&[ticker.as_str()])?
+            .into_iter()
+            .find(|q| {
+                q.symbol
+                    .as_deref()
+                    .is_some_and(|s| s.eq_ignore_ascii_case(&ticker))
+            })
+            .with_context(|| format!("Yahoo does not know ticker {}", ticker))?;
+        asset_details(&ticker, &quote, name, currency)?
+    } else {
+        // clap requires both unless --auto is given.
+        (name.unwrap(), currency.unwrap())
+    };
     conn.execute(
         "INSERT INTO assets(ticker, name, currency) VALUES (?1,?2,?3)",
         params![ticker, name, currency],
@@ -49,6 +64,41 @@ fn add_asset(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
+/// Name and currency of a new asset from its provider quote. A name given on the
+/// command line wins; a currency given there must match the listing's.
+fn asset_details(
+    ticker: &str,
+    quote: &Quote,
+    name: Option<String>,
+    currency: Option<String>,
+) -> Result<(String, String)> {
+    let listed = quote
+        .currency
+        .as_deref()
+        .with_context(|| format!("Yahoo has no currency for {}", ticker))?;
+    if listed == "GBp" {
+        eprintln!(
+            "Note: {} is quoted in pence (GBp); prices will be stored in pence",
+            ticker
+        );
+    }
+    let listed = listed.to_uppercase();
+    if let Some(given) = currency {
+        ensure!(
+            given.eq_ignore_ascii_case(&listed),
+            "{} is listed in {}, not {}",
+            ticker,
+            listed,
+            given.to_uppercase()
+        );
+    }
+    let name = name
+        .or_else(|| quote.long_name.clone())
+        .or_else(|| quote.short_name.clone())
+        .unwrap_or_else(|| ticker.to_string());
+    Ok((name, listed))
+}
+
 fn list_assets(conn: &Connection) -> Result<()> {
     let mut stmt = conn.prepare("SELECT ticker, name, currency FROM assets ORDER BY ticker")?;
     let rows = stmt.query_map([], |r| {
@@ -63,14 +113,15 @@ fn list_assets(conn: &Connection) -> Result<()> {
         let (t, n, c) = row?;
         data.push(vec![t, n, c]);
     }
-    println!("{}", pretty_table(&["Ticker", "Name", "CCY"], data));
+    crate::utils::emit(pretty_table(&["Ticker", "Name", "CCY"], data));
     Ok(())
 }
 
-fn trade(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+fn trade(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
         Some(("buy", sub)) => record_trade(conn, sub, "buy"),
         Some(("sell", sub)) => record_trade(conn, sub, "sell"),
+        Some(("drip", sub)) => drip(conn, sub),
         _ => Ok(()),
     }
 }
@@ -95,6 +146,9 @@ fn record_trade(conn: &Connection, sub: &clap::ArgMatches, side: &str) -> Result
 
     let asset_id = id_for_asset(conn, &ticker)?;
     let account_id = id_for_account(conn, &account)?;
+    let fee_currency = sub
+        .get_one::<String>("fee-currency")
+        .map(|c| c.trim().to_uppercase());
 
     conn.execute(
         "INSERT INTO trades(date, asset_id, account_id, quantity, price, fees, side)
@@ -109,36 +163,184 @@ fn record_trade(conn: &Connection, sub: &clap::ArgMatches, side: &str) -> Result
             side
         ],
     )?;
+    if let Some(ccy) = &fee_currency {
+        conn.execute(
+            "UPDATE trades SET fee_currency=?1 WHERE id=?2",
+            params![ccy, conn.last_insert_rowid()],
+        )?;
+    }
+    println!(
+        "Recorded {} {} x {} @ {} (fees {}{})",
+        side,
+        qty,
+        ticker,
+        price,
+        fees,
+        fee_currency.map(|c| format!(" {}", c)).unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Reinvest a cash dividend: the dividend is income on `--date` and buys fractional
+/// shares at that day's price (the latest cached one on or before it, or `--price`).
+fn drip(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let date = parse_date(sub.get_one::<String>("date").unwrap().trim())?;
+    let ticker = sub.get_one::<String>("ticker").unwrap().trim().to_string();
+    let account = sub.get_one::<String>("account").unwrap().trim().to_string();
+    let amount = parse_decimal(sub.get_one::<String>("amount").unwrap().trim())?;
+    ensure!(amount > Decimal::ZERO, "Dividend amount must be positive");
+    let asset_id = id_for_asset(conn, &ticker)?;
+    let account_id = id_for_account(conn, &account)?;
+
+    let (price, price_day) = match sub.get_one::<String>("price") {
+        Some(raw) => (parse_decimal(raw.trim())?, date.to_string()),
+        None => {
+            let (price_s, day): (String, String) = conn
+                .query_row(
+                    "SELECT price, substr(as_of, 1, 10) FROM prices
+                     WHERE asset_id=?1 AND substr(as_of, 1, 10) <= ?2
+                     ORDER BY as_of DESC LIMIT 1",
+                    params![asset_id, date.to_string()],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .optional()?
+                .with_context(|| {
+                    format!(
+                        "No price for {} on or before {}; pass --price",
+                        ticker, date
+                    )
+                })?;
+            let price = Decimal::from_str_exact(&price_s)
+                .with_context(|| format!("Invalid stored price '{}' for {}", price_s, ticker))?;
+            (price, day)
+        }
+    };
+    ensure!(price > Decimal::ZERO, "Price must be positive");
+    // Brokers credit fractional shares to a few decimals and never more than the cash.
+    let quantity =
+        (amount / price).round_dp_with_strategy(6, rust_decimal::RoundingStrategy::ToZero);
+    ensure!(
+        quantity > Decimal::ZERO,
+        "{} buys no shares at {}",
+        amount,
+        price
+    );
+
+    let tx = conn.savepoint()?;
+    tx.execute(
+        "INSERT INTO trades(date, asset_id, account_id, quantity, price, fees, side, note)
+         VALUES (?1,?2,?3,?4,?5,'0','buy','drip')",
+        params![
+            date.to_string(),
+            asset_id,
+            account_id,
+            quantity.to_string(),
+            price.to_string()
+        ],
+    )?;
+    let trade_id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO dividends(date, asset_id, account_id, amount, trade_id)
+         VALUES (?1,?2,?3,?4,?5)",
+        params![
+            date.to_string(),
+            asset_id,
+            account_id,
+            amount.to_string(),
+            trade_id
+        ],
+    )?;
+    tx.commit()?;
     println!(
-        "Recorded {} {} x {} @ {} (fees {})",
-        side, qty, ticker, price, fees
+        "Reinvested dividend {} of {} into {} @ {} (price of {})",
+        amount, ticker, quantity, price, price_day
     );
     Ok(())
 }
 
 fn value(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
     if sub.get_flag("live") {
-        fetch_prices(conn)?;
+        let fired = fetch_prices(conn)?;
+        crate::commands::price_alerts::notify(conn, false, None, &fired)?;
+    }
+    let at = sub
+        .get_one::<String>("at")
+        .map(|d| parse_date(d.trim()))
+        .transpose()?;
+    let target = sub
+        .get_one::<String>("currency")
+        .map(|c| c.trim().to_uppercase());
+
+    let positions = portfolio_positions(conn, at)?;
+    if at.is_none() && target.is_none() {
+        let rows = positions
+            .into_iter()
+            .map(|position| {
+                let price = fmt_amount(position.last_price, &position.currency);
+                let value = fmt_amount(position.market_value, &position.currency);
+                vec![
+                    position.ticker,
+                    position.currency,
+                    fmt_qty(position.quantity),
+                    price,
+                    value,
+                ]
+            })
+            .collect();
+
+        crate::utils::emit(pretty_table(
+            &["Ticker", "CCY", "Qty", "Price", "Value"],
+            rows,
+        ));
+        return Ok(());
     }
 
-    let positions = portfolio_positions(conn)?;
-    let rows = positions
+    // A statement for one day: holdings then, each at its last price on or before that
+    // day, converted at that day's rates.
+    let target = match target {
+        Some(ccy) => ccy,
+        None => crate::utils::get_base_currency(conn)?,
+    };
+    let date = at.unwrap_or_else(crate::utils::today);
+    let valued = convert_positions(conn, positions, date, &target)?;
+    let total: Decimal = valued.iter().map(|(_, value)| *value).sum();
+    let mut rows: Vec<Vec<String>> = valued
         .into_iter()
-        .map(|position| {
+        .map(|(position, value)| {
             vec![
                 position.ticker,
+                fmt_qty(position.quantity),
+                fmt_amount(position.last_price, &position.currency),
+                position
+                    .price_date
+                    .unwrap_or_else(|| "no price".to_string()),
+                fmt_amount(position.market_value, &position.currency),
                 position.currency,
-                format!("{:.4}", position.quantity),
-                format!("{:.2}", position.last_price),
-                format!("{:.2}", position.market_value),
+                fmt_amount(value, &target),
             ]
         })
         .collect();
-
-    println!(
-        "{}",
-        pretty_table(&["Ticker", "CCY", "Qty", "Price", "Value"], rows)
-    );
+    rows.push(vec![
+        "Total".to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        fmt_amount(total, &target),
+    ]);
+    crate::utils::emit(pretty_table(
+        &[
+            &format!("Ticker (at {})", date),
+            "Qty",
+            "Price",
+            "Price date",
+            "Value",
+            "CCY",
+            &format!("Value ({})", target),
+        ],
+        rows,
+    ));
     Ok(())
 }
 
@@ -148,14 +350,22 @@ struct PositionSummary {
     currency: String,
     quantity: Decimal,
     last_price: Decimal,
+    /// Day of `last_price`; `None` when the asset has no price yet.
+    price_date: Option<String>,
     market_value: Decimal,
 }
 
-fn portfolio_positions(conn: &Connection) -> Result<Vec<PositionSummary>> {
+/// Open positions valued at the latest price; with `as_of`, only trades and prices
+/// on or before that date count.
+fn portfolio_positions(
+    conn: &Connection,
+    as_of: Option<NaiveDate>,
+) -> Result<Vec<PositionSummary>> {
     struct AssetRow {
         ticker: String,
         currency: String,
         last_price: Decimal,
+        price_date: Option<String>,
     }
 
     let mut stmt =
@@ -179,6 +389,7 @@ fn portfolio_positions(conn: &Connection) -> Result<Vec<PositionSummary>> {
             ticker,
             currency,
             last_price: Decimal::ZERO,
+            price_date: None,
         });
         index_by_id.insert(id, idx);
     }
@@ -188,20 +399,28 @@ fn portfolio_positions(conn: &Connection) -> Result<Vec<PositionSummary>> {
     }
 
     let mut price_stmt = conn.prepare_cached(
-        "SELECT asset_id, price FROM (
+        "SELECT asset_id, price, day FROM (
              SELECT asset_id,
                     price,
+                    substr(as_of, 1, 10) AS day,
                     ROW_NUMBER() OVER (
                         PARTITION BY asset_id
                         ORDER BY as_of DESC, rowid DESC
                     ) AS rn
              FROM prices
+             WHERE ?1 IS NULL OR substr(as_of, 1, 10) <= ?1
          ) WHERE rn = 1",
     )?;
-    let price_rows =
-        price_stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+    let cutoff = as_of.map(|d| d.to_string());
+    let price_rows = price_stmt.query_map(params![cutoff], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+        ))
+    })?;
     for price in price_rows {
-        let (asset_id, price_s) = price?;
+        let (asset_id, price_s, day) = price?;
         let Some(&idx) = index_by_id.get(&asset_id) else {
             continue;
         };
@@ -210,11 +429,14 @@ fn portfolio_positions(conn: &Connection) -> Result<Vec<PositionSummary>> {
         let last_price = Decimal::from_str_exact(&price_s)
             .with_context(|| format!("Invalid stored price '{}' for asset {}", price_s, ticker))?;
         asset.last_price = last_price;
+        asset.price_date = Some(day);
     }
 
     let mut net_quantities = vec![Decimal::ZERO; assets.len()];
-    let mut trades_stmt = conn.prepare_cached("SELECT asset_id, quantity, side FROM trades")?;
-    let trades = trades_stmt.query_map([], |r| {
+    let mut trades_stmt = conn.prepare_cached(
+        "SELECT asset_id, quantity, side FROM trades WHERE ?1 IS NULL OR date <= ?1",
+    )?;
+    let trades = trades_stmt.query_map(params![cutoff], |r| {
         Ok((
             r.get::<_, i64>(0)?,
             r.get::<_, String>(1)?,
@@ -256,6 +478,7 @@ fn portfolio_positions(conn: &Connection) -> Result<Vec<PositionSummary>> {
             ticker: asset.ticker,
             currency: asset.currency,
             last_price: asset.last_price,
+            price_date: asset.price_date,
             quantity,
         });
     }
@@ -263,6 +486,31 @@ fn portfolio_positions(conn: &Connection) -> Result<Vec<PositionSummary>> {
     Ok(positions)
 }
 
+/// Market value of all positions held on `date`, converted to `base` at that date.
+pub fn portfolio_value_at(conn: &Connection, date: NaiveDate, base: &str) -> Result<Decimal> {
+    let positions = portfolio_positions(conn, Some(date))?;
+    Ok(convert_positions(conn, positions, date, base)?
+        .into_iter()
+        .map(|(_, value)| value)
+        .sum())
+}
+
+/// Each position with its market value in `target` at the rates valuing `date`.
+fn convert_positions(
+    conn: &Connection,
+    positions: Vec<PositionSummary>,
+    date: NaiveDate,
+    target: &str,
+) -> Result<Vec<(PositionSummary, Decimal)>> {
+    let rate_date = crate::utils::valuation_date(conn, date)?;
+    let items: Vec<_> = positions
+        .iter()
+        .map(|p| (rate_date, p.market_value, p.currency.as_str()))
+        .collect();
+    let converted = crate::utils::fx_convert_batch(conn, &items, target)?;
+    Ok(positions.into_iter().zip(converted).collect())
+}
+
 fn tax_cg(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let year = sub
         .get_one::<String>("year")
@@ -275,16 +523,34 @@ fn tax_cg(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
             vec![
                 row.ticker,
                 row.sell_date,
-                row.currency,
-                format!("{:.2}", row.realized_gain),
+                row.currency.clone(),
+                fmt_amount(row.realized_gain, &row.currency),
             ]
         })
         .collect();
 
-    println!(
-        "{}",
-        pretty_table(&["Ticker", "Sell Date", "CCY", "Realized Gain"], table_rows)
-    );
+    crate::utils::emit(pretty_table(
+        &["Ticker", "Sell Date", "CCY", "Realized Gain"],
+        table_rows,
+    ));
+
+    let dividend_rows = dividend_income_rows(conn, &year)?;
+    if !dividend_rows.is_empty() {
+        crate::utils::emit("Dividend income (reinvested):");
+        crate::utils::emit(pretty_table(
+            &["Ticker", "Date", "Qty bought", "Price", "CCY", "Income"],
+            dividend_rows,
+        ));
+    }
+
+    let vest_rows = vest_income_rows(conn, &year)?;
+    if !vest_rows.is_empty() {
+        crate::utils::emit("Vest income (RSU value / ESPP discount):");
+        crate::utils::emit(pretty_table(
+            &["Ticker", "Vest Date", "Qty", "FMV", "CCY", "Income"],
+            vest_rows,
+        ));
+    }
     Ok(())
 }
 
@@ -312,6 +578,7 @@ struct SellRecord {
 }
 
 fn load_sells_before(
+    conn: &Connection,
     stmt: &mut rusqlite::Statement<'_>,
     ticker: &str,
     cutoff: NaiveDate,
@@ -322,11 +589,13 @@ fn load_sells_before(
             r.get::<_, String>(1)?,
             r.get::<_, String>(2)?,
             r.get::<_, String>(3)?,
+            r.get::<_, String>(4)?,
+            r.get::<_, String>(5)?,
         ))
     })?;
     let mut sells = Vec::new();
     for row in rows {
-        let (date_s, qty_s, price_s, fee_s) = row?;
+        let (date_s, qty_s, price_s, fee_s, fee_ccy, ccy) = row?;
         let date = parse_date(&date_s)
             .with_context(|| format!("Invalid sell date '{}' for {}", date_s, ticker))?;
         let qty_raw = Decimal::from_str_exact(&qty_s)
@@ -339,6 +608,7 @@ fn load_sells_before(
             .with_context(|| format!("Invalid sell price '{}' for {}", price_s, ticker))?;
         let fees = Decimal::from_str_exact(&fee_s)
             .with_context(|| format!("Invalid sell fees '{}' for {}", fee_s, ticker))?;
+        let fees = fees_in(conn, date, fees, &fee_ccy, &ccy)?;
         sells.push(SellRecord {
             date,
             quantity: qty,
@@ -415,18 +685,24 @@ fn match_sell_against_lots(
     }
 }
 
-fn load_buy_lots(stmt: &mut rusqlite::Statement<'_>, ticker: &str) -> Result<Vec<Lot>> {
+fn load_buy_lots(
+    conn: &Connection,
+    stmt: &mut rusqlite::Statement<'_>,
+    ticker: &str,
+) -> Result<Vec<Lot>> {
     let rows = stmt.query_map([ticker], |r| {
         Ok((
             r.get::<_, String>(0)?,
             r.get::<_, String>(1)?,
             r.get::<_, String>(2)?,
             r.get::<_, String>(3)?,
+            r.get::<_, String>(4)?,
+            r.get::<_, String>(5)?,
         ))
     })?;
     let mut lots = Vec::new();
     for row in rows {
-        let (date_s, qty_s, price_s, fee_s) = row?;
+        let (date_s, qty_s, price_s, fee_s, fee_ccy, ccy) = row?;
         let date = parse_date(&date_s)
             .with_context(|| format!("Invalid buy date '{}' for {}", date_s, ticker))?;
         let qty_raw = Decimal::from_str_exact(&qty_s)
@@ -439,6 +715,7 @@ fn load_buy_lots(stmt: &mut rusqlite::Statement<'_>, ticker: &str) -> Result<Vec
             .with_context(|| format!("Invalid buy price '{}' for {}", price_s, ticker))?;
         let fees = Decimal::from_str_exact(&fee_s)
             .with_context(|| format!("Invalid buy fees '{}' for {}", fee_s, ticker))?;
+        let fees = fees_in(conn, date, fees, &fee_ccy, &ccy)?;
         lots.push(Lot {
             date,
             remaining: qty,
@@ -450,6 +727,21 @@ fn load_buy_lots(stmt: &mut rusqlite::Statement<'_>, ticker: &str) -> Result<Vec
     Ok(lots)
 }
 
+/// Trade fees in the asset currency; fees charged in another currency are converted
+/// at the trade date.
+fn fees_in(
+    conn: &Connection,
+    date: NaiveDate,
+    fees: Decimal,
+    fee_currency: &str,
+    asset_currency: &str,
+) -> Result<Decimal> {
+    if fees.is_zero() || fee_currency.eq_ignore_ascii_case(asset_currency) {
+        return Ok(fees);
+    }
+    crate::utils::fx_convert(conn, date, fees, fee_currency, asset_currency)
+}
+
 fn realized_gains(conn: &Connection, year: &str) -> Result<Vec<RealizedGainRow>> {
     let year_int: i32 = year
         .parse()
@@ -458,7 +750,8 @@ fn realized_gains(conn: &Connection, year: &str) -> Result<Vec<RealizedGainRow>>
         chrono::NaiveDate::from_ymd_opt(year_int, 1, 1).context("Invalid year start date")?;
 
     let mut sell_stmt = conn.prepare(
-        "SELECT a.ticker, t.date, t.quantity, t.price, t.fees, a.currency
+        "SELECT a.ticker, t.date, t.quantity, t.price, t.fees, a.currency,
+                COALESCE(t.fee_currency, a.currency)
          FROM trades t JOIN assets a ON t.asset_id=a.id
          WHERE t.side='sell' AND substr(t.date,1,4)=?1 ORDER BY a.ticker, t.date",
     )?;
@@ -470,16 +763,21 @@ fn realized_gains(conn: &Connection, year: &str) -> Result<Vec<RealizedGainRow>>
             r.get::<_, String>(3)?,
             r.get::<_, String>(4)?,
             r.get::<_, String>(5)?,
+            r.get::<_, String>(6)?,
         ))
     })?;
 
     let mut lot_stmt = conn.prepare(
-        "SELECT t.date, t.quantity, t.price, t.fees FROM trades t JOIN assets a ON t.asset_id=a.id
+        "SELECT t.date, t.quantity, t.price, t.fees, COALESCE(t.fee_currency, a.currency),
+                a.currency
+         FROM trades t JOIN assets a ON t.asset_id=a.id
          WHERE a.ticker=?1 AND t.side='buy' ORDER BY t.date",
     )?;
 
     let mut prior_sell_stmt = conn.prepare(
-        "SELECT t.date, t.quantity, t.price, t.fees FROM trades t JOIN assets a ON t.asset_id=a.id
+        "SELECT t.date, t.quantity, t.price, t.fees, COALESCE(t.fee_currency, a.currency),
+                a.currency
+         FROM trades t JOIN assets a ON t.asset_id=a.id
          WHERE a.ticker=?1 AND t.side='sell' AND t.date<?2 ORDER BY t.date",
     )?;
 
@@ -488,7 +786,7 @@ fn realized_gains(conn: &Connection, year: &str) -> Result<Vec<RealizedGainRow>>
     let mut results = Vec::new();
 
     for sell in sells {
-        let (ticker, sell_date, qty_s, price_s, fee_s, currency) = sell?;
+        let (ticker, sell_date, qty_s, price_s, fee_s, currency, fee_ccy) = sell?;
         let sell_qty_raw = Decimal::from_str_exact(&qty_s)
             .with_context(|| format!("Invalid sell quantity '{}' for {}", qty_s, ticker))?;
         let sell_qty = sell_qty_raw.abs();
@@ -507,11 +805,12 @@ fn realized_gains(conn: &Connection, year: &str) -> Result<Vec<RealizedGainRow>>
             .with_context(|| format!("Invalid sell fees '{}' for {}", fee_s, ticker))?;
         let sell_date_parsed = parse_date(&sell_date)
             .with_context(|| format!("Invalid sell date '{}' for {}", sell_date, ticker))?;
+        let sell_fees = fees_in(conn, sell_date_parsed, sell_fees, &fee_ccy, &currency)?;
 
         let lots = match lots_cache.entry(ticker.clone()) {
             Entry::Occupied(entry) => entry.into_mut(),
             Entry::Vacant(entry) => {
-                let loaded = load_buy_lots(&mut lot_stmt, &ticker)?;
+                let loaded = load_buy_lots(conn, &mut lot_stmt, &ticker)?;
                 entry.insert(loaded)
             }
         };
@@ -525,7 +824,7 @@ fn realized_gains(conn: &Connection, year: &str) -> Result<Vec<RealizedGainRow>>
         }
 
         if pre_consumed.insert(ticker.clone()) {
-            let prior_sells = load_sells_before(&mut prior_sell_stmt, &ticker, year_start)?;
+            let prior_sells = load_sells_before(conn, &mut prior_sell_stmt, &ticker, year_start)?;
             for sell in prior_sells {
                 match_sell_against_lots(
                     &ticker,
@@ -558,6 +857,337 @@ fn realized_gains(conn: &Connection, year: &str) -> Result<Vec<RealizedGainRow>>
     Ok(results)
 }
 
+fn grant_cmd(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => add_grant(conn, sub),
+        Some(("vest", sub)) => vest_grant(conn, sub),
+        Some(("list", _)) => list_grants(conn),
+        _ => Ok(()),
+    }
+}
+
+fn parse_vest_schedule(raw: &str) -> Result<Vec<(NaiveDate, Decimal)>> {
+    let mut schedule = Vec::new();
+    for part in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (date_s, qty_s) = part
+            .split_once(':')
+            .with_context(|| format!("Invalid vest entry '{}', expected YYYY-MM-DD:QTY", part))?;
+        let date = parse_date(date_s.trim())?;
+        let qty = parse_decimal(qty_s.trim())?;
+        ensure!(
+            qty > Decimal::ZERO,
+            "Vest quantity must be positive in '{}'",
+            part
+        );
+        schedule.push((date, qty));
+    }
+    schedule.sort_by_key(|(date, _)| *date);
+    if let Some(pair) = schedule.windows(2).find(|w| w[0].0 == w[1].0) {
+        bail!(
+            "Vest date {} appears more than once in the schedule; give it one combined quantity",
+            pair[0].0
+        );
+    }
+    Ok(schedule)
+}
+
+fn add_grant(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let ticker = sub.get_one::<String>("ticker").unwrap().trim().to_string();
+    let account = sub.get_one::<String>("account").unwrap().trim().to_string();
+    let kind = sub.get_one::<String>("kind").unwrap().trim().to_lowercase();
+    ensure!(
+        kind == "rsu" || kind == "espp",
+        "Unknown grant kind '{}' (use rsu|espp)",
+        kind
+    );
+    let date = parse_date(sub.get_one::<String>("date").unwrap().trim())?;
+    let quantity = parse_decimal(sub.get_one::<String>("quantity").unwrap().trim())?;
+    ensure!(quantity > Decimal::ZERO, "Grant quantity must be positive");
+    let purchase_price = match sub.get_one::<String>("purchase-price") {
+        Some(raw) => parse_decimal(raw.trim())?,
+        None => Decimal::ZERO,
+    };
+    let schedule = match sub.get_one::<String>("schedule") {
+        Some(raw) => parse_vest_schedule(raw)?,
+        None => Vec::new(),
+    };
+    let scheduled_total: Decimal = schedule.iter().map(|(_, qty)| *qty).sum();
+    ensure!(
+        scheduled_total <= quantity,
+        "Vest schedule totals {} but the grant is only {} shares",
+        scheduled_total,
+        quantity
+    );
+
+    let asset_id = id_for_asset(conn, &ticker)?;
+    let account_id = id_for_account(conn, &account)?;
+
+    let tx = conn.savepoint()?;
+    tx.execute(
+        "INSERT INTO grants(asset_id, account_id, kind, grant_date, quantity, purchase_price)
+         VALUES (?1,?2,?3,?4,?5,?6)",
+        params![
+            asset_id,
+            account_id,
+            &kind,
+            date.to_string(),
+            quantity.to_string(),
+            purchase_price.to_string()
+        ],
+    )?;
+    let grant_id = tx.last_insert_rowid();
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO grant_schedule(grant_id, date, quantity) VALUES (?1,?2,?3)",
+        )?;
+        for (vest_date, qty) in &schedule {
+            stmt.execute(params![grant_id, vest_date.to_string(), qty.to_string()])?;
+        }
+    }
+    tx.commit()?;
+    println!(
+        "Added {} grant #{}: {} x {} ({} scheduled vests)",
+        kind.to_uppercase(),
+        grant_id,
+        quantity,
+        ticker,
+        schedule.len()
+    );
+    Ok(())
+}
+
+fn vest_grant(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let grant_id = sub
+        .get_one::<String>("id")
+        .unwrap()
+        .trim()
+        .parse::<i64>()
+        .context("Invalid grant id")?;
+    let date = parse_date(sub.get_one::<String>("date").unwrap().trim())?;
+    let fmv = parse_decimal(sub.get_one::<String>("fmv").unwrap().trim())?;
+    ensure!(fmv >= Decimal::ZERO, "Fair market value cannot be negative");
+
+    let tx = conn.savepoint()?;
+    let (asset_id, account_id, ticker, grant_qty_s, purchase_s): (
+        i64,
+        i64,
+        String,
+        String,
+        String,
+    ) = tx
+        .query_row(
+            "SELECT g.asset_id, g.account_id, a.ticker, g.quantity, g.purchase_price
+             FROM grants g JOIN assets a ON g.asset_id=a.id WHERE g.id=?1",
+            params![grant_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+        )
+        .optional()?
+        .with_context(|| format!("Grant #{} not found", grant_id))?;
+    let grant_qty = Decimal::from_str_exact(&grant_qty_s)
+        .with_context(|| format!("Invalid grant quantity '{}'", grant_qty_s))?;
+    let purchase_price = Decimal::from_str_exact(&purchase_s)
+        .with_context(|| format!("Invalid purchase price '{}'", purchase_s))?;
+
+    let quantity = match sub.get_one::<String>("quantity") {
+        Some(raw) => parse_decimal(raw.trim())?,
+        None => {
+            let scheduled: Option<String> = tx
+                .query_row(
+                    "SELECT quantity FROM grant_schedule WHERE grant_id=?1 AND date=?2",
+                    params![grant_id, date.to_string()],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            let scheduled = scheduled.with_context(|| {
+                format!(
+                    "No vest scheduled for grant #{} on {}; pass --quantity",
+                    grant_id, date
+                )
+            })?;
+            Decimal::from_str_exact(&scheduled)
+                .with_context(|| format!("Invalid scheduled quantity '{}'", scheduled))?
+        }
+    };
+    ensure!(quantity > Decimal::ZERO, "Vest quantity must be positive");
+
+    let vested = vested_quantity(&tx, grant_id)?;
+    ensure!(
+        vested + quantity <= grant_qty,
+        "Vesting {} would exceed grant #{} ({} of {} already vested)",
+        quantity,
+        grant_id,
+        vested,
+        grant_qty
+    );
+
+    // Shares enter the position at FMV so later sells only realize post-vest gains;
+    // the ESPP discount (or full RSU value) is ordinary income on the vest date.
+    tx.execute(
+        "INSERT INTO trades(date, asset_id, account_id, quantity, price, fees, side, note)
+         VALUES (?1,?2,?3,?4,?5,'0','buy',?6)",
+        params![
+            date.to_string(),
+            asset_id,
+            account_id,
+            quantity.to_string(),
+            fmv.to_string(),
+            format!("vest: grant #{}", grant_id)
+        ],
+    )?;
+    let trade_id = tx.last_insert_rowid();
+    let income = (fmv - purchase_price) * quantity;
+    tx.execute(
+        "INSERT INTO grant_vests(grant_id, date, quantity, fmv, income, trade_id)
+         VALUES (?1,?2,?3,?4,?5,?6)",
+        params![
+            grant_id,
+            date.to_string(),
+            quantity.to_string(),
+            fmv.to_string(),
+            income.to_string(),
+            trade_id
+        ],
+    )?;
+    tx.commit()?;
+    println!(
+        "Vested {} x {} @ {} from grant #{} (income {})",
+        quantity,
+        ticker,
+        fmv,
+        grant_id,
+        fmt_amount(income, "")
+    );
+    Ok(())
+}
+
+fn vested_quantity(conn: &Connection, grant_id: i64) -> Result<Decimal> {
+    let mut stmt = conn.prepare_cached("SELECT quantity FROM grant_vests WHERE grant_id=?1")?;
+    let rows = stmt.query_map(params![grant_id], |r| r.get::<_, String>(0))?;
+    let mut total = Decimal::ZERO;
+    for row in rows {
+        let qty_s = row?;
+        total += Decimal::from_str_exact(&qty_s)
+            .with_context(|| format!("Invalid vest quantity '{}'", qty_s))?;
+    }
+    Ok(total)
+}
+
+fn list_grants(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT g.id, a.ticker, g.kind, g.grant_date, g.quantity,
+                (SELECT MIN(s.date) FROM grant_schedule s
+                  WHERE s.grant_id=g.id
+                    AND s.date NOT IN (SELECT v.date FROM grant_vests v WHERE v.grant_id=g.id))
+         FROM grants g JOIN assets a ON g.asset_id=a.id ORDER BY g.grant_date, g.id",
+    )?;
+    let rows = stmt.query_map([], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
+            r.get::<_, String>(4)?,
+            r.get::<_, Option<String>>(5)?,
+        ))
+    })?;
+    let mut income_stmt =
+        conn.prepare_cached("SELECT income FROM grant_vests WHERE grant_id=?1")?;
+    let mut data = Vec::new();
+    for row in rows {
+        let (id, ticker, kind, grant_date, qty, next_vest) = row?;
+        let vested = vested_quantity(conn, id)?;
+        let mut income = Decimal::ZERO;
+        for value in income_stmt.query_map(params![id], |r| r.get::<_, String>(0))? {
+            let value = value?;
+            income += Decimal::from_str_exact(&value)
+                .with_context(|| format!("Invalid vest income '{}'", value))?;
+        }
+        data.push(vec![
+            id.to_string(),
+            ticker,
+            kind.to_uppercase(),
+            grant_date,
+            qty,
+            vested.to_string(),
+            fmt_amount(income, ""),
+            next_vest.unwrap_or_default(),
+        ]);
+    }
+    crate::utils::emit(pretty_table(
+        &[
+            "ID",
+            "Ticker",
+            "Kind",
+            "Granted",
+            "Qty",
+            "Vested",
+            "Income",
+            "Next Vest",
+        ],
+        data,
+    ));
+    Ok(())
+}
+
+fn vest_income_rows(conn: &Connection, year: &str) -> Result<Vec<Vec<String>>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.ticker, v.date, v.quantity, v.fmv, v.income, a.currency
+         FROM grant_vests v
+         JOIN grants g ON v.grant_id=g.id
+         JOIN assets a ON g.asset_id=a.id
+         WHERE substr(v.date,1,4)=?1 ORDER BY v.date, a.ticker",
+    )?;
+    let rows = stmt.query_map([year], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
+            r.get::<_, String>(4)?,
+            r.get::<_, String>(5)?,
+        ))
+    })?;
+    let mut data = Vec::new();
+    for row in rows {
+        let (ticker, date, qty, fmv, income_s, ccy) = row?;
+        let income = Decimal::from_str_exact(&income_s)
+            .with_context(|| format!("Invalid vest income '{}' for {}", income_s, ticker))?;
+        let income = fmt_amount(income, &ccy);
+        data.push(vec![ticker, date, qty, fmv, ccy, income]);
+    }
+    Ok(data)
+}
+
+fn dividend_income_rows(conn: &Connection, year: &str) -> Result<Vec<Vec<String>>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.ticker, d.date, COALESCE(t.quantity, ''), COALESCE(t.price, ''), a.currency,
+                d.amount
+         FROM dividends d
+         JOIN assets a ON d.asset_id=a.id
+         LEFT JOIN trades t ON d.trade_id=t.id
+         WHERE substr(d.date,1,4)=?1 ORDER BY d.date, a.ticker",
+    )?;
+    let rows = stmt.query_map([year], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
+            r.get::<_, String>(4)?,
+            r.get::<_, String>(5)?,
+        ))
+    })?;
+    let mut data = Vec::new();
+    for row in rows {
+        let (ticker, date, qty, price, ccy, amount_s) = row?;
+        let amount = Decimal::from_str_exact(&amount_s)
+            .with_context(|| format!("Invalid dividend '{}' for {}", amount_s, ticker))?;
+        let income = fmt_amount(amount, &ccy);
+        data.push(vec![ticker, date, qty, price, ccy, income]);
+    }
+    Ok(data)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -565,12 +1195,37 @@ mod tests {
     use rusqlite::Connection;
     use std::str::FromStr;
 
+    #[test]
+    fn auto_asset_details_come_from_the_listing() {
+        let quote = Quote {
+            regular_market_price: Some(110.2),
+            symbol: Some("VWRL.AS".into()),
+            currency: Some("EUR".into()),
+            long_name: Some("Vanguard FTSE All-World UCITS ETF".into()),
+            short_name: Some("VANGUARD FTSE AW".into()),
+        };
+        assert_eq!(
+            asset_details("VWRL.AS", &quote, None, None).unwrap(),
+            (
+                "Vanguard FTSE All-World UCITS ETF".to_string(),
+                "EUR".to_string()
+            )
+        );
+        assert_eq!(
+            asset_details("VWRL.AS", &quote, Some("VWRL".into()), Some("eur".into())).unwrap(),
+            ("VWRL".to_string(), "EUR".to_string())
+        );
+        let err = asset_details("VWRL.AS", &quote, None, Some("USD".into())).unwrap_err();
+        assert_eq!(err.to_string(), "VWRL.AS is listed in EUR, not USD");
+    }
+
     fn setup_conn() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
         conn.execute_batch(
             r#"
             CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
             CREATE TABLE assets(id INTEGER PRIMARY KEY AUTOINCREMENT, ticker TEXT, name TEXT, currency TEXT);
+            CREATE TABLE asset_aliases(id INTEGER PRIMARY KEY, asset_id INTEGER, symbol TEXT UNIQUE COLLATE NOCASE, effective TEXT);
             CREATE TABLE trades(
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 date TEXT NOT NULL,
@@ -579,7 +1234,9 @@ mod tests {
                 quantity TEXT NOT NULL,
                 price TEXT NOT NULL,
                 fees TEXT NOT NULL DEFAULT '0',
-                side TEXT NOT NULL
+                side TEXT NOT NULL,
+                note TEXT,
+                fee_currency TEXT
             );
             CREATE TABLE prices(
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -588,6 +1245,30 @@ mod tests {
                 price TEXT NOT NULL,
                 source TEXT NOT NULL
             );
+            CREATE TABLE grants(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                asset_id INTEGER NOT NULL,
+                account_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                grant_date TEXT NOT NULL,
+                quantity TEXT NOT NULL,
+                purchase_price TEXT NOT NULL DEFAULT '0'
+            );
+            CREATE TABLE grant_schedule(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                grant_id INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                quantity TEXT NOT NULL
+            );
+            CREATE TABLE grant_vests(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                grant_id INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                quantity TEXT NOT NULL,
+                fmv TEXT NOT NULL,
+                income TEXT NOT NULL,
+                trade_id INTEGER
+            );
             "#,
         )
         .unwrap();
@@ -615,6 +1296,7 @@ mod tests {
             .arg(arg!(--quantity <QTY>).required(true))
             .arg(arg!(--price <PRICE>).required(true))
             .arg(arg!(--fees <FEES>).required(false))
+            .arg(arg!(--"fee-currency" <CCY>).required(false))
             .try_get_matches_from([
                 "buy",
                 "--date",
@@ -698,7 +1380,7 @@ mod tests {
         )
         .unwrap();
 
-        let positions = portfolio_positions(&conn).unwrap();
+        let positions = portfolio_positions(&conn, None).unwrap();
         assert!(positions.is_empty());
     }
 
@@ -740,7 +1422,7 @@ mod tests {
         )
         .unwrap();
 
-        let positions = super::portfolio_positions(&conn).unwrap();
+        let positions = super::portfolio_positions(&conn, None).unwrap();
         assert_eq!(positions.len(), 1);
         let pos = &positions[0];
         assert_eq!(pos.ticker, "ABC");
@@ -755,6 +1437,91 @@ mod tests {
         assert_eq!(pos.market_value, expected_value);
     }
 
+    #[test]
+    fn drip_buys_fractional_shares_and_records_the_dividend() {
+        let mut conn = setup_conn();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE dividends(id INTEGER PRIMARY KEY, date TEXT, asset_id INTEGER, account_id INTEGER, amount TEXT, trade_id INTEGER);
+            INSERT INTO accounts(id, name, type, currency) VALUES (1, 'Broker', 'broker', 'USD');
+            INSERT INTO assets(id, ticker, name, currency) VALUES (1, 'VTI', 'Vanguard Total', 'USD');
+            INSERT INTO prices(asset_id, as_of, price, source) VALUES (1, '2025-03-27T21:00:00+00:00', '280', 'yahoo');
+            "#,
+        )
+        .unwrap();
+        let run = |conn: &mut Connection, extra: &[&str]| {
+            let mut argv = vec![
+                "moneyclip",
+                "portfolio",
+                "trade",
+                "drip",
+                "--date",
+                "2025-03-28",
+                "--ticker",
+                "VTI",
+                "--account",
+                "Broker",
+                "--amount",
+                "12.34",
+            ];
+            argv.extend_from_slice(extra);
+            let m = crate::cli::build_cli().get_matches_from(argv);
+            super::handle(conn, m.subcommand().unwrap().1)
+        };
+        run(&mut conn, &[]).unwrap();
+        run(&mut conn, &["--price", "0"]).unwrap_err();
+
+        let (qty, price, note): (String, String, String) = conn
+            .query_row("SELECT quantity, price, note FROM trades", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(
+            (qty.as_str(), price.as_str(), note.as_str()),
+            ("0.044071", "280", "drip")
+        );
+        let rows = super::dividend_income_rows(&conn, "2025").unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][2], "0.044071");
+        assert_eq!(rows[0][5], "12.34");
+        assert_eq!(
+            super::portfolio_positions(&conn, None).unwrap()[0]
+                .quantity
+                .to_string(),
+            "0.044071"
+        );
+    }
+
+    #[test]
+    fn value_at_uses_trades_prices_and_fx_on_or_before_the_date() {
+        let conn = setup_conn();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+            CREATE TABLE fx_rates(date TEXT, base TEXT, quote TEXT, rate TEXT);
+            INSERT INTO accounts(id, name, type, currency) VALUES (1, 'Broker', 'broker', 'USD');
+            INSERT INTO assets(id, ticker, name, currency) VALUES (1, 'ABC', 'ABC Corp', 'USD');
+            INSERT INTO trades(date, asset_id, account_id, quantity, price, side)
+                VALUES ('2024-03-01', 1, 1, '10', '90', 'buy'),
+                       ('2025-01-10', 1, 1, '5', '120', 'buy');
+            INSERT INTO prices(asset_id, as_of, price, source)
+                VALUES (1, '2024-12-30T21:00:00+00:00', '100', 'yahoo'),
+                       (1, '2025-01-02T21:00:00+00:00', '130', 'yahoo');
+            INSERT INTO fx_rates(date, base, quote, rate)
+                VALUES ('2024-12-27', 'USD', 'EUR', '0.9'),
+                       ('2025-01-02', 'USD', 'EUR', '0.8');
+            "#,
+        )
+        .unwrap();
+
+        let at = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let positions = super::portfolio_positions(&conn, Some(at)).unwrap();
+        assert_eq!(positions[0].quantity, Decimal::from(10));
+        assert_eq!(positions[0].price_date.as_deref(), Some("2024-12-30"));
+        let value = super::portfolio_value_at(&conn, at, "EUR").unwrap();
+        assert_eq!(value, Decimal::from(900));
+    }
+
     #[test]
     fn realized_gains_respect_fifo_across_multiple_sells() {
         let conn = setup_conn();
@@ -800,6 +1567,27 @@ mod tests {
         assert_eq!(rows[1].realized_gain, expected_second);
     }
 
+    #[test]
+    fn realized_gains_convert_fees_charged_in_another_currency() {
+        let conn = setup_conn();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+            CREATE TABLE fx_rates(date TEXT, base TEXT, quote TEXT, rate TEXT);
+            INSERT INTO fx_rates(date, base, quote, rate) VALUES ('2025-01-01', 'EUR', 'USD', '1.1');
+            INSERT INTO accounts(id, name, type, currency) VALUES (1, 'Broker', 'broker', 'EUR');
+            INSERT INTO assets(id, ticker, name, currency) VALUES (1, 'ABC', 'ABC Corp', 'USD');
+            INSERT INTO trades(date, asset_id, account_id, quantity, price, fees, side, fee_currency)
+                VALUES ('2025-02-03', 1, 1, '10', '100', '10', 'buy', 'EUR'),
+                       ('2025-05-06', 1, 1, '10', '120', '5', 'sell', NULL);
+            "#,
+        )
+        .unwrap();
+
+        let rows = realized_gains(&conn, "2025").unwrap();
+        assert_eq!(rows[0].realized_gain, Decimal::from_str("184").unwrap());
+    }
+
     #[test]
     fn realized_gains_error_when_lots_missing() {
         let conn = setup_conn();
@@ -929,35 +1717,310 @@ mod tests {
         let expected_gain = Decimal::from_str("198").unwrap();
         assert_eq!(rows[0].realized_gain, expected_gain);
     }
-}
+    fn grant_matches(args: &[&str]) -> clap::ArgMatches {
+        let mut argv = vec!["moneyclip", "portfolio", "grant"];
+        argv.extend_from_slice(args);
+        let matches = crate::cli::build_cli().get_matches_from(argv);
+        let (_, portfolio_m) = matches.subcommand().unwrap();
+        let (_, grant_m) = portfolio_m.subcommand().unwrap();
+        grant_m.clone()
+    }
 
-#[derive(Debug, Deserialize)]
-#[allow(non_snake_case)]
-struct YahooResponse {
-    quoteResponse: QuoteResponse,
-}
-#[derive(Debug, Deserialize)]
-#[allow(non_snake_case)]
-struct QuoteResponse {
-    result: Vec<YahooQuote>,
-}
-#[derive(Debug, Deserialize)]
-struct YahooQuote {
-    #[serde(rename = "regularMarketPrice")]
-    regular_market_price: Option<f64>,
-    symbol: Option<String>,
-    #[serde(rename = "currency")]
-    _currency: Option<String>,
+    #[test]
+    fn weekly_price_prune_keeps_week_and_month_end_rows() {
+        let mut conn = setup_conn();
+        conn.execute_batch(
+            r#"
+            INSERT INTO assets(id, ticker, name, currency) VALUES (1, 'ABC', 'ABC Corp', 'USD');
+            INSERT INTO prices(asset_id, as_of, price, source) VALUES
+                (1, '2024-01-29T10:00:00+00:00', '1', 'yahoo'),
+                (1, '2024-01-30T10:00:00+00:00', '2', 'yahoo'),
+                (1, '2024-01-31T10:00:00+00:00', '3', 'yahoo'),
+                (1, '2024-02-01T10:00:00+00:00', '4', 'yahoo'),
+                (1, '2024-02-02T09:00:00+00:00', '5', 'yahoo'),
+                (1, '2024-02-02T16:00:00+00:00', '6', 'yahoo'),
+                (1, '2024-02-05T10:00:00+00:00', '7', 'yahoo'),
+                (1, '2024-02-06T10:00:00+00:00', '8', 'yahoo');
+            "#,
+        )
+        .unwrap();
+        let before = NaiveDate::from_ymd_opt(2024, 2, 6).unwrap();
+
+        let preview = prune_prices(&mut conn, PriceKeep::Weekly, before, true).unwrap();
+        assert_eq!(
+            preview,
+            vec![PrunedPrices {
+                ticker: "ABC".into(),
+                rows: 7,
+                removed: 4,
+            }]
+        );
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM prices", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 8);
+
+        prune_prices(&mut conn, PriceKeep::Weekly, before, false).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT price FROM prices ORDER BY as_of")
+            .unwrap();
+        let left: Vec<String> = stmt
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        // Jan 31 is the month-end point inside the week ending Feb 2.
+        assert_eq!(left, vec!["3", "6", "7", "8"]);
+    }
+
+    #[test]
+    fn vest_schedule_rejects_a_date_given_twice() {
+        let schedule = parse_vest_schedule("2026-01-01:5, 2025-01-01:5").unwrap();
+        assert_eq!(schedule[0].0, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        let err = parse_vest_schedule("2025-01-01:5,2026-01-01:5,2025-01-01:2").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Vest date 2025-01-01 appears more than once in the schedule; give it one combined quantity"
+        );
+    }
+
+    #[test]
+    fn espp_vest_books_trade_at_fmv_and_discount_income() {
+        let mut conn = setup_conn();
+        conn.execute(
+            "INSERT INTO accounts(id, name, type, currency) VALUES (1, 'Broker', 'broker', 'USD')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO assets(id, ticker, name, currency) VALUES (1, 'EMP', 'Employer', 'USD')",
+            [],
+        )
+        .unwrap();
+
+        grant_cmd(
+            &mut conn,
+            &grant_matches(&[
+                "add",
+                "--ticker",
+                "EMP",
+                "--account",
+                "Broker",
+                "--kind",
+                "espp",
+                "--date",
+                "2025-01-01",
+                "--quantity",
+                "10",
+                "--purchase-price",
+                "85",
+                "--schedule",
+                "2025-06-30:6",
+            ]),
+        )
+        .unwrap();
+        grant_cmd(
+            &mut conn,
+            &grant_matches(&["vest", "--id", "1", "--date", "2025-06-30", "--fmv", "100"]),
+        )
+        .unwrap();
+
+        let (qty, price, side): (String, String, String) = conn
+            .query_row("SELECT quantity, price, side FROM trades", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(
+            (qty.as_str(), price.as_str(), side.as_str()),
+            ("6", "100", "buy")
+        );
+        let income: String = conn
+            .query_row("SELECT income FROM grant_vests", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(Decimal::from_str(&income).unwrap(), Decimal::from(90));
+
+        let err = grant_cmd(
+            &mut conn,
+            &grant_matches(&[
+                "vest",
+                "--id",
+                "1",
+                "--date",
+                "2025-12-31",
+                "--fmv",
+                "110",
+                "--quantity",
+                "5",
+            ]),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("would exceed grant #1"));
+    }
 }
 
 fn price_cmd(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
-        Some(("fetch", _)) => fetch_prices(conn),
+        Some(("fetch", sub)) => {
+            let fired = fetch_prices(conn)?;
+            crate::commands::price_alerts::notify(
+                conn,
+                sub.get_flag("email"),
+                sub.get_one::<String>("webhook").map(|u| u.trim()),
+                &fired,
+            )
+        }
         Some(("list", _)) => list_prices(conn),
+        Some(("prune", sub)) => prune_prices_cmd(conn, sub),
         _ => Ok(()),
     }
 }
 
+/// Keep only the last price row per asset per day; `fetch` stores a full timestamp,
+/// so repeated fetches within a day otherwise pile up.
+pub fn prune_intraday_prices(conn: &Connection) -> Result<usize> {
+    let deleted = conn.execute(
+        "DELETE FROM prices WHERE id IN (
+             SELECT id FROM (
+                 SELECT id,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY asset_id, substr(as_of, 1, 10)
+                            ORDER BY as_of DESC, id DESC
+                        ) AS rn
+                 FROM prices
+             ) WHERE rn > 1
+         )",
+        [],
+    )?;
+    Ok(deleted)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceKeep {
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PrunedPrices {
+    pub ticker: String,
+    pub rows: usize,
+    pub removed: usize,
+}
+
+fn prune_prices_cmd(conn: &mut Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let keep = match sub.get_one::<String>("keep").unwrap().trim() {
+        "daily" => PriceKeep::Daily,
+        "weekly" => PriceKeep::Weekly,
+        other => return Err(anyhow!("Unknown --keep '{}'; use daily or weekly", other)),
+    };
+    let before = parse_date(sub.get_one::<String>("before").unwrap().trim())?;
+    let dry_run = sub.get_flag("dry-run");
+    let summary = prune_prices(conn, keep, before, dry_run)?;
+    let removed: usize = summary.iter().map(|s| s.removed).sum();
+    let rows = summary
+        .into_iter()
+        .map(|s| {
+            vec![
+                s.ticker,
+                s.rows.to_string(),
+                (s.rows - s.removed).to_string(),
+                s.removed.to_string(),
+            ]
+        })
+        .collect();
+    crate::utils::emit(pretty_table(&["Ticker", "Rows", "Kept", "Removed"], rows));
+    if dry_run {
+        println!(
+            "(dry-run) {} price rows before {} would be removed",
+            removed, before
+        );
+    } else {
+        println!("Removed {} price rows before {}", removed, before);
+    }
+    Ok(())
+}
+
+/// Downsample price rows dated before `before` to the last row per day or ISO week.
+/// The last row of every month is always kept so month-end valuations are unchanged.
+pub fn prune_prices(
+    conn: &mut Connection,
+    keep: PriceKeep,
+    before: NaiveDate,
+    dry_run: bool,
+) -> Result<Vec<PrunedPrices>> {
+    use chrono::Datelike;
+
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.asset_id, COALESCE(a.ticker, '#' || p.asset_id), p.as_of
+         FROM prices p LEFT JOIN assets a ON a.id = p.asset_id
+         WHERE substr(p.as_of, 1, 10) < ?1
+         ORDER BY p.asset_id, p.as_of, p.id",
+    )?;
+    let rows = stmt.query_map([before.to_string()], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, i64>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut summary: Vec<PrunedPrices> = Vec::new();
+    let mut asset_ids: Vec<i64> = Vec::new();
+    let mut last_in_bucket: HashMap<(i64, i32, u32), i64> = HashMap::new();
+    let mut last_in_month: HashMap<(i64, i32, u32), i64> = HashMap::new();
+    let mut all_ids = Vec::new();
+    for row in rows {
+        let (id, asset_id, ticker, as_of) = row?;
+        let day = as_of.get(..10).unwrap_or(&as_of);
+        let date = parse_date(day)
+            .with_context(|| format!("Invalid price date '{}' for {}", as_of, ticker))?;
+        let bucket = match keep {
+            PriceKeep::Daily => (date.year(), date.ordinal()),
+            PriceKeep::Weekly => (date.iso_week().year(), date.iso_week().week()),
+        };
+        last_in_bucket.insert((asset_id, bucket.0, bucket.1), id);
+        last_in_month.insert((asset_id, date.year(), date.month()), id);
+        if asset_ids.last() != Some(&asset_id) {
+            asset_ids.push(asset_id);
+            summary.push(PrunedPrices {
+                ticker,
+                rows: 0,
+                removed: 0,
+            });
+        }
+        summary.last_mut().unwrap().rows += 1;
+        all_ids.push((summary.len() - 1, id));
+    }
+    drop(stmt);
+
+    let kept: HashSet<i64> = last_in_bucket
+        .into_values()
+        .chain(last_in_month.into_values())
+        .collect();
+    let doomed: Vec<i64> = all_ids
+        .into_iter()
+        .filter(|(_, id)| !kept.contains(id))
+        .map(|(idx, id)| {
+            summary[idx].removed += 1;
+            id
+        })
+        .collect();
+
+    if !dry_run && !doomed.is_empty() {
+        let tx = conn.savepoint()?;
+        {
+            let mut delete = tx.prepare_cached("DELETE FROM prices WHERE id = ?1")?;
+            for id in &doomed {
+                delete.execute([id])?;
+            }
+        }
+        tx.commit()?;
+    }
+    summary.retain(|s| s.removed > 0);
+    Ok(summary)
+}
+
 fn list_prices(conn: &Connection) -> Result<()> {
     let mut stmt = conn.prepare(
         "SELECT a.ticker, p.as_of, p.price, a.currency, p.source
@@ -976,68 +2039,97 @@ fn list_prices(conn: &Connection) -> Result<()> {
     let mut data = Vec::new();
     for row in rows {
         let (tic, ts, px, ccy, src) = row?;
-        data.push(vec![tic, ts, px, ccy, src]);
+        data.push(vec![
+            tic,
+            crate::utils::display_timestamp(&ts),
+            px,
+            ccy,
+            src,
+        ]);
     }
-    println!(
-        "{}",
-        pretty_table(&["Ticker", "As Of", "Price", "CCY", "Source"], data)
-    );
+    crate::utils::emit(pretty_table(
+        &["Ticker", "As Of", "Price", "CCY", "Source"],
+        data,
+    ));
     Ok(())
 }
 
-fn fetch_prices(conn: &mut Connection) -> Result<()> {
-    let mut stmt = conn.prepare_cached("SELECT id, ticker FROM assets ORDER BY ticker")?;
-    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
-
-    let mut assets = Vec::new();
-    for row in rows {
-        assets.push(row?);
-    }
-
-    drop(stmt);
-
+/// Fetch the latest quotes, then check price alerts against them; returns the alerts
+/// that fired.
+fn fetch_prices(conn: &mut Connection) -> Result<Vec<crate::commands::price_alerts::FiredAlert>> {
+    let assets = quote_symbols(conn)?;
     if assets.is_empty() {
         println!("No assets to fetch");
-        return Ok(());
+        return Ok(Vec::new());
     }
 
+    crate::utils::ensure_provider(conn, "price_provider", "yahoo")?;
     let symbols = assets
         .iter()
         .map(|(_, ticker)| ticker.as_str())
         .collect::<Vec<_>>();
-    let url = format!(
-        "https://query1.finance.yahoo.com/v7/finance/quote?symbols={}",
-        symbols.join(",")
-    );
-    let client = http_client()?;
-    let resp = client.get(url).send()?.error_for_status()?;
-    let yr: YahooResponse = resp.json()?;
+    let progress =
+        crate::progress::spinner(format!("Fetching {} price(s) from Yahoo", symbols.len()));
+    let quotes = Yahoo.quotes(&symbols)?;
+    drop(progress);
+    store_quotes(conn, &assets, quotes)?;
+    crate::commands::price_alerts::check_alerts(conn)
+}
 
+/// Symbols to quote for every asset: the one in effect today, which before an
+/// announced rename is the old one.
+pub(crate) fn quote_symbols(conn: &Connection) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare_cached("SELECT id, ticker FROM assets ORDER BY ticker")?;
+    let assets = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let today = crate::utils::today();
+    assets
+        .into_iter()
+        .map(|(id, ticker)| {
+            Ok((
+                id,
+                crate::commands::assets::symbol_on(conn, id, &ticker, today)?,
+            ))
+        })
+        .collect()
+}
+
+/// Store the prices of `quotes` for `assets` (id, symbol) at the current time; returns
+/// how many were stored.
+pub(crate) fn store_quotes(
+    conn: &mut Connection,
+    assets: &[(i64, String)],
+    quotes: Vec<Quote>,
+) -> Result<usize> {
     let mut id_by_ticker: HashMap<&str, i64> = HashMap::with_capacity(assets.len());
-    for (id, ticker) in &assets {
+    for (id, ticker) in assets {
         id_by_ticker.insert(ticker.as_str(), *id);
     }
 
-    let mut updates = Vec::with_capacity(yr.quoteResponse.result.len());
-    for q in yr.quoteResponse.result {
-        if let (Some(sym), Some(px)) = (q.symbol, q.regular_market_price) {
-            if let Some(&asset_id) = id_by_ticker.get(sym.as_str()) {
-                if let Some(px_decimal) = Decimal::from_f64_retain(px) {
-                    updates.push((asset_id, px_decimal.to_string()));
-                }
-            }
+    let mut updates = Vec::with_capacity(quotes.len());
+    for q in quotes {
+        let (Some(sym), Some(px)) = (q.symbol, q.regular_market_price) else {
+            continue;
+        };
+        let Some(&asset_id) = id_by_ticker.get(sym.as_str()) else {
+            continue;
+        };
+        if let Some(px_decimal) = Decimal::from_f64_retain(px) {
+            updates.push((asset_id, px_decimal.to_string()));
         }
     }
 
-    let now = Utc::now().to_rfc3339();
+    // Local offset, so the stored day (`substr(as_of, 1, 10)`) is the configured zone's day.
+    let now = crate::utils::now_local().to_rfc3339();
     if updates.is_empty() {
         println!("No Yahoo prices updated at {}", now);
-        return Ok(());
+        return Ok(0);
     }
 
     let total_updates = updates.len();
 
-    let tx = conn.transaction()?;
+    let tx = conn.savepoint()?;
     let mut insert = tx.prepare_cached(
         "INSERT INTO prices(asset_id, as_of, price, source) VALUES (?1, ?2, ?3, 'yahoo')",
     )?;
@@ -1048,5 +2140,5 @@ fn fetch_prices(conn: &mut Connection) -> Result<()> {
     tx.commit()?;
 
     println!("Fetched {} prices at {}", total_updates, now);
-    Ok(())
+    Ok(total_updates)
 }
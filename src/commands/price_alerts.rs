@@ -0,0 +1,289 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Price alerts (`portfolio alert`): thresholds per asset, checked against the latest
+//! cached price after every `portfolio price fetch`. An alert fires once when the
+//! price crosses a threshold and again only after it has come back inside.
+
+use crate::logging::SendTraced;
+use crate::models::PriceAlertRow;
+use crate::utils::{id_for_asset, maybe_print_json, parse_decimal, pretty_table};
+use anyhow::{Context, Result, bail, ensure};
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+
+/// Exit status of `portfolio alert check` when an alert fired.
+pub const EXIT_ALERTS: i32 = 1;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => {
+            let ticker = sub.get_one::<String>("ticker").unwrap().trim();
+            let asset_id = id_for_asset(conn, ticker)?;
+            let above = threshold(sub, "above")?;
+            let below = threshold(sub, "below")?;
+            if let (Some(above), Some(below)) = (above, below) {
+                ensure!(
+                    below < above,
+                    "--below {} must be under --above {}",
+                    below,
+                    above
+                );
+            }
+            conn.execute(
+                "INSERT INTO price_alerts(asset_id, above, below) VALUES (?1, ?2, ?3)",
+                params![
+                    asset_id,
+                    above.map(|a| a.to_string()),
+                    below.map(|b| b.to_string())
+                ],
+            )?;
+            println!(
+                "Added alert {} on {}",
+                conn.last_insert_rowid(),
+                ticker.to_uppercase()
+            );
+        }
+        Some(("list", sub)) => {
+            let mut stmt = conn.prepare(
+                "SELECT al.id, a.ticker, al.above, al.below, al.state, al.fired_at
+                 FROM price_alerts al JOIN assets a ON a.id=al.asset_id
+                 ORDER BY a.ticker, al.id",
+            )?;
+            let data = stmt
+                .query_map([], |r| {
+                    Ok(PriceAlertRow {
+                        id: r.get(0)?,
+                        ticker: r.get(1)?,
+                        above: r.get(2)?,
+                        below: r.get(3)?,
+                        state: r.get(4)?,
+                        last_fired: r.get(5)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                println!(
+                    "{}",
+                    pretty_table(
+                        &["ID", "Ticker", "Above", "Below", "State", "Last fired"],
+                        data.into_iter().map(PriceAlertRow::cells).collect()
+                    )
+                );
+            }
+        }
+        Some(("rm", sub)) => {
+            let id = *sub.get_one::<i64>("id").unwrap();
+            if conn.execute("DELETE FROM price_alerts WHERE id=?1", params![id])? == 0 {
+                bail!("No price alert {}", id);
+            }
+            println!("Removed price alert {}", id);
+        }
+        Some(("check", sub)) => {
+            let fired = check_alerts(conn)?;
+            if !fired.is_empty() {
+                crate::utils::set_exit_code(EXIT_ALERTS);
+            }
+            notify(
+                conn,
+                sub.get_flag("email"),
+                sub.get_one::<String>("webhook").map(|u| u.trim()),
+                &fired,
+            )?;
+            if fired.is_empty() {
+                println!("No price alerts crossed");
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn threshold(sub: &clap::ArgMatches, name: &str) -> Result<Option<Decimal>> {
+    sub.get_one::<String>(name)
+        .map(|v| {
+            let price = parse_decimal(v.trim())
+                .with_context(|| format!("Invalid --{} '{}'", name, v.trim()))?;
+            ensure!(price > Decimal::ZERO, "--{} must be positive", name);
+            Ok(price)
+        })
+        .transpose()
+}
+
+/// A threshold crossed by the latest price.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct FiredAlert {
+    pub id: i64,
+    pub ticker: String,
+    pub currency: String,
+    pub price: Decimal,
+    /// `above` or `below`.
+    pub crossed: String,
+    pub threshold: Decimal,
+}
+
+impl std::fmt::Display for FiredAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {} {} is {} {}",
+            self.ticker, self.price, self.currency, self.crossed, self.threshold
+        )
+    }
+}
+
+/// Compare every alert with its asset's latest cached price and record where the price
+/// now is. Returns the alerts whose price moved beyond a threshold since the last check
+/// (or that start out beyond one).
+pub fn check_alerts(conn: &Connection) -> Result<Vec<FiredAlert>> {
+    let mut stmt = conn.prepare(
+        "SELECT al.id, a.ticker, a.currency, al.above, al.below, al.state,
+                (SELECT p.price FROM prices p WHERE p.asset_id=al.asset_id
+                 ORDER BY p.as_of DESC LIMIT 1)
+         FROM price_alerts al JOIN assets a ON a.id=al.asset_id
+         ORDER BY a.ticker, al.id",
+    )?;
+    type Row = (
+        i64,
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    );
+    let rows = stmt
+        .query_map([], |r| {
+            Ok((
+                r.get(0)?,
+                r.get(1)?,
+                r.get(2)?,
+                r.get(3)?,
+                r.get(4)?,
+                r.get(5)?,
+                r.get(6)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<Row>>>()?;
+    let parse = |raw: &str| {
+        raw.trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid price '{}'", raw))
+    };
+    let mut fired = Vec::new();
+    for (id, ticker, currency, above, below, state, price) in rows {
+        let Some(price) = price else {
+            continue;
+        };
+        let price = parse(&price)?;
+        let above = above.as_deref().map(parse).transpose()?;
+        let below = below.as_deref().map(parse).transpose()?;
+        let now = match (above, below) {
+            (Some(above), _) if price >= above => Some(("above", above)),
+            (_, Some(below)) if price <= below => Some(("below", below)),
+            _ => None,
+        };
+        let new_state = now.map_or("inside", |(side, _)| side);
+        if state.as_deref() == Some(new_state) {
+            continue;
+        }
+        if let Some((side, threshold)) = now {
+            conn.execute(
+                "UPDATE price_alerts SET state=?1, fired_at=datetime('now') WHERE id=?2",
+                params![side, id],
+            )?;
+            fired.push(FiredAlert {
+                id,
+                ticker,
+                currency,
+                price,
+                crossed: side.to_string(),
+                threshold,
+            });
+        } else {
+            conn.execute(
+                "UPDATE price_alerts SET state=?1 WHERE id=?2",
+                params![new_state, id],
+            )?;
+        }
+    }
+    Ok(fired)
+}
+
+/// Print fired alerts and pass them on by mail to `digest_to` and/or to a webhook.
+pub fn notify(
+    conn: &Connection,
+    email: bool,
+    webhook: Option<&str>,
+    fired: &[FiredAlert],
+) -> Result<()> {
+    if fired.is_empty() {
+        return Ok(());
+    }
+    let summary = fired
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    for alert in fired {
+        println!("Price alert: {}", alert);
+    }
+    if email {
+        let subject = format!("Moneyclip: {} price alerts", fired.len());
+        crate::commands::digest::send_email(conn, &subject, &summary)?;
+    }
+    if let Some(url) = webhook {
+        let body = serde_json::json!({
+            "text": format!("Price alerts:\n{}", summary),
+            "alerts": fired,
+        });
+        crate::utils::http_client()?
+            .post(url)
+            .json(&body)
+            .send_traced()
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("POST alerts to {}", url))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_fire_once_per_crossing() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT, name TEXT, currency TEXT);
+            CREATE TABLE prices(id INTEGER PRIMARY KEY, asset_id INTEGER, as_of TEXT, price TEXT, source TEXT);
+            CREATE TABLE price_alerts(id INTEGER PRIMARY KEY, asset_id INTEGER, above TEXT, below TEXT, state TEXT, fired_at TEXT);
+            INSERT INTO assets(id, ticker, name, currency) VALUES (1, 'AAPL', 'Apple', 'USD');
+            INSERT INTO price_alerts(asset_id, above, below) VALUES (1, '250', '180');
+            "#,
+        )
+        .unwrap();
+        let price = |as_of: &str, price: &str| {
+            conn.execute(
+                "INSERT INTO prices(asset_id, as_of, price, source) VALUES (1, ?1, ?2, 'test')",
+                params![as_of, price],
+            )
+            .unwrap();
+            check_alerts(&conn).unwrap()
+        };
+
+        assert!(check_alerts(&conn).unwrap().is_empty());
+        assert!(price("2025-06-02", "210").is_empty());
+        let fired = price("2025-06-03", "251.5");
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].to_string(), "AAPL at 251.5 USD is above 250");
+        assert!(price("2025-06-04", "260").is_empty());
+        assert!(price("2025-06-05", "200").is_empty());
+        assert_eq!(price("2025-06-06", "255")[0].crossed, "above");
+        assert_eq!(price("2025-06-07", "175")[0].crossed, "below");
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Projects: a free-form tag on transactions (`--project`) that cuts across
+//! categories, costed by `report by-project`.
+
+use crate::utils::{
+    fmt_amount, fx_convert_batch, get_base_currency, maybe_print_json, pretty_table,
+};
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+
+/// Income and costs of one project, or of one category within a project, in the base
+/// currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ProjectLine {
+    pub name: String,
+    pub transactions: usize,
+    pub first: String,
+    pub last: String,
+    pub income: Decimal,
+    /// Spending as a positive amount.
+    pub costs: Decimal,
+    pub net: Decimal,
+}
+
+pub fn by_project_report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let project = sub.get_one::<String>("project").map(|p| p.trim());
+    let (from, to) = crate::cli::date_range(sub)?;
+    let lines = project_costs(conn, project, from, to)?;
+    if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &lines)? {
+        return Ok(());
+    }
+    if lines.is_empty() {
+        crate::utils::emit(match project {
+            Some(p) => format!("No transactions for project '{}'", p),
+            None => "No transactions have a project; tag them with --project".to_string(),
+        });
+        return Ok(());
+    }
+    let base = get_base_currency(conn)?;
+    let (mut income, mut costs) = (Decimal::ZERO, Decimal::ZERO);
+    let mut count = 0;
+    let mut rows = Vec::new();
+    for l in &lines {
+        income += l.income;
+        costs += l.costs;
+        count += l.transactions;
+        rows.push(vec![
+            l.name.clone(),
+            l.transactions.to_string(),
+            format!("{} - {}", l.first, l.last),
+            fmt_amount(l.income, &base),
+            fmt_amount(l.costs, &base),
+            fmt_amount(l.net, &base),
+        ]);
+    }
+    if lines.len() > 1 {
+        rows.push(vec![
+            "Total".to_string(),
+            count.to_string(),
+            String::new(),
+            fmt_amount(income, &base),
+            fmt_amount(costs, &base),
+            fmt_amount(income - costs, &base),
+        ]);
+    }
+    let first = match project {
+        Some(p) => format!("Category ({})", p),
+        None => "Project".to_string(),
+    };
+    crate::utils::emit(pretty_table(
+        &[
+            &first,
+            "Tx",
+            "Dates",
+            "Income",
+            "Costs",
+            &format!("Net ({})", base),
+        ],
+        rows,
+    ));
+    Ok(())
+}
+
+/// Tagged transactions in the date range per project, or per category of `project`
+/// when one is given, each amount converted to the base currency at its date.
+pub fn project_costs(
+    conn: &Connection,
+    project: Option<&str>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<ProjectLine>> {
+    let base = get_base_currency(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT CASE WHEN ?1 IS NULL THEN t.project ELSE COALESCE(c.name, '(uncategorized)') END,
+                t.date, t.amount, t.currency
+         FROM transactions t LEFT JOIN categories c ON t.category_id=c.id
+         WHERE t.project IS NOT NULL AND (?1 IS NULL OR t.project=?1 COLLATE NOCASE)
+           AND (?2 IS NULL OR t.date>=?2) AND (?3 IS NULL OR t.date<=?3)
+         ORDER BY 1 COLLATE NOCASE, t.date",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![
+                project,
+                from.map(|d| d.to_string()),
+                to.map(|d| d.to_string())
+            ],
+            |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, String>(2)?,
+                    r.get::<_, String>(3)?,
+                ))
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut items = Vec::with_capacity(rows.len());
+    for (_, date, amount, currency) in &rows {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        items.push((date, amount, currency.as_str()));
+    }
+    let converted = fx_convert_batch(conn, &items, &base)?;
+    let mut out: Vec<ProjectLine> = Vec::new();
+    for ((name, date, ..), amount) in rows.iter().zip(converted) {
+        let line = match out.last_mut() {
+            Some(l) if l.name.eq_ignore_ascii_case(name) => l,
+            _ => {
+                out.push(ProjectLine {
+                    name: name.clone(),
+                    transactions: 0,
+                    first: date.clone(),
+                    last: date.clone(),
+                    income: Decimal::ZERO,
+                    costs: Decimal::ZERO,
+                    net: Decimal::ZERO,
+                });
+                out.last_mut().unwrap()
+            }
+        };
+        line.transactions += 1;
+        line.last = date.clone();
+        if amount > Decimal::ZERO {
+            line.income += amount;
+        } else {
+            line.costs -= amount;
+        }
+        line.net += amount;
+    }
+    Ok(out)
+}
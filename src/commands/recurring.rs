@@ -0,0 +1,496 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Generated transactions (`recurring run`). So far these are interest postings for
+//! accounts with an APY (`account interest set`). Interest for each compounding
+//! period is the account's balance at the period end times the period's rate, and is
+//! credited on the period's last day. Later periods earn interest on earlier interest.
+//! A `--dry-run` up to a future date shows how the balance grows without writing.
+
+use crate::models::InterestRow;
+use crate::utils::{
+    fmt_amount, fmt_percent, id_for_account, id_for_category, maybe_print_json, parse_date,
+    parse_decimal, pretty_table, today,
+};
+use anyhow::{Context, Result, bail, ensure};
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+
+/// Payee of posted interest transactions.
+pub const INTEREST_PAYEE: &str = "Interest";
+
+/// `account interest set|list|rm`.
+pub fn interest(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("set", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let account_id = id_for_account(conn, name)?;
+            let apy = parse_decimal(sub.get_one::<String>("apy").unwrap().trim())
+                .context("Invalid --apy")?;
+            ensure!(apy > Decimal::ZERO, "--apy must be positive");
+            let compounding = sub.get_one::<String>("compounding").unwrap();
+            let since = match sub.get_one::<String>("from") {
+                Some(d) => parse_date(d.trim())?,
+                None => today(),
+            };
+            let category_id = sub
+                .get_one::<String>("category")
+                .map(|c| id_for_category(conn, c.trim()))
+                .transpose()?;
+            // Changing the rate keeps what was already posted.
+            conn.execute(
+                "INSERT INTO account_interest(account_id, apy, compounding, category_id, since)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(account_id) DO UPDATE SET apy=excluded.apy,
+                    compounding=excluded.compounding, category_id=excluded.category_id",
+                params![
+                    account_id,
+                    apy.to_string(),
+                    compounding,
+                    category_id,
+                    since.to_string()
+                ],
+            )?;
+            println!(
+                "'{}' earns {}% APY, compounded {}; post it with `recurring run`",
+                name, apy, compounding
+            );
+        }
+        Some(("list", sub)) => {
+            let mut stmt = conn.prepare(
+                "SELECT a.name, i.apy, i.compounding, c.name, i.since, i.posted_through
+                 FROM account_interest i JOIN accounts a ON a.id=i.account_id
+                 LEFT JOIN categories c ON c.id=i.category_id
+                 ORDER BY a.name",
+            )?;
+            let data = stmt
+                .query_map([], |r| {
+                    Ok(InterestRow {
+                        account: r.get(0)?,
+                        apy_percent: r.get(1)?,
+                        compounding: r.get(2)?,
+                        category: r.get(3)?,
+                        since: r.get(4)?,
+                        posted_through: r.get(5)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                println!(
+                    "{}",
+                    pretty_table(
+                        &[
+                            "Account",
+                            "APY %",
+                            "Compounding",
+                            "Category",
+                            "Since",
+                            "Posted through"
+                        ],
+                        data.into_iter().map(InterestRow::cells).collect()
+                    )
+                );
+            }
+        }
+        Some(("rm", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let account_id = id_for_account(conn, name)?;
+            if conn.execute(
+                "DELETE FROM account_interest WHERE account_id=?1",
+                params![account_id],
+            )? == 0
+            {
+                bail!("'{}' has no interest configured", name);
+            }
+            println!("'{}' no longer earns interest; posted interest stays", name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `recurring run`.
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    if let Some(("run", sub)) = m.subcommand() {
+        let through = match sub.get_one::<String>("through") {
+            Some(d) => parse_date(d.trim())?,
+            None => today(),
+        };
+        let dry_run = sub.get_flag("dry-run");
+        let due = accruals(conn, through)?;
+        if !dry_run {
+            post(conn, &due, through)?;
+        }
+        if maybe_print_json(sub.get_flag("json"), false, &due)? {
+            return Ok(());
+        }
+        if due.is_empty() {
+            println!("Nothing due through {}", through);
+            return Ok(());
+        }
+        let rows = due
+            .iter()
+            .map(|a| {
+                vec![
+                    a.account.clone(),
+                    a.date.to_string(),
+                    fmt_amount(a.balance, &a.currency),
+                    fmt_percent(a.rate * Decimal::ONE_HUNDRED),
+                    fmt_amount(a.amount, &a.currency),
+                    a.currency.clone(),
+                ]
+            })
+            .collect();
+        let balance = if dry_run {
+            "Balance (projected)"
+        } else {
+            "Balance"
+        };
+        crate::utils::emit(pretty_table(
+            &["Account", "Date", balance, "Rate %", "Interest", "CCY"],
+            rows,
+        ));
+        if dry_run {
+            println!("Dry run: {} interest postings not written", due.len());
+        } else {
+            println!("Posted {} interest transactions", due.len());
+        }
+    }
+    Ok(())
+}
+
+/// One period's interest on one account.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Accrual {
+    #[serde(skip)]
+    pub account_id: i64,
+    #[serde(skip)]
+    pub category_id: Option<i64>,
+    pub account: String,
+    pub date: NaiveDate,
+    /// Balance at the period end, including interest of earlier periods in this run.
+    pub balance: Decimal,
+    /// Rate of one compounding period.
+    pub rate: Decimal,
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+/// How often interest is credited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compounding {
+    Daily,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Compounding {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "daily" => Self::Daily,
+            "monthly" => Self::Monthly,
+            "quarterly" => Self::Quarterly,
+            "yearly" => Self::Yearly,
+            other => bail!(
+                "Unknown compounding '{}' (daily|monthly|quarterly|yearly)",
+                other
+            ),
+        })
+    }
+
+    fn periods_per_year(self) -> u32 {
+        match self {
+            Self::Daily => 365,
+            Self::Monthly => 12,
+            Self::Quarterly => 4,
+            Self::Yearly => 1,
+        }
+    }
+
+    /// First day of the period containing `d`.
+    fn period_start(self, d: NaiveDate) -> NaiveDate {
+        let month = match self {
+            Self::Daily => return d,
+            Self::Monthly => d.month(),
+            Self::Quarterly => (d.month() - 1) / 3 * 3 + 1,
+            Self::Yearly => 1,
+        };
+        NaiveDate::from_ymd_opt(d.year(), month, 1).unwrap_or(d)
+    }
+
+    /// Last day of the period containing `d`.
+    fn period_end(self, d: NaiveDate) -> NaiveDate {
+        let months = match self {
+            Self::Daily => return d,
+            Self::Monthly => 1,
+            Self::Quarterly => 3,
+            Self::Yearly => 12,
+        };
+        self.period_start(d)
+            .checked_add_months(chrono::Months::new(months))
+            .and_then(|n| n.pred_opt())
+            .unwrap_or(d)
+    }
+
+    /// The last period end on or before `d`.
+    fn last_end(self, d: NaiveDate) -> NaiveDate {
+        if self.period_end(d) == d {
+            d
+        } else {
+            self.period_start(d).pred_opt().unwrap_or(d)
+        }
+    }
+
+    /// The rate per period that compounds to `apy` percent over a year.
+    pub fn period_rate(self, apy: Decimal) -> Result<Decimal> {
+        let yearly = (Decimal::ONE + apy / Decimal::ONE_HUNDRED)
+            .to_f64()
+            .context("APY out of range")?;
+        let rate = yearly.powf(1.0 / f64::from(self.periods_per_year())) - 1.0;
+        Ok(Decimal::from_f64(rate)
+            .context("APY out of range")?
+            .round_dp(12))
+    }
+}
+
+/// Interest due on every configured account for the periods that ended after what was
+/// last posted, up to `through`. Accrual starts with the first full period on or after
+/// the account's `--from` date.
+pub fn accruals(conn: &Connection, through: NaiveDate) -> Result<Vec<Accrual>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.account_id, a.name, a.currency, i.apy, i.compounding, i.category_id,
+                i.since, i.posted_through
+         FROM account_interest i JOIN accounts a ON a.id=i.account_id
+         ORDER BY a.name",
+    )?;
+    type Row = (
+        i64,
+        String,
+        String,
+        String,
+        String,
+        Option<i64>,
+        String,
+        Option<String>,
+    );
+    let configs = stmt
+        .query_map([], |r| {
+            Ok((
+                r.get(0)?,
+                r.get(1)?,
+                r.get(2)?,
+                r.get(3)?,
+                r.get(4)?,
+                r.get(5)?,
+                r.get(6)?,
+                r.get(7)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<Row>>>()?;
+    let mut out = Vec::new();
+    for (account_id, account, currency, apy, compounding, category_id, since, posted) in configs {
+        let compounding = Compounding::parse(&compounding)?;
+        let apy = parse_decimal(&apy).with_context(|| format!("Invalid APY of '{}'", account))?;
+        let rate = compounding.period_rate(apy)?;
+        let dp = crate::utils::minor_units(&currency);
+        let mut cursor = match posted {
+            Some(d) => parse_date(&d)?,
+            // The period end before the first full period.
+            None => {
+                let since = parse_date(&since)?;
+                let first = if compounding.period_start(since) == since {
+                    since
+                } else {
+                    compounding.period_end(since).succ_opt().unwrap_or(since)
+                };
+                first.pred_opt().context("Invalid --from")?
+            }
+        };
+        // Interest computed in this run but not yet in the ledger.
+        let mut accrued = Decimal::ZERO;
+        while let Some(next) = cursor.succ_opt() {
+            let end = compounding.period_end(next);
+            if end > through {
+                break;
+            }
+            let balance = balance_on(conn, account_id, end)? + accrued;
+            let amount = if balance > Decimal::ZERO {
+                (balance * rate).round_dp(dp)
+            } else {
+                Decimal::ZERO
+            };
+            if !amount.is_zero() {
+                accrued += amount;
+                out.push(Accrual {
+                    account_id,
+                    category_id,
+                    account: account.clone(),
+                    date: end,
+                    balance,
+                    rate,
+                    amount,
+                    currency: currency.clone(),
+                });
+            }
+            cursor = end;
+        }
+    }
+    Ok(out)
+}
+
+fn balance_on(conn: &Connection, account_id: i64, date: NaiveDate) -> Result<Decimal> {
+    let mut stmt =
+        conn.prepare_cached("SELECT amount FROM transactions WHERE account_id=?1 AND date <= ?2")?;
+    let amounts = stmt
+        .query_map(params![account_id, date.to_string()], |r| {
+            r.get::<_, String>(0)
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut total = Decimal::ZERO;
+    for amount in amounts {
+        total += amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amount))?;
+    }
+    Ok(total)
+}
+
+/// Write `due` as transactions and mark every configured account posted through
+/// `through`, so periods without interest are not looked at again.
+pub fn post(conn: &Connection, due: &[Accrual], through: NaiveDate) -> Result<()> {
+    conn.execute_batch("SAVEPOINT recurring_run")?;
+    let res = (|| -> Result<()> {
+        for a in due {
+            conn.execute(
+                "INSERT INTO transactions(date, account_id, amount, payee, category_id, currency, note)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'interest')",
+                params![
+                    a.date.to_string(),
+                    a.account_id,
+                    a.amount.to_string(),
+                    INTEREST_PAYEE,
+                    a.category_id,
+                    a.currency
+                ],
+            )?;
+        }
+        // Only whole periods are posted; the one still running at `through` waits.
+        let mut stmt = conn.prepare("SELECT account_id, compounding FROM account_interest")?;
+        let configs = stmt
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (account_id, compounding) in configs {
+            let compounding = Compounding::parse(&compounding)?;
+            let done = compounding.last_end(through);
+            conn.execute(
+                "UPDATE account_interest SET posted_through=?1
+                 WHERE account_id=?2 AND (posted_through IS NULL OR posted_through < ?1)
+                   AND since <= ?1",
+                params![done.to_string(), account_id],
+            )?;
+        }
+        Ok(())
+    })();
+    match res {
+        Ok(()) => {
+            conn.execute_batch("RELEASE recurring_run")?;
+            Ok(())
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO recurring_run; RELEASE recurring_run")?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn setup(apy: &str, compounding: &str, since: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, currency TEXT);
+            CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT, account_id INTEGER, amount TEXT,
+                payee TEXT, category_id INTEGER, currency TEXT, note TEXT);
+            CREATE TABLE account_interest(account_id INTEGER PRIMARY KEY, apy TEXT, compounding TEXT,
+                category_id INTEGER, since TEXT, posted_through TEXT);
+            INSERT INTO accounts(id, name, currency) VALUES (1, 'Savings', 'USD');
+            INSERT INTO transactions(date, account_id, amount, payee, currency) VALUES ('2024-01-01', 1, '1000', 'Deposit', 'USD');
+            "#,
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO account_interest(account_id, apy, compounding, since) VALUES (1, ?1, ?2, ?3)",
+            params![apy, compounding, since],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn interest_compounds_and_posts_each_period_once() {
+        let conn = setup("5", "yearly", "2024-01-01");
+        let due = accruals(&conn, day("2025-12-31")).unwrap();
+        let amounts: Vec<_> = due.iter().map(|a| (a.date, a.amount)).collect();
+        assert_eq!(
+            amounts,
+            vec![
+                (day("2024-12-31"), Decimal::from(50)),
+                (day("2025-12-31"), Decimal::from_str("52.50").unwrap()),
+            ]
+        );
+
+        post(&conn, &due[..1], day("2025-06-30")).unwrap();
+        let due = accruals(&conn, day("2025-12-31")).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].balance, Decimal::from(1050));
+        post(&conn, &due, day("2025-12-31")).unwrap();
+        assert!(accruals(&conn, day("2025-12-31")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn monthly_interest_starts_with_the_first_full_month() {
+        let conn = setup("12.682503", "monthly", "2024-01-15");
+        let due = accruals(&conn, day("2024-03-15")).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].date, day("2024-02-29"));
+        assert_eq!(due[0].amount, Decimal::from_str("10.00").unwrap());
+    }
+
+    #[test]
+    fn posted_interest_keeps_its_cents_under_a_display_override() {
+        let mut conn = setup("5", "yearly", "2024-01-01");
+        let matches = crate::cli::build_cli().get_matches_from([
+            "moneyclip",
+            "--money-dp",
+            "0",
+            "recurring",
+            "run",
+            "--through",
+            "2025-12-31",
+        ]);
+        crate::commands::dispatch(&mut conn, &matches).unwrap();
+        let posted: Vec<String> = conn
+            .prepare("SELECT amount FROM transactions WHERE note='interest' ORDER BY date")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(posted, vec!["50.00", "52.50"]);
+    }
+}
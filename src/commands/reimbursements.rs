@@ -0,0 +1,180 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Shared expenses: transactions paid on someone else's behalf (`tx add --reimbursable`),
+//! what they still owe (`report owed`) and matching their repayments (`tx settle`).
+
+use crate::utils::{fmt_amount, fx_convert, parse_amount, parse_date, pretty_table};
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+
+/// Who a reimbursable transaction is owed by when `--reimbursable` has no name, e.g.
+/// for work expense reports.
+pub const UNNAMED: &str = "expenses";
+
+/// Outstanding reimbursements of one person in one currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Owed {
+    pub person: String,
+    pub currency: String,
+    pub items: usize,
+    pub since: String,
+    pub amount: Decimal,
+}
+
+pub fn owed_report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let person = sub.get_one::<String>("person").map(|p| p.trim());
+    let owed = owed(conn, person)?;
+    if crate::utils::maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &owed)? {
+        return Ok(());
+    }
+    if owed.is_empty() {
+        crate::utils::emit("Nothing outstanding");
+        return Ok(());
+    }
+    let rows = owed
+        .into_iter()
+        .map(|o| {
+            vec![
+                o.person,
+                o.currency.clone(),
+                o.items.to_string(),
+                o.since,
+                fmt_amount(o.amount, &o.currency),
+            ]
+        })
+        .collect();
+    crate::utils::emit(pretty_table(
+        &["Person", "CCY", "Items", "Since", "Owed"],
+        rows,
+    ));
+    Ok(())
+}
+
+/// Unsettled reimbursable transactions summed per person and currency. Outflows add to
+/// what is owed; a reimbursable inflow (money received for them) reduces it.
+pub fn owed(conn: &Connection, person: Option<&str>) -> Result<Vec<Owed>> {
+    let mut stmt = conn.prepare(
+        "SELECT reimbursable_by, currency, date, amount FROM transactions
+         WHERE reimbursable_by IS NOT NULL AND settled_by IS NULL
+           AND (?1 IS NULL OR reimbursable_by=?1 COLLATE NOCASE)
+         ORDER BY reimbursable_by, currency, date",
+    )?;
+    let mut rows = stmt.query(params![person])?;
+    let mut out: Vec<Owed> = Vec::new();
+    while let Some(r) = rows.next()? {
+        let person: String = r.get(0)?;
+        let currency: String = r.get(1)?;
+        let date: String = r.get(2)?;
+        let amount: String = r.get(3)?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        match out.last_mut() {
+            Some(o) if o.person == person && o.currency == currency => {
+                o.items += 1;
+                o.amount -= amount;
+            }
+            _ => out.push(Owed {
+                person,
+                currency,
+                items: 1,
+                since: date,
+                amount: -amount,
+            }),
+        }
+    }
+    Ok(out)
+}
+
+pub fn settle_cmd(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let person = sub.get_one::<String>("person").unwrap().trim();
+    let date = parse_date(sub.get_one::<String>("date").unwrap().trim())?;
+    let amount = parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
+    let account = sub.get_one::<String>("account").map(|a| a.trim());
+    let repayment = crate::commands::transactions::find_by_amount(conn, date, amount, account)?;
+    let settled = settle(conn, person, repayment)?;
+    if settled.is_empty() {
+        bail!("Nothing outstanding for {}", person);
+    }
+    println!(
+        "Settled {} item(s) for {} with the repayment of {} on {}",
+        settled.len(),
+        person,
+        amount,
+        date
+    );
+    for o in owed(conn, Some(person))? {
+        println!(
+            "Still owed: {} {}",
+            fmt_amount(o.amount, &o.currency),
+            o.currency
+        );
+    }
+    Ok(())
+}
+
+/// Link `person`'s outstanding items to the repayment transaction `repayment_id`,
+/// oldest first, for as long as the repayment covers them (each item converted to the
+/// repayment's currency at the repayment date). Returns the ids of settled items.
+pub fn settle(conn: &Connection, person: &str, repayment_id: i64) -> Result<Vec<i64>> {
+    let (date, amount, currency): (String, String, String) = conn.query_row(
+        "SELECT date, amount, currency FROM transactions WHERE id=?1",
+        params![repayment_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+    )?;
+    let date = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+    let mut left = amount
+        .trim()
+        .parse::<Decimal>()
+        .with_context(|| format!("Invalid repayment amount '{}'", amount))?
+        .abs();
+    let mut stmt = conn.prepare(
+        "SELECT id, amount, currency FROM transactions
+         WHERE reimbursable_by=?1 COLLATE NOCASE AND settled_by IS NULL
+         ORDER BY date, id",
+    )?;
+    let items = stmt
+        .query_map(params![person], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut settled = Vec::new();
+    for (id, amount, ccy) in items {
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amount))?;
+        let due = fx_convert(conn, date, -amount, &ccy, &currency)?;
+        if due > left {
+            break;
+        }
+        left -= due;
+        settled.push(id);
+    }
+    conn.execute_batch("SAVEPOINT settle")?;
+    let result = (|| -> Result<()> {
+        let mut update = conn.prepare("UPDATE transactions SET settled_by=?1 WHERE id=?2")?;
+        for id in &settled {
+            update.execute(params![repayment_id, id])?;
+        }
+        Ok(())
+    })();
+    match result {
+        Ok(()) => conn.execute_batch("RELEASE settle")?,
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO settle; RELEASE settle")?;
+            return Err(e);
+        }
+    }
+    Ok(settled)
+}
@@ -4,15 +4,35 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::pretty_table;
+use crate::models::{BalanceRow, CashflowRow, CategorySpendRow, FeeRow, GroupBalanceRow};
+use crate::utils::{fmt_amount, pretty_table};
 use anyhow::{Context, Result};
 use rusqlite::Connection;
+use rust_decimal::Decimal;
 
 pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
         Some(("balances", sub)) => balances(conn, sub)?,
         Some(("cashflow", sub)) => cashflow(conn, sub)?,
         Some(("spend-by-category", sub)) => spend_by_category(conn, sub)?,
+        Some(("fees", sub)) => fees(conn, sub)?,
+        Some(("heatmap", sub)) => heatmap(conn, sub)?,
+        Some(("subscriptions", sub)) => subscriptions(conn, sub)?,
+        Some(("anomalies", sub)) => anomalies(conn, sub)?,
+        Some(("compare", sub)) => compare(conn, sub)?,
+        Some(("balance-history", sub)) => balance_history(conn, sub)?,
+        Some(("spend-by-location", sub)) => {
+            crate::commands::locations::spend_by_location_report(conn, sub)?
+        }
+        Some(("trip", sub)) => crate::commands::trips::trip_report(conn, sub)?,
+        Some(("by-project", sub)) => crate::commands::projects::by_project_report(conn, sub)?,
+        Some(("owed", sub)) => crate::commands::reimbursements::owed_report(conn, sub)?,
+        Some(("tax-collected", sub)) => {
+            crate::commands::sales_tax::tax_collected_report(conn, sub)?
+        }
+        Some(("snapshot", sub)) => crate::commands::snapshots::handle(conn, sub)?,
+        Some(("diff", sub)) => crate::commands::snapshots::diff(conn, sub)?,
+        Some(("digest", sub)) => crate::commands::digest::handle(conn, sub)?,
         _ => {}
     }
     Ok(())
@@ -25,80 +45,251 @@ fn balances(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let out_ccy = sub
         .get_one::<String>("currency")
         .map(|s| s.trim().to_uppercase());
-    let mut stmt = conn.prepare(
-        "SELECT a.name, a.currency, IFNULL(SUM(t.amount),0) AS bal
-         FROM accounts a
-         LEFT JOIN transactions t ON t.account_id=a.id
-         GROUP BY a.id ORDER BY a.name",
-    )?;
-    let rows = stmt.query_map([], |r| {
-        Ok((
-            r.get::<_, String>(0)?,
-            r.get::<_, String>(1)?,
-            r.get::<_, f64>(2)?,
-        ))
-    })?;
+    if sub.get_flag("by-group") {
+        let target = match out_ccy {
+            Some(c) => c,
+            None => crate::utils::get_base_currency(conn)?,
+        };
+        let data = balances_by_group(conn, &target, crate::utils::today())?;
+        if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
+            let hdr = format!("Balance ({})", target);
+            let cells = data.into_iter().map(GroupBalanceRow::cells).collect();
+            crate::utils::emit(pretty_table(&["Group", "Account", &hdr], cells));
+        }
+        return Ok(());
+    }
     let mut data = Vec::new();
-    if show_base || out_ccy.is_some() {
-        let base = crate::utils::get_base_currency(conn)?;
-        for row in rows {
-            let (name, ccy, bal_f) = row?;
-            let today = chrono::Utc::now().date_naive();
-            let bal_dec = rust_decimal::Decimal::try_from(bal_f)
-                .with_context(|| format!("Invalid balance '{}' for account {}", bal_f, name))?;
-            let target = out_ccy.clone().unwrap_or(base.clone());
-            let bal_base = crate::utils::fx_convert(conn, today, bal_dec, &ccy, &target)?;
-            data.push(vec![
-                format!("{} (in {})", name, target),
-                target.clone(),
-                format!("{:.2}", bal_base),
-            ]);
+    let converted = show_base || out_ccy.is_some();
+    if converted {
+        let target = match out_ccy {
+            Some(c) => c,
+            None => crate::utils::get_base_currency(conn)?,
+        };
+        let today = crate::utils::today();
+        for account in account_balances(conn)? {
+            let mut total = Decimal::ZERO;
+            for (ccy, amount) in &account.by_currency {
+                total += crate::utils::fx_convert(conn, today, *amount, ccy, &target)?;
+            }
+            data.push(BalanceRow {
+                account: account.name,
+                balance: fmt_amount(total, &target),
+                currency: target.clone(),
+            });
         }
     } else {
-        for row in rows {
-            let (name, ccy, bal_f) = row?;
-            data.push(vec![name, ccy, format!("{:.2}", bal_f)]);
+        for account in account_balances(conn)? {
+            for (ccy, amount) in account.by_currency {
+                data.push(BalanceRow {
+                    account: account.name.clone(),
+                    balance: fmt_amount(amount, &ccy),
+                    currency: ccy,
+                });
+            }
         }
     }
     if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
-        println!("{}", pretty_table(&["Account", "CCY", "Balance"], data));
+        let cells = data
+            .into_iter()
+            .map(|row| {
+                let account = if converted {
+                    format!("{} (in {})", row.account, row.currency)
+                } else {
+                    row.account
+                };
+                vec![account, row.currency, row.balance]
+            })
+            .collect();
+        crate::utils::emit(pretty_table(&["Account", "CCY", "Balance"], cells));
     }
     Ok(())
 }
 
+/// An account's balance per transaction currency, summed as decimals.
+pub struct AccountBalance {
+    pub name: String,
+    /// The account's own currency first (zero when it has no rows in it), then any
+    /// other currency its transactions use, alphabetically.
+    pub by_currency: Vec<(String, Decimal)>,
+}
+
+/// Balances of every account by name. Rows in a currency other than the account's
+/// (which `doctor` warns about) get their own sub-balance instead of being added to
+/// the account's as if they were in its currency.
+pub fn account_balances(conn: &Connection) -> Result<Vec<AccountBalance>> {
+    use std::collections::BTreeMap;
+
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.name, a.currency, t.currency, t.amount
+         FROM accounts a LEFT JOIN transactions t ON t.account_id=a.id
+         ORDER BY a.name, a.id",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut out: Vec<(i64, String, String, BTreeMap<String, Decimal>)> = Vec::new();
+    while let Some(r) = rows.next()? {
+        let id: i64 = r.get(0)?;
+        if out.last().is_none_or(|(last, ..)| *last != id) {
+            out.push((id, r.get(1)?, r.get(2)?, BTreeMap::new()));
+        }
+        let (_, name, own, sums) = out.last_mut().unwrap();
+        let Some(amount) = r.get::<_, Option<String>>(4)? else {
+            continue;
+        };
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in account {}", amount, name))?;
+        let ccy = r
+            .get::<_, Option<String>>(3)?
+            .unwrap_or_else(|| own.clone());
+        *sums.entry(ccy.to_uppercase()).or_default() += amount;
+    }
+    Ok(out
+        .into_iter()
+        .map(|(_, name, own, mut sums)| {
+            let own = own.to_uppercase();
+            let mut by_currency = vec![(own.clone(), sums.remove(&own).unwrap_or_default())];
+            by_currency.extend(sums);
+            AccountBalance { name, by_currency }
+        })
+        .collect())
+}
+
+/// Balances converted to `target` on `date`, grouped by account group (accounts without
+/// one fall under `(ungrouped)`, listed last) with a subtotal row per group and a total.
+pub fn balances_by_group(
+    conn: &Connection,
+    target: &str,
+    date: chrono::NaiveDate,
+) -> Result<Vec<GroupBalanceRow>> {
+    use rust_decimal::Decimal;
+    use std::collections::BTreeMap;
+
+    let mut stmt = conn.prepare(
+        "SELECT a.group_name, a.name, COALESCE(t.currency, a.currency), t.amount
+         FROM accounts a LEFT JOIN transactions t ON t.account_id=a.id",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut native: BTreeMap<(Option<String>, String, String), Decimal> = BTreeMap::new();
+    while let Some(r) = rows.next()? {
+        let group = r
+            .get::<_, Option<String>>(0)?
+            .filter(|g| !g.trim().is_empty());
+        let amount = match r.get::<_, Option<String>>(3)? {
+            Some(s) => s
+                .trim()
+                .parse::<Decimal>()
+                .with_context(|| format!("Invalid amount '{}' in transactions", s))?,
+            None => Decimal::ZERO,
+        };
+        *native.entry((group, r.get(1)?, r.get(2)?)).or_default() += amount;
+    }
+
+    // BTreeMap puts None first; ungrouped accounts belong at the end.
+    type Group = (Option<String>, Vec<(String, Decimal)>);
+    let mut groups: Vec<Group> = Vec::new();
+    for ((group, account, ccy), amount) in native {
+        let converted = crate::utils::fx_convert(conn, date, amount, &ccy, target)?;
+        match groups.last_mut() {
+            Some((g, accounts)) if *g == group => match accounts.last_mut() {
+                // Sub-balances of one account in several currencies are consecutive.
+                Some((last, sum)) if *last == account => *sum += converted,
+                _ => accounts.push((account, converted)),
+            },
+            _ => groups.push((group, vec![(account, converted)])),
+        }
+    }
+    if groups.first().is_some_and(|(g, _)| g.is_none()) {
+        groups.rotate_left(1);
+    }
+
+    let mut data = Vec::new();
+    let mut total = Decimal::ZERO;
+    for (group, accounts) in groups {
+        let label = group.unwrap_or_else(|| "(ungrouped)".to_string());
+        let subtotal: Decimal = accounts.iter().map(|(_, amt)| *amt).sum();
+        for (account, amount) in accounts {
+            data.push(GroupBalanceRow {
+                group: label.clone(),
+                account,
+                balance: fmt_amount(amount, target),
+            });
+        }
+        data.push(GroupBalanceRow {
+            group: label,
+            account: "Subtotal".into(),
+            balance: fmt_amount(subtotal, target),
+        });
+        total += subtotal;
+    }
+    data.push(GroupBalanceRow {
+        group: "Total".into(),
+        account: String::new(),
+        balance: fmt_amount(total, target),
+    });
+    Ok(data)
+}
+
+/// `--constant-fx DATE` pins every conversion to one day's rates so period-over-period
+/// changes reflect spending rather than exchange-rate movement.
+fn constant_fx_date(sub: &clap::ArgMatches) -> Result<Option<chrono::NaiveDate>> {
+    sub.get_one::<String>("constant-fx")
+        .map(|raw| crate::utils::parse_date(raw.trim()))
+        .transpose()
+}
+
 fn cashflow(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let json_flag = sub.get_flag("json");
     let jsonl_flag = sub.get_flag("jsonl");
     let show_base = sub.get_flag("base");
-    let months: usize = *sub.get_one::<usize>("months").unwrap_or(&12);
+    let (from, to) = crate::cli::date_range(sub)?;
+    // An explicit range shows every month in it unless --months caps it.
+    let default_months = if from.is_some() || to.is_some() {
+        usize::MAX
+    } else {
+        12
+    };
+    let months: usize = *sub.get_one::<usize>("months").unwrap_or(&default_months);
     let out_ccy = sub
         .get_one::<String>("currency")
         .map(|s| s.trim().to_uppercase());
+    let fx_date = constant_fx_date(sub)?;
     let mut stmt = conn.prepare(
-        "SELECT substr(date,1,7) AS month, date, amount, currency
+        "SELECT date, amount, currency
          FROM transactions
+         WHERE (?1 IS NULL OR date>=?1) AND (?2 IS NULL OR date<=?2)
          ORDER BY date DESC",
     )?;
-    let rows = stmt.query_map([], |r| {
+    let range = rusqlite::params![from.map(|d| d.to_string()), to.map(|d| d.to_string())];
+    let rows = stmt.query_map(range, |r| {
         Ok((
             r.get::<_, String>(0)?,
             r.get::<_, String>(1)?,
-            r.get::<_, f64>(2)?,
-            r.get::<_, String>(3)?,
+            r.get::<_, String>(2)?,
         ))
     })?;
+    let periods = crate::utils::periods();
+    let weekly = sub.get_one::<String>("by").map(|s| s.as_str()) == Some("week");
 
     use std::collections::BTreeMap;
     let mut map: BTreeMap<String, (rust_decimal::Decimal, rust_decimal::Decimal)> = BTreeMap::new();
     let base = crate::utils::get_base_currency(conn)?;
 
     for row in rows {
-        let (m, d, amt_f, ccy) = row?;
+        let (d, amt_s, ccy) = row?;
         let date = chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")?;
-        let amt = rust_decimal::Decimal::try_from(amt_f)
-            .with_context(|| format!("Invalid amount '{}' on {}", amt_f, d))?;
-        let amt_base = if show_base || out_ccy.is_some() {
-            crate::utils::fx_convert(conn, date, amt, &ccy, &base)?
+        let m = if weekly {
+            periods.week_of(date).to_string()
+        } else {
+            periods.month_of(date)
+        };
+        let amt = amt_s
+            .trim()
+            .parse::<rust_decimal::Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amt_s, d))?;
+        let amt_base = if show_base || out_ccy.is_some() || fx_date.is_some() {
+            let target = out_ccy.as_deref().unwrap_or(&base);
+            crate::utils::fx_convert(conn, fx_date.unwrap_or(date), amt, &ccy, target)?
         } else {
             amt
         };
@@ -111,32 +302,130 @@ fn cashflow(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
             entry.1 += -amt_base;
         }
     }
+    if sub.get_flag("real") {
+        let cpi_base = sub.get_one::<String>("cpi-base").map(|s| s.as_str());
+        let deflator = crate::commands::cpi::Deflator::load(conn, cpi_base)?;
+        for (m, (inc, exp)) in map.iter_mut().rev().take(months) {
+            *inc = deflator.real(m, *inc)?;
+            *exp = deflator.real(m, *exp)?;
+        }
+    }
+    let shown_ccy = out_ccy.clone().unwrap_or(base);
+    if sub.get_flag("chart") {
+        let shown: Vec<_> = map.iter().rev().take(months).collect();
+        crate::utils::emit(
+            cashflow_chart(shown.into_iter().rev(), &shown_ccy).trim_end_matches('\n'),
+        );
+        return Ok(());
+    }
     let mut data = Vec::new();
     for (m, (inc, exp)) in map.iter().rev().take(months) {
-        data.push(vec![
-            m.clone(),
-            format!("{:.2}", inc),
-            format!("{:.2}", exp),
-        ]);
+        data.push(CashflowRow {
+            period: m.clone(),
+            income: fmt_amount(*inc, &shown_ccy),
+            expense: fmt_amount(*exp, &shown_ccy),
+        });
     }
     if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
-        println!("{}", pretty_table(&["Month", "Income", "Expense"], data));
+        let period = if weekly { "Week of" } else { "Month" };
+        let cells = data.into_iter().map(CashflowRow::cells).collect();
+        crate::utils::emit(pretty_table(&[period, "Income", "Expense"], cells));
     }
     Ok(())
 }
 
+/// Income and expense bars per month, oldest first, with a sparkline of the net.
+fn cashflow_chart<'a>(
+    months: impl Iterator<
+        Item = (
+            &'a String,
+            &'a (rust_decimal::Decimal, rust_decimal::Decimal),
+        ),
+    >,
+    ccy: &str,
+) -> String {
+    use rust_decimal::Decimal;
+    const WIDTH: usize = 40;
+    let months: Vec<_> = months.collect();
+    let max = months
+        .iter()
+        .map(|(_, (inc, exp))| (*inc).max(*exp))
+        .max()
+        .unwrap_or_default();
+    let mut out = String::new();
+    for (m, (inc, exp)) in &months {
+        out.push_str(&format!(
+            "{} in  {:<w$} {}\n",
+            m,
+            crate::utils::bar(*inc, max, WIDTH),
+            fmt_amount(*inc, ccy),
+            w = WIDTH
+        ));
+        out.push_str(&format!(
+            "{:pad$} out {:<w$} {}\n",
+            "",
+            crate::utils::bar(*exp, max, WIDTH),
+            fmt_amount(*exp, ccy),
+            w = WIDTH,
+            pad = m.len()
+        ));
+    }
+    let net: Vec<Decimal> = months.iter().map(|(_, (inc, exp))| *inc - *exp).collect();
+    if !net.is_empty() {
+        out.push_str(&format!("net {}\n", crate::utils::sparkline(&net)));
+    }
+    out
+}
+
 fn spend_by_category(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let json_flag = sub.get_flag("json");
     let jsonl_flag = sub.get_flag("jsonl");
     let show_base = sub.get_flag("base");
-    let month = sub.get_one::<String>("month").unwrap().trim().to_string();
+    let month = sub
+        .get_one::<String>("month")
+        .map(|m| crate::utils::parse_month(m.trim()))
+        .transpose()?;
+    let (from, to) = crate::cli::date_range(sub)?;
+    let filter = rusqlite::params![
+        month,
+        from.map(|d| d.to_string()),
+        to.map(|d| d.to_string())
+    ];
     let out_ccy = sub
         .get_one::<String>("currency")
         .map(|s| s.trim().to_uppercase());
-    if show_base || out_ccy.is_some() {
+    let fx_date = constant_fx_date(sub)?;
+    let totals = if sub.get_flag("totals") {
+        let target = match &out_ccy {
+            Some(c) => c.clone(),
+            None => crate::utils::get_base_currency(conn)?,
+        };
+        Some(spend_totals(conn, filter, &target, fx_date)?)
+    } else {
+        None
+    };
+    let footer = |data: &mut Vec<Vec<String>>| {
+        if let Some(totals) = &totals {
+            for (i, (amount, ccy)) in totals.lines().into_iter().enumerate() {
+                let label = if i == 0 {
+                    format!("Total ({} transactions)", totals.count)
+                } else {
+                    String::new()
+                };
+                data.push(vec![label, format!("{} {}", amount, ccy)]);
+            }
+        }
+    };
+    if show_base || out_ccy.is_some() || fx_date.is_some() {
         let base = crate::utils::get_base_currency(conn)?;
-        let mut stmt = conn.prepare("SELECT c.name, t.date, -t.amount as out, t.currency FROM transactions t LEFT JOIN categories c ON t.category_id=c.id WHERE substr(t.date,1,7)=?1 AND t.amount < 0")?;
-        let rows = stmt.query_map([month.as_str()], |r| {
+        let mut stmt = conn.prepare(
+            "SELECT c.name, t.date, -t.amount as out, t.currency
+             FROM transactions t LEFT JOIN categories c ON t.category_id=c.id
+             WHERE (?1 IS NULL OR substr(t.date,1,7)=?1)
+               AND (?2 IS NULL OR t.date>=?2) AND (?3 IS NULL OR t.date<=?3)
+               AND t.amount < 0",
+        )?;
+        let rows = stmt.query_map(filter, |r| {
             Ok((
                 r.get::<_, Option<String>>(0)?,
                 r.get::<_, String>(1)?,
@@ -153,39 +442,909 @@ fn spend_by_category(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
             let out_dec = rust_decimal::Decimal::try_from(out_f)
                 .with_context(|| format!("Invalid amount '{}' for {}", out_f, cat))?;
             let target = out_ccy.clone().unwrap_or(base.clone());
-            let out_base = crate::utils::fx_convert(conn, date, out_dec, &ccy, &target)?;
+            let out_base =
+                crate::utils::fx_convert(conn, fx_date.unwrap_or(date), out_dec, &ccy, &target)?;
             *agg.entry(cat).or_insert(rust_decimal::Decimal::ZERO) += out_base;
         }
-        let mut data = Vec::new();
         let mut items: Vec<_> = agg.into_iter().collect();
-        items.sort_by(|a, b| b.1.cmp(&a.1));
+        items.sort_by_key(|item| std::cmp::Reverse(item.1));
+        let target = out_ccy.as_deref().unwrap_or(&base);
+        if sub.get_flag("chart") {
+            crate::utils::emit(category_chart(&items, Some(target)).trim_end_matches('\n'));
+            return Ok(());
+        }
+        let mut data = Vec::new();
         for (cat, amt) in items {
-            data.push(vec![cat, format!("{:.2}", amt)]);
+            data.push(CategorySpendRow {
+                category: cat,
+                spent: fmt_amount(amt, target),
+            });
         }
         let hdr = if let Some(ref t) = out_ccy {
             format!("Spent ({})", t)
         } else {
             "Spent (BASE)".to_string()
         };
-        if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
-            println!("{}", pretty_table(&["Category", &hdr], data));
+        if !crate::utils::maybe_print_json_with_totals(
+            json_flag,
+            jsonl_flag,
+            &data,
+            totals.as_ref(),
+        )? {
+            let mut cells = data.into_iter().map(CategorySpendRow::cells).collect();
+            footer(&mut cells);
+            crate::utils::emit(pretty_table(&["Category", &hdr], cells));
         }
     } else {
         let mut stmt = conn.prepare(
             "SELECT c.name, printf('%.2f', -SUM(t.amount)) AS spent
              FROM transactions t LEFT JOIN categories c ON t.category_id=c.id
-             WHERE substr(t.date,1,7)=?1 AND t.amount < 0
+             WHERE (?1 IS NULL OR substr(t.date,1,7)=?1)
+               AND (?2 IS NULL OR t.date>=?2) AND (?3 IS NULL OR t.date<=?3)
+               AND t.amount < 0
              GROUP BY c.name ORDER BY spent DESC",
         )?;
-        let rows = stmt.query_map([month.as_str()], |r| {
+        let rows = stmt.query_map(filter, |r| {
             Ok((r.get::<_, Option<String>>(0)?, r.get::<_, String>(1)?))
         })?;
         let mut data = Vec::new();
         for row in rows {
             let (cat, spent) = row?;
-            data.push(vec![cat.unwrap_or("(uncategorized)".into()), spent]);
+            data.push(CategorySpendRow {
+                category: cat.unwrap_or("(uncategorized)".into()),
+                spent,
+            });
+        }
+        if sub.get_flag("chart") {
+            let mut items = data
+                .iter()
+                .map(|r| Ok((r.category.clone(), r.spent.parse()?)))
+                .collect::<Result<Vec<_>>>()?;
+            items.sort_by_key(|item: &(String, rust_decimal::Decimal)| std::cmp::Reverse(item.1));
+            crate::utils::emit(category_chart(&items, None).trim_end_matches('\n'));
+            return Ok(());
+        }
+        if !crate::utils::maybe_print_json_with_totals(
+            json_flag,
+            jsonl_flag,
+            &data,
+            totals.as_ref(),
+        )? {
+            let mut cells = data.into_iter().map(CategorySpendRow::cells).collect();
+            footer(&mut cells);
+            crate::utils::emit(pretty_table(&["Category", "Spent"], cells));
+        }
+    }
+    Ok(())
+}
+
+/// One bar per category, largest spend first and scaled to it, with each category's
+/// share of the total. Without `ccy` the amounts are account-currency sums as stored.
+fn category_chart(items: &[(String, rust_decimal::Decimal)], ccy: Option<&str>) -> String {
+    const WIDTH: usize = 40;
+    let max = items.iter().map(|(_, amt)| *amt).max().unwrap_or_default();
+    let total: rust_decimal::Decimal = items.iter().map(|(_, amt)| *amt).sum();
+    let width = items
+        .iter()
+        .map(|(cat, _)| cat.chars().count())
+        .max()
+        .unwrap_or(0);
+    let amounts: Vec<String> = items
+        .iter()
+        .map(|(_, amt)| match ccy {
+            Some(ccy) => format!("{} {}", fmt_amount(*amt, ccy), ccy),
+            None => amt.to_string(),
+        })
+        .collect();
+    let amount_width = amounts.iter().map(|a| a.chars().count()).max().unwrap_or(0);
+    let mut out = String::new();
+    for ((cat, amt), amount) in items.iter().zip(&amounts) {
+        let share = if total.is_zero() {
+            rust_decimal::Decimal::ZERO
+        } else {
+            *amt * rust_decimal::Decimal::from(100) / total
+        };
+        out.push_str(&format!(
+            "{:<width$} {:<w$} {:>amount_width$} {:>6}%\n",
+            cat,
+            crate::utils::bar(*amt, max, WIDTH),
+            amount,
+            crate::utils::fmt_percent(share),
+            width = width,
+            w = WIDTH,
+            amount_width = amount_width
+        ));
+    }
+    if out.is_empty() {
+        out.push_str("No spending in this period\n");
+    }
+    out
+}
+
+/// `--totals` for `spend-by-category`: outflows matching `filter` (month, from, to),
+/// summed per currency and converted to `target` at each date or at `fx_date`.
+pub fn spend_totals(
+    conn: &Connection,
+    filter: &[&dyn rusqlite::ToSql],
+    target: &str,
+    fx_date: Option<chrono::NaiveDate>,
+) -> Result<crate::utils::Totals> {
+    let mut stmt = conn.prepare(
+        "SELECT t.date, t.amount, t.currency
+         FROM transactions t
+         WHERE (?1 IS NULL OR substr(t.date,1,7)=?1)
+           AND (?2 IS NULL OR t.date>=?2) AND (?3 IS NULL OR t.date<=?3)
+           AND t.amount < 0",
+    )?;
+    let mut rows = stmt.query(filter)?;
+    let mut items = Vec::new();
+    while let Some(r) = rows.next()? {
+        let date: String = r.get(0)?;
+        let amount: String = r.get(1)?;
+        let amount = amount
+            .trim()
+            .parse::<rust_decimal::Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        let date = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+        items.push((fx_date.unwrap_or(date), -amount, r.get::<_, String>(2)?));
+    }
+    let refs: Vec<_> = items.iter().map(|(d, a, c)| (*d, *a, c.as_str())).collect();
+    crate::utils::compute_totals(conn, &refs, target)
+}
+
+fn fees(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let year = sub.get_one::<String>("year").unwrap().trim().to_string();
+    let collect = |id: &str, default: &str| -> Vec<String> {
+        let values: Vec<String> = sub
+            .get_many::<String>(id)
+            .map(|vals| vals.map(|v| v.trim().to_string()).collect())
+            .unwrap_or_default();
+        if values.is_empty() {
+            vec![default.to_string()]
+        } else {
+            values
+        }
+    };
+    let fee_cats = collect("fee-category", "Fees");
+    let interest_cats = collect("interest-category", "Interest");
+    let base = crate::utils::get_base_currency(conn)?;
+
+    let data = build_fees_report(conn, &year, &fee_cats, &interest_cats, &base)?;
+    if !crate::utils::maybe_print_json(json_flag, jsonl_flag, &data)? {
+        let hdr = format!("Cost ({})", base);
+        let cells = data.into_iter().map(FeeRow::cells).collect();
+        crate::utils::emit(pretty_table(&["Source", "Account", &hdr], cells));
+    }
+    Ok(())
+}
+
+fn heatmap(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let year = sub.get_one::<String>("year").unwrap().trim().to_string();
+    let category = sub.get_one::<String>("category").map(|c| c.trim());
+    let format = sub
+        .get_one::<String>("format")
+        .map(|f| f.trim().to_lowercase())
+        .unwrap_or_else(|| "csv".into());
+    let base = crate::utils::get_base_currency(conn)?;
+    let days = build_heatmap(conn, &year, category, &base)?;
+    match format.as_str() {
+        "csv" => crate::utils::with_output(|out| {
+            let mut wtr = csv::Writer::from_writer(out);
+            for day in &days {
+                wtr.serialize(day)?;
+            }
+            wtr.flush()?;
+            Ok(())
+        })?,
+        "json" => {
+            crate::utils::maybe_print_json(true, false, &days)?;
+        }
+        other => anyhow::bail!("Unknown format: {} (use csv|json)", other),
+    }
+    Ok(())
+}
+
+/// One cell of the spending heatmap: ISO week and weekday locate it in a calendar grid.
+/// Days around New Year can fall in a week of the neighbouring year, so the week is
+/// identified by `week_year` and `week` together.
+#[derive(Debug, serde::Serialize)]
+pub struct HeatmapDay {
+    pub date: String,
+    pub week_year: i32,
+    pub week: u32,
+    pub weekday: String,
+    pub spent: String,
+}
+
+/// Expenses per calendar day of `year` in `base`, optionally for one category. Every day
+/// is present, with zero spend where nothing was paid, so the output is a complete grid.
+pub fn build_heatmap(
+    conn: &Connection,
+    year: &str,
+    category: Option<&str>,
+    base: &str,
+) -> Result<Vec<HeatmapDay>> {
+    use chrono::{Datelike, NaiveDate};
+    use rust_decimal::Decimal;
+    use std::collections::BTreeMap;
+
+    let y = year
+        .parse::<i32>()
+        .with_context(|| format!("Invalid year '{}'", year))?;
+    let category_id = category
+        .map(|c| crate::utils::id_for_category(conn, c))
+        .transpose()?;
+    let mut stmt = conn.prepare(
+        "SELECT date, amount, currency FROM transactions
+         WHERE substr(date,1,4)=?1 AND (?2 IS NULL OR category_id=?2)",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![year, category_id])?;
+    let mut spends = Vec::new();
+    while let Some(r) = rows.next()? {
+        let d: String = r.get(0)?;
+        let amt_s: String = r.get(1)?;
+        let amt = amt_s
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amt_s))?;
+        if amt >= Decimal::ZERO {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(&d, "%Y-%m-%d")?;
+        spends.push((date, -amt, r.get::<_, String>(2)?));
+    }
+    let items: Vec<_> = spends
+        .iter()
+        .map(|(date, amt, ccy)| (*date, *amt, ccy.as_str()))
+        .collect();
+    let converted = crate::utils::fx_convert_batch(conn, &items, base)?;
+    let mut per_day: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+    for ((date, _, _), amount) in items.iter().zip(converted) {
+        *per_day.entry(*date).or_default() += amount;
+    }
+
+    let first =
+        NaiveDate::from_ymd_opt(y, 1, 1).with_context(|| format!("Invalid year '{}'", year))?;
+    Ok(first
+        .iter_days()
+        .take_while(|d| d.year() == y)
+        .map(|d| HeatmapDay {
+            date: d.to_string(),
+            week_year: d.iso_week().year(),
+            week: d.iso_week().week(),
+            weekday: d.weekday().to_string(),
+            spent: fmt_amount(per_day.get(&d).copied().unwrap_or_default(), base),
+        })
+        .collect())
+}
+
+fn subscriptions(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let min_charges = *sub.get_one::<usize>("min-charges").unwrap_or(&3);
+    let found = detect_subscriptions(conn, crate::utils::today(), min_charges)?;
+    if crate::utils::maybe_print_json(json_flag, jsonl_flag, &found)? {
+        return Ok(());
+    }
+    let rows = found
+        .into_iter()
+        .map(|s| {
+            let change = match (s.previous_amount, s.changed_on) {
+                (Some(prev), Some(on)) => format!(
+                    "{} -> {} on {}",
+                    fmt_amount(prev, &s.currency),
+                    fmt_amount(s.amount, &s.currency),
+                    on
+                ),
+                _ => String::new(),
+            };
+            let mut status = if s.active { "active" } else { "lapsed" }.to_string();
+            if s.increased {
+                status.push_str(", increased");
+            }
+            vec![
+                s.payee,
+                s.currency.clone(),
+                s.charges.to_string(),
+                s.last_charge,
+                fmt_amount(s.amount, &s.currency),
+                fmt_amount(s.annualized, &s.currency),
+                change,
+                status,
+            ]
+        })
+        .collect();
+    crate::utils::emit(pretty_table(
+        &[
+            "Payee",
+            "CCY",
+            "Charges",
+            "Last charge",
+            "Amount",
+            "Per year",
+            "Last change",
+            "Status",
+        ],
+        rows,
+    ));
+    Ok(())
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Subscription {
+    pub payee: String,
+    pub currency: String,
+    pub charges: usize,
+    pub last_charge: String,
+    pub amount: rust_decimal::Decimal,
+    pub annualized: rust_decimal::Decimal,
+    pub previous_amount: Option<rust_decimal::Decimal>,
+    pub changed_on: Option<String>,
+    pub increased: bool,
+    pub active: bool,
+}
+
+/// Recurring monthly charges: at least `min_charges` expenses to the same payee (case
+/// and whitespace insensitive) in one currency, roughly a month apart (median gap of
+/// 25-35 days), each within 25% of the typical amount. A subscription is active when
+/// it was charged within the last 45 days of `today`; sorted by annualized cost.
+pub fn detect_subscriptions(
+    conn: &Connection,
+    today: chrono::NaiveDate,
+    min_charges: usize,
+) -> Result<Vec<Subscription>> {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use std::collections::BTreeMap;
+
+    let mut stmt =
+        conn.prepare("SELECT payee, currency, date, amount FROM transactions ORDER BY date, id")?;
+    let mut rows = stmt.query([])?;
+    // (normalized payee, currency) -> (payee as first written, dated charges)
+    type Charges = (String, Vec<(NaiveDate, Decimal)>);
+    let mut groups: BTreeMap<(String, String), Charges> = BTreeMap::new();
+    while let Some(r) = rows.next()? {
+        let payee: String = r.get(0)?;
+        let currency: String = r.get(1)?;
+        let d: String = r.get(2)?;
+        let amt_s: String = r.get(3)?;
+        let amt = amt_s
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amt_s))?;
+        if amt >= Decimal::ZERO {
+            continue;
         }
-        println!("{}", pretty_table(&["Category", "Spent"], data));
+        let date = NaiveDate::parse_from_str(&d, "%Y-%m-%d")?;
+        let key = (
+            payee
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase(),
+            currency,
+        );
+        groups
+            .entry(key)
+            .or_insert_with(|| (payee.trim().to_string(), Vec::new()))
+            .1
+            .push((date, -amt));
     }
+
+    fn median<T: Ord + Copy>(values: &mut [T]) -> T {
+        values.sort();
+        values[values.len() / 2]
+    }
+
+    let mut found = Vec::new();
+    for ((_, currency), (payee, charges)) in groups {
+        if charges.len() < min_charges.max(2) {
+            continue;
+        }
+        let mut gaps: Vec<i64> = charges
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0).num_days())
+            .collect();
+        if !(25..=35).contains(&median(&mut gaps)) {
+            continue;
+        }
+        let mut amounts: Vec<Decimal> = charges.iter().map(|c| c.1).collect();
+        let typical = median(&mut amounts);
+        let tolerance = typical * Decimal::new(25, 2);
+        if charges.iter().any(|c| (c.1 - typical).abs() > tolerance) {
+            continue;
+        }
+
+        let (last_date, amount) = *charges.last().unwrap();
+        let change = charges
+            .windows(2)
+            .rev()
+            .find(|w| w[0].1 != w[1].1)
+            .map(|w| (w[0].1, w[1].0));
+        found.push(Subscription {
+            payee,
+            currency,
+            charges: charges.len(),
+            last_charge: last_date.to_string(),
+            amount,
+            annualized: amount * Decimal::from(12),
+            previous_amount: change.map(|c| c.0),
+            changed_on: change.map(|c| c.1.to_string()),
+            increased: change.is_some_and(|(prev, _)| amount > prev),
+            active: (today - last_date).num_days() <= 45,
+        });
+    }
+    found.sort_by_key(|s| std::cmp::Reverse(s.annualized));
+    Ok(found)
+}
+
+fn anomalies(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let month = crate::utils::parse_month(sub.get_one::<String>("month").unwrap().trim())?;
+    let z = *sub.get_one::<f64>("z").unwrap_or(&3.0);
+    let new_payee_min = match sub.get_one::<String>("new-payee-min") {
+        Some(raw) => crate::utils::parse_amount(raw.trim())?,
+        None => rust_decimal::Decimal::from(100),
+    };
+    let found = find_anomalies(conn, &month, z, new_payee_min)?;
+    if crate::utils::maybe_print_json(json_flag, jsonl_flag, &found)? {
+        return Ok(());
+    }
+    let rows = found
+        .into_iter()
+        .map(|a| {
+            vec![
+                a.date,
+                a.payee,
+                a.category.unwrap_or_default(),
+                fmt_amount(a.amount, &a.currency),
+                a.currency,
+                a.reason,
+            ]
+        })
+        .collect();
+    crate::utils::emit(pretty_table(
+        &["Date", "Payee", "Category", "Amount", "CCY", "Why"],
+        rows,
+    ));
     Ok(())
 }
+
+fn balance_history(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let account = sub.get_one::<String>("account").unwrap().trim();
+    let (from, to) = crate::cli::date_range(sub)?;
+    let target = match sub.get_one::<String>("currency") {
+        Some(c) => Some(c.trim().to_uppercase()),
+        None if sub.get_flag("base") => Some(crate::utils::get_base_currency(conn)?),
+        None => None,
+    };
+    let to = to.unwrap_or_else(crate::utils::today);
+    let days = daily_balances(conn, account, from, to, target.as_deref())?;
+    if crate::utils::maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &days)? {
+        return Ok(());
+    }
+    let Some(low) = days.iter().min_by_key(|d| d.balance) else {
+        crate::utils::emit(format!("No transactions for {}", account));
+        return Ok(());
+    };
+    let summary = format!(
+        "Minimum {} on {}; {} of {} days below zero",
+        fmt_amount(low.balance, &low.currency),
+        low.date,
+        days.iter().filter(|d| d.balance.is_sign_negative()).count(),
+        days.len()
+    );
+    let data = days
+        .iter()
+        .map(|d| {
+            vec![
+                d.date.clone(),
+                fmt_amount(d.change, &d.currency),
+                fmt_amount(d.balance, &d.currency),
+            ]
+        })
+        .collect();
+    let ccy = &days[0].currency;
+    crate::utils::emit(pretty_table(
+        &[
+            "Date",
+            &format!("Change ({})", ccy),
+            &format!("Balance ({})", ccy),
+        ],
+        data,
+    ));
+    crate::utils::emit(summary);
+    Ok(())
+}
+
+/// End-of-day balance of one account for `report balance-history`.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct DailyBalance {
+    pub date: String,
+    pub change: Decimal,
+    pub balance: Decimal,
+    pub currency: String,
+}
+
+/// Running balance of `account` for every day from `from` (default: its first
+/// transaction) through `to`, starting from everything booked before `from`. With
+/// `target`, each day's change and balance are converted at that day's rate.
+pub fn daily_balances(
+    conn: &Connection,
+    account: &str,
+    from: Option<chrono::NaiveDate>,
+    to: chrono::NaiveDate,
+    target: Option<&str>,
+) -> Result<Vec<DailyBalance>> {
+    let account_id = crate::utils::id_for_account(conn, account)?;
+    let native: String = conn.query_row(
+        "SELECT currency FROM accounts WHERE id=?1",
+        [account_id],
+        |r| r.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT date, amount FROM transactions WHERE account_id=?1 AND date<=?2 ORDER BY date",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![account_id, to.to_string()])?;
+    let mut opening = Decimal::ZERO;
+    let mut changes: std::collections::BTreeMap<chrono::NaiveDate, Decimal> = Default::default();
+    while let Some(r) = rows.next()? {
+        let date: String = r.get(0)?;
+        let amount: String = r.get(1)?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        let date = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+        match from {
+            Some(from) if date < from => opening += amount,
+            _ => *changes.entry(date).or_default() += amount,
+        }
+    }
+    let Some(start) = from.or_else(|| changes.keys().next().copied()) else {
+        return Ok(Vec::new());
+    };
+    let currency = target.unwrap_or(&native).to_string();
+    let mut out = Vec::new();
+    let mut balance = opening;
+    for date in start.iter_days().take_while(|d| *d <= to) {
+        let change = changes.get(&date).copied().unwrap_or_default();
+        balance += change;
+        let convert = |amount| match target {
+            Some(t) => crate::utils::fx_convert(conn, date, amount, &native, t),
+            None => Ok(amount),
+        };
+        out.push(DailyBalance {
+            date: date.to_string(),
+            change: convert(change)?,
+            balance: convert(balance)?,
+            currency: currency.clone(),
+        });
+    }
+    Ok(out)
+}
+
+fn compare(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let a = crate::utils::parse_month(sub.get_one::<String>("a").unwrap())?;
+    let b = crate::utils::parse_month(sub.get_one::<String>("b").unwrap())?;
+    let by = sub.get_one::<String>("by").unwrap();
+    let base = crate::utils::get_base_currency(conn)?;
+    let rows = compare_spending(conn, &a, &b, by, &base)?;
+    if crate::utils::maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &rows)? {
+        return Ok(());
+    }
+    let (total_a, total_b) = rows
+        .iter()
+        .fold((Decimal::ZERO, Decimal::ZERO), |(ta, tb), r| {
+            (ta + r.a, tb + r.b)
+        });
+    let line = |key: String, a: Decimal, b: Decimal| {
+        vec![
+            key,
+            fmt_amount(a, &base),
+            fmt_amount(b, &base),
+            crate::utils::fmt_signed(b - a, &base),
+            change_percent(a, b)
+                .map(|p| format!("{}%", crate::utils::fmt_percent(p)))
+                .unwrap_or_else(|| "new".into()),
+        ]
+    };
+    let mut data: Vec<_> = rows.into_iter().map(|r| line(r.key, r.a, r.b)).collect();
+    data.push(line("Total".into(), total_a, total_b));
+    let group = match by.as_str() {
+        "payee" => "Payee",
+        "account" => "Account",
+        _ => "Category",
+    };
+    crate::utils::emit(pretty_table(
+        &[group, &a, &b, &format!("Change ({})", base), "Change %"],
+        data,
+    ));
+    Ok(())
+}
+
+/// Spending for one group in both months of `report compare`, in the base currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct CompareRow {
+    pub key: String,
+    pub a: Decimal,
+    pub b: Decimal,
+    pub change: Decimal,
+    /// `None` when nothing was spent in month `a`.
+    pub change_percent: Option<Decimal>,
+}
+
+fn change_percent(a: Decimal, b: Decimal) -> Option<Decimal> {
+    (!a.is_zero()).then(|| (b - a) / a * Decimal::ONE_HUNDRED)
+}
+
+/// Outflows of months `a` and `b` grouped by category, payee or account and converted
+/// to `base` at each transaction's date, sorted by the size of the change.
+pub fn compare_spending(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+    by: &str,
+    base: &str,
+) -> Result<Vec<CompareRow>> {
+    let key = match by {
+        "category" => "COALESCE(c.name, '(uncategorized)')",
+        "payee" => "t.payee",
+        "account" => "a.name",
+        other => anyhow::bail!("Unknown grouping '{}' (use category|payee|account)", other),
+    };
+    let sql = format!(
+        "SELECT {key}, substr(t.date,1,7), t.date, t.amount, t.currency
+         FROM transactions t
+         LEFT JOIN categories c ON t.category_id=c.id
+         LEFT JOIN accounts a ON t.account_id=a.id
+         WHERE substr(t.date,1,7) IN (?1, ?2) AND t.amount < 0"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params![a, b])?;
+    let mut keyed = Vec::new();
+    let mut items = Vec::new();
+    while let Some(r) = rows.next()? {
+        let date: String = r.get(2)?;
+        let amount: String = r.get(3)?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        keyed.push((
+            r.get::<_, Option<String>>(0)?.unwrap_or_default(),
+            r.get::<_, String>(1)? == b,
+        ));
+        items.push((
+            chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")?,
+            -amount,
+            r.get::<_, String>(4)?,
+        ));
+    }
+    let refs: Vec<_> = items.iter().map(|(d, a, c)| (*d, *a, c.as_str())).collect();
+    let converted = crate::utils::fx_convert_batch(conn, &refs, base)?;
+    let mut groups: std::collections::BTreeMap<String, (Decimal, Decimal)> = Default::default();
+    for ((key, in_b), amount) in keyed.into_iter().zip(converted) {
+        let entry = groups.entry(key).or_default();
+        if in_b {
+            entry.1 += amount;
+        } else {
+            entry.0 += amount;
+        }
+    }
+    let mut out: Vec<CompareRow> = groups
+        .into_iter()
+        .map(|(key, (a, b))| CompareRow {
+            key,
+            a,
+            b,
+            change: b - a,
+            change_percent: change_percent(a, b),
+        })
+        .collect();
+    out.sort_by(|x, y| y.change.abs().cmp(&x.change.abs()).then(x.key.cmp(&y.key)));
+    Ok(out)
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Anomaly {
+    pub id: i64,
+    pub date: String,
+    pub payee: String,
+    pub category: Option<String>,
+    pub amount: rust_decimal::Decimal,
+    pub currency: String,
+    pub reason: String,
+}
+
+/// Unusual expenses in `month`:
+/// - outliers more than `z` standard deviations above the mean of earlier expenses in
+///   the same category (or payee when uncategorized) and currency, given at least five
+///   earlier charges; the deviation is floored at 5% of the mean so near-constant
+///   bills do not flag on cents,
+/// - the first charge from a payee when it is at least `new_payee_min`,
+/// - repeated charges with the same payee and amount within a day of each other.
+pub fn find_anomalies(
+    conn: &Connection,
+    month: &str,
+    z: f64,
+    new_payee_min: rust_decimal::Decimal,
+) -> Result<Vec<Anomaly>> {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use rust_decimal::prelude::ToPrimitive;
+    use std::collections::{HashMap, HashSet};
+
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.date, t.payee, c.name, t.category_id, t.amount, t.currency
+         FROM transactions t LEFT JOIN categories c ON c.id = t.category_id
+         WHERE substr(t.date,1,7) <= ?1
+         ORDER BY t.date, t.id",
+    )?;
+    let mut rows = stmt.query([month])?;
+    let mut history: HashMap<(String, String), Vec<f64>> = HashMap::new();
+    let mut known_payees: HashSet<String> = HashSet::new();
+    let mut current = Vec::new();
+    while let Some(r) = rows.next()? {
+        let d: String = r.get(1)?;
+        let payee: String = r.get(2)?;
+        let category_id: Option<i64> = r.get(4)?;
+        let amt_s: String = r.get(5)?;
+        let currency: String = r.get(6)?;
+        let amount = amt_s
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' in transactions", amt_s))?;
+        let norm_payee = payee.trim().to_lowercase();
+        let group = match category_id {
+            Some(id) => (format!("category:{}", id), currency.clone()),
+            None => (format!("payee:{}", norm_payee), currency.clone()),
+        };
+        if !d.starts_with(month) {
+            known_payees.insert(norm_payee);
+            if amount < Decimal::ZERO {
+                history
+                    .entry(group)
+                    .or_default()
+                    .push((-amount).to_f64().unwrap_or(0.0));
+            }
+            continue;
+        }
+        if amount < Decimal::ZERO {
+            let date = NaiveDate::parse_from_str(&d, "%Y-%m-%d")?;
+            let anomaly = Anomaly {
+                id: r.get(0)?,
+                date: d,
+                payee,
+                category: r.get(3)?,
+                amount: -amount,
+                currency,
+                reason: String::new(),
+            };
+            current.push((date, norm_payee, group, anomaly));
+        }
+    }
+
+    let mut reasons: Vec<Vec<String>> = vec![Vec::new(); current.len()];
+    for (i, (date, norm_payee, group, tx)) in current.iter().enumerate() {
+        if let Some(past) = history.get(group).filter(|p| p.len() >= 5) {
+            let n = past.len() as f64;
+            let mean = past.iter().sum::<f64>() / n;
+            let sd = (past.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n)
+                .sqrt()
+                .max(mean * 0.05);
+            let x = tx.amount.to_f64().unwrap_or(0.0);
+            let score = if sd > 0.0 { (x - mean) / sd } else { 0.0 };
+            if score > z {
+                reasons[i].push(format!("{:.1}σ above usual {:.2}", score, mean));
+            }
+        }
+        if !known_payees.contains(norm_payee) && tx.amount >= new_payee_min {
+            reasons[i].push("new payee".to_string());
+        }
+        let duplicate = current.iter().enumerate().any(|(j, (d, p, _, other))| {
+            j != i
+                && p == norm_payee
+                && other.amount == tx.amount
+                && other.currency == tx.currency
+                && (*d - *date).num_days().abs() <= 1
+        });
+        if duplicate {
+            reasons[i].push("possible duplicate".to_string());
+        }
+    }
+    let found = current
+        .into_iter()
+        .zip(reasons)
+        .filter(|(_, why)| !why.is_empty())
+        .map(|((_, _, _, tx), why)| Anomaly {
+            reason: why.join("; "),
+            ..tx
+        })
+        .collect();
+    Ok(found)
+}
+
+/// Yearly cost of holding accounts: categorized bank fees and interest (expenses net of
+/// refunds) plus trade commissions, each converted to `base` at its own date.
+pub fn build_fees_report(
+    conn: &Connection,
+    year: &str,
+    fee_categories: &[String],
+    interest_categories: &[String],
+    base: &str,
+) -> Result<Vec<FeeRow>> {
+    use rust_decimal::Decimal;
+    use std::collections::BTreeMap;
+
+    year.parse::<i32>()
+        .with_context(|| format!("Invalid year '{}'", year))?;
+
+    let mut totals: BTreeMap<(usize, String), Decimal> = BTreeMap::new();
+    let mut tx_stmt = conn.prepare_cached(
+        "SELECT a.name, t.date, t.amount, t.currency
+         FROM transactions t
+         JOIN accounts a ON t.account_id=a.id
+         JOIN categories c ON t.category_id=c.id
+         WHERE c.name=?1 AND substr(t.date,1,4)=?2",
+    )?;
+    for (order, cats) in [(0usize, fee_categories), (1, interest_categories)] {
+        for cat in cats {
+            let mut rows = tx_stmt.query(rusqlite::params![cat, year])?;
+            while let Some(r) = rows.next()? {
+                let account: String = r.get(0)?;
+                let d: String = r.get(1)?;
+                let amt_s: String = r.get(2)?;
+                let ccy: String = r.get(3)?;
+                let date = chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")?;
+                let amt = amt_s
+                    .parse::<Decimal>()
+                    .with_context(|| format!("Invalid amount '{}' in transactions", amt_s))?;
+                let cost = crate::utils::fx_convert(conn, date, -amt, &ccy, base)?;
+                *totals.entry((order, account)).or_insert(Decimal::ZERO) += cost;
+            }
+        }
+    }
+
+    let mut trade_stmt = conn.prepare_cached(
+        "SELECT ac.name, t.date, t.fees, COALESCE(t.fee_currency, a.currency)
+         FROM trades t
+         JOIN assets a ON t.asset_id=a.id
+         JOIN accounts ac ON t.account_id=ac.id
+         WHERE substr(t.date,1,4)=?1",
+    )?;
+    let mut rows = trade_stmt.query([year])?;
+    while let Some(r) = rows.next()? {
+        let account: String = r.get(0)?;
+        let d: String = r.get(1)?;
+        let fee_s: String = r.get(2)?;
+        let ccy: String = r.get(3)?;
+        let fee = fee_s
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid trade fees '{}' on {}", fee_s, d))?;
+        if fee.is_zero() {
+            continue;
+        }
+        let date = chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")?;
+        let cost = crate::utils::fx_convert(conn, date, fee, &ccy, base)?;
+        *totals.entry((2, account)).or_insert(Decimal::ZERO) += cost;
+    }
+
+    let labels = ["Bank fees", "Interest", "Trade fees"];
+    let mut grand_total = Decimal::ZERO;
+    let mut data = Vec::with_capacity(totals.len() + 1);
+    for ((order, account), amount) in totals {
+        grand_total += amount;
+        data.push(FeeRow {
+            source: labels[order].to_string(),
+            account,
+            cost: fmt_amount(amount, base),
+        });
+    }
+    data.push(FeeRow {
+        source: "Total".to_string(),
+        account: String::new(),
+        cost: fmt_amount(grand_total, base),
+    });
+    Ok(data)
+}
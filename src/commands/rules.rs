@@ -4,17 +4,47 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use crate::utils::{id_for_category, invalidate_rule_cache, pretty_table};
-use anyhow::{Result, anyhow};
+use crate::utils::{
+    MatchField, id_for_category, invalidate_rule_cache, matching_rules, maybe_print_json,
+    pretty_table, rule_regex_source, today,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::NaiveDate;
 use regex::Regex;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Rule packs shipped with the binary for `rules bootstrap`.
+pub const PACKS: &[(&str, &str)] = &[
+    ("default-us", include_str!("rule_packs/default-us.toml")),
+    ("default-in", include_str!("rule_packs/default-in.toml")),
+];
+
+#[derive(Debug, Deserialize)]
+pub struct RulePack {
+    pub rule: Vec<PackRule>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PackRule {
+    pub pattern: String,
+    pub category: String,
+    /// Optional payee rewrite, as with `rules add --payee_rewrite`.
+    pub payee: Option<String>,
+}
 
 pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
         Some(("add", sub)) => {
             let pattern_raw = sub.get_one::<String>("pattern").unwrap();
             let pattern = pattern_raw.trim();
-            Regex::new(pattern)
+            let ignore_case = sub.get_flag("ignore-case");
+            let literal = sub.get_flag("literal");
+            let skip = sub.get_flag("skip");
+            let field = sub.get_one::<String>("match-field").unwrap();
+            MatchField::parse(field)?;
+            Regex::new(&rule_regex_source(pattern, ignore_case, literal))
                 .map_err(|err| anyhow!("Invalid regex pattern '{}': {}", pattern, err))?;
 
             let cat = sub
@@ -33,34 +63,116 @@ pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
                 None
             };
             conn.execute(
-                "INSERT INTO rules(pattern, category_id, payee_rewrite) VALUES (?1,?2,?3)",
-                params![pattern, cat_id, rewrite],
+                "INSERT INTO rules(pattern, category_id, payee_rewrite, ignore_case, literal, match_field, skip)
+                 VALUES (?1,?2,?3,?4,?5,?6,?7)",
+                params![pattern, cat_id, rewrite, ignore_case, literal, field.trim().to_ascii_lowercase(), skip],
             )?;
             invalidate_rule_cache(conn);
-            println!(
-                "Added rule: /{}/ -> category {:?}, rewrite {:?}",
-                pattern, cat_id, rewrite
-            );
+            if skip {
+                println!("Added skip rule: /{}/ rows are dropped on import", pattern);
+            } else {
+                println!(
+                    "Added rule: /{}/ -> category {:?}, rewrite {:?}",
+                    pattern, cat_id, rewrite
+                );
+            }
         }
         Some(("list", _)) => {
-            let mut stmt = conn.prepare("SELECT id, pattern, COALESCE((SELECT name FROM categories WHERE id=category_id),'') as category, COALESCE(payee_rewrite,'') FROM rules ORDER BY id DESC")?;
+            let mut stmt = conn.prepare("SELECT id, pattern, COALESCE((SELECT name FROM categories WHERE id=category_id),'') as category, COALESCE(payee_rewrite,''), ignore_case, literal, match_field, skip FROM rules ORDER BY id DESC")?;
             let rows = stmt.query_map([], |r| {
+                let mut options = vec![r.get::<_, String>(6)?];
+                if r.get::<_, bool>(7)? {
+                    options.push("skip".into());
+                }
+                if r.get::<_, bool>(5)? {
+                    options.push("literal".into());
+                }
+                if r.get::<_, bool>(4)? {
+                    options.push("ignore-case".into());
+                }
                 Ok((
                     r.get::<_, i64>(0)?,
                     r.get::<_, String>(1)?,
                     r.get::<_, String>(2)?,
                     r.get::<_, String>(3)?,
+                    options.join(", "),
                 ))
             })?;
             let mut data = Vec::new();
             for row in rows {
-                let (id, pat, cat, rew) = row?;
-                data.push(vec![id.to_string(), pat, cat, rew]);
+                let (id, pat, cat, rew, opts) = row?;
+                data.push(vec![id.to_string(), pat, cat, rew, opts]);
+            }
+            println!(
+                "{}",
+                pretty_table(
+                    &["ID", "Pattern", "Category", "Payee Rewrite", "Matches"],
+                    data
+                )
+            );
+        }
+        Some(("lint", sub)) => {
+            let days = *sub.get_one::<u64>("days").unwrap();
+            let since = today() - chrono::Days::new(days);
+            let report = lint(conn, since)?;
+            if maybe_print_json(sub.get_flag("json"), false, std::slice::from_ref(&report))? {
+                return Ok(());
+            }
+            if report.overlaps.is_empty() {
+                println!(
+                    "No overlapping rules among {} payees since {}",
+                    report.payees_checked, since
+                );
+                return Ok(());
             }
+            let rows = report
+                .overlaps
+                .iter()
+                .map(|o| {
+                    vec![
+                        o.rule.to_string(),
+                        o.pattern.clone(),
+                        o.shadows.to_string(),
+                        o.shadowed_pattern.clone(),
+                        o.payees.to_string(),
+                        o.examples.join(", "),
+                    ]
+                })
+                .collect();
             println!(
                 "{}",
-                pretty_table(&["ID", "Pattern", "Category", "Payee Rewrite"], data)
+                pretty_table(
+                    &[
+                        "Rule",
+                        "Pattern",
+                        "Shadows rule",
+                        "Its pattern",
+                        "Payees",
+                        "Examples"
+                    ],
+                    rows
+                )
             );
+            for s in &report.shadowed {
+                let by: Vec<String> = s.by.iter().map(|id| id.to_string()).collect();
+                println!(
+                    "Rule {} /{}/ never applies: rule(s) {} match its payees first",
+                    s.rule,
+                    s.pattern,
+                    by.join(", ")
+                );
+            }
+        }
+        Some(("bootstrap", sub)) => {
+            let name = sub.get_one::<String>("pack").unwrap().trim();
+            let (added, skipped, created) = bootstrap(conn, name)?;
+            println!(
+                "Added {} rules from pack '{}' ({} already present)",
+                added, name, skipped
+            );
+            if !created.is_empty() {
+                println!("Created categories: {}", created.join(", "));
+            }
         }
         Some(("rm", sub)) => {
             let raw = sub.get_one::<String>("id").unwrap();
@@ -73,3 +185,185 @@ pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
     }
     Ok(())
 }
+
+/// Two rules matching the same payees; `rule` is tried first and wins.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct RuleOverlap {
+    pub rule: i64,
+    pub pattern: String,
+    pub shadows: i64,
+    pub shadowed_pattern: String,
+    /// Distinct payees both rules match.
+    pub payees: usize,
+    pub examples: Vec<String>,
+}
+
+/// A rule that matches recent payees but never takes effect on any of them.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ShadowedRule {
+    pub rule: i64,
+    pub pattern: String,
+    /// The rules that win on its payees.
+    pub by: Vec<i64>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct RuleLint {
+    pub payees_checked: usize,
+    pub overlaps: Vec<RuleOverlap>,
+    pub shadowed: Vec<ShadowedRule>,
+}
+
+/// Examples listed per overlap.
+const LINT_EXAMPLES: usize = 3;
+
+/// Check the rules against the payees and notes of transactions since `since`. Stored
+/// payees may already be rewritten by a rule, so they approximate what the bank sent.
+pub fn lint(conn: &Connection, since: NaiveDate) -> Result<RuleLint> {
+    let patterns: BTreeMap<i64, String> = conn
+        .prepare("SELECT id, pattern FROM rules")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT payee, note FROM transactions WHERE date >= ?1 ORDER BY payee, note",
+    )?;
+    let samples = stmt
+        .query_map(params![since.to_string()], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // (winner, loser) -> payees both match; loser -> winners over it.
+    let mut pairs: BTreeMap<(i64, i64), BTreeSet<String>> = BTreeMap::new();
+    let mut winners: BTreeMap<i64, BTreeSet<i64>> = BTreeMap::new();
+    let mut won: BTreeSet<i64> = BTreeSet::new();
+    for (payee, note) in &samples {
+        let matched = matching_rules(conn, payee, note.as_deref())?;
+        let Some((&first, rest)) = matched.split_first() else {
+            continue;
+        };
+        won.insert(first);
+        for &loser in rest {
+            pairs
+                .entry((first, loser))
+                .or_default()
+                .insert(payee.clone());
+            winners.entry(loser).or_default().insert(first);
+        }
+    }
+    let pattern = |id: &i64| patterns.get(id).cloned().unwrap_or_default();
+    let mut overlaps: Vec<RuleOverlap> = pairs
+        .into_iter()
+        .map(|((rule, shadows), payees)| RuleOverlap {
+            rule,
+            pattern: pattern(&rule),
+            shadows,
+            shadowed_pattern: pattern(&shadows),
+            payees: payees.len(),
+            examples: payees.into_iter().take(LINT_EXAMPLES).collect(),
+        })
+        .collect();
+    overlaps.sort_by(|a, b| b.payees.cmp(&a.payees).then(a.rule.cmp(&b.rule)));
+    let shadowed = winners
+        .into_iter()
+        .filter(|(id, _)| !won.contains(id))
+        .map(|(rule, by)| ShadowedRule {
+            rule,
+            pattern: pattern(&rule),
+            by: by.into_iter().collect(),
+        })
+        .collect();
+    Ok(RuleLint {
+        payees_checked: samples.len(),
+        overlaps,
+        shadowed,
+    })
+}
+
+pub fn load_pack(name: &str) -> Result<RulePack> {
+    let Some((_, text)) = PACKS.iter().find(|(n, _)| *n == name) else {
+        let known: Vec<&str> = PACKS.iter().map(|(n, _)| *n).collect();
+        bail!("Unknown rule pack '{}'; known: {}", name, known.join(", "));
+    };
+    toml::from_str(text).with_context(|| format!("Parse rule pack '{}'", name))
+}
+
+/// Seed the rules of pack `name`, creating missing categories. Patterns that already
+/// exist are skipped so running it twice is harmless. Newer rules win at import
+/// time, so the pack is inserted last-to-first to keep its own order of precedence.
+/// Returns (added, skipped, created categories).
+pub fn bootstrap(conn: &Connection, name: &str) -> Result<(usize, usize, Vec<String>)> {
+    let pack = load_pack(name)?;
+    conn.execute_batch("SAVEPOINT rules_bootstrap")?;
+    match seed_pack(conn, &pack) {
+        Ok(seeded) => {
+            conn.execute_batch("RELEASE rules_bootstrap")?;
+            invalidate_rule_cache(conn);
+            Ok(seeded)
+        }
+        Err(err) => {
+            conn.execute_batch("ROLLBACK TO rules_bootstrap; RELEASE rules_bootstrap")?;
+            Err(err)
+        }
+    }
+}
+
+fn seed_pack(conn: &Connection, pack: &RulePack) -> Result<(usize, usize, Vec<String>)> {
+    let (mut added, mut skipped, mut created) = (0, 0, Vec::new());
+    for rule in pack.rule.iter().rev() {
+        Regex::new(&rule.pattern)
+            .map_err(|err| anyhow!("Invalid regex pattern '{}': {}", rule.pattern, err))?;
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM rules WHERE pattern=?1)",
+            params![rule.pattern],
+            |r| r.get(0),
+        )?;
+        if exists {
+            skipped += 1;
+            continue;
+        }
+        let cat_id = match conn
+            .query_row(
+                "SELECT id FROM categories WHERE name=?1 COLLATE NOCASE",
+                params![rule.category],
+                |r| r.get::<_, i64>(0),
+            )
+            .optional()?
+        {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    "INSERT INTO categories(name) VALUES (?1)",
+                    params![rule.category],
+                )?;
+                created.push(rule.category.clone());
+                conn.last_insert_rowid()
+            }
+        };
+        conn.execute(
+            "INSERT INTO rules(pattern, category_id, payee_rewrite) VALUES (?1,?2,?3)",
+            params![rule.pattern, cat_id, rule.payee],
+        )?;
+        added += 1;
+    }
+    created.reverse();
+    Ok((added, skipped, created))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PACKS, load_pack};
+    use regex::Regex;
+
+    #[test]
+    fn shipped_packs_parse_and_compile() {
+        for (name, _) in PACKS {
+            let pack = load_pack(name).unwrap();
+            assert!(!pack.rule.is_empty(), "{} is empty", name);
+            for rule in &pack.rule {
+                Regex::new(&rule.pattern).unwrap();
+            }
+        }
+        assert!(load_pack("default-xx").is_err());
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Sales tax (VAT/GST) carried by transactions: the tax part of each amount and its
+//! code, and `report tax-collected` for what is due to the tax office per quarter.
+
+use crate::utils::{
+    fmt_amount, fx_convert_batch, get_base_currency, maybe_print_json, minor_units,
+    parse_amount_in, parse_decimal, parse_quarter, pretty_table,
+};
+use anyhow::{Context, Result, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, params};
+use rust_decimal::Decimal;
+
+/// The tax part of `amount` when `rate_percent` is included in it, rounded to the
+/// currency's minor unit: 119 at 19% holds 19.
+pub fn inclusive_tax(amount: Decimal, rate_percent: Decimal, ccy: &str) -> Decimal {
+    let hundred = Decimal::ONE_HUNDRED;
+    (amount * rate_percent / (hundred + rate_percent)).round_dp(minor_units(ccy))
+}
+
+/// Tax of a transaction of `amount` from an explicit tax amount or an inclusive rate
+/// (`19` or `19%`), signed like the transaction: collected on income, paid on spending.
+pub fn tax_for(
+    amount: Decimal,
+    tax: Option<&str>,
+    rate: Option<&str>,
+    ccy: &str,
+) -> Result<Option<Decimal>> {
+    let tax = match (tax, rate) {
+        (Some(tax), _) => {
+            let tax = parse_amount_in(tax.trim(), ccy)?.abs();
+            if amount < Decimal::ZERO { -tax } else { tax }
+        }
+        (None, Some(rate)) => {
+            let rate = parse_decimal(rate.trim().trim_end_matches('%'))
+                .with_context(|| format!("Invalid tax rate '{}'", rate))?;
+            ensure!(rate >= Decimal::ZERO, "Tax rate must not be negative");
+            inclusive_tax(amount, rate, ccy)
+        }
+        (None, None) => return Ok(None),
+    };
+    ensure!(
+        tax.abs() <= amount.abs(),
+        "Tax {} is more than the amount {}",
+        tax.abs(),
+        amount.abs()
+    );
+    Ok(Some(tax))
+}
+
+/// Tax of one code over a period, in the base currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct TaxLine {
+    pub code: String,
+    /// Tax contained in income (output tax).
+    pub collected: Decimal,
+    /// Tax contained in spending (input tax), as a positive amount.
+    pub paid: Decimal,
+    /// `collected - paid`: what is owed to the tax office, or reclaimable if negative.
+    pub net: Decimal,
+}
+
+pub fn tax_collected_report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let quarter = sub.get_one::<String>("quarter").unwrap();
+    let (label, from, to) = parse_quarter(quarter)?;
+    let lines = tax_collected(conn, from, to)?;
+    if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &lines)? {
+        return Ok(());
+    }
+    if lines.is_empty() {
+        crate::utils::emit(format!("No taxed transactions in {}", label));
+        return Ok(());
+    }
+    let base = get_base_currency(conn)?;
+    let mut total = (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+    let mut rows = Vec::new();
+    for l in &lines {
+        total = (total.0 + l.collected, total.1 + l.paid, total.2 + l.net);
+        rows.push(vec![
+            l.code.clone(),
+            fmt_amount(l.collected, &base),
+            fmt_amount(l.paid, &base),
+            fmt_amount(l.net, &base),
+        ]);
+    }
+    rows.push(vec![
+        "Total".to_string(),
+        fmt_amount(total.0, &base),
+        fmt_amount(total.1, &base),
+        fmt_amount(total.2, &base),
+    ]);
+    crate::utils::emit(pretty_table(
+        &[
+            &format!("Tax code ({})", label),
+            "Collected",
+            "Paid",
+            &format!("Net due ({})", base),
+        ],
+        rows,
+    ));
+    Ok(())
+}
+
+/// Tax on transactions dated `from..=to` per tax code, converted to the base currency
+/// at each transaction's date. Transactions with a code but no tax amount (exempt or
+/// zero-rated sales) still list their code.
+pub fn tax_collected(conn: &Connection, from: NaiveDate, to: NaiveDate) -> Result<Vec<TaxLine>> {
+    let base = get_base_currency(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(tax_code, '(none)'), date, COALESCE(tax_amount, '0'), currency
+         FROM transactions
+         WHERE (tax_amount IS NOT NULL OR tax_code IS NOT NULL) AND date BETWEEN ?1 AND ?2
+         ORDER BY 1, date",
+    )?;
+    let rows = stmt
+        .query_map(params![from.to_string(), to.to_string()], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut items = Vec::with_capacity(rows.len());
+    for (_, date, tax, currency) in &rows {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        let tax = tax
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid tax amount '{}' on {}", tax, date))?;
+        items.push((date, tax, currency.as_str()));
+    }
+    let converted = fx_convert_batch(conn, &items, &base)?;
+    let mut out: Vec<TaxLine> = Vec::new();
+    for ((code, ..), tax) in rows.iter().zip(converted) {
+        let line = match out.last_mut() {
+            Some(l) if l.code == *code => l,
+            _ => {
+                out.push(TaxLine {
+                    code: code.clone(),
+                    collected: Decimal::ZERO,
+                    paid: Decimal::ZERO,
+                    net: Decimal::ZERO,
+                });
+                out.last_mut().unwrap()
+            }
+        };
+        if tax > Decimal::ZERO {
+            line.collected += tax;
+        } else {
+            line.paid -= tax;
+        }
+        line.net += tax;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Precision, Settings, with_settings};
+    use std::str::FromStr;
+
+    #[test]
+    fn tax_keeps_its_minor_units_under_a_display_override() {
+        let settings = Settings {
+            precision: Precision {
+                money: 0,
+                ..Precision::DEFAULT
+            },
+            ..Settings::DEFAULT
+        };
+        let amount = Decimal::from(-100);
+        let (inclusive, explicit) = with_settings(settings, || {
+            (
+                tax_for(amount, None, Some("19%"), "USD").unwrap(),
+                tax_for(amount, Some("10/3"), None, "USD").unwrap(),
+            )
+        });
+        assert_eq!(inclusive, Some(Decimal::from_str("-15.97").unwrap()));
+        assert_eq!(explicit, Some(Decimal::from_str("-3.33").unwrap()));
+    }
+}
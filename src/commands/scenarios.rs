@@ -0,0 +1,324 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Budget scenarios (`budget scenario`): named what-if copies of one month's budgets,
+//! edited on their own and compared with the real budgets of that month. Real budgets
+//! are never written.
+
+use crate::commands::budgets::budget_in;
+use crate::models::ScenarioRow;
+use crate::utils::{
+    fmt_amount, fmt_percent, fx_convert, fx_convert_batch, get_base_currency, id_for_category,
+    maybe_print_json, parse_amount, parse_month, pretty_table, valuation_month_end,
+};
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("create", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let month = parse_month(sub.get_one::<String>("month").unwrap().trim())?;
+            let copied = create(conn, name, &month)?;
+            println!(
+                "Created scenario '{}' from {} budget(s) of {}",
+                name, copied, month
+            );
+        }
+        Some(("set", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let id = scenario_id(conn, name)?.0;
+            let category = sub.get_one::<String>("category").unwrap().trim();
+            let cat_id = id_for_category(conn, category)?;
+            let amount = parse_amount(sub.get_one::<String>("amount").unwrap().trim())?;
+            let ccy = match sub.get_one::<String>("currency") {
+                Some(c) => c.trim().to_uppercase(),
+                None => get_base_currency(conn)?,
+            };
+            conn.execute(
+                "INSERT INTO budget_scenario_lines(scenario_id, category_id, amount, currency)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(scenario_id, category_id)
+                 DO UPDATE SET amount=excluded.amount, currency=excluded.currency",
+                params![id, cat_id, amount.to_string(), ccy],
+            )?;
+            println!("Scenario '{}': {} = {} {}", name, category, amount, ccy);
+        }
+        Some(("list", sub)) => {
+            let mut stmt = conn.prepare(
+                "SELECT s.name, s.month, COUNT(l.id) FROM budget_scenarios s
+                 LEFT JOIN budget_scenario_lines l ON l.scenario_id=s.id
+                 GROUP BY s.id ORDER BY s.name",
+            )?;
+            let data = stmt
+                .query_map([], |r| {
+                    Ok(ScenarioRow {
+                        scenario: r.get(0)?,
+                        month: r.get(1)?,
+                        categories: r.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                println!(
+                    "{}",
+                    pretty_table(
+                        &["Scenario", "Month", "Categories"],
+                        data.into_iter().map(ScenarioRow::cells).collect()
+                    )
+                );
+            }
+        }
+        Some(("compare", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let income = sub
+                .get_one::<String>("income")
+                .map(|i| parse_amount(i.trim()))
+                .transpose()?;
+            let cmp = compare(conn, name, income)?;
+            if maybe_print_json(sub.get_flag("json"), false, std::slice::from_ref(&cmp))? {
+                return Ok(());
+            }
+            let ccy = &cmp.currency;
+            let mut rows: Vec<Vec<String>> = cmp
+                .lines
+                .iter()
+                .map(|l| {
+                    vec![
+                        l.category.clone(),
+                        fmt_amount(l.baseline, ccy),
+                        fmt_amount(l.scenario, ccy),
+                        fmt_amount(l.scenario - l.baseline, ccy),
+                    ]
+                })
+                .collect();
+            rows.push(vec![
+                "Total budgeted".to_string(),
+                fmt_amount(cmp.baseline_total, ccy),
+                fmt_amount(cmp.scenario_total, ccy),
+                fmt_amount(cmp.scenario_total - cmp.baseline_total, ccy),
+            ]);
+            rows.push(vec![
+                "Income".to_string(),
+                fmt_amount(cmp.income, ccy),
+                fmt_amount(cmp.income, ccy),
+                String::new(),
+            ]);
+            let baseline_savings = cmp.income - cmp.baseline_total;
+            let scenario_savings = cmp.income - cmp.scenario_total;
+            rows.push(vec![
+                "Savings".to_string(),
+                fmt_amount(baseline_savings, ccy),
+                fmt_amount(scenario_savings, ccy),
+                fmt_amount(scenario_savings - baseline_savings, ccy),
+            ]);
+            let rate = |r: Option<Decimal>| r.map_or("n/a".to_string(), fmt_percent);
+            rows.push(vec![
+                "Savings rate %".to_string(),
+                rate(cmp.baseline_rate),
+                rate(cmp.scenario_rate),
+                match (cmp.baseline_rate, cmp.scenario_rate) {
+                    (Some(b), Some(s)) => fmt_percent(s - b),
+                    _ => String::new(),
+                },
+            ]);
+            crate::utils::emit(pretty_table(
+                &[
+                    &format!("{} ({})", cmp.name, cmp.month),
+                    "Baseline",
+                    "Scenario",
+                    "Change",
+                ],
+                rows,
+            ));
+        }
+        Some(("rm", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            if conn.execute("DELETE FROM budget_scenarios WHERE name=?1", params![name])? == 0 {
+                bail!("No budget scenario '{}'", name);
+            }
+            println!("Removed budget scenario '{}'", name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn scenario_id(conn: &Connection, name: &str) -> Result<(i64, String)> {
+    conn.query_row(
+        "SELECT id, month FROM budget_scenarios WHERE name=?1",
+        params![name],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )
+    .optional()?
+    .with_context(|| format!("No budget scenario '{}'; see `budget scenario list`", name))
+}
+
+/// Copy the budgets of `month` into a new scenario `name`; returns how many were copied.
+pub fn create(conn: &Connection, name: &str, month: &str) -> Result<usize> {
+    conn.execute_batch("SAVEPOINT budget_scenario")?;
+    let res = (|| -> Result<usize> {
+        conn.execute(
+            "INSERT INTO budget_scenarios(name, month) VALUES (?1, ?2)",
+            params![name, month],
+        )
+        .with_context(|| format!("Create scenario '{}'", name))?;
+        let id = conn.last_insert_rowid();
+        // Budgets without a currency predate per-budget currencies and are in BASE.
+        Ok(conn.execute(
+            "INSERT INTO budget_scenario_lines(scenario_id, category_id, amount, currency)
+             SELECT ?1, category_id, amount, COALESCE(currency, ?3) FROM budgets WHERE month=?2",
+            params![id, month, get_base_currency(conn)?],
+        )?)
+    })();
+    match res {
+        Ok(copied) => {
+            conn.execute_batch("RELEASE budget_scenario")?;
+            Ok(copied)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO budget_scenario; RELEASE budget_scenario")?;
+            Err(e)
+        }
+    }
+}
+
+/// One category's budget in the real month and in the scenario, in BASE.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ScenarioLine {
+    pub category: String,
+    pub baseline: Decimal,
+    pub scenario: Decimal,
+}
+
+/// A scenario against the real budgets of its month, in BASE.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ScenarioComparison {
+    pub name: String,
+    pub month: String,
+    pub currency: String,
+    pub income: Decimal,
+    pub lines: Vec<ScenarioLine>,
+    pub baseline_total: Decimal,
+    pub scenario_total: Decimal,
+    /// Percent of income left after budgeted spending; `None` without income.
+    pub baseline_rate: Option<Decimal>,
+    pub scenario_rate: Option<Decimal>,
+}
+
+/// Compare scenario `name` with the month's current budgets. Savings are income less
+/// everything budgeted; income is the month's actual inflows unless `income` is given.
+pub fn compare(
+    conn: &Connection,
+    name: &str,
+    income: Option<Decimal>,
+) -> Result<ScenarioComparison> {
+    let (id, month) = scenario_id(conn, name)?;
+    let base = get_base_currency(conn)?;
+    let month_end = valuation_month_end(conn, &month)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, l.amount, l.currency FROM budget_scenario_lines l
+         JOIN categories c ON c.id=l.category_id WHERE l.scenario_id=?1",
+    )?;
+    let scenario = stmt
+        .query_map(params![id], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name FROM budgets b JOIN categories c ON c.id=b.category_id
+         WHERE b.month=?1",
+    )?;
+    let budgeted = stmt
+        .query_map(params![month], |r| {
+            Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut lines: BTreeMap<String, ScenarioLine> = BTreeMap::new();
+    for (cat_id, category) in budgeted {
+        let baseline = budget_in(conn, cat_id, &month, &base)?;
+        lines.insert(
+            category.clone(),
+            ScenarioLine {
+                category,
+                baseline,
+                scenario: Decimal::ZERO,
+            },
+        );
+    }
+    for (_, category, raw, ccy) in scenario {
+        let amount = raw
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid scenario amount '{}' for {}", raw, category))?;
+        let amount = fx_convert(conn, month_end, amount, &ccy, &base)?;
+        lines
+            .entry(category.clone())
+            .or_insert_with(|| ScenarioLine {
+                category,
+                baseline: Decimal::ZERO,
+                scenario: Decimal::ZERO,
+            })
+            .scenario = amount;
+    }
+    let lines: Vec<ScenarioLine> = lines.into_values().collect();
+    let baseline_total: Decimal = lines.iter().map(|l| l.baseline).sum();
+    let scenario_total: Decimal = lines.iter().map(|l| l.scenario).sum();
+    let income = match income {
+        Some(i) => i,
+        None => month_income(conn, &month, &base)?,
+    };
+    let rate = |spent: Decimal| {
+        (income > Decimal::ZERO).then(|| (income - spent) / income * Decimal::ONE_HUNDRED)
+    };
+    Ok(ScenarioComparison {
+        name: name.to_string(),
+        month,
+        currency: base,
+        income,
+        baseline_rate: rate(baseline_total),
+        scenario_rate: rate(scenario_total),
+        lines,
+        baseline_total,
+        scenario_total,
+    })
+}
+
+/// Inflows of `month` in `base`, each converted at its own date.
+fn month_income(conn: &Connection, month: &str, base: &str) -> Result<Decimal> {
+    let (start, end) = crate::utils::periods().month_range(month)?;
+    let mut stmt = conn.prepare(
+        "SELECT date, amount, currency FROM transactions
+         WHERE amount > 0 AND date BETWEEN ?1 AND ?2",
+    )?;
+    let rows = stmt
+        .query_map(params![start.to_string(), end.to_string()], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut items = Vec::with_capacity(rows.len());
+    for (date, amount, ccy) in &rows {
+        let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        items.push((date, amount, ccy.as_str()));
+    }
+    Ok(fx_convert_batch(conn, &items, base)?.into_iter().sum())
+}
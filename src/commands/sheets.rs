@@ -0,0 +1,276 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! `export sheets`: push transactions, budgets or envelopes into a Google Sheets tab.
+//! Talking to Google needs the `sheets` feature; building the rows is always built.
+
+use crate::commands::exporter::{budget_rows, envelope_rows};
+use anyhow::{Result, bail};
+use rusqlite::{Connection, params};
+
+/// What `--source` can push.
+pub const SOURCES: &[&str] = &["transactions", "budgets", "envelopes"];
+
+pub fn handle(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let spreadsheet = sub.get_one::<String>("spreadsheet-id").unwrap().trim();
+    let tab = sub.get_one::<String>("tab").unwrap().trim();
+    let append = sub.get_one::<String>("mode").map(|s| s.as_str()) == Some("append");
+    let mut values = sheet_values(conn, sub)?;
+    if append {
+        // The tab already has its header row.
+        values.remove(0);
+    }
+    let rows = values.len();
+    push(conn, spreadsheet, tab, values, append)?;
+    let verb = if append { "Appended" } else { "Wrote" };
+    println!("{} {} rows to tab '{}'", verb, rows, tab);
+    Ok(())
+}
+
+/// Header row plus one row per record of `--source`, as the sheet will show them.
+pub fn sheet_values(conn: &Connection, sub: &clap::ArgMatches) -> Result<Vec<Vec<String>>> {
+    let source = sub.get_one::<String>("source").unwrap().as_str();
+    let month = sub
+        .get_one::<String>("month")
+        .map(|m| crate::utils::parse_month(m.trim()))
+        .transpose()?;
+    let mut values = Vec::new();
+    match source {
+        "transactions" => {
+            let (from, to) = crate::cli::date_range(sub)?;
+            values.push(
+                [
+                    "date", "account", "payee", "amount", "currency", "category", "note",
+                ]
+                .map(String::from)
+                .to_vec(),
+            );
+            let mut stmt = conn.prepare(
+                "SELECT t.date, a.name, t.payee, t.amount, t.currency, c.name, t.note
+                 FROM transactions t
+                 LEFT JOIN accounts a ON t.account_id=a.id
+                 LEFT JOIN categories c ON t.category_id=c.id
+                 WHERE (?1 IS NULL OR t.date>=?1) AND (?2 IS NULL OR t.date<=?2)
+                   AND (?3 IS NULL OR substr(t.date,1,7)=?3)
+                 ORDER BY t.date, t.id",
+            )?;
+            let mut rows = stmt.query(params![
+                from.map(|d| d.to_string()),
+                to.map(|d| d.to_string()),
+                month
+            ])?;
+            while let Some(r) = rows.next()? {
+                let mut row = Vec::with_capacity(7);
+                for i in 0..7 {
+                    let cell = r.get::<_, Option<String>>(i)?.unwrap_or_default();
+                    // Amounts (column 3) should still be read as numbers.
+                    row.push(if i == 3 { cell } else { text_cell(cell) });
+                }
+                values.push(row);
+            }
+        }
+        "budgets" | "envelopes" => {
+            let Some(month) = month else {
+                bail!("--source {} needs --month", source);
+            };
+            if source == "budgets" {
+                values.push(
+                    [
+                        "month",
+                        "category",
+                        "budget",
+                        "spent",
+                        "remaining",
+                        "currency",
+                    ]
+                    .map(String::from)
+                    .to_vec(),
+                );
+                for b in budget_rows(conn, &[month])? {
+                    values.push(vec![
+                        b.month,
+                        text_cell(b.category),
+                        b.budget,
+                        b.spent,
+                        b.remaining,
+                        b.currency,
+                    ]);
+                }
+            } else {
+                values.push(
+                    [
+                        "month",
+                        "category",
+                        "carryover",
+                        "funded",
+                        "spent",
+                        "available",
+                        "currency",
+                    ]
+                    .map(String::from)
+                    .to_vec(),
+                );
+                for e in envelope_rows(conn, &month)? {
+                    values.push(vec![
+                        e.month,
+                        text_cell(e.category),
+                        e.carryover,
+                        e.funded,
+                        e.spent,
+                        e.available,
+                        e.currency,
+                    ]);
+                }
+            }
+        }
+        other => bail!("Unknown source '{}'; known: {}", other, SOURCES.join(", ")),
+    }
+    Ok(values)
+}
+
+/// Sheets parses cells as if typed (`USER_ENTERED`, so amounts and dates become
+/// numbers), which would run an imported payee like `=IMPORTXML(...)` as a formula.
+/// A leading `'` makes such free text a plain string; the quote itself is not shown.
+fn text_cell(text: String) -> String {
+    if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", text)
+    } else {
+        text
+    }
+}
+
+/// Write `values` to `tab`: clear it and start at A1, or append below what is there.
+/// Authenticates as the service account whose JSON key `sheets_credentials` points to;
+/// share the spreadsheet with that account's e-mail address first.
+#[cfg(feature = "sheets")]
+fn push(
+    conn: &Connection,
+    spreadsheet: &str,
+    tab: &str,
+    values: Vec<Vec<String>>,
+    append: bool,
+) -> Result<()> {
+    use crate::logging::SendTraced;
+    use anyhow::Context;
+
+    let token = access_token(conn)?;
+    let client = crate::utils::http_client()?;
+    let range_url = |range: &str, suffix: &str| -> Result<reqwest::Url> {
+        let mut url = reqwest::Url::parse("https://sheets.googleapis.com/v4/spreadsheets")?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid Sheets URL"))?
+            .push(spreadsheet)
+            .push("values")
+            .push(&format!("{}{}", range, suffix));
+        Ok(url)
+    };
+    let body = serde_json::json!({ "values": values });
+    let response = if append {
+        let mut url = range_url(&format!("'{}'!A1", tab), ":append")?;
+        url.query_pairs_mut()
+            .append_pair("valueInputOption", "USER_ENTERED")
+            .append_pair("insertDataOption", "INSERT_ROWS");
+        client
+            .post(url)
+            .bearer_auth(&token)
+            .json(&body)
+            .send_traced()
+    } else {
+        client
+            .post(range_url(&format!("'{}'", tab), ":clear")?)
+            .bearer_auth(&token)
+            .json(&serde_json::json!({}))
+            .send_traced()
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Clear tab '{}'", tab))?;
+        let mut url = range_url(&format!("'{}'!A1", tab), "")?;
+        url.query_pairs_mut()
+            .append_pair("valueInputOption", "USER_ENTERED");
+        client
+            .put(url)
+            .bearer_auth(&token)
+            .json(&body)
+            .send_traced()
+    };
+    response
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Write to tab '{}' of spreadsheet {}", tab, spreadsheet))?;
+    Ok(())
+}
+
+/// OAuth token for the service account: a signed JWT exchanged at the key's token URI.
+#[cfg(feature = "sheets")]
+fn access_token(conn: &Connection) -> Result<String> {
+    use crate::logging::SendTraced;
+    use anyhow::Context;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize)]
+    struct ServiceAccount {
+        client_email: String,
+        private_key: String,
+        token_uri: String,
+    }
+    #[derive(Serialize)]
+    struct Claims<'a> {
+        iss: &'a str,
+        scope: &'a str,
+        aud: &'a str,
+        iat: i64,
+        exp: i64,
+    }
+    #[derive(Deserialize)]
+    struct Token {
+        access_token: String,
+    }
+
+    let path = crate::utils::get_setting(conn, "sheets_credentials")?.context(
+        "Set the service-account key with `config set sheets_credentials /path/to/key.json`",
+    )?;
+    let raw =
+        std::fs::read_to_string(&path).with_context(|| format!("Read credentials {}", path))?;
+    let account: ServiceAccount = serde_json::from_str(&raw)
+        .with_context(|| format!("{} is not a service-account key", path))?;
+    let now = chrono::Utc::now().timestamp();
+    let claims = Claims {
+        iss: &account.client_email,
+        scope: "https://www.googleapis.com/auth/spreadsheets",
+        aud: &account.token_uri,
+        iat: now,
+        exp: now + 3600,
+    };
+    let key = jsonwebtoken::EncodingKey::from_rsa_pem(account.private_key.as_bytes())
+        .context("Invalid private_key in the service-account key")?;
+    let assertion = jsonwebtoken::encode(
+        &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+        &claims,
+        &key,
+    )?;
+    let body = format!(
+        "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer&assertion={}",
+        assertion
+    );
+    let token: Token = crate::utils::http_client()?
+        .post(&account.token_uri)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body)
+        .send_traced()
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Sign in as {}", account.client_email))?
+        .json()?;
+    Ok(token.access_token)
+}
+
+#[cfg(not(feature = "sheets"))]
+fn push(
+    _conn: &Connection,
+    _spreadsheet: &str,
+    _tab: &str,
+    _values: Vec<Vec<String>>,
+    _append: bool,
+) -> Result<()> {
+    bail!("This build has no Google Sheets support; rebuild with `--features sheets`")
+}
@@ -0,0 +1,233 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::utils::{fmt_amount, fmt_signed, maybe_print_json, pretty_table};
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Report figures keyed by `(section, key)`, e.g. `("spend", "2025-07 Groceries (USD)")`.
+pub type Figures = BTreeMap<(String, String), Decimal>;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("save", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let lines = save_snapshot(conn, name)?;
+            println!("Saved snapshot '{}' ({} figures)", name, lines);
+        }
+        Some(("list", _)) => {
+            let mut stmt = conn.prepare(
+                "SELECT s.name, s.created_at, COUNT(l.key)
+                 FROM report_snapshots s
+                 LEFT JOIN report_snapshot_lines l ON l.snapshot_id = s.id
+                 GROUP BY s.id ORDER BY s.created_at, s.name",
+            )?;
+            let rows = stmt
+                .query_map([], |r| {
+                    Ok(vec![
+                        r.get::<_, String>(0)?,
+                        r.get::<_, String>(1)?,
+                        r.get::<_, i64>(2)?.to_string(),
+                    ])
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            crate::utils::emit(pretty_table(&["Name", "Saved", "Figures"], rows));
+        }
+        Some(("rm", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let id = snapshot_id(conn, name)?;
+            conn.execute(
+                "DELETE FROM report_snapshot_lines WHERE snapshot_id=?1",
+                params![id],
+            )?;
+            conn.execute("DELETE FROM report_snapshots WHERE id=?1", params![id])?;
+            println!("Removed snapshot '{}'", name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `report diff FROM [TO]`; without TO the snapshot is compared with the live data.
+pub fn diff(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let json_flag = sub.get_flag("json");
+    let jsonl_flag = sub.get_flag("jsonl");
+    let from = sub.get_one::<String>("from").unwrap().trim();
+    let before = load_snapshot(conn, from)?;
+    let after = match sub.get_one::<String>("to") {
+        Some(to) => load_snapshot(conn, to.trim())?,
+        None => collect_figures(conn)?,
+    };
+    let changes = diff_figures(&before, &after);
+    if maybe_print_json(json_flag, jsonl_flag, &changes)? {
+        return Ok(());
+    }
+    if changes.is_empty() {
+        crate::utils::emit("No differences");
+        return Ok(());
+    }
+    let show = |v: Option<Decimal>| v.map(|d| fmt_amount(d, "")).unwrap_or("-".into());
+    let rows = changes
+        .iter()
+        .map(|c| {
+            vec![
+                c.section.clone(),
+                c.key.clone(),
+                show(c.before),
+                show(c.after),
+                fmt_signed(c.change, ""),
+            ]
+        })
+        .collect();
+    crate::utils::emit(pretty_table(
+        &["Section", "Item", "Before", "After", "Change"],
+        rows,
+    ));
+    Ok(())
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FigureChange {
+    pub section: String,
+    pub key: String,
+    pub before: Option<Decimal>,
+    pub after: Option<Decimal>,
+    pub change: Decimal,
+}
+
+/// Current account balances, monthly spend per category and monthly budgets, each
+/// in its own currency so snapshots do not move with FX rates.
+pub fn collect_figures(conn: &Connection) -> Result<Figures> {
+    let mut figures = Figures::new();
+    let parse = |s: &str, what: &str| {
+        s.trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid {} amount '{}'", what, s))
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT a.name, a.currency, t.amount
+         FROM accounts a LEFT JOIN transactions t ON t.account_id = a.id",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(r) = rows.next()? {
+        let key = format!("{} ({})", r.get::<_, String>(0)?, r.get::<_, String>(1)?);
+        let amount = match r.get::<_, Option<String>>(2)? {
+            Some(s) => parse(&s, "transaction")?,
+            None => Decimal::ZERO,
+        };
+        *figures.entry(("balance".into(), key)).or_default() += amount;
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT substr(t.date, 1, 7), c.name, t.currency, t.amount
+         FROM transactions t LEFT JOIN categories c ON c.id = t.category_id",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(r) = rows.next()? {
+        let amount = parse(&r.get::<_, String>(3)?, "transaction")?;
+        if amount >= Decimal::ZERO {
+            continue;
+        }
+        let key = format!(
+            "{} {} ({})",
+            r.get::<_, String>(0)?,
+            r.get::<_, Option<String>>(1)?
+                .unwrap_or("(uncategorized)".into()),
+            r.get::<_, String>(2)?
+        );
+        *figures.entry(("spend".into(), key)).or_default() -= amount;
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT b.month, c.name, b.amount FROM budgets b JOIN categories c ON c.id = b.category_id",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(r) = rows.next()? {
+        let key = format!("{} {}", r.get::<_, String>(0)?, r.get::<_, String>(1)?);
+        let amount = parse(&r.get::<_, String>(2)?, "budget")?;
+        figures.insert(("budget".into(), key), amount);
+    }
+    Ok(figures)
+}
+
+/// Store the current figures under `name`; returns how many were saved.
+pub fn save_snapshot(conn: &Connection, name: &str) -> Result<usize> {
+    if name.is_empty() {
+        bail!("Snapshot name must not be empty");
+    }
+    if snapshot_id(conn, name).is_ok() {
+        bail!(
+            "Snapshot '{}' already exists; remove it with `report snapshot rm --name {}`",
+            name,
+            name
+        );
+    }
+    let figures = collect_figures(conn)?;
+    conn.execute(
+        "INSERT INTO report_snapshots(name) VALUES (?1)",
+        params![name],
+    )?;
+    let id = conn.last_insert_rowid();
+    let mut insert = conn.prepare_cached(
+        "INSERT INTO report_snapshot_lines(snapshot_id, section, key, value) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for ((section, key), value) in &figures {
+        insert.execute(params![id, section, key, value.to_string()])?;
+    }
+    Ok(figures.len())
+}
+
+pub fn load_snapshot(conn: &Connection, name: &str) -> Result<Figures> {
+    let id = snapshot_id(conn, name)?;
+    let mut stmt =
+        conn.prepare("SELECT section, key, value FROM report_snapshot_lines WHERE snapshot_id=?1")?;
+    let mut rows = stmt.query(params![id])?;
+    let mut figures = Figures::new();
+    while let Some(r) = rows.next()? {
+        let value: String = r.get(2)?;
+        let amount = value
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid value '{}' in snapshot '{}'", value, name))?;
+        figures.insert((r.get(0)?, r.get(1)?), amount);
+    }
+    Ok(figures)
+}
+
+fn snapshot_id(conn: &Connection, name: &str) -> Result<i64> {
+    conn.query_row(
+        "SELECT id FROM report_snapshots WHERE name=?1",
+        params![name],
+        |r| r.get(0),
+    )
+    .optional()?
+    .with_context(|| format!("Snapshot '{}' not found", name))
+}
+
+/// Figures that differ between two snapshots, ordered by section and key.
+pub fn diff_figures(before: &Figures, after: &Figures) -> Vec<FigureChange> {
+    let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .filter_map(|k| {
+            let b = before.get(k).copied();
+            let a = after.get(k).copied();
+            let change = a.unwrap_or_default() - b.unwrap_or_default();
+            if b == a {
+                return None;
+            }
+            Some(FigureChange {
+                section: k.0.clone(),
+                key: k.1.clone(),
+                before: b,
+                after: a,
+                change,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! `sync`: fetch FX rates and asset prices together on the non-blocking providers,
+//! once or every `--every` minutes as a long-running process. Needs the `async` build
+//! feature.
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+pub fn handle(conn: &mut Connection, m: &clap::ArgMatches) -> Result<()> {
+    let days = *m.get_one::<u64>("days").unwrap();
+    let every = m.get_one::<u64>("every").copied();
+    run(conn, days, every)
+}
+
+#[cfg(not(feature = "async"))]
+fn run(_conn: &mut Connection, _days: u64, _every: Option<u64>) -> Result<()> {
+    anyhow::bail!("`sync` needs the `async` build feature (cargo build --features async)")
+}
+
+#[cfg(feature = "async")]
+fn run(conn: &mut Connection, days: u64, every: Option<u64>) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let Some(minutes) = every else {
+            return sync_once(conn, days).await;
+        };
+        let mut ticks = tokio::time::interval(std::time::Duration::from_secs(minutes * 60));
+        loop {
+            ticks.tick().await;
+            // A failed round (e.g. the network is down) is retried on the next tick.
+            if let Err(err) = sync_once(conn, days).await {
+                eprintln!("Warning: sync failed: {:#}", err);
+            }
+        }
+    })
+}
+
+/// Fetch the last `days` of rates and the latest prices concurrently, then store both
+/// and check price alerts.
+#[cfg(feature = "async")]
+async fn sync_once(conn: &mut Connection, days: u64) -> Result<()> {
+    use crate::commands::{fx, portfolio, price_alerts};
+    use crate::providers::asynchronous::{AsyncFxProvider, AsyncPriceProvider};
+    use crate::providers::{Frankfurter, Yahoo};
+    use crate::utils::{ensure_provider, get_base_currency, today};
+
+    let base = get_base_currency(conn)?.trim().to_uppercase();
+    let targets: Vec<String> = fx::distinct_currencies(conn)?
+        .into_iter()
+        .filter(|c| c != &base)
+        .collect();
+    let assets = portfolio::quote_symbols(conn)?;
+    if !targets.is_empty() {
+        ensure_provider(conn, "fx_provider", "frankfurter")?;
+    }
+    if !assets.is_empty() {
+        ensure_provider(conn, "price_provider", "yahoo")?;
+    }
+    let symbols: Vec<&str> = assets.iter().map(|(_, s)| s.as_str()).collect();
+    let end = today();
+    let start = end - chrono::Days::new(days);
+
+    let rates = async {
+        if targets.is_empty() {
+            return Ok(None);
+        }
+        Frankfurter
+            .rates(&base, &targets, start, end)
+            .await
+            .map(Some)
+    };
+    let quotes = async {
+        if symbols.is_empty() {
+            return Ok(None);
+        }
+        Yahoo.quotes(&symbols).await.map(Some)
+    };
+    let (rates, quotes): (Result<_>, Result<_>) = tokio::join!(rates, quotes);
+
+    if let Some(rates) = rates? {
+        let upserted = fx::store_rates(conn, &base, rates)?;
+        println!(
+            "FX rates fetched via Frankfurter (ECB); {} rows upserted.",
+            upserted
+        );
+    }
+    if let Some(quotes) = quotes? {
+        portfolio::store_quotes(conn, &assets, quotes)?;
+        let fired = price_alerts::check_alerts(conn)?;
+        price_alerts::notify(conn, false, None, &fired)?;
+    }
+    Ok(())
+}
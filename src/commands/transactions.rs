@@ -4,48 +4,130 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
+use crate::commands::categories::{CapBreach, cap_breach};
+use crate::interactive;
 use crate::utils::{
-    apply_import_rules, id_for_account, id_for_category, maybe_print_json, parse_date,
-    parse_decimal, pretty_table,
+    Totals, apply_import_rules, compute_totals, ensure_period_open, get_base_currency, get_setting,
+    id_for_account, id_for_category, maybe_print_json_with_totals, parse_amount, parse_amount_in,
+    parse_date, pretty_table,
 };
-use anyhow::Result;
-use rusqlite::{Connection, params};
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
 use serde::Serialize;
+use std::collections::BTreeMap;
 
 pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
     match m.subcommand() {
         Some(("add", sub)) => add(conn, sub)?,
         Some(("list", sub)) => list(conn, sub)?,
+        Some(("template", sub)) => template_cmd(conn, sub)?,
+        Some(("meta", sub)) => crate::commands::meta::handle(conn, sub)?,
+        Some(("geocode", sub)) => crate::commands::locations::geocode(conn, sub)?,
+        Some(("settle", sub)) => crate::commands::reimbursements::settle_cmd(conn, sub)?,
         _ => {}
     }
     Ok(())
 }
 
 fn add(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
-    let date_raw = sub.get_one::<String>("date").unwrap();
-    let date = parse_date(date_raw.trim())?;
-    let account_name = sub.get_one::<String>("account").unwrap().trim().to_string();
-    let amount_raw = sub.get_one::<String>("amount").unwrap();
-    let amount = parse_decimal(amount_raw.trim())?;
-    let mut payee = sub
-        .get_one::<String>("payee")
-        .map(|s| s.trim().to_string())
-        .unwrap();
-    let category = sub
-        .get_one::<String>("category")
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty());
-    let note = sub
-        .get_one::<String>("note")
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty());
+    let template = match sub.get_one::<String>("template") {
+        Some(name) => Some(load_template(conn, name.trim())?),
+        None => None,
+    };
+    let field = |id: &str| -> Option<String> {
+        sub.get_one::<String>(id)
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .or_else(|| template.as_ref().and_then(|t| t.field(id)))
+    };
+
+    let interactive = interactive::enabled(sub);
+    let mut prompted = false;
+    let missing = |flag: &str| {
+        anyhow!(
+            "Missing --{}; pass it or run in a terminal without --no-input",
+            flag
+        )
+    };
+
+    let date = match sub.get_one::<String>("date") {
+        Some(raw) => parse_date(raw.trim())?,
+        None if template.is_some() => crate::utils::today(),
+        None if interactive => parse_date(&interactive::text("Date", Some("today"), &[], |s| {
+            parse_date(s).map(|_| ())
+        })?)?,
+        None => return Err(missing("date")),
+    };
+    ensure_period_open(conn, date)?;
+    let account_name = match field("account").or(get_setting(conn, "default_account")?) {
+        Some(name) => name,
+        None if interactive => {
+            prompted = true;
+            let names = column_values(conn, "SELECT name FROM accounts ORDER BY name")?;
+            ensure!(
+                !names.is_empty(),
+                "No accounts yet; add one with `account add`"
+            );
+            names[interactive::choose("Account", &names, None)?].clone()
+        }
+        None => {
+            bail!("No --account given; pass --account or run `config set default_account <NAME>`")
+        }
+    };
+    let mut payee = match field("payee") {
+        Some(p) => p,
+        None if interactive => {
+            prompted = true;
+            let payees = column_values(
+                conn,
+                "SELECT payee FROM transactions GROUP BY payee ORDER BY COUNT(*) DESC LIMIT 500",
+            )?;
+            interactive::text("Payee", None, &payees, |s| {
+                ensure!(!s.is_empty(), "Payee is required");
+                Ok(())
+            })?
+        }
+        None => return Err(missing("payee")),
+    };
+    // Checked now, evaluated once the account's currency is known.
+    let raw_amount = match field("amount") {
+        Some(raw) => {
+            parse_amount(&raw)?;
+            raw
+        }
+        None if interactive => {
+            prompted = true;
+            interactive::text("Amount (negative = expense)", None, &[], |s| {
+                parse_amount(s).map(|_| ())
+            })?
+        }
+        None => return Err(missing("amount")),
+    };
+    let category = match field("category") {
+        Some(c) => Some(c),
+        None if prompted => {
+            let mut names = vec!["(none / use rules)".to_string()];
+            names.extend(column_values(
+                conn,
+                "SELECT name FROM categories ORDER BY name",
+            )?);
+            match interactive::choose("Category", &names, Some(0))? {
+                0 => None,
+                i => Some(names[i].clone()),
+            }
+        }
+        None => None,
+    };
+    let note = field("note");
 
     let account_id = id_for_account(conn, &account_name)?;
-    let currency: String = conn.query_row(
-        "SELECT currency FROM accounts WHERE id=?1",
+    let (currency, account_default_category): (String, Option<i64>) = conn.query_row(
+        "SELECT currency, default_category_id FROM accounts WHERE id=?1",
         params![account_id],
-        |r| r.get(0),
+        |r| Ok((r.get(0)?, r.get(1)?)),
     )?;
+    let amount = parse_amount_in(&raw_amount, &currency)?;
     let mut category_id = if let Some(cat) = category.as_deref() {
         Some(id_for_category(conn, cat)?)
     } else {
@@ -54,42 +136,324 @@ fn add(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
 
     let (rule_cat, rewrite) = apply_import_rules(conn, &payee, note.as_deref())?;
     if category_id.is_none() {
-        category_id = rule_cat;
+        category_id = rule_cat.or(account_default_category);
+    }
+    if category_id.is_none() {
+        category_id = get_setting(conn, "default_category")?
+            .map(|name| id_for_category(conn, &name))
+            .transpose()?;
     }
     if let Some(newp) = rewrite.filter(|newp| newp != &payee) {
         println!("Payee rewritten: {} -> {}", payee, newp);
         payee = newp;
     }
 
-    conn.execute(
-        "INSERT INTO transactions(date, account_id, amount, payee, category_id, currency, note)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
-            date.to_string(),
-            account_id,
-            amount.to_string(),
-            &payee,
-            category_id,
-            currency,
-            note
-        ],
+    let tax = crate::commands::sales_tax::tax_for(
+        amount,
+        sub.get_one::<String>("tax").map(String::as_str),
+        sub.get_one::<String>("tax-rate").map(String::as_str),
+        &currency,
     )?;
+    let tax_code = sub.get_one::<String>("tax-code").map(|c| c.trim());
+    let project = field("project");
+    let place = field("location");
+    let coords = sub
+        .get_one::<String>("coords")
+        .map(|c| crate::commands::locations::parse_coords(c))
+        .transpose()?;
+
+    let jar_id = sub
+        .get_one::<String>("jar")
+        .map(|j| crate::commands::jars::jar_id(conn, account_id, j.trim()))
+        .transpose()?;
+
+    conn.execute_batch("SAVEPOINT tx_add")?;
+    let inserted = (|| -> Result<Option<CapBreach>> {
+        conn.execute(
+            "INSERT INTO transactions(date, account_id, amount, payee, category_id, currency, note)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                date.to_string(),
+                account_id,
+                amount.to_string(),
+                &payee,
+                category_id,
+                currency,
+                note
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        if place.is_some() || coords.is_some() {
+            crate::commands::locations::set_location(conn, id, place.as_deref(), coords)?;
+        }
+        if let Some(person) = sub.get_one::<String>("reimbursable") {
+            let person = match person.trim() {
+                "" => crate::commands::reimbursements::UNNAMED,
+                p => p,
+            };
+            conn.execute(
+                "UPDATE transactions SET reimbursable_by=?1 WHERE id=?2",
+                params![person, id],
+            )?;
+        }
+        if let Some(project) = &project {
+            conn.execute(
+                "UPDATE transactions SET project=?1 WHERE id=?2",
+                params![project, id],
+            )?;
+        }
+        if let Some(jar_id) = jar_id {
+            conn.execute(
+                "UPDATE transactions SET jar_id=?1 WHERE id=?2",
+                params![jar_id, id],
+            )?;
+        }
+        if tax.is_some() || tax_code.is_some() {
+            conn.execute(
+                "UPDATE transactions SET tax_amount=?1, tax_code=?2 WHERE id=?3",
+                params![tax.map(|t| t.to_string()), tax_code, id],
+            )?;
+        }
+        match category_id {
+            Some(id) if amount < Decimal::ZERO => {
+                cap_breach(conn, id, &crate::utils::periods().month_of(date))
+            }
+            _ => Ok(None),
+        }
+    })();
+    let breach = match inserted {
+        Ok(Some(breach)) if sub.get_flag("strict") => {
+            conn.execute_batch("ROLLBACK TO tx_add; RELEASE tx_add")?;
+            bail!("Not recorded: {} (--strict)", breach);
+        }
+        Ok(breach) => {
+            conn.execute_batch("RELEASE tx_add")?;
+            breach
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO tx_add; RELEASE tx_add")?;
+            return Err(e);
+        }
+    };
     println!(
-        "Recorded {} on {} at '{}' (acct: {})",
-        amount, date, payee, account_name
+        "{}",
+        crate::i18n::trf(
+            "Recorded {} on {} at '{}' (acct: {})",
+            &[&amount, &date, &payee, &account_name]
+        )
     );
+    if let Some(breach) = breach {
+        eprintln!("{}", crate::i18n::trf("Warning: {}", &[&breach]));
+    }
+    Ok(())
+}
+
+/// The one transaction of exactly `amount` on `date` (optionally in `account`), for
+/// commands that link an existing entry such as a repayment or an invoice payment.
+pub fn find_by_amount(
+    conn: &Connection,
+    date: chrono::NaiveDate,
+    amount: Decimal,
+    account: Option<&str>,
+) -> Result<i64> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.amount FROM transactions t JOIN accounts a ON t.account_id=a.id
+         WHERE t.date=?1 AND (?2 IS NULL OR a.name=?2)",
+    )?;
+    let mut ids = Vec::new();
+    let mut rows = stmt.query(params![date.to_string(), account])?;
+    while let Some(r) = rows.next()? {
+        if r.get::<_, String>(1)?.trim().parse::<Decimal>().ok() == Some(amount) {
+            ids.push(r.get::<_, i64>(0)?);
+        }
+    }
+    match ids.as_slice() {
+        [id] => Ok(*id),
+        [] => bail!(
+            "No transaction of {} on {}; record it with `tx add` first",
+            amount,
+            date
+        ),
+        _ => bail!(
+            "{} transactions of {} on {}; pass --account to pick one",
+            ids.len(),
+            amount,
+            date
+        ),
+    }
+}
+
+fn column_values(conn: &Connection, sql: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let values = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(values)
+}
+
+#[derive(Debug, Serialize)]
+pub struct TxTemplate {
+    pub name: String,
+    pub account: Option<String>,
+    pub amount: Option<String>,
+    pub payee: Option<String>,
+    pub category: Option<String>,
+    pub note: Option<String>,
+}
+
+impl TxTemplate {
+    fn field(&self, id: &str) -> Option<String> {
+        match id {
+            "account" => self.account.clone(),
+            "amount" => self.amount.clone(),
+            // A template without an explicit payee books under its own name.
+            "payee" => self.payee.clone().or_else(|| Some(self.name.clone())),
+            "category" => self.category.clone(),
+            "note" => self.note.clone(),
+            _ => None,
+        }
+    }
+}
+
+fn template_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<TxTemplate> {
+    Ok(TxTemplate {
+        name: r.get(0)?,
+        account: r.get(1)?,
+        amount: r.get(2)?,
+        payee: r.get(3)?,
+        category: r.get(4)?,
+        note: r.get(5)?,
+    })
+}
+
+fn load_template(conn: &Connection, name: &str) -> Result<TxTemplate> {
+    conn.query_row(
+        "SELECT name, account, amount, payee, category, note FROM tx_templates WHERE name=?1",
+        params![name],
+        template_row,
+    )
+    .optional()?
+    .with_context(|| format!("Template '{}' not found", name))
+}
+
+fn template_cmd(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("save", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim().to_string();
+            let mut template = TxTemplate {
+                name,
+                account: None,
+                amount: None,
+                payee: None,
+                category: None,
+                note: None,
+            };
+            apply_template_flags(&mut template, sub);
+            store_template(conn, &template, true)?;
+            println!("Saved template '{}'", template.name);
+        }
+        Some(("edit", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let mut template = load_template(conn, name)?;
+            apply_template_flags(&mut template, sub);
+            store_template(conn, &template, false)?;
+            println!("Updated template '{}'", template.name);
+        }
+        Some(("list", _)) => {
+            let mut stmt = conn.prepare(
+                "SELECT name, account, amount, payee, category, note FROM tx_templates ORDER BY name",
+            )?;
+            let rows = stmt
+                .query_map([], template_row)?
+                .map(|t| {
+                    t.map(|t| {
+                        vec![
+                            t.name,
+                            t.account.unwrap_or_default(),
+                            t.amount.unwrap_or_default(),
+                            t.payee.unwrap_or_default(),
+                            t.category.unwrap_or_default(),
+                            t.note.unwrap_or_default(),
+                        ]
+                    })
+                })
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            crate::utils::emit(pretty_table(
+                &["Name", "Account", "Amount", "Payee", "Category", "Note"],
+                rows,
+            ));
+        }
+        Some(("rm", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let n = conn.execute("DELETE FROM tx_templates WHERE name=?1", params![name])?;
+            if n == 0 {
+                bail!("Template '{}' not found", name);
+            }
+            println!("Removed template '{}'", name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn apply_template_flags(template: &mut TxTemplate, sub: &clap::ArgMatches) {
+    let flag = |id: &str| {
+        sub.get_one::<String>(id)
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    template.account = flag("account").or(template.account.take());
+    template.amount = flag("amount").or(template.amount.take());
+    template.payee = flag("payee").or(template.payee.take());
+    template.category = flag("category").or(template.category.take());
+    template.note = flag("note").or(template.note.take());
+}
+
+fn store_template(conn: &Connection, t: &TxTemplate, replace: bool) -> Result<()> {
+    if let Some(account) = &t.account {
+        id_for_account(conn, account)?;
+    }
+    if let Some(category) = &t.category {
+        id_for_category(conn, category)?;
+    }
+    if let Some(amount) = &t.amount {
+        parse_amount(amount)?;
+    }
+    let sql = if replace {
+        "INSERT INTO tx_templates(name, account, amount, payee, category, note)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(name) DO UPDATE SET account=excluded.account, amount=excluded.amount,
+             payee=excluded.payee, category=excluded.category, note=excluded.note"
+    } else {
+        "UPDATE tx_templates SET account=?2, amount=?3, payee=?4, category=?5, note=?6
+         WHERE name=?1"
+    };
+    conn.execute(
+        sql,
+        params![t.name, t.account, t.amount, t.payee, t.category, t.note],
+    )?;
     Ok(())
 }
 
 fn list(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
     let json_flag = sub.get_flag("json");
     let jsonl_flag = sub.get_flag("jsonl");
+    if sub.get_flag("count-only") {
+        crate::utils::emit(count_rows(conn, sub)?);
+        return Ok(());
+    }
     let data = query_rows(conn, sub)?;
-    if !maybe_print_json(json_flag, jsonl_flag, &data)? {
-        let rows: Vec<Vec<String>> = data
+    let totals = if sub.get_flag("totals") {
+        Some(list_totals(conn, sub)?)
+    } else {
+        None
+    };
+    if !maybe_print_json_with_totals(json_flag, jsonl_flag, &data, totals.as_ref())? {
+        let mut rows: Vec<Vec<String>> = data
             .iter()
             .map(|r| {
                 vec![
+                    r.id.to_string(),
                     r.date.clone(),
                     r.account.clone(),
                     r.payee.clone(),
@@ -100,21 +464,89 @@ fn list(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
                 ]
             })
             .collect();
-        println!(
-            "{}",
-            pretty_table(
-                &[
-                    "Date", "Account", "Payee", "Amount", "CCY", "Category", "Note"
-                ],
-                rows,
-            )
-        );
+        if let Some(totals) = &totals {
+            for (i, (amount, ccy)) in totals.lines().into_iter().enumerate() {
+                let label = if i == 0 {
+                    format!("{} transactions", totals.count)
+                } else {
+                    String::new()
+                };
+                rows.push(vec![
+                    "Total".into(),
+                    String::new(),
+                    String::new(),
+                    label,
+                    amount,
+                    ccy,
+                    String::new(),
+                    String::new(),
+                ]);
+            }
+        }
+        crate::utils::emit(pretty_table(
+            &[
+                "ID", "Date", "Account", "Payee", "Amount", "CCY", "Category", "Note",
+            ],
+            rows,
+        ));
+        if totals.is_none() {
+            crate::utils::emit(list_footer(&data)?);
+        }
     }
     Ok(())
 }
 
+/// `--totals` for `tx list`: every matching transaction (ignoring `--limit` and
+/// `--offset`), with the converted sum in the base currency at each date's rate.
+pub fn list_totals(conn: &Connection, sub: &clap::ArgMatches) -> Result<Totals> {
+    let (filter, params_vec) = list_filter(sub)?;
+    let sql = format!("SELECT t.date, t.amount, t.currency{}", filter);
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(&params_vec))?;
+    let mut items = Vec::new();
+    while let Some(r) = rows.next()? {
+        let date: String = r.get(0)?;
+        let amount: String = r.get(1)?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        items.push((parse_date(&date)?, amount, r.get::<_, String>(2)?));
+    }
+    let refs: Vec<_> = items.iter().map(|(d, a, c)| (*d, *a, c.as_str())).collect();
+    compute_totals(conn, &refs, &get_base_currency(conn)?)
+}
+
+/// `N transactions, total -12.50 USD` with one total per currency shown.
+pub fn list_footer(rows: &[TransactionRow]) -> Result<String> {
+    let mut totals: BTreeMap<&str, Decimal> = BTreeMap::new();
+    for r in rows {
+        let amount = r
+            .amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", r.amount, r.date))?;
+        *totals.entry(r.currency.as_str()).or_default() += amount;
+    }
+    let sums: Vec<String> = totals
+        .iter()
+        .map(|(ccy, sum)| format!("{} {}", crate::utils::fmt_amount(*sum, ccy), ccy))
+        .collect();
+    let noun = if rows.len() == 1 {
+        "transaction"
+    } else {
+        "transactions"
+    };
+    Ok(match sums.len() {
+        0 => format!("0 {}", noun),
+        1 => format!("{} {}, total {}", rows.len(), noun, sums[0]),
+        _ => format!("{} {}, totals {}", rows.len(), noun, sums.join(", ")),
+    })
+}
+
 #[derive(Serialize)]
 pub struct TransactionRow {
+    pub id: i64,
     pub date: String,
     pub account: String,
     pub payee: String,
@@ -124,9 +556,11 @@ pub struct TransactionRow {
     pub note: String,
 }
 
-pub fn query_rows(conn: &Connection, sub: &clap::ArgMatches) -> Result<Vec<TransactionRow>> {
+/// `FROM ... WHERE ...` for `tx list` and its bound parameters, shared by
+/// [`query_rows`] and [`count_rows`].
+fn list_filter(sub: &clap::ArgMatches) -> Result<(String, Vec<String>)> {
     let mut sql = String::from(
-        "SELECT t.date, a.name, t.payee, t.amount, t.currency, c.name, t.note FROM transactions t LEFT JOIN accounts a ON t.account_id=a.id LEFT JOIN categories c ON t.category_id=c.id WHERE 1=1",
+        " FROM transactions t LEFT JOIN accounts a ON t.account_id=a.id LEFT JOIN categories c ON t.category_id=c.id WHERE 1=1",
     );
     let mut params_vec: Vec<String> = Vec::new();
 
@@ -134,7 +568,7 @@ pub fn query_rows(conn: &Connection, sub: &clap::ArgMatches) -> Result<Vec<Trans
         let month = month_raw.trim();
         if !month.is_empty() {
             sql.push_str(" AND substr(t.date,1,7)=?");
-            params_vec.push(month.to_string());
+            params_vec.push(crate::utils::parse_month(month)?);
         }
     }
     if let Some(acct_raw) = sub.get_one::<String>("account") {
@@ -151,10 +585,72 @@ pub fn query_rows(conn: &Connection, sub: &clap::ArgMatches) -> Result<Vec<Trans
             params_vec.push(cat.to_string());
         }
     }
-    sql.push_str(" ORDER BY t.date DESC, t.id DESC");
+    if let Some(project) = sub.get_one::<String>("project") {
+        sql.push_str(" AND t.project=? COLLATE NOCASE");
+        params_vec.push(project.trim().to_string());
+    }
+    if let Some(jar) = sub.get_one::<String>("jar") {
+        sql.push_str(" AND t.jar_id IN (SELECT id FROM jars WHERE name=? COLLATE NOCASE)");
+        params_vec.push(jar.trim().to_string());
+    }
+    for raw in sub.get_many::<String>("meta").into_iter().flatten() {
+        let (cond, values) = crate::commands::meta::meta_filter(raw);
+        sql.push_str(&cond);
+        params_vec.extend(values);
+    }
+    if sub.get_flag("uncategorized") {
+        sql.push_str(" AND t.category_id IS NULL");
+    }
+    if sub.get_flag("no-note") {
+        sql.push_str(" AND TRIM(IFNULL(t.note,''))=''");
+    }
+    if let Some(ccy) = sub.get_one::<String>("currency") {
+        sql.push_str(" AND t.currency=?");
+        params_vec.push(ccy.trim().to_uppercase());
+    }
+    let (from, to) = crate::cli::date_range(sub)?;
+    if let Some(from) = from {
+        sql.push_str(" AND t.date>=?");
+        params_vec.push(from.to_string());
+    }
+    if let Some(to) = to {
+        sql.push_str(" AND t.date<=?");
+        params_vec.push(to.to_string());
+    }
+    Ok((sql, params_vec))
+}
+
+/// Number of transactions `tx list` would show, ignoring `--limit`/`--offset`.
+pub fn count_rows(conn: &Connection, sub: &clap::ArgMatches) -> Result<usize> {
+    let (filter, params_vec) = list_filter(sub)?;
+    let sql = format!("SELECT COUNT(*){}", filter);
+    let count: i64 = conn.query_row(&sql, rusqlite::params_from_iter(&params_vec), |r| r.get(0))?;
+    Ok(count as usize)
+}
+
+pub fn query_rows(conn: &Connection, sub: &clap::ArgMatches) -> Result<Vec<TransactionRow>> {
+    let (filter, mut params_vec) = list_filter(sub)?;
+    let mut sql = format!(
+        "SELECT t.date, a.name, t.payee, t.amount, t.currency, c.name, t.note, t.id{}",
+        filter
+    );
+    let key = match sub.get_one::<String>("sort").map(String::as_str) {
+        Some("amount") => "CAST(t.amount AS REAL)",
+        Some("payee") => "t.payee COLLATE NOCASE",
+        _ => "t.date",
+    };
+    let dir = if sub.get_flag("asc") { "ASC" } else { "DESC" };
+    sql.push_str(&format!(" ORDER BY {} {}, t.id {}", key, dir, dir));
+    let offset = sub.get_one::<usize>("offset").copied().unwrap_or(0);
     if let Some(limit) = sub.get_one::<usize>("limit") {
         sql.push_str(" LIMIT ?");
         params_vec.push(limit.to_string());
+    } else if offset > 0 {
+        sql.push_str(" LIMIT -1");
+    }
+    if offset > 0 {
+        sql.push_str(" OFFSET ?");
+        params_vec.push(offset.to_string());
     }
 
     let mut stmt = conn.prepare(&sql)?;
@@ -178,6 +674,7 @@ pub fn query_rows(conn: &Connection, sub: &clap::ArgMatches) -> Result<Vec<Trans
         let category: Option<String> = r.get(5)?;
         let note: Option<String> = r.get(6)?;
         data.push(TransactionRow {
+            id: r.get(7)?,
             date,
             account,
             payee,
@@ -0,0 +1,215 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Trips (`trip`): a named date range, optionally with a project tag for bookings
+//! made before or after it, and `report trip` for what it cost.
+
+use crate::models::TripRow;
+use crate::utils::{
+    fmt_amount, fmt_money, fx_convert_batch, get_base_currency, maybe_print_json, parse_date,
+    pretty_table,
+};
+use anyhow::{Context, Result, bail, ensure};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            let from = parse_date(sub.get_one::<String>("from").unwrap().trim())?;
+            let to = parse_date(sub.get_one::<String>("to").unwrap().trim())?;
+            ensure!(from <= to, "Trip ends ({}) before it starts ({})", to, from);
+            let tag = sub.get_one::<String>("tag").map(|t| t.trim());
+            conn.execute(
+                "INSERT INTO trips(name, start_date, end_date, tag) VALUES (?1, ?2, ?3, ?4)",
+                params![name, from.to_string(), to.to_string(), tag],
+            )
+            .with_context(|| format!("Add trip '{}'", name))?;
+            println!(
+                "Added trip '{}' from {} to {} ({} days)",
+                name,
+                from,
+                to,
+                (to - from).num_days() + 1
+            );
+        }
+        Some(("list", sub)) => {
+            let mut stmt = conn.prepare(
+                "SELECT name, start_date, end_date, tag FROM trips
+                 ORDER BY start_date DESC",
+            )?;
+            let data = stmt
+                .query_map([], |r| {
+                    Ok(TripRow {
+                        trip: r.get(0)?,
+                        from: r.get(1)?,
+                        to: r.get(2)?,
+                        tag: r.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            if !maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                let cells = data.into_iter().map(TripRow::cells).collect();
+                println!("{}", pretty_table(&["Trip", "From", "To", "Tag"], cells));
+            }
+        }
+        Some(("rm", sub)) => {
+            let name = sub.get_one::<String>("name").unwrap().trim();
+            if conn.execute("DELETE FROM trips WHERE name=?1", params![name])? == 0 {
+                bail!("No trip named '{}'", name);
+            }
+            println!("Removed trip '{}'", name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Spending of one category on a trip.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct TripLine {
+    pub category: String,
+    pub original: BTreeMap<String, Decimal>,
+    pub base: Decimal,
+}
+
+/// What a trip cost: per category, in original currencies and the base currency.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct TripCost {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub days: i64,
+    pub tag: Option<String>,
+    pub transactions: usize,
+    pub categories: Vec<TripLine>,
+    pub original: BTreeMap<String, Decimal>,
+    pub total: Decimal,
+    pub per_day: Decimal,
+}
+
+pub fn trip_report(conn: &Connection, sub: &clap::ArgMatches) -> Result<()> {
+    let name = sub.get_one::<String>("name").unwrap().trim();
+    let cost = trip_cost(conn, name)?;
+    if maybe_print_json(sub.get_flag("json"), false, std::slice::from_ref(&cost))? {
+        return Ok(());
+    }
+    let base = get_base_currency(conn)?;
+    let originals = |m: &BTreeMap<String, Decimal>| {
+        m.iter()
+            .map(|(ccy, amount)| fmt_money(amount, ccy))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut rows: Vec<Vec<String>> = cost
+        .categories
+        .iter()
+        .map(|l| {
+            vec![
+                l.category.clone(),
+                originals(&l.original),
+                fmt_amount(l.base, &base),
+            ]
+        })
+        .collect();
+    rows.push(vec![
+        "Total".to_string(),
+        originals(&cost.original),
+        fmt_amount(cost.total, &base),
+    ]);
+    rows.push(vec![
+        format!("Per day ({} days)", cost.days),
+        String::new(),
+        fmt_amount(cost.per_day, &base),
+    ]);
+    let title = format!("{} ({} - {})", cost.name, cost.from, cost.to);
+    crate::utils::emit(pretty_table(
+        &[&title, "Spent", &format!("Spent ({})", base)],
+        rows,
+    ));
+    Ok(())
+}
+
+/// Outflows dated within the trip, plus those tagged with its project tag at any date
+/// (flights and bookings paid in advance), each converted to the base currency at its
+/// own date. Categories are ordered by base-currency total, largest first.
+pub fn trip_cost(conn: &Connection, name: &str) -> Result<TripCost> {
+    let (from, to, tag): (String, String, Option<String>) = conn
+        .query_row(
+            "SELECT start_date, end_date, tag FROM trips WHERE name=?1",
+            params![name],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .optional()?
+        .with_context(|| format!("No trip named '{}'", name))?;
+    let base = get_base_currency(conn)?;
+    let sql = if tag.is_some() {
+        "SELECT COALESCE(c.name, '(uncategorized)'), t.date, t.amount, t.currency
+         FROM transactions t LEFT JOIN categories c ON t.category_id=c.id
+         WHERE t.amount < 0 AND (t.date BETWEEN ?1 AND ?2 OR t.project=?3 COLLATE NOCASE)"
+    } else {
+        // Untagged trips must not need the project column.
+        "SELECT COALESCE(c.name, '(uncategorized)'), t.date, t.amount, t.currency
+         FROM transactions t LEFT JOIN categories c ON t.category_id=c.id
+         WHERE t.amount < 0 AND t.date BETWEEN ?1 AND ?2 AND ?3 IS NULL"
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt
+        .query_map(params![from, to, tag], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut items = Vec::with_capacity(rows.len());
+    for (_, date, amount, currency) in &rows {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        let amount = amount
+            .trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid amount '{}' on {}", amount, date))?;
+        items.push((date, -amount, currency.as_str()));
+    }
+    let converted = fx_convert_batch(conn, &items, &base)?;
+    let mut by_category: BTreeMap<&str, TripLine> = BTreeMap::new();
+    let mut original: BTreeMap<String, Decimal> = BTreeMap::new();
+    let mut total = Decimal::ZERO;
+    for (((category, ..), (_, amount, currency)), in_base) in rows.iter().zip(&items).zip(converted)
+    {
+        let line = by_category
+            .entry(category.as_str())
+            .or_insert_with(|| TripLine {
+                category: category.clone(),
+                original: BTreeMap::new(),
+                base: Decimal::ZERO,
+            });
+        *line.original.entry(currency.to_string()).or_default() += *amount;
+        line.base += in_base;
+        *original.entry(currency.to_string()).or_default() += *amount;
+        total += in_base;
+    }
+    let mut categories: Vec<TripLine> = by_category.into_values().collect();
+    categories.sort_by_key(|l| std::cmp::Reverse(l.base));
+    let days = (parse_date(&to)? - parse_date(&from)?).num_days() + 1;
+    Ok(TripCost {
+        name: name.to_string(),
+        from,
+        to,
+        days,
+        tag,
+        transactions: rows.len(),
+        categories,
+        original,
+        total,
+        per_day: (total / Decimal::from(days)).round_dp(crate::utils::money_dp(&base)),
+    })
+}
@@ -0,0 +1,199 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Watchlist (`portfolio watch`): tickers followed without holding them. They are
+//! assets without trades, so `price fetch` quotes them while valuation and tax,
+//! which only count positions, leave them out.
+
+use crate::utils::{fmt_amount, fmt_percent, maybe_print_json, pretty_table};
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OptionalExtension, params};
+use rust_decimal::Decimal;
+
+pub fn handle(conn: &Connection, m: &clap::ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        Some(("add", sub)) => {
+            let ticker = sub.get_one::<String>("ticker").unwrap().trim();
+            let existing: Option<i64> = conn
+                .query_row(
+                    "SELECT id FROM assets WHERE ticker=?1 COLLATE NOCASE",
+                    params![ticker],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            match existing {
+                Some(id) => {
+                    conn.execute("UPDATE assets SET watched=1 WHERE id=?1", params![id])?;
+                }
+                None => {
+                    let Some(currency) = sub.get_one::<String>("currency") else {
+                        bail!("{} is not an asset yet; pass --currency", ticker);
+                    };
+                    let name = sub
+                        .get_one::<String>("name")
+                        .map(|n| n.trim())
+                        .unwrap_or(ticker);
+                    conn.execute(
+                        "INSERT INTO assets(ticker, name, currency, watched) VALUES (?1, ?2, ?3, 1)",
+                        params![ticker, name, currency.trim().to_uppercase()],
+                    )?;
+                }
+            }
+            println!(
+                "Watching {}; prices come with `portfolio price fetch`",
+                ticker
+            );
+        }
+        Some(("remove", sub)) => {
+            let ticker = sub.get_one::<String>("ticker").unwrap().trim();
+            if conn.execute(
+                "UPDATE assets SET watched=0 WHERE ticker=?1 COLLATE NOCASE AND watched=1",
+                params![ticker],
+            )? == 0
+            {
+                bail!("{} is not on the watchlist", ticker);
+            }
+            println!("Stopped watching {}", ticker);
+        }
+        Some(("list", sub)) => {
+            let data = watchlist(conn)?;
+            if maybe_print_json(sub.get_flag("json"), sub.get_flag("jsonl"), &data)? {
+                return Ok(());
+            }
+            if data.is_empty() {
+                crate::utils::emit("Nothing watched; add a ticker with `portfolio watch add`");
+                return Ok(());
+            }
+            let show =
+                |d: Option<Decimal>, ccy: &str| d.map_or(String::new(), |d| fmt_amount(d, ccy));
+            let rows = data
+                .iter()
+                .map(|w| {
+                    vec![
+                        w.ticker.clone(),
+                        w.name.clone(),
+                        w.currency.clone(),
+                        show(w.price, &w.currency),
+                        w.as_of.clone().unwrap_or_else(|| "no price".to_string()),
+                        show(w.change, &w.currency),
+                        w.change_percent.map_or(String::new(), fmt_percent),
+                    ]
+                })
+                .collect();
+            crate::utils::emit(pretty_table(
+                &[
+                    "Ticker", "Name", "CCY", "Price", "As of", "Change", "Change %",
+                ],
+                rows,
+            ));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A watched ticker's latest cached price and its change from the previous day's close.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Watched {
+    pub ticker: String,
+    pub name: String,
+    pub currency: String,
+    pub price: Option<Decimal>,
+    pub as_of: Option<String>,
+    pub change: Option<Decimal>,
+    pub change_percent: Option<Decimal>,
+}
+
+/// Watched tickers by ticker, each with its latest price and the change since the last
+/// price of an earlier day.
+pub fn watchlist(conn: &Connection) -> Result<Vec<Watched>> {
+    let mut stmt = conn
+        .prepare("SELECT id, ticker, name, currency FROM assets WHERE watched=1 ORDER BY ticker")?;
+    let assets = stmt
+        .query_map([], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut latest = conn
+        .prepare("SELECT price, as_of FROM prices WHERE asset_id=?1 ORDER BY as_of DESC LIMIT 1")?;
+    let mut previous = conn.prepare(
+        "SELECT price FROM prices WHERE asset_id=?1 AND substr(as_of, 1, 10) < ?2
+         ORDER BY as_of DESC LIMIT 1",
+    )?;
+    let parse = |raw: String| {
+        raw.trim()
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid price '{}'", raw))
+    };
+    let mut out = Vec::with_capacity(assets.len());
+    for (id, ticker, name, currency) in assets {
+        let last: Option<(String, String)> = latest
+            .query_row(params![id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .optional()?;
+        let (price, as_of, change, change_percent) = match last {
+            Some((price, as_of)) => {
+                let price = parse(price)?;
+                let before: Option<String> = previous
+                    .query_row(params![id, &as_of[..10.min(as_of.len())]], |r| r.get(0))
+                    .optional()?;
+                let before = before.map(parse).transpose()?;
+                let change = before.map(|b| price - b);
+                let percent = before
+                    .filter(|b| !b.is_zero())
+                    .map(|b| (price - b) / b * Decimal::ONE_HUNDRED);
+                (Some(price), Some(as_of), change, percent)
+            }
+            None => (None, None, None, None),
+        };
+        out.push(Watched {
+            ticker,
+            name,
+            currency,
+            price,
+            as_of,
+            change,
+            change_percent,
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn watchlist_shows_change_since_previous_day() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT, name TEXT, currency TEXT, watched INTEGER NOT NULL DEFAULT 0);
+            CREATE TABLE prices(id INTEGER PRIMARY KEY, asset_id INTEGER, as_of TEXT, price TEXT);
+            INSERT INTO assets(id, ticker, name, currency, watched) VALUES
+                (1, 'NVDA', 'Nvidia', 'USD', 1), (2, 'AAPL', 'Apple', 'USD', 0), (3, 'ASML', 'ASML', 'EUR', 1);
+            INSERT INTO prices(asset_id, as_of, price) VALUES
+                (1, '2025-06-02T20:00:00', '100'), (1, '2025-06-03T14:00:00', '104'),
+                (1, '2025-06-03T20:00:00', '105'), (2, '2025-06-03T20:00:00', '200');
+            "#,
+        )
+        .unwrap();
+
+        let list = watchlist(&conn).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].ticker, "ASML");
+        assert_eq!(list[0].price, None);
+        let nvda = &list[1];
+        assert_eq!(nvda.price, Some(Decimal::from(105)));
+        assert_eq!(nvda.change, Some(Decimal::from(5)));
+        assert_eq!(nvda.change_percent, Some(Decimal::from_str("5").unwrap()));
+    }
+}
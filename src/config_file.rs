@@ -0,0 +1,248 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! `config.toml` in the platform config dir, for settings kept in dotfiles. Top-level
+//! keys are the settings of `config list` and apply where the database has no value
+//! (`config set` wins). `[defaults]` adds flags to a command unless they are given on
+//! the command line, and `[hooks]` runs a shell command after a command succeeds:
+//!
+//! ```toml
+//! language = "de"
+//! fx_provider = "off"
+//!
+//! [defaults]
+//! "report cashflow" = "--months 6"
+//! tx = "--no-input"
+//!
+//! [hooks]
+//! "import transactions" = "moneyclip rules lint"
+//! ```
+//!
+//! A key in `[defaults]` or `[hooks]` names a command or a group of commands; the
+//! longest one matching the command wins.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigFile {
+    pub settings: BTreeMap<String, String>,
+    /// Extra flags per command path, as one shell-quoted string.
+    pub defaults: BTreeMap<String, String>,
+    /// Shell command per command path, run after it succeeds.
+    pub hooks: BTreeMap<String, String>,
+}
+
+/// Settings of the loaded file, consulted by [`crate::utils::get_setting`].
+static FILE_SETTINGS: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// A setting from the installed `config.toml`, if it has one.
+pub fn setting(key: &str) -> Option<String> {
+    FILE_SETTINGS.read().unwrap().get(key).cloned()
+}
+
+pub fn path() -> Result<PathBuf> {
+    Ok(crate::db::config_dir()?.join("config.toml"))
+}
+
+impl ConfigFile {
+    /// Read `config.toml`; a missing file is an empty one.
+    pub fn load() -> Result<Self> {
+        let path = path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("Read {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Open {}", path.display())),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(text)?;
+        let mut file = ConfigFile::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("defaults", toml::Value::Table(t)) => file.defaults = strings(&key, t)?,
+                ("hooks", toml::Value::Table(t)) => file.hooks = strings(&key, t)?,
+                (_, toml::Value::Table(_)) => bail!("Unknown section [{}]", key),
+                (_, value) => {
+                    let key = crate::commands::config::known_key(&key)?;
+                    file.settings.insert(key.to_string(), scalar(key, value)?);
+                }
+            }
+        }
+        for (command, flags) in &file.defaults {
+            shell_words::split(flags)
+                .with_context(|| format!("Invalid default flags for '{}'", command))?;
+        }
+        Ok(file)
+    }
+
+    /// Make the file's settings visible to [`crate::utils::get_setting`].
+    pub fn install(&self) {
+        *FILE_SETTINGS.write().unwrap() = self.settings.clone();
+    }
+
+    /// `args` with the `[defaults]` of their command appended, leaving out flags the
+    /// command line already has. Arguments that do not parse are returned unchanged
+    /// for clap to report.
+    pub fn with_defaults(&self, args: Vec<OsString>) -> Result<Vec<OsString>> {
+        if self.defaults.is_empty() {
+            return Ok(args);
+        }
+        let Ok(matches) = crate::cli::build_cli().try_get_matches_from(&args) else {
+            return Ok(args);
+        };
+        let (path, _) = crate::cli::command_path(&matches);
+        let mut given: Vec<String> = args
+            .iter()
+            .skip(1)
+            .filter_map(|a| a.to_str())
+            .filter(|a| a.starts_with('-'))
+            .map(|a| flag_name(a).to_string())
+            .collect();
+        let mut out = args;
+        for (_, flags) in matching(&self.defaults, &path) {
+            for group in flag_groups(shell_words::split(flags)?) {
+                let name = flag_name(&group[0]).to_string();
+                if given.contains(&name) {
+                    continue;
+                }
+                out.extend(group.into_iter().map(OsString::from));
+                given.push(name);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Run the `[hooks]` command for `path` through the shell, with `MONEYCLIP_COMMAND`
+    /// set to it. The command has already succeeded, so a failing hook only warns.
+    pub fn run_hook(&self, path: &str) -> Result<()> {
+        let Some((_, hook)) = matching(&self.hooks, path).next() else {
+            return Ok(());
+        };
+        let mut shell = if cfg!(windows) {
+            let mut c = std::process::Command::new("cmd");
+            c.arg("/C");
+            c
+        } else {
+            let mut c = std::process::Command::new("sh");
+            c.arg("-c");
+            c
+        };
+        let status = shell
+            .arg(hook)
+            .env("MONEYCLIP_COMMAND", path)
+            .status()
+            .with_context(|| format!("Run hook '{}'", hook))?;
+        if !status.success() {
+            eprintln!("Warning: hook '{}' failed ({})", hook, status);
+        }
+        Ok(())
+    }
+}
+
+/// Entries whose key is `path` or a group containing it, most specific first.
+fn matching<'a>(
+    entries: &'a BTreeMap<String, String>,
+    path: &str,
+) -> impl Iterator<Item = (&'a String, &'a String)> {
+    let mut found: Vec<_> = entries
+        .iter()
+        .filter(|(key, _)| {
+            let key = key.trim();
+            path == key || path.starts_with(&format!("{} ", key))
+        })
+        .collect();
+    found.sort_by_key(|(key, _)| std::cmp::Reverse(key.trim().len()));
+    found.into_iter()
+}
+
+/// `--months=6` and `--months` both name `--months`.
+fn flag_name(arg: &str) -> &str {
+    arg.split_once('=').map_or(arg, |(name, _)| name)
+}
+
+/// Split flags into groups of a flag and the values after it.
+fn flag_groups(words: Vec<String>) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for word in words {
+        match groups.last_mut() {
+            Some(group) if !word.starts_with('-') => group.push(word),
+            _ => groups.push(vec![word]),
+        }
+    }
+    groups
+}
+
+fn strings(section: &str, table: toml::Table) -> Result<BTreeMap<String, String>> {
+    table
+        .into_iter()
+        .map(|(key, value)| match value {
+            toml::Value::String(s) => Ok((key, s)),
+            _ => Err(anyhow!("[{}] '{}' must be a string", section, key)),
+        })
+        .collect()
+}
+
+fn scalar(key: &str, value: toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(s) => s,
+        toml::Value::Integer(n) => n.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        _ => bail!("Setting '{}' must be a string, number or boolean", key),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_skip_flags_given_and_prefer_specific_commands() {
+        let file = ConfigFile::parse(
+            r#"
+            language = "de"
+            money_dp = 0
+
+            [defaults]
+            report = "--markdown"
+            "report cashflow" = "--months 6 --by week"
+
+            [hooks]
+            import = "true"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.settings["language"], "de");
+        assert_eq!(file.settings["money_dp"], "0");
+
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            file.with_defaults(args(&["moneyclip", "report", "cashflow", "--months=3"]))
+                .unwrap(),
+            args(&[
+                "moneyclip",
+                "report",
+                "cashflow",
+                "--months=3",
+                "--by",
+                "week",
+                "--markdown"
+            ])
+        );
+        assert_eq!(
+            file.with_defaults(args(&["moneyclip", "report", "balances"]))
+                .unwrap(),
+            args(&["moneyclip", "report", "balances", "--markdown"])
+        );
+
+        assert!(ConfigFile::parse("colour = \"blue\"").is_err());
+        assert!(ConfigFile::parse("[aliases]\nx = \"y\"").is_err());
+    }
+}
@@ -7,19 +7,33 @@
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use once_cell::sync::Lazy;
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
 use std::fs;
 use std::path::PathBuf;
 
 static APP: Lazy<(&str, &str, &str)> =
     Lazy::new(|| ("com.alphavelocity", "Moneyclip", "moneyclip"));
 
-pub fn db_path() -> Result<PathBuf> {
+/// The platform data dir holding the database (and log files), created if missing.
+pub fn data_dir() -> Result<PathBuf> {
     let proj = ProjectDirs::from(APP.0, APP.1, APP.2)
         .context("Could not determine platform-specific data dir")?;
     let data_dir = proj.data_dir();
     fs::create_dir_all(data_dir).context("Failed to create data dir")?;
-    Ok(data_dir.join("moneyclip.sqlite"))
+    Ok(data_dir.to_path_buf())
+}
+
+/// The platform config dir holding `config.toml`, created if missing.
+pub fn config_dir() -> Result<PathBuf> {
+    let proj = ProjectDirs::from(APP.0, APP.1, APP.2)
+        .context("Could not determine platform-specific config dir")?;
+    let config_dir = proj.config_dir();
+    fs::create_dir_all(config_dir).context("Failed to create config dir")?;
+    Ok(config_dir.to_path_buf())
+}
+
+pub fn db_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("moneyclip.sqlite"))
 }
 
 pub fn open_or_init() -> Result<Connection> {
@@ -30,6 +44,21 @@ pub fn open_or_init() -> Result<Connection> {
     Ok(conn)
 }
 
+/// Open the existing database without write access; the schema is not touched.
+pub fn open_read_only() -> Result<Connection> {
+    let path = db_path()?;
+    anyhow::ensure!(
+        path.exists(),
+        "No database at {}; run `moneyclip init` first",
+        path.display()
+    );
+    Connection::open_with_flags(
+        &path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .with_context(|| format!("Open DB read-only at {}", path.display()))
+}
+
 fn init_schema(conn: &mut Connection) -> Result<()> {
     conn.execute_batch(
         r#"
@@ -68,15 +97,87 @@ fn init_schema(conn: &mut Connection) -> Result<()> {
     );
     CREATE INDEX IF NOT EXISTS idx_transactions_date ON transactions(date);
 
+    -- Saved quick-entry presets for `tx add --template`; names are resolved at use
+    CREATE TABLE IF NOT EXISTS tx_templates(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        account TEXT,
+        amount TEXT,
+        payee TEXT,
+        category TEXT,
+        note TEXT
+    );
+
+    -- Months finalized with `period close`; transactions dated inside are frozen
+    CREATE TABLE IF NOT EXISTS closed_periods(
+        month TEXT PRIMARY KEY, -- YYYY-MM
+        closed_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE TRIGGER IF NOT EXISTS transactions_closed_insert
+    BEFORE INSERT ON transactions
+    WHEN EXISTS(SELECT 1 FROM closed_periods WHERE month = substr(NEW.date, 1, 7))
+    BEGIN SELECT RAISE(ABORT, 'transaction falls in a closed period'); END;
+    CREATE TRIGGER IF NOT EXISTS transactions_closed_update
+    BEFORE UPDATE ON transactions
+    WHEN EXISTS(SELECT 1 FROM closed_periods
+                WHERE month IN (substr(OLD.date, 1, 7), substr(NEW.date, 1, 7)))
+    BEGIN SELECT RAISE(ABORT, 'transaction falls in a closed period'); END;
+    CREATE TRIGGER IF NOT EXISTS transactions_closed_delete
+    BEFORE DELETE ON transactions
+    WHEN EXISTS(SELECT 1 FROM closed_periods WHERE month = substr(OLD.date, 1, 7))
+    BEGIN SELECT RAISE(ABORT, 'transaction falls in a closed period'); END;
+
+    -- Figures captured by `report snapshot save` for `report diff`
+    CREATE TABLE IF NOT EXISTS report_snapshots(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE TABLE IF NOT EXISTS report_snapshot_lines(
+        snapshot_id INTEGER NOT NULL,
+        section TEXT NOT NULL, -- balance | spend | budget
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY(snapshot_id, section, key),
+        FOREIGN KEY(snapshot_id) REFERENCES report_snapshots(id) ON DELETE CASCADE
+    );
+
     CREATE TABLE IF NOT EXISTS budgets(
         id INTEGER PRIMARY KEY AUTOINCREMENT,
         month TEXT NOT NULL,
         category_id INTEGER NOT NULL,
-        amount TEXT NOT NULL, -- stored in BASE currency
+        amount TEXT NOT NULL, -- in `currency`, or BASE when that is NULL
         UNIQUE(month, category_id),
         FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
     );
 
+    -- What-if copies of one month's budgets (`budget scenario`)
+    CREATE TABLE IF NOT EXISTS budget_scenarios(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        month TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE TABLE IF NOT EXISTS budget_scenario_lines(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        scenario_id INTEGER NOT NULL REFERENCES budget_scenarios(id) ON DELETE CASCADE,
+        category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+        amount TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        UNIQUE(scenario_id, category_id)
+    );
+
+    -- Savings goals; progress is whatever is available in the backing envelope
+    CREATE TABLE IF NOT EXISTS goals(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        category_id INTEGER NOT NULL,
+        target TEXT NOT NULL, -- BASE currency
+        target_date TEXT,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
+    );
+
     CREATE TABLE IF NOT EXISTS assets(
         id INTEGER PRIMARY KEY AUTOINCREMENT,
         ticker TEXT NOT NULL UNIQUE,
@@ -99,6 +200,62 @@ fn init_schema(conn: &mut Connection) -> Result<()> {
     );
     CREATE INDEX IF NOT EXISTS idx_trades_date ON trades(date);
 
+    -- RSU/ESPP grants; vests are booked as buy trades at fair market value
+    CREATE TABLE IF NOT EXISTS grants(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        asset_id INTEGER NOT NULL,
+        account_id INTEGER NOT NULL,
+        kind TEXT NOT NULL CHECK(kind IN ('rsu','espp')),
+        grant_date TEXT NOT NULL,
+        quantity TEXT NOT NULL,
+        purchase_price TEXT NOT NULL DEFAULT '0',
+        FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+        FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
+    );
+
+    CREATE TABLE IF NOT EXISTS grant_schedule(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        grant_id INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        quantity TEXT NOT NULL,
+        UNIQUE(grant_id, date),
+        FOREIGN KEY(grant_id) REFERENCES grants(id) ON DELETE CASCADE
+    );
+
+    CREATE TABLE IF NOT EXISTS grant_vests(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        grant_id INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        quantity TEXT NOT NULL,
+        fmv TEXT NOT NULL,
+        income TEXT NOT NULL, -- (fmv - purchase_price) * quantity, in asset currency
+        trade_id INTEGER,
+        FOREIGN KEY(grant_id) REFERENCES grants(id) ON DELETE CASCADE,
+        FOREIGN KEY(trade_id) REFERENCES trades(id) ON DELETE SET NULL
+    );
+
+    -- Old symbols of renamed assets; `effective` is the first day of the new ticker
+    CREATE TABLE IF NOT EXISTS asset_aliases(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        asset_id INTEGER NOT NULL,
+        symbol TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        effective TEXT NOT NULL,
+        FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE
+    );
+
+    -- Cash dividends reinvested by `trade drip`; amount in asset currency
+    CREATE TABLE IF NOT EXISTS dividends(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        date TEXT NOT NULL,
+        asset_id INTEGER NOT NULL,
+        account_id INTEGER NOT NULL,
+        amount TEXT NOT NULL,
+        trade_id INTEGER,
+        FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+        FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+        FOREIGN KEY(trade_id) REFERENCES trades(id) ON DELETE SET NULL
+    );
+
     CREATE TABLE IF NOT EXISTS prices(
         id INTEGER PRIMARY KEY AUTOINCREMENT,
         asset_id INTEGER NOT NULL,
@@ -109,6 +266,18 @@ fn init_schema(conn: &mut Connection) -> Result<()> {
         FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE
     );
 
+    -- Price alerts; state is where the latest checked price was: above, below or inside
+    CREATE TABLE IF NOT EXISTS price_alerts(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        asset_id INTEGER NOT NULL,
+        above TEXT,
+        below TEXT,
+        state TEXT,
+        fired_at TEXT,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE
+    );
+
     -- FX rates: store base->quote rate (1 base = rate quote) per day
     CREATE TABLE IF NOT EXISTS fx_rates(
         id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -128,7 +297,204 @@ fn init_schema(conn: &mut Connection) -> Result<()> {
         created_at TEXT NOT NULL DEFAULT (datetime('now')),
         FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE SET NULL
     );
+
+    -- Audit trail of `fx set-base`; rate_date is set when values were migrated
+    CREATE TABLE IF NOT EXISTS base_currency_changes(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        changed_at TEXT NOT NULL DEFAULT (datetime('now')),
+        from_currency TEXT NOT NULL,
+        to_currency TEXT NOT NULL,
+        rate_date TEXT,
+        budgets INTEGER NOT NULL DEFAULT 0,
+        goals INTEGER NOT NULL DEFAULT 0
+    );
+
+    -- Rows moved aside by `db repair-decimals --quarantine`, as they were
+    CREATE TABLE IF NOT EXISTS quarantined_rows(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        table_name TEXT NOT NULL,
+        row_id INTEGER NOT NULL,
+        column_name TEXT NOT NULL,
+        raw TEXT NOT NULL,
+        row_json TEXT NOT NULL,
+        quarantined_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+
+    -- Saved CSV dialects for `import transactions --profile`
+    CREATE TABLE IF NOT EXISTS import_profiles(
+        name TEXT PRIMARY KEY,
+        delimiter TEXT NOT NULL DEFAULT ',',
+        decimal_comma INTEGER NOT NULL DEFAULT 0,
+        encoding TEXT NOT NULL DEFAULT 'UTF-8',
+        debit_column TEXT,
+        credit_column TEXT,
+        invert_sign INTEGER NOT NULL DEFAULT 0
+    );
+
+    -- Bank-provided categories and card MCCs mapped by `import mcc-map`
+    CREATE TABLE IF NOT EXISTS mcc_map(
+        code TEXT PRIMARY KEY COLLATE NOCASE,
+        category_id INTEGER NOT NULL,
+        FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
+    );
+
+    -- Wishlist for `plan`; funded from an envelope directly or through a goal
+    CREATE TABLE IF NOT EXISTS transaction_meta(
+        tx_id INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY(tx_id, key),
+        FOREIGN KEY(tx_id) REFERENCES transactions(id) ON DELETE CASCADE
+    );
+
+    -- Jars: sub-balances of one account; NULL on a move is the unassigned balance
+    CREATE TABLE IF NOT EXISTS jars(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        account_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        UNIQUE(account_id, name),
+        FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
+    );
+
+    CREATE TABLE IF NOT EXISTS jar_moves(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        date TEXT NOT NULL,
+        from_jar_id INTEGER REFERENCES jars(id) ON DELETE SET NULL,
+        to_jar_id INTEGER REFERENCES jars(id) ON DELETE SET NULL,
+        amount TEXT NOT NULL,
+        note TEXT,
+        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+
+    -- Interest earned by an account, posted by `recurring run` through posted_through
+    CREATE TABLE IF NOT EXISTS account_interest(
+        account_id INTEGER PRIMARY KEY REFERENCES accounts(id) ON DELETE CASCADE,
+        apy TEXT NOT NULL,
+        compounding TEXT NOT NULL CHECK(compounding IN ('daily','monthly','quarterly','yearly')),
+        category_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
+        since TEXT NOT NULL,
+        posted_through TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS trips(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        start_date TEXT NOT NULL,
+        end_date TEXT NOT NULL,
+        tag TEXT,
+        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+
+    CREATE TABLE IF NOT EXISTS invoices(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        number TEXT NOT NULL UNIQUE,
+        client TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        issued TEXT NOT NULL,
+        due TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'draft' CHECK(status IN ('draft','sent','paid')),
+        sent_on TEXT,
+        paid_on TEXT,
+        paid_tx_id INTEGER,
+        note TEXT,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        FOREIGN KEY(paid_tx_id) REFERENCES transactions(id) ON DELETE SET NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS planned_purchases(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        item TEXT NOT NULL UNIQUE,
+        cost TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        month TEXT,
+        category_id INTEGER,
+        goal_id INTEGER,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE SET NULL,
+        FOREIGN KEY(goal_id) REFERENCES goals(id) ON DELETE SET NULL
+    );
+
+    -- Consumer price index per month (YYYY-MM), for inflation-adjusted reports
+    CREATE TABLE IF NOT EXISTS cpi(
+        month TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+
+    -- Imports committed in chunks (`import transactions --batch-size`); rows_done
+    -- counts the committed rows of all files in order, for `import resume`
+    CREATE TABLE IF NOT EXISTS import_batches(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        paths TEXT NOT NULL,
+        sheet TEXT,
+        format TEXT NOT NULL,
+        batch_size INTEGER NOT NULL,
+        rows_total INTEGER NOT NULL,
+        rows_done INTEGER NOT NULL DEFAULT 0,
+        file_before INTEGER NOT NULL DEFAULT 0,
+        started_at TEXT NOT NULL DEFAULT (datetime('now')),
+        finished_at TEXT
+    );
     "#,
     )?;
+    ensure_column(
+        conn,
+        "accounts",
+        "default_category_id",
+        "INTEGER REFERENCES categories(id) ON DELETE SET NULL",
+    )?;
+    ensure_column(conn, "accounts", "group_name", "TEXT")?;
+    ensure_column(conn, "import_profiles", "bank_category_column", "TEXT")?;
+    ensure_column(conn, "import_profiles", "balance_column", "TEXT")?;
+    // NULL: saved before budgets had a currency, read as the base currency.
+    ensure_column(conn, "budgets", "currency", "TEXT")?;
+    ensure_column(conn, "rules", "ignore_case", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "rules", "literal", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "rules", "match_field", "TEXT NOT NULL DEFAULT 'both'")?;
+    ensure_column(conn, "rules", "skip", "INTEGER NOT NULL DEFAULT 0")?;
+    // Monthly spending cap checked at entry time, independent of budgets.
+    ensure_column(conn, "categories", "monthly_cap", "TEXT")?;
+    ensure_column(conn, "categories", "cap_currency", "TEXT")?;
+    // Set by `account merge` on the account merged away; hidden from `account list`.
+    ensure_column(conn, "accounts", "archived_at", "TEXT")?;
+    // Shared expenses: who owes this transaction back, and the repayment that settled it.
+    ensure_column(conn, "transactions", "reimbursable_by", "TEXT")?;
+    ensure_column(
+        conn,
+        "transactions",
+        "settled_by",
+        "INTEGER REFERENCES transactions(id) ON DELETE SET NULL",
+    )?;
+    ensure_column(conn, "transactions", "tax_amount", "TEXT")?;
+    ensure_column(conn, "transactions", "tax_code", "TEXT")?;
+    ensure_column(conn, "transactions", "project", "TEXT")?;
+    ensure_column(conn, "transactions", "location", "TEXT")?;
+    ensure_column(conn, "transactions", "latitude", "REAL")?;
+    ensure_column(conn, "transactions", "longitude", "REAL")?;
+    ensure_column(
+        conn,
+        "transactions",
+        "jar_id",
+        "INTEGER REFERENCES jars(id) ON DELETE SET NULL",
+    )?;
+    // NULL: fees are in the asset's currency.
+    ensure_column(conn, "trades", "fee_currency", "TEXT")?;
+    // 1: on the watchlist (`portfolio watch`), priced whether held or not.
+    ensure_column(conn, "assets", "watched", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+/// Add `column` to an existing table when an older database predates it.
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let present: bool = conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{table}') WHERE name=?1)"),
+        [column],
+        |r| r.get(0),
+    )?;
+    if !present {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))
+            .with_context(|| format!("Add column {table}.{column}"))?;
+    }
     Ok(())
 }
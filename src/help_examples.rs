@@ -0,0 +1,1011 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Copy-pasteable examples shown at the end of every command's `--help`.
+
+use clap::Command;
+
+/// Examples per subcommand path, written without the leading `moneyclip`.
+/// Every line must parse against the CLI; `tests/cli_tests.rs` checks that.
+const EXAMPLES: &[(&str, &[&str])] = &[
+    (
+        "init",
+        &["init", "--no-input init --base INR --template basic"],
+    ),
+    (
+        "account",
+        &[
+            "account add --name \"HDFC Savings\" --type bank --currency INR",
+            "account list --all",
+        ],
+    ),
+    (
+        "account add",
+        &[
+            "account add --name \"HDFC Savings\" --type bank --currency INR",
+            "account add --name \"Revolut USD\" --type bank --currency USD --group Liquid",
+        ],
+    ),
+    ("account list", &["account list", "account list --all"]),
+    (
+        "account merge",
+        &[
+            "account merge --from \"Old Checking\" --into Checking --dry-run",
+            "account merge --from \"Old Checking\" --into Checking",
+        ],
+    ),
+    (
+        "account set-group",
+        &[
+            "account set-group --name \"HDFC Savings\" --group Liquid",
+            "account set-group --name \"HDFC Savings\" --clear",
+        ],
+    ),
+    (
+        "account set-default-category",
+        &[
+            "account set-default-category --name \"Revolut USD\" --category Dining",
+            "account set-default-category --name \"Revolut USD\" --clear",
+        ],
+    ),
+    (
+        "account set-currency",
+        &[
+            "account set-currency --name \"Revolut USD\" --currency EUR --convert-history --dry-run",
+            "account set-currency --name \"Revolut USD\" --currency EUR --keep-history",
+        ],
+    ),
+    (
+        "account interest",
+        &[
+            "account interest set --name \"HDFC Savings\" --apy 4.5",
+            "account interest list",
+        ],
+    ),
+    (
+        "account interest set",
+        &[
+            "account interest set --name \"HDFC Savings\" --apy 4.5",
+            "account interest set --name \"HDFC Savings\" --apy 4.5 --compounding monthly --from 2025-01-01 --category Interest",
+        ],
+    ),
+    (
+        "account interest list",
+        &["account interest list", "account interest list --json"],
+    ),
+    (
+        "account interest rm",
+        &["account interest rm --name \"HDFC Savings\""],
+    ),
+    (
+        "account rm",
+        &[
+            "account rm --name \"Old Checking\"",
+            "account rm --name \"Old Checking\" --yes",
+        ],
+    ),
+    (
+        "category",
+        &["category add --name Groceries", "category list"],
+    ),
+    (
+        "category add",
+        &[
+            "category add --name Groceries",
+            "category add --name \"Eating Out\"",
+        ],
+    ),
+    (
+        "category list",
+        &["category list", "--markdown category list"],
+    ),
+    (
+        "category rm",
+        &[
+            "category rm --name Dining",
+            "category rm --name Dining --yes",
+        ],
+    ),
+    (
+        "category set-cap",
+        &[
+            "category set-cap --name Dining --amount 300",
+            "category set-cap --name Travel --amount 500 --currency EUR",
+            "category set-cap --name Dining --clear",
+        ],
+    ),
+    (
+        "import",
+        &[
+            "import transactions --path statements.csv",
+            "import resume --batch 3",
+        ],
+    ),
+    (
+        "import transactions",
+        &[
+            "import transactions --path statements.csv",
+            "import transactions --path umsaetze.csv --delimiter \";\" --decimal-comma --encoding windows-1252",
+            "import transactions --path statement.xlsx --sheet 2",
+            "import transactions --path mybank.csv --profile mybank --skip-before 2025-08-01",
+            "import transactions --path 'history/*.csv' --batch-size 5000",
+        ],
+    ),
+    (
+        "import resume",
+        &["import resume --batch 3", "import resume --batch 3 --json"],
+    ),
+    (
+        "import pdf",
+        &[
+            "import pdf --path statement.pdf --profile hdfc --account \"HDFC Savings\" --dry-run",
+            "import pdf --path statement.pdf --profile generic --account Card",
+        ],
+    ),
+    (
+        "import mcc-map",
+        &[
+            "import mcc-map set --code 5411 --category Groceries",
+            "import mcc-map list",
+        ],
+    ),
+    (
+        "import mcc-map set",
+        &[
+            "import mcc-map set --code 5411 --category Groceries",
+            "import mcc-map set --code Restaurants --category Dining",
+        ],
+    ),
+    ("import mcc-map list", &["import mcc-map list"]),
+    ("import mcc-map rm", &["import mcc-map rm --code 5411"]),
+    (
+        "import profile",
+        &[
+            "import profile add --name mybank --delimiter \";\" --decimal-comma",
+            "import profile list",
+        ],
+    ),
+    (
+        "import profile add",
+        &[
+            "import profile add --name mybank --delimiter \";\" --debit-column Withdrawal --credit-column Deposit",
+            "import profile add --name card --invert-sign --bank-category-column MCC",
+        ],
+    ),
+    ("import profile list", &["import profile list"]),
+    ("import profile rm", &["import profile rm --name mybank"]),
+    (
+        "export",
+        &[
+            "export transactions --format csv --out all.csv",
+            "export budgets --year 2025 --out budgets-2025.csv",
+        ],
+    ),
+    (
+        "export transactions",
+        &[
+            "export transactions --format csv --out all.csv",
+            "export transactions --format json --out 2025.json --from 2025-01-01 --to 2025-12-31 --with-meta",
+        ],
+    ),
+    (
+        "export budgets",
+        &[
+            "export budgets --year 2025 --out budgets-2025.csv",
+            "export budgets --month 2025-08 --format json --out budgets.json",
+        ],
+    ),
+    (
+        "export envelopes",
+        &[
+            "export envelopes --month 2025-08 --out envelopes.csv",
+            "export envelopes --month 2025-08 --format json --out envelopes.json",
+        ],
+    ),
+    (
+        "export sheets",
+        &[
+            "export sheets --spreadsheet-id 1AbC --tab Transactions --month 2025-08 --mode append",
+            "export sheets --spreadsheet-id 1AbC --tab Budget --source budgets --month 2025-08",
+        ],
+    ),
+    (
+        "export anonymized",
+        &["export anonymized --out repro.sqlite"],
+    ),
+    (
+        "fx",
+        &[
+            "fx fetch --days 180",
+            "fx convert --date 2025-08-15 --amount 100 --from EUR --to INR",
+        ],
+    ),
+    (
+        "fx set-base",
+        &[
+            "fx set-base --currency INR",
+            "fx set-base --currency EUR --migrate --date 2025-09-01",
+        ],
+    ),
+    ("fx base-history", &["fx base-history"]),
+    ("fx fetch", &["fx fetch", "fx fetch --days 180"]),
+    (
+        "fx backfill",
+        &["fx backfill --dry-run", "fx backfill --max-age 7"],
+    ),
+    (
+        "fx set-policy",
+        &[
+            "fx set-policy --policy direct",
+            "fx set-policy --policy best",
+        ],
+    ),
+    (
+        "fx set-valuation-date",
+        &[
+            "fx set-valuation-date --policy nearest",
+            "fx set-valuation-date --policy previous",
+        ],
+    ),
+    (
+        "fx explain",
+        &[
+            "fx explain --date 2025-08-15 --from EUR --to JPY",
+            "fx explain --date 2025-08-15 --from EUR --to JPY --json",
+        ],
+    ),
+    (
+        "fx doctor",
+        &["fx doctor", "fx doctor --max-jump 15 --tolerance 0.5"],
+    ),
+    ("fx list", &["fx list", "fx list --markdown"]),
+    (
+        "fx convert",
+        &[
+            "fx convert --date 2025-08-15 --amount 100 --from EUR --to INR",
+            "fx convert --series --from-date 2025-06-01 --to-date 2025-08-31 --step week --amount 2000 --from EUR --to USD",
+        ],
+    ),
+    (
+        "doctor",
+        &[
+            "doctor",
+            "doctor --inactive-months 6",
+            "doctor --severity error --json",
+        ],
+    ),
+    ("period", &["period close 2025-07", "period list"]),
+    (
+        "period close",
+        &["period close 2025-07", "period close last"],
+    ),
+    ("period reopen", &["period reopen 2025-07 --force"]),
+    ("period list", &["period list"]),
+    (
+        "close-month",
+        &[
+            "close-month 2025-08 --dry-run",
+            "close-month last --skip prices",
+            "close-month 2025-08 --statement Checking=1520.40",
+            "close-month 2025-08 --force",
+        ],
+    ),
+    ("batch", &["batch --file month-close.txt", "batch --file -"]),
+    (
+        "config",
+        &["config set default_account Cash", "config list"],
+    ),
+    (
+        "config set",
+        &[
+            "config set default_account \"HDFC Savings\"",
+            "config set timezone Asia/Kolkata",
+            "config set close_checklist doctor,lock,summary",
+        ],
+    ),
+    ("config get", &["config get default_account"]),
+    ("config unset", &["config unset default_account"]),
+    ("config list", &["config list"]),
+    ("config edit", &["config edit"]),
+    ("config env", &["config env"]),
+    ("db", &["db maintain", "db repair-decimals --dry-run"]),
+    ("db maintain", &["db maintain", "db maintain --keep-prices"]),
+    (
+        "db repair-decimals",
+        &[
+            "db repair-decimals --dry-run",
+            "db repair-decimals --quarantine",
+        ],
+    ),
+    (
+        "rules",
+        &[
+            "rules add --pattern \"(?i)amazon|amzn\" --category Shopping",
+            "rules list",
+        ],
+    ),
+    (
+        "rules add",
+        &[
+            "rules add --pattern \"(?i)amazon|amzn\" --category Shopping --payee_rewrite Amazon",
+            "rules add --pattern \"SQ *BLUE BOTTLE\" --literal --ignore-case --category Dining",
+            "rules add --pattern pending --ignore-case --match-field note --skip",
+        ],
+    ),
+    ("rules list", &["rules list"]),
+    (
+        "rules lint",
+        &["rules lint", "rules lint --days 180 --json"],
+    ),
+    (
+        "rules bootstrap",
+        &[
+            "rules bootstrap --pack default-in",
+            "rules bootstrap --pack default-us",
+        ],
+    ),
+    ("rules rm", &["rules rm --id 4"]),
+    (
+        "plan",
+        &[
+            "plan add --item \"Standing desk\" --cost 450 --month 2025-11 --envelope Home",
+            "plan afford --item \"Standing desk\"",
+        ],
+    ),
+    (
+        "plan add",
+        &[
+            "plan add --item \"Standing desk\" --cost 450 --month 2025-11 --envelope Home",
+            "plan add --item Flights --cost 900 --currency EUR --goal Japan",
+        ],
+    ),
+    ("plan list", &["plan list", "plan list --json"]),
+    ("plan rm", &["plan rm --item \"Standing desk\""]),
+    (
+        "plan afford",
+        &[
+            "plan afford --item \"Standing desk\"",
+            "plan afford --item \"Standing desk\" --as-of 2025-10-15 --json",
+        ],
+    ),
+    (
+        "trip",
+        &[
+            "trip add --name Japan-2025 --from 2025-04-01 --to 2025-04-20",
+            "report trip --name Japan-2025",
+        ],
+    ),
+    (
+        "trip add",
+        &[
+            "trip add --name Japan-2025 --from 2025-04-01 --to 2025-04-20",
+            "trip add --name Japan-2025 --from 2025-04-01 --to 2025-04-20 --tag Japan",
+        ],
+    ),
+    ("trip list", &["trip list", "trip list --json"]),
+    ("trip rm", &["trip rm --name Japan-2025"]),
+    (
+        "jar",
+        &["jar add --account \"HDFC Savings\" --name Emma", "jar list"],
+    ),
+    (
+        "jar add",
+        &["jar add --account \"HDFC Savings\" --name Emma"],
+    ),
+    (
+        "jar list",
+        &["jar list", "jar list --account \"HDFC Savings\" --json"],
+    ),
+    (
+        "jar move",
+        &[
+            "jar move --account \"HDFC Savings\" --to Emma --amount 10 --note \"Weekly allowance\"",
+            "jar move --account \"HDFC Savings\" --from Emma --to Noah --amount 5 --date 2025-08-20",
+        ],
+    ),
+    ("jar rm", &["jar rm --account \"HDFC Savings\" --name Emma"]),
+    (
+        "asset",
+        &[
+            "asset alias add --from FB --to META --effective 2022-06-09",
+            "asset alias list",
+        ],
+    ),
+    (
+        "asset alias",
+        &[
+            "asset alias add --from FB --to META --effective 2022-06-09",
+            "asset alias list",
+        ],
+    ),
+    (
+        "asset alias add",
+        &["asset alias add --from FB --to META --effective 2022-06-09"],
+    ),
+    (
+        "asset alias list",
+        &["asset alias list", "asset alias list --json"],
+    ),
+    ("asset alias rm", &["asset alias rm --from FB"]),
+    (
+        "recurring",
+        &[
+            "recurring run",
+            "recurring run --through 2026-12-31 --dry-run",
+        ],
+    ),
+    (
+        "recurring run",
+        &[
+            "recurring run",
+            "recurring run --through 2026-12-31 --dry-run",
+            "recurring run --json",
+        ],
+    ),
+    ("sync", &["sync --days 7", "sync --every 30"]),
+    (
+        "cpi",
+        &["cpi fetch", "cpi set --month 2025-08 --value 322.1"],
+    ),
+    ("cpi set", &["cpi set --month 2025-08 --value 322.1"]),
+    ("cpi import", &["cpi import --path cpi.csv"]),
+    ("cpi fetch", &["cpi fetch", "cpi fetch --series CPIAUCSL"]),
+    ("cpi list", &["cpi list", "cpi list --json"]),
+    (
+        "budget",
+        &[
+            "budget set --month 2025-08 --category Groceries --amount 12000",
+            "budget report --month 2025-08 --base",
+        ],
+    ),
+    (
+        "budget set",
+        &[
+            "budget set --month 2025-08 --category Groceries --amount 12000",
+            "budget set --month 2025-08 --category Travel --amount 500 --currency EUR",
+        ],
+    ),
+    (
+        "budget rebase",
+        &[
+            "budget rebase --to EUR --dry-run",
+            "budget rebase --to EUR --from INR",
+        ],
+    ),
+    (
+        "budget scenario",
+        &[
+            "budget scenario create --name \"new apartment\" --month 2025-08",
+            "budget scenario compare --name \"new apartment\"",
+        ],
+    ),
+    (
+        "budget scenario create",
+        &["budget scenario create --name \"new apartment\" --month 2025-08"],
+    ),
+    (
+        "budget scenario set",
+        &[
+            "budget scenario set --name \"new apartment\" --category Rent --amount 32000",
+            "budget scenario set --name \"new apartment\" --category Travel --amount 300 --currency EUR",
+        ],
+    ),
+    (
+        "budget scenario compare",
+        &[
+            "budget scenario compare --name \"new apartment\"",
+            "budget scenario compare --name \"new apartment\" --income 150000 --json",
+        ],
+    ),
+    ("budget scenario list", &["budget scenario list"]),
+    (
+        "budget scenario rm",
+        &["budget scenario rm --name \"new apartment\""],
+    ),
+    (
+        "budget list",
+        &[
+            "budget list --month 2025-08",
+            "budget list --month 2025-08 --totals --json",
+        ],
+    ),
+    (
+        "budget report",
+        &[
+            "budget report --month 2025-08 --base",
+            "budget report --month last --chart",
+            "budget report --month 2025-08 --format html --out budget.html",
+        ],
+    ),
+    (
+        "envelope",
+        &[
+            "envelope fund --month 2025-08 --category Groceries --amount 12000",
+            "envelope status --month 2025-08",
+        ],
+    ),
+    (
+        "envelope fund",
+        &["envelope fund --month 2025-08 --category Groceries --amount 12000"],
+    ),
+    (
+        "envelope move",
+        &["envelope move --month 2025-08 --from Groceries --to Dining --amount 1000"],
+    ),
+    (
+        "envelope sweep",
+        &[
+            "envelope sweep --month 2025-08 --to-goal Japan --dry-run",
+            "envelope sweep --month 2025-08 --to-goal Japan",
+        ],
+    ),
+    (
+        "envelope alerts",
+        &[
+            "envelope alerts --threshold 10%",
+            "envelope alerts --month 2025-08 --threshold 500 --email",
+        ],
+    ),
+    (
+        "envelope status",
+        &[
+            "envelope status --month 2025-08",
+            "envelope status --as-of 2025-08-15",
+            "envelope status --month 2025-08 --currency EUR --json",
+        ],
+    ),
+    (
+        "envelope history",
+        &[
+            "envelope history --category Groceries",
+            "envelope history --category Groceries --months 12 --to 2025-08",
+        ],
+    ),
+    (
+        "goal",
+        &[
+            "goal add --name Japan --category Vacation --target 250000 --by 2026-03-31",
+            "goal status",
+        ],
+    ),
+    (
+        "goal add",
+        &[
+            "goal add --name Japan --category Vacation --target 250000",
+            "goal add --name Japan --category Vacation --target 250000 --by 2026-03-31",
+        ],
+    ),
+    ("goal rm", &["goal rm --name Japan"]),
+    (
+        "goal status",
+        &["goal status", "goal status --month 2025-08 --json"],
+    ),
+    (
+        "invoice",
+        &[
+            "invoice create --client Acme --amount 2400 --currency EUR --due 2025-09-30",
+            "invoice aging",
+        ],
+    ),
+    (
+        "invoice create",
+        &[
+            "invoice create --client Acme --amount 2400 --due 2025-09-30",
+            "invoice create --client Acme --amount 2400 --currency EUR --due 2025-09-30 --issued 2025-09-01 --number INV-0007 --note \"August retainer\"",
+        ],
+    ),
+    (
+        "invoice list",
+        &["invoice list", "invoice list --status open --client Acme"],
+    ),
+    (
+        "invoice sent",
+        &[
+            "invoice sent --number INV-0001",
+            "invoice sent --number INV-0001 --date 2025-09-02",
+        ],
+    ),
+    (
+        "invoice paid",
+        &[
+            "invoice paid --number INV-0001 --date 2025-09-28",
+            "invoice paid --number INV-0001 --tx 812",
+        ],
+    ),
+    (
+        "invoice aging",
+        &["invoice aging", "invoice aging --as-of 2025-10-31 --json"],
+    ),
+    (
+        "portfolio",
+        &[
+            "portfolio add-asset --ticker VWRL.AS --auto",
+            "portfolio value --live",
+        ],
+    ),
+    (
+        "portfolio add-asset",
+        &[
+            "portfolio add-asset --ticker TCS.NS --name \"Tata Consultancy Services\" --currency INR",
+            "portfolio add-asset --ticker VWRL.AS --auto",
+        ],
+    ),
+    ("portfolio list-assets", &["portfolio list-assets"]),
+    (
+        "portfolio trade",
+        &[
+            "portfolio trade buy --date 2025-03-03 --ticker AAPL --account Broker --quantity 5 --price 238.10",
+            "portfolio trade sell --date 2025-06-02 --ticker AAPL --account Broker --quantity 2 --price 201.45",
+        ],
+    ),
+    (
+        "portfolio trade buy",
+        &[
+            "portfolio trade buy --date 2025-03-03 --ticker AAPL --account Broker --quantity 5 --price 238.10",
+            "portfolio trade buy --date 2025-03-03 --ticker AAPL --account Broker --quantity 5 --price 238.10 --fees 4.90 --fee-currency EUR",
+        ],
+    ),
+    (
+        "portfolio trade sell",
+        &[
+            "portfolio trade sell --date 2025-06-02 --ticker AAPL --account Broker --quantity 2 --price 201.45",
+            "portfolio trade sell --date 2025-06-02 --ticker AAPL --account Broker --quantity 2 --price 201.45 --fees 4.90",
+        ],
+    ),
+    (
+        "portfolio trade drip",
+        &[
+            "portfolio trade drip --ticker VTI --account Broker --date 2025-03-28 --amount 12.34",
+            "portfolio trade drip --ticker VTI --account Broker --date 2025-03-28 --amount 12.34 --price 281.20",
+        ],
+    ),
+    (
+        "portfolio grant",
+        &[
+            "portfolio grant add --ticker EMP --account Broker --kind rsu --date 2025-01-01 --quantity 100",
+            "portfolio grant list",
+        ],
+    ),
+    (
+        "portfolio grant add",
+        &[
+            "portfolio grant add --ticker EMP --account Broker --kind rsu --date 2025-01-01 --quantity 100 --schedule 2026-01-01:25,2027-01-01:25",
+            "portfolio grant add --ticker EMP --account Broker --kind espp --date 2025-06-30 --quantity 40 --purchase-price 85.00",
+        ],
+    ),
+    (
+        "portfolio grant vest",
+        &[
+            "portfolio grant vest --id 1 --date 2026-01-01 --fmv 142.10",
+            "portfolio grant vest --id 1 --date 2026-01-01 --fmv 142.10 --quantity 25",
+        ],
+    ),
+    ("portfolio grant list", &["portfolio grant list"]),
+    (
+        "portfolio alert",
+        &[
+            "portfolio alert add --ticker AAPL --above 250 --below 180",
+            "portfolio alert check",
+        ],
+    ),
+    (
+        "portfolio alert add",
+        &[
+            "portfolio alert add --ticker AAPL --above 250",
+            "portfolio alert add --ticker AAPL --above 250 --below 180",
+        ],
+    ),
+    (
+        "portfolio alert list",
+        &["portfolio alert list", "portfolio alert list --json"],
+    ),
+    ("portfolio alert rm", &["portfolio alert rm --id 1"]),
+    (
+        "portfolio alert check",
+        &[
+            "portfolio alert check",
+            "portfolio alert check --email",
+            "portfolio alert check --webhook https://hooks.example.com/moneyclip",
+        ],
+    ),
+    (
+        "portfolio watch",
+        &[
+            "portfolio watch add --ticker NVDA --currency USD",
+            "portfolio watch list",
+        ],
+    ),
+    (
+        "portfolio watch add",
+        &[
+            "portfolio watch add --ticker NVDA --currency USD",
+            "portfolio watch add --ticker ASML.AS --name ASML --currency EUR",
+        ],
+    ),
+    (
+        "portfolio watch remove",
+        &["portfolio watch remove --ticker NVDA"],
+    ),
+    (
+        "portfolio watch list",
+        &["portfolio watch list", "portfolio watch list --json"],
+    ),
+    (
+        "portfolio price",
+        &["portfolio price fetch", "portfolio price list"],
+    ),
+    (
+        "portfolio price fetch",
+        &[
+            "portfolio price fetch",
+            "portfolio price fetch --webhook https://hooks.example.com/moneyclip",
+        ],
+    ),
+    ("portfolio price list", &["portfolio price list"]),
+    (
+        "portfolio price prune",
+        &[
+            "portfolio price prune --keep weekly --before 2023-01-01 --dry-run",
+            "portfolio price prune --keep daily --before 2020-01-01",
+        ],
+    ),
+    (
+        "portfolio value",
+        &[
+            "portfolio value",
+            "portfolio value --live",
+            "portfolio value --at 2024-12-31 --currency EUR",
+        ],
+    ),
+    (
+        "portfolio tax",
+        &[
+            "portfolio tax --year 2025",
+            "portfolio tax --year 2025 --format csv --out gains-2025.csv",
+        ],
+    ),
+    (
+        "tx",
+        &[
+            "tx add --date today --account Cash --amount -12 --payee Lunch",
+            "tx list --month this",
+        ],
+    ),
+    (
+        "tx add",
+        &[
+            "tx add",
+            "tx add --date 2025-08-12 --account \"HDFC Savings\" --amount -1250.75 --payee \"Big Bazaar\" --category Groceries",
+            "tx add --date yesterday --account Cash --amount \"-(38.40+6)/3\" --payee \"Dinner split\"",
+            "tx add --template coffee",
+        ],
+    ),
+    (
+        "tx meta",
+        &[
+            "tx meta set --id 812 --key ref --value R-100",
+            "tx meta get --id 812",
+        ],
+    ),
+    (
+        "tx meta set",
+        &["tx meta set --id 812 --key ref --value R-100"],
+    ),
+    (
+        "tx meta get",
+        &["tx meta get --id 812", "tx meta get --id 812 --key ref"],
+    ),
+    ("tx meta rm", &["tx meta rm --id 812 --key ref"]),
+    (
+        "tx geocode",
+        &["tx geocode --dry-run", "tx geocode --limit 20"],
+    ),
+    (
+        "tx settle",
+        &[
+            "tx settle --person Alex --date 2025-08-20 --amount 42",
+            "tx settle --person Alex --date 2025-08-20 --amount 42 --account \"HDFC Savings\"",
+        ],
+    ),
+    (
+        "tx template",
+        &[
+            "tx template save --name coffee --account Cash --category Dining --amount -4.50",
+            "tx template list",
+        ],
+    ),
+    (
+        "tx template save",
+        &["tx template save --name coffee --account Cash --category Dining --amount -4.50"],
+    ),
+    (
+        "tx template edit",
+        &["tx template edit --name coffee --amount -4.80 --payee \"Blue Bottle\""],
+    ),
+    ("tx template list", &["tx template list"]),
+    ("tx template rm", &["tx template rm --name coffee"]),
+    (
+        "tx list",
+        &[
+            "tx list --month this",
+            "tx list --from 2025-07-15 --to 2025-08-14 --account Cash",
+            "tx list --sort amount --asc --limit 20 --offset 40",
+            "tx list --uncategorized --no-note --currency EUR",
+        ],
+    ),
+    (
+        "report",
+        &[
+            "report balances --base",
+            "report cashflow --months 6 --chart",
+        ],
+    ),
+    (
+        "report snapshot",
+        &[
+            "report snapshot save --name pre-close",
+            "report snapshot list",
+        ],
+    ),
+    (
+        "report snapshot save",
+        &["report snapshot save --name pre-close"],
+    ),
+    ("report snapshot list", &["report snapshot list"]),
+    (
+        "report snapshot rm",
+        &["report snapshot rm --name pre-close"],
+    ),
+    (
+        "report digest",
+        &[
+            "report digest --period week",
+            "report digest --period month --out digest.md",
+            "report digest --email",
+        ],
+    ),
+    (
+        "report balances",
+        &[
+            "report balances",
+            "report balances --base --by-group",
+            "report balances --currency EUR --json",
+        ],
+    ),
+    (
+        "report cashflow",
+        &[
+            "report cashflow --months 6 --chart",
+            "report cashflow --months 12 --by week --base",
+            "report cashflow --from 2019-01-01 --real --cpi-base 2020",
+            "report cashflow --months 12 --format csv --out cashflow.csv",
+        ],
+    ),
+    (
+        "report fees",
+        &[
+            "report fees --year 2025",
+            "report fees --year 2025 --fee-category \"Bank Fees\"",
+        ],
+    ),
+    (
+        "report subscriptions",
+        &[
+            "report subscriptions",
+            "report subscriptions --min-charges 4 --json",
+        ],
+    ),
+    (
+        "report anomalies",
+        &[
+            "report anomalies --month last",
+            "report anomalies --month 2025-08 --z 2.5 --new-payee-min 200",
+        ],
+    ),
+    (
+        "report compare",
+        &[
+            "report compare --a 2025-07 --b 2025-08",
+            "report compare --a 2025-07 --b 2025-08 --by payee",
+        ],
+    ),
+    (
+        "report balance-history",
+        &[
+            "report balance-history --account Checking --from 2025-06-01 --to 2025-08-31",
+            "report balance-history --account \"Revolut USD\" --base --format csv --out revolut.csv",
+        ],
+    ),
+    ("report owed", &["report owed", "report owed --person Alex"]),
+    (
+        "report by-project",
+        &[
+            "report by-project",
+            "report by-project --project Kitchen --from 2025-01-01",
+        ],
+    ),
+    (
+        "report spend-by-location",
+        &[
+            "report spend-by-location",
+            "report spend-by-location --by country --from 2025-04-01 --to 2025-04-20",
+        ],
+    ),
+    (
+        "report trip",
+        &[
+            "report trip --name Japan-2025",
+            "report trip --name Japan-2025 --json",
+        ],
+    ),
+    (
+        "report tax-collected",
+        &[
+            "report tax-collected",
+            "report tax-collected --quarter 2025-Q3",
+        ],
+    ),
+    (
+        "report diff",
+        &[
+            "report diff pre-close",
+            "report diff pre-close post-close --json",
+        ],
+    ),
+    (
+        "report spend-by-category",
+        &[
+            "report spend-by-category --month 2025-08 --base",
+            "report spend-by-category --from \"4w ago\" --base --chart",
+            "report spend-by-category --month 2025-08 --jsonl",
+        ],
+    ),
+    (
+        "report heatmap",
+        &[
+            "report heatmap --year 2025",
+            "report heatmap --year 2025 --category Dining --format json --out dining.json",
+        ],
+    ),
+];
+
+/// Appends an "Examples:" section to `cmd` and, recursively, to its
+/// subcommands. Existing `after_help` text is kept above the examples.
+pub(crate) fn with_examples(cmd: Command) -> Command {
+    attach_to_subcommands(cmd, "")
+}
+
+fn attach(mut cmd: Command, parent: &str) -> Command {
+    let path = if parent.is_empty() {
+        cmd.get_name().to_string()
+    } else {
+        format!("{parent} {}", cmd.get_name())
+    };
+    if let Some((_, lines)) = EXAMPLES.iter().find(|(p, _)| *p == path) {
+        let mut text = cmd
+            .get_after_help()
+            .map(|h| format!("{h}\n\n"))
+            .unwrap_or_default();
+        text.push_str("Examples:");
+        for line in lines.iter() {
+            text.push_str("\n  moneyclip ");
+            text.push_str(line);
+        }
+        cmd = cmd.after_help(text);
+    }
+    attach_to_subcommands(cmd, &path)
+}
+
+fn attach_to_subcommands(mut cmd: Command, path: &str) -> Command {
+    let names: Vec<String> = cmd
+        .get_subcommands()
+        .map(|c| c.get_name().to_string())
+        .collect();
+    for name in names {
+        cmd = cmd.mut_subcommand(&name, |c| attach(c, path));
+    }
+    cmd
+}
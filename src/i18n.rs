@@ -0,0 +1,234 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Translated labels for people reading reports, chosen with the `language` setting.
+//! Table headers and summary rows are translated when tables are drawn; a few frequent
+//! messages go through [`trf`]. CSV and JSON output, and text without a translation,
+//! stay English so scripts keep working.
+
+use anyhow::{Result, bail};
+use rusqlite::Connection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    En,
+    De,
+    Es,
+    Hi,
+}
+
+impl Language {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s.trim().to_lowercase().as_str() {
+            "en" => Self::En,
+            "de" => Self::De,
+            "es" => Self::Es,
+            "hi" => Self::Hi,
+            other => bail!("Unknown language '{}' (en|de|es|hi)", other),
+        })
+    }
+}
+
+pub fn language() -> Language {
+    crate::utils::settings().language
+}
+
+pub(crate) fn load_language(conn: &Connection) -> Result<Language> {
+    match crate::utils::get_setting(conn, "language")? {
+        Some(v) => Language::parse(&v),
+        None => Ok(Language::En),
+    }
+}
+
+/// English text with its German, Spanish and Hindi forms.
+const TEXTS: &[(&str, &str, &str, &str)] = &[
+    ("Account", "Konto", "Cuenta", "खाता"),
+    ("Amount", "Betrag", "Importe", "राशि"),
+    ("Available", "Verfügbar", "Disponible", "उपलब्ध"),
+    ("Balance", "Saldo", "Saldo", "शेष"),
+    ("Baseline", "Ausgangslage", "Base", "आधार"),
+    ("Budget", "Budget", "Presupuesto", "बजट"),
+    ("Budgets", "Budgets", "Presupuestos", "बजट"),
+    ("CCY", "Whg.", "Mon.", "मुद्रा"),
+    ("Categories", "Kategorien", "Categorías", "श्रेणियाँ"),
+    ("Category", "Kategorie", "Categoría", "श्रेणी"),
+    ("Change", "Änderung", "Cambio", "बदलाव"),
+    ("Change %", "Änderung %", "Cambio %", "बदलाव %"),
+    ("Cost", "Kosten", "Coste", "लागत"),
+    ("Created", "Erstellt", "Creado", "बनाया गया"),
+    ("Currency", "Währung", "Moneda", "मुद्रा"),
+    ("Date", "Datum", "Fecha", "तारीख"),
+    ("Due", "Fällig", "Vence", "देय"),
+    ("Envelope", "Umschlag", "Sobre", "लिफ़ाफ़ा"),
+    ("Expense", "Ausgaben", "Gastos", "व्यय"),
+    ("From", "Von", "Desde", "से"),
+    ("Goal", "Ziel", "Meta", "लक्ष्य"),
+    ("Group", "Gruppe", "Grupo", "समूह"),
+    ("Income", "Einnahmen", "Ingresos", "आय"),
+    ("Interest", "Zinsen", "Intereses", "ब्याज"),
+    ("Month", "Monat", "Mes", "महीना"),
+    ("Name", "Name", "Nombre", "नाम"),
+    ("Net", "Netto", "Neto", "शुद्ध"),
+    ("Note", "Notiz", "Nota", "टिप्पणी"),
+    ("Paid", "Bezahlt", "Pagado", "भुगतान किया"),
+    ("Payee", "Empfänger", "Beneficiario", "प्राप्तकर्ता"),
+    ("Per day", "Pro Tag", "Por día", "प्रति दिन"),
+    ("Per month", "Pro Monat", "Por mes", "प्रति माह"),
+    ("Per year", "Pro Jahr", "Por año", "प्रति वर्ष"),
+    ("Price", "Kurs", "Precio", "मूल्य"),
+    ("Progress", "Fortschritt", "Progreso", "प्रगति"),
+    ("Qty", "Menge", "Cant.", "मात्रा"),
+    ("Rate", "Kurs", "Tipo", "दर"),
+    ("Remaining", "Verbleibend", "Restante", "शेष राशि"),
+    ("Savings", "Ersparnis", "Ahorro", "बचत"),
+    (
+        "Savings rate %",
+        "Sparquote %",
+        "Tasa de ahorro %",
+        "बचत दर %",
+    ),
+    ("Scenario", "Szenario", "Escenario", "परिदृश्य"),
+    ("Spent", "Ausgegeben", "Gastado", "खर्च"),
+    ("Status", "Status", "Estado", "स्थिति"),
+    ("Subtotal", "Zwischensumme", "Subtotal", "उप-योग"),
+    ("Target", "Ziel", "Objetivo", "लक्ष्य"),
+    ("Ticker", "Symbol", "Símbolo", "टिकर"),
+    ("To", "Bis", "Hasta", "तक"),
+    ("Total", "Summe", "Total", "कुल"),
+    (
+        "Total budgeted",
+        "Budget gesamt",
+        "Total presupuestado",
+        "कुल बजट",
+    ),
+    ("Transactions", "Buchungen", "Movimientos", "लेन-देन"),
+    ("Type", "Typ", "Tipo", "प्रकार"),
+    ("Value", "Wert", "Valor", "मूल्यांकन"),
+    // Messages, with `{}` where `trf` puts its arguments.
+    (
+        "Recorded {} on {} at '{}' (acct: {})",
+        "{} am {} bei '{}' gebucht (Konto: {})",
+        "{} registrado el {} en '{}' (cuenta: {})",
+        "{} दर्ज किया, {} को '{}' पर (खाता: {})",
+    ),
+    (
+        "Added account '{}' ({}, {})",
+        "Konto '{}' angelegt ({}, {})",
+        "Cuenta '{}' añadida ({}, {})",
+        "खाता '{}' जोड़ा गया ({}, {})",
+    ),
+    (
+        "Budget set for {} / {} = {} {}",
+        "Budget für {} / {} = {} {}",
+        "Presupuesto para {} / {} = {} {}",
+        "{} / {} का बजट = {} {}",
+    ),
+    ("Warning: {}", "Warnung: {}", "Aviso: {}", "चेतावनी: {}"),
+    (
+        "Nothing removed",
+        "Nichts entfernt",
+        "No se eliminó nada",
+        "कुछ नहीं हटाया गया",
+    ),
+];
+
+/// `text` in the current language, or `text` itself when there is no translation.
+pub fn tr(text: &str) -> &str {
+    lookup(language(), text)
+}
+
+fn lookup(lang: Language, text: &str) -> &str {
+    if lang == Language::En {
+        return text;
+    }
+    match TEXTS.iter().find(|(en, ..)| *en == text) {
+        Some((_, de, es, hi)) => match lang {
+            Language::De => de,
+            Language::Es => es,
+            Language::Hi => hi,
+            Language::En => text,
+        },
+        None => text,
+    }
+}
+
+/// A table header: exact matches are translated, and for `Spent (EUR)`-style headers
+/// the part before the parenthesis.
+pub fn tr_header(header: &str) -> String {
+    header_in(language(), header)
+}
+
+fn header_in(lang: Language, header: &str) -> String {
+    let whole = lookup(lang, header);
+    if whole != header {
+        return whole.to_string();
+    }
+    match header.split_once(" (") {
+        Some((head, rest)) => format!("{} ({}", lookup(lang, head), rest),
+        None => header.to_string(),
+    }
+}
+
+/// Labels of summary rows, translated wherever they fill a whole cell.
+const ROW_LABELS: &[&str] = &["Total", "Subtotal", "Total budgeted", "Income", "Savings"];
+
+/// A table cell: summary labels are translated, data is left alone.
+pub fn tr_cell(cell: String) -> String {
+    if ROW_LABELS.contains(&cell.as_str()) {
+        tr(&cell).to_string()
+    } else {
+        cell
+    }
+}
+
+/// Translate `template` and fill its `{}` placeholders with `args` in order.
+pub fn trf(template: &str, args: &[&dyn std::fmt::Display]) -> String {
+    fill(tr(template), args)
+}
+
+fn fill(template: &str, args: &[&dyn std::fmt::Display]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut parts = template.split("{}").peekable();
+    while let Some(part) = parts.next() {
+        out.push_str(part);
+        if parts.peek().is_some() {
+            match args.next() {
+                Some(arg) => out.push_str(&arg.to_string()),
+                None => out.push_str("{}"),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_known_labels_and_keeps_the_rest() {
+        assert_eq!(lookup(Language::De, "Category"), "Kategorie");
+        assert_eq!(lookup(Language::En, "Category"), "Category");
+        assert_eq!(lookup(Language::Es, "Envelope balance"), "Envelope balance");
+        assert_eq!(header_in(Language::De, "Spent (EUR)"), "Ausgegeben (EUR)");
+        assert_eq!(
+            header_in(Language::Es, "Ticker (at 2024-12-31)"),
+            "Símbolo (at 2024-12-31)"
+        );
+        assert_eq!(
+            fill(lookup(Language::De, "Warning: {}"), &[&"over cap"]),
+            "Warnung: over cap"
+        );
+        assert_eq!(
+            fill(
+                lookup(Language::Hi, "Budget set for {} / {} = {} {}"),
+                &[&"2025-08", &"Rent", &1000, &"INR"]
+            ),
+            "2025-08 / Rent का बजट = 1000 INR"
+        );
+    }
+}
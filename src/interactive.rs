@@ -0,0 +1,98 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Terminal prompts used when required flags are omitted.
+
+use anyhow::{Result, bail};
+use dialoguer::{Completion, Confirm, FuzzySelect, Input, theme::ColorfulTheme};
+use std::io::IsTerminal;
+
+/// Prompts are allowed when both stdin and stdout are terminals and neither the global
+/// `--no-input` flag nor `MONEYCLIP_NO_INPUT` is set.
+pub fn enabled(m: &clap::ArgMatches) -> bool {
+    let no_input = m.try_get_one::<bool>("no-input").ok().flatten() == Some(&true)
+        || crate::utils::env_flag("MONEYCLIP_NO_INPUT");
+    !no_input && std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+struct Suggestions<'a>(&'a [String]);
+
+impl Completion for Suggestions<'_> {
+    fn get(&self, input: &str) -> Option<String> {
+        let needle = input.to_lowercase();
+        self.0
+            .iter()
+            .find(|s| s.to_lowercase().starts_with(&needle))
+            .cloned()
+    }
+}
+
+/// Free-text prompt; Tab completes from `completions`, `validate` rejects bad input
+/// in place so the user can retype it.
+pub fn text(
+    prompt: &str,
+    default: Option<&str>,
+    completions: &[String],
+    validate: impl Fn(&str) -> Result<()>,
+) -> Result<String> {
+    let theme = ColorfulTheme::default();
+    let suggestions = Suggestions(completions);
+    let mut input = Input::<String>::with_theme(&theme)
+        .with_prompt(prompt)
+        .completion_with(&suggestions)
+        .validate_with(|s: &String| validate(s.trim()).map_err(|e| e.to_string()));
+    if let Some(d) = default {
+        input = input.default(d.to_string());
+    }
+    Ok(input.interact_text()?.trim().to_string())
+}
+
+/// Fuzzy-searchable pick from `items`; returns the chosen index.
+pub fn choose(prompt: &str, items: &[String], default: Option<usize>) -> Result<usize> {
+    let theme = ColorfulTheme::default();
+    let mut select = FuzzySelect::with_theme(&theme)
+        .with_prompt(prompt)
+        .items(items);
+    if let Some(d) = default {
+        select = select.default(d);
+    }
+    Ok(select.interact()?)
+}
+
+/// Yes/no question.
+pub fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(default)
+        .interact()?)
+}
+
+/// Show what removing `what` takes with it and decide whether to go ahead: always
+/// when nothing else is affected or `--yes` was given, otherwise ask in a terminal
+/// and refuse without one.
+pub fn confirm_removal(
+    m: &clap::ArgMatches,
+    what: &str,
+    impact: &crate::utils::RemovalImpact,
+) -> Result<bool> {
+    if impact.is_empty() {
+        return Ok(true);
+    }
+    println!("Removing {} also affects:", what);
+    for (count, table, effect) in impact {
+        println!("  {} {}: {}", count, table, effect);
+    }
+    if m.get_flag("yes") {
+        return Ok(true);
+    }
+    if !enabled(m) {
+        bail!(
+            "Removing {} affects other records; pass --yes to confirm",
+            what
+        );
+    }
+    confirm(&format!("Remove {}?", what), false)
+}
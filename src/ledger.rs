@@ -0,0 +1,81 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! [`Ledger`], the library's handle on a moneyclip database: it owns the connection
+//! and the database's display settings.
+//!
+//! Lookups memoized for the connection (FX graphs and rates, compiled rule patterns)
+//! are attached to the connection itself when the ledger wraps it, so every function
+//! taking a `&Connection` uses them. Display settings are in force only inside
+//! [`Ledger::with`] and [`Ledger::dispatch`], on the calling thread; two ledgers
+//! never see each other's.
+
+use crate::utils::Settings;
+use anyhow::Result;
+use rusqlite::Connection;
+
+pub struct Ledger {
+    conn: Connection,
+    settings: Settings,
+}
+
+impl Ledger {
+    /// Open the user's database, creating or migrating its schema, and read its
+    /// display settings.
+    pub fn open() -> Result<Self> {
+        let mut ledger = Self::new(crate::db::open_or_init()?);
+        ledger.load_settings()?;
+        Ok(ledger)
+    }
+
+    /// Open the existing database without write access; the schema is not touched.
+    pub fn open_read_only() -> Result<Self> {
+        let mut ledger = Self::new(crate::db::open_read_only()?);
+        ledger.load_settings()?;
+        Ok(ledger)
+    }
+
+    /// Wrap an open connection, e.g. an in-memory database, with empty caches and
+    /// the default settings.
+    pub fn new(mut conn: Connection) -> Self {
+        crate::logging::trace_sql(&mut conn);
+        crate::utils::attach_caches(&conn);
+        Ledger {
+            conn,
+            settings: Settings::DEFAULT,
+        }
+    }
+
+    /// Re-read this database's timezone, precision, period and language settings.
+    pub fn load_settings(&mut self) -> Result<()> {
+        self.settings = Settings::load(&self.conn)?;
+        Ok(())
+    }
+
+    pub fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// The connection, still carrying its caches.
+    pub fn into_connection(self) -> Connection {
+        self.conn
+    }
+
+    /// Run `f` on the connection with this ledger's settings in force.
+    pub fn with<R>(&mut self, f: impl FnOnce(&mut Connection) -> R) -> R {
+        let conn = &mut self.conn;
+        crate::utils::with_settings(self.settings, || f(conn))
+    }
+
+    /// Run a parsed command line, as the `moneyclip` binary does.
+    pub fn dispatch(&mut self, matches: &clap::ArgMatches) -> Result<()> {
+        self.with(|conn| crate::commands::dispatch(conn, matches))
+    }
+}
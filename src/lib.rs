@@ -6,6 +6,16 @@
 
 pub mod cli;
 pub mod commands;
+pub mod config_file;
 pub mod db;
+mod help_examples;
+pub mod i18n;
+pub mod interactive;
+pub mod ledger;
+pub mod logging;
 pub mod models;
+pub mod progress;
+pub mod providers;
 pub mod utils;
+
+pub use ledger::Ledger;
@@ -0,0 +1,149 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Diagnostic logging with `tracing`, off unless `--log-level` or `MONEYCLIP_LOG` asks
+//! for it. Events go to stderr, or with `--log-file` to a daily file under the data dir
+//! (`logs/moneyclip.log.YYYY-MM-DD`, the last week kept). Besides per-module targets,
+//! SQL statements log to `moneyclip::sql`, HTTP requests to `moneyclip::http` and
+//! cache lookups to `moneyclip::cache`, so e.g. `MONEYCLIP_LOG=moneyclip::sql=debug`
+//! times only the queries.
+
+use anyhow::{Context, Result, anyhow};
+use rusqlite::Connection;
+use std::time::{Duration, Instant};
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable with a level or `tracing` filter directives.
+pub const LOG_ENV: &str = "MONEYCLIP_LOG";
+
+const KEEP_LOG_FILES: usize = 7;
+
+/// Install the process-wide subscriber. `level` (from `--log-level`) wins over
+/// [`LOG_ENV`]; a bare level applies to moneyclip's own events, with dependencies
+/// kept at warnings. Does nothing when neither is set.
+pub fn init(level: Option<&str>, to_file: bool) -> Result<()> {
+    let directives = match level {
+        Some(level) => level.to_string(),
+        None => match std::env::var(LOG_ENV) {
+            Ok(v) if !v.trim().is_empty() => v,
+            _ => return Ok(()),
+        },
+    };
+    let filter = filter(&directives)?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(true);
+    let installed = if to_file {
+        let dir = crate::db::data_dir()?.join("logs");
+        let appender = tracing_appender::rolling::Builder::new()
+            .rotation(tracing_appender::rolling::Rotation::DAILY)
+            .filename_prefix("moneyclip.log")
+            .max_log_files(KEEP_LOG_FILES)
+            .build(&dir)
+            .with_context(|| format!("Open log file in {}", dir.display()))?;
+        builder.with_ansi(false).with_writer(appender).try_init()
+    } else {
+        builder.with_writer(std::io::stderr).try_init()
+    };
+    installed.map_err(|err| anyhow!("Failed to start logging: {err}"))
+}
+
+fn filter(directives: &str) -> Result<EnvFilter> {
+    let directives = directives.trim();
+    let expanded = match directives.to_lowercase().as_str() {
+        "off" => "off".to_string(),
+        level @ ("error" | "warn" | "info" | "debug" | "trace") => {
+            format!("warn,moneyclip={level}")
+        }
+        _ => directives.to_string(),
+    };
+    EnvFilter::try_new(&expanded)
+        .with_context(|| format!("Invalid log level or filter '{}'", directives))
+}
+
+/// Log every statement `conn` runs, with its duration, when `moneyclip::sql` debug
+/// events are enabled.
+pub(crate) fn trace_sql(conn: &mut Connection) {
+    if tracing::enabled!(target: "moneyclip::sql", tracing::Level::DEBUG) {
+        conn.profile(Some(log_statement));
+    }
+}
+
+fn log_statement(sql: &str, elapsed: Duration) {
+    tracing::debug!(
+        target: "moneyclip::sql",
+        elapsed_us = elapsed.as_micros() as u64,
+        "{}",
+        sql.split_whitespace().collect::<Vec<_>>().join(" ")
+    );
+}
+
+/// `send` for HTTP requests, logging the URL, status and time taken.
+pub(crate) trait SendTraced {
+    fn send_traced(self) -> reqwest::Result<reqwest::blocking::Response>;
+}
+
+impl SendTraced for reqwest::blocking::RequestBuilder {
+    fn send_traced(self) -> reqwest::Result<reqwest::blocking::Response> {
+        let started = Instant::now();
+        let result = self.send();
+        match &result {
+            Ok(resp) => log_response(resp.url(), resp.status(), started),
+            Err(err) => log_failure(err, started),
+        }
+        result
+    }
+}
+
+/// [`SendTraced`] for the non-blocking client.
+#[cfg(feature = "async")]
+pub(crate) async fn send_async(
+    request: reqwest::RequestBuilder,
+) -> reqwest::Result<reqwest::Response> {
+    let started = Instant::now();
+    let result = request.send().await;
+    match &result {
+        Ok(resp) => log_response(resp.url(), resp.status(), started),
+        Err(err) => log_failure(err, started),
+    }
+    result
+}
+
+fn log_response(url: &reqwest::Url, status: reqwest::StatusCode, started: Instant) {
+    tracing::debug!(
+        target: "moneyclip::http",
+        %url,
+        status = status.as_u16(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "HTTP request"
+    );
+}
+
+fn log_failure(err: &reqwest::Error, started: Instant) {
+    tracing::warn!(
+        target: "moneyclip::http",
+        url = err.url().map(|u| u.as_str()).unwrap_or(""),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        error = %err,
+        "HTTP request failed"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_levels_scope_to_moneyclip_and_filters_pass_through() {
+        assert_eq!(filter("debug").unwrap().to_string(), "moneyclip=debug,warn");
+        assert_eq!(filter("OFF").unwrap().to_string(), "off");
+        assert_eq!(
+            filter("moneyclip::sql=debug").unwrap().to_string(),
+            "moneyclip::sql=debug"
+        );
+        assert!(filter("moneyclip=loud").is_err());
+    }
+}
@@ -4,36 +4,41 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 
-use moneyclip::{cli, commands, db};
+use moneyclip::{Ledger, cli, config_file::ConfigFile};
 
 fn main() -> Result<()> {
-    let cli = cli::build_cli();
-    let matches = cli.get_matches();
+    // A broken file must not lock users out of `config edit`.
+    let config = ConfigFile::load().unwrap_or_else(|err| {
+        eprintln!("Warning: {:#}; ignoring config.toml", err);
+        ConfigFile::default()
+    });
+    config.install();
+    let args = cli::with_env_json(config.with_defaults(std::env::args_os().collect())?);
+    let matches = cli::build_cli().get_matches_from(args);
+    moneyclip::logging::init(
+        matches.get_one::<String>("log-level").map(String::as_str),
+        matches.get_flag("log-file"),
+    )?;
 
-    let mut conn = db::open_or_init()?;
-
-    match matches.subcommand() {
-        Some(("init", _)) => {
-            println!("Database initialized at {}", db::db_path()?.display());
-        }
-        Some(("account", sub)) => commands::accounts::handle(&conn, sub)?,
-        Some(("category", sub)) => commands::categories::handle(&conn, sub)?,
-        Some(("tx", sub)) => commands::transactions::handle(&conn, sub)?,
-        Some(("budget", sub)) => commands::budgets::handle(&conn, sub)?,
-        Some(("report", sub)) => commands::reports::handle(&conn, sub)?,
-        Some(("portfolio", sub)) => commands::portfolio::handle(&mut conn, sub)?,
-        Some(("import", sub)) => commands::importer::handle(&mut conn, sub)?,
-        Some(("export", sub)) => commands::exporter::handle(&conn, sub)?,
-        Some(("fx", sub)) => commands::fx::handle(&mut conn, sub)?,
-        Some(("doctor", _)) => commands::doctor::handle(&conn)?,
-        Some(("envelope", sub)) => commands::envelopes::handle(&conn, sub)?,
-        Some(("rules", sub)) => commands::rules::handle(&conn, sub)?,
-        _ => {
-            cli::build_cli().print_help()?;
-            println!();
+    let mut ledger = if matches.get_flag("read-only") {
+        if cli::command_mutates(&matches) {
+            bail!(
+                "`{}` writes to the database and cannot run with --read-only",
+                cli::command_path(&matches).0
+            );
         }
+        Ledger::open_read_only()?
+    } else {
+        Ledger::open()?
+    };
+
+    ledger.dispatch(&matches)?;
+    let code = moneyclip::utils::exit_code();
+    if code != 0 {
+        drop(ledger);
+        std::process::exit(code);
     }
-    Ok(())
+    config.run_hook(&cli::command_path(&matches).0)
 }
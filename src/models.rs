@@ -62,3 +62,339 @@ pub struct Trade {
     pub side: String,
     pub note: Option<String>,
 }
+
+/// Version of the row shapes printed by `--json` and `--jsonl`; every row carries it as
+/// `schema_version`. Field names below are a contract with scripts: renaming, removing
+/// or retyping a field bumps the version, adding one does not. Amounts are decimal
+/// strings formatted like the table, without currency symbols.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// `report balances`: one row per account and currency its transactions use (the
+/// account's own first), or one per account converted with `--base` or `--currency`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceRow {
+    pub account: String,
+    pub currency: String,
+    pub balance: String,
+}
+
+/// `report balances --by-group`: one account, then a `Subtotal` row per group and a
+/// final `Total` row with an empty account.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupBalanceRow {
+    pub group: String,
+    pub account: String,
+    pub balance: String,
+}
+
+impl GroupBalanceRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.group, self.account, self.balance]
+    }
+}
+
+/// `report cashflow`: income and expenses of one month, or one week with `--by week`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CashflowRow {
+    pub period: String,
+    pub income: String,
+    pub expense: String,
+}
+
+impl CashflowRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.period, self.income, self.expense]
+    }
+}
+
+/// `report spend-by-category`: outflows of one category.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategorySpendRow {
+    pub category: String,
+    pub spent: String,
+}
+
+impl CategorySpendRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.category, self.spent]
+    }
+}
+
+/// `report fees`: cost per source (`Bank fees`, `Interest`, `Trade fees`) and account,
+/// ending with a `Total` row with an empty account.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeRow {
+    pub source: String,
+    pub account: String,
+    pub cost: String,
+}
+
+impl FeeRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.source, self.account, self.cost]
+    }
+}
+
+/// `budget list`: one stored budget in its own currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetRow {
+    pub month: String,
+    pub category: String,
+    pub amount: String,
+    pub currency: String,
+}
+
+impl BudgetRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.month, self.category, self.amount, self.currency]
+    }
+}
+
+/// `budget report`: budget against spending of one category for the month, both in
+/// `currency`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetReportRow {
+    pub category: String,
+    pub budget: String,
+    pub spent: String,
+    pub currency: String,
+}
+
+impl BudgetReportRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.category, self.budget, self.spent, self.currency]
+    }
+}
+
+/// `envelope status`: one category's envelope; `daily_burn` and `projected` are set
+/// only with `--as-of`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvelopeStatusRow {
+    pub category: String,
+    pub carryover: String,
+    pub budget: String,
+    pub spent: String,
+    pub available: String,
+    pub daily_burn: Option<String>,
+    pub projected: Option<String>,
+}
+
+impl EnvelopeStatusRow {
+    pub fn cells(self) -> Vec<String> {
+        let mut cells = vec![
+            self.category,
+            self.carryover,
+            self.budget,
+            self.spent,
+            self.available,
+        ];
+        cells.extend(self.daily_burn);
+        cells.extend(self.projected);
+        cells
+    }
+}
+
+/// `envelope history`: one month of one envelope, in the base currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvelopeMonthRow {
+    pub month: String,
+    pub carryover: String,
+    pub funded: String,
+    pub spent: String,
+    pub available: String,
+}
+
+impl EnvelopeMonthRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![
+            self.month,
+            self.carryover,
+            self.funded,
+            self.spent,
+            self.available,
+        ]
+    }
+}
+
+/// `goal status`: progress of one goal; `per_month` is `None` without a target date
+/// or once the goal is reached, and `"overdue"` after its date.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GoalStatusRow {
+    pub goal: String,
+    pub envelope: String,
+    pub saved: String,
+    pub target: String,
+    pub remaining: String,
+    pub progress_percent: String,
+    pub by: Option<String>,
+    pub per_month: Option<String>,
+}
+
+impl GoalStatusRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![
+            self.goal,
+            self.envelope,
+            self.saved,
+            self.target,
+            self.remaining,
+            format!("{}%", self.progress_percent),
+            self.by.unwrap_or_default(),
+            self.per_month.unwrap_or_default(),
+        ]
+    }
+}
+
+/// `account interest list`: one interest-bearing account.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InterestRow {
+    pub account: String,
+    pub apy_percent: String,
+    pub compounding: String,
+    pub category: Option<String>,
+    pub since: String,
+    pub posted_through: Option<String>,
+}
+
+impl InterestRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![
+            self.account,
+            self.apy_percent,
+            self.compounding,
+            self.category.unwrap_or_default(),
+            self.since,
+            self.posted_through.unwrap_or_default(),
+        ]
+    }
+}
+
+/// `plan list`: one planned purchase and the envelope or goal funding it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlanRow {
+    pub item: String,
+    pub cost: String,
+    pub currency: String,
+    pub month: Option<String>,
+    pub funded_from: Option<String>,
+}
+
+impl PlanRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![
+            self.item,
+            self.cost,
+            self.currency,
+            self.month.unwrap_or_default(),
+            self.funded_from.unwrap_or_default(),
+        ]
+    }
+}
+
+/// `portfolio alert list`: one price alert.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceAlertRow {
+    pub id: i64,
+    pub ticker: String,
+    pub above: Option<String>,
+    pub below: Option<String>,
+    pub state: Option<String>,
+    pub last_fired: Option<String>,
+}
+
+impl PriceAlertRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.ticker,
+            self.above.unwrap_or_default(),
+            self.below.unwrap_or_default(),
+            self.state.unwrap_or_default(),
+            self.last_fired.unwrap_or_default(),
+        ]
+    }
+}
+
+/// `trip list`: one trip's date range and project tag.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TripRow {
+    pub trip: String,
+    pub from: String,
+    pub to: String,
+    pub tag: Option<String>,
+}
+
+impl TripRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.trip, self.from, self.to, self.tag.unwrap_or_default()]
+    }
+}
+
+/// `budget scenario list`: one scenario and how many categories it sets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScenarioRow {
+    pub scenario: String,
+    pub month: String,
+    pub categories: i64,
+}
+
+impl ScenarioRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.scenario, self.month, self.categories.to_string()]
+    }
+}
+
+/// `invoice list`: one invoice; `status` is `draft`, `sent` or `paid`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvoiceRow {
+    pub number: String,
+    pub client: String,
+    pub issued: String,
+    pub due: String,
+    pub amount: String,
+    pub currency: String,
+    pub status: String,
+    pub paid_on: Option<String>,
+}
+
+impl InvoiceRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![
+            self.number,
+            self.client,
+            self.issued,
+            self.due,
+            self.amount,
+            self.currency,
+            self.status,
+            self.paid_on.unwrap_or_default(),
+        ]
+    }
+}
+
+/// `cpi list`: the index value of one month.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CpiRow {
+    pub month: String,
+    pub value: String,
+}
+
+impl CpiRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.month, self.value]
+    }
+}
+
+/// `asset alias list`: an old ticker symbol and the asset it now resolves to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetAliasRow {
+    pub old_symbol: String,
+    pub ticker: String,
+    pub renamed_on: String,
+}
+
+impl AssetAliasRow {
+    pub fn cells(self) -> Vec<String> {
+        vec![self.old_symbol, self.ticker, self.renamed_on]
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Spinners and progress bars for network calls and large imports. They are drawn on
+//! stderr and only when stdout is a terminal, so piped or captured output stays clean;
+//! the global `--quiet` flag hides them too.
+
+use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Imports with fewer rows finish too quickly to need a bar.
+pub const LARGE_IMPORT_ROWS: usize = 1000;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Progress is shown when stdout is a terminal and `--quiet` was not given.
+pub fn enabled() -> bool {
+    !QUIET.load(Ordering::Relaxed) && std::io::stdout().is_terminal()
+}
+
+/// A spinner with `message` for a call of unknown length; cleared when dropped.
+pub fn spinner(message: impl Into<String>) -> ProgressBar {
+    if !enabled() {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new_spinner()
+        .with_style(ProgressStyle::with_template("{spinner} {msg} ({elapsed})").unwrap())
+        .with_message(message.into())
+        .with_finish(ProgressFinish::AndClear);
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb
+}
+
+/// A bar counting `len` steps of `message`; cleared when dropped.
+pub fn bar(len: usize, message: impl Into<String>) -> ProgressBar {
+    if !enabled() {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(len as u64)
+        .with_style(
+            ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} ({eta} left)")
+                .unwrap()
+                .progress_chars("=> "),
+        )
+        .with_message(message.into())
+        .with_finish(ProgressFinish::AndClear)
+}
@@ -0,0 +1,181 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Market data providers: Yahoo Finance quotes and Frankfurter (ECB) rates. The CLI
+//! uses the blocking traits; with the `async` build feature [`asynchronous`] offers the
+//! same providers on a non-blocking client, so `sync` fetches both at once.
+
+use crate::logging::SendTraced;
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Rates by date (`YYYY-MM-DD`), then by quote currency.
+pub type RateSeries = HashMap<String, HashMap<String, f64>>;
+
+/// One Yahoo Finance quote.
+#[derive(Debug, Deserialize)]
+pub struct Quote {
+    #[serde(rename = "regularMarketPrice")]
+    pub regular_market_price: Option<f64>,
+    pub symbol: Option<String>,
+    pub currency: Option<String>,
+    #[serde(rename = "longName")]
+    pub long_name: Option<String>,
+    #[serde(rename = "shortName")]
+    pub short_name: Option<String>,
+}
+
+pub trait PriceProvider {
+    /// Latest quotes for `symbols`; symbols the provider does not know are left out.
+    fn quotes(&self, symbols: &[&str]) -> Result<Vec<Quote>>;
+}
+
+pub trait FxProvider {
+    /// Rates of `base` against `quotes` for each published day from `start` to `end`.
+    fn rates(
+        &self,
+        base: &str,
+        quotes: &[String],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<RateSeries>;
+}
+
+/// Yahoo Finance quote API.
+pub struct Yahoo;
+
+/// Frankfurter, a public API around the ECB reference rates.
+pub struct Frankfurter;
+
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+struct YahooResponse {
+    quoteResponse: QuoteResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuoteResponse {
+    result: Vec<Quote>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Series {
+    rates: RateSeries,
+}
+
+impl Yahoo {
+    fn url(symbols: &[&str]) -> String {
+        format!(
+            "https://query1.finance.yahoo.com/v7/finance/quote?symbols={}",
+            symbols.join(",")
+        )
+    }
+}
+
+impl Frankfurter {
+    fn url(base: &str, quotes: &[String], start: NaiveDate, end: NaiveDate) -> String {
+        format!(
+            "https://api.frankfurter.dev/{start}..{end}?from={base}&to={}",
+            quotes.join(",")
+        )
+    }
+}
+
+impl PriceProvider for Yahoo {
+    fn quotes(&self, symbols: &[&str]) -> Result<Vec<Quote>> {
+        let resp = crate::utils::http_client()?
+            .get(Self::url(symbols))
+            .send_traced()?
+            .error_for_status()?;
+        let yr: YahooResponse = resp.json()?;
+        Ok(yr.quoteResponse.result)
+    }
+}
+
+impl FxProvider for Frankfurter {
+    fn rates(
+        &self,
+        base: &str,
+        quotes: &[String],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<RateSeries> {
+        let resp = crate::utils::http_client()?
+            .get(Self::url(base, quotes, start, end))
+            .send_traced()?
+            .error_for_status()?;
+        Ok(resp.json::<Series>()?.rates)
+    }
+}
+
+/// Non-blocking variants of the provider traits, for the tokio-based `sync`.
+#[cfg(feature = "async")]
+pub mod asynchronous {
+    use super::{Frankfurter, Quote, RateSeries, Series, Yahoo, YahooResponse};
+    use crate::logging::send_async;
+    use anyhow::{Result, anyhow};
+    use chrono::NaiveDate;
+    use once_cell::sync::OnceCell;
+    use std::future::Future;
+
+    pub trait AsyncPriceProvider {
+        /// Latest quotes for `symbols`; symbols the provider does not know are left out.
+        fn quotes(&self, symbols: &[&str]) -> impl Future<Output = Result<Vec<Quote>>> + Send;
+    }
+
+    pub trait AsyncFxProvider {
+        /// Rates of `base` against `quotes` for each published day from `start` to `end`.
+        fn rates(
+            &self,
+            base: &str,
+            quotes: &[String],
+            start: NaiveDate,
+            end: NaiveDate,
+        ) -> impl Future<Output = Result<RateSeries>> + Send;
+    }
+
+    static CLIENT: OnceCell<reqwest::Client> = OnceCell::new();
+
+    /// Shared non-blocking client with the same timeout and user agent as
+    /// [`crate::utils::http_client`].
+    pub fn client() -> Result<&'static reqwest::Client> {
+        crate::utils::ensure_online()?;
+        CLIENT.get_or_try_init(|| {
+            reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(15))
+                .user_agent(crate::utils::UA)
+                .build()
+                .map_err(|err| anyhow!("Failed to build HTTP client: {err}"))
+        })
+    }
+
+    impl AsyncPriceProvider for Yahoo {
+        async fn quotes(&self, symbols: &[&str]) -> Result<Vec<Quote>> {
+            let resp = send_async(client()?.get(Self::url(symbols)))
+                .await?
+                .error_for_status()?;
+            let yr: YahooResponse = resp.json().await?;
+            Ok(yr.quoteResponse.result)
+        }
+    }
+
+    impl AsyncFxProvider for Frankfurter {
+        async fn rates(
+            &self,
+            base: &str,
+            quotes: &[String],
+            start: NaiveDate,
+            end: NaiveDate,
+        ) -> Result<RateSeries> {
+            let resp = send_async(client()?.get(Self::url(base, quotes, start, end)))
+                .await?
+                .error_for_status()?;
+            Ok(resp.json::<Series>().await?.rates)
+        }
+    }
+}
@@ -4,21 +4,27 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use anyhow::{Context, Result, anyhow, ensure};
+use anyhow::{Context, Result, anyhow, bail, ensure};
 use chrono::NaiveDate;
-use comfy_table::{Cell, Table, presets::UTF8_FULL};
+use comfy_table::{
+    Cell, Table,
+    presets::{ASCII_MARKDOWN, UTF8_FULL},
+};
 use rusqlite::{Connection, OptionalExtension, ffi, params};
 use rust_decimal::Decimal;
 use std::{
     borrow::Cow,
     collections::{BinaryHeap, HashMap, VecDeque, hash_map::Entry},
     io::{self, Write},
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicI32, Ordering},
+    },
 };
 
-use once_cell::sync::{Lazy, OnceCell};
+use once_cell::sync::OnceCell;
 
-const UA: &str = concat!(
+pub(crate) const UA: &str = concat!(
     "moneyclip/",
     env!("CARGO_PKG_VERSION"),
     " (+https://github.com/alphavelocity/moneyclip)"
@@ -26,7 +32,30 @@ const UA: &str = concat!(
 
 static HTTP_CLIENT: OnceCell<reqwest::blocking::Client> = OnceCell::new();
 
+/// Whether environment variable `name` is set to 1, true, yes or on.
+pub fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|v| {
+        matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
+}
+
+/// `MONEYCLIP_OFFLINE`: nothing goes online, whatever the provider settings say.
+pub fn offline() -> bool {
+    env_flag("MONEYCLIP_OFFLINE")
+}
+
+pub(crate) fn ensure_online() -> Result<()> {
+    if offline() {
+        bail!("Network access is disabled by MONEYCLIP_OFFLINE");
+    }
+    Ok(())
+}
+
 pub fn http_client() -> Result<&'static reqwest::blocking::Client> {
+    ensure_online()?;
     HTTP_CLIENT.get_or_try_init(|| {
         reqwest::blocking::Client::builder()
             .timeout(std::time::Duration::from_secs(15))
@@ -36,15 +65,149 @@ pub fn http_client() -> Result<&'static reqwest::blocking::Client> {
     })
 }
 
+pub fn parse_timezone(name: &str) -> Result<chrono_tz::Tz> {
+    name.trim().parse::<chrono_tz::Tz>().map_err(|_| {
+        anyhow!(
+            "Unknown time zone '{}'; use an IANA name like Europe/Berlin",
+            name.trim()
+        )
+    })
+}
+
+/// Zone from the `timezone` setting; `None` follows the system clock.
+fn load_timezone(conn: &Connection) -> Result<Option<chrono_tz::Tz>> {
+    get_setting(conn, "timezone")?
+        .map(|name| parse_timezone(&name))
+        .transpose()
+}
+
+/// Current time in the configured zone (or the system's).
+pub fn now_local() -> chrono::DateTime<chrono::FixedOffset> {
+    let now = chrono::Utc::now();
+    match settings().timezone {
+        Some(tz) => now.with_timezone(&tz).fixed_offset(),
+        None => now.with_timezone(&chrono::Local).fixed_offset(),
+    }
+}
+
+/// Calendar day in the configured zone; "today", relative dates and `this` month
+/// all resolve against it.
+pub fn today() -> NaiveDate {
+    now_local().date_naive()
+}
+
+/// An RFC 3339 timestamp shown in the configured zone as `YYYY-MM-DD HH:MM`;
+/// anything else (e.g. a bare date) is returned unchanged.
+pub fn display_timestamp(raw: &str) -> String {
+    display_timestamp_in(raw, settings().timezone)
+}
+
+fn display_timestamp_in(raw: &str, tz: Option<chrono_tz::Tz>) -> String {
+    let Ok(ts) = chrono::DateTime::parse_from_rfc3339(raw.trim()) else {
+        return raw.to_string();
+    };
+    let local = match tz {
+        Some(tz) => ts.with_timezone(&tz).naive_local(),
+        None => ts.with_timezone(&chrono::Local).naive_local(),
+    };
+    local.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Parse `YYYY-MM-DD` or a relative form: `today`, `yesterday`, `tomorrow`,
+/// `last <weekday>`, `Nd ago` / `N days ago`, `Nw ago` / `N weeks ago`.
 pub fn parse_date(s: &str) -> Result<NaiveDate> {
-    NaiveDate::parse_from_str(s, "%Y-%m-%d")
-        .with_context(|| format!("Invalid date '{}', expected YYYY-MM-DD", s))
+    parse_date_on(s, today())
 }
 
+pub fn parse_date_on(s: &str, today: NaiveDate) -> Result<NaiveDate> {
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(d);
+    }
+    relative_date(&s.trim().to_lowercase(), today).with_context(|| {
+        format!(
+            "Invalid date '{}', expected YYYY-MM-DD, today, yesterday, last <weekday> or Nd ago",
+            s
+        )
+    })
+}
+
+fn relative_date(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    use chrono::{Datelike, Days, Weekday};
+
+    match s {
+        "today" => return Some(today),
+        "yesterday" => return today.checked_sub_days(Days::new(1)),
+        "tomorrow" => return today.checked_add_days(Days::new(1)),
+        _ => {}
+    }
+    if let Some(day) = s.strip_prefix("last ") {
+        let wd: Weekday = day.trim().parse().ok()?;
+        let back = (7 + today.weekday().num_days_from_monday() - wd.num_days_from_monday()) % 7;
+        let back = if back == 0 { 7 } else { back };
+        return today.checked_sub_days(Days::new(back.into()));
+    }
+    let span = s.strip_suffix("ago")?.trim();
+    let split = span.find(|c: char| !c.is_ascii_digit())?;
+    let n: u64 = span[..split].parse().ok()?;
+    let days = match span[split..].trim() {
+        "d" | "day" | "days" => n,
+        "w" | "week" | "weeks" => n.checked_mul(7)?,
+        _ => return None,
+    };
+    today.checked_sub_days(Days::new(days))
+}
+
+/// Validate a month and normalize it to `YYYY-MM`; also accepts `this`/`current`
+/// and `last`/`previous` relative to today.
 pub fn parse_month(s: &str) -> Result<String> {
-    chrono::NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d")
-        .with_context(|| format!("Invalid month '{}', expected YYYY-MM", s))?;
-    Ok(s.to_string())
+    parse_month_on(s, today())
+}
+
+pub fn parse_month_on(s: &str, today: NaiveDate) -> Result<String> {
+    use chrono::{Datelike, Months};
+
+    // With a statement-style month start, "this" is the period today falls in.
+    let first = month_end(&periods().month_of(today))?.with_day(1).unwrap();
+    let date = match s.trim().to_lowercase().as_str() {
+        "this" | "current" => first,
+        "last" | "previous" => first - Months::new(1),
+        _ => chrono::NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d")
+            .with_context(|| format!("Invalid month '{}', expected YYYY-MM, this or last", s))?,
+    };
+    Ok(date.format("%Y-%m").to_string())
+}
+
+/// A calendar quarter as `YYYY-Qn` (also `YYYYQn`, `this` or `last`) and its first and
+/// last day. Tax quarters follow the calendar, not `month_start_day`.
+pub fn parse_quarter(s: &str) -> Result<(String, NaiveDate, NaiveDate)> {
+    parse_quarter_on(s, today())
+}
+
+pub fn parse_quarter_on(s: &str, today: NaiveDate) -> Result<(String, NaiveDate, NaiveDate)> {
+    use chrono::{Datelike, Months};
+
+    let current = (today.year(), (today.month() - 1) / 3 + 1);
+    let (year, q) = match s.trim().to_lowercase().as_str() {
+        "this" | "current" => current,
+        "last" | "previous" => match current {
+            (y, 1) => (y - 1, 4),
+            (y, q) => (y, q - 1),
+        },
+        other => {
+            let parsed = other
+                .split_once('q')
+                .and_then(|(y, q)| Some((y.trim_end_matches('-').parse::<i32>().ok()?, q)))
+                .and_then(|(y, q)| Some((y, q.parse::<u32>().ok()?)))
+                .filter(|(_, q)| (1..=4).contains(q));
+            parsed.with_context(|| {
+                format!("Invalid quarter '{}', expected YYYY-Qn, this or last", s)
+            })?
+        }
+    };
+    let start = NaiveDate::from_ymd_opt(year, (q - 1) * 3 + 1, 1)
+        .with_context(|| format!("Invalid quarter '{}'", s))?;
+    let end = start + Months::new(3) - chrono::Days::new(1);
+    Ok((format!("{}-Q{}", year, q), start, end))
 }
 
 pub fn parse_decimal(s: &str) -> Result<Decimal> {
@@ -52,69 +215,993 @@ pub fn parse_decimal(s: &str) -> Result<Decimal> {
         .with_context(|| format!("Invalid decimal '{}'", s))
 }
 
-#[allow(dead_code)]
+/// Parse a user-entered amount, allowing simple arithmetic such as `12.50+3.99`,
+/// `-3*4.25` or `(120-15)/3` so bills can be split without a calculator. Results of
+/// arithmetic are rounded to cents; use [`parse_amount_in`] when the currency is known.
+pub fn parse_amount(s: &str) -> Result<Decimal> {
+    parse_amount_dp(s, 2)
+}
+
+/// [`parse_amount`] with arithmetic rounded to the minor units of `ccy`, so `100/3`
+/// stores `33.33` (or `33` in JPY) rather than 28 digits.
+pub fn parse_amount_in(s: &str, ccy: &str) -> Result<Decimal> {
+    parse_amount_dp(s, minor_units(ccy))
+}
+
+fn parse_amount_dp(s: &str, dp: u32) -> Result<Decimal> {
+    if let Ok(d) = s.trim().parse::<Decimal>() {
+        return Ok(d);
+    }
+    let mut expr = AmountExpr {
+        src: s.as_bytes(),
+        pos: 0,
+    };
+    let value = expr
+        .sum()
+        .and_then(|v| {
+            expr.skip_ws();
+            ensure!(
+                expr.pos == expr.src.len(),
+                "unexpected '{}'",
+                &s[expr.pos..]
+            );
+            Ok(v)
+        })
+        .with_context(|| format!("Invalid amount '{}'", s))?;
+    Ok(value.round_dp(dp).normalize())
+}
+
+struct AmountExpr<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl AmountExpr<'_> {
+    fn skip_ws(&mut self) {
+        while self
+            .src
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, op: u8) -> bool {
+        self.skip_ws();
+        if self.src.get(self.pos) == Some(&op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn sum(&mut self) -> Result<Decimal> {
+        let mut acc = self.product()?;
+        loop {
+            if self.eat(b'+') {
+                acc = acc.checked_add(self.product()?).context("overflow")?;
+            } else if self.eat(b'-') {
+                acc = acc.checked_sub(self.product()?).context("overflow")?;
+            } else {
+                return Ok(acc);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Decimal> {
+        let mut acc = self.unary()?;
+        loop {
+            if self.eat(b'*') {
+                acc = acc.checked_mul(self.unary()?).context("overflow")?;
+            } else if self.eat(b'/') {
+                let rhs = self.unary()?;
+                ensure!(!rhs.is_zero(), "division by zero");
+                acc = acc.checked_div(rhs).context("overflow")?;
+            } else {
+                return Ok(acc);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Decimal> {
+        if self.eat(b'-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat(b'+') {
+            return self.unary();
+        }
+        if self.eat(b'(') {
+            let inner = self.sum()?;
+            ensure!(self.eat(b')'), "missing ')'");
+            return Ok(inner);
+        }
+        self.skip_ws();
+        let start = self.pos;
+        while self
+            .src
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_digit() || *b == b'.')
+        {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.src[start..self.pos])?;
+        ensure!(
+            !digits.is_empty(),
+            "expected a number at position {}",
+            start + 1
+        );
+        digits
+            .parse::<Decimal>()
+            .with_context(|| format!("bad number '{}'", digits))
+    }
+}
+
+/// How displayed figures are rounded (`rounding` setting).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// 0.125 -> 0.13 (default).
+    HalfUp,
+    /// Banker's rounding: 0.125 -> 0.12, 0.135 -> 0.14.
+    HalfEven,
+}
+
+impl Rounding {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "half-up" => Ok(Self::HalfUp),
+            "half-even" | "bankers" => Ok(Self::HalfEven),
+            other => bail!("Unknown rounding '{}' (use half-up|half-even)", other),
+        }
+    }
+
+    fn strategy(self) -> rust_decimal::RoundingStrategy {
+        match self {
+            Self::HalfUp => rust_decimal::RoundingStrategy::MidpointAwayFromZero,
+            Self::HalfEven => rust_decimal::RoundingStrategy::MidpointNearestEven,
+        }
+    }
+}
+
+/// Display precision from the `money_dp`, `quantity_dp`, `percent_dp`, `rounding` and
+/// `currency_symbols` settings, overridable per run with `--money-dp` / `--qty-dp` /
+/// `--symbols`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Precision {
+    /// Most decimals for money; currencies without minor units always show none.
+    pub money: u32,
+    pub quantity: u32,
+    pub percent: u32,
+    pub rounding: Rounding,
+    /// Amounts as `€12.50` instead of bare numbers.
+    pub symbols: bool,
+}
+
+impl Precision {
+    pub const DEFAULT: Precision = Precision {
+        money: 2,
+        quantity: 4,
+        percent: 1,
+        rounding: Rounding::HalfUp,
+        symbols: false,
+    };
+}
+
+pub fn precision() -> Precision {
+    settings().precision
+}
+
+/// Bounds for each precision setting, checked by `config set` and the flags.
+pub fn check_precision(key: &str, value: u32) -> Result<u32> {
+    let (lo, hi) = match key {
+        "money_dp" => (0, 2),
+        "quantity_dp" => (4, 8),
+        _ => (0, 4),
+    };
+    ensure!(
+        (lo..=hi).contains(&value),
+        "{} must be between {} and {}",
+        key,
+        lo,
+        hi
+    );
+    Ok(value)
+}
+
+fn load_precision(conn: &Connection) -> Result<Precision> {
+    let mut p = Precision::DEFAULT;
+    for (key, slot) in [
+        ("money_dp", &mut p.money),
+        ("quantity_dp", &mut p.quantity),
+        ("percent_dp", &mut p.percent),
+    ] {
+        if let Some(v) = get_setting(conn, key)? {
+            let n = v
+                .trim()
+                .parse::<u32>()
+                .with_context(|| format!("Setting {} is not a number: '{}'", key, v))?;
+            *slot = check_precision(key, n)?;
+        }
+    }
+    if let Some(v) = get_setting(conn, "rounding")? {
+        p.rounding = Rounding::parse(&v)?;
+    }
+    if let Some(v) = get_setting(conn, "currency_symbols")? {
+        p.symbols = v.trim() == "true";
+    }
+    Ok(p)
+}
+
+/// How reports cut time into periods, from the `week_start` and `month_start_day`
+/// settings. With a month start of 25, month `2025-08` runs from 25 August through
+/// 24 September.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Periods {
+    pub week_start: chrono::Weekday,
+    pub month_start_day: u32,
+}
+
+impl Periods {
+    pub const DEFAULT: Periods = Periods {
+        week_start: chrono::Weekday::Mon,
+        month_start_day: 1,
+    };
+
+    /// The month (YYYY-MM) whose period contains `date`.
+    pub fn month_of(&self, date: NaiveDate) -> String {
+        use chrono::Datelike;
+        let month = if date.day() >= self.month_start_day {
+            date
+        } else {
+            date - chrono::Months::new(1)
+        };
+        month.format("%Y-%m").to_string()
+    }
+
+    /// First and last day of `month`'s period.
+    pub fn month_range(&self, month: &str) -> Result<(NaiveDate, NaiveDate)> {
+        use chrono::Datelike;
+        let start = month_end(month)?
+            .with_day(self.month_start_day)
+            .with_context(|| format!("Invalid month '{}'", month))?;
+        let next = start + chrono::Months::new(1);
+        Ok((start, next.pred_opt().unwrap()))
+    }
+
+    /// First day of the week containing `date`.
+    pub fn week_of(&self, date: NaiveDate) -> NaiveDate {
+        use chrono::Datelike;
+        let back = (7 + date.weekday().num_days_from_monday()
+            - self.week_start.num_days_from_monday())
+            % 7;
+        date - chrono::Days::new(back.into())
+    }
+}
+
+pub fn periods() -> Periods {
+    settings().periods
+}
+
+/// `monday` or `sunday`, as stored in `week_start`.
+pub fn parse_week_start(s: &str) -> Result<chrono::Weekday> {
+    match s.trim().to_lowercase().as_str() {
+        "mon" | "monday" => Ok(chrono::Weekday::Mon),
+        "sun" | "sunday" => Ok(chrono::Weekday::Sun),
+        other => bail!("Unknown week start '{}' (use monday|sunday)", other),
+    }
+}
+
+/// `month_start_day` must exist in every month.
+pub fn check_month_start_day(day: u32) -> Result<u32> {
+    ensure!(
+        (1..=28).contains(&day),
+        "month_start_day must be between 1 and 28"
+    );
+    Ok(day)
+}
+
+fn load_periods(conn: &Connection) -> Result<Periods> {
+    let mut p = Periods::DEFAULT;
+    if let Some(v) = get_setting(conn, "week_start")? {
+        p.week_start = parse_week_start(&v)?;
+    }
+    if let Some(v) = get_setting(conn, "month_start_day")? {
+        let day = v
+            .trim()
+            .parse::<u32>()
+            .with_context(|| format!("Setting month_start_day is not a number: '{}'", v))?;
+        p.month_start_day = check_month_start_day(day)?;
+    }
+    Ok(p)
+}
+
+/// A database's display settings: the zone "today" is taken in, how amounts are
+/// rounded and shown, how periods are cut and which language labels are in. A
+/// [`Ledger`](crate::ledger::Ledger) loads them and puts them in force on its thread
+/// while it runs something; see [`with_settings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub timezone: Option<chrono_tz::Tz>,
+    pub precision: Precision,
+    pub periods: Periods,
+    pub language: crate::i18n::Language,
+}
+
+impl Settings {
+    pub const DEFAULT: Settings = Settings {
+        timezone: None,
+        precision: Precision::DEFAULT,
+        periods: Periods::DEFAULT,
+        language: crate::i18n::Language::En,
+    };
+
+    /// Read the `timezone`, precision, period and `language` settings stored in `conn`.
+    pub fn load(conn: &Connection) -> Result<Settings> {
+        Ok(Settings {
+            timezone: load_timezone(conn)?,
+            precision: load_precision(conn)?,
+            periods: load_periods(conn)?,
+            language: crate::i18n::load_language(conn)?,
+        })
+    }
+}
+
+thread_local! {
+    static SETTINGS: std::cell::Cell<Settings> = const { std::cell::Cell::new(Settings::DEFAULT) };
+}
+
+/// The settings in force on this thread; the defaults outside [`with_settings`].
+pub fn settings() -> Settings {
+    SETTINGS.with(|s| s.get())
+}
+
+/// Run `f` with `settings` in force on this thread, then put back the ones it was
+/// called with, also when `f` panics.
+pub fn with_settings<R>(settings: Settings, f: impl FnOnce() -> R) -> R {
+    struct Restore(Settings);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SETTINGS.with(|s| s.set(self.0));
+        }
+    }
+    let _restore = Restore(SETTINGS.with(|s| s.replace(settings)));
+    f()
+}
+
+/// ISO 4217 currencies without minor units.
+const ZERO_DECIMAL_CURRENCIES: &[&str] = &[
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "VND", "VUV",
+    "XAF", "XOF", "XPF",
+];
+
+/// ISO 4217 currencies with three minor digits.
+const THREE_DECIMAL_CURRENCIES: &[&str] = &["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+/// ISO 4217 minor units of `ccy` (0, 2 or 3), whatever the display settings. Amounts
+/// the ledger computes and stores are rounded to these; [`money_dp`] is for output.
+pub fn minor_units(ccy: &str) -> u32 {
+    let ccy = ccy.trim().to_uppercase();
+    if ZERO_DECIMAL_CURRENCIES.contains(&ccy.as_str()) {
+        0
+    } else if THREE_DECIMAL_CURRENCIES.contains(&ccy.as_str()) {
+        3
+    } else {
+        2
+    }
+}
+
+/// Decimals shown for an amount in `ccy`: the currency's minor units, capped by the
+/// money precision. At the default of 2, three-digit currencies show all three.
+pub fn money_dp(ccy: &str) -> u32 {
+    let money = precision().money;
+    match minor_units(ccy) {
+        0 => 0,
+        3 if money == 2 => 3,
+        _ => money,
+    }
+}
+
+/// Symbols of common currencies; others are written as `CHF 12.50`.
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("AUD", "A$"),
+    ("BRL", "R$"),
+    ("CAD", "CA$"),
+    ("CNY", "CN¥"),
+    ("EUR", "€"),
+    ("GBP", "£"),
+    ("HKD", "HK$"),
+    ("ILS", "₪"),
+    ("INR", "₹"),
+    ("JPY", "¥"),
+    ("KRW", "₩"),
+    ("MXN", "MX$"),
+    ("NGN", "₦"),
+    ("NZD", "NZ$"),
+    ("PHP", "₱"),
+    ("RUB", "₽"),
+    ("SGD", "S$"),
+    ("THB", "฿"),
+    ("TRY", "₺"),
+    ("UAH", "₴"),
+    ("USD", "$"),
+    ("VND", "₫"),
+];
+
+/// `formatted` (a number already at `ccy`'s precision) with the currency's symbol:
+/// `€12.50`, `-₹40.00`, `CHF 3.00`.
+fn with_symbol(formatted: &str, ccy: &str) -> String {
+    let ccy = ccy.trim().to_uppercase();
+    let (sign, digits) = match formatted.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", formatted),
+    };
+    match CURRENCY_SYMBOLS.iter().find(|(code, _)| *code == ccy) {
+        Some((_, symbol)) => format!("{}{}{}", sign, symbol, digits),
+        None => format!("{}{} {}", sign, ccy, digits),
+    }
+}
+
+fn fmt_dp(d: Decimal, dp: u32, rounding: Rounding) -> String {
+    let rounded = d.round_dp_with_strategy(dp, rounding.strategy());
+    format!("{:.*}", dp as usize, rounded)
+}
+
+/// An amount in `ccy` at that currency's display precision, e.g. `1234.50`, `1235` (JPY).
+/// With `currency_symbols` on it carries the currency's symbol: `€1234.50`.
+pub fn fmt_amount(d: Decimal, ccy: &str) -> String {
+    let p = precision();
+    let amount = fmt_dp(d, money_dp(ccy), p.rounding);
+    if p.symbols {
+        with_symbol(&amount, ccy)
+    } else {
+        amount
+    }
+}
+
+/// [`fmt_amount`] with an explicit sign, for changes and differences.
+pub fn fmt_signed(d: Decimal, ccy: &str) -> String {
+    let s = fmt_amount(d, ccy);
+    if s.starts_with('-') {
+        s
+    } else {
+        format!("+{}", s)
+    }
+}
+
+/// `USD 12.50`, or `$12.50` with `currency_symbols` on.
 pub fn fmt_money(d: &Decimal, ccy: &str) -> String {
-    format!("{} {}", ccy, d.round_dp(2))
+    if precision().symbols {
+        fmt_amount(*d, ccy)
+    } else {
+        format!("{} {}", ccy, fmt_amount(*d, ccy))
+    }
+}
+
+/// A share or unit count at the quantity precision.
+pub fn fmt_qty(d: Decimal) -> String {
+    let p = precision();
+    fmt_dp(d, p.quantity, p.rounding)
+}
+
+/// A percentage figure (already multiplied by 100) at the percent precision.
+pub fn fmt_percent(d: Decimal) -> String {
+    let p = precision();
+    fmt_dp(d, p.percent, p.rounding)
+}
+
+/// How command output is rendered: `--format` on report-style commands, or the
+/// global `--markdown` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    /// Rows as JSON, for commands that support `--json`; other output stays tables.
+    Json,
+    Csv,
+    Markdown,
+    Html,
 }
 
-pub fn pretty_table(headers: &[&str], rows: Vec<Vec<String>>) -> Table {
-    let mut t = Table::new();
-    t.load_preset(UTF8_FULL);
-    t.set_header(headers.iter().map(|h| Cell::new(*h)));
-    for r in rows {
-        t.add_row(r.into_iter().map(Cell::new));
+impl OutputFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            other => bail!(
+                "Unknown format '{}' (use table|json|csv|markdown|html)",
+                other
+            ),
+        }
     }
-    t
+}
+
+// Per-invocation output state is thread-local so commands run side by side (as the
+// tests do) never see each other's format or captured output.
+thread_local! {
+    static OUTPUT_FORMAT: std::cell::Cell<OutputFormat> =
+        const { std::cell::Cell::new(OutputFormat::Table) };
+    /// Output collected for `--out` instead of going to stdout.
+    static CAPTURE: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+pub fn set_output_format(format: OutputFormat) {
+    OUTPUT_FORMAT.with(|f| f.set(format));
+}
+
+pub fn output_format() -> OutputFormat {
+    OUTPUT_FORMAT.with(|f| f.get())
+}
+
+/// Collect everything passed to [`emit`] (and JSON output) from now on.
+pub fn start_capture() {
+    CAPTURE.with(|c| *c.borrow_mut() = Some(String::new()));
+}
+
+/// Stop collecting and return what was collected.
+pub fn take_capture() -> String {
+    CAPTURE.with(|c| c.borrow_mut().take().unwrap_or_default())
+}
+
+fn capturing() -> bool {
+    CAPTURE.with(|c| c.borrow().is_some())
+}
+
+/// Print one line of command output, or collect it while `--out` is in effect.
+pub fn emit(text: impl std::fmt::Display) {
+    if !capturing() {
+        println!("{}", text);
+        return;
+    }
+    CAPTURE.with(|c| {
+        if let Some(buf) = c.borrow_mut().as_mut() {
+            use std::fmt::Write as _;
+            let _ = writeln!(buf, "{}", text);
+        }
+    });
+}
+
+/// Write `contents` to `path` so readers never see a partial file: a temporary
+/// file in the same directory is written first and then renamed over `path`.
+pub fn write_atomic(path: &std::path::Path, contents: &[u8]) -> Result<()> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("{} is not a file path", path.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, contents).with_context(|| format!("Write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("Move output into place at {}", path.display())
+    })
+}
+
+/// Process exit status requested by a command that succeeded but has something to
+/// report, such as `doctor` findings; `main` exits with it after dispatch.
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+pub fn set_exit_code(code: i32) {
+    EXIT_CODE.store(code, Ordering::Relaxed);
+}
+
+pub fn exit_code() -> i32 {
+    EXIT_CODE.load(Ordering::Relaxed)
+}
+
+pub fn pretty_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    styled_table(headers, rows, output_format())
+}
+
+fn styled_table(headers: &[&str], rows: Vec<Vec<String>>, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(Vec::new());
+            let _ = wtr.write_record(headers);
+            for r in &rows {
+                let _ = wtr.write_record(r);
+            }
+            let bytes = wtr.into_inner().unwrap_or_default();
+            String::from_utf8_lossy(&bytes).trim_end().to_string()
+        }
+        OutputFormat::Html => {
+            let rows: Vec<Vec<String>> = rows
+                .into_iter()
+                .map(|r| r.into_iter().map(crate::i18n::tr_cell).collect())
+                .collect();
+            let cells = |row: &[String], tag: &str| -> String {
+                row.iter()
+                    .map(|c| format!("<{tag}>{}</{tag}>", html_escape(c)))
+                    .collect()
+            };
+            let header: Vec<String> = headers.iter().map(|h| crate::i18n::tr_header(h)).collect();
+            let mut html = format!("<table>\n<tr>{}</tr>", cells(&header, "th"));
+            for r in &rows {
+                html.push_str(&format!("\n<tr>{}</tr>", cells(r, "td")));
+            }
+            html.push_str("\n</table>");
+            html
+        }
+        _ => {
+            let markdown = format == OutputFormat::Markdown;
+            // A bare `|` in a payee or note would split the cell in markdown.
+            let cell = |text: String| {
+                Cell::new(if markdown {
+                    text.replace('|', "\\|")
+                } else {
+                    text
+                })
+            };
+            let mut t = Table::new();
+            t.load_preset(if markdown { ASCII_MARKDOWN } else { UTF8_FULL });
+            t.set_header(headers.iter().map(|h| cell(crate::i18n::tr_header(h))));
+            for r in rows {
+                t.add_row(r.into_iter().map(|c| cell(crate::i18n::tr_cell(c))));
+            }
+            t.to_string()
+        }
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Horizontal bar of `value / max` at `width` cells, in eighth-cell steps.
+pub fn bar(value: Decimal, max: Decimal, width: usize) -> String {
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    if max <= Decimal::ZERO || value <= Decimal::ZERO {
+        return String::new();
+    }
+    let eighths = (value.min(max) / max * Decimal::from(width * 8))
+        .round()
+        .try_into()
+        .unwrap_or(0usize);
+    let mut out = "█".repeat(eighths / 8);
+    if eighths % 8 != 0 {
+        out.push(PARTIAL[eighths % 8]);
+    }
+    out
+}
+
+/// One block character per value, scaled between the smallest and largest.
+pub fn sparkline(values: &[Decimal]) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let (Some(min), Some(max)) = (values.iter().min(), values.iter().max()) else {
+        return String::new();
+    };
+    let span = *max - *min;
+    values
+        .iter()
+        .map(|v| {
+            if span.is_zero() {
+                return LEVELS[3];
+            }
+            let level: usize = ((*v - *min) / span * Decimal::from(7))
+                .round()
+                .try_into()
+                .unwrap_or(0);
+            LEVELS[level.min(7)]
+        })
+        .collect()
+}
+
+/// Rows that go with a removed record: (count, table, what happens to them).
+pub type RemovalImpact = Vec<(i64, &'static str, &'static str)>;
+
+/// Count the rows of each `(table, column, effect)` that reference `id`, leaving out
+/// tables with none. Tables or columns an older database lacks count as none.
+pub fn removal_impact(
+    conn: &Connection,
+    id: i64,
+    refs: &[(&'static str, &'static str, &'static str)],
+) -> Result<RemovalImpact> {
+    let mut impact = Vec::new();
+    for (table, column, effect) in refs {
+        let present: bool = conn.query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{table}') WHERE name=?1)"),
+            [column],
+            |r| r.get(0),
+        )?;
+        if !present {
+            continue;
+        }
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {table} WHERE {column}=?1"),
+            [id],
+            |r| r.get(0),
+        )?;
+        if count > 0 {
+            impact.push((count, *table, *effect));
+        }
+    }
+    Ok(impact)
 }
 
 pub fn id_for_account(conn: &Connection, name: &str) -> Result<i64> {
-    let mut stmt = conn.prepare_cached("SELECT id FROM accounts WHERE name=?1")?;
-    let id: i64 = stmt
-        .query_row(params![name], |r| r.get(0))
-        .with_context(|| format!("Account '{}' not found", name))?;
-    Ok(id)
+    lookup_id(conn, "Account", "accounts", "name", name)
 }
 
 pub fn id_for_category(conn: &Connection, name: &str) -> Result<i64> {
-    let mut stmt = conn.prepare_cached("SELECT id FROM categories WHERE name=?1")?;
-    let id: i64 = stmt
-        .query_row(params![name], |r| r.get(0))
-        .with_context(|| format!("Category '{}' not found", name))?;
-    Ok(id)
+    lookup_id(conn, "Category", "categories", "name", name)
 }
 
 pub fn id_for_asset(conn: &Connection, ticker: &str) -> Result<i64> {
-    let mut stmt = conn.prepare_cached("SELECT id FROM assets WHERE ticker=?1")?;
-    let id: i64 = stmt
-        .query_row(params![ticker], |r| r.get(0))
-        .with_context(|| format!("Asset '{}' not found", ticker))?;
-    Ok(id)
+    // A renamed ticker (`asset alias`) still finds its asset.
+    if let Some(id) = crate::commands::assets::aliased_asset(conn, ticker.trim())? {
+        return Ok(id);
+    }
+    lookup_id(conn, "Asset", "assets", "ticker", ticker)
 }
 
-// Base currency settings
-pub fn get_base_currency(conn: &Connection) -> Result<String> {
+/// Exact lookup by name; on a miss, accept a unique case-insensitive prefix when
+/// `match_prefixes` is enabled, otherwise fail with the closest existing names.
+fn lookup_id(
+    conn: &Connection,
+    label: &str,
+    table: &'static str,
+    column: &'static str,
+    name: &str,
+) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(&format!("SELECT id FROM {table} WHERE {column}=?1"))?;
+    if let Some(id) = stmt
+        .query_row(params![name], |r| r.get::<_, i64>(0))
+        .optional()?
+    {
+        return Ok(id);
+    }
+
+    let mut all = conn.prepare_cached(&format!("SELECT id, {column} FROM {table}"))?;
+    let candidates = all
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let prefixes = get_setting(conn, "match_prefixes")?.is_some_and(|v| v == "true");
+    let needle = name.trim().to_lowercase();
+    if prefixes && !needle.is_empty() {
+        let mut hits = candidates
+            .iter()
+            .filter(|(_, c)| c.to_lowercase().starts_with(&needle));
+        if let (Some((id, full)), None) = (hits.next(), hits.next()) {
+            eprintln!("Using {} '{}' for '{}'", label.to_lowercase(), full, name);
+            return Ok(*id);
+        }
+    }
+
+    let names: Vec<&str> = candidates.iter().map(|(_, c)| c.as_str()).collect();
+    let suggestions = suggest_names(name, &names);
+    if suggestions.is_empty() {
+        Err(anyhow!("{} '{}' not found", label, name))
+    } else {
+        let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+        Err(anyhow!(
+            "{} '{}' not found. Did you mean {}?",
+            label,
+            name,
+            quoted.join(" or ")
+        ))
+    }
+}
+
+/// Up to three existing names closest to `name` (Jaro-Winkler, case-insensitive).
+pub fn suggest_names<'a>(name: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+    let needle = name.trim().to_lowercase();
+    let mut scored: Vec<(f64, &str)> = candidates
+        .iter()
+        .map(|c| (strsim::jaro_winkler(&needle, &c.to_lowercase()), *c))
+        .filter(|(score, _)| *score >= 0.8)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    scored.into_iter().take(3).map(|(_, c)| c).collect()
+}
+
+/// Fail when `date` lies in a month closed with `period close`.
+pub fn ensure_period_open(conn: &Connection, date: NaiveDate) -> Result<()> {
+    let month = date.format("%Y-%m").to_string();
+    let closed: bool = conn
+        .prepare_cached("SELECT EXISTS(SELECT 1 FROM closed_periods WHERE month=?1)")?
+        .query_row(params![&month], |r| r.get(0))?;
+    ensure!(
+        !closed,
+        "Period {} is closed; reopen it with `period reopen {} --force`",
+        month,
+        month
+    );
+    Ok(())
+}
+
+/// A setting from the database, else from `config.toml` (see [`crate::config_file`]).
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
     let v: Option<String> = conn
         .query_row(
-            "SELECT value FROM settings WHERE key='base_currency'",
-            [],
+            "SELECT value FROM settings WHERE key=?1",
+            params![key],
             |r| r.get(0),
         )
         .optional()?;
-    Ok(v.unwrap_or_else(|| "USD".to_string()))
+    Ok(v.or_else(|| crate::config_file::setting(key)))
 }
 
-pub fn set_base_currency(conn: &Connection, ccy: &str) -> Result<()> {
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
     conn.execute(
-        "INSERT INTO settings(key, value) VALUES('base_currency', ?1)
+        "INSERT INTO settings(key, value) VALUES(?1, ?2)
          ON CONFLICT(key) DO UPDATE SET value=excluded.value",
-        params![ccy],
+        params![key, value],
     )?;
     Ok(())
 }
 
+// Base currency settings
+/// The stored base currency; without one, `MONEYCLIP_BASE_CURRENCY`, else USD.
+pub fn get_base_currency(conn: &Connection) -> Result<String> {
+    Ok(match get_setting(conn, "base_currency")? {
+        Some(base) => base,
+        None => env_base_currency().unwrap_or_else(|| "USD".to_string()),
+    })
+}
+
+/// `MONEYCLIP_BASE_CURRENCY`, upper-cased.
+pub fn env_base_currency() -> Option<String> {
+    std::env::var("MONEYCLIP_BASE_CURRENCY")
+        .ok()
+        .map(|v| v.trim().to_uppercase())
+        .filter(|v| !v.is_empty())
+}
+
+pub fn set_base_currency(conn: &Connection, ccy: &str) -> Result<()> {
+    set_setting(conn, "base_currency", ccy)
+}
+
+/// Fail unless the online source behind `key` (`fx_provider` or `price_provider`) is
+/// `provider`, its default; `off` keeps moneyclip from going online for it.
+pub fn ensure_provider(conn: &Connection, key: &str, provider: &str) -> Result<()> {
+    if offline() {
+        bail!("{} is disabled by MONEYCLIP_OFFLINE", key);
+    }
+    match get_setting(conn, key)? {
+        None => Ok(()),
+        Some(v) if v == provider => Ok(()),
+        Some(v) if v == "off" => bail!(
+            "{} is off; enable it with `config set {} {}`",
+            key,
+            key,
+            provider
+        ),
+        Some(v) => bail!("Unknown {} '{}' (use {}|off)", key, v, provider),
+    }
+}
+
+/// How `fx_convert` picks a route when several rate paths connect two currencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FxPathPolicy {
+    /// Whichever path yields the largest converted amount (historical behaviour).
+    Best,
+    /// A direct pair, then a hop through the base currency, then the fewest hops.
+    Direct,
+}
+
+impl FxPathPolicy {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "best" => Ok(Self::Best),
+            "direct" => Ok(Self::Direct),
+            other => Err(anyhow!(
+                "Unknown FX path policy '{}' (use best|direct)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Best => "best",
+            Self::Direct => "direct",
+        }
+    }
+}
+
+pub fn get_fx_path_policy(conn: &Connection) -> Result<FxPathPolicy> {
+    match get_setting(conn, "fx_path_policy")? {
+        Some(v) => FxPathPolicy::parse(&v),
+        None => Ok(FxPathPolicy::Best),
+    }
+}
+
+/// Which rate applies when a valuation date (a month end, a portfolio date) falls on
+/// a weekend or holiday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValuationPolicy {
+    /// The latest rate on or before the date, i.e. the previous business day's
+    /// fixing (default; the historical behaviour).
+    Previous,
+    /// The closer business day: Saturday uses Friday, Sunday uses Monday. Holidays
+    /// still fall back to the latest rate before the chosen day.
+    Nearest,
+}
+
+impl ValuationPolicy {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "previous" => Ok(Self::Previous),
+            "nearest" => Ok(Self::Nearest),
+            other => Err(anyhow!(
+                "Unknown valuation date policy '{}' (use previous|nearest)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Previous => "previous",
+            Self::Nearest => "nearest",
+        }
+    }
+
+    pub fn apply(&self, date: NaiveDate) -> NaiveDate {
+        use chrono::{Datelike, Duration, Weekday};
+        match (self, date.weekday()) {
+            (Self::Nearest, Weekday::Sat) => date - Duration::days(1),
+            (Self::Nearest, Weekday::Sun) => date + Duration::days(1),
+            _ => date,
+        }
+    }
+}
+
+pub fn get_valuation_policy(conn: &Connection) -> Result<ValuationPolicy> {
+    match get_setting(conn, "valuation_date")? {
+        Some(v) => ValuationPolicy::parse(&v),
+        None => Ok(ValuationPolicy::Previous),
+    }
+}
+
+/// Day whose rates value `date` under the `valuation_date` setting.
+pub fn valuation_date(conn: &Connection, date: NaiveDate) -> Result<NaiveDate> {
+    Ok(get_valuation_policy(conn)?.apply(date))
+}
+
+/// Rate date for month-level conversions (budgets, envelopes): the last day of the month's
+/// period under the valuation policy.
+pub fn valuation_month_end(conn: &Connection, month: &str) -> Result<NaiveDate> {
+    valuation_date(conn, periods().month_range(month)?.1)
+}
+
 struct FxGraph {
-    adjacency: Vec<Vec<(usize, Decimal)>>,
+    adjacency: Vec<Vec<FxEdge>>,
     currency_index: HashMap<String, usize>,
+    currencies: Vec<String>,
+    pairs: Vec<FxPair>,
+}
+
+struct FxEdge {
+    to: usize,
+    rate: Decimal,
+    pair: usize,
+}
+
+/// Latest stored row for one base/quote pair on or before the graph date.
+struct FxPair {
+    base: String,
+    quote: String,
+    rate: Decimal,
+    date: String,
+}
+
+/// One hop of a resolved conversion, as reported by `fx_explain`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FxStep {
+    pub from: String,
+    pub to: String,
+    pub pair: String,
+    pub rate_date: String,
+    pub stored_rate: Decimal,
+    pub applied_rate: Decimal,
 }
 
 struct FxGraphCacheEntry {
@@ -122,12 +1209,53 @@ struct FxGraphCacheEntry {
     total_changes: i64,
     graphs: HashMap<NaiveDate, Arc<FxGraph>>,
     order: VecDeque<NaiveDate>,
+    factors: HashMap<(NaiveDate, String, String), Option<Decimal>>,
+}
+
+/// Lookups memoized for one connection, attached to it by [`attach_caches`].
+#[derive(Default)]
+struct Caches {
+    fx: RwLock<Option<FxGraphCacheEntry>>,
+    /// Compiled rule patterns keyed by their source text. Rule rows themselves are
+    /// re-read on every lookup (the table is tiny).
+    regexes: RwLock<HashMap<String, Regex>>,
 }
 
-static FX_GRAPH_CACHE: Lazy<RwLock<HashMap<usize, FxGraphCacheEntry>>> =
-    Lazy::new(|| RwLock::new(HashMap::new()));
+const CACHES_KEY: &std::ffi::CStr = c"moneyclip.caches";
+
+/// Give `conn` fresh caches. SQLite keeps them with the connection, so they serve
+/// its transactions and savepoints too and are freed when it closes.
+pub(crate) fn attach_caches(conn: &Connection) {
+    unsafe extern "C" fn release(caches: *mut std::ffi::c_void) {
+        drop(unsafe { Arc::from_raw(caches as *const Caches) });
+    }
+    let caches = Arc::into_raw(Arc::new(Caches::default()));
+    unsafe {
+        ffi::sqlite3_set_clientdata(
+            conn.handle(),
+            CACHES_KEY.as_ptr(),
+            caches as *mut std::ffi::c_void,
+            Some(release),
+        );
+    }
+}
+
+/// The caches attached to `conn`; `None` for connections not opened through a
+/// ledger, where nothing is memoized.
+fn caches_for(conn: &Connection) -> Option<Arc<Caches>> {
+    unsafe {
+        let caches = ffi::sqlite3_get_clientdata(conn.handle(), CACHES_KEY.as_ptr());
+        if caches.is_null() {
+            return None;
+        }
+        // The connection keeps its own reference; this one is the caller's.
+        Arc::increment_strong_count(caches as *const Caches);
+        Some(Arc::from_raw(caches as *const Caches))
+    }
+}
 
 const MAX_FX_GRAPH_CACHE_DATES: usize = 32;
+const MAX_FX_FACTOR_CACHE_ENTRIES: usize = 4096;
 
 /// Convert an amount from 'from_ccy' to 'to_ccy' using the closest on-or-before rate.
 /// We store base->quote rates. If pair not found directly, we attempt via the base currency hub.
@@ -141,93 +1269,275 @@ pub fn fx_convert(
     if from_ccy == to_ccy {
         return Ok(amount);
     }
-    let graph = fx_graph_for(conn, date)?;
+    if amount.is_zero() {
+        let graph = fx_graph_for(conn, date)?;
+        if graph.currency_index.contains_key(from_ccy) && graph.currency_index.contains_key(to_ccy)
+        {
+            return Ok(amount);
+        }
+        return Err(no_fx_path(from_ccy, to_ccy, date));
+    }
+    Ok(amount * fx_rate(conn, date, from_ccy, to_ccy)?)
+}
 
-    let Some(&from_idx) = graph.currency_index.get(from_ccy) else {
-        return Err(anyhow!(
-            "No FX rate path from {} to {} on or before {}",
-            from_ccy,
-            to_ccy,
-            date
-        ));
-    };
-    let Some(&to_idx) = graph.currency_index.get(to_ccy) else {
-        return Err(anyhow!(
-            "No FX rate path from {} to {} on or before {}",
-            from_ccy,
-            to_ccy,
-            date
-        ));
+/// Convert many `(date, amount, currency)` rows into `to_ccy`, resolving each distinct
+/// `(date, currency)` pair once. Results are returned in input order.
+pub fn fx_convert_batch(
+    conn: &Connection,
+    items: &[(NaiveDate, Decimal, &str)],
+    to_ccy: &str,
+) -> Result<Vec<Decimal>> {
+    let mut factors: HashMap<(NaiveDate, &str), Decimal> = HashMap::new();
+    let mut out = Vec::with_capacity(items.len());
+    for &(date, amount, from_ccy) in items {
+        if from_ccy == to_ccy || amount.is_zero() {
+            out.push(fx_convert(conn, date, amount, from_ccy, to_ccy)?);
+            continue;
+        }
+        let factor = match factors.entry((date, from_ccy)) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => *entry.insert(fx_rate(conn, date, from_ccy, to_ccy)?),
+        };
+        out.push(amount * factor);
+    }
+    Ok(out)
+}
+
+/// Units of `to_ccy` per one unit of `from_ccy` on `date`. Resolved factors are memoized
+/// next to the per-date graphs and share their invalidation.
+pub fn fx_rate(
+    conn: &Connection,
+    date: NaiveDate,
+    from_ccy: &str,
+    to_ccy: &str,
+) -> Result<Decimal> {
+    if from_ccy == to_ccy {
+        return Ok(Decimal::ONE);
+    }
+    let caches = caches_for(conn);
+    let key = (date, from_ccy.to_string(), to_ccy.to_string());
+    let current_version = data_version(conn)?;
+    let change_count = total_changes(conn);
+
+    let cached = caches.as_ref().and_then(|c| {
+        c.fx.read()
+            .unwrap()
+            .as_ref()
+            .filter(|entry| {
+                entry.data_version == current_version && entry.total_changes == change_count
+            })
+            .and_then(|entry| entry.factors.get(&key).copied())
+    });
+    let factor = match cached {
+        Some(factor) => factor,
+        None => {
+            let graph = fx_graph_for(conn, date)?;
+            let policy = get_fx_path_policy(conn)?;
+            let base = get_base_currency(conn)?;
+            let factor = resolve_path(&graph, from_ccy, to_ccy, policy, &base).map(|path| {
+                path.iter()
+                    .fold(Decimal::ONE, |acc, (_, edge)| acc * edge.rate)
+            });
+            if let Some(caches) = &caches
+                && let Some(entry) = caches.fx.write().unwrap().as_mut().filter(|entry| {
+                    entry.data_version == current_version && entry.total_changes == change_count
+                })
+            {
+                if entry.factors.len() >= MAX_FX_FACTOR_CACHE_ENTRIES {
+                    entry.factors.clear();
+                }
+                entry.factors.insert(key, factor);
+            }
+            factor
+        }
     };
+    factor.ok_or_else(|| no_fx_path(from_ccy, to_ccy, date))
+}
 
-    let magnitude = amount.abs();
-    if magnitude.is_zero() {
-        return Ok(amount);
+fn no_fx_path(from_ccy: &str, to_ccy: &str, date: NaiveDate) -> anyhow::Error {
+    anyhow!(
+        "No FX rate path from {} to {} on or before {}",
+        from_ccy,
+        to_ccy,
+        date
+    )
+}
+
+/// Describe the rates `fx_convert` would chain to convert `from_ccy` into `to_ccy`.
+pub fn fx_explain(
+    conn: &Connection,
+    date: NaiveDate,
+    from_ccy: &str,
+    to_ccy: &str,
+) -> Result<(FxPathPolicy, Vec<FxStep>)> {
+    let policy = get_fx_path_policy(conn)?;
+    if from_ccy == to_ccy {
+        return Ok((policy, Vec::new()));
+    }
+    let graph = fx_graph_for(conn, date)?;
+    let base = get_base_currency(conn)?;
+    let path = resolve_path(&graph, from_ccy, to_ccy, policy, &base)
+        .ok_or_else(|| no_fx_path(from_ccy, to_ccy, date))?;
+    let steps = path
+        .into_iter()
+        .map(|(from_idx, edge)| {
+            let pair = &graph.pairs[edge.pair];
+            FxStep {
+                from: graph.currencies[from_idx].clone(),
+                to: graph.currencies[edge.to].clone(),
+                pair: format!("{}/{}", pair.base, pair.quote),
+                rate_date: pair.date.clone(),
+                stored_rate: pair.rate,
+                applied_rate: edge.rate,
+            }
+        })
+        .collect();
+    Ok((policy, steps))
+}
+
+fn resolve_path<'g>(
+    graph: &'g FxGraph,
+    from_ccy: &str,
+    to_ccy: &str,
+    policy: FxPathPolicy,
+    base_ccy: &str,
+) -> Option<Vec<(usize, &'g FxEdge)>> {
+    let from_idx = *graph.currency_index.get(from_ccy)?;
+    let to_idx = *graph.currency_index.get(to_ccy)?;
+    match policy {
+        FxPathPolicy::Best => best_path(graph, from_idx, to_idx),
+        FxPathPolicy::Direct => {
+            if let Some(edge) = direct_edge(graph, from_idx, to_idx) {
+                return Some(vec![(from_idx, edge)]);
+            }
+            let via_hub = graph.currency_index.get(base_ccy).and_then(|&hub| {
+                Some(vec![
+                    (from_idx, direct_edge(graph, from_idx, hub)?),
+                    (hub, direct_edge(graph, hub, to_idx)?),
+                ])
+            });
+            if via_hub.is_some() {
+                return via_hub;
+            }
+            shortest_path(graph, from_idx, to_idx)
+        }
     }
+}
+
+/// Prefer the pair stored in the requested direction over a reciprocal.
+fn direct_edge(graph: &FxGraph, from_idx: usize, to_idx: usize) -> Option<&FxEdge> {
+    let mut candidates = graph.adjacency[from_idx]
+        .iter()
+        .filter(|edge| edge.to == to_idx);
+    let first = candidates.next()?;
+    let from_ccy = &graph.currencies[from_idx];
+    Some(
+        std::iter::once(first)
+            .chain(candidates)
+            .find(|edge| &graph.pairs[edge.pair].base == from_ccy)
+            .unwrap_or(first),
+    )
+}
 
+fn best_path(graph: &FxGraph, from_idx: usize, to_idx: usize) -> Option<Vec<(usize, &FxEdge)>> {
     let adjacency = &graph.adjacency;
     let mut best = vec![Decimal::ZERO; adjacency.len()];
+    let mut via: Vec<Option<(usize, &FxEdge)>> = (0..adjacency.len()).map(|_| None).collect();
     let mut heap: BinaryHeap<(Decimal, usize)> = BinaryHeap::new();
-    best[from_idx] = magnitude;
-    heap.push((magnitude, from_idx));
+    best[from_idx] = Decimal::ONE;
+    heap.push((Decimal::ONE, from_idx));
 
-    while let Some((current_amount, idx)) = heap.pop() {
-        if current_amount < best[idx] {
+    while let Some((current, idx)) = heap.pop() {
+        if current < best[idx] {
             continue;
         }
         if idx == to_idx {
-            let signed = if amount.is_sign_negative() {
-                -current_amount
-            } else {
-                current_amount
-            };
-            return Ok(signed);
+            return Some(unwind_path(&via, from_idx, to_idx));
         }
 
-        for &(next_idx, rate) in &adjacency[idx] {
-            let next_amount = current_amount * rate;
-            if next_amount > best[next_idx] {
-                best[next_idx] = next_amount;
-                heap.push((next_amount, next_idx));
+        for edge in &adjacency[idx] {
+            let next = current * edge.rate;
+            if next > best[edge.to] {
+                best[edge.to] = next;
+                via[edge.to] = Some((idx, edge));
+                heap.push((next, edge.to));
             }
         }
     }
+    None
+}
 
-    Err(anyhow!(
-        "No FX rate path from {} to {} on or before {}",
-        from_ccy,
-        to_ccy,
-        date
-    ))
+fn shortest_path(graph: &FxGraph, from_idx: usize, to_idx: usize) -> Option<Vec<(usize, &FxEdge)>> {
+    let mut via: Vec<Option<(usize, &FxEdge)>> = (0..graph.adjacency.len()).map(|_| None).collect();
+    let mut seen = vec![false; graph.adjacency.len()];
+    let mut queue = VecDeque::from([from_idx]);
+    seen[from_idx] = true;
+    while let Some(idx) = queue.pop_front() {
+        if idx == to_idx {
+            return Some(unwind_path(&via, from_idx, to_idx));
+        }
+        for edge in &graph.adjacency[idx] {
+            if !seen[edge.to] {
+                seen[edge.to] = true;
+                via[edge.to] = Some((idx, edge));
+                queue.push_back(edge.to);
+            }
+        }
+    }
+    None
+}
+
+fn unwind_path<'g>(
+    via: &[Option<(usize, &'g FxEdge)>],
+    from_idx: usize,
+    to_idx: usize,
+) -> Vec<(usize, &'g FxEdge)> {
+    let mut path = Vec::new();
+    let mut idx = to_idx;
+    while idx != from_idx {
+        let Some((prev, edge)) = via[idx] else {
+            break;
+        };
+        path.push((prev, edge));
+        idx = prev;
+    }
+    path.reverse();
+    path
 }
 
 fn fx_graph_for(conn: &Connection, date: NaiveDate) -> Result<Arc<FxGraph>> {
-    let conn_key = unsafe { conn.handle() as usize };
+    let Some(caches) = caches_for(conn) else {
+        return Ok(Arc::new(build_fx_graph(conn, date)?));
+    };
     let current_version = data_version(conn)?;
     let change_count = total_changes(conn);
 
-    if let Some(graph) = {
-        let cache = FX_GRAPH_CACHE.read().unwrap();
-        cache
-            .get(&conn_key)
-            .filter(|entry| {
-                entry.data_version == current_version && entry.total_changes == change_count
-            })
-            .and_then(|entry| entry.graphs.get(&date).cloned())
-    } {
+    if let Some(graph) = caches
+        .fx
+        .read()
+        .unwrap()
+        .as_ref()
+        .filter(|entry| {
+            entry.data_version == current_version && entry.total_changes == change_count
+        })
+        .and_then(|entry| entry.graphs.get(&date).cloned())
+    {
+        tracing::trace!(target: "moneyclip::cache", %date, "FX graph cache hit");
         return Ok(graph);
     }
+    tracing::trace!(target: "moneyclip::cache", %date, "FX graph cache miss");
 
     let graph = Arc::new(build_fx_graph(conn, date)?);
     let refreshed_version = data_version(conn)?;
     let refreshed_changes = total_changes(conn);
 
-    let mut cache = FX_GRAPH_CACHE.write().unwrap();
-    let entry = cache.entry(conn_key).or_insert_with(|| FxGraphCacheEntry {
+    let mut cache = caches.fx.write().unwrap();
+    let entry = cache.get_or_insert_with(|| FxGraphCacheEntry {
         data_version: refreshed_version,
         total_changes: refreshed_changes,
         graphs: HashMap::new(),
         order: VecDeque::new(),
+        factors: HashMap::new(),
     });
 
     if entry.data_version != refreshed_version || entry.total_changes != refreshed_changes {
@@ -235,6 +1545,7 @@ fn fx_graph_for(conn: &Connection, date: NaiveDate) -> Result<Arc<FxGraph>> {
         entry.total_changes = refreshed_changes;
         entry.graphs.clear();
         entry.order.clear();
+        entry.factors.clear();
     }
 
     entry.order.retain(|d| d != &date);
@@ -254,22 +1565,26 @@ fn build_fx_graph(conn: &Connection, date: NaiveDate) -> Result<FxGraph> {
     let date_str = date.format("%Y-%m-%d").to_string();
 
     let mut stmt = conn.prepare_cached(
-        "SELECT base, quote, rate FROM (
-             SELECT base, quote, rate,
+        "SELECT base, quote, rate, date FROM (
+             SELECT base, quote, rate, date,
                     ROW_NUMBER() OVER (PARTITION BY base, quote ORDER BY date DESC) AS rn
              FROM fx_rates
              WHERE date <= ?1
          )
-         WHERE rn = 1",
+         WHERE rn = 1
+         ORDER BY base, quote",
     )?;
     let mut rows = stmt.query(params![&date_str])?;
-    let mut adjacency: Vec<Vec<(usize, Decimal)>> = Vec::new();
+    let mut adjacency: Vec<Vec<FxEdge>> = Vec::new();
     let mut currency_index: HashMap<String, usize> = HashMap::new();
+    let mut currencies: Vec<String> = Vec::new();
+    let mut pairs: Vec<FxPair> = Vec::new();
 
     while let Some(row) = rows.next()? {
         let base: String = row.get(0)?;
         let quote: String = row.get(1)?;
         let rate_str: String = row.get(2)?;
+        let rate_date: String = row.get(3)?;
         let rate = rate_str
             .parse::<Decimal>()
             .with_context(|| format!("Invalid rate '{}' for {}/{}", rate_str, base, quote))?;
@@ -281,32 +1596,43 @@ fn build_fx_graph(conn: &Connection, date: NaiveDate) -> Result<FxGraph> {
             date
         );
 
-        let base_idx = match currency_index.entry(base) {
-            Entry::Occupied(entry) => *entry.get(),
-            Entry::Vacant(entry) => {
-                let idx = adjacency.len();
-                adjacency.push(Vec::new());
-                entry.insert(idx);
-                idx
-            }
-        };
-        let quote_idx = match currency_index.entry(quote) {
+        let mut index_of = |ccy: &str| match currency_index.entry(ccy.to_string()) {
             Entry::Occupied(entry) => *entry.get(),
             Entry::Vacant(entry) => {
                 let idx = adjacency.len();
                 adjacency.push(Vec::new());
+                currencies.push(ccy.to_string());
                 entry.insert(idx);
                 idx
             }
         };
-
-        adjacency[base_idx].push((quote_idx, rate));
-        adjacency[quote_idx].push((base_idx, Decimal::ONE / rate));
+        let base_idx = index_of(&base);
+        let quote_idx = index_of(&quote);
+
+        let pair = pairs.len();
+        adjacency[base_idx].push(FxEdge {
+            to: quote_idx,
+            rate,
+            pair,
+        });
+        adjacency[quote_idx].push(FxEdge {
+            to: base_idx,
+            rate: Decimal::ONE / rate,
+            pair,
+        });
+        pairs.push(FxPair {
+            base,
+            quote,
+            rate,
+            date: rate_date,
+        });
     }
 
     Ok(FxGraph {
         adjacency,
         currency_index,
+        currencies,
+        pairs,
     })
 }
 
@@ -333,24 +1659,84 @@ pub fn month_end(month: &str) -> Result<NaiveDate> {
         .ok_or_else(|| anyhow::anyhow!("Invalid month '{}'", month))
 }
 
+/// `YYYY-MM` moved by `delta` months, e.g. `shift_month("2025-01", -1)` is `2024-12`.
+pub fn shift_month(month: &str, delta: i32) -> Result<String> {
+    use chrono::Datelike;
+    let first = month_end(month)?.with_day(1).unwrap();
+    let shifted = if delta >= 0 {
+        first.checked_add_months(chrono::Months::new(delta as u32))
+    } else {
+        first.checked_sub_months(chrono::Months::new(delta.unsigned_abs()))
+    }
+    .ok_or_else(|| anyhow::anyhow!("Month '{}' shifted by {} is out of range", month, delta))?;
+    Ok(shifted.format("%Y-%m").to_string())
+}
+
 use regex::Regex;
 
 #[derive(Clone)]
 struct CompiledRule {
+    id: i64,
     regex: Regex,
     category_id: Option<i64>,
     rewrite: Option<String>,
+    field: MatchField,
+    /// Drops matching rows on import instead of categorizing them.
+    skip: bool,
 }
 
-struct RuleCacheEntry {
-    rules: Arc<Vec<CompiledRule>>,
-    data_version: i64,
+impl CompiledRule {
+    /// The text this rule is tested against: `both` is the payee and memo joined, and
+    /// `note` rules have nothing to test without a memo.
+    fn haystack<'a>(
+        &self,
+        payee: &'a str,
+        memo: Option<&'a str>,
+        both: &'a str,
+    ) -> Option<&'a str> {
+        match (self.field, memo) {
+            (MatchField::Payee, _) => Some(payee),
+            (MatchField::Note, m) => m,
+            (MatchField::Both, _) => Some(both),
+        }
+    }
+}
+
+/// What a rule's pattern is tested against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchField {
+    Payee,
+    Note,
+    Both,
+}
+
+impl MatchField {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "payee" => Ok(MatchField::Payee),
+            "note" => Ok(MatchField::Note),
+            "both" => Ok(MatchField::Both),
+            other => bail!("Unknown match field '{}' (use payee|note|both)", other),
+        }
+    }
 }
 
-static RULE_CACHE: Lazy<RwLock<HashMap<usize, RuleCacheEntry>>> =
-    Lazy::new(|| RwLock::new(HashMap::new()));
+/// Regex source for a stored rule: `literal` patterns are escaped so merchant names
+/// full of `*`, `.` or `+` match as typed, and `ignore_case` prepends `(?i)`.
+pub fn rule_regex_source(pattern: &str, ignore_case: bool, literal: bool) -> String {
+    let body = if literal {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    if ignore_case {
+        format!("(?i){}", body)
+    } else {
+        body
+    }
+}
 
-const MAX_RULE_CACHE_ENTRIES: usize = 32;
+const MAX_REGEX_CACHE_ENTRIES: usize = 256;
 
 fn data_version(conn: &Connection) -> Result<i64> {
     conn.query_row("PRAGMA data_version", [], |r| r.get(0))
@@ -366,13 +1752,28 @@ pub fn maybe_print_json<T: serde::Serialize>(
     jsonl_flag: bool,
     rows: &[T],
 ) -> Result<bool> {
+    let json_flag = json_flag || (!jsonl_flag && output_format() == OutputFormat::Json);
     if !json_flag && !jsonl_flag {
         return Ok(false);
     }
+    with_output(|w| maybe_print_json_to(json_flag, jsonl_flag, rows, w))
+}
 
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-    maybe_print_json_to(json_flag, jsonl_flag, rows, &mut handle)
+/// Run `write` against stdout, or against the `--out` buffer while capturing.
+pub fn with_output<R>(write: impl FnOnce(&mut dyn Write) -> Result<R>) -> Result<R> {
+    if !capturing() {
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        return write(&mut handle);
+    }
+    let mut bytes = Vec::new();
+    let result = write(&mut bytes)?;
+    CAPTURE.with(|c| {
+        if let Some(buf) = c.borrow_mut().as_mut() {
+            buf.push_str(&String::from_utf8_lossy(&bytes));
+        }
+    });
+    Ok(result)
 }
 
 fn maybe_print_json_to<T, W>(
@@ -386,14 +1787,15 @@ where
     W: Write,
 {
     if json_flag {
-        serde_json::to_writer_pretty(&mut writer, rows)?;
+        let rows: Vec<_> = rows.iter().map(Versioned::new).collect();
+        serde_json::to_writer_pretty(&mut writer, &rows)?;
         writer.write_all(b"\n")?;
         return Ok(true);
     }
 
     if jsonl_flag {
         for row in rows {
-            serde_json::to_writer(&mut writer, row)?;
+            serde_json::to_writer(&mut writer, &Versioned::new(row))?;
             writer.write_all(b"\n")?;
         }
         return Ok(true);
@@ -402,61 +1804,194 @@ where
     Ok(false)
 }
 
+/// A JSON row with [`crate::models::SCHEMA_VERSION`] ahead of its own fields, so
+/// every `--json` row and `--jsonl` line says which shape it has.
+#[derive(serde::Serialize)]
+struct Versioned<'a, T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    row: &'a T,
+}
+
+impl<'a, T> Versioned<'a, T> {
+    fn new(row: &'a T) -> Self {
+        Self {
+            schema_version: crate::models::SCHEMA_VERSION,
+            row,
+        }
+    }
+}
+
+/// Aggregate behind `--totals`: row count, sum per currency and their sum converted
+/// to `currency`, each amount at the date it belongs to.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Totals {
+    pub count: usize,
+    pub by_currency: std::collections::BTreeMap<String, String>,
+    pub currency: String,
+    pub converted: String,
+}
+
+impl Totals {
+    /// `(amount, currency)` lines for a table footer: one per currency, then the
+    /// converted sum when it adds information.
+    pub fn lines(&self) -> Vec<(String, String)> {
+        let mut lines: Vec<(String, String)> = self
+            .by_currency
+            .iter()
+            .map(|(ccy, sum)| (sum.clone(), ccy.clone()))
+            .collect();
+        let single_in_target = lines.len() == 1 && self.by_currency.contains_key(&self.currency);
+        if !single_in_target {
+            lines.push((
+                self.converted.clone(),
+                format!("{} (converted)", self.currency),
+            ));
+        }
+        lines
+    }
+}
+
+pub fn compute_totals(
+    conn: &Connection,
+    items: &[(NaiveDate, Decimal, &str)],
+    target: &str,
+) -> Result<Totals> {
+    let mut by_currency: std::collections::BTreeMap<String, Decimal> = Default::default();
+    for (_, amount, ccy) in items {
+        *by_currency.entry(ccy.to_uppercase()).or_default() += *amount;
+    }
+    let converted: Decimal = fx_convert_batch(conn, items, target)?.into_iter().sum();
+    Ok(Totals {
+        count: items.len(),
+        by_currency: by_currency
+            .into_iter()
+            .map(|(ccy, sum)| {
+                let shown = fmt_amount(sum, &ccy);
+                (ccy, shown)
+            })
+            .collect(),
+        currency: target.to_string(),
+        converted: fmt_amount(converted, target),
+    })
+}
+
+/// [`maybe_print_json`] with an optional `--totals` aggregate: `--json` wraps the
+/// output as `{"schema_version": N, "rows": [...], "totals": {...}}` and `--jsonl`
+/// ends with a `{"schema_version": N, "totals": {...}}` line.
+pub fn maybe_print_json_with_totals<T: serde::Serialize>(
+    json_flag: bool,
+    jsonl_flag: bool,
+    rows: &[T],
+    totals: Option<&Totals>,
+) -> Result<bool> {
+    let json_flag = json_flag || (!jsonl_flag && output_format() == OutputFormat::Json);
+    with_output(|w| print_json_with_totals_to(json_flag, jsonl_flag, rows, totals, w))
+}
+
+fn print_json_with_totals_to<T, W>(
+    json_flag: bool,
+    jsonl_flag: bool,
+    rows: &[T],
+    totals: Option<&Totals>,
+    mut writer: W,
+) -> Result<bool>
+where
+    T: serde::Serialize,
+    W: Write,
+{
+    let Some(totals) = totals else {
+        return maybe_print_json_to(json_flag, jsonl_flag, rows, writer);
+    };
+    if json_flag {
+        let rows: Vec<_> = rows.iter().map(Versioned::new).collect();
+        let body = serde_json::json!({ "rows": rows, "totals": totals });
+        serde_json::to_writer_pretty(&mut writer, &Versioned::new(&body))?;
+        writer.write_all(b"\n")?;
+        return Ok(true);
+    }
+    if jsonl_flag {
+        maybe_print_json_to(false, true, rows, &mut writer)?;
+        let line = serde_json::json!({ "totals": totals });
+        serde_json::to_writer(&mut writer, &Versioned::new(&line))?;
+        writer.write_all(b"\n")?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
 pub fn apply_import_rules(
     conn: &Connection,
     payee: &str,
     memo: Option<&str>,
 ) -> Result<(Option<i64>, Option<String>)> {
-    let hay = memo
-        .map(|m| Cow::Owned(format!("{} {}", payee, m)))
-        .unwrap_or_else(|| Cow::Borrowed(payee));
+    let rules = load_rules(conn)?;
+    Ok(match_rules(&rules, payee, memo))
+}
 
-    let conn_key = unsafe { conn.handle() as usize };
-    let current_version = data_version(conn)?;
+/// Whether a skip rule (`rules add --skip`) matches, so the imported row is dropped.
+pub fn import_skip_rule(conn: &Connection, payee: &str, memo: Option<&str>) -> Result<bool> {
+    let rules = load_rules(conn)?;
+    let both = joined(payee, memo);
+    Ok(rules.iter().filter(|r| r.skip).any(|r| {
+        r.haystack(payee, memo, &both)
+            .is_some_and(|hay| r.regex.is_match(hay))
+    }))
+}
 
-    if let Some(rules) = {
-        let cache = RULE_CACHE.read().unwrap();
-        cache
-            .get(&conn_key)
-            .filter(|entry| entry.data_version == current_version)
-            .map(|entry| Arc::clone(&entry.rules))
-    } {
-        return Ok(match_rules(&rules, hay.as_ref()));
-    }
+/// IDs of every rule matching `payee`/`memo`, in the order an import tries them: skip
+/// rules first, then newest first. Only the first one takes effect.
+pub fn matching_rules(conn: &Connection, payee: &str, memo: Option<&str>) -> Result<Vec<i64>> {
+    let rules = load_rules(conn)?;
+    let both = joined(payee, memo);
+    let (skip, categorize): (Vec<_>, Vec<_>) = rules.iter().partition(|r| r.skip);
+    Ok(skip
+        .into_iter()
+        .chain(categorize)
+        .filter(|r| {
+            r.haystack(payee, memo, &both)
+                .is_some_and(|hay| r.regex.is_match(hay))
+        })
+        .map(|r| r.id)
+        .collect())
+}
 
-    let compiled = Arc::new(load_rules(conn)?);
-    let refreshed_version = data_version(conn)?;
+fn joined<'a>(payee: &'a str, memo: Option<&str>) -> Cow<'a, str> {
+    memo.map(|m| Cow::Owned(format!("{} {}", payee, m)))
+        .unwrap_or_else(|| Cow::Borrowed(payee))
+}
+
+fn compile_pattern(conn: &Connection, id: i64, pattern: &str) -> Result<Regex> {
+    let caches = caches_for(conn);
+    if let Some(regex) = caches
+        .as_ref()
+        .and_then(|c| c.regexes.read().unwrap().get(pattern).cloned())
     {
-        let mut cache = RULE_CACHE.write().unwrap();
-        cache.insert(
-            conn_key,
-            RuleCacheEntry {
-                rules: Arc::clone(&compiled),
-                data_version: refreshed_version,
-            },
-        );
-        if cache.len() > MAX_RULE_CACHE_ENTRIES {
-            let mut candidates: Vec<usize> = cache
-                .keys()
-                .filter(|key| **key != conn_key)
-                .copied()
-                .collect();
-            while cache.len() > MAX_RULE_CACHE_ENTRIES {
-                if let Some(key) = candidates.pop() {
-                    cache.remove(&key);
-                } else {
-                    break;
-                }
-            }
+        tracing::trace!(target: "moneyclip::cache", rule = id, "rule pattern cache hit");
+        return Ok(regex);
+    }
+    let regex = Regex::new(pattern).map_err(|err| {
+        anyhow!(
+            "Invalid regex pattern '{}' for rule {}: {}",
+            pattern,
+            id,
+            err
+        )
+    })?;
+    if let Some(caches) = caches {
+        let mut cache = caches.regexes.write().unwrap();
+        if cache.len() >= MAX_REGEX_CACHE_ENTRIES {
+            cache.clear();
         }
+        cache.insert(pattern.to_string(), regex.clone());
     }
-
-    Ok(match_rules(&compiled, hay.as_ref()))
+    Ok(regex)
 }
 
 fn load_rules(conn: &Connection) -> Result<Vec<CompiledRule>> {
     let mut stmt = conn.prepare_cached(
-        "SELECT id, pattern, category_id, payee_rewrite FROM rules ORDER BY id DESC",
+        "SELECT id, pattern, category_id, payee_rewrite, ignore_case, literal, match_field, skip
+         FROM rules ORDER BY id DESC",
     )?;
     let mut rows = stmt.query([])?;
     let mut compiled = Vec::new();
@@ -465,42 +2000,124 @@ fn load_rules(conn: &Connection) -> Result<Vec<CompiledRule>> {
         let pattern: String = row.get(1)?;
         let category_id: Option<i64> = row.get(2)?;
         let rewrite: Option<String> = row.get(3)?;
-        let regex = Regex::new(&pattern).map_err(|err| {
-            anyhow!(
-                "Invalid regex pattern '{}' for rule {}: {}",
-                pattern,
-                id,
-                err
-            )
-        })?;
+        let source = rule_regex_source(&pattern, row.get(4)?, row.get(5)?);
+        let field =
+            MatchField::parse(&row.get::<_, String>(6)?).with_context(|| format!("Rule {}", id))?;
         compiled.push(CompiledRule {
-            regex,
+            id,
+            regex: compile_pattern(conn, id, &source)?,
             category_id,
             rewrite,
+            field,
+            skip: row.get(7)?,
         });
     }
     Ok(compiled)
 }
 
-fn match_rules(rules: &[CompiledRule], hay: &str) -> (Option<i64>, Option<String>) {
-    for rule in rules {
-        if rule.regex.is_match(hay) {
-            return (rule.category_id, rule.rewrite.clone());
+/// First matching rule's category and payee rewrite. The rewrite is a replacement
+/// template, so `$1`, `${1}` or `$name` insert the pattern's capture groups (the same
+/// syntax as `Regex::replace`; `$$` is a literal dollar).
+///
+/// `both` rules see the payee and memo joined by a space; `note` rules are skipped
+/// when there is no memo, and skip rules never categorize.
+fn match_rules(
+    rules: &[CompiledRule],
+    payee: &str,
+    memo: Option<&str>,
+) -> (Option<i64>, Option<String>) {
+    let both = joined(payee, memo);
+    for rule in rules.iter().filter(|r| !r.skip) {
+        let Some(hay) = rule.haystack(payee, memo, &both) else {
+            continue;
+        };
+        if let Some(caps) = rule.regex.captures(hay) {
+            let rewrite = rule.rewrite.as_ref().map(|template| {
+                let mut expanded = String::new();
+                caps.expand(template, &mut expanded);
+                expanded.trim().to_string()
+            });
+            return (rule.category_id, rewrite);
         }
     }
     (None, None)
 }
 
-pub fn invalidate_rule_cache(_conn: &Connection) {
-    let mut cache = RULE_CACHE.write().unwrap();
-    cache.clear();
+pub fn invalidate_rule_cache(conn: &Connection) {
+    if let Some(caches) = caches_for(conn) {
+        caches.regexes.write().unwrap().clear();
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::maybe_print_json_to;
+    use super::{
+        OutputFormat, Periods, Rounding, Totals, ValuationPolicy, bar, check_precision,
+        display_timestamp_in, fmt_amount, fmt_dp, fmt_signed, maybe_print_json_to, minor_units,
+        money_dp, parse_amount, parse_amount_in, parse_date_on, parse_month_on, parse_quarter_on,
+        parse_timezone, print_json_with_totals_to, shift_month, sparkline, styled_table,
+        suggest_names, with_symbol,
+    };
+    use chrono::NaiveDate;
     use serde::Serialize;
 
+    #[test]
+    fn suggestions_rank_close_names_first() {
+        let names = ["Checking", "Savings", "Cash", "Credit Card"];
+        assert_eq!(suggest_names("Chekcing", &names), vec!["Checking"]);
+        assert_eq!(suggest_names("cash", &names), vec!["Cash"]);
+        assert!(suggest_names("Brokerage", &names).is_empty());
+    }
+
+    #[test]
+    fn amount_expressions_evaluate_with_precedence() {
+        let a = |s: &str| parse_amount(s).unwrap().to_string();
+        assert_eq!(a("-4.50"), "-4.50");
+        assert_eq!(a("12.50+3.99"), "16.49");
+        assert_eq!(a("-3*4.25"), "-12.75");
+        assert_eq!(a("10 - 2 * 3"), "4");
+        assert_eq!(a("(120-15)/3"), "35");
+        assert_eq!(a("-(10+5)"), "-15");
+        assert_eq!(a("100/3"), "33.33");
+        assert_eq!(a("-2/3"), "-0.67");
+        assert_eq!(parse_amount_in("1000/3", "JPY").unwrap().to_string(), "333");
+        assert_eq!(parse_amount_in("10/3", "BHD").unwrap().to_string(), "3.333");
+        // Typed amounts are kept as entered.
+        assert_eq!(a("0.125"), "0.125");
+        assert!(parse_amount("1/0").is_err());
+        assert!(parse_amount("2+").is_err());
+        assert!(parse_amount("(1+2").is_err());
+        assert!(parse_amount("5 apples").is_err());
+    }
+
+    #[test]
+    fn relative_dates_and_months_resolve_against_today() {
+        // 2025-03-12 is a Wednesday.
+        let today = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+        let d = |s: &str| parse_date_on(s, today).unwrap().to_string();
+        assert_eq!(d("2025-01-31"), "2025-01-31");
+        assert_eq!(d("today"), "2025-03-12");
+        assert_eq!(d(" Yesterday "), "2025-03-11");
+        assert_eq!(d("last friday"), "2025-03-07");
+        assert_eq!(d("last wed"), "2025-03-05");
+        assert_eq!(d("2d ago"), "2025-03-10");
+        assert_eq!(d("3 weeks ago"), "2025-02-19");
+        assert!(parse_date_on("someday", today).is_err());
+        assert!(parse_date_on("2m ago", today).is_err());
+
+        let m = |s: &str| parse_month_on(s, today).unwrap();
+        assert_eq!(m("last"), "2025-02");
+        assert_eq!(m("this"), "2025-03");
+        assert_eq!(m("2024-7"), "2024-07");
+        assert_eq!(
+            parse_month_on("last", NaiveDate::from_ymd_opt(2025, 1, 5).unwrap()).unwrap(),
+            "2024-12"
+        );
+        assert!(parse_month_on("2024-13", today).is_err());
+        assert_eq!(shift_month("2025-01", -1).unwrap(), "2024-12");
+        assert_eq!(shift_month("2024-11", 14).unwrap(), "2026-01");
+    }
+
     #[derive(Serialize)]
     struct Row {
         value: i32,
@@ -514,7 +2131,7 @@ mod tests {
         assert!(printed);
         assert_eq!(
             String::from_utf8(buf).unwrap(),
-            "[\n  {\n    \"value\": 1\n  }\n]\n"
+            "[\n  {\n    \"schema_version\": 1,\n    \"value\": 1\n  }\n]\n"
         );
     }
 
@@ -526,8 +2143,114 @@ mod tests {
         assert!(printed);
         assert_eq!(
             String::from_utf8(buf).unwrap(),
-            "{\"value\":1}\n{\"value\":2}\n"
+            "{\"schema_version\":1,\"value\":1}\n{\"schema_version\":1,\"value\":2}\n"
+        );
+    }
+
+    #[test]
+    fn totals_wrap_json_and_trail_jsonl() {
+        let rows = vec![Row { value: 1 }];
+        let totals = Totals {
+            count: 1,
+            by_currency: [("USD".to_string(), "1.00".to_string())].into(),
+            currency: "USD".into(),
+            converted: "1.00".into(),
+        };
+        let mut buf = Vec::new();
+        assert!(print_json_with_totals_to(true, false, &rows, Some(&totals), &mut buf).unwrap());
+        let body: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(body["schema_version"], 1);
+        assert_eq!(body["rows"][0]["schema_version"], 1);
+        assert_eq!(body["rows"][0]["value"], 1);
+        assert_eq!(body["totals"]["count"], 1);
+
+        let mut buf = Vec::new();
+        assert!(print_json_with_totals_to(false, true, &rows, Some(&totals), &mut buf).unwrap());
+        let text = String::from_utf8(buf).unwrap();
+        let last = text.lines().last().unwrap();
+        let last: serde_json::Value = serde_json::from_str(last).unwrap();
+        assert_eq!(last["schema_version"], 1);
+        assert_eq!(last["totals"]["converted"], "1.00");
+        assert_eq!(
+            totals.lines(),
+            vec![("1.00".to_string(), "USD".to_string())]
+        );
+    }
+
+    #[test]
+    fn timestamps_display_in_the_configured_zone() {
+        let kolkata = parse_timezone("Asia/Kolkata").unwrap();
+        assert_eq!(
+            display_timestamp_in("2025-03-31T20:00:00+00:00", Some(kolkata)),
+            "2025-04-01 01:30"
+        );
+        assert_eq!(
+            display_timestamp_in("2025-03-31", Some(kolkata)),
+            "2025-03-31"
+        );
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn periods_follow_month_start_and_week_start() {
+        let d = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let payday = Periods {
+            week_start: chrono::Weekday::Sun,
+            month_start_day: 25,
+        };
+        assert_eq!(payday.month_of(d("2025-08-24")), "2025-07");
+        assert_eq!(payday.month_of(d("2025-08-25")), "2025-08");
+        assert_eq!(payday.month_of(d("2025-01-10")), "2024-12");
+        assert_eq!(
+            payday.month_range("2025-12").unwrap(),
+            (d("2025-12-25"), d("2026-01-24"))
+        );
+        // 2025-08-06 is a Wednesday.
+        assert_eq!(payday.week_of(d("2025-08-06")), d("2025-08-03"));
+        assert_eq!(Periods::DEFAULT.week_of(d("2025-08-06")), d("2025-08-04"));
+        assert_eq!(
+            Periods::DEFAULT.month_range("2024-02").unwrap(),
+            (d("2024-02-01"), d("2024-02-29"))
+        );
+    }
+
+    #[test]
+    fn valuation_policy_moves_weekends_only() {
+        let day = |d| NaiveDate::from_ymd_opt(2025, 8, d).unwrap();
+        // 2025-08-29 is a Friday, 30/31 the weekend, 09-01 a Monday.
+        let prev = ValuationPolicy::parse("previous").unwrap();
+        let near = ValuationPolicy::parse("Nearest").unwrap();
+        assert_eq!(prev.apply(day(31)), day(31));
+        assert_eq!(near.apply(day(30)), day(29));
+        assert_eq!(
+            near.apply(day(31)),
+            NaiveDate::from_ymd_opt(2025, 9, 1).unwrap()
+        );
+        assert_eq!(near.apply(day(28)), day(28));
+        assert!(ValuationPolicy::parse("later").is_err());
+    }
+
+    #[test]
+    fn amounts_follow_currency_precision_and_rounding() {
+        let d = |s: &str| s.parse::<rust_decimal::Decimal>().unwrap();
+        assert_eq!(fmt_dp(d("0.125"), 2, Rounding::HalfUp), "0.13");
+        assert_eq!(fmt_dp(d("0.125"), 2, Rounding::HalfEven), "0.12");
+        assert_eq!(fmt_dp(d("-2.5"), 0, Rounding::HalfUp), "-3");
+        assert_eq!(fmt_dp(d("7"), 2, Rounding::HalfUp), "7.00");
+        assert_eq!(money_dp("jpy"), 0);
+        assert_eq!(money_dp("KWD"), 3);
+        assert_eq!(
+            [minor_units("jpy"), minor_units("USD"), minor_units("kwd")],
+            [0, 2, 3]
         );
+        assert_eq!(with_symbol("12.50", "eur"), "€12.50");
+        assert_eq!(with_symbol("-40.00", "INR"), "-₹40.00");
+        assert_eq!(with_symbol("1235", "JPY"), "¥1235");
+        assert_eq!(with_symbol("3.00", "CHF"), "CHF 3.00");
+        assert_eq!(fmt_amount(d("1234.5"), "JPY"), "1235");
+        assert_eq!(fmt_signed(d("3"), "JPY"), "+3");
+        assert!(check_precision("quantity_dp", 2).is_err());
+        assert!(Rounding::parse("bankers").is_ok());
     }
 
     #[test]
@@ -538,4 +2261,51 @@ mod tests {
         assert!(!printed);
         assert!(buf.is_empty());
     }
+
+    #[test]
+    fn markdown_tables_use_pipe_syntax() {
+        let rows = vec![vec!["Groceries".to_string(), "12.50".to_string()]];
+        let table = styled_table(&["Category", "Spent"], rows, OutputFormat::Markdown);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("| Category"));
+        assert!(lines[1].starts_with("|-"));
+        assert!(lines[2].starts_with("| Groceries"));
+
+        let rows = vec![vec!["A|B Ltd".to_string(), "1.00".to_string()]];
+        let table = styled_table(&["Payee", "Amount"], rows, OutputFormat::Markdown);
+        let row = table.lines().nth(2).unwrap();
+        assert!(row.starts_with("| A\\|B Ltd"), "{row}");
+        assert_eq!(row.matches('|').count(), 4);
+    }
+
+    #[test]
+    fn bars_and_sparklines_scale_to_the_maximum() {
+        let d = |s: &str| s.parse::<rust_decimal::Decimal>().unwrap();
+        assert_eq!(bar(d("50"), d("100"), 4), "██");
+        assert_eq!(bar(d("10"), d("80"), 1), "▏");
+        assert_eq!(bar(d("150"), d("100"), 3), "███");
+        assert_eq!(bar(d("-5"), d("100"), 3), "");
+        assert_eq!(sparkline(&[d("0"), d("7"), d("3.5")]), "▁█▅");
+        assert_eq!(sparkline(&[d("2"), d("2")]), "▄▄");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn quarters_parse_labels_and_relative_names() {
+        let d = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = d(2025, 2, 14);
+        assert_eq!(
+            parse_quarter_on("2025-Q3", today).unwrap(),
+            ("2025-Q3".to_string(), d(2025, 7, 1), d(2025, 9, 30))
+        );
+        assert_eq!(
+            parse_quarter_on("2024q4", today).unwrap().2,
+            d(2024, 12, 31)
+        );
+        assert_eq!(parse_quarter_on("this", today).unwrap().0, "2025-Q1");
+        assert_eq!(parse_quarter_on("last", today).unwrap().0, "2024-Q4");
+        assert!(parse_quarter_on("2025-Q5", today).is_err());
+        assert!(parse_quarter_on("2025-03", today).is_err());
+    }
 }
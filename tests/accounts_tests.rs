@@ -0,0 +1,257 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use moneyclip::{cli, commands::accounts};
+use rusqlite::Connection;
+
+fn setup() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO settings VALUES ('base_currency','USD');
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, type TEXT, currency TEXT);
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT, account_id INTEGER,
+            amount TEXT, payee TEXT, category_id INTEGER, currency TEXT, note TEXT);
+        CREATE TABLE fx_rates(date TEXT NOT NULL, base TEXT NOT NULL, quote TEXT NOT NULL, rate TEXT NOT NULL, UNIQUE(date, base, quote));
+        INSERT INTO accounts VALUES (1,'Revolut','bank','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-01-10',1,'100.00','Deposit','USD'),
+            ('2025-02-10',1,'-50.00','Shop','USD'),
+            ('2025-02-11',1,'-7.5','Cafe','GBP');
+        INSERT INTO fx_rates VALUES ('2025-01-01','USD','EUR','0.90'), ('2025-02-01','USD','EUR','0.80');
+        "#,
+    )
+    .unwrap();
+    conn
+}
+
+fn run(conn: &Connection, args: &[&str]) -> anyhow::Result<()> {
+    let argv = ["moneyclip", "account", "set-currency", "--name", "Revolut"]
+        .iter()
+        .chain(args);
+    let matches = cli::build_cli().get_matches_from(argv);
+    let Some(("account", sub)) = matches.subcommand() else {
+        panic!("account not parsed");
+    };
+    accounts::handle(conn, sub)
+}
+
+fn amounts(conn: &Connection) -> Vec<(String, String)> {
+    conn.prepare("SELECT amount, currency FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn set_currency_converts_history_at_each_dates_rate() {
+    let conn = setup();
+    let plan = accounts::plan_currency_change(&conn, "Revolut", "EUR", true).unwrap();
+    assert_eq!(plan.balance_before.to_string(), "50.00");
+    assert_eq!(plan.balance_after.to_string(), "50.00"); // 90 - 40
+    assert_eq!(plan.other_currency, 1);
+
+    run(
+        &conn,
+        &["--currency", "eur", "--convert-history", "--dry-run"],
+    )
+    .unwrap();
+    assert_eq!(amounts(&conn)[0], ("100.00".into(), "USD".into()));
+
+    run(&conn, &["--currency", "eur", "--convert-history"]).unwrap();
+    assert_eq!(
+        amounts(&conn),
+        vec![
+            ("90.00".into(), "EUR".into()),
+            ("-40.00".into(), "EUR".into()),
+            ("-7.5".into(), "GBP".into()),
+        ]
+    );
+    let ccy: String = conn
+        .query_row("SELECT currency FROM accounts", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(ccy, "EUR");
+    assert!(run(&conn, &["--currency", "EUR", "--keep-history"]).is_err());
+}
+
+#[test]
+fn set_currency_can_relabel_without_converting() {
+    let conn = setup();
+    run(&conn, &["--currency", "INR", "--keep-history"]).unwrap();
+    assert_eq!(amounts(&conn)[1], ("-50.00".into(), "INR".into()));
+
+    let parsed = cli::build_cli().try_get_matches_from([
+        "moneyclip",
+        "account",
+        "set-currency",
+        "--name",
+        "Revolut",
+        "--currency",
+        "EUR",
+    ]);
+    assert!(parsed.is_err(), "one of the history flags is required");
+}
+
+#[test]
+fn rm_with_transactions_needs_yes() {
+    let conn = setup();
+    conn.execute("INSERT INTO accounts VALUES (2,'Spare','cash','USD')", [])
+        .unwrap();
+    let rm = |args: &[&str]| {
+        let argv = ["moneyclip", "--no-input", "account", "rm"]
+            .iter()
+            .chain(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("account", sub)) = matches.subcommand() else {
+            panic!("account not parsed");
+        };
+        accounts::handle(&conn, sub)
+    };
+    let impact = moneyclip::utils::removal_impact(
+        &conn,
+        1,
+        &[
+            ("transactions", "account_id", "deleted"),
+            ("trades", "account_id", "deleted"),
+        ],
+    )
+    .unwrap();
+    assert_eq!(impact, vec![(3, "transactions", "deleted")]);
+
+    let err = rm(&["--name", "Revolut"]).unwrap_err();
+    assert!(err.to_string().contains("pass --yes"), "{err}");
+    rm(&["--name", "Spare"]).unwrap();
+    assert!(rm(&["--name", "Spare"]).is_err());
+    rm(&["--name", "Revolut", "--yes"]).unwrap();
+    let left: i64 = conn
+        .query_row("SELECT COUNT(*) FROM accounts", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(left, 0);
+}
+
+#[test]
+fn merge_moves_history_and_archives_the_duplicate() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE accounts ADD COLUMN group_name TEXT;
+        ALTER TABLE accounts ADD COLUMN default_category_id INTEGER;
+        ALTER TABLE accounts ADD COLUMN archived_at TEXT;
+        CREATE TABLE trades(id INTEGER PRIMARY KEY, account_id INTEGER);
+        CREATE TABLE grants(id INTEGER PRIMARY KEY, account_id INTEGER);
+        CREATE TABLE dividends(id INTEGER PRIMARY KEY, account_id INTEGER, trade_id INTEGER);
+        ALTER TABLE transactions ADD COLUMN jar_id INTEGER;
+        CREATE TABLE jars(id INTEGER PRIMARY KEY, account_id INTEGER NOT NULL, name TEXT NOT NULL,
+            UNIQUE(account_id, name));
+        CREATE TABLE jar_moves(id INTEGER PRIMARY KEY, date TEXT, from_jar_id INTEGER,
+            to_jar_id INTEGER, amount TEXT NOT NULL, note TEXT);
+        INSERT INTO jars VALUES (1,1,'Holiday'), (2,2,'holiday'), (3,2,'Gifts');
+        INSERT INTO jar_moves(date,from_jar_id,to_jar_id,amount) VALUES
+            ('2025-03-01',NULL,2,'10'), ('2025-03-02',NULL,3,'5');
+        INSERT INTO accounts(id,name,type,currency,group_name) VALUES
+            (2,'Revolut Old','bank','USD','Liquid'), (3,'Euro','bank','EUR',NULL);
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-03-01',2,'25.00','Refund','USD');
+        INSERT INTO trades(account_id) VALUES (2);
+        INSERT INTO dividends(account_id, trade_id) VALUES (2, 1);
+        CREATE TABLE account_interest(account_id INTEGER PRIMARY KEY, apy TEXT NOT NULL);
+        INSERT INTO account_interest VALUES (2, '4.5');
+        "#,
+    )
+    .unwrap();
+    let merge = |args: &[&str]| {
+        let argv = ["moneyclip", "account", "merge"].iter().chain(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("account", sub)) = matches.subcommand() else {
+            panic!("account not parsed");
+        };
+        accounts::handle(&conn, sub)
+    };
+
+    let plan = accounts::plan_merge(&conn, "Revolut Old", "Revolut").unwrap();
+    assert_eq!(
+        (
+            plan.transactions,
+            plan.trades,
+            plan.grants,
+            plan.dividends,
+            plan.jars
+        ),
+        (1, 1, 0, 1, 2)
+    );
+    assert!(plan.interest);
+    assert_eq!(plan.balance_after.to_string(), "67.50");
+    conn.execute("INSERT INTO account_interest VALUES (1, '3')", [])
+        .unwrap();
+    let err = accounts::plan_merge(&conn, "Revolut Old", "Revolut").unwrap_err();
+    assert!(err.to_string().contains("earn interest"), "{err}");
+    conn.execute("DELETE FROM account_interest WHERE account_id=1", [])
+        .unwrap();
+    assert!(accounts::plan_merge(&conn, "Euro", "Revolut").is_err());
+    assert!(accounts::plan_merge(&conn, "Revolut", "Revolut").is_err());
+
+    merge(&["--from", "Revolut Old", "--into", "Revolut", "--dry-run"]).unwrap();
+    let on_old: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM transactions WHERE account_id=2",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(on_old, 1);
+
+    merge(&["--from", "Revolut Old", "--into", "Revolut"]).unwrap();
+    let moved: (i64, i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM transactions WHERE account_id=1),
+                    (SELECT COUNT(*) FROM trades WHERE account_id=1),
+                    (SELECT COUNT(*) FROM dividends WHERE account_id=1)",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(moved, (4, 1, 1));
+    let (group, archived): (Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT (SELECT group_name FROM accounts WHERE id=1),
+                    (SELECT archived_at FROM accounts WHERE id=2)",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(group.as_deref(), Some("Liquid"));
+    let jars: Vec<(i64, i64, String)> = conn
+        .prepare("SELECT id, account_id, name FROM jars ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        jars,
+        vec![(1, 1, "Holiday".into()), (3, 1, "Gifts".into())],
+        "same-named jar folded in, the other moved"
+    );
+    let holiday: String = conn
+        .query_row("SELECT amount FROM jar_moves WHERE to_jar_id=1", [], |r| {
+            r.get(0)
+        })
+        .unwrap();
+    assert_eq!(holiday, "10");
+    let apy: String = conn
+        .query_row(
+            "SELECT apy FROM account_interest WHERE account_id=1",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(apy, "4.5");
+    assert!(archived.is_some());
+    assert!(merge(&["--from", "Revolut Old", "--into", "Revolut"]).is_err());
+}
@@ -0,0 +1,124 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use moneyclip::commands::batch;
+use rusqlite::Connection;
+
+fn base_conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT UNIQUE, type TEXT, currency TEXT, default_category_id INTEGER);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT UNIQUE, monthly_cap TEXT, cap_currency TEXT);
+        CREATE TABLE closed_periods(month TEXT PRIMARY KEY, closed_at TEXT);
+        CREATE TABLE transactions(
+            id INTEGER PRIMARY KEY,
+            date TEXT NOT NULL,
+            account_id INTEGER NOT NULL,
+            amount TEXT NOT NULL,
+            payee TEXT NOT NULL,
+            category_id INTEGER,
+            currency TEXT NOT NULL,
+            note TEXT
+        );
+        CREATE TABLE rules(id INTEGER PRIMARY KEY, pattern TEXT NOT NULL, category_id INTEGER, payee_rewrite TEXT, note TEXT, created_at TEXT, ignore_case INTEGER NOT NULL DEFAULT 0, literal INTEGER NOT NULL DEFAULT 0, match_field TEXT NOT NULL DEFAULT 'both', skip INTEGER NOT NULL DEFAULT 0);
+        "#,
+    )
+    .unwrap();
+    conn
+}
+
+fn count(conn: &Connection, table: &str) -> i64 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |r| r.get(0))
+        .unwrap()
+}
+
+#[test]
+fn batch_commits_all_lines_together() {
+    let mut conn = base_conn();
+    let script = r#"
+        # monthly close
+        account add --name Cash --type cash --currency USD
+        moneyclip category add --name "Eating Out"
+        tx add --date 2025-07-31 --account Cash --amount -12.50 --payee "Late lunch" --category "Eating Out"
+    "#;
+    assert_eq!(batch::run_script(&mut conn, script).unwrap(), 3);
+    assert_eq!(count(&conn, "accounts"), 1);
+    assert_eq!(count(&conn, "transactions"), 1);
+}
+
+#[test]
+fn batch_rolls_back_everything_on_failure() {
+    let mut conn = base_conn();
+    let script = r#"
+        account add --name Cash --type cash --currency USD
+        tx add --date 2025-07-31 --account Cash --amount -1 --payee Ok
+        tx add --date 2025-07-31 --account Missing --amount -1 --payee Broken
+    "#;
+    let err = batch::run_script(&mut conn, script).unwrap_err();
+    assert!(format!("{err:#}").contains("Line 4"), "{err:#}");
+    assert_eq!(count(&conn, "accounts"), 0);
+    assert_eq!(count(&conn, "transactions"), 0);
+
+    let err = batch::run_script(&mut conn, "tx add --bogus").unwrap_err();
+    assert!(err.to_string().starts_with("Line 1:"));
+    assert!(batch::run_script(&mut conn, "db maintain").is_err());
+}
+
+#[test]
+fn batch_lines_do_not_leak_display_overrides() {
+    let mut conn = base_conn();
+    let before = moneyclip::utils::precision();
+    let script = r#"
+        tx list --money-dp 0 --symbols --rounding half-even
+        tx list --json
+    "#;
+    batch::run_script(&mut conn, script).unwrap();
+    assert_eq!(moneyclip::utils::precision(), before);
+}
+
+#[test]
+fn ledgers_keep_their_display_settings_to_themselves() {
+    use moneyclip::Ledger;
+    use moneyclip::utils::{Precision, precision};
+    let conn = base_conn();
+    conn.execute(
+        "INSERT INTO settings(key,value) VALUES ('money_dp','0')",
+        [],
+    )
+    .unwrap();
+    let mut ours = Ledger::new(conn);
+    ours.load_settings().unwrap();
+    let mut theirs = Ledger::new(base_conn());
+    theirs.load_settings().unwrap();
+
+    assert_eq!(ours.with(|_| precision().money), 0);
+    assert_eq!(theirs.with(|_| precision().money), 2);
+    assert_eq!(ours.with(|_| theirs.with(|_| precision().money)), 2);
+    assert_eq!(precision(), Precision::DEFAULT);
+}
+
+#[test]
+fn batch_runs_commands_that_open_their_own_savepoint() {
+    let mut conn = base_conn();
+    conn.execute_batch("CREATE TABLE cpi(month TEXT PRIMARY KEY, value TEXT NOT NULL);")
+        .unwrap();
+    let script = r#"
+        cpi set --month 2025-01 --value 310.5
+        cpi set --month 2025-02 --value 311.2
+    "#;
+    assert_eq!(batch::run_script(&mut conn, script).unwrap(), 2);
+    assert_eq!(count(&conn, "cpi"), 2);
+
+    // A later failure still rolls the CPI rows back with the rest of the batch.
+    let script = r#"
+        cpi set --month 2025-03 --value 312.0
+        tx add --date 2025-07-31 --account Missing --amount -1 --payee Broken
+    "#;
+    assert!(batch::run_script(&mut conn, script).is_err());
+    assert_eq!(count(&conn, "cpi"), 2);
+}
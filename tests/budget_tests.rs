@@ -13,7 +13,7 @@ fn base_conn() -> Connection {
         PRAGMA foreign_keys = ON;
         CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
         CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
-        CREATE TABLE budgets(id INTEGER PRIMARY KEY AUTOINCREMENT, month TEXT NOT NULL, category_id INTEGER NOT NULL, amount TEXT NOT NULL, UNIQUE(month, category_id));
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY AUTOINCREMENT, month TEXT NOT NULL, category_id INTEGER NOT NULL, amount TEXT NOT NULL, currency TEXT, UNIQUE(month, category_id));
         CREATE TABLE transactions(id INTEGER PRIMARY KEY AUTOINCREMENT, date TEXT NOT NULL, account_id INTEGER, amount TEXT NOT NULL, payee TEXT, category_id INTEGER, currency TEXT NOT NULL, note TEXT);
         CREATE TABLE fx_rates(date TEXT NOT NULL, base TEXT NOT NULL, quote TEXT NOT NULL, rate TEXT NOT NULL, UNIQUE(date, base, quote));
     "#).unwrap();
@@ -111,3 +111,169 @@ fn budget_spent_in_base_from_foreign() {
     // Format with two decimal places to ensure trailing zeros
     assert_eq!(format!("{:.2}", total), "10.00");
 }
+
+fn run(conn: &Connection, args: &[&str]) {
+    let argv = ["moneyclip", "budget"].iter().chain(args);
+    let matches = cli::build_cli().get_matches_from(argv);
+    let Some(("budget", sub)) = matches.subcommand() else {
+        panic!("budget not parsed");
+    };
+    budgets::handle(conn, sub).unwrap();
+}
+
+#[test]
+fn budgets_keep_their_currency_and_rebase_after_base_change() {
+    let mut conn = setup();
+    run(
+        &conn,
+        &[
+            "set",
+            "--month",
+            "2025-08",
+            "--category",
+            "Dining",
+            "--amount",
+            "90",
+            "--currency",
+            "eur",
+        ],
+    );
+    // 90 EUR at 0.90 is 100 USD in the base-currency report.
+    let rows = budgets::build_budget_report(&conn, "2025-08", "USD", None).unwrap();
+    assert_eq!(rows[0].budget, "100.00");
+
+    // A legacy row without a currency is read as the base.
+    conn.execute(
+        "INSERT INTO budgets(month, category_id, amount) VALUES('2025-07', 1, '45.00')",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-07-01','USD','EUR','0.80')",
+        [],
+    )
+    .unwrap();
+    run(&conn, &["rebase", "--to", "EUR", "--dry-run"]);
+    let legacy: Option<String> = conn
+        .query_row(
+            "SELECT currency FROM budgets WHERE month='2025-07'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(legacy, None);
+
+    run(&conn, &["rebase", "--to", "EUR"]);
+    let rows: Vec<(String, String, String)> = conn
+        .prepare("SELECT month, amount, currency FROM budgets ORDER BY month")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            ("2025-07".into(), "36.00".into(), "EUR".into()),
+            ("2025-08".into(), "90".into(), "EUR".into()),
+        ]
+    );
+
+    // Rebased amounts are rounded to the target currency's minor units.
+    conn.execute(
+        "INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-07-01','USD','JPY','147.333')",
+        [],
+    )
+    .unwrap();
+    run(&conn, &["rebase", "--to", "JPY"]);
+    let amounts: Vec<String> = conn
+        .prepare("SELECT amount FROM budgets WHERE currency='JPY' ORDER BY month")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(amounts, vec!["6630", "14733"]);
+
+    // A display override does not change what is stored.
+    conn.execute(
+        "INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-07-01','USD','GBP','0.7777')",
+        [],
+    )
+    .unwrap();
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "--money-dp",
+        "0",
+        "budget",
+        "rebase",
+        "--to",
+        "GBP",
+    ]);
+    moneyclip::commands::dispatch(&mut conn, &matches).unwrap();
+    let amounts: Vec<String> = conn
+        .prepare("SELECT amount FROM budgets WHERE currency='GBP' ORDER BY month")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(amounts, vec!["35.00", "77.77"]);
+}
+
+#[test]
+fn scenario_compares_savings_rate_without_touching_budgets() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE budget_scenarios(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, month TEXT NOT NULL);
+        CREATE TABLE budget_scenario_lines(id INTEGER PRIMARY KEY AUTOINCREMENT, scenario_id INTEGER NOT NULL REFERENCES budget_scenarios(id) ON DELETE CASCADE,
+            category_id INTEGER NOT NULL, amount TEXT NOT NULL, currency TEXT NOT NULL, UNIQUE(scenario_id, category_id));
+        INSERT INTO categories(name) VALUES('Rent');
+        INSERT INTO budgets(month, category_id, amount, currency) VALUES('2025-08', 2, '1000', 'USD');
+        INSERT INTO transactions(date, amount, payee, currency) VALUES('2025-08-01', '2000', 'Salary', 'USD');
+        "#,
+    )
+    .unwrap();
+    run(
+        &conn,
+        &[
+            "scenario",
+            "create",
+            "--name",
+            "new apartment",
+            "--month",
+            "2025-08",
+        ],
+    );
+    run(
+        &conn,
+        &[
+            "scenario",
+            "set",
+            "--name",
+            "new apartment",
+            "--category",
+            "Rent",
+            "--amount",
+            "1450",
+        ],
+    );
+
+    let cmp = moneyclip::commands::scenarios::compare(&conn, "new apartment", None).unwrap();
+    assert_eq!(cmp.income.to_string(), "2000");
+    assert_eq!(cmp.baseline_total.to_string(), "1050.00");
+    assert_eq!(cmp.scenario_total.to_string(), "1500.00");
+    assert_eq!(cmp.baseline_rate.unwrap().round_dp(1).to_string(), "47.5");
+    assert_eq!(cmp.scenario_rate.unwrap().round_dp(1).to_string(), "25.0");
+    let rent: String = conn
+        .query_row("SELECT amount FROM budgets WHERE category_id=2", [], |r| {
+            r.get(0)
+        })
+        .unwrap();
+    assert_eq!(rent, "1000");
+
+    let cmp =
+        moneyclip::commands::scenarios::compare(&conn, "new apartment", Some(3000.into())).unwrap();
+    assert_eq!(cmp.scenario_rate.unwrap().round_dp(0).to_string(), "50");
+}
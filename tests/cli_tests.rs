@@ -0,0 +1,111 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use moneyclip::cli;
+
+fn mutates(args: &[&str]) -> bool {
+    let mut argv = vec!["moneyclip", "--read-only"];
+    argv.extend_from_slice(args);
+    let matches = cli::build_cli().get_matches_from(argv);
+    assert!(matches.get_flag("read-only"));
+    cli::command_mutates(&matches)
+}
+
+#[test]
+fn read_only_classifies_commands() {
+    assert!(!mutates(&["report", "balances"]));
+    assert!(!mutates(&["portfolio", "price", "list"]));
+    assert!(!mutates(&["fx", "backfill", "--dry-run"]));
+    assert!(!mutates(&["portfolio", "value"]));
+
+    assert!(mutates(&["init"]));
+    assert!(mutates(&["portfolio", "value", "--live"]));
+    assert!(mutates(&["fx", "backfill"]));
+    assert!(mutates(&[
+        "account",
+        "add",
+        "--name",
+        "A",
+        "--type",
+        "bank",
+        "--currency",
+        "USD"
+    ]));
+    assert!(mutates(&["db", "maintain"]));
+    assert!(mutates(&["report", "snapshot", "save", "--name", "x"]));
+    assert!(!mutates(&["report", "diff", "x"]));
+}
+
+#[test]
+fn moneyclip_json_adds_json_only_where_it_parses() {
+    let args = |list: &[&str]| {
+        list.iter()
+            .map(std::ffi::OsString::from)
+            .collect::<Vec<_>>()
+    };
+    // Only this test reads MONEYCLIP_JSON.
+    unsafe { std::env::set_var("MONEYCLIP_JSON", "1") };
+    let listed = cli::with_env_json(args(&["moneyclip", "report", "balances"]));
+    let lines = cli::with_env_json(args(&["moneyclip", "tx", "list", "--jsonl"]));
+    let added = cli::with_env_json(args(&["moneyclip", "config", "list"]));
+    unsafe { std::env::remove_var("MONEYCLIP_JSON") };
+
+    assert_eq!(listed, args(&["moneyclip", "report", "balances", "--json"]));
+    assert_eq!(lines, args(&["moneyclip", "tx", "list", "--jsonl"]));
+    assert_eq!(added, args(&["moneyclip", "config", "list"]));
+}
+
+fn collect_examples(
+    cmd: &clap::Command,
+    path: &str,
+    missing: &mut Vec<String>,
+    lines: &mut Vec<String>,
+) {
+    for sub in cmd.get_subcommands() {
+        if sub.is_hide_set() || sub.get_name() == "help" {
+            continue;
+        }
+        let path = format!("{path} {}", sub.get_name()).trim().to_string();
+        let help = sub
+            .get_after_help()
+            .map(|h| h.to_string())
+            .unwrap_or_default();
+        match help.split_once("Examples:") {
+            Some((_, examples)) => lines.extend(
+                examples
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(String::from),
+            ),
+            None => missing.push(path.clone()),
+        }
+        collect_examples(sub, &path, missing, lines);
+    }
+}
+
+#[test]
+fn every_command_has_examples_that_parse() {
+    let mut missing = Vec::new();
+    let mut lines = Vec::new();
+    collect_examples(&cli::build_cli(), "", &mut missing, &mut lines);
+    assert!(missing.is_empty(), "no examples for: {missing:?}");
+
+    let broken: Vec<String> = lines
+        .iter()
+        .filter_map(|line| {
+            let argv = shell_words::split(line).unwrap();
+            assert_eq!(argv[0], "moneyclip", "{line}");
+            let err = cli::build_cli().try_get_matches_from(&argv).err()?;
+            Some(format!("{line}\n{}", err.render()))
+        })
+        .collect();
+    assert!(
+        broken.is_empty(),
+        "examples that do not parse:\n{}",
+        broken.join("\n")
+    );
+}
@@ -0,0 +1,125 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use moneyclip::{cli, commands::database};
+use rusqlite::Connection;
+
+#[test]
+fn db_maintain_keeps_last_price_per_day() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE prices(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            asset_id INTEGER NOT NULL,
+            as_of TEXT NOT NULL,
+            price TEXT NOT NULL,
+            source TEXT NOT NULL,
+            UNIQUE(asset_id, as_of)
+        );
+        INSERT INTO prices(asset_id,as_of,price,source) VALUES (1,'2025-01-02T09:00:00+00:00','10','yahoo');
+        INSERT INTO prices(asset_id,as_of,price,source) VALUES (1,'2025-01-02T15:00:00+00:00','11','yahoo');
+        INSERT INTO prices(asset_id,as_of,price,source) VALUES (1,'2025-01-03T15:00:00+00:00','12','yahoo');
+        INSERT INTO prices(asset_id,as_of,price,source) VALUES (2,'2025-01-02T09:00:00+00:00','50','yahoo');
+        "#,
+    )
+    .unwrap();
+
+    let matches = cli::build_cli().get_matches_from(["moneyclip", "db", "maintain"]);
+    let Some(("db", db_m)) = matches.subcommand() else {
+        panic!("db command not parsed");
+    };
+    database::handle(&conn, db_m).unwrap();
+
+    let mut stmt = conn
+        .prepare("SELECT asset_id, price FROM prices ORDER BY asset_id, as_of")
+        .unwrap();
+    let rows: Vec<(i64, String)> = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(
+        rows,
+        vec![(1, "11".into()), (1, "12".into()), (2, "50".into())]
+    );
+}
+
+#[test]
+fn repair_decimals_rewrites_readable_values_and_quarantines_the_rest() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT, amount TEXT, payee TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT, monthly_cap TEXT);
+        CREATE TABLE quarantined_rows(id INTEGER PRIMARY KEY AUTOINCREMENT, table_name TEXT,
+            row_id INTEGER, column_name TEXT, raw TEXT, row_json TEXT,
+            quarantined_at TEXT DEFAULT (datetime('now')));
+        INSERT INTO transactions VALUES
+            (1,'2025-01-02','-12.50','Ok'),
+            (2,'2025-01-03','1.5E+3','Sci'),
+            (3,'2025-01-04','-1.234,56','Locale'),
+            (4,'2025-01-05','','Empty');
+        INSERT INTO categories VALUES (1,'Dining','12,5'), (2,'Travel','n/a'), (3,'Rent',NULL);
+        "#,
+    )
+    .unwrap();
+
+    let bad = database::find_bad_decimals(&conn).unwrap();
+    let found: Vec<(&str, i64, Option<String>)> = bad
+        .iter()
+        .map(|b| (b.table, b.rowid, b.repaired.map(|d| d.to_string())))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("transactions", 2, Some("1500".into())),
+            ("transactions", 3, Some("-1234.56".into())),
+            ("transactions", 4, None),
+            ("categories", 1, Some("12.5".into())),
+            ("categories", 2, None),
+        ]
+    );
+
+    let run = |args: &[&str]| {
+        let argv = ["moneyclip", "db", "repair-decimals"].iter().chain(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("db", db_m)) = matches.subcommand() else {
+            panic!("db command not parsed");
+        };
+        database::handle(&conn, db_m).unwrap();
+    };
+    run(&["--quarantine", "--dry-run"]);
+    assert_eq!(database::find_bad_decimals(&conn).unwrap().len(), 5);
+
+    run(&["--quarantine"]);
+    assert!(database::find_bad_decimals(&conn).unwrap().is_empty());
+    let amounts: Vec<String> = conn
+        .prepare("SELECT amount FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(amounts, vec!["-12.50", "1500", "-1234.56"]);
+    let cap: Option<String> = conn
+        .query_row("SELECT monthly_cap FROM categories WHERE id=2", [], |r| {
+            r.get(0)
+        })
+        .unwrap();
+    assert_eq!(cap, None);
+    let quarantined: Vec<(String, String)> = conn
+        .prepare("SELECT table_name, row_json FROM quarantined_rows ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(quarantined.len(), 2);
+    assert!(quarantined[0].1.contains(r#""payee":"Empty""#));
+    assert_eq!(quarantined[1].0, "categories");
+    assert!(database::repair_decimal("(1,234.00)").unwrap() == "-1234".parse().unwrap());
+}
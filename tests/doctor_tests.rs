@@ -0,0 +1,178 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use chrono::NaiveDate;
+use moneyclip::cli;
+use moneyclip::commands::doctor::{self, Severity};
+use rusqlite::Connection;
+
+#[test]
+fn doctor_flags_stale_reference_data() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT, account_id INTEGER,
+            amount TEXT, payee TEXT, category_id INTEGER, currency TEXT, note TEXT);
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT, category_id INTEGER, amount TEXT);
+        CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT, name TEXT, currency TEXT);
+        CREATE TABLE trades(id INTEGER PRIMARY KEY, date TEXT, asset_id INTEGER, account_id INTEGER,
+            quantity TEXT, price TEXT, fees TEXT, side TEXT, note TEXT);
+        CREATE TABLE prices(id INTEGER PRIMARY KEY, asset_id INTEGER, as_of TEXT, price TEXT, source TEXT);
+        CREATE TABLE grants(id INTEGER PRIMARY KEY, asset_id INTEGER, account_id INTEGER);
+
+        INSERT INTO accounts VALUES (1,'Checking','bank','USD'), (2,'Old Card','card','USD'),
+            (3,'Broker','broker','USD'), (4,'Empty','cash','USD');
+        INSERT INTO categories VALUES (1,'Groceries'), (2,'Gym'), (3,'Hobbies');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-09-01',1,'-10','Market',1,'USD'),
+            ('2023-02-11',2,'-5','Cafe',NULL,'USD');
+        INSERT INTO budgets(month,category_id,amount) VALUES
+            ('2025-08',1,'100'), ('2025-07',2,'30'), ('2025-08',2,'30');
+        INSERT INTO assets VALUES (1,'VTI','Total Market','USD'), (2,'OLD','Delisted','USD'),
+            (3,'RSU','Employer','USD');
+        INSERT INTO trades(date,asset_id,account_id,quantity,price,side) VALUES
+            ('2025-06-01',1,3,'1','200','buy');
+        INSERT INTO grants(asset_id,account_id) VALUES (3,3);
+        "#,
+    )
+    .unwrap();
+
+    let today = NaiveDate::from_ymd_opt(2025, 10, 1).unwrap();
+    let findings = doctor::stale_checks(&conn, today, 12).unwrap();
+    assert!(findings.iter().all(|f| f.severity == Severity::Warn));
+    let got: Vec<(&str, &str, &str)> = findings
+        .iter()
+        .map(|f| (f.code, f.entity.as_str(), f.detail.as_str()))
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            (
+                "budget_never_spent",
+                "Gym",
+                "2 budgeted months (2025-07..2025-08), no transactions"
+            ),
+            ("unused_category", "Hobbies", "no transactions or budgets"),
+            ("unused_asset", "OLD", "no trades, grants or prices"),
+            ("inactive_account", "Empty", "no activity"),
+            ("inactive_account", "Old Card", "last activity 2023-02-11"),
+        ]
+    );
+
+    // A shorter window also catches the broker, whose last trade was in June.
+    let findings = doctor::stale_checks(&conn, today, 3).unwrap();
+    assert!(
+        findings
+            .iter()
+            .any(|f| f.entity == "Broker" && f.detail == "last activity 2025-06-01")
+    );
+}
+
+#[test]
+fn doctor_severity_filter_sets_exit_status() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO settings VALUES ('base_currency','USD');
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT, account_id INTEGER,
+            amount TEXT, payee TEXT, category_id INTEGER, currency TEXT, note TEXT);
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT, category_id INTEGER, amount TEXT);
+        CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT, name TEXT, currency TEXT);
+        CREATE TABLE trades(id INTEGER PRIMARY KEY, date TEXT, asset_id INTEGER, account_id INTEGER,
+            quantity TEXT, price TEXT, fees TEXT, side TEXT, note TEXT);
+        CREATE TABLE prices(id INTEGER PRIMARY KEY, asset_id INTEGER, as_of TEXT, price TEXT, source TEXT);
+        CREATE TABLE grants(id INTEGER PRIMARY KEY, asset_id INTEGER, account_id INTEGER);
+        CREATE TABLE fx_rates(date TEXT, base TEXT, quote TEXT, rate TEXT);
+        INSERT INTO accounts VALUES (1,'Travel','bank','EUR'), (2,'Idle','cash','USD');
+        "#,
+    )
+    .unwrap();
+    let today = NaiveDate::from_ymd_opt(2025, 10, 1).unwrap();
+    conn.execute(
+        "INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES (?1,1,'-3','Cafe','EUR')",
+        [today.to_string()],
+    )
+    .unwrap();
+
+    let findings = doctor::run_checks(&conn, today, 12).unwrap();
+    assert_eq!(findings[0].code, "missing_fx");
+    assert!(
+        findings
+            .iter()
+            .all(|f| f.code != "account_currency_mismatch")
+    );
+    assert_eq!(findings[0].severity, Severity::Error);
+    assert!(findings[1..].iter().all(|f| f.severity == Severity::Warn));
+
+    let run = |args: &[&str]| {
+        let argv = ["moneyclip", "doctor", "--json"].iter().chain(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("doctor", sub)) = matches.subcommand() else {
+            panic!("doctor not parsed");
+        };
+        doctor::handle(&conn, sub).unwrap();
+        moneyclip::utils::exit_code()
+    };
+    assert_eq!(run(&[]), doctor::EXIT_ERRORS);
+    conn.execute_batch("INSERT INTO fx_rates VALUES ('2025-01-01','USD','EUR','0.9')")
+        .unwrap();
+    assert_eq!(run(&[]), doctor::EXIT_WARNINGS);
+    assert_eq!(run(&["--severity", "error"]), 0);
+    assert!(
+        cli::build_cli()
+            .try_get_matches_from(["moneyclip", "doctor", "--inactive-months", "-1"])
+            .is_err()
+    );
+}
+
+#[test]
+fn doctor_warns_about_rows_in_another_currency_than_their_account() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO settings VALUES ('base_currency','USD');
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT, account_id INTEGER,
+            amount TEXT, payee TEXT, category_id INTEGER, currency TEXT, note TEXT);
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT, category_id INTEGER, amount TEXT);
+        CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT, name TEXT, currency TEXT);
+        CREATE TABLE trades(id INTEGER PRIMARY KEY, date TEXT, asset_id INTEGER, account_id INTEGER,
+            quantity TEXT, price TEXT, fees TEXT, side TEXT, note TEXT);
+        CREATE TABLE prices(id INTEGER PRIMARY KEY, asset_id INTEGER, as_of TEXT, price TEXT, source TEXT);
+        CREATE TABLE grants(id INTEGER PRIMARY KEY, asset_id INTEGER, account_id INTEGER);
+        CREATE TABLE fx_rates(date TEXT, base TEXT, quote TEXT, rate TEXT);
+        INSERT INTO fx_rates VALUES ('2025-01-01','USD','EUR','0.9');
+        INSERT INTO accounts VALUES (1,'Checking','bank','USD'), (2,'Travel','bank','EUR');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-09-01',1,'-10','Market','USD'),
+            ('2025-09-02',1,'-4','Cafe','EUR'),
+            ('2025-09-03',1,'-6','Museum','eur'),
+            ('2025-09-04',2,'-8','Train','EUR');
+        "#,
+    )
+    .unwrap();
+    let today = NaiveDate::from_ymd_opt(2025, 10, 1).unwrap();
+    let mismatches: Vec<_> = doctor::run_checks(&conn, today, 12)
+        .unwrap()
+        .into_iter()
+        .filter(|f| f.code == "account_currency_mismatch")
+        .collect();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].severity, Severity::Warn);
+    assert_eq!(mismatches[0].entity, "Checking");
+    assert!(
+        mismatches[0]
+            .detail
+            .starts_with("2 transaction(s) in EUR, not the account's USD")
+    );
+}
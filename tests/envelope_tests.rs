@@ -4,7 +4,11 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use moneyclip::{cli, commands::envelopes};
+use moneyclip::{
+    cli,
+    commands::{envelopes, goals},
+    models::{EnvelopeMonthRow, GoalStatusRow},
+};
 use rusqlite::{Connection, params};
 use rust_decimal::Decimal;
 
@@ -14,7 +18,7 @@ fn setup() -> Connection {
         PRAGMA foreign_keys = ON;
         CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
         CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
-        CREATE TABLE budgets(id INTEGER PRIMARY KEY AUTOINCREMENT, month TEXT NOT NULL, category_id INTEGER NOT NULL, amount TEXT NOT NULL, UNIQUE(month, category_id));
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY AUTOINCREMENT, month TEXT NOT NULL, category_id INTEGER NOT NULL, amount TEXT NOT NULL, currency TEXT, UNIQUE(month, category_id));
         CREATE TABLE transactions(id INTEGER PRIMARY KEY AUTOINCREMENT, date TEXT NOT NULL, account_id INTEGER, amount TEXT NOT NULL, payee TEXT, category_id INTEGER, currency TEXT NOT NULL, note TEXT);
         CREATE TABLE fx_rates(date TEXT NOT NULL, base TEXT NOT NULL, quote TEXT NOT NULL, rate TEXT NOT NULL, UNIQUE(date, base, quote));
     "#).unwrap();
@@ -139,3 +143,213 @@ fn envelope_carryover_preserves_decimal_precision() {
     assert!(budget_m.is_zero());
     assert!(spent_m.is_zero());
 }
+
+#[test]
+fn envelope_history_rolls_months_forward() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        INSERT INTO budgets(month, category_id, amount) VALUES('2025-08', 1, '50');
+        INSERT INTO transactions(date, amount, category_id, currency) VALUES('2025-07-12', '-30', 1, 'USD');
+        INSERT INTO transactions(date, amount, category_id, currency) VALUES('2025-08-03', '-90', 1, 'USD');
+        "#,
+    )
+    .unwrap();
+
+    let rows: Vec<_> = envelopes::envelope_history(&conn, 1, "2025-09", 3)
+        .unwrap()
+        .into_iter()
+        .map(EnvelopeMonthRow::cells)
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            vec!["2025-07", "0.00", "100.00", "30.00", "70.00"],
+            vec!["2025-08", "70.00", "50.00", "90.00", "30.00"],
+            vec!["2025-09", "30.00", "0.00", "0.00", "30.00"],
+        ]
+    );
+}
+
+#[test]
+fn goal_tracks_envelope_and_sweep_moves_leftovers() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE goals(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, category_id INTEGER NOT NULL, target TEXT NOT NULL, target_date TEXT, created_at TEXT);
+        INSERT INTO categories(name) VALUES('Vacation'), ('Dining');
+        INSERT INTO budgets(month, category_id, amount) VALUES('2025-07', 3, '80');
+        INSERT INTO transactions(date, amount, category_id, currency) VALUES('2025-07-20', '-30', 1, 'USD');
+        INSERT INTO transactions(date, amount, category_id, currency) VALUES('2025-07-21', '-95', 3, 'USD');
+        "#,
+    )
+    .unwrap();
+    let run = |argv: &[&str]| {
+        let matches = cli::build_cli().get_matches_from(argv);
+        match matches.subcommand() {
+            Some(("envelope", m)) => envelopes::handle(&conn, m),
+            Some(("goal", m)) => goals::handle(&conn, m),
+            _ => panic!("command not parsed"),
+        }
+    };
+
+    run(&[
+        "moneyclip",
+        "goal",
+        "add",
+        "--name",
+        "Japan",
+        "--category",
+        "Vacation",
+        "--target",
+        "1000",
+        "--by",
+        "2025-12-31",
+    ])
+    .unwrap();
+    run(&[
+        "moneyclip",
+        "envelope",
+        "fund",
+        "--month",
+        "2025-07",
+        "--category",
+        "Vacation",
+        "--amount",
+        "200",
+    ])
+    .unwrap();
+    assert_eq!(
+        goals::goal_status(&conn, "2025-07").unwrap(),
+        vec![GoalStatusRow {
+            goal: "Japan".into(),
+            envelope: "Vacation".into(),
+            saved: "200.00".into(),
+            target: "1000.00".into(),
+            remaining: "800.00".into(),
+            progress_percent: "20.0".into(),
+            by: Some("2025-12-31".into()),
+            per_month: Some("133.33".into()),
+        }]
+    );
+
+    // Groceries has 70 left; Dining is overspent and contributes nothing.
+    let preview = envelopes::sweep_to_goal(&conn, "2025-07", "Japan", true).unwrap();
+    assert_eq!(preview.len(), 1);
+    assert_eq!(
+        goals::goal_status(&conn, "2025-07").unwrap()[0].saved,
+        "200.00"
+    );
+
+    run(&[
+        "moneyclip",
+        "envelope",
+        "sweep",
+        "--month",
+        "2025-07",
+        "--to-goal",
+        "Japan",
+    ])
+    .unwrap();
+    let status = goals::goal_status(&conn, "2025-07").unwrap();
+    assert_eq!(status[0].saved, "270.00");
+    let (carry, budget, spent) = envelopes::envelope_compute(&conn, 1, "2025-07").unwrap();
+    assert!((carry + budget - spent).is_zero());
+    assert!(envelopes::sweep_to_goal(&conn, "2025-07", "Nope", true).is_err());
+}
+
+#[test]
+fn envelope_as_of_counts_spending_to_date_and_projects_month_end() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        INSERT INTO transactions(date, amount, category_id, currency) VALUES ('2025-07-05','-20',1,'USD');
+        INSERT INTO transactions(date, amount, category_id, currency) VALUES ('2025-07-10','-10',1,'USD');
+        INSERT INTO transactions(date, amount, category_id, currency) VALUES ('2025-07-20','-50',1,'USD');
+        "#,
+    )
+    .unwrap();
+    let as_of = chrono::NaiveDate::from_ymd_opt(2025, 7, 15).unwrap();
+    let (carry, budget_m, spent_m) =
+        envelopes::envelope_compute_as_of(&conn, 1, "2025-07", Some(as_of)).unwrap();
+    assert_eq!(spent_m, Decimal::new(30, 0));
+
+    let month_end = chrono::NaiveDate::from_ymd_opt(2025, 7, 31).unwrap();
+    let (burn, projected) = envelopes::burn_projection(carry + budget_m, spent_m, as_of, month_end);
+    assert_eq!(burn, Decimal::new(2, 0));
+    assert_eq!(projected, Decimal::new(38, 0));
+
+    let (_, _, whole) = envelopes::envelope_compute(&conn, 1, "2025-07").unwrap();
+    assert_eq!(whole, Decimal::new(80, 0));
+    assert!(
+        cli::build_cli()
+            .try_get_matches_from(["moneyclip", "envelope", "status", "--as-of", "2025-07-15"])
+            .is_ok()
+    );
+}
+
+#[test]
+fn alerts_list_envelopes_below_amount_or_percent() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        INSERT INTO categories(name) VALUES ('Dining'), ('Unused');
+        INSERT INTO budgets(month, category_id, amount)
+            SELECT '2025-07', id, '200.00' FROM categories WHERE name='Dining';
+        INSERT INTO transactions(date, amount, category_id, currency)
+            SELECT '2025-07-12', '-185', id, 'USD' FROM categories WHERE name='Dining';
+        INSERT INTO transactions(date, amount, category_id, currency)
+            SELECT '2025-07-15', '-60', id, 'USD' FROM categories WHERE name='Groceries';
+        "#,
+    )
+    .unwrap();
+    let low = |threshold: &str| {
+        let threshold = envelopes::Threshold::parse(threshold).unwrap();
+        envelopes::envelope_alerts(&conn, "2025-07", threshold)
+            .unwrap()
+            .into_iter()
+            .map(|a| (a.category, a.available.normalize().to_string()))
+            .collect::<Vec<_>>()
+    };
+    // Dining has 15 left, Groceries 40; Unused has no envelope and is never listed.
+    assert_eq!(low("20"), vec![("Dining".to_string(), "15".to_string())]);
+    assert_eq!(
+        low("50%"),
+        vec![
+            ("Dining".to_string(), "15".to_string()),
+            ("Groceries".to_string(), "40".to_string()),
+        ]
+    );
+    assert!(low("5").is_empty());
+}
+
+#[test]
+fn plan_afford_uses_envelope_scheduled_funding_then_spare_money() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE goals(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, category_id INTEGER NOT NULL, target TEXT NOT NULL, target_date TEXT);
+        CREATE TABLE planned_purchases(id INTEGER PRIMARY KEY AUTOINCREMENT, item TEXT NOT NULL UNIQUE, cost TEXT NOT NULL, currency TEXT NOT NULL, month TEXT, category_id INTEGER, goal_id INTEGER);
+        INSERT INTO categories(name) VALUES ('Dining');
+        INSERT INTO budgets(month, category_id, amount)
+            SELECT '2025-07', id, '300' FROM categories WHERE name='Dining';
+        INSERT INTO budgets(month, category_id, amount)
+            SELECT '2025-08', id, '50' FROM categories WHERE name='Groceries';
+        INSERT INTO transactions(date, amount, category_id, currency)
+            SELECT '2025-07-05', '-30', id, 'USD' FROM categories WHERE name='Dining';
+        INSERT INTO planned_purchases(item, cost, currency, month, category_id)
+            SELECT 'Blender', '250', 'USD', '2025-09', id FROM categories WHERE name='Groceries';
+        "#,
+    )
+    .unwrap();
+    let today = chrono::NaiveDate::from_ymd_opt(2025, 7, 10).unwrap();
+    let check = moneyclip::commands::plans::check_affordability(&conn, "Blender", today).unwrap();
+    assert_eq!(check.envelope.as_deref(), Some("Groceries"));
+    assert_eq!(check.available, Decimal::new(100, 0));
+    assert_eq!(check.scheduled, Decimal::new(50, 0));
+    // Dining: 270 left, spending 3 a day for the remaining 21 days leaves 207 spare.
+    assert_eq!(check.spare.len(), 1);
+    assert_eq!(check.spare[0].1.normalize(), Decimal::new(207, 0));
+    assert_eq!(check.from_others, Decimal::new(100, 0));
+    assert!(check.shortfall.is_zero());
+}
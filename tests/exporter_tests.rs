@@ -110,3 +110,362 @@ fn export_transactions_rejects_unknown_format() {
     }
     assert!(!out_path.exists());
 }
+
+#[test]
+fn export_anonymized_scrambles_identifying_fields() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Joint Checking at First Bank','bank','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency,note) VALUES ('2025-02-10',1,'-100.00','Dr. Smith Dental',  'USD','filling');
+        INSERT INTO transactions(date,account_id,amount,payee,currency,note) VALUES ('2025-02-28',1,'-40.00','Dr. Smith Dental','USD',NULL);
+        INSERT INTO transactions(date,account_id,amount,payee,currency,note) VALUES ('2025-03-01',1,'2500','Employer Inc','USD',NULL);
+        "#,
+    )
+    .unwrap();
+    let dir = tempdir().unwrap();
+    let out = dir.path().join("repro.sqlite");
+
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "export",
+        "anonymized",
+        "--out",
+        out.to_str().unwrap(),
+    ]);
+    let Some(("export", export_m)) = matches.subcommand() else {
+        panic!("export command not parsed");
+    };
+    exporter::handle(&conn, export_m).unwrap();
+
+    let copy = Connection::open(&out).unwrap();
+    let account: String = copy
+        .query_row("SELECT name FROM accounts WHERE id=1", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(account, "Account 1");
+
+    let mut stmt = copy
+        .prepare("SELECT date, amount, payee, note FROM transactions ORDER BY id")
+        .unwrap();
+    let rows: Vec<(String, String, String, Option<String>)> = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].2.starts_with("payee-"));
+    assert_eq!(rows[0].2, rows[1].2);
+    assert_ne!(rows[0].2, rows[2].2);
+    assert!(rows[0].3.as_deref().unwrap().starts_with("note-"));
+    assert_eq!(rows[1].3, None);
+    for ((date, amount, _, _), (orig_month, orig_amount)) in
+        rows.iter()
+            .zip([("2025-02", -100.0), ("2025-02", -40.0), ("2025-03", 2500.0)])
+    {
+        assert!(date.starts_with(orig_month), "{date} left {orig_month}");
+        let value: f64 = amount.parse().unwrap();
+        let ratio = value / orig_amount;
+        assert!((0.8..=1.2).contains(&ratio), "{amount} vs {orig_amount}");
+    }
+    assert_eq!(rows[0].1.split('.').nth(1).map(str::len), Some(2));
+
+    let original: String = conn
+        .query_row("SELECT payee FROM transactions WHERE id=1", [], |r| {
+            r.get(0)
+        })
+        .unwrap();
+    assert_eq!(original, "Dr. Smith Dental");
+}
+
+/// Export `conn` through `export anonymized` and open the copy.
+fn anonymized_copy(conn: &Connection, dir: &std::path::Path) -> Connection {
+    let out = dir.join("repro.sqlite");
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "export",
+        "anonymized",
+        "--out",
+        out.to_str().unwrap(),
+    ]);
+    let Some(("export", export_m)) = matches.subcommand() else {
+        panic!("export command not parsed");
+    };
+    exporter::handle(conn, export_m).unwrap();
+    Connection::open(&out).unwrap()
+}
+
+#[test]
+fn export_anonymized_replaces_names_in_feature_columns() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE transactions ADD COLUMN reimbursable_by TEXT;
+        ALTER TABLE transactions ADD COLUMN tax_amount TEXT;
+        ALTER TABLE transactions ADD COLUMN project TEXT;
+        ALTER TABLE transactions ADD COLUMN location TEXT;
+        ALTER TABLE transactions ADD COLUMN latitude REAL;
+        ALTER TABLE transactions ADD COLUMN longitude REAL;
+        CREATE TABLE trips(id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, start_date TEXT NOT NULL,
+            end_date TEXT NOT NULL, tag TEXT);
+        CREATE TABLE jars(id INTEGER PRIMARY KEY, account_id INTEGER NOT NULL, name TEXT NOT NULL);
+        CREATE TABLE jar_moves(id INTEGER PRIMARY KEY, date TEXT NOT NULL, from_jar_id INTEGER,
+            to_jar_id INTEGER, amount TEXT NOT NULL, note TEXT);
+        INSERT INTO jars VALUES (1,1,'Emma college fund'), (2,1,'Noah');
+        INSERT INTO jar_moves VALUES (1,'2025-02-01',NULL,1,'50','birthday money from grandma');
+        INSERT INTO trips VALUES
+            (1,'Lisbon','2025-02-01','2025-02-05',NULL),
+            (2,'Kyoto','2025-04-01','2025-04-09','Kitchen remodel'),
+            (3,'Oslo','2025-06-01','2025-06-03','Oslo 2025');
+        CREATE TABLE invoices(id INTEGER PRIMARY KEY, number TEXT NOT NULL, client TEXT NOT NULL,
+            amount TEXT NOT NULL, currency TEXT NOT NULL, note TEXT);
+        INSERT INTO invoices VALUES
+            (1,'INV-1','Acme Holdings','1200.00','USD','March retainer'),
+            (2,'INV-2','Acme Holdings','800.00','USD',NULL);
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency,reimbursable_by) VALUES
+            ('2025-02-10',1,'-60.00','Pizzeria','USD','Alice Jones'),
+            ('2025-02-11',1,'-30.00','Cinema','USD','Bob Stone'),
+            ('2025-02-12',1,'-20.00','Cafe','USD','Alice Jones'),
+            ('2025-02-13',1,'-10.00','Bakery','USD',NULL);
+        UPDATE transactions SET tax_amount='-10.00' WHERE id=1;
+        UPDATE transactions SET project='Kitchen remodel' WHERE id IN (2,4);
+        UPDATE transactions SET location='12 Elm St, Springfield', latitude=39.7817, longitude=-89.6501
+            WHERE id IN (1,3);
+        UPDATE transactions SET location='Main Sq', latitude=39.8 WHERE id=4;
+        "#,
+    )
+    .unwrap();
+    let dir = tempdir().unwrap();
+    let copy = anonymized_copy(&conn, dir.path());
+
+    let people: Vec<Option<String>> = copy
+        .prepare("SELECT reimbursable_by FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(
+        people,
+        [
+            Some("Person 1".to_string()),
+            Some("Person 2".to_string()),
+            Some("Person 1".to_string()),
+            None
+        ]
+    );
+
+    let projects: Vec<Option<String>> = copy
+        .prepare("SELECT project FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let project = Some("Project 1".to_string());
+    assert_eq!(projects, [None, project.clone(), None, project]);
+
+    let jars: Vec<String> = copy
+        .prepare("SELECT name FROM jars ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(jars, ["Jar 1", "Jar 2"]);
+    let move_note: String = copy
+        .query_row("SELECT note FROM jar_moves", [], |r| r.get(0))
+        .unwrap();
+    assert!(move_note.starts_with("note-"));
+
+    let trips: Vec<(String, Option<String>)> = copy
+        .prepare("SELECT name, tag FROM trips ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let trips: Vec<_> = trips
+        .iter()
+        .map(|(name, tag)| (name.as_str(), tag.as_deref()))
+        .collect();
+    assert_eq!(
+        trips,
+        [
+            ("Trip 1", None),
+            ("Trip 2", Some("Project 1")),
+            ("Trip 3", Some("Project 2"))
+        ]
+    );
+
+    let places: Vec<(Option<String>, Option<f64>, Option<f64>)> = copy
+        .prepare("SELECT location, latitude, longitude FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let locations: Vec<_> = places.iter().map(|p| p.0.as_deref()).collect();
+    assert_eq!(
+        locations,
+        [
+            Some("Location 1"),
+            None,
+            Some("Location 1"),
+            Some("Location 2")
+        ]
+    );
+    assert!(places.iter().all(|p| p.1.is_none() && p.2.is_none()));
+
+    let (amount, tax): (String, String) = copy
+        .query_row(
+            "SELECT amount, tax_amount FROM transactions WHERE id=1",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    let tax: rust_decimal::Decimal = tax.parse().unwrap();
+    let amount: rust_decimal::Decimal = amount.parse().unwrap();
+    // Taxed at 1/6 before and after: scaled with its amount, not left at -10.00.
+    assert_eq!(tax, (amount / rust_decimal::Decimal::from(6)).round_dp(2));
+
+    let invoices: Vec<(String, String, Option<String>)> = copy
+        .prepare("SELECT client, amount, note FROM invoices ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert!(invoices[0].0.starts_with("payee-"));
+    assert_eq!(invoices[0].0, invoices[1].0);
+    assert!(invoices[0].2.as_deref().unwrap().starts_with("note-"));
+    for ((_, amount, _), original) in invoices.iter().zip([1200.0, 800.0]) {
+        let ratio = amount.parse::<f64>().unwrap() / original;
+        assert!((0.8..=1.2).contains(&ratio), "{amount} vs {original}");
+        assert_eq!(amount.split('.').nth(1).map(str::len), Some(2));
+    }
+}
+
+#[test]
+fn export_budgets_and_envelopes_as_csv() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO settings VALUES ('base_currency','USD');
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT NOT NULL, category_id INTEGER NOT NULL,
+            amount TEXT NOT NULL, currency TEXT, UNIQUE(month, category_id));
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT NOT NULL, account_id INTEGER,
+            amount TEXT NOT NULL, payee TEXT, category_id INTEGER, currency TEXT NOT NULL, note TEXT);
+        CREATE TABLE fx_rates(date TEXT NOT NULL, base TEXT NOT NULL, quote TEXT NOT NULL, rate TEXT NOT NULL);
+        INSERT INTO categories VALUES (1,'Groceries'), (2,'Unused');
+        INSERT INTO budgets(month,category_id,amount,currency) VALUES
+            ('2025-07',1,'100','USD'), ('2025-08',1,'120','USD');
+        INSERT INTO transactions(date,amount,category_id,currency) VALUES
+            ('2025-07-05','-30','1','USD'), ('2025-08-02','-150','1','USD');
+        "#,
+    )
+    .unwrap();
+    let dir = tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let argv = ["moneyclip", "export"].iter().chain(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("export", sub)) = matches.subcommand() else {
+            panic!("export not parsed");
+        };
+        exporter::handle(&conn, sub).unwrap();
+    };
+
+    let budgets = dir.path().join("budgets.csv");
+    run(&[
+        "budgets",
+        "--year",
+        "2025",
+        "--out",
+        budgets.to_str().unwrap(),
+    ]);
+    assert_eq!(
+        std::fs::read_to_string(&budgets).unwrap(),
+        "month,category,budget,spent,remaining,currency\n\
+         2025-07,Groceries,100.00,30.00,70.00,USD\n\
+         2025-08,Groceries,120.00,150.00,-30.00,USD\n"
+    );
+
+    let envelopes = dir.path().join("envelopes.json");
+    run(&[
+        "envelopes",
+        "--month",
+        "2025-08",
+        "--format",
+        "json",
+        "--out",
+        envelopes.to_str().unwrap(),
+    ]);
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&envelopes).unwrap()).unwrap();
+    assert_eq!(
+        value,
+        json!([{
+            "month": "2025-08",
+            "category": "Groceries",
+            "carryover": "70.00",
+            "funded": "120.00",
+            "spent": "150.00",
+            "available": "40.00",
+            "currency": "USD"
+        }])
+    );
+}
+
+#[test]
+fn sheets_values_start_with_a_header_row() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts VALUES (1,'Checking','bank','USD');
+        INSERT INTO categories VALUES (1,'Food');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-07-31',1,'-4.50','Cafe',1,'USD'),
+            ('2025-08-01',1,'-12.00','Market',NULL,'USD');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency,note) VALUES
+            ('2025-08-02',1,'-1.00','=IMPORTXML("http://x.example","//a")',1,'USD','+1 fee');
+        "#,
+    )
+    .unwrap();
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "export",
+        "sheets",
+        "--spreadsheet-id",
+        "abc",
+        "--tab",
+        "Transactions",
+        "--month",
+        "2025-08",
+    ]);
+    let sub = matches
+        .subcommand_matches("export")
+        .and_then(|m| m.subcommand_matches("sheets"))
+        .unwrap();
+    let values = moneyclip::commands::sheets::sheet_values(&conn, sub).unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0][0], "date");
+    assert_eq!(
+        values[1],
+        vec!["2025-08-01", "Checking", "Market", "-12.00", "USD", "", ""]
+    );
+    // Text that Sheets would evaluate is quoted; amounts stay numbers.
+    assert_eq!(
+        values[2],
+        vec![
+            "2025-08-02",
+            "Checking",
+            "'=IMPORTXML(\"http://x.example\",\"//a\")",
+            "-1.00",
+            "USD",
+            "Food",
+            "'+1 fee"
+        ]
+    );
+}
@@ -178,3 +178,283 @@ fn fx_cache_refreshes_after_rate_updates() {
     let refreshed = moneyclip::utils::fx_convert(&conn, date, amount, "EUR", "JPY").unwrap();
     assert_eq!(format!("{:.2}", refreshed), "240.00");
 }
+
+#[test]
+fn fx_batch_conversion_matches_single_conversions() {
+    let conn = setup();
+    conn.execute(
+        "INSERT INTO fx_rates(date,base,quote,rate) VALUES (?1,?2,?3,?4)",
+        params!["2025-01-01", "USD", "EUR", "0.5"],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO fx_rates(date,base,quote,rate) VALUES (?1,?2,?3,?4)",
+        params!["2025-02-01", "USD", "EUR", "0.8"],
+    )
+    .unwrap();
+
+    let jan = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+    let feb = NaiveDate::from_ymd_opt(2025, 2, 15).unwrap();
+    let items = [
+        (jan, Decimal::new(10, 0), "EUR"),
+        (feb, Decimal::new(-8, 0), "EUR"),
+        (jan, Decimal::new(5, 0), "USD"),
+        (jan, Decimal::new(20, 0), "EUR"),
+    ];
+    let converted = moneyclip::utils::fx_convert_batch(&conn, &items, "USD").unwrap();
+    let formatted: Vec<String> = converted.iter().map(|d| format!("{:.2}", d)).collect();
+    assert_eq!(formatted, vec!["20.00", "-10.00", "5.00", "40.00"]);
+
+    for ((date, amount, ccy), batch) in items.iter().zip(converted) {
+        let single = moneyclip::utils::fx_convert(&conn, *date, *amount, ccy, "USD").unwrap();
+        assert_eq!(single, batch);
+    }
+}
+
+#[test]
+fn fx_direct_policy_prefers_stored_pair_and_explains_path() {
+    let conn = setup();
+    let date = NaiveDate::from_ymd_opt(2025, 8, 15).unwrap();
+    for (base, quote, rate) in [
+        ("USD", "CAD", "2.0"),
+        ("USD", "GBP", "0.5"),
+        ("CAD", "GBP", "0.1"),
+    ] {
+        conn.execute(
+            "INSERT INTO fx_rates(date,base,quote,rate) VALUES (?1,?2,?3,?4)",
+            params!["2025-08-01", base, quote, rate],
+        )
+        .unwrap();
+    }
+    moneyclip::utils::set_setting(&conn, "fx_path_policy", "direct").unwrap();
+
+    let amount = Decimal::new(1000, 2); // 10 CAD
+    let converted = moneyclip::utils::fx_convert(&conn, date, amount, "CAD", "GBP").unwrap();
+    assert_eq!(format!("{:.2}", converted), "1.00");
+
+    let (policy, steps) = moneyclip::utils::fx_explain(&conn, date, "CAD", "GBP").unwrap();
+    assert_eq!(policy, moneyclip::utils::FxPathPolicy::Direct);
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0].pair, "CAD/GBP");
+    assert_eq!(steps[0].rate_date, "2025-08-01");
+
+    // Without a direct pair the base currency hub is used.
+    let (_, hub_steps) = moneyclip::utils::fx_explain(&conn, date, "GBP", "CAD").unwrap();
+    assert_eq!(hub_steps.len(), 1);
+    conn.execute("DELETE FROM fx_rates WHERE base='CAD'", [])
+        .unwrap();
+    let (_, hub_steps) = moneyclip::utils::fx_explain(&conn, date, "GBP", "CAD").unwrap();
+    let legs: Vec<(&str, &str)> = hub_steps
+        .iter()
+        .map(|s| (s.from.as_str(), s.to.as_str()))
+        .collect();
+    assert_eq!(legs, vec![("GBP", "USD"), ("USD", "CAD")]);
+}
+
+#[test]
+fn fx_rate_gaps_cover_transactions_and_trades_in_foreign_currencies() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT NOT NULL, amount TEXT NOT NULL, currency TEXT NOT NULL);
+        CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT, currency TEXT);
+        CREATE TABLE trades(id INTEGER PRIMARY KEY, date TEXT NOT NULL, asset_id INTEGER NOT NULL);
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-03-01','USD','EUR','0.92');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-03-01','GBP','USD','1.27');
+        INSERT INTO transactions(date,amount,currency) VALUES ('2025-03-05','-10','EUR');
+        INSERT INTO transactions(date,amount,currency) VALUES ('2025-03-20','-10','eur');
+        INSERT INTO transactions(date,amount,currency) VALUES ('2025-03-20','-10','USD');
+        INSERT INTO transactions(date,amount,currency) VALUES ('2025-03-02','-10','GBP');
+        INSERT INTO assets(id,ticker,currency) VALUES (1,'SAP','EUR'), (2,'7203.T','JPY');
+        INSERT INTO trades(date,asset_id) VALUES ('2025-03-20',1), ('2025-03-21',2);
+        "#,
+    )
+    .unwrap();
+
+    let gaps = moneyclip::commands::fx::rate_gaps(&conn, "USD", Some(7)).unwrap();
+    let found: Vec<(String, String)> = gaps
+        .into_iter()
+        .map(|g| (g.date.to_string(), g.currency))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("2025-03-20".to_string(), "EUR".to_string()),
+            ("2025-03-21".to_string(), "JPY".to_string()),
+        ]
+    );
+    // Without an age limit (what `doctor` checks) any earlier rate covers a date.
+    let gaps = moneyclip::commands::fx::rate_gaps(&conn, "USD", None).unwrap();
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps[0].currency, "JPY");
+}
+
+#[test]
+fn fx_set_base_migrate_converts_budgets_and_goals_and_records_change() {
+    let mut conn = setup();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT NOT NULL, category_id INTEGER NOT NULL, amount TEXT NOT NULL, currency TEXT);
+        CREATE TABLE goals(id INTEGER PRIMARY KEY, name TEXT NOT NULL, category_id INTEGER NOT NULL, target TEXT NOT NULL);
+        CREATE TABLE base_currency_changes(id INTEGER PRIMARY KEY AUTOINCREMENT, changed_at TEXT NOT NULL DEFAULT (datetime('now')), from_currency TEXT NOT NULL, to_currency TEXT NOT NULL, rate_date TEXT, budgets INTEGER NOT NULL DEFAULT 0, goals INTEGER NOT NULL DEFAULT 0);
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-06-02','USD','EUR','0.9');
+        INSERT INTO budgets(month,category_id,amount,currency) VALUES ('2025-06',1,'100',NULL), ('2025-06',2,'45','EUR');
+        INSERT INTO goals(name,category_id,target) VALUES ('Trip',1,'1000');
+        "#,
+    )
+    .unwrap();
+    let date = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+
+    let change = moneyclip::commands::fx::change_base(&mut conn, "EUR", Some(date), false).unwrap();
+    assert_eq!(
+        (change.from.as_str(), change.budgets, change.goals),
+        ("USD", 1, 1)
+    );
+
+    let budgets: Vec<(Decimal, String)> = conn
+        .prepare("SELECT amount, currency FROM budgets ORDER BY id")
+        .unwrap()
+        .query_map([], |r| {
+            Ok((r.get::<_, String>(0)?.parse().unwrap(), r.get(1)?))
+        })
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap();
+    assert_eq!(
+        budgets,
+        vec![
+            (Decimal::new(90, 0), "EUR".to_string()),
+            (Decimal::new(45, 0), "EUR".to_string())
+        ]
+    );
+    let target: String = conn
+        .query_row("SELECT target FROM goals", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(target.parse::<Decimal>().unwrap(), Decimal::new(900, 0));
+    assert_eq!(
+        moneyclip::utils::get_base_currency(&conn).unwrap(),
+        "EUR".to_string()
+    );
+    let audit: (String, String, Option<String>) = conn
+        .query_row(
+            "SELECT from_currency, to_currency, rate_date FROM base_currency_changes",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        audit,
+        ("USD".into(), "EUR".into(), Some("2025-06-02".into()))
+    );
+
+    // Without --migrate the budgets keep their old-base meaning via their currency.
+    conn.execute(
+        "INSERT INTO budgets(month,category_id,amount) VALUES ('2025-07',1,'50')",
+        params![],
+    )
+    .unwrap();
+    let change = moneyclip::commands::fx::change_base(&mut conn, "GBP", None, false).unwrap();
+    assert_eq!((change.budgets, change.goals), (0, 1));
+    let stamped: String = conn
+        .query_row(
+            "SELECT currency FROM budgets WHERE month='2025-07'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(stamped, "EUR");
+    assert!(moneyclip::commands::fx::change_base(&mut conn, "GBP", None, false).is_err());
+
+    // Migrated amounts are rounded to the new base's minor units.
+    conn.execute_batch(
+        r#"
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-06-02','EUR','JPY','161.777');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-06-02','GBP','JPY','190.555');
+        "#,
+    )
+    .unwrap();
+    moneyclip::commands::fx::change_base(&mut conn, "JPY", Some(date), false).unwrap();
+    let first: String = conn
+        .query_row("SELECT amount FROM budgets ORDER BY id", [], |r| r.get(0))
+        .unwrap();
+    let target: String = conn
+        .query_row("SELECT target FROM goals", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!((first.as_str(), target.as_str()), ("14560", "171500"));
+
+    // A display override does not change what is stored.
+    let matches = moneyclip::cli::build_cli().get_matches_from([
+        "moneyclip",
+        "--money-dp",
+        "0",
+        "fx",
+        "set-base",
+        "--currency",
+        "GBP",
+        "--migrate",
+        "--date",
+        "2025-06-02",
+        "--no-fetch",
+    ]);
+    moneyclip::commands::dispatch(&mut conn, &matches).unwrap();
+    let first: String = conn
+        .query_row("SELECT amount FROM budgets ORDER BY id", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(first, "76.41");
+}
+
+#[test]
+fn convert_series_steps_through_the_range() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-08-01','EUR','USD','1.10');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-08-08','EUR','USD','1.20');
+        "#,
+    )
+    .unwrap();
+    let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let points = moneyclip::commands::fx::convert_series(
+        &conn,
+        Decimal::new(2000, 0),
+        "EUR",
+        "USD",
+        date("2025-08-01"),
+        date("2025-08-20"),
+        "week",
+    )
+    .unwrap();
+    let got: Vec<_> = points
+        .iter()
+        .map(|p| (p.date.as_str(), p.converted.normalize().to_string()))
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            ("2025-08-01", "2200".to_string()),
+            ("2025-08-08", "2400".to_string()),
+            ("2025-08-15", "2400".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn ledger_caches_never_serve_another_database() {
+    let date = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+    for rate in 1..=20 {
+        let conn = setup();
+        conn.execute(
+            "INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','USD','EUR',?1)",
+            params![rate.to_string()],
+        )
+        .unwrap();
+        let mut ledger = moneyclip::Ledger::new(conn);
+        ledger.with(|conn| {
+            let first = moneyclip::utils::fx_rate(conn, date, "USD", "EUR").unwrap();
+            assert_eq!(first, Decimal::from(rate));
+            conn.execute("UPDATE fx_rates SET rate='0.5'", []).unwrap();
+            let updated = moneyclip::utils::fx_rate(conn, date, "USD", "EUR").unwrap();
+            assert_eq!(updated, Decimal::new(5, 1));
+        });
+    }
+}
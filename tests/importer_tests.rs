@@ -14,7 +14,8 @@ fn base_conn() -> Connection {
     conn.execute_batch(
         r#"
         CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
-        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT, monthly_cap TEXT, cap_currency TEXT);
+        CREATE TABLE closed_periods(month TEXT PRIMARY KEY, closed_at TEXT);
         CREATE TABLE transactions(
             id INTEGER PRIMARY KEY,
             date TEXT NOT NULL,
@@ -25,13 +26,41 @@ fn base_conn() -> Connection {
             currency TEXT NOT NULL,
             note TEXT
         );
+        CREATE TABLE import_profiles(
+            name TEXT PRIMARY KEY,
+            delimiter TEXT NOT NULL DEFAULT ',',
+            decimal_comma INTEGER NOT NULL DEFAULT 0,
+            encoding TEXT NOT NULL DEFAULT 'UTF-8',
+            debit_column TEXT,
+            credit_column TEXT,
+            invert_sign INTEGER NOT NULL DEFAULT 0,
+            bank_category_column TEXT,
+            balance_column TEXT
+        );
+        CREATE TABLE mcc_map(code TEXT PRIMARY KEY COLLATE NOCASE, category_id INTEGER NOT NULL);
         CREATE TABLE rules(
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             pattern TEXT NOT NULL,
             category_id INTEGER,
             payee_rewrite TEXT,
             note TEXT,
-            created_at TEXT
+            created_at TEXT,
+            ignore_case INTEGER NOT NULL DEFAULT 0,
+            literal INTEGER NOT NULL DEFAULT 0,
+            match_field TEXT NOT NULL DEFAULT 'both',
+            skip INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE import_batches(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            paths TEXT NOT NULL,
+            sheet TEXT,
+            format TEXT NOT NULL,
+            batch_size INTEGER NOT NULL,
+            rows_total INTEGER NOT NULL,
+            rows_done INTEGER NOT NULL DEFAULT 0,
+            file_before INTEGER NOT NULL DEFAULT 0,
+            started_at TEXT NOT NULL DEFAULT (datetime('now')),
+            finished_at TEXT
         );
         "#,
     )
@@ -378,3 +407,868 @@ fn importer_allows_case_insensitive_currency_match() {
     assert_eq!(count, 1);
     assert_eq!(currency, "USD");
 }
+
+#[test]
+fn importer_reads_semicolon_windows_1252_with_decimal_comma() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'Girokonto','bank','EUR')",
+        [],
+    )
+    .unwrap();
+
+    // "Café Müller" in Windows-1252, semicolons and European amounts.
+    let mut bytes = b"date;payee;amount;category;account;currency;note\n".to_vec();
+    bytes.extend_from_slice(b"2025-02-03;Caf\xe9 M\xfcller;-1.234,56;;Girokonto;EUR;\n");
+    bytes.extend_from_slice(b"2025-02-04;Gehalt;2500,00;;Girokonto;EUR;\n");
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&bytes).unwrap();
+    file.flush().unwrap();
+
+    let path = file.path().to_str().unwrap().to_string();
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "import",
+        "transactions",
+        "--path",
+        &path,
+        "--delimiter",
+        ";",
+        "--decimal-comma",
+        "--encoding",
+        "windows-1252",
+    ]);
+    let Some(("import", import_m)) = matches.subcommand() else {
+        panic!("no import subcommand");
+    };
+    importer::handle(&mut conn, import_m).unwrap();
+
+    let rows: Vec<(String, String)> = conn
+        .prepare("SELECT payee, amount FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            ("Café Müller".to_string(), "-1234.56".to_string()),
+            ("Gehalt".to_string(), "2500.00".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn importer_strips_utf8_bom_and_reads_tabs() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD')",
+        [],
+    )
+    .unwrap();
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(b"\xef\xbb\xbfdate\tpayee\tamount\tcategory\taccount\tcurrency\tnote\n")
+        .unwrap();
+    file.write_all(b"2025-02-03\tShop\t-5.00\t\tA1\tUSD\t\n")
+        .unwrap();
+    file.flush().unwrap();
+
+    let format = importer::CsvFormat::default();
+    let text = format.decode(&std::fs::read(file.path()).unwrap()).unwrap();
+    assert!(text.starts_with("date\t"));
+
+    let path = file.path().to_str().unwrap().to_string();
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "import",
+        "transactions",
+        "--path",
+        &path,
+        "--delimiter",
+        "tab",
+    ]);
+    let Some(("import", import_m)) = matches.subcommand() else {
+        panic!("no import subcommand");
+    };
+    importer::handle(&mut conn, import_m).unwrap();
+    let amount: String = conn
+        .query_row("SELECT amount FROM transactions", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(amount, "-5.00");
+}
+
+fn run_import(conn: &mut Connection, args: &[&str]) -> anyhow::Result<()> {
+    let argv = ["moneyclip", "import"].iter().chain(args);
+    let matches = cli::build_cli().get_matches_from(argv);
+    let Some(("import", import_m)) = matches.subcommand() else {
+        panic!("no import subcommand");
+    };
+    importer::handle(conn, import_m)
+}
+
+#[test]
+fn importer_profile_combines_debit_and_credit_columns() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD')",
+        [],
+    )
+    .unwrap();
+    run_import(
+        &mut conn,
+        &[
+            "profile",
+            "add",
+            "--name",
+            "mybank",
+            "--delimiter",
+            ";",
+            "--debit-column",
+            "Withdrawal",
+            "--credit-column",
+            "Deposit",
+        ],
+    )
+    .unwrap();
+
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "Date;Payee;Withdrawal;Deposit;Account\n2025-02-03;Shop;12.50;;A1\n2025-02-04;Salary;;2000;A1"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    run_import(
+        &mut conn,
+        &["transactions", "--path", &path, "--profile", "mybank"],
+    )
+    .unwrap();
+
+    let amounts: Vec<String> = conn
+        .prepare("SELECT amount FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(amounts, vec!["-12.50", "2000"]);
+
+    // Missing both cells is an error, and the profile's columns must exist.
+    writeln!(file, "2025-02-05;Nothing;;;A1").unwrap();
+    file.flush().unwrap();
+    let err = run_import(
+        &mut conn,
+        &["transactions", "--path", &path, "--profile", "mybank"],
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("No debit or credit amount"));
+    assert!(
+        run_import(
+            &mut conn,
+            &["transactions", "--path", &path, "--debit-column", "Out"]
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn importer_no_flags_switch_off_profile_booleans() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD')",
+        [],
+    )
+    .unwrap();
+    run_import(
+        &mut conn,
+        &[
+            "profile",
+            "add",
+            "--name",
+            "eu",
+            "--decimal-comma",
+            "--invert-sign",
+        ],
+    )
+    .unwrap();
+
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "date,payee,amount,account\n2025-02-03,Shop,-1234.50,A1"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    run_import(
+        &mut conn,
+        &[
+            "transactions",
+            "--path",
+            &path,
+            "--profile",
+            "eu",
+            "--no-decimal-comma",
+            "--no-invert-sign",
+        ],
+    )
+    .unwrap();
+    let amount: String = conn
+        .query_row("SELECT amount FROM transactions", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(amount, "-1234.50");
+
+    // The later of a flag and its --no- form wins.
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "import",
+        "transactions",
+        "--path",
+        "x.csv",
+        "--no-invert-sign",
+        "--invert-sign",
+    ]);
+    let Some(("import", import_m)) = matches.subcommand() else {
+        panic!("no import subcommand");
+    };
+    let Some(("transactions", sub)) = import_m.subcommand() else {
+        panic!("no transactions subcommand");
+    };
+    assert!(sub.get_flag("invert-sign") && !sub.get_flag("no-invert-sign"));
+}
+
+#[test]
+fn importer_invert_sign_flips_amounts() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'Card','card','USD')",
+        [],
+    )
+    .unwrap();
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "date,payee,amount,category,account,currency,note\n2025-02-03,Shop,40.00,,Card,USD,\n2025-02-04,Refund,-5.00,,Card,USD,"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    run_import(
+        &mut conn,
+        &["transactions", "--path", &path, "--invert-sign"],
+    )
+    .unwrap();
+
+    let amounts: Vec<String> = conn
+        .prepare("SELECT amount FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(amounts, vec!["-40.00", "5.00"]);
+}
+
+#[test]
+fn importer_reads_globs_and_repeated_paths_in_one_transaction() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD'), (2,'A2','card','USD')",
+        [],
+    )
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let header = "date,payee,amount,category,account,currency,note";
+    std::fs::write(
+        dir.path().join("a1.csv"),
+        format!("{}\n2025-02-03,Shop,-5.00,,A1,USD,\n", header),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("a2.csv"),
+        format!(
+            "{}\n2025-02-04,Cafe,-3.00,,A2,USD,\n2025-02-05,Cafe,-4.00,,A2,USD,\n",
+            header
+        ),
+    )
+    .unwrap();
+    let extra = dir.path().join("extra.txt");
+    std::fs::write(
+        &extra,
+        format!("{}\n2025-02-06,Bank,10.00,,A1,USD,\n", header),
+    )
+    .unwrap();
+
+    let pattern = dir.path().join("*.csv").to_str().unwrap().to_string();
+    let paths = importer::expand_paths([pattern.clone(), pattern.clone()].iter()).unwrap();
+    assert_eq!(paths.len(), 2);
+    assert!(paths[0].ends_with("a1.csv") && paths[1].ends_with("a2.csv"));
+
+    let extra = extra.to_str().unwrap().to_string();
+    run_import(
+        &mut conn,
+        &["transactions", "--path", &pattern, "--path", &extra],
+    )
+    .unwrap();
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(count, 4);
+
+    // One bad file rolls back every file in the invocation.
+    let bad = dir.path().join("bad.txt");
+    std::fs::write(&bad, format!("{}\n2025-02-07,Shop,abc,,A1,USD,\n", header)).unwrap();
+    let bad = bad.to_str().unwrap().to_string();
+    let err = run_import(
+        &mut conn,
+        &["transactions", "--path", &pattern, "--path", &bad],
+    )
+    .unwrap_err();
+    assert!(format!("{:#}", err).contains("bad.txt"));
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(count, 4);
+
+    let missing = dir.path().join("*.ofx").to_str().unwrap().to_string();
+    assert!(run_import(&mut conn, &["transactions", "--path", &missing]).is_err());
+}
+
+#[test]
+fn importer_reads_xlsx_sheets_with_dates_and_numbers() {
+    use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD')",
+        [],
+    )
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("statement.xlsx");
+    let mut workbook = Workbook::new();
+    workbook.add_worksheet().set_name("Summary").unwrap();
+    let sheet = workbook.add_worksheet().set_name("Transactions").unwrap();
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    for (col, name) in ["Date", "Payee", "Amount", "Account"].iter().enumerate() {
+        sheet.write_string(0, col as u16, *name).unwrap();
+    }
+    let date = ExcelDateTime::from_ymd(2025, 3, 14).unwrap();
+    sheet
+        .write_datetime_with_format(1, 0, &date, &date_format)
+        .unwrap();
+    sheet.write_string(1, 1, "Grocer").unwrap();
+    sheet.write_number(1, 2, -42.5).unwrap();
+    sheet.write_string(1, 3, "A1").unwrap();
+    sheet.write_string(2, 0, "2025-03-15").unwrap();
+    sheet.write_string(2, 1, "Salary").unwrap();
+    sheet.write_number(2, 2, 1000).unwrap();
+    sheet.write_string(2, 3, "A1").unwrap();
+    workbook.save(&path).unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    let plain = importer::CsvFormat::default();
+    let (headers, rows) = importer::read_sheet(&path, Some("Transactions"), &plain).unwrap();
+    assert_eq!(&headers[0], "Date");
+    assert_eq!(&rows[0][0], "2025-03-14");
+
+    // The first sheet has no transactions; pick the second by number.
+    assert!(run_import(&mut conn, &["transactions", "--path", &path]).is_err());
+    run_import(
+        &mut conn,
+        &["transactions", "--path", &path, "--sheet", "2"],
+    )
+    .unwrap();
+    let rows: Vec<(String, String)> = conn
+        .prepare("SELECT date, amount FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            ("2025-03-14".to_string(), "-42.5".to_string()),
+            ("2025-03-15".to_string(), "1000".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn importer_keeps_xlsx_numbers_exact_with_decimal_comma() {
+    use rust_xlsxwriter::Workbook;
+
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','EUR')",
+        [],
+    )
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("umsaetze.xlsx");
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    for (col, name) in ["Date", "Payee", "Amount", "Account"].iter().enumerate() {
+        sheet.write_string(0, col as u16, *name).unwrap();
+    }
+    // A number cell and a text cell typed the German way.
+    sheet.write_string(1, 0, "2025-03-14").unwrap();
+    sheet.write_string(1, 1, "Bäckerei").unwrap();
+    sheet.write_number(1, 2, -12.5).unwrap();
+    sheet.write_string(1, 3, "A1").unwrap();
+    sheet.write_string(2, 0, "2025-03-15").unwrap();
+    sheet.write_string(2, 1, "Gehalt").unwrap();
+    sheet.write_string(2, 2, "1.234,56").unwrap();
+    sheet.write_string(2, 3, "A1").unwrap();
+    workbook.save(&path).unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    run_import(
+        &mut conn,
+        &["transactions", "--path", &path, "--decimal-comma"],
+    )
+    .unwrap();
+    let amounts: Vec<String> = conn
+        .prepare("SELECT amount FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(amounts, ["-12.5", "1234.56"]);
+}
+
+#[test]
+fn importer_maps_bank_categories_before_rules() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Card','card','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Groceries'), (2,'Dining'), (3,'Shopping');
+        INSERT INTO rules(pattern, category_id) VALUES ('(?i)market', 3);
+        "#,
+    )
+    .unwrap();
+    run_import(
+        &mut conn,
+        &[
+            "mcc-map",
+            "set",
+            "--code",
+            "5411",
+            "--category",
+            "Groceries",
+        ],
+    )
+    .unwrap();
+    run_import(
+        &mut conn,
+        &[
+            "mcc-map",
+            "set",
+            "--code",
+            "restaurants",
+            "--category",
+            "Dining",
+        ],
+    )
+    .unwrap();
+
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "date,payee,amount,account,MCC\n\
+         2025-02-03,Fresh Market,-20.00,Card,5411\n\
+         2025-02-04,Pasta Place,-30.00,Card,Restaurants\n\
+         2025-02-05,Corner Market,-5.00,Card,9999\n\
+         2025-02-06,Pasta Place,-8.00,Card,"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    run_import(
+        &mut conn,
+        &[
+            "transactions",
+            "--path",
+            &path,
+            "--bank-category-column",
+            "mcc",
+        ],
+    )
+    .unwrap();
+
+    let cats: Vec<Option<i64>> = conn
+        .prepare("SELECT category_id FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    // Mapped codes win over the "market" rule; unmapped codes fall through to rules.
+    assert_eq!(cats, vec![Some(1), Some(2), Some(3), None]);
+}
+
+#[test]
+fn importer_checks_running_balance_against_amounts_and_account() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency)
+            VALUES ('2025-03-01',1,'100.00','Opening','USD');
+        "#,
+    )
+    .unwrap();
+    let headers = csv::StringRecord::from(vec!["date", "payee", "amount", "account", "Balance"]);
+    // Newest first, as many banks export; the 03-04 row (-5.00) is missing.
+    let records = vec![
+        csv::StringRecord::from(vec!["2025-03-05", "Cafe", "-3.00", "Checking", "77.00"]),
+        csv::StringRecord::from(vec!["2025-03-03", "Books", "-15.00", "Checking", "85.00"]),
+    ];
+    let format = importer::CsvFormat {
+        balance_column: Some("balance".into()),
+        ..importer::CsvFormat::default()
+    };
+    let report = importer::import_records(
+        &conn,
+        &headers,
+        records,
+        &format,
+        &mut importer::ImportCaches::default(),
+    )
+    .unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.balances.len(), 2);
+
+    let warnings = importer::balance_warnings(&conn, &report.balances).unwrap();
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].contains("balance on 2025-03-05 is 77.00"));
+    assert!(warnings[0].contains("gives 82.00"));
+    assert!(warnings[1].contains("moneyclip has 82.00"));
+    assert!(warnings[1].contains("difference -5.00"));
+
+    conn.execute(
+        "INSERT INTO transactions(date,account_id,amount,payee,currency)
+         VALUES ('2025-03-04',1,'-5.00','Lunch','USD')",
+        [],
+    )
+    .unwrap();
+    // The account now agrees with the bank; only the gap inside the file remains.
+    let warnings = importer::balance_warnings(&conn, &report.balances).unwrap();
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert!(warnings[0].contains("missing or duplicated"));
+}
+
+#[test]
+fn importer_reads_sales_tax_columns_for_the_quarterly_report() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE transactions ADD COLUMN tax_amount TEXT;
+        ALTER TABLE transactions ADD COLUMN tax_code TEXT;
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO settings(key,value) VALUES ('base_currency','EUR');
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Biz','bank','EUR');
+        "#,
+    )
+    .unwrap();
+
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "date,payee,amount,account,tax_amount,tax_rate,tax_code\n\
+         2025-01-10,Client A,1190.00,Biz,,19%,VAT19\n\
+         2025-02-03,Client B,500.00,Biz,,,EXEMPT\n\
+         2025-02-14,Office supplies,-59.50,Biz,9.50,,VAT19\n\
+         2025-04-02,Client A,119.00,Biz,,19,VAT19\n\
+         2025-03-01,Coffee,-4.00,Biz,,,"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    let matches =
+        cli::build_cli().get_matches_from(["moneyclip", "import", "transactions", "--path", &path]);
+    let Some(("import", import_m)) = matches.subcommand() else {
+        panic!("no import subcommand");
+    };
+    importer::handle(&mut conn, import_m).unwrap();
+
+    let taxed: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM transactions WHERE tax_amount IS NOT NULL",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(taxed, 3);
+
+    let (_, from, to) = moneyclip::utils::parse_quarter("2025-Q1").unwrap();
+    let lines = moneyclip::commands::sales_tax::tax_collected(&conn, from, to).unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].code, "EXEMPT");
+    assert!(lines[0].net.is_zero());
+    assert_eq!(lines[1].code, "VAT19");
+    assert_eq!(lines[1].collected.to_string(), "190.00");
+    assert_eq!(lines[1].paid.to_string(), "9.50");
+    assert_eq!(lines[1].net.to_string(), "180.50");
+}
+
+#[test]
+fn meta_columns_survive_import_and_export() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE transaction_meta(tx_id INTEGER, key TEXT, value TEXT, PRIMARY KEY(tx_id, key));
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Card','card','USD');
+        "#,
+    )
+    .unwrap();
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "date,payee,amount,account,Reference,Card\n\
+         2025-03-01,Cafe,-4.50,Card,R-100,1234\n\
+         2025-03-02,Books,-20.00,Card,R-101,\n\
+         2025-03-03,Fuel,-40.00,Card,,9876"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    let run = |conn: &mut Connection, argv: &[&str]| {
+        let matches = cli::build_cli().get_matches_from(argv);
+        match matches.subcommand() {
+            Some(("import", m)) => importer::handle(conn, m),
+            Some(("tx", m)) => moneyclip::commands::transactions::handle(conn, m),
+            Some(("export", m)) => moneyclip::commands::exporter::handle(conn, m),
+            _ => panic!("command not parsed"),
+        }
+    };
+    run(
+        &mut conn,
+        &[
+            "moneyclip",
+            "import",
+            "transactions",
+            "--path",
+            &path,
+            "--meta-column",
+            "Reference=ref",
+            "--meta-column",
+            "Card=card_last4",
+        ],
+    )
+    .unwrap();
+
+    let meta = moneyclip::commands::meta::get_meta(&conn, 1).unwrap();
+    assert_eq!(meta.get("ref").map(String::as_str), Some("R-100"));
+    assert_eq!(meta.get("card_last4").map(String::as_str), Some("1234"));
+    assert_eq!(
+        moneyclip::commands::meta::get_meta(&conn, 2).unwrap().len(),
+        1
+    );
+
+    run(
+        &mut conn,
+        &[
+            "moneyclip",
+            "tx",
+            "meta",
+            "set",
+            "--id",
+            "3",
+            "--key",
+            "ref",
+            "--value",
+            "R-102",
+        ],
+    )
+    .unwrap();
+    let matches =
+        cli::build_cli().get_matches_from(["moneyclip", "tx", "list", "--meta", "card_last4"]);
+    let Some(("tx", tx_m)) = matches.subcommand() else {
+        panic!("tx command not parsed");
+    };
+    let Some(("list", list_m)) = tx_m.subcommand() else {
+        panic!("no list subcommand");
+    };
+    let rows = moneyclip::commands::transactions::query_rows(&conn, list_m).unwrap();
+    let payees: Vec<&str> = rows.iter().map(|r| r.payee.as_str()).collect();
+    assert_eq!(payees, ["Fuel", "Cafe"]);
+
+    let out = NamedTempFile::new().unwrap();
+    let out_path = out.path().to_str().unwrap().to_string();
+    run(
+        &mut conn,
+        &[
+            "moneyclip",
+            "export",
+            "transactions",
+            "--format",
+            "csv",
+            "--out",
+            &out_path,
+            "--with-meta",
+        ],
+    )
+    .unwrap();
+    let exported = std::fs::read_to_string(&out_path).unwrap();
+    let lines: Vec<&str> = exported.lines().collect();
+    assert_eq!(
+        lines[0],
+        "date,account,payee,amount,currency,category,note,card_last4,ref"
+    );
+    assert_eq!(lines[2], "2025-03-02,Card,Books,-20.00,USD,,,,R-101");
+    assert_eq!(lines[3], "2025-03-03,Card,Fuel,-40.00,USD,,,9876,R-102");
+}
+
+#[test]
+fn importer_drops_skip_rule_and_skip_option_rows() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD')",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO rules(pattern, match_field, skip) VALUES('(?i)pending', 'note', 1)",
+        [],
+    )
+    .unwrap();
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "date,payee,amount,category,account,currency,note\n\
+         ,AVAILABLE BALANCE,n/a,,A1,,\n\
+         2025-01-28,Old Shop,-9.00,,A1,USD,\n\
+         2025-02-03,Cafe,-4.00,,A1,USD,PENDING\n\
+         2025-02-03,Cafe,-4.00,,A1,USD,\n\
+         2025-02-04,Pending Plumbing,-80.00,,A1,USD,"
+    )
+    .unwrap();
+    file.flush().unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    run_import(
+        &mut conn,
+        &[
+            "transactions",
+            "--path",
+            &path,
+            "--skip-before",
+            "2025-02-01",
+            "--skip-payee-regex",
+            "^AVAILABLE BALANCE",
+        ],
+    )
+    .unwrap();
+
+    let payees: Vec<String> = conn
+        .prepare("SELECT payee FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(payees, vec!["Cafe", "Pending Plumbing"]);
+}
+
+#[test]
+fn import_summary_counts_categories_transfers_and_duplicates() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD'), (2,'Savings','bank','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Dining'), (2,'Groceries');
+        INSERT INTO rules(pattern, category_id) VALUES ('(?i)cafe', 1);
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES ('2025-03-01',1,'-12.00','Corner Store','USD');
+        "#,
+    )
+    .unwrap();
+    let headers = csv::StringRecord::from(vec!["date", "payee", "amount", "category", "account"]);
+    let records = [
+        ["2025-03-01", "Corner Store", "-12.00", "", "Checking"],
+        ["2025-03-02", "Blue Cafe", "-4.50", "", "Checking"],
+        ["2025-03-02", "Market", "-30.00", "Groceries", "Checking"],
+        ["2025-03-03", "To savings", "-100.00", "", "Checking"],
+        ["2025-03-04", "From checking", "100.00", "", "Savings"],
+        ["2025-03-05", "Corner Store", "-3.00", "", "Checking"],
+    ]
+    .into_iter()
+    .map(|r| csv::StringRecord::from(r.to_vec()))
+    .collect();
+    let report = importer::import_records(
+        &conn,
+        &headers,
+        records,
+        &importer::CsvFormat::default(),
+        &mut importer::ImportCaches::default(),
+    )
+    .unwrap();
+    let summary = importer::summarize(&conn, &[report]).unwrap();
+
+    assert_eq!(summary.imported, 6);
+    assert_eq!(summary.categorized_from_file, 1);
+    assert_eq!(summary.categorized_by_rules, 1);
+    assert_eq!(summary.uncategorized, 4);
+    assert_eq!(summary.top_uncategorized[0].payee, "Corner Store");
+    assert_eq!(summary.top_uncategorized[0].rows, 2);
+    assert_eq!(summary.transfers, 2);
+    assert_eq!(summary.possible_duplicates, 1);
+}
+
+#[test]
+fn batched_import_keeps_committed_chunks_and_resumes_after_them() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Dining');
+        INSERT INTO rules(pattern, category_id) VALUES ('(?i)cafe', 1);
+        "#,
+    )
+    .unwrap();
+    let rows = |bad: &str| {
+        format!(
+            "date,payee,amount,account\n2025-03-01,Cafe A,-1.00,Checking\n2025-03-02,Shop B,-2.00,Checking\n\
+             2025-03-03,Shop C,-3.00,Checking\n{},Cafe D,-4.00,Checking\n2025-03-05,Shop E,-5.00,Checking\n",
+            bad
+        )
+    };
+    let file = NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), rows("2025-13-40")).unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let err = run_import(
+        &mut conn,
+        &["transactions", "--path", path, "--batch-size", "2"],
+    )
+    .unwrap_err();
+    assert!(format!("{:#}", err).contains("2 of 5 rows committed"));
+    assert!(format!("{:#}", err).contains("import resume --batch 1"));
+    let count = |conn: &Connection| -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+            .unwrap()
+    };
+    assert_eq!(count(&conn), 2);
+
+    std::fs::write(file.path(), rows("2025-03-04")).unwrap();
+    run_import(&mut conn, &["resume", "--batch", "1"]).unwrap();
+    assert_eq!(count(&conn), 5);
+    let payees: Vec<(String, Option<i64>)> = conn
+        .prepare("SELECT payee, category_id FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap();
+    assert_eq!(payees[0], ("Cafe A".to_string(), Some(1)));
+    assert_eq!(payees[3], ("Cafe D".to_string(), Some(1)));
+    assert_eq!(payees[4].0, "Shop E");
+
+    let again = run_import(&mut conn, &["resume", "--batch", "1"]).unwrap_err();
+    assert!(again.to_string().contains("already finished"));
+}
@@ -0,0 +1,96 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use moneyclip::commands::init;
+use rusqlite::Connection;
+
+#[test]
+fn init_template_adds_missing_accounts_and_categories_once() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO settings VALUES ('base_currency','EUR');
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, type TEXT, currency TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+        INSERT INTO accounts(name,type,currency) VALUES ('Cash','cash','USD');
+        INSERT INTO categories(name) VALUES ('Groceries');
+        "#,
+    )
+    .unwrap();
+
+    let (accounts, categories) = init::apply_template(&conn, "basic").unwrap();
+    assert_eq!(accounts, vec!["Checking"]);
+    assert!(!categories.contains(&String::from("Groceries")));
+    assert!(categories.contains(&String::from("Salary")));
+    let ccy: String = conn
+        .query_row(
+            "SELECT currency FROM accounts WHERE name='Checking'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(ccy, "EUR");
+
+    let (accounts, categories) = init::apply_template(&conn, "basic").unwrap();
+    assert!(accounts.is_empty() && categories.is_empty());
+    assert!(init::apply_template(&conn, "corporate").is_err());
+}
+
+#[test]
+fn providers_can_be_switched_off() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);")
+        .unwrap();
+    moneyclip::utils::ensure_provider(&conn, "fx_provider", "frankfurter").unwrap();
+    moneyclip::utils::set_setting(&conn, "fx_provider", "off").unwrap();
+    let err = moneyclip::utils::ensure_provider(&conn, "fx_provider", "frankfurter").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("config set fx_provider frankfurter")
+    );
+}
+
+#[test]
+fn init_base_on_an_existing_ledger_goes_through_change_base() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT, category_id INTEGER,
+            amount TEXT NOT NULL, currency TEXT);
+        CREATE TABLE goals(id INTEGER PRIMARY KEY, name TEXT, category_id INTEGER, target TEXT);
+        CREATE TABLE base_currency_changes(id INTEGER PRIMARY KEY, changed_at TEXT,
+            from_currency TEXT NOT NULL, to_currency TEXT NOT NULL, rate_date TEXT,
+            budgets INTEGER NOT NULL DEFAULT 0, goals INTEGER NOT NULL DEFAULT 0);
+        INSERT INTO budgets(month,category_id,amount) VALUES ('2025-01',1,'300');
+        "#,
+    )
+    .unwrap();
+    let base = |conn: &Connection| moneyclip::utils::get_setting(conn, "base_currency").unwrap();
+
+    // A new ledger just stores the base.
+    init::set_base(&mut conn, "USD").unwrap();
+    assert_eq!(base(&conn).as_deref(), Some("USD"));
+    init::set_base(&mut conn, "usd").unwrap();
+    let changes = |conn: &Connection| -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM base_currency_changes", [], |r| {
+            r.get(0)
+        })
+        .unwrap()
+    };
+    assert_eq!(changes(&conn), 0);
+
+    // Changing it later keeps unstamped budgets in the old base and is recorded.
+    init::set_base(&mut conn, "EUR").unwrap();
+    assert_eq!(base(&conn).as_deref(), Some("EUR"));
+    let budget_ccy: String = conn
+        .query_row("SELECT currency FROM budgets", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(budget_ccy, "USD");
+    assert_eq!(changes(&conn), 1);
+    assert!(init::set_base(&mut conn, "EURO").is_err());
+}
@@ -0,0 +1,136 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use chrono::NaiveDate;
+use moneyclip::{cli, commands::invoices};
+use rusqlite::Connection;
+
+fn setup() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
+        CREATE TABLE transactions(
+            id INTEGER PRIMARY KEY,
+            date TEXT NOT NULL,
+            account_id INTEGER NOT NULL,
+            amount TEXT NOT NULL,
+            payee TEXT NOT NULL,
+            category_id INTEGER,
+            currency TEXT NOT NULL,
+            note TEXT
+        );
+        CREATE TABLE fx_rates(date TEXT, base TEXT, quote TEXT, rate TEXT, PRIMARY KEY(date, base, quote));
+        CREATE TABLE invoices(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            number TEXT NOT NULL UNIQUE,
+            client TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            currency TEXT NOT NULL,
+            issued TEXT NOT NULL,
+            due TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'draft',
+            sent_on TEXT,
+            paid_on TEXT,
+            paid_tx_id INTEGER,
+            note TEXT,
+            created_at TEXT
+        );
+        INSERT INTO settings(key,value) VALUES ('base_currency','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','EUR','USD','1.10');
+        "#,
+    )
+    .unwrap();
+    conn
+}
+
+fn run(conn: &Connection, args: &[&str]) -> anyhow::Result<()> {
+    let mut argv = vec!["moneyclip", "invoice"];
+    argv.extend_from_slice(args);
+    let matches = cli::build_cli().get_matches_from(argv);
+    let Some(("invoice", m)) = matches.subcommand() else {
+        panic!("invoice command not parsed");
+    };
+    invoices::handle(conn, m)
+}
+
+#[test]
+fn aging_buckets_sent_invoices_until_paid() {
+    let conn = setup();
+    let create = |client: &str, amount: &str, ccy: &str, issued: &str, due: &str| {
+        run(
+            &conn,
+            &[
+                "create",
+                "--client",
+                client,
+                "--amount",
+                amount,
+                "--currency",
+                ccy,
+                "--issued",
+                issued,
+                "--due",
+                due,
+            ],
+        )
+        .unwrap();
+    };
+    create("Acme", "1000", "USD", "2025-01-01", "2025-01-31");
+    create("Acme", "500", "USD", "2025-03-01", "2025-03-31");
+    create("Globex", "200", "EUR", "2025-02-01", "2025-03-03");
+    create("Initech", "300", "USD", "2025-03-01", "2025-03-31");
+    for number in ["INV-0001", "INV-0002", "INV-0003"] {
+        run(&conn, &["sent", "--number", number, "--date", "2025-03-01"]).unwrap();
+    }
+
+    let as_of = NaiveDate::from_ymd_opt(2025, 4, 15).unwrap();
+    let aging = invoices::aging(&conn, as_of).unwrap();
+    // Initech's invoice is still a draft.
+    assert_eq!(aging.len(), 2);
+    assert_eq!(aging[0].client, "Acme");
+    assert_eq!(aging[0].days_1_30.to_string(), "500");
+    assert_eq!(aging[0].days_61_90.to_string(), "1000");
+    assert_eq!(aging[0].total.to_string(), "1500");
+    assert_eq!(aging[1].client, "Globex");
+    assert_eq!(aging[1].days_31_60.to_string(), "220.00");
+
+    conn.execute(
+        "INSERT INTO transactions(id,date,account_id,amount,payee,currency) VALUES
+         (10,'2025-04-10',1,'1000.00','Acme','USD'), (11,'2025-04-10',1,'-1000','Rent','USD')",
+        [],
+    )
+    .unwrap();
+    run(
+        &conn,
+        &["paid", "--number", "INV-0001", "--date", "2025-04-10"],
+    )
+    .unwrap();
+    let (status, paid_on, tx): (String, String, i64) = conn
+        .query_row(
+            "SELECT status, paid_on, paid_tx_id FROM invoices WHERE number='INV-0001'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        (status.as_str(), paid_on.as_str(), tx),
+        ("paid", "2025-04-10", 10)
+    );
+    let aging = invoices::aging(&conn, as_of).unwrap();
+    assert_eq!(aging[0].total.to_string(), "500");
+
+    let err = invoices::mark_paid(&conn, "INV-0002", 11)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("outflow"), "{}", err);
+    let err = invoices::mark_paid(&conn, "INV-0002", 10)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("already paid invoice INV-0001"), "{}", err);
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use moneyclip::{cli, commands};
+use rusqlite::Connection;
+
+fn base_conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT, default_category_id INTEGER);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT, monthly_cap TEXT, cap_currency TEXT);
+        CREATE TABLE transactions(
+            id INTEGER PRIMARY KEY,
+            date TEXT NOT NULL,
+            account_id INTEGER NOT NULL,
+            amount TEXT NOT NULL,
+            payee TEXT NOT NULL,
+            category_id INTEGER,
+            currency TEXT NOT NULL,
+            note TEXT
+        );
+        CREATE TABLE rules(id INTEGER PRIMARY KEY, pattern TEXT NOT NULL, category_id INTEGER, payee_rewrite TEXT, note TEXT, created_at TEXT, ignore_case INTEGER NOT NULL DEFAULT 0, literal INTEGER NOT NULL DEFAULT 0, match_field TEXT NOT NULL DEFAULT 'both', skip INTEGER NOT NULL DEFAULT 0);
+        CREATE TABLE closed_periods(month TEXT PRIMARY KEY, closed_at TEXT NOT NULL DEFAULT (datetime('now')));
+        CREATE TRIGGER transactions_closed_delete BEFORE DELETE ON transactions
+        WHEN EXISTS(SELECT 1 FROM closed_periods WHERE month = substr(OLD.date, 1, 7))
+        BEGIN SELECT RAISE(ABORT, 'transaction falls in a closed period'); END;
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','USD');
+        "#,
+    )
+    .unwrap();
+    conn
+}
+
+fn run(conn: &mut Connection, args: &[&str]) -> anyhow::Result<()> {
+    let argv = ["moneyclip", "--no-input"].iter().chain(args).copied();
+    let matches = cli::build_cli().try_get_matches_from(argv)?;
+    commands::dispatch(conn, &matches)
+}
+
+fn add(conn: &mut Connection, date: &str) -> anyhow::Result<()> {
+    run(
+        conn,
+        &[
+            "tx",
+            "add",
+            "--date",
+            date,
+            "--account",
+            "Cash",
+            "--amount",
+            "-5",
+            "--payee",
+            "P",
+        ],
+    )
+}
+
+#[test]
+fn closed_period_rejects_changes_until_forced_reopen() {
+    let mut conn = base_conn();
+    add(&mut conn, "2025-07-10").unwrap();
+
+    run(&mut conn, &["period", "close", "2025-07"]).unwrap();
+    let err = add(&mut conn, "2025-07-20").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Period 2025-07 is closed; reopen it with `period reopen 2025-07 --force`"
+    );
+    add(&mut conn, "2025-08-01").unwrap();
+    assert!(
+        conn.execute("DELETE FROM transactions WHERE date='2025-07-10'", [])
+            .is_err()
+    );
+
+    assert!(run(&mut conn, &["period", "reopen", "2025-07"]).is_err());
+    run(&mut conn, &["period", "reopen", "2025-07", "--force"]).unwrap();
+    add(&mut conn, "2025-07-20").unwrap();
+    conn.execute("DELETE FROM transactions WHERE date='2025-07-10'", [])
+        .unwrap();
+}
+
+#[test]
+fn close_month_runs_configured_checklist() {
+    let mut conn = base_conn();
+    assert!(
+        run(
+            &mut conn,
+            &["config", "set", "close_checklist", "lock,audit"]
+        )
+        .is_err()
+    );
+    run(&mut conn, &["config", "set", "close_checklist", "lock"]).unwrap();
+
+    run(&mut conn, &["close-month", "2025-03", "--dry-run"]).unwrap();
+    run(&mut conn, &["close-month", "2025-03", "--skip", "lock"]).unwrap();
+    add(&mut conn, "2025-03-10").unwrap();
+
+    run(&mut conn, &["close-month", "2025-03"]).unwrap();
+    assert!(add(&mut conn, "2025-03-11").is_err());
+    assert_eq!(
+        commands::close::parse_checklist(" doctor, lock,doctor ").unwrap(),
+        vec!["doctor", "lock"]
+    );
+}
+
+#[test]
+fn close_month_reconcile_stops_on_a_statement_difference() {
+    let mut conn = base_conn();
+    run(
+        &mut conn,
+        &["config", "set", "close_checklist", "reconcile,lock"],
+    )
+    .unwrap();
+    add(&mut conn, "2025-03-10").unwrap();
+    add(&mut conn, "2025-04-02").unwrap();
+
+    let err = run(
+        &mut conn,
+        &["close-month", "2025-03", "--statement", "Cash=-7"],
+    )
+    .unwrap_err();
+    assert!(format!("{:#}", err).contains("do not match their statement"));
+    add(&mut conn, "2025-03-11").unwrap();
+
+    // The April row is after the month end and does not count.
+    run(
+        &mut conn,
+        &["close-month", "2025-03", "--statement", "Cash=-10"],
+    )
+    .unwrap();
+    assert!(add(&mut conn, "2025-03-12").is_err());
+    assert!(
+        run(
+            &mut conn,
+            &["close-month", "2025-03", "--statement", "Cash"]
+        )
+        .is_err()
+    );
+}
@@ -0,0 +1,769 @@
+// Copyright (c) 2025 Soumyadip Sarkar.
+// All rights reserved.
+//
+// This source code is licensed under the license found in the
+// LICENSE file in the root directory of this source tree.
+
+use moneyclip::cli;
+use moneyclip::commands::{self, digest, reports, snapshots};
+use moneyclip::models::{FeeRow, GroupBalanceRow};
+use rusqlite::Connection;
+
+fn base_conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO settings(key,value) VALUES('base_currency','USD');
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE transactions(
+            id INTEGER PRIMARY KEY,
+            date TEXT NOT NULL,
+            account_id INTEGER NOT NULL,
+            amount TEXT NOT NULL,
+            payee TEXT NOT NULL,
+            category_id INTEGER,
+            currency TEXT NOT NULL,
+            note TEXT
+        );
+        CREATE TABLE assets(id INTEGER PRIMARY KEY, ticker TEXT, name TEXT, currency TEXT);
+        CREATE TABLE trades(
+            id INTEGER PRIMARY KEY,
+            date TEXT NOT NULL,
+            asset_id INTEGER NOT NULL,
+            account_id INTEGER NOT NULL,
+            quantity TEXT NOT NULL,
+            price TEXT NOT NULL,
+            fees TEXT NOT NULL DEFAULT '0',
+            side TEXT NOT NULL,
+            note TEXT,
+            fee_currency TEXT
+        );
+        CREATE TABLE fx_rates(date TEXT NOT NULL, base TEXT NOT NULL, quote TEXT NOT NULL, rate TEXT NOT NULL, UNIQUE(date, base, quote));
+        "#,
+    )
+    .unwrap();
+    conn
+}
+
+#[test]
+fn fees_report_combines_categories_and_trade_fees() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (2,'Broker','broker','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Fees');
+        INSERT INTO categories(id,name) VALUES (2,'Interest');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES ('2025-02-01',1,'-5.00','Bank',1,'USD');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES ('2025-03-01',1,'2.00','Bank refund',1,'USD');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES ('2024-12-01',1,'-9.00','Bank',1,'USD');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES ('2025-04-01',1,'-12.50','Card',2,'USD');
+        INSERT INTO assets(id,ticker,name,currency) VALUES (1,'SAP','SAP SE','EUR');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','USD','EUR','0.50');
+        INSERT INTO trades(date,asset_id,account_id,quantity,price,fees,side) VALUES ('2025-05-01',1,2,'1','100','1.50','buy');
+        "#,
+    )
+    .unwrap();
+
+    let rows = reports::build_fees_report(
+        &conn,
+        "2025",
+        &["Fees".to_string()],
+        &["Interest".to_string()],
+        "USD",
+    )
+    .unwrap();
+    let rows: Vec<_> = rows.into_iter().map(FeeRow::cells).collect();
+    assert_eq!(
+        rows,
+        vec![
+            vec!["Bank fees".to_string(), "Checking".into(), "3.00".into()],
+            vec!["Interest".to_string(), "Checking".into(), "12.50".into()],
+            vec!["Trade fees".to_string(), "Broker".into(), "3.00".into()],
+            vec!["Total".to_string(), String::new(), "18.50".into()],
+        ]
+    );
+}
+
+#[test]
+fn snapshot_diff_reports_changed_figures_only() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT, category_id INTEGER, amount TEXT, currency TEXT);
+        CREATE TABLE report_snapshots(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, created_at TEXT NOT NULL DEFAULT (datetime('now')));
+        CREATE TABLE report_snapshot_lines(snapshot_id INTEGER, section TEXT, key TEXT, value TEXT);
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Groceries'), (2,'Rent');
+        INSERT INTO budgets(month,category_id,amount) VALUES ('2025-07',1,'400');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-07-01',1,'2000','Salary',NULL,'USD'),
+            ('2025-07-03',1,'-1000','Landlord',2,'USD'),
+            ('2025-07-05',1,'-80.50','Market',1,'USD');
+        "#,
+    )
+    .unwrap();
+
+    snapshots::save_snapshot(&conn, "pre-close").unwrap();
+    assert!(snapshots::save_snapshot(&conn, "pre-close").is_err());
+    conn.execute_batch(
+        r#"
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency)
+            VALUES ('2025-07-28',1,'-19.50','Market',1,'USD');
+        UPDATE budgets SET amount='400.00';
+        "#,
+    )
+    .unwrap();
+    snapshots::save_snapshot(&conn, "post-close").unwrap();
+
+    let before = snapshots::load_snapshot(&conn, "pre-close").unwrap();
+    let after = snapshots::load_snapshot(&conn, "post-close").unwrap();
+    let changes: Vec<_> = snapshots::diff_figures(&before, &after)
+        .into_iter()
+        .map(|c| (c.section, c.key, c.change.to_string()))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (
+                "balance".to_string(),
+                "Checking (USD)".to_string(),
+                "-19.50".to_string()
+            ),
+            (
+                "spend".to_string(),
+                "2025-07 Groceries (USD)".to_string(),
+                "19.50".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        snapshots::diff_figures(&after, &snapshots::collect_figures(&conn).unwrap()),
+        vec![]
+    );
+}
+
+#[test]
+fn heatmap_covers_every_day_and_sums_expenses() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Dining'), (2,'Rent');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2024-03-01',1,'-12.50','Cafe',1,'USD'),
+            ('2024-03-01',1,'-7.50','Cafe',1,'USD'),
+            ('2024-03-01',1,'-900','Landlord',2,'USD'),
+            ('2024-03-02',1,'2000','Salary',NULL,'USD');
+        "#,
+    )
+    .unwrap();
+
+    let all = reports::build_heatmap(&conn, "2024", None, "USD").unwrap();
+    assert_eq!(all.len(), 366);
+    let march_first = all.iter().find(|d| d.date == "2024-03-01").unwrap();
+    assert_eq!(march_first.spent, "920.00");
+    assert_eq!(march_first.weekday, "Fri");
+    assert_eq!((march_first.week_year, march_first.week), (2024, 9));
+    // The last days of 2024 are in the first ISO week of 2025.
+    let last = all.last().unwrap();
+    assert_eq!((last.week_year, last.week), (2025, 1));
+    assert!(
+        all.iter()
+            .filter(|d| d.date != "2024-03-01")
+            .all(|d| d.spent == "0.00")
+    );
+
+    let dining = reports::build_heatmap(&conn, "2024", Some("Dining"), "USD").unwrap();
+    assert_eq!(
+        dining
+            .iter()
+            .find(|d| d.date == "2024-03-01")
+            .unwrap()
+            .spent,
+        "20.00"
+    );
+}
+
+#[test]
+fn subscriptions_detect_monthly_charges_and_price_increases() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Card','card','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-01-05',1,'-15.49','Netflix','USD'),
+            ('2025-02-05',1,'-15.49','NETFLIX ','USD'),
+            ('2025-03-05',1,'-17.99','Netflix','USD'),
+            ('2025-04-05',1,'-17.99','Netflix','USD'),
+            ('2025-01-10',1,'-9.99','Gym','USD'),
+            ('2025-02-09',1,'-9.99','Gym','USD'),
+            ('2025-03-11',1,'-9.99','Gym','USD'),
+            ('2025-01-03',1,'-40','Grocer','USD'),
+            ('2025-01-04',1,'-120','Grocer','USD'),
+            ('2025-02-20',1,'-60','Grocer','USD'),
+            ('2025-04-01',1,'3000','Employer','USD'),
+            ('2025-05-01',1,'3000','Employer','USD'),
+            ('2025-06-01',1,'3000','Employer','USD');
+        "#,
+    )
+    .unwrap();
+    let today = chrono::NaiveDate::from_ymd_opt(2025, 4, 20).unwrap();
+
+    let found = reports::detect_subscriptions(&conn, today, 3).unwrap();
+    assert_eq!(found.len(), 2);
+    let netflix = &found[0];
+    assert_eq!(netflix.payee, "Netflix");
+    assert_eq!(netflix.charges, 4);
+    assert_eq!(netflix.annualized.to_string(), "215.88");
+    assert_eq!(netflix.previous_amount.unwrap().to_string(), "15.49");
+    assert_eq!(netflix.changed_on.as_deref(), Some("2025-03-05"));
+    assert!(netflix.increased && netflix.active);
+
+    let gym = &found[1];
+    assert_eq!(gym.payee, "Gym");
+    assert!(gym.previous_amount.is_none() && !gym.increased);
+    assert!(gym.active);
+
+    assert!(
+        reports::detect_subscriptions(&conn, today, 4)
+            .unwrap()
+            .len()
+            == 1
+    );
+}
+
+#[test]
+fn anomalies_flag_outliers_new_payees_and_duplicates() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Card','card','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Groceries');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-01-04',1,'-52','Market',1,'USD'),
+            ('2025-01-18',1,'-61','Market',1,'USD'),
+            ('2025-02-02',1,'-48','Market',1,'USD'),
+            ('2025-02-16',1,'-55','Market',1,'USD'),
+            ('2025-03-03',1,'-58','Market',1,'USD'),
+            ('2025-03-17',1,'-50','Market',1,'USD'),
+            ('2025-04-05',1,'-57','Market',1,'USD'),
+            ('2025-04-12',1,'-412','Market',1,'USD'),
+            ('2025-04-20',1,'-250','Unknown Shop',NULL,'USD'),
+            ('2025-04-21',1,'-30','Tiny Shop',NULL,'USD'),
+            ('2025-04-22',1,'-19.99','Streamer',NULL,'USD'),
+            ('2025-04-22',1,'-19.99','Streamer',NULL,'USD');
+        "#,
+    )
+    .unwrap();
+
+    let found =
+        reports::find_anomalies(&conn, "2025-04", 3.0, rust_decimal::Decimal::from(100)).unwrap();
+    let summary: Vec<(&str, &str)> = found
+        .iter()
+        .map(|a| (a.payee.as_str(), a.reason.as_str()))
+        .collect();
+    assert_eq!(summary.len(), 4, "{:?}", summary);
+    assert_eq!(found[0].amount.to_string(), "412");
+    assert!(summary[0].1.contains("above usual 54.00"));
+    assert_eq!(summary[1], ("Unknown Shop", "new payee"));
+    assert_eq!(summary[2], ("Streamer", "possible duplicate"));
+    assert_eq!(summary[3], ("Streamer", "possible duplicate"));
+}
+
+#[test]
+fn balances_by_group_subtotals_in_base() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE accounts ADD COLUMN group_name TEXT;
+        INSERT INTO accounts(id,name,type,currency,group_name) VALUES
+            (1,'Checking','bank','USD','Liquid'),
+            (2,'Euro Savings','bank','EUR','Liquid'),
+            (3,'401k','broker','USD','Retirement'),
+            (4,'Wallet','cash','USD',NULL);
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','USD','EUR','0.5');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-01-02',1,'100.50','Pay','USD'),
+            ('2025-01-02',2,'50','Pay','EUR'),
+            ('2025-01-02',3,'1000','Pay','USD'),
+            ('2025-01-02',4,'20','Pay','USD');
+        "#,
+    )
+    .unwrap();
+    let date = chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+    let rows: Vec<_> = reports::balances_by_group(&conn, "USD", date)
+        .unwrap()
+        .into_iter()
+        .map(GroupBalanceRow::cells)
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            vec!["Liquid", "Checking", "100.50"],
+            vec!["Liquid", "Euro Savings", "100.00"],
+            vec!["Liquid", "Subtotal", "200.50"],
+            vec!["Retirement", "401k", "1000.00"],
+            vec!["Retirement", "Subtotal", "1000.00"],
+            vec!["(ungrouped)", "Wallet", "20.00"],
+            vec!["(ungrouped)", "Subtotal", "20.00"],
+            vec!["Total", "", "1220.50"],
+        ]
+    );
+}
+
+#[test]
+fn digest_summarises_balances_spend_overruns_and_portfolio() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE budgets(id INTEGER PRIMARY KEY, month TEXT, category_id INTEGER, amount TEXT, currency TEXT);
+        CREATE TABLE prices(id INTEGER PRIMARY KEY, asset_id INTEGER, as_of TEXT, price TEXT, source TEXT);
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD'), (2,'Euro','bank','EUR');
+        INSERT INTO categories(id,name) VALUES (1,'Groceries'), (2,'Dining');
+        INSERT INTO budgets(month,category_id,amount) VALUES ('2025-07',1,'100'), ('2025-07',2,'500');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','USD','EUR','0.50');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-06-30',1,'1000','Salary',NULL,'USD'),
+            ('2025-07-02',1,'-80','Market',1,'USD'),
+            ('2025-07-03',2,'-20','Market',1,'EUR'),
+            ('2025-07-04',1,'-30','Bistro',2,'USD');
+        INSERT INTO assets(id,ticker,name,currency) VALUES (1,'SAP','SAP SE','EUR');
+        INSERT INTO trades(date,asset_id,account_id,quantity,price,side) VALUES ('2025-06-01',1,1,'2','100','buy');
+        INSERT INTO prices(asset_id,as_of,price,source) VALUES (1,'2025-06-30','100','manual'), (1,'2025-07-05','110','manual'), (1,'2025-08-01','500','manual');
+        "#,
+    )
+    .unwrap();
+
+    let end = chrono::NaiveDate::from_ymd_opt(2025, 7, 7).unwrap();
+    let start = digest::period_start("week", end).unwrap();
+    assert_eq!(start.to_string(), "2025-07-01");
+    let md = digest::build_digest(&conn, start, end).unwrap();
+
+    assert!(
+        md.contains("| Checking | 1000.00 | 890.00 | -110.00 |"),
+        "{}",
+        md
+    );
+    assert!(md.contains("| Euro | 0.00 | -40.00 | -40.00 |"), "{}", md);
+    assert!(
+        md.contains("| **Total** | 1000.00 | 850.00 | -150.00 |"),
+        "{}",
+        md
+    );
+    let groceries = md.find("| Groceries | 120.00 |").expect("groceries row");
+    let dining = md.find("| Dining | 30.00 |").expect("dining row");
+    assert!(groceries < dining);
+    assert!(md.contains("- 2025-07 Groceries: spent 120.00 of 100.00 (+20.00)"));
+    assert!(!md.contains("Dining: spent"));
+    assert!(md.contains("Value 400.00 -> 440.00 (+40.00)"), "{}", md);
+}
+
+#[test]
+fn spend_totals_count_outflows_per_currency_and_convert() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-04-01','USD','EUR','0.8');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-04-03',1,'-10.00','Cafe','USD'),
+            ('2025-04-05',2,'-8.00','Bakery','EUR'),
+            ('2025-04-06',1,'500.00','Salary','USD'),
+            ('2025-05-01',1,'-99.00','Later','USD');
+        "#,
+    )
+    .unwrap();
+
+    let filter = rusqlite::params![Some("2025-04"), None::<String>, None::<String>];
+    let totals = reports::spend_totals(&conn, filter, "USD", None).unwrap();
+    assert_eq!(totals.count, 2);
+    assert_eq!(totals.by_currency["USD"], "10.00");
+    assert_eq!(totals.by_currency["EUR"], "8.00");
+    assert_eq!(totals.converted, "20.00");
+    assert_eq!(
+        totals.lines().last().unwrap(),
+        &("20.00".to_string(), "USD (converted)".to_string())
+    );
+}
+
+#[test]
+fn out_writes_the_chosen_format_to_a_file() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Fees');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES ('2025-02-01',1,'-5.00','Bank',1,'USD');
+        "#,
+    )
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fees.csv");
+    let run = |conn: &mut Connection, format: &str| {
+        let matches = cli::build_cli().get_matches_from([
+            "moneyclip",
+            "report",
+            "fees",
+            "--year",
+            "2025",
+            "--format",
+            format,
+            "--out",
+            path.to_str().unwrap(),
+        ]);
+        commands::dispatch(conn, &matches).unwrap();
+        std::fs::read_to_string(&path).unwrap()
+    };
+
+    assert_eq!(
+        run(&mut conn, "csv"),
+        "Source,Account,Cost (USD)\nBank fees,Checking,5.00\nTotal,,5.00\n"
+    );
+    let json: serde_json::Value = serde_json::from_str(&run(&mut conn, "json")).unwrap();
+    assert_eq!(
+        json[0],
+        serde_json::json!({
+            "schema_version": 1,
+            "source": "Bank fees",
+            "account": "Checking",
+            "cost": "5.00"
+        })
+    );
+    assert!(run(&mut conn, "html").starts_with("<table>"));
+    // Nothing but the target is left behind.
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn real_cashflow_deflates_by_cpi() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE cpi(month TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2020-03-01',1,'1000','Salary','USD'),
+            ('2024-03-01',1,'1000','Salary','USD'),
+            ('2024-03-02',1,'-300','Rent','USD');
+        "#,
+    )
+    .unwrap();
+    let values = moneyclip::commands::cpi::parse_cpi_csv(
+        "observation_date,CPIAUCSL\n2020-01-01,100\n2020-02-01,.\n2024-01-01,125\n",
+    )
+    .unwrap();
+    assert_eq!(values.len(), 2);
+    for (month, value) in values {
+        conn.execute(
+            "INSERT INTO cpi(month,value) VALUES (?1,?2)",
+            [month, value.to_string()],
+        )
+        .unwrap();
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("real.csv");
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "report",
+        "cashflow",
+        "--real",
+        "--cpi-base",
+        "2020",
+        "--format",
+        "csv",
+        "--out",
+        path.to_str().unwrap(),
+    ]);
+    commands::dispatch(&mut conn, &matches).unwrap();
+    // 2020 months keep their value; 2024 uses the January index (latest on file).
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "Month,Income,Expense\n2024-03,800.00,240.00\n2020-03,1000.00,0.00\n"
+    );
+}
+
+#[test]
+fn compare_sorts_groups_by_biggest_change() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Groceries'), (2,'Travel');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-07-01','USD','EUR','0.50');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-07-03',1,'-200','Market',1,'USD'),
+            ('2025-08-03',1,'-150','Market',1,'USD'),
+            ('2025-08-10',1,'-300','Airline',2,'EUR'),
+            ('2025-08-11',1,'-20','Kiosk',NULL,'USD'),
+            ('2025-08-12',1,'2000','Salary',NULL,'USD');
+        "#,
+    )
+    .unwrap();
+    let rows = reports::compare_spending(&conn, "2025-07", "2025-08", "category", "USD").unwrap();
+    let got: Vec<_> = rows
+        .iter()
+        .map(|r| {
+            (
+                r.key.as_str(),
+                r.change.normalize().to_string(),
+                r.change_percent.map(|p| p.normalize().to_string()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            ("Travel", "600".to_string(), None),
+            ("Groceries", "-50".to_string(), Some("-25".to_string())),
+            ("(uncategorized)", "20".to_string(), None),
+        ]
+    );
+}
+
+#[test]
+fn balance_history_fills_every_day_from_the_opening_balance() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-07-20',1,'100','Opening','USD'),
+            ('2025-08-02',1,'-150','Rent','USD'),
+            ('2025-08-02',1,'20','Refund','USD'),
+            ('2025-08-04',1,'500','Salary','USD');
+        "#,
+    )
+    .unwrap();
+    let date = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let days = reports::daily_balances(
+        &conn,
+        "Checking",
+        Some(date("2025-08-01")),
+        date("2025-08-04"),
+        None,
+    )
+    .unwrap();
+    let got: Vec<_> = days
+        .iter()
+        .map(|d| (d.date.as_str(), d.balance.to_string()))
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            ("2025-08-01", "100".to_string()),
+            ("2025-08-02", "-30".to_string()),
+            ("2025-08-03", "-30".to_string()),
+            ("2025-08-04", "470".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn json_rows_carry_schema_version_and_plain_amounts() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES ('2025-02-01',1,'12.50','Pay','USD');
+        "#,
+    )
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("balances.json");
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "--symbols",
+        "report",
+        "balances",
+        "--base",
+        "--json",
+        "--out",
+        path.to_str().unwrap(),
+    ]);
+    commands::dispatch(&mut conn, &matches).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([{
+            "schema_version": moneyclip::models::SCHEMA_VERSION,
+            "account": "Checking",
+            "currency": "USD",
+            "balance": "12.50"
+        }])
+    );
+}
+
+#[test]
+fn balances_keep_foreign_currency_rows_apart() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE accounts ADD COLUMN group_name TEXT;
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD'), (2,'Cash','cash','USD');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','USD','EUR','0.5');
+        INSERT INTO transactions(date,account_id,amount,payee,currency) VALUES
+            ('2025-01-02',1,'0.10','Pay','USD'),
+            ('2025-01-02',1,'0.20','Pay','USD'),
+            ('2025-01-03',1,'10','Refund','EUR');
+        "#,
+    )
+    .unwrap();
+    let balances: Vec<_> = reports::account_balances(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|a| (a.name, a.by_currency))
+        .collect();
+    assert_eq!(
+        balances,
+        vec![
+            (
+                "Cash".to_string(),
+                vec![("USD".to_string(), rust_decimal::Decimal::ZERO)]
+            ),
+            (
+                "Checking".to_string(),
+                vec![
+                    ("USD".to_string(), rust_decimal::Decimal::new(30, 2)),
+                    ("EUR".to_string(), rust_decimal::Decimal::from(10)),
+                ]
+            ),
+        ]
+    );
+
+    // Converted, the EUR rows count at the EUR rate rather than as dollars.
+    let date = chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+    let grouped = reports::balances_by_group(&conn, "USD", date).unwrap();
+    assert_eq!(grouped[1].account, "Checking");
+    assert_eq!(grouped[1].balance, "20.30");
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("balances.json");
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "report",
+        "balances",
+        "--json",
+        "--out",
+        path.to_str().unwrap(),
+    ]);
+    commands::dispatch(&mut conn, &matches).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let rows: Vec<_> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["account"].as_str().unwrap(),
+                r["currency"].as_str().unwrap(),
+                r["balance"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("Cash", "USD", "0.00"),
+            ("Checking", "USD", "0.30"),
+            ("Checking", "EUR", "10.00"),
+        ]
+    );
+}
+
+#[test]
+fn spend_by_category_chart_draws_a_bar_per_category() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Rent'),(2,'Dining');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-08-01',1,'-9.00','Cafe',2,'USD'),
+            ('2025-08-02',1,'-27.00','Landlord',1,'USD'),
+            ('2025-08-03',1,'100.00','Employer',NULL,'USD');
+        "#,
+    )
+    .unwrap();
+    for extra in [None, Some("--base")] {
+        let mut args = vec![
+            "moneyclip",
+            "report",
+            "spend-by-category",
+            "--month",
+            "2025-08",
+            "--chart",
+        ];
+        args.extend(extra);
+        let matches = cli::build_cli().get_matches_from(args);
+        moneyclip::utils::start_capture();
+        commands::dispatch(&mut conn, &matches).unwrap();
+        let out = moneyclip::utils::take_capture();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2, "{out}");
+        // Largest first, drawn at full width; the smaller bar is scaled to it.
+        assert!(lines[0].starts_with("Rent   ") && lines[0].contains(&"█".repeat(40)));
+        assert!(
+            lines[0].contains("27.00") && lines[0].trim_end().ends_with(" 75.0%"),
+            "{out}"
+        );
+        assert!(lines[1].starts_with("Dining ") && !lines[1].contains(&"█".repeat(14)));
+        assert!(lines[1].contains(" 9.00") && lines[1].trim_end().ends_with(" 25.0%"));
+    }
+}
+
+#[test]
+fn constant_fx_values_every_month_at_the_fixed_date() {
+    let mut conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Euro','bank','EUR');
+        INSERT INTO categories(id,name) VALUES (1,'Rent');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','USD','EUR','0.5'), ('2025-02-01','USD','EUR','0.8');
+        INSERT INTO transactions(date,account_id,amount,payee,category_id,currency) VALUES
+            ('2025-01-10',1,'-10.00','Landlord',1,'EUR'),
+            ('2025-02-10',1,'-10.00','Landlord',1,'EUR');
+        "#,
+    )
+    .unwrap();
+    let mut json = |args: &[&str]| -> serde_json::Value {
+        let argv = ["moneyclip", "report"].iter().chain(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        moneyclip::utils::start_capture();
+        commands::dispatch(&mut conn, &matches).unwrap();
+        serde_json::from_str(&moneyclip::utils::take_capture()).unwrap()
+    };
+
+    // At each date's rate February costs less; at January's rate both months match.
+    let range = [
+        "--from",
+        "2025-01-01",
+        "--to",
+        "2025-02-28",
+        "--base",
+        "--json",
+    ];
+    let per_date = json(&[&["spend-by-category"], &range[..]].concat());
+    assert_eq!(per_date[0]["spent"], "32.50");
+    let fixed = json(
+        &[
+            &["spend-by-category"],
+            &range[..],
+            &["--constant-fx", "2025-01-15"],
+        ]
+        .concat(),
+    );
+    assert_eq!(fixed[0]["spent"], "40.00");
+
+    let fixed = json(&[&["cashflow"], &range[..], &["--constant-fx", "2025-01-15"]].concat());
+    let expenses: Vec<&str> = fixed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["expense"].as_str().unwrap())
+        .collect();
+    assert_eq!(expenses, vec!["20.00", "20.00"]);
+}
@@ -13,8 +13,8 @@ fn setup() -> Connection {
     conn.execute_batch(r#"
         CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
         INSERT INTO settings(key,value) VALUES('base_currency','USD');
-        CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
-        CREATE TABLE rules(id INTEGER PRIMARY KEY AUTOINCREMENT, pattern TEXT NOT NULL, category_id INTEGER, payee_rewrite TEXT, note TEXT, created_at TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, monthly_cap TEXT, cap_currency TEXT);
+        CREATE TABLE rules(id INTEGER PRIMARY KEY AUTOINCREMENT, pattern TEXT NOT NULL, category_id INTEGER, payee_rewrite TEXT, note TEXT, created_at TEXT, ignore_case INTEGER NOT NULL DEFAULT 0, literal INTEGER NOT NULL DEFAULT 0, match_field TEXT NOT NULL DEFAULT 'both', skip INTEGER NOT NULL DEFAULT 0);
     "#).unwrap();
     conn.execute("INSERT INTO categories(name) VALUES('Shopping')", [])
         .unwrap();
@@ -38,6 +38,87 @@ fn rule_applies_regex_and_rewrite() {
     assert_eq!(r, Some(String::from("Amazon")));
 }
 
+#[test]
+fn rule_rewrite_expands_capture_groups() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        INSERT INTO rules(pattern, payee_rewrite) VALUES('(?i)^UBER\s+\*?(TRIP|EATS)', 'Uber $1');
+        INSERT INTO rules(pattern, payee_rewrite) VALUES('(?i)^POS (?<shop>[A-Z]+)\d*', '${shop} (card) $$');
+        "#,
+    )
+    .unwrap();
+    moneyclip::utils::invalidate_rule_cache(&conn);
+
+    let rewrite = |payee: &str| {
+        moneyclip::utils::apply_import_rules(&conn, payee, None)
+            .unwrap()
+            .1
+    };
+    assert_eq!(
+        rewrite("UBER *EATS 8005928996"),
+        Some(String::from("Uber EATS"))
+    );
+    assert_eq!(rewrite("uber trip"), Some(String::from("Uber trip")));
+    assert_eq!(
+        rewrite("POS TESCO123 LONDON"),
+        Some(String::from("TESCO (card) $"))
+    );
+}
+
+#[test]
+fn rules_add_stores_literal_case_and_field_options() {
+    let conn = setup();
+    let add = |args: &[&str]| {
+        let argv = ["moneyclip", "rules", "add"].iter().chain(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("rules", rules_m)) = matches.subcommand() else {
+            panic!("rules command not parsed");
+        };
+        rules::handle(&conn, rules_m).unwrap();
+    };
+    // Unescaped this would be an invalid regex (`*` repeating nothing).
+    add(&[
+        "--pattern",
+        "*SQ *BLUE BOTTLE",
+        "--literal",
+        "--ignore-case",
+        "--category",
+        "Shopping",
+    ]);
+    add(&[
+        "--pattern",
+        "reimb",
+        "--match-field",
+        "note",
+        "--payee_rewrite",
+        "Reimbursed",
+    ]);
+    add(&[
+        "--pattern",
+        "Gift",
+        "--match-field",
+        "payee",
+        "--payee_rewrite",
+        "Gift shop",
+    ]);
+
+    let apply = |payee: &str, memo: Option<&str>| {
+        moneyclip::utils::apply_import_rules(&conn, payee, memo).unwrap()
+    };
+    assert!(apply("*sq *blue bottle coffee", None).0.is_some());
+    assert_eq!(apply("SQ BLUE BOTTLE", None), (None, None));
+    assert_eq!(apply("reimb corp", None), (None, None));
+    assert_eq!(
+        apply("ACME", Some("reimb pending")).1,
+        Some(String::from("Reimbursed"))
+    );
+    // Payee-only rules ignore the note, and matching stays case-sensitive by default.
+    assert_eq!(apply("Store", Some("Gift for Sam")), (None, None));
+    assert_eq!(apply("gift", None), (None, None));
+    assert_eq!(apply("Gift", None).1, Some(String::from("Gift shop")));
+}
+
 #[test]
 fn rules_add_rejects_invalid_regex() {
     let conn = setup();
@@ -130,9 +211,9 @@ fn rule_cache_invalidation_affects_other_connections() {
             r#"
         CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
         INSERT INTO settings(key,value) VALUES('base_currency','USD');
-        CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, monthly_cap TEXT, cap_currency TEXT);
         CREATE TABLE rules(id INTEGER PRIMARY KEY AUTOINCREMENT, pattern TEXT NOT NULL, category_id INTEGER, payee_rewrite TEXT,
- note TEXT, created_at TEXT);
+ note TEXT, created_at TEXT, ignore_case INTEGER NOT NULL DEFAULT 0, literal INTEGER NOT NULL DEFAULT 0, match_field TEXT NOT NULL DEFAULT 'both', skip INTEGER NOT NULL DEFAULT 0);
     "#,
         )
         .unwrap();
@@ -175,8 +256,8 @@ fn rule_cache_detects_external_mutations() {
             r#"
         CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
         INSERT INTO settings(key,value) VALUES('base_currency','USD');
-        CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE);
-        CREATE TABLE rules(id INTEGER PRIMARY KEY AUTOINCREMENT, pattern TEXT NOT NULL, category_id INTEGER, payee_rewrite TEXT, note TEXT, created_at TEXT);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, monthly_cap TEXT, cap_currency TEXT);
+        CREATE TABLE rules(id INTEGER PRIMARY KEY AUTOINCREMENT, pattern TEXT NOT NULL, category_id INTEGER, payee_rewrite TEXT, note TEXT, created_at TEXT, ignore_case INTEGER NOT NULL DEFAULT 0, literal INTEGER NOT NULL DEFAULT 0, match_field TEXT NOT NULL DEFAULT 'both', skip INTEGER NOT NULL DEFAULT 0);
     "#,
         )
         .unwrap();
@@ -206,3 +287,67 @@ fn rule_cache_detects_external_mutations() {
         moneyclip::utils::apply_import_rules(&conn_b, "AMAZON MARKETPLACE", None).unwrap();
     assert_eq!(rewrite_updated, Some(String::from("Amazon Fresh")));
 }
+
+#[test]
+fn rules_bootstrap_seeds_pack_once_in_pack_order() {
+    let conn = setup();
+    let (added, skipped, created) = rules::bootstrap(&conn, "default-us").unwrap();
+    assert!(added > 0);
+    assert_eq!(skipped, 0);
+    assert!(created.contains(&String::from("Groceries")));
+    assert!(!created.contains(&String::from("Shopping")));
+
+    let category = |payee: &str| -> Option<String> {
+        let (cat, _) = moneyclip::utils::apply_import_rules(&conn, payee, None).unwrap();
+        cat.map(|id| {
+            conn.query_row("SELECT name FROM categories WHERE id=?1", [id], |r| {
+                r.get(0)
+            })
+            .unwrap()
+        })
+    };
+    assert_eq!(category("WHOLE FOODS #123").as_deref(), Some("Groceries"));
+    // The Dining entry comes before Transport in the pack, so it wins for Uber Eats.
+    assert_eq!(category("UBER EATS 8005928996").as_deref(), Some("Dining"));
+    assert_eq!(category("UBER *TRIP").as_deref(), Some("Transport"));
+    let (_, rewrite) =
+        moneyclip::utils::apply_import_rules(&conn, "AMZN Mktp US*AB123", None).unwrap();
+    assert_eq!(rewrite, Some(String::from("Amazon")));
+
+    let (again, skipped, created) = rules::bootstrap(&conn, "default-us").unwrap();
+    assert_eq!((again, skipped), (0, added));
+    assert!(created.is_empty());
+    assert!(rules::bootstrap(&conn, "default-xx").is_err());
+}
+
+#[test]
+fn rules_lint_reports_overlaps_and_shadowed_rules() {
+    let conn = setup();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE transactions(id INTEGER PRIMARY KEY, date TEXT, payee TEXT, note TEXT);
+        INSERT INTO rules(pattern, category_id) VALUES ('(?i)uber eats', 1);
+        INSERT INTO rules(pattern, category_id) VALUES ('(?i)uber', 1);
+        INSERT INTO rules(pattern, category_id) VALUES ('(?i)coffee', 1);
+        INSERT INTO transactions(date, payee, note) VALUES
+            ('2025-05-01', 'UBER EATS 123', NULL),
+            ('2025-05-02', 'Uber Trip', NULL),
+            ('2025-05-03', 'Corner Coffee', NULL),
+            ('2024-01-01', 'Old Coffee Uber', NULL);
+        "#,
+    )
+    .unwrap();
+    moneyclip::utils::invalidate_rule_cache(&conn);
+    let since = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+    let lint = rules::lint(&conn, since).unwrap();
+    assert_eq!(lint.payees_checked, 3);
+    assert_eq!(lint.overlaps.len(), 1);
+    assert_eq!((lint.overlaps[0].rule, lint.overlaps[0].shadows), (2, 1));
+    assert_eq!(lint.overlaps[0].examples, vec!["UBER EATS 123"]);
+    assert_eq!(lint.shadowed.len(), 1);
+    assert_eq!(
+        (lint.shadowed[0].rule, lint.shadowed[0].by.clone()),
+        (1, vec![2])
+    );
+}
@@ -4,15 +4,20 @@
 // This source code is licensed under the license found in the
 // LICENSE file in the root directory of this source tree.
 
-use moneyclip::{cli, commands::transactions};
+use moneyclip::{
+    cli,
+    commands::{accounts, config, transactions},
+};
 use rusqlite::{Connection, params};
 
 fn base_conn() -> Connection {
     let conn = Connection::open_in_memory().unwrap();
     conn.execute_batch(
         r#"
-        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT);
-        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT);
+        CREATE TABLE settings(key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        CREATE TABLE accounts(id INTEGER PRIMARY KEY, name TEXT, type TEXT, currency TEXT, default_category_id INTEGER);
+        CREATE TABLE categories(id INTEGER PRIMARY KEY, name TEXT, monthly_cap TEXT, cap_currency TEXT);
+        CREATE TABLE closed_periods(month TEXT PRIMARY KEY, closed_at TEXT);
         CREATE TABLE transactions(
             id INTEGER PRIMARY KEY,
             date TEXT NOT NULL,
@@ -29,7 +34,11 @@ fn base_conn() -> Connection {
             category_id INTEGER,
             payee_rewrite TEXT,
             note TEXT,
-            created_at TEXT
+            created_at TEXT,
+            ignore_case INTEGER NOT NULL DEFAULT 0,
+            literal INTEGER NOT NULL DEFAULT 0,
+            match_field TEXT NOT NULL DEFAULT 'both',
+            skip INTEGER NOT NULL DEFAULT 0
         );
         "#,
     )
@@ -106,6 +115,113 @@ fn list_filters_trim_inputs() {
     }
 }
 
+fn list_rows(
+    conn: &Connection,
+    args: &[&str],
+) -> anyhow::Result<Vec<transactions::TransactionRow>> {
+    let argv = ["moneyclip", "tx", "list"].iter().chain(args).copied();
+    let matches = cli::build_cli().get_matches_from(argv);
+    let Some(("tx", tx_m)) = matches.subcommand() else {
+        panic!("no tx subcommand");
+    };
+    let Some(("list", list_m)) = tx_m.subcommand() else {
+        panic!("no list subcommand");
+    };
+    transactions::query_rows(conn, list_m)
+}
+
+#[test]
+fn list_date_range_is_inclusive() {
+    let conn = setup_with_seed_transactions();
+    let dates = |args: &[&str]| -> Vec<String> {
+        list_rows(&conn, args)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.date)
+            .collect()
+    };
+    assert_eq!(
+        dates(&["--from", "2025-01-02", "--to", "2025-01-02"]),
+        vec!["2025-01-02"]
+    );
+    assert_eq!(
+        dates(&["--from", "2025-01-02"]),
+        vec!["2025-01-03", "2025-01-02"]
+    );
+    assert_eq!(dates(&["--to", "2025-01-01"]), vec!["2025-01-01"]);
+    let Err(err) = list_rows(&conn, &["--from", "2025-02-01", "--to", "2025-01-01"]) else {
+        panic!("reversed range accepted");
+    };
+    assert_eq!(
+        err.to_string(),
+        "--from 2025-02-01 is after --to 2025-01-01"
+    );
+}
+
+#[test]
+fn list_pages_sorts_and_counts() {
+    let conn = setup_with_seed_transactions();
+    conn.execute_batch(
+        "UPDATE transactions SET amount='-5', payee='b' WHERE date='2025-01-01';
+         UPDATE transactions SET amount='-30', payee='C' WHERE date='2025-01-02';
+         UPDATE transactions SET amount='-100', payee='a' WHERE date='2025-01-03';",
+    )
+    .unwrap();
+    let payees = |args: &[&str]| -> Vec<String> {
+        list_rows(&conn, args)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.payee)
+            .collect()
+    };
+    assert_eq!(payees(&["--limit", "1", "--offset", "1"]), vec!["C"]);
+    assert_eq!(payees(&["--offset", "2"]), vec!["b"]);
+    assert_eq!(payees(&["--sort", "amount"]), vec!["b", "C", "a"]);
+    assert_eq!(payees(&["--sort", "payee", "--asc"]), vec!["a", "b", "C"]);
+    assert_eq!(payees(&["--asc", "--limit", "2"]), vec!["b", "C"]);
+
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "tx",
+        "list",
+        "--from",
+        "2025-01-02",
+        "--count-only",
+    ]);
+    let (_, list_m) = cli::command_path(&matches);
+    assert_eq!(transactions::count_rows(&conn, list_m).unwrap(), 2);
+}
+
+#[test]
+fn list_cleanup_filters_and_footer() {
+    let conn = setup_with_seed_transactions();
+    conn.execute_batch(
+        "UPDATE transactions SET category_id=NULL, note='card' WHERE date='2025-01-01';
+         UPDATE transactions SET currency='EUR', note=' ' WHERE date='2025-01-02';",
+    )
+    .unwrap();
+    let dates = |args: &[&str]| -> Vec<String> {
+        list_rows(&conn, args)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.date)
+            .collect()
+    };
+    assert_eq!(dates(&["--uncategorized"]), vec!["2025-01-01"]);
+    assert_eq!(dates(&["--no-note"]), vec!["2025-01-03", "2025-01-02"]);
+    assert_eq!(dates(&["--currency", "eur"]), vec!["2025-01-02"]);
+
+    let rows = list_rows(&conn, &[]).unwrap();
+    assert_eq!(
+        transactions::list_footer(&rows).unwrap(),
+        "3 transactions, totals -10.00 EUR, -20.00 USD"
+    );
+    assert_eq!(
+        transactions::list_footer(&rows[..1]).unwrap(),
+        "1 transaction, total -10.00 USD"
+    );
+}
+
 #[test]
 fn manual_add_applies_rewrite_even_with_manual_category() {
     let conn = base_conn();
@@ -255,3 +371,803 @@ fn manual_add_errors_on_invalid_rule_regex() {
         .unwrap();
     assert_eq!(count, 0);
 }
+
+fn run_tx(conn: &Connection, args: &[&str]) -> anyhow::Result<()> {
+    let mut argv = vec!["moneyclip", "tx"];
+    argv.extend_from_slice(args);
+    let matches = cli::build_cli().get_matches_from(argv);
+    let Some(("tx", tx_m)) = matches.subcommand() else {
+        panic!("tx command not parsed");
+    };
+    transactions::handle(conn, tx_m)
+}
+
+#[test]
+fn tx_add_from_template_uses_saved_fields_and_overrides() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE tx_templates(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            account TEXT,
+            amount TEXT,
+            payee TEXT,
+            category TEXT,
+            note TEXT
+        );
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','EUR');
+        INSERT INTO categories(id,name) VALUES (1,'Dining');
+        "#,
+    )
+    .unwrap();
+
+    run_tx(
+        &conn,
+        &[
+            "template",
+            "save",
+            "--name",
+            "coffee",
+            "--account",
+            "Cash",
+            "--category",
+            "Dining",
+            "--amount",
+            "-4.50",
+        ],
+    )
+    .unwrap();
+    assert!(
+        run_tx(
+            &conn,
+            &["template", "save", "--name", "bad", "--account", "Nope"]
+        )
+        .is_err()
+    );
+
+    run_tx(
+        &conn,
+        &["add", "--template", "coffee", "--date", "2025-03-01"],
+    )
+    .unwrap();
+    run_tx(
+        &conn,
+        &["template", "edit", "--name", "coffee", "--amount", "-5"],
+    )
+    .unwrap();
+    run_tx(
+        &conn,
+        &[
+            "add",
+            "--template",
+            "coffee",
+            "--date",
+            "2025-03-02",
+            "--payee",
+            "Kiosk",
+        ],
+    )
+    .unwrap();
+
+    let mut stmt = conn
+        .prepare("SELECT date, amount, payee, category_id, currency FROM transactions ORDER BY id")
+        .unwrap();
+    let rows: Vec<(String, String, String, Option<i64>, String)> = stmt
+        .query_map([], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))
+        })
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (
+                "2025-03-01".into(),
+                "-4.50".into(),
+                "coffee".into(),
+                Some(1),
+                "EUR".into()
+            ),
+            (
+                "2025-03-02".into(),
+                "-5".into(),
+                "Kiosk".into(),
+                Some(1),
+                "EUR".into()
+            ),
+        ]
+    );
+
+    run_tx(&conn, &["template", "rm", "--name", "coffee"]).unwrap();
+    assert!(run_tx(&conn, &["add", "--template", "coffee"]).is_err());
+}
+
+#[test]
+fn tx_add_falls_back_to_configured_defaults() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (2,'Visa','card','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Misc');
+        INSERT INTO categories(id,name) VALUES (2,'Card Payment');
+        "#,
+    )
+    .unwrap();
+    let run = |argv: &[&str]| {
+        let matches = cli::build_cli().get_matches_from(argv);
+        match matches.subcommand() {
+            Some(("config", m)) => config::handle(&conn, m),
+            Some(("account", m)) => accounts::handle(&conn, m),
+            Some(("tx", m)) => transactions::handle(&conn, m),
+            _ => panic!("command not parsed"),
+        }
+    };
+
+    assert!(
+        run(&[
+            "moneyclip",
+            "--no-input",
+            "tx",
+            "add",
+            "--date",
+            "2025-01-01",
+            "--amount",
+            "1",
+            "--payee",
+            "P"
+        ])
+        .is_err()
+    );
+    assert!(run(&["moneyclip", "config", "set", "default_account", "Nope"]).is_err());
+    assert!(run(&["moneyclip", "config", "set", "colour", "blue"]).is_err());
+    run(&["moneyclip", "config", "set", "default_account", "Cash"]).unwrap();
+    run(&["moneyclip", "config", "set", "default_category", "Misc"]).unwrap();
+    run(&[
+        "moneyclip",
+        "account",
+        "set-default-category",
+        "--name",
+        "Visa",
+        "--category",
+        "Card Payment",
+    ])
+    .unwrap();
+
+    run(&[
+        "moneyclip",
+        "tx",
+        "add",
+        "--date",
+        "2025-01-02",
+        "--amount",
+        "-3",
+        "--payee",
+        "Snack",
+    ])
+    .unwrap();
+    run(&[
+        "moneyclip",
+        "tx",
+        "add",
+        "--date",
+        "2025-01-03",
+        "--account",
+        "Visa",
+        "--amount",
+        "200",
+        "--payee",
+        "Autopay",
+    ])
+    .unwrap();
+
+    let mut stmt = conn
+        .prepare("SELECT account_id, category_id FROM transactions ORDER BY id")
+        .unwrap();
+    let rows: Vec<(i64, Option<i64>)> = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(rows, vec![(1, Some(1)), (2, Some(2))]);
+}
+
+#[test]
+fn unknown_account_suggests_and_prefix_opt_in_resolves() {
+    let conn = base_conn();
+    conn.execute_batch(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'Checking','bank','USD'), (2,'Credit Card','card','USD');",
+    )
+    .unwrap();
+    let add = |account: &str| {
+        let matches = cli::build_cli().get_matches_from([
+            "moneyclip",
+            "tx",
+            "add",
+            "--date",
+            "2025-01-01",
+            "--account",
+            account,
+            "--amount",
+            "-1",
+            "--payee",
+            "P",
+        ]);
+        let Some(("tx", m)) = matches.subcommand() else {
+            panic!("tx command not parsed");
+        };
+        transactions::handle(&conn, m)
+    };
+
+    let err = add("Chekcing").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Account 'Chekcing' not found. Did you mean 'Checking'?"
+    );
+    assert!(add("Che").is_err());
+
+    let matches =
+        cli::build_cli().get_matches_from(["moneyclip", "config", "set", "match_prefixes", "true"]);
+    let Some(("config", m)) = matches.subcommand() else {
+        panic!("config command not parsed");
+    };
+    config::handle(&conn, m).unwrap();
+    add("che").unwrap();
+    assert!(add("c").is_err(), "ambiguous prefix must not resolve");
+    let account: i64 = conn
+        .query_row("SELECT account_id FROM transactions", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(account, 1);
+}
+
+#[test]
+fn tx_add_without_input_reports_missing_flags() {
+    let conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD')",
+        [],
+    )
+    .unwrap();
+    let matches = cli::build_cli().get_matches_from([
+        "moneyclip",
+        "tx",
+        "add",
+        "--no-input",
+        "--date",
+        "2025-01-01",
+        "--account",
+        "A1",
+        "--payee",
+        "P",
+    ]);
+    let Some(("tx", tx_m)) = matches.subcommand() else {
+        panic!("tx command not parsed");
+    };
+    let err = transactions::handle(&conn, tx_m).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Missing --amount; pass it or run in a terminal without --no-input"
+    );
+}
+
+#[test]
+fn tx_add_warns_over_monthly_cap_and_strict_refuses() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        INSERT INTO settings(key,value) VALUES ('base_currency','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','USD');
+        INSERT INTO categories(id,name) VALUES (1,'Dining');
+        "#,
+    )
+    .unwrap();
+    let run = |argv: &[&str]| {
+        let matches = cli::build_cli().get_matches_from(argv);
+        match matches.subcommand() {
+            Some(("category", m)) => moneyclip::commands::categories::handle(&conn, m),
+            Some(("tx", m)) => transactions::handle(&conn, m),
+            _ => panic!("command not parsed"),
+        }
+    };
+    let add = |amount: &str, strict: bool| {
+        let mut argv = vec![
+            "moneyclip",
+            "--no-input",
+            "tx",
+            "add",
+            "--date",
+            "2025-03-10",
+            "--account",
+            "Cash",
+            "--payee",
+            "Bistro",
+            "--category",
+            "Dining",
+            "--amount",
+            amount,
+        ];
+        if strict {
+            argv.push("--strict");
+        }
+        run(&argv)
+    };
+
+    run(&[
+        "moneyclip",
+        "category",
+        "set-cap",
+        "--name",
+        "Dining",
+        "--amount",
+        "100",
+    ])
+    .unwrap();
+    add("-60", true).unwrap();
+    assert!(
+        moneyclip::commands::categories::cap_breach(&conn, 1, "2025-03")
+            .unwrap()
+            .is_none()
+    );
+
+    let err = add("-50", true).unwrap_err().to_string();
+    assert!(err.contains("over its monthly cap"), "{}", err);
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+
+    add("-50", false).unwrap();
+    let breach = moneyclip::commands::categories::cap_breach(&conn, 1, "2025-03")
+        .unwrap()
+        .unwrap();
+    assert_eq!(breach.spent.to_string(), "110");
+    assert_eq!(breach.currency, "USD");
+
+    run(&[
+        "moneyclip",
+        "category",
+        "set-cap",
+        "--name",
+        "Dining",
+        "--clear",
+    ])
+    .unwrap();
+    assert!(
+        moneyclip::commands::categories::cap_breach(&conn, 1, "2025-03")
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn reimbursable_items_are_owed_until_settled() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE transactions ADD COLUMN reimbursable_by TEXT;
+        ALTER TABLE transactions ADD COLUMN settled_by INTEGER;
+        INSERT INTO settings(key,value) VALUES ('base_currency','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','USD');
+        "#,
+    )
+    .unwrap();
+    let add = |date: &str, amount: &str, extra: &[&str]| {
+        let mut argv = vec![
+            "moneyclip",
+            "--no-input",
+            "tx",
+            "add",
+            "--date",
+            date,
+            "--account",
+            "Cash",
+            "--payee",
+            "Shop",
+            "--amount",
+            amount,
+        ];
+        argv.extend_from_slice(extra);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("tx", m)) = matches.subcommand() else {
+            panic!("tx command not parsed");
+        };
+        transactions::handle(&conn, m)
+    };
+    add("2025-03-01", "-30", &["--reimbursable", "Alex"]).unwrap();
+    add("2025-03-05", "-20", &["--reimbursable", "Alex"]).unwrap();
+    add("2025-03-06", "-12.50", &["--reimbursable"]).unwrap();
+    add("2025-03-07", "-8", &[]).unwrap();
+
+    let owed = moneyclip::commands::reimbursements::owed(&conn, None).unwrap();
+    assert_eq!(owed.len(), 2);
+    assert_eq!(owed[0].person, "Alex");
+    assert_eq!(owed[0].items, 2);
+    assert_eq!(owed[0].since, "2025-03-01");
+    assert_eq!(owed[0].amount.to_string(), "50");
+    assert_eq!(owed[1].person, "expenses");
+    assert_eq!(owed[1].amount.to_string(), "12.50");
+
+    // A repayment covering only the oldest item settles just that one.
+    add("2025-03-10", "35", &[]).unwrap();
+    run_tx(
+        &conn,
+        &[
+            "settle",
+            "--person",
+            "alex",
+            "--date",
+            "2025-03-10",
+            "--amount",
+            "35.00",
+        ],
+    )
+    .unwrap();
+    let owed = moneyclip::commands::reimbursements::owed(&conn, Some("Alex")).unwrap();
+    assert_eq!(owed[0].items, 1);
+    assert_eq!(owed[0].amount.to_string(), "20");
+    let settled_by: i64 = conn
+        .query_row(
+            "SELECT settled_by FROM transactions WHERE date='2025-03-01'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(settled_by, 5);
+
+    let err = run_tx(
+        &conn,
+        &[
+            "settle",
+            "--person",
+            "Alex",
+            "--date",
+            "2025-03-11",
+            "--amount",
+            "20",
+        ],
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("tx add"), "{}", err);
+}
+
+#[test]
+fn project_tags_filter_lists_and_cost_per_project() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE transactions ADD COLUMN project TEXT;
+        CREATE TABLE fx_rates(id INTEGER PRIMARY KEY, date TEXT, base TEXT, quote TEXT, rate TEXT);
+        INSERT INTO settings(key,value) VALUES ('base_currency','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','USD'), (2,'Euro','bank','EUR');
+        INSERT INTO categories(id,name) VALUES (1,'Materials'), (2,'Labour');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','EUR','USD','1.10');
+        "#,
+    )
+    .unwrap();
+    let add = |date: &str, account: &str, amount: &str, category: &str, project: Option<&str>| {
+        let mut args = vec![
+            "add",
+            "--date",
+            date,
+            "--account",
+            account,
+            "--payee",
+            "Shop",
+            "--amount",
+            amount,
+            "--category",
+            category,
+        ];
+        if let Some(p) = project {
+            args.extend(["--project", p]);
+        }
+        run_tx(&conn, &args).unwrap();
+    };
+    add("2025-03-01", "Cash", "-400", "Materials", Some("Kitchen"));
+    add("2025-03-08", "Euro", "-1000", "Labour", Some("Kitchen"));
+    add("2025-03-09", "Cash", "-25", "Materials", None);
+    add("2025-04-02", "Cash", "900", "Labour", Some("Bakery"));
+    add("2025-04-05", "Cash", "-150", "Materials", Some("bakery"));
+
+    let matches =
+        cli::build_cli().get_matches_from(["moneyclip", "tx", "list", "--project", "kitchen"]);
+    let Some(("tx", tx_m)) = matches.subcommand() else {
+        panic!("tx command not parsed");
+    };
+    let Some(("list", list_m)) = tx_m.subcommand() else {
+        panic!("no list subcommand");
+    };
+    assert_eq!(transactions::query_rows(&conn, list_m).unwrap().len(), 2);
+
+    let lines = moneyclip::commands::projects::project_costs(&conn, None, None, None).unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].name, "Bakery");
+    assert_eq!(lines[0].transactions, 2);
+    assert_eq!(lines[0].net.to_string(), "750");
+    assert_eq!(lines[1].name, "Kitchen");
+    assert_eq!(lines[1].costs.to_string(), "1500.00");
+    assert_eq!(
+        (lines[1].first.as_str(), lines[1].last.as_str()),
+        ("2025-03-01", "2025-03-08")
+    );
+
+    let by_category =
+        moneyclip::commands::projects::project_costs(&conn, Some("Kitchen"), None, None).unwrap();
+    let names: Vec<&str> = by_category.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, ["Labour", "Materials"]);
+}
+
+#[test]
+fn spend_by_location_groups_places_and_coordinates() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE transactions ADD COLUMN location TEXT;
+        ALTER TABLE transactions ADD COLUMN latitude REAL;
+        ALTER TABLE transactions ADD COLUMN longitude REAL;
+        CREATE TABLE fx_rates(id INTEGER PRIMARY KEY, date TEXT, base TEXT, quote TEXT, rate TEXT);
+        INSERT INTO settings(key,value) VALUES ('base_currency','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','USD'), (2,'Yen','cash','JPY');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','JPY','USD','0.0065');
+        "#,
+    )
+    .unwrap();
+    let add = |account: &str, amount: &str, extra: &[&str]| {
+        let mut args = vec![
+            "add",
+            "--date",
+            "2025-04-03",
+            "--account",
+            account,
+            "--payee",
+            "Shop",
+            "--amount",
+            amount,
+        ];
+        args.extend_from_slice(extra);
+        run_tx(&conn, &args)
+    };
+    add("Yen", "-10000", &["--location", "Kyoto, Japan"]).unwrap();
+    add("Yen", "-4000", &["--location", "Tokyo, Japan"]).unwrap();
+    add("Cash", "-30", &["--location", "Kyoto, Japan"]).unwrap();
+    add("Cash", "-12", &["--coords", "35.0116,135.7681"]).unwrap();
+    add("Cash", "-5", &[]).unwrap();
+    assert!(add("Cash", "-1", &["--coords", "135.7,35.0"]).is_err());
+
+    let places =
+        moneyclip::commands::locations::spend_by_location(&conn, "place", None, None).unwrap();
+    let names: Vec<&str> = places.iter().map(|l| l.location.as_str()).collect();
+    assert_eq!(names, ["Kyoto, Japan", "Tokyo, Japan", "35.01,135.77"]);
+    assert_eq!(places[0].transactions, 2);
+    assert_eq!(places[0].original["JPY"].to_string(), "10000");
+    assert_eq!(places[0].original["USD"].to_string(), "30");
+    assert_eq!(places[0].base.to_string(), "95.0000");
+
+    let countries =
+        moneyclip::commands::locations::spend_by_location(&conn, "country", None, None).unwrap();
+    assert_eq!(countries[0].location, "Japan");
+    assert_eq!(countries[0].transactions, 3);
+    assert_eq!(countries[0].base.to_string(), "121.0000");
+}
+
+#[test]
+fn trip_cost_covers_dates_and_tagged_bookings() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        ALTER TABLE transactions ADD COLUMN project TEXT;
+        CREATE TABLE trips(id INTEGER PRIMARY KEY, name TEXT UNIQUE, start_date TEXT, end_date TEXT, tag TEXT);
+        CREATE TABLE fx_rates(id INTEGER PRIMARY KEY, date TEXT, base TEXT, quote TEXT, rate TEXT);
+        INSERT INTO settings(key,value) VALUES ('base_currency','USD');
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Cash','cash','USD'), (2,'Yen','cash','JPY');
+        INSERT INTO fx_rates(date,base,quote,rate) VALUES ('2025-01-01','JPY','USD','0.0065');
+        INSERT INTO categories(name) VALUES ('Flights'), ('Food'), ('Lodging'), ('Refund');
+        "#,
+    )
+    .unwrap();
+    let add = |date: &str, account: &str, amount: &str, category: &str, extra: &[&str]| {
+        let mut args = vec![
+            "add",
+            "--date",
+            date,
+            "--account",
+            account,
+            "--payee",
+            "Shop",
+            "--amount",
+            amount,
+            "--category",
+            category,
+        ];
+        args.extend_from_slice(extra);
+        run_tx(&conn, &args).unwrap()
+    };
+    add(
+        "2025-01-15",
+        "Cash",
+        "-900",
+        "Flights",
+        &["--project", "Japan"],
+    );
+    add("2025-04-01", "Yen", "-20000", "Food", &[]);
+    add("2025-04-04", "Yen", "-10000", "Lodging", &[]);
+    add("2025-04-05", "Cash", "-40", "Food", &[]);
+    add("2025-04-05", "Cash", "100", "Refund", &[]);
+    add("2025-04-06", "Cash", "-70", "Food", &[]);
+    conn.execute(
+        "INSERT INTO trips(name,start_date,end_date,tag) VALUES ('Japan-2025','2025-04-01','2025-04-05','Japan')",
+        [],
+    )
+    .unwrap();
+
+    let cost = moneyclip::commands::trips::trip_cost(&conn, "Japan-2025").unwrap();
+    assert_eq!(cost.days, 5);
+    assert_eq!(cost.transactions, 4);
+    let names: Vec<&str> = cost
+        .categories
+        .iter()
+        .map(|l| l.category.as_str())
+        .collect();
+    assert_eq!(names, ["Flights", "Food", "Lodging"]);
+    assert_eq!(cost.categories[1].original["JPY"].to_string(), "20000");
+    assert_eq!(cost.categories[1].original["USD"].to_string(), "40");
+    assert_eq!(cost.original["JPY"].to_string(), "30000");
+    assert_eq!(cost.total.to_string(), "1135.0000");
+    assert_eq!(cost.per_day.to_string(), "227.00");
+    assert!(moneyclip::commands::trips::trip_cost(&conn, "Peru").is_err());
+}
+
+#[test]
+fn jars_track_sub_balances_of_an_account() {
+    let conn = base_conn();
+    conn.execute_batch(
+        r#"
+        CREATE TABLE jars(id INTEGER PRIMARY KEY, account_id INTEGER, name TEXT, UNIQUE(account_id, name));
+        CREATE TABLE jar_moves(id INTEGER PRIMARY KEY, date TEXT, from_jar_id INTEGER, to_jar_id INTEGER, amount TEXT, note TEXT);
+        ALTER TABLE transactions ADD COLUMN jar_id INTEGER;
+        INSERT INTO accounts(id,name,type,currency) VALUES (1,'Family','bank','USD');
+        "#,
+    )
+    .unwrap();
+    let run_jar = |args: &[&str]| {
+        let mut argv = vec!["moneyclip", "jar"];
+        argv.extend_from_slice(args);
+        let matches = cli::build_cli().get_matches_from(argv);
+        let Some(("jar", m)) = matches.subcommand() else {
+            panic!("jar command not parsed");
+        };
+        moneyclip::commands::jars::handle(&conn, m)
+    };
+    run_jar(&["add", "--account", "Family", "--name", "Emma"]).unwrap();
+    run_jar(&["add", "--account", "Family", "--name", "Noah"]).unwrap();
+    run_tx(
+        &conn,
+        &[
+            "add",
+            "--date",
+            "2025-05-01",
+            "--account",
+            "Family",
+            "--amount",
+            "500",
+            "--payee",
+            "Salary",
+        ],
+    )
+    .unwrap();
+    run_jar(&[
+        "move",
+        "--account",
+        "Family",
+        "--to",
+        "Emma",
+        "--amount",
+        "20",
+    ])
+    .unwrap();
+    run_jar(&[
+        "move",
+        "--account",
+        "Family",
+        "--to",
+        "Noah",
+        "--amount",
+        "10",
+    ])
+    .unwrap();
+    run_jar(&[
+        "move",
+        "--account",
+        "Family",
+        "--from",
+        "Emma",
+        "--to",
+        "Noah",
+        "--amount",
+        "5",
+    ])
+    .unwrap();
+    run_tx(
+        &conn,
+        &[
+            "add",
+            "--date",
+            "2025-05-03",
+            "--account",
+            "Family",
+            "--amount",
+            "-8",
+            "--payee",
+            "Toys",
+            "--jar",
+            "Noah",
+        ],
+    )
+    .unwrap();
+    assert!(
+        run_jar(&[
+            "move",
+            "--account",
+            "Family",
+            "--from",
+            "Noah",
+            "--amount",
+            "8"
+        ])
+        .is_err()
+    );
+    assert!(run_jar(&["rm", "--account", "Family", "--name", "Emma"]).is_err());
+
+    let balances = moneyclip::commands::jars::jar_balances(&conn, None).unwrap();
+    let shown: Vec<(&str, String)> = balances
+        .iter()
+        .map(|b| (b.jar.as_str(), b.balance.to_string()))
+        .collect();
+    assert_eq!(
+        shown,
+        [
+            ("Emma", "15".to_string()),
+            ("Noah", "7".to_string()),
+            ("(unassigned)", "470".to_string())
+        ]
+    );
+}
+
+#[test]
+fn amount_arithmetic_is_stored_in_minor_units_whatever_the_display_precision() {
+    let mut conn = base_conn();
+    conn.execute(
+        "INSERT INTO accounts(id,name,type,currency) VALUES (1,'A1','bank','USD'), (2,'Yen','bank','JPY')",
+        [],
+    )
+    .unwrap();
+    for (account, amount) in [("A1", "10/3"), ("Yen", "1000/3")] {
+        let matches = cli::build_cli().get_matches_from([
+            "moneyclip",
+            "--money-dp",
+            "0",
+            "tx",
+            "add",
+            "--date",
+            "2025-02-01",
+            "--account",
+            account,
+            "--amount",
+            amount,
+            "--payee",
+            "Split",
+        ]);
+        moneyclip::commands::dispatch(&mut conn, &matches).unwrap();
+    }
+    let amounts: Vec<String> = conn
+        .prepare("SELECT amount FROM transactions ORDER BY id")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(amounts, vec!["3.33", "333"]);
+}